@@ -112,7 +112,7 @@ echo "🎯 Ready for Production Testing"
 echo "================================"
 echo ""
 echo "Next steps to validate the fix:"
-echo "1. Run daemon mode: cargo run --release -- --daemon"
+echo "1. Run daemon mode: cargo run --release -- daemon"
 echo "2. Monitor logs for token refreshes (every 45 minutes)"
 echo "3. Watch for successful refreshes at 45, 90, 135, 180 minutes"
 echo "4. Verify NO OAuth2 authorization URLs are requested"
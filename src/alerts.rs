@@ -0,0 +1,566 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::hooks::{self, HookEvent, HooksConfig};
+use crate::i18n::Locale;
+use crate::mold;
+use crate::slack_notifier::{NotificationKind, NotificationSource, Severity, SlackNotifier};
+use crate::storage::Storage;
+use crate::templates::{NotificationEvent, NotificationTemplates};
+use crate::units::UnitSystem;
+use crate::xsense::TemperatureReading;
+
+/// Delay before the first webhook retry, doubled after each attempt
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Rules currently triggered, per scope (profile) and rule name
+///
+/// A rule fires once, then again only after its condition cleared.
+static ACTIVE: Mutex<BTreeSet<(String, String)>> = Mutex::new(BTreeSet::new());
+
+/// Current stay of a humidity rule's room in its condition, per scope and rule name
+static HUMIDITY_STREAKS: Mutex<BTreeMap<(String, String), HumidityStreak>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy)]
+struct HumidityStreak {
+    since: DateTime<Utc>,
+    alerted: bool,
+}
+
+/// Temperature (or humidity) condition of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Below(f64),
+    Above(f64),
+}
+
+impl Condition {
+    pub fn matches(&self, value: f64) -> bool {
+        match self {
+            Condition::Below(threshold) => value < *threshold,
+            Condition::Above(threshold) => value > *threshold,
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        match self {
+            Condition::Below(threshold) | Condition::Above(threshold) => *threshold,
+        }
+    }
+
+    pub fn operator(&self) -> &'static str {
+        match self {
+            Condition::Below(_) => "<",
+            Condition::Above(_) => ">",
+        }
+    }
+}
+
+/// Alert rule from `ALERT_RULES`, with the webhook called when it fires (`ALERT_WEBHOOKS`)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub sensor_id: String,
+    pub condition: Condition,
+    pub webhook: Option<String>,
+}
+
+/// Parse `name:sensor<threshold` / `name:sensor>threshold` entries separated by ';'
+///
+/// Webhooks are `name=url` entries separated by ';'. Invalid entries are
+/// logged and skipped.
+pub fn parse_rules(rules: &str, webhooks: &str) -> Vec<AlertRule> {
+    let webhooks: BTreeMap<&str, &str> = webhooks
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(name, url)| (name.trim(), url.trim()))
+        .collect();
+
+    let rules: Vec<AlertRule> = rules
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let rule = parse_rule(entry, &webhooks);
+            if rule.is_none() {
+                warn!("Invalid alert rule '{}' (expected name:sensor<threshold or name:sensor>threshold) - ignored", entry);
+            }
+            rule
+        })
+        .collect();
+
+    for name in webhooks.keys().filter(|name| !rules.iter().any(|rule| rule.name == **name)) {
+        warn!("Webhook defined for unknown alert rule '{}' - ignored", name);
+    }
+    rules
+}
+
+fn parse_rule(entry: &str, webhooks: &BTreeMap<&str, &str>) -> Option<AlertRule> {
+    let (name, sensor_id, condition) = parse_condition(entry)?;
+    Some(AlertRule {
+        webhook: webhooks.get(name.as_str()).map(|url| url.to_string()),
+        name,
+        sensor_id,
+        condition,
+    })
+}
+
+/// Name, sensor and condition of a `name:sensor<threshold` / `name:sensor>threshold` entry
+fn parse_condition(entry: &str) -> Option<(String, String, Condition)> {
+    let (name, expression) = entry.split_once(':')?;
+    let (sensor_id, threshold, condition): (&str, &str, fn(f64) -> Condition) = match expression.split_once('<') {
+        Some((sensor_id, threshold)) => (sensor_id, threshold, Condition::Below),
+        None => {
+            let (sensor_id, threshold) = expression.split_once('>')?;
+            (sensor_id, threshold, Condition::Above)
+        }
+    };
+    let (name, sensor_id) = (name.trim(), sensor_id.trim());
+    if name.is_empty() || sensor_id.is_empty() {
+        return None;
+    }
+
+    Some((name.to_string(), sensor_id.to_string(), condition(threshold.trim().parse().ok()?)))
+}
+
+/// Humidity rule of a room from `HUMIDITY_ALERT_RULES`: too dry below, too humid above (%)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HumidityRule {
+    pub name: String,
+    pub sensor_id: String,
+    pub condition: Condition,
+    /// How long the room must stay in the condition before the rule fires
+    pub duration: Duration,
+}
+
+/// Parse `name:sensor<percent` / `name:sensor>percent/duration` entries separated by ';'
+///
+/// Without a duration, the rule fires on the first reading in the condition.
+/// Invalid entries are logged and skipped.
+pub fn parse_humidity_rules(rules: &str) -> Vec<HumidityRule> {
+    rules
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let rule = parse_humidity_rule(entry);
+            if rule.is_none() {
+                warn!("Invalid humidity alert rule '{}' (expected name:sensor<percent or name:sensor>percent/duration) - ignored", entry);
+            }
+            rule
+        })
+        .collect()
+}
+
+fn parse_humidity_rule(entry: &str) -> Option<HumidityRule> {
+    let (expression, duration) = match entry.split_once('/') {
+        Some((expression, duration)) => (expression, humantime::parse_duration(duration.trim()).ok()?),
+        None => (entry, Duration::ZERO),
+    };
+    let (name, sensor_id, condition) = parse_condition(expression)?;
+    if !(0.0..=100.0).contains(&condition.threshold()) {
+        return None;
+    }
+    Some(HumidityRule { name, sensor_id, condition, duration })
+}
+
+/// Rule fired by a reading
+#[derive(Debug, Clone)]
+pub struct TriggeredAlert {
+    pub rule: AlertRule,
+    pub reading: TemperatureReading,
+}
+
+impl TriggeredAlert {
+    /// Values exposed to the `alert` and `alert_webhook` templates
+    pub fn context(&self) -> minijinja::Value {
+        minijinja::context! {
+            rule => &self.rule.name,
+            sensor_id => &self.rule.sensor_id,
+            condition => self.rule.condition.operator(),
+            threshold => self.rule.condition.threshold(),
+            temperature => self.reading.temperature,
+            humidity => self.reading.humidity,
+            timestamp => self.reading.timestamp.to_rfc3339(),
+        }
+    }
+
+    /// Alert as JSON: default webhook body and `HOOK_ON_ALERT` payload
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "rule": self.rule.name,
+            "sensor_id": self.rule.sensor_id,
+            "condition": self.rule.condition.operator(),
+            "threshold": self.rule.condition.threshold(),
+            "temperature": self.reading.temperature,
+            "humidity": self.reading.humidity,
+            "timestamp": self.reading.timestamp.to_rfc3339(),
+        })
+    }
+}
+
+/// Rules met by the latest reading of their sensor in the batch
+///
+/// A triggered rule does not fire again in this process until a later
+/// reading clears its condition.
+pub fn evaluate(scope: &str, rules: &[AlertRule], readings: &[TemperatureReading]) -> Vec<TriggeredAlert> {
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut triggered = Vec::new();
+    for rule in rules {
+        let latest = readings
+            .iter()
+            .filter(|reading| reading.sensor_id == rule.sensor_id)
+            .max_by_key(|reading| reading.timestamp);
+        let Some(latest) = latest else {
+            continue;
+        };
+
+        let key = (scope.to_string(), rule.name.clone());
+        if !rule.condition.matches(latest.temperature) {
+            active.remove(&key);
+        } else if active.insert(key) {
+            triggered.push(TriggeredAlert { rule: rule.clone(), reading: latest.clone() });
+        }
+    }
+    triggered
+}
+
+/// Humidity rule whose room stayed in its condition for the rule's duration
+#[derive(Debug, Clone)]
+pub struct HumidityAlert {
+    pub rule: HumidityRule,
+    /// First reading of the stay in the condition
+    pub since: DateTime<Utc>,
+    pub reading: TemperatureReading,
+}
+
+impl HumidityAlert {
+    pub fn hours(&self) -> i64 {
+        (self.reading.timestamp - self.since).num_hours()
+    }
+
+    /// Alert as JSON: `HOOK_ON_ALERT` payload
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "rule": self.rule.name,
+            "sensor_id": self.rule.sensor_id,
+            "condition": self.rule.condition.operator(),
+            "threshold": self.rule.condition.threshold(),
+            "humidity": self.reading.humidity,
+            "since": self.since.to_rfc3339(),
+            "hours": self.hours(),
+            "timestamp": self.reading.timestamp.to_rfc3339(),
+        })
+    }
+}
+
+/// Humidity rules whose room stayed in the condition for at least their duration
+///
+/// Readings without humidity are ignored. The stay is followed across
+/// batches in this process and fires once; a reading out of the condition
+/// ends it.
+pub fn evaluate_humidity(scope: &str, rules: &[HumidityRule], readings: &[TemperatureReading]) -> Vec<HumidityAlert> {
+    let mut streaks = HUMIDITY_STREAKS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut triggered = Vec::new();
+    for rule in rules {
+        let mut room: Vec<&TemperatureReading> = readings
+            .iter()
+            .filter(|reading| reading.sensor_id == rule.sensor_id && reading.humidity.is_some())
+            .collect();
+        room.sort_by_key(|reading| reading.timestamp);
+        let Some(latest) = room.last() else {
+            continue;
+        };
+
+        let key = (scope.to_string(), rule.name.clone());
+        for reading in &room {
+            match reading.humidity {
+                Some(humidity) if rule.condition.matches(humidity) => {
+                    streaks.entry(key.clone()).or_insert(HumidityStreak { since: reading.timestamp, alerted: false });
+                }
+                _ => {
+                    streaks.remove(&key);
+                }
+            }
+        }
+
+        let Some(streak) = streaks.get_mut(&key) else {
+            continue;
+        };
+        let lasted = (latest.timestamp - streak.since).to_std().unwrap_or_default();
+        if !streak.alerted && lasted >= rule.duration {
+            streak.alerted = true;
+            triggered.push(HumidityAlert { rule: rule.clone(), since: streak.since, reading: (*latest).clone() });
+        }
+    }
+    triggered
+}
+
+/// Body posted to the webhook: the `alert_webhook` template, or the alert as JSON
+pub fn webhook_payload(templates: &NotificationTemplates, alert: &TriggeredAlert) -> String {
+    templates.render_or(NotificationEvent::AlertWebhook, alert.context(), || alert.to_json().to_string())
+}
+
+/// POST the payload to the webhook, retrying with a doubling delay
+pub async fn call_webhook(url: &str, payload: &str, retries: u32) -> Result<()> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        match post(url, payload).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < retries => {
+                debug!("Webhook {} failed (attempt {}): {:#}", url, attempt + 1, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e).with_context(|| format!("Webhook {} failed after {} attempt(s)", url, attempt + 1)),
+        }
+    }
+}
+
+async fn post(url: &str, payload: &str) -> Result<()> {
+    use google_gmail1::{hyper, hyper_rustls};
+
+    // Home automation webhooks are often plain HTTP on the LAN
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = hyper::Client::builder().build::<_, hyper::Body>(connector);
+
+    let request = hyper::Request::post(url)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(payload.to_string()))?;
+    let response = client.request(request).await.context("Request failed")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Answered {}", response.status());
+    }
+    Ok(())
+}
+
+/// Alerts raised once temperature readings are saved, whatever brought them
+/// (X-Sense emails, pushed readings, dropped or imported files)
+pub struct Alerting {
+    rules: Vec<AlertRule>,
+    humidity_rules: Vec<HumidityRule>,
+    mold_alert_after_hours: u32,
+    webhook_retries: u32,
+    /// Profile of the configuration, empty without profiles
+    scope: String,
+    hooks: HooksConfig,
+    units: UnitSystem,
+    locale: Locale,
+    templates: NotificationTemplates,
+}
+
+impl Alerting {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Alerting {
+            rules: config.alerts.rules.clone(),
+            humidity_rules: config.alerts.humidity_rules.clone(),
+            mold_alert_after_hours: config.mold_alert_after_hours,
+            webhook_retries: config.alerts.webhook_retries,
+            scope: config.profile.clone().unwrap_or_default(),
+            hooks: config.hooks.clone(),
+            units: config.units,
+            locale: config.locale,
+            templates: NotificationTemplates::from_config(config)?,
+        })
+    }
+
+    /// Profile name given to alert hooks, None for a single-home setup
+    fn profile(&self) -> Option<&str> {
+        Some(self.scope.as_str()).filter(|scope| !scope.is_empty())
+    }
+
+    /// Mold risk of the sensors that got new readings, then the alert rules (with
+    /// their webhook actions) and humidity rules on the saved readings
+    ///
+    /// Failures are logged, they never fail the save.
+    pub async fn check_saved(&self, storage: Option<&dyn Storage>, slack: Option<&SlackNotifier>, source: NotificationSource,
+                             inserted: BTreeSet<&String>, saved: &[TemperatureReading]) {
+        let locale = self.locale;
+
+        // Rooms that stayed too long in the mold risk zone
+        if let Some(storage) = storage {
+            for alert in mold::due_alerts(storage, inserted, self.mold_alert_after_hours).await {
+                warn!("🍄 {} in the mold risk zone for {} hour(s)", alert.sensor_id, alert.hours);
+                let data = serde_json::json!({
+                    "kind": "mold",
+                    "profile": self.profile(),
+                    "sensor_id": alert.sensor_id,
+                    "since": alert.since.to_rfc3339(),
+                    "hours": alert.hours,
+                });
+                hooks::run(&self.hooks, HookEvent::Alert, data).await;
+                if let Some(slack) = slack {
+                    let since = alert.since.format("%Y-%m-%d %H:%M UTC").to_string();
+                    let message = locale.notify_mold_risk(&alert.sensor_id, alert.hours, &since);
+                    if let Err(e) = slack.notify(source, Severity::Error, NotificationKind::Alert, Some(&alert.sensor_id), &message).await {
+                        debug!("Failed to send Slack notification: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Alert rules on the latest saved readings, with their webhook actions
+        for alert in evaluate(&self.scope, &self.rules, saved) {
+            warn!("🚨 Alert {}: {} at {}°C", alert.rule.name, alert.rule.sensor_id, alert.reading.temperature);
+            let mut data = alert.to_json();
+            data["kind"] = "rule".into();
+            data["profile"] = self.profile().into();
+            hooks::run(&self.hooks, HookEvent::Alert, data).await;
+            if let Some(slack) = slack {
+                let message = self.templates.render_or(NotificationEvent::Alert, alert.context(), || {
+                    locale.notify_alert(&alert.rule.name, &alert.rule.sensor_id,
+                                        &self.units.format_temperature(alert.reading.temperature),
+                                        alert.rule.condition.operator(),
+                                        &self.units.format_temperature(alert.rule.condition.threshold()))
+                });
+                if let Err(e) = slack.notify(source, Severity::Error, NotificationKind::Alert, Some(&alert.rule.name), &message).await {
+                    debug!("Failed to send Slack notification: {}", e);
+                }
+            }
+
+            let Some(url) = &alert.rule.webhook else { continue };
+            let payload = webhook_payload(&self.templates, &alert);
+            if let Err(e) = call_webhook(url, &payload, self.webhook_retries).await {
+                warn!("❌ {:#}", e);
+                if let Some(slack) = slack {
+                    let message = locale.notify_webhook_failed(&alert.rule.name, &format!("{:#}", e));
+                    if let Err(e) = slack.notify(source, Severity::Error, NotificationKind::Error, Some(&alert.rule.name), &message).await {
+                        debug!("Failed to send Slack notification: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Humidity rules: rooms too dry or too humid for long enough
+        for alert in evaluate_humidity(&self.scope, &self.humidity_rules, saved) {
+            let humidity = alert.reading.humidity.unwrap_or_default();
+            warn!("💧 Humidity alert {}: {} at {}% for {} hour(s)", alert.rule.name, alert.rule.sensor_id, humidity, alert.hours());
+            let mut data = alert.to_json();
+            data["kind"] = "humidity".into();
+            data["profile"] = self.profile().into();
+            hooks::run(&self.hooks, HookEvent::Alert, data).await;
+            if let Some(slack) = slack {
+                let message = locale.notify_humidity_alert(&alert.rule.name, &alert.rule.sensor_id, humidity,
+                                                           alert.rule.condition, alert.hours());
+                if let Err(e) = slack.notify(source, Severity::Error, NotificationKind::Alert, Some(&alert.rule.name), &message).await {
+                    debug!("Failed to send Slack notification: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn humid(sensor: &str, hour: u32, humidity: f64) -> TemperatureReading {
+        TemperatureReading { humidity: Some(humidity), ..reading(sensor, hour, 19.0) }
+    }
+
+    fn reading(sensor: &str, hour: u32, temperature: f64) -> TemperatureReading {
+        TemperatureReading {
+            sensor_id: sensor.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, hour, 0, 0).unwrap(),
+            temperature,
+            humidity: None,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules("cabane_gel:cabane<3; serre_chaude: serre > 35.5;invalid;x:y=2",
+                                "cabane_gel=http://ha.local:8123/api/webhook/chauffage");
+        assert_eq!(rules, [
+            AlertRule {
+                name: "cabane_gel".to_string(),
+                sensor_id: "cabane".to_string(),
+                condition: Condition::Below(3.0),
+                webhook: Some("http://ha.local:8123/api/webhook/chauffage".to_string()),
+            },
+            AlertRule {
+                name: "serre_chaude".to_string(),
+                sensor_id: "serre".to_string(),
+                condition: Condition::Above(35.5),
+                webhook: None,
+            },
+        ]);
+        assert!(parse_rules("", "").is_empty());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let scope = "test_evaluate";
+        let rules = parse_rules("cabane_gel:cabane<3", "");
+        // Only the latest reading of the sensor counts
+        assert!(evaluate(scope, &rules, &[reading("cabane", 10, 2.0), reading("cabane", 11, 4.0)]).is_empty());
+
+        let alerts = evaluate(scope, &rules, &[reading("cabane", 12, 2.5), reading("garage", 12, 0.0)]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].reading.temperature, 2.5);
+        assert!(evaluate(scope, &rules, &[reading("cabane", 13, 1.0)]).is_empty());
+        assert!(evaluate("other_profile", &rules, &[reading("cabane", 13, 1.0)]).len() == 1);
+
+        // Cleared, then fired again
+        assert!(evaluate(scope, &rules, &[reading("cabane", 14, 5.0)]).is_empty());
+        assert_eq!(evaluate(scope, &rules, &[reading("cabane", 15, 2.0)]).len(), 1);
+
+        let payload: serde_json::Value = serde_json::from_str(&webhook_payload(&NotificationTemplates::default(), &alerts[0])).unwrap();
+        assert_eq!(payload["rule"], "cabane_gel");
+        assert_eq!(payload["temperature"], 2.5);
+    }
+
+    #[test]
+    fn test_parse_humidity_rules() {
+        let rules = parse_humidity_rules("salon_sec:salon<35; cave_humide:cave>70/6h;x:y>120;z:w>60/soon");
+        assert_eq!(rules, [
+            HumidityRule {
+                name: "salon_sec".to_string(),
+                sensor_id: "salon".to_string(),
+                condition: Condition::Below(35.0),
+                duration: Duration::ZERO,
+            },
+            HumidityRule {
+                name: "cave_humide".to_string(),
+                sensor_id: "cave".to_string(),
+                condition: Condition::Above(70.0),
+                duration: Duration::from_secs(6 * 3600),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_evaluate_humidity() {
+        let scope = "test_evaluate_humidity";
+        let rules = parse_humidity_rules("cave_humide:cave>70/6h");
+        // 4 hours in the condition: not yet
+        assert!(evaluate_humidity(scope, &rules, &[humid("cave", 8, 75.0), humid("cave", 10, 80.0), humid("cave", 12, 72.0)]).is_empty());
+
+        // The stay goes on in the next batch, temperature-only readings are ignored
+        let alerts = evaluate_humidity(scope, &rules, &[humid("cave", 14, 74.0), reading("cave", 15, 12.0)]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].hours(), 6);
+        assert!(evaluate_humidity(scope, &rules, &[humid("cave", 16, 78.0)]).is_empty());
+
+        // A dry reading ends the stay, a new one starts from zero
+        assert!(evaluate_humidity(scope, &rules, &[humid("cave", 17, 60.0), humid("cave", 18, 75.0)]).is_empty());
+        assert!(evaluate_humidity(scope, &rules, &[humid("cave", 23, 71.0)]).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::attachment_parser::Attachment;
+
+/// Placeholders accepted in `DATA_DIR_LAYOUT`
+const PLACEHOLDERS: [&str; 4] = ["source", "year", "month", "day"];
+
+/// Directory layout of the attachments saved under `DATA_DIR` (`DATA_DIR_LAYOUT`)
+///
+/// A relative path pattern with `{source}`, `{year}`, `{month}` and `{day}`
+/// placeholders, `{source}/{year}/{month}` by default. An empty pattern
+/// saves every file directly in `DATA_DIR`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ArchiveLayout {
+    pattern: String,
+}
+
+impl Default for ArchiveLayout {
+    fn default() -> Self {
+        ArchiveLayout { pattern: "{source}/{year}/{month}".to_string() }
+    }
+}
+
+impl ArchiveLayout {
+    /// None for unknown placeholders, absolute paths or `..` components
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim().trim_matches('/');
+        if pattern.split('/').any(|component| component == "..") || pattern.starts_with('\\') {
+            return None;
+        }
+
+        let mut rest = pattern;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}')? + start;
+            if !PLACEHOLDERS.contains(&&rest[start + 1..end]) {
+                return None;
+            }
+            rest = &rest[end + 1..];
+        }
+        if rest.contains('}') {
+            return None;
+        }
+        Some(ArchiveLayout { pattern: pattern.to_string() })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Directory of a file from this source, received at this date
+    pub fn directory(&self, data_dir: &Path, source: &str, date: DateTime<Utc>) -> PathBuf {
+        let relative = self.pattern
+            .replace("{source}", source)
+            .replace("{year}", &date.format("%Y").to_string())
+            .replace("{month}", &date.format("%m").to_string())
+            .replace("{day}", &date.format("%d").to_string());
+        relative
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .fold(data_dir.to_path_buf(), |path, component| path.join(component))
+    }
+}
+
+/// `<YYYY-MM-DD>_<HHMMSS>_<attachment name>`, keeping only portable characters
+pub fn file_name(date: DateTime<Utc>, original: &str) -> String {
+    // Attachment names may carry a client path
+    let original = original.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = original
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    let name = name.trim_start_matches('.');
+    let name = if name.is_empty() { "attachment" } else { name };
+    format!("{}_{}", date.format("%Y-%m-%d_%H%M%S"), name)
+}
+
+/// Save an attachment under `data_dir` and return its path
+///
+/// A different file already saved under the same name gets a `-1`, `-2`...
+/// suffix before its extension; the same content saved again (an email
+/// processed twice) is not duplicated.
+pub async fn save_attachment(data_dir: &Path, layout: &ArchiveLayout, source: &str, date: DateTime<Utc>,
+                             filename: &str, content: &[u8]) -> Result<PathBuf> {
+    let dir = layout.directory(data_dir, source, date);
+    tokio::fs::create_dir_all(&dir).await
+        .with_context(|| format!("Unable to create {}", dir.display()))?;
+
+    let name = file_name(date, filename);
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name.as_str(), String::new()),
+    };
+    for attempt in 0.. {
+        let path = match attempt {
+            0 => dir.join(&name),
+            n => dir.join(format!("{}-{}{}", stem, n, extension)),
+        };
+        match tokio::fs::read(&path).await {
+            Ok(existing) if existing == content => return Ok(path),
+            Ok(_) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tokio::fs::write(&path, content).await
+                    .with_context(|| format!("Unable to write {}", path.display()))?;
+                return Ok(path);
+            }
+            Err(e) => return Err(e).with_context(|| format!("Unable to read {}", path.display())),
+        }
+    }
+    unreachable!("attempts are unbounded")
+}
+
+/// Where a source keeps the attachments of the emails it processes (`<SOURCE>_SAVE_ATTACHMENTS`)
+///
+/// Built by `BaseEmailProcessor` for the runs that write, and handed to the
+/// strategy of the source with each email.
+#[derive(Debug, Clone)]
+pub struct AttachmentArchive {
+    data_dir: PathBuf,
+    layout: ArchiveLayout,
+    source: &'static str,
+}
+
+impl AttachmentArchive {
+    pub fn new(data_dir: impl Into<PathBuf>, layout: ArchiveLayout, source: &'static str) -> Self {
+        AttachmentArchive { data_dir: data_dir.into(), layout, source }
+    }
+
+    /// Save the attachments of an email received at `date`
+    ///
+    /// A file that cannot be saved is logged: the readings are processed anyway.
+    pub async fn save_all(&self, date: DateTime<Utc>, attachments: &[Attachment]) -> usize {
+        let mut saved = 0;
+        for attachment in attachments {
+            match save_attachment(&self.data_dir, &self.layout, self.source, date, &attachment.filename, &attachment.content).await {
+                Ok(path) => {
+                    debug!("Attachment {} saved to {}", attachment.filename, path.display());
+                    saved += 1;
+                }
+                Err(e) => warn!("Unable to save attachment {}: {:#}", attachment.filename, e),
+            }
+        }
+        saved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_layout() {
+        let date = Utc.with_ymd_and_hms(2025, 3, 7, 6, 30, 0).unwrap();
+        let data_dir = Path::new("data");
+        assert_eq!(ArchiveLayout::default().directory(data_dir, "xsense", date), Path::new("data/xsense/2025/03"));
+        let daily = ArchiveLayout::parse("/{year}/{month}-{day}/{source}/").unwrap();
+        assert_eq!(daily.directory(data_dir, "xsense", date), Path::new("data/2025/03-07/xsense"));
+        assert_eq!(ArchiveLayout::parse("").unwrap().directory(data_dir, "xsense", date), Path::new("data"));
+
+        assert_eq!(ArchiveLayout::parse("{source}/{week}"), None);
+        assert_eq!(ArchiveLayout::parse("{source}/../{year}"), None);
+        assert_eq!(ArchiveLayout::parse("{source"), None);
+
+        assert_eq!(file_name(date, "C:\\exports\\Salon Export (1).csv"), "2025-03-07_063000_Salon_Export__1_.csv");
+        assert_eq!(file_name(date, ".."), "2025-03-07_063000_attachment");
+    }
+
+    #[tokio::test]
+    async fn test_save_attachment() {
+        let dir = tempfile::tempdir().unwrap();
+        let date = Utc.with_ymd_and_hms(2025, 3, 7, 6, 30, 0).unwrap();
+        let layout = ArchiveLayout::default();
+
+        let first = save_attachment(dir.path(), &layout, "xsense", date, "salon.csv", b"a").await.unwrap();
+        assert_eq!(first, dir.path().join("xsense/2025/03/2025-03-07_063000_salon.csv"));
+        // Same content: same file; other content: next free name
+        assert_eq!(save_attachment(dir.path(), &layout, "xsense", date, "salon.csv", b"a").await.unwrap(), first);
+        let second = save_attachment(dir.path(), &layout, "xsense", date, "salon.csv", b"b").await.unwrap();
+        assert_eq!(second, dir.path().join("xsense/2025/03/2025-03-07_063000_salon-1.csv"));
+        assert_eq!(std::fs::read(&second).unwrap(), b"b");
+
+        let archive = AttachmentArchive::new(dir.path(), layout, "blueriot");
+        let attachment = |filename: &str| Attachment {
+            filename: filename.to_string(),
+            content: b"report".to_vec(),
+            content_type: "application/pdf".to_string(),
+        };
+        assert_eq!(archive.save_all(date, &[attachment("report.pdf"), attachment("chart.png")]).await, 2);
+        assert!(dir.path().join("blueriot/2025/03/2025-03-07_063000_chart.png").exists());
+    }
+}
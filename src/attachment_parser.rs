@@ -14,7 +14,7 @@ pub struct AttachmentParser;
 
 impl AttachmentParser {
     pub fn parse_email(raw_email: &[u8]) -> Result<Vec<Attachment>> {
-        debug!("Parsing email pour extraire les pièces jointes");
+        debug!("Parsing email to extract attachments");
         
         // For now, using a basic but functional MIME parser
         // We will improve this later with a better API
@@ -26,7 +26,7 @@ impl AttachmentParser {
         // First analyze the MIME structure of the email
         Self::analyze_email_structure(&email_str);
         
-        // Rechercher les sections avec Content-Disposition: attachment
+        // Search for sections with Content-Disposition: attachment
         let mut current_pos = 0;
         while let Some(attachment_start) = email_str[current_pos..].find("Content-Disposition: attachment") {
             let abs_start = current_pos + attachment_start;
@@ -53,7 +53,7 @@ impl AttachmentParser {
                             if let Ok(content) = Self::decode_attachment_content(content_str) {
                                 let content_type = Self::guess_content_type(&filename);
                                 
-                                debug!("Attachment found: {} ({}), taille: {} bytes", 
+                                debug!("Attachment found: {} ({}), size: {} bytes", 
                                        filename, content_type, content.len());
                                 
                                 attachments.push(Attachment {
@@ -220,7 +220,7 @@ impl AttachmentParser {
                 let contents = part.contents();
                 debug!("Attachment {} content size: {} bytes", i, contents.len());
                 
-                // Essayer d'extraire le vrai nom du fichier
+                // Try to extract the real filename
                 let filename = Self::extract_real_filename_from_part(part, i);
                 debug!("Extracted filename for attachment {}: {}", i, filename);
                 
@@ -256,7 +256,7 @@ impl AttachmentParser {
     }    
     
     fn extract_filename_from_headers(headers: &str) -> Option<String> {
-        // Chercher filename= dans les headers
+        // Search for filename= in headers
         if let Some(filename_start) = headers.find("filename=") {
             let filename_part = &headers[filename_start + 9..];
             if let Some(filename_end) = filename_part.find(['\r', '\n', ';']) {
@@ -373,7 +373,7 @@ impl AttachmentParser {
         Ok(result)
     }
     
-    fn guess_content_type(filename: &str) -> String {
+    pub fn guess_content_type(filename: &str) -> String {
         let lowercase_name = filename.to_lowercase();
         if lowercase_name.ends_with(".csv") {
             "text/csv".to_string()
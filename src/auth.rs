@@ -0,0 +1,158 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use google_gmail1::{hyper, hyper_rustls, oauth2};
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::config::GmailConfig;
+use crate::gmail_client::{access_scope, build_authenticator, build_device_authenticator};
+
+/// Google endpoint revoking an access or refresh token (and the grant behind it)
+const REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+
+/// Token stored in GMAIL_TOKEN_CACHE_PATH, as shown by `homemetrics auth status`
+#[derive(Debug, Clone)]
+pub struct CachedToken {
+    pub scopes: Vec<String>,
+    /// Expiry of the access token, refreshed automatically with the refresh token
+    pub expires_at: Option<DateTime<Utc>>,
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+}
+
+impl CachedToken {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Entry of the yup-oauth2 disk storage
+#[derive(Deserialize)]
+struct CacheEntry {
+    scopes: Vec<String>,
+    token: oauth2::storage::TokenInfo,
+}
+
+/// Tokens of the cache file, empty when it does not exist
+pub fn read_token_cache(path: &Path) -> Result<Vec<CachedToken>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Unable to read token cache {}", path.display())),
+    };
+
+    let entries: Vec<CacheEntry> = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid token cache {}", path.display()))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| CachedToken {
+            scopes: entry.scopes,
+            expires_at: entry.token.expires_at
+                .and_then(|expires_at| DateTime::from_timestamp(expires_at.unix_timestamp(), 0)),
+            access_token: entry.token.access_token,
+            refresh_token: entry.token.refresh_token,
+        })
+        .collect())
+}
+
+/// Run the OAuth2 consent flow now (unless a usable token is cached) and return the cached tokens
+///
+/// With `force`, the cached token is deleted first so the consent flow always runs.
+/// With `device`, the device flow is used instead of a local browser redirect.
+pub async fn login(config: &GmailConfig, force: bool, device: bool) -> Result<Vec<CachedToken>> {
+    let path = Path::new(&config.token_cache_path);
+    if force && path.exists() {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Unable to delete token cache {}", path.display()))?;
+    }
+
+    let auth = if device {
+        build_device_authenticator(config).await?
+    } else {
+        build_authenticator(config).await?
+    };
+    auth.token(&[access_scope(config.access).as_ref()])
+        .await
+        .context("OAuth2 authorization failed")?;
+
+    info!("✅ Gmail OAuth2 token cached in {}", config.token_cache_path);
+    read_token_cache(path)
+}
+
+/// Revoke the cached token at Google, then delete the cache file
+///
+/// Returns false when there was no cached token. The cache is deleted even
+/// when Google cannot be reached, the grant then stays listed in the Google
+/// account permissions until removed there.
+pub async fn revoke(config: &GmailConfig) -> Result<bool> {
+    let path = Path::new(&config.token_cache_path);
+    let tokens = read_token_cache(path)?;
+    if tokens.is_empty() && !path.exists() {
+        return Ok(false);
+    }
+
+    // Revoking the refresh token also invalidates the access tokens issued from it
+    for token in &tokens {
+        let Some(value) = token.refresh_token.as_ref().or(token.access_token.as_ref()) else {
+            continue;
+        };
+        if let Err(e) = revoke_at_google(value).await {
+            warn!("⚠️  Unable to revoke the token at Google: {}", e);
+        }
+    }
+
+    std::fs::remove_file(path)
+        .with_context(|| format!("Unable to delete token cache {}", path.display()))?;
+    info!("🗑️  Token cache {} deleted", path.display());
+    Ok(true)
+}
+
+async fn revoke_at_google(token: &str) -> Result<()> {
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_only()
+        .enable_http1()
+        .build();
+    let client = hyper::Client::builder().build::<_, hyper::Body>(connector);
+
+    let body = form_urlencoded::Serializer::new(String::new())
+        .append_pair("token", token)
+        .finish();
+    let request = hyper::Request::post(REVOKE_URL)
+        .header(hyper::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(hyper::Body::from(body))?;
+
+    let response = client.request(request).await.context("Revocation request failed")?;
+    // 400 invalid_token: already revoked or expired, nothing left to revoke
+    if !response.status().is_success() && response.status() != hyper::StatusCode::BAD_REQUEST {
+        anyhow::bail!("Google answered {}", response.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_token_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gmail-token-cache.json");
+        assert!(read_token_cache(&path).unwrap().is_empty());
+
+        std::fs::write(&path, r#"[{
+            "scopes": ["https://www.googleapis.com/auth/gmail.modify"],
+            "token": {"access_token": "ya29.a0Af", "refresh_token": "1//0gXy", "expires_at": null, "id_token": null}
+        }]"#).unwrap();
+        let tokens = read_token_cache(&path).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].scopes, ["https://www.googleapis.com/auth/gmail.modify"]);
+        assert_eq!(tokens[0].refresh_token.as_deref(), Some("1//0gXy"));
+        assert!(!tokens[0].is_expired(Utc::now()));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(read_token_cache(&path).is_err());
+    }
+}
@@ -1,43 +1,97 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use log::{debug, warn};
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+use std::sync::OnceLock;
+
+use crate::email::{ExtractError, LanguagePatterns, PatternSet};
+
+/// Pool identifier used when no `BLUERIOT_POOLS` rule matches
+pub const DEFAULT_POOL_ID: &str = "main";
+
+#[derive(Debug, Clone, Serialize)]
 pub struct PoolReading {
+    pub pool_id: String,
     pub timestamp: DateTime<Utc>,
     pub temperature: Option<f64>,
     pub ph: Option<f64>,
     pub orp: Option<i32>,
 }
 
+impl PoolReading {
+    /// Sensor row of this pool, e.g. "pool/main"
+    pub fn sensor_id(&self) -> String {
+        format!("pool/{}", self.pool_id)
+    }
+}
+
+/// Blue Riot wording per app language, then language-neutral unit patterns
+const PATTERN_SETS: &[PatternSet] = &[
+    PatternSet {
+        language: "en",
+        markers: &["the", "your", "water", "pool", "temperature", "measurement", "last", "hello", "is"],
+        fields: &[
+            ("temperature", &[
+                r"water\s+temperature\s*[:=]?\s*(-?[0-9]+(?:[.,][0-9]+)?)",
+                r"temperature\s*[:=]?\s*(-?[0-9]+(?:[.,][0-9]+)?)",
+                r"\btemp\b\s*[:=]?\s*(-?[0-9]+(?:[.,][0-9]+)?)",
+            ]),
+            ("ph", &[r"\bph\b(?:\s+level)?\s*[:=]?\s*([0-9]+[.,][0-9]+)"]),
+            ("orp", &[r"\borp\b\s*[:=]?\s*([0-9]+)", r"redox\s*[:=]?\s*([0-9]+)"]),
+        ],
+    },
+    PatternSet {
+        language: "fr",
+        markers: &["le", "la", "les", "de", "du", "votre", "eau", "piscine", "température", "mesure", "bonjour", "est"],
+        fields: &[
+            ("temperature", &[
+                r"temp[ée]rature\s+de\s+l['’]eau\s*[:=]?\s*(-?[0-9]+(?:[.,][0-9]+)?)",
+                r"temp[ée]rature\s*[:=]?\s*(-?[0-9]+(?:[.,][0-9]+)?)",
+            ]),
+            ("ph", &[r"\bph\b\s*[:=]?\s*([0-9]+[.,][0-9]+)"]),
+            ("orp", &[r"redox(?:\s*\(orp\))?\s*[:=]?\s*([0-9]+)", r"\borp\b\s*[:=]?\s*([0-9]+)"]),
+        ],
+    },
+    PatternSet {
+        language: "",
+        markers: &[],
+        fields: &[
+            ("temperature", &[r"([0-9]+[.,][0-9]+)\s*°C"]),
+            ("orp", &[r"([0-9]+)\s*mV"]),
+        ],
+    },
+];
+
+fn patterns() -> &'static LanguagePatterns {
+    static PATTERNS: OnceLock<LanguagePatterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| LanguagePatterns::new(PATTERN_SETS))
+}
+
 /// Extract pool metrics from Blue Riot email text content
 /// 
-/// Expected formats:
-/// - "Temperature: 25.5°C" or "Température: 25.5°C" or "Temp: 25.5"
-/// - "pH: 7.2" or "pH: 7,2"
-/// - "ORP: 720 mV" or "Redox: 720" or "ORP: 720mV"
+/// The app writes its emails in the user's language: the language is detected
+/// first and its patterns are tried before the others. Expected formats:
+/// - "Water temperature: 25.5°C", "Température de l'eau : 25,5 °C" or "Temp: 25.5"
+/// - "pH: 7.2" or "pH : 7,2"
+/// - "ORP: 720 mV", "Redox (ORP) : 720" or "ORP: 720mV"
 pub fn extract_pool_metrics(text: &str, timestamp: DateTime<Utc>) -> Result<PoolReading> {
     debug!("Extracting pool metrics from text (length: {} bytes)", text.len());
+
+    let language = patterns().detect(text);
+    debug!("Detected email language: {}", language.unwrap_or("unknown"));
     
-    let mut reading = PoolReading {
+    let reading = PoolReading {
+        pool_id: DEFAULT_POOL_ID.to_string(),
         timestamp,
-        temperature: None,
-        ph: None,
-        orp: None,
+        temperature: extract_temperature(text, language),
+        ph: extract_ph(text, language),
+        orp: extract_orp(text, language),
     };
     
-    // Extract temperature
-    reading.temperature = extract_temperature(text);
-    
-    // Extract pH
-    reading.ph = extract_ph(text);
-    
-    // Extract ORP
-    reading.orp = extract_orp(text);
-    
     // Validate that we extracted at least one metric
     if reading.temperature.is_none() && reading.ph.is_none() && reading.orp.is_none() {
-        anyhow::bail!("No pool metrics found in email text");
+        return Err(ExtractError::NoData("No pool metrics found in email text".to_string()).into());
     }
     
     debug!("Extracted pool reading: temp={:?}°C, pH={:?}, ORP={:?} mV", 
@@ -46,94 +100,55 @@ pub fn extract_pool_metrics(text: &str, timestamp: DateTime<Utc>) -> Result<Pool
     Ok(reading)
 }
 
+fn parse_decimal(value: &str) -> Option<f64> {
+    value.replace(',', ".").parse().ok()
+}
+
 /// Extract temperature from text
-/// Patterns: "Temperature: 25.5°C", "Température: 25.5", "Temp: 25,5"
-fn extract_temperature(text: &str) -> Option<f64> {
-    // Try different patterns
-    let patterns = [
-        r"(?i)temp[ée]rature[:\s]+([0-9]+[.,][0-9]+)",
-        r"(?i)temp[:\s]+([0-9]+[.,][0-9]+)",
-        r"([0-9]+[.,][0-9]+)\s*°C",
-    ];
-    
-    for pattern in &patterns {
-        if let Ok(re) = regex::Regex::new(pattern) {
-            if let Some(caps) = re.captures(text) {
-                if let Some(temp_str) = caps.get(1) {
-                    let temp_normalized = temp_str.as_str().replace(',', ".");
-                    if let Ok(temp) = temp_normalized.parse::<f64>() {
-                        debug!("Found temperature: {}°C (pattern: {})", temp, pattern);
-                        return Some(temp);
-                    }
-                }
-            }
-        }
+fn extract_temperature(text: &str, language: Option<&str>) -> Option<f64> {
+    let temperature = patterns().captures(text, language, "temperature").into_iter().find_map(parse_decimal);
+    match temperature {
+        Some(temp) => debug!("Found temperature: {}°C", temp),
+        None => warn!("Temperature not found in text"),
     }
-    
-    warn!("Temperature not found in text");
-    None
+    temperature
 }
 
-/// Extract pH from text
-/// Patterns: "pH: 7.2", "pH 7,2", "ph: 7.25"
-fn extract_ph(text: &str) -> Option<f64> {
-    let patterns = [
-        r"(?i)ph[:\s]+([0-9]+[.,][0-9]+)",
-        r"(?i)ph\s*=\s*([0-9]+[.,][0-9]+)",
-    ];
-    
-    for pattern in &patterns {
-        if let Ok(re) = regex::Regex::new(pattern) {
-            if let Some(caps) = re.captures(text) {
-                if let Some(ph_str) = caps.get(1) {
-                    let ph_normalized = ph_str.as_str().replace(',', ".");
-                    if let Ok(ph) = ph_normalized.parse::<f64>() {
-                        // Validate pH range (0-14)
-                        if (0.0..=14.0).contains(&ph) {
-                            debug!("Found pH: {} (pattern: {})", ph, pattern);
-                            return Some(ph);
-                        } else {
-                            warn!("pH value out of range: {}", ph);
-                        }
-                    }
-                }
+/// Extract pH from text, skipping values outside 0-14
+fn extract_ph(text: &str, language: Option<&str>) -> Option<f64> {
+    let ph = patterns().captures(text, language, "ph").into_iter()
+        .filter_map(parse_decimal)
+        .find(|ph| {
+            let valid = (0.0..=14.0).contains(ph);
+            if !valid {
+                warn!("pH value out of range: {}", ph);
             }
-        }
+            valid
+        });
+    match ph {
+        Some(ph) => debug!("Found pH: {}", ph),
+        None => warn!("pH not found in text"),
     }
-    
-    warn!("pH not found in text");
-    None
+    ph
 }
 
-/// Extract ORP (Oxidation-Reduction Potential) from text
-/// Patterns: "ORP: 720 mV", "Redox: 720", "ORP: 720mV"
-fn extract_orp(text: &str) -> Option<i32> {
-    let patterns = [
-        r"(?i)orp[:\s]+([0-9]+)\s*m?V?",
-        r"(?i)redox[:\s]+([0-9]+)\s*m?V?",
-        r"([0-9]+)\s*mV",
-    ];
-    
-    for pattern in &patterns {
-        if let Ok(re) = regex::Regex::new(pattern) {
-            if let Some(caps) = re.captures(text) {
-                if let Some(orp_str) = caps.get(1) {
-                    if let Ok(orp) = orp_str.as_str().parse::<i32>() {
-                        // Validate ORP range (typically 0-1000 mV for pools)
-                        if (0..=1000).contains(&orp) {
-                            debug!("Found ORP: {} mV (pattern: {})", orp, pattern);
-                            return Some(orp);
-                        } else {
-                            warn!("ORP value out of range: {}", orp);
-                        }
-                    }
-                }
+/// Extract ORP (Oxidation-Reduction Potential) from text, skipping values
+/// outside 0-1000 mV (the usual range for pools)
+fn extract_orp(text: &str, language: Option<&str>) -> Option<i32> {
+    let orp = patterns().captures(text, language, "orp").into_iter()
+        .filter_map(|value| value.parse::<i32>().ok())
+        .find(|orp| {
+            let valid = (0..=1000).contains(orp);
+            if !valid {
+                warn!("ORP value out of range: {}", orp);
             }
-        }
+            valid
+        });
+    match orp {
+        Some(orp) => debug!("Found ORP: {} mV", orp),
+        None => warn!("ORP not found in text"),
     }
-    
-    warn!("ORP not found in text");
-    None
+    orp
 }
 
 #[cfg(test)]
@@ -142,28 +157,28 @@ mod tests {
     
     #[test]
     fn test_extract_temperature() {
-        assert_eq!(extract_temperature("Temperature: 25.5°C"), Some(25.5));
-        assert_eq!(extract_temperature("Température: 24,8°C"), Some(24.8));
-        assert_eq!(extract_temperature("Temp: 26.2"), Some(26.2));
-        assert_eq!(extract_temperature("No temp here"), None);
+        assert_eq!(extract_temperature("Temperature: 25.5°C", None), Some(25.5));
+        assert_eq!(extract_temperature("Température: 24,8°C", None), Some(24.8));
+        assert_eq!(extract_temperature("Temp: 26.2", None), Some(26.2));
+        assert_eq!(extract_temperature("No temp here", None), None);
     }
     
     #[test]
     fn test_extract_ph() {
-        assert_eq!(extract_ph("pH: 7.2"), Some(7.2));
-        assert_eq!(extract_ph("pH 7,4"), Some(7.4));
-        assert_eq!(extract_ph("ph = 7.15"), Some(7.15));
-        assert_eq!(extract_ph("No pH here"), None);
-        assert_eq!(extract_ph("pH: 15.0"), None); // Out of range
+        assert_eq!(extract_ph("pH: 7.2", None), Some(7.2));
+        assert_eq!(extract_ph("pH 7,4", None), Some(7.4));
+        assert_eq!(extract_ph("ph = 7.15", None), Some(7.15));
+        assert_eq!(extract_ph("No pH here", None), None);
+        assert_eq!(extract_ph("pH: 15.0", None), None); // Out of range
     }
     
     #[test]
     fn test_extract_orp() {
-        assert_eq!(extract_orp("ORP: 720 mV"), Some(720));
-        assert_eq!(extract_orp("Redox: 680"), Some(680));
-        assert_eq!(extract_orp("ORP: 750mV"), Some(750));
-        assert_eq!(extract_orp("No ORP here"), None);
-        assert_eq!(extract_orp("ORP: 2000"), None); // Out of range
+        assert_eq!(extract_orp("ORP: 720 mV", None), Some(720));
+        assert_eq!(extract_orp("Redox: 680", None), Some(680));
+        assert_eq!(extract_orp("ORP: 750mV", None), Some(750));
+        assert_eq!(extract_orp("No ORP here", None), None);
+        assert_eq!(extract_orp("ORP: 2000", None), None); // Out of range
     }
     
     #[test]
@@ -176,4 +191,17 @@ mod tests {
         assert_eq!(reading.ph, Some(7.2));
         assert_eq!(reading.orp, Some(720));
     }
+
+    #[test]
+    fn test_extract_pool_metrics_by_language() {
+        let fr = "Bonjour,\nVoici la dernière mesure de votre piscine.\nTempérature de l'eau : 26,5 °C\npH : 7,3\nRedox (ORP) : 705 mV";
+        assert_eq!(patterns().detect(fr), Some("fr"));
+        let reading = extract_pool_metrics(fr, Utc::now()).unwrap();
+        assert_eq!((reading.temperature, reading.ph, reading.orp), (Some(26.5), Some(7.3), Some(705)));
+
+        let en = "Hello,\nHere is the last measurement of your pool.\nWater temperature: 27 °C\npH level: 7.6\nORP: 650 mV";
+        assert_eq!(patterns().detect(en), Some("en"));
+        let reading = extract_pool_metrics(en, Utc::now()).unwrap();
+        assert_eq!((reading.temperature, reading.ph, reading.orp), (Some(27.0), Some(7.6), Some(650)));
+    }
 }
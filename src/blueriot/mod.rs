@@ -1,6 +1,8 @@
 /// Blue Riot pool monitoring email processing module
 pub mod extractor;
+pub mod pools;
 pub mod processor;
 
-pub use extractor::PoolReading;
+pub use extractor::{PoolReading, DEFAULT_POOL_ID};
+pub use pools::PoolResolver;
 pub use processor::BlueRiotEmailProcessor;
@@ -0,0 +1,77 @@
+use anyhow::{Result, Context};
+use regex::Regex;
+
+use super::extractor::DEFAULT_POOL_ID;
+
+/// Maps a Blue Riot email to the pool it reports on
+///
+/// Rules come from `BLUERIOT_POOLS` (`spa=spa|jacuzzi;main=piscine`) and are
+/// tried in order against the subject first, then the body (device name).
+/// Patterns are case-insensitive regexes.
+#[derive(Debug, Clone, Default)]
+pub struct PoolResolver {
+    rules: Vec<(String, Regex)>,
+}
+
+impl PoolResolver {
+    pub fn from_config(pools: &[(String, String)]) -> Result<Self> {
+        let rules = pools.iter()
+            .map(|(pool_id, pattern)| {
+                let re = Regex::new(&format!("(?i){}", pattern))
+                    .with_context(|| format!("Invalid BLUERIOT_POOLS pattern '{}' for pool '{}'", pattern, pool_id))?;
+                Ok((pool_id.clone(), re))
+            })
+            .collect::<Result<_>>()?;
+        Ok(PoolResolver { rules })
+    }
+
+    /// True when pools are configured (pool id shown in messages and summaries)
+    pub fn is_multi_pool(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    pub fn resolve(&self, subject: &str, text: &str) -> String {
+        [subject, text].iter()
+            .find_map(|haystack| {
+                self.rules.iter()
+                    .find(|(_, re)| re.is_match(haystack))
+                    .map(|(pool_id, _)| pool_id.clone())
+            })
+            .unwrap_or_else(|| DEFAULT_POOL_ID.to_string())
+    }
+}
+
+/// Parse `BLUERIOT_POOLS`: `id=pattern` entries separated by `;`
+///
+/// `;` is the only separator since patterns commonly contain `,` or `|`.
+pub fn parse_pools(value: &str) -> Vec<(String, String)> {
+    value.split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let (pool_id, pattern) = entry.split_once('=')?;
+            let (pool_id, pattern) = (pool_id.trim().to_lowercase(), pattern.trim());
+            if pool_id.is_empty() || pattern.is_empty() {
+                log::warn!("Ignoring invalid BLUERIOT_POOLS entry '{}'", entry.trim());
+                return None;
+            }
+            Some((pool_id, pattern.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_pool() {
+        let resolver = PoolResolver::from_config(&parse_pools("spa=spa|jacuzzi; main=piscine")).unwrap();
+
+        assert_eq!(resolver.resolve("Blue Connect - Jacuzzi", ""), "spa");
+        assert_eq!(resolver.resolve("Your weekly report", "Device: Piscine"), "main");
+        // Subject wins over the body
+        assert_eq!(resolver.resolve("Spa report", "Piscine"), "spa");
+        assert_eq!(resolver.resolve("Report", "nothing"), DEFAULT_POOL_ID);
+        assert_eq!(PoolResolver::default().resolve("Spa", ""), DEFAULT_POOL_ID);
+    }
+}
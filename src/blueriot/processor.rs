@@ -1,15 +1,65 @@
-use anyhow::{Result, Context};
-use log::{debug, info};
+use anyhow::Result;
+use std::path::Path;
+use log::{debug, info, warn};
 
+use crate::archive::AttachmentArchive;
+use crate::attachment_parser::AttachmentParser;
 use crate::config::Config;
-use crate::gmail_client::GmailClient;
-use crate::database::Database;
-use crate::slack_notifier::SlackNotifier;
-use crate::email::{EmailProcessingStrategy, BaseEmailProcessor};
+use crate::pipeline::EmailSource;
+use crate::gmail_client::{GmailClient, PostAction};
+use crate::storage::{FailedExtraction, SensorType, Storage};
+use crate::slack_notifier::{NotificationKind, NotificationSource, Severity, SlackNotifier};
+use crate::email::{DryRunDetail, EmailHeaders, EmailProcessingStrategy, BaseEmailProcessor, EmailFilter, ExtractError, ProcessingResult, Provenance, Reading, RunLimits, RunSummary};
+use crate::i18n::Locale;
+use crate::templates::{NotificationEvent, NotificationTemplates};
+use crate::units::UnitSystem;
 use super::extractor;
+use super::pools::PoolResolver;
+use crate::transforms::TransformChain;
+use crate::validation::validate_pool_reading;
+
+/// Name used for the pool in per-sensor run summaries
+const POOL_SENSOR_NAME: &str = "pool";
 
 /// Blue Riot specific processing strategy
-pub struct BlueRiotStrategy;
+pub struct BlueRiotStrategy {
+    units: UnitSystem,
+    locale: Locale,
+    templates: NotificationTemplates,
+    filter: EmailFilter,
+    todo_label: String,
+    after_processing: Vec<PostAction>,
+    pools: PoolResolver,
+    site: Option<String>,
+    save_attachments: bool,
+    transforms: TransformChain,
+}
+
+impl BlueRiotStrategy {
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(BlueRiotStrategy {
+            units: config.units,
+            locale: config.locale,
+            templates: NotificationTemplates::from_config(config)?,
+            filter: EmailFilter::from_config(&config.blueriot.filter)?,
+            todo_label: format!("{}/todo/blueriot", config.gmail.label_prefix),
+            after_processing: config.blueriot.after_processing.clone(),
+            pools: PoolResolver::from_config(&config.pools)?,
+            site: config.site.clone(),
+            save_attachments: config.blueriot.save_attachments,
+            transforms: config.blueriot.transforms.clone(),
+        })
+    }
+    
+    /// Summary key of a pool: "pool" for a single pool, "pool/<id>" otherwise
+    fn sensor_name(&self, pool_id: &str) -> String {
+        if self.pools.is_multi_pool() {
+            format!("{}/{}", POOL_SENSOR_NAME, pool_id)
+        } else {
+            POOL_SENSOR_NAME.to_string()
+        }
+    }
+}
 
 impl EmailProcessingStrategy for BlueRiotStrategy {
     fn search_emails<'a, 'b: 'a>(&'a self, gmail: &'b GmailClient) -> 
@@ -20,26 +70,35 @@ impl EmailProcessingStrategy for BlueRiotStrategy {
     fn process_single_email<'a, 'b: 'a, 'c: 'a>(
         &'a self,
         gmail: &'b GmailClient,
-        database: Option<&'c Database>,
+        database: Option<&'c dyn Storage>,
         slack: Option<&'c SlackNotifier>,
+        archive: Option<&'c AttachmentArchive>,
         message_id: &'a str,
-        is_dry_run: bool,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize>> + Send + 'a>> {
+        dry_run: Option<DryRunDetail>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ProcessingResult>> + Send + 'a>> {
         Box::pin(async move {
             debug!("Processing Blue Riot email ID: {}", message_id);
+            let is_dry_run = dry_run.is_some();
+            let show = dry_run.is_some_and(|detail| detail.per_email());
             
             // Fetch email metadata
-            let (subject, _from) = gmail.fetch_email_metadata(message_id).await?;
+            let (subject, from) = gmail.fetch_email_metadata(message_id).await?;
             debug!("Email subject: {}", subject);
             
             // Fetch complete email content
             let email = gmail.fetch_email_complete(message_id).await?;
             debug!("Email date: {}", email.date);
+            let headers = EmailHeaders { subject: subject.clone(), from, date: email.date };
+            
+            // Reports may come with a PDF or charts, kept as received
+            if let Some(archive) = archive {
+                archive.save_all(email.date, &AttachmentParser::parse_email(&email.content)?).await;
+            }
             
             // Parse email to extract text body
             let parsed_email = mail_parser::MessageParser::default()
                 .parse(&email.content)
-                .context("Failed to parse email")?;
+                .ok_or_else(|| ExtractError::Email("not a valid RFC 822 message".to_string()))?;
             
             // Extract text from email body
             let mut text_content = String::new();
@@ -69,39 +128,120 @@ impl EmailProcessingStrategy for BlueRiotStrategy {
                 text_content = String::from_utf8_lossy(&email.content).to_string();
             }
             
-            if is_dry_run {
-                println!("\n📧 Email: {}", subject);
-                println!("📅 Date: {}", email.date);
-                println!("📄 Text content (first 500 chars):\n{}\n", 
-                         &text_content.chars().take(500).collect::<String>());
+            if show {
+                println!("\n📧 {}: {}", self.locale.label_subject(), subject);
+                println!("📅 {}: {}", self.locale.label_date(), email.date);
+                if dry_run == Some(DryRunDetail::Full) {
+                    println!("{}\n", self.locale.text_content_full(&text_content));
+                } else {
+                    println!("{}\n", self.locale.text_content_preview(&text_content.chars().take(500).collect::<String>()));
+                }
             }
             
             // Extract pool metrics from email text
-            let pool_reading = extractor::extract_pool_metrics(&text_content, email.date)
-                .context("Failed to extract pool metrics from email")?;
+            let mut pool_reading = match extractor::extract_pool_metrics(&text_content, email.date) {
+                Ok(reading) => reading,
+                Err(e) => {
+                    // Keep the email text so the parser failure can be reproduced
+                    if let (false, Some(db)) = (is_dry_run, database) {
+                        let failure = FailedExtraction {
+                            source: "blueriot".to_string(),
+                            email_id: message_id.to_string(),
+                            filename: String::new(),
+                            content: text_content.clone().into_bytes(),
+                            error: format!("{:#}", e),
+                        };
+                        if let Err(e) = db.save_failed_extraction(&failure).await {
+                            warn!("Unable to keep failed email text {}: {:#}", message_id, e);
+                        }
+                    }
+                    return Err(e.context("Failed to extract pool metrics from email"));
+                }
+            };
+            pool_reading.pool_id = self.pools.resolve(&subject, &text_content);
+            self.transforms.apply_pool(&mut pool_reading);
+            debug!("Email attributed to pool '{}'", pool_reading.pool_id);
+            let sensor_name = self.sensor_name(&pool_reading.pool_id);
             
-            if is_dry_run {
-                println!("🏊 Pool Metrics Extracted:");
-                if let Some(temp) = pool_reading.temperature {
-                    println!("   🌡️  Temperature: {:.1}°C", temp);
+            // Out-of-range readings are kept in rejected_readings instead of pool_readings
+            if let Some(rejection) = validate_pool_reading(&pool_reading, message_id) {
+                let mut result = ProcessingResult { headers: Some(headers), ..ProcessingResult::empty() };
+                result.sensors.entry(sensor_name).or_default().rejected += 1;
+                if is_dry_run {
+                    if show {
+                        println!("{}", self.locale.reading_rejected(&rejection.sensor_id, &rejection.reason));
+                    }
+                } else if let Some(db) = database {
+                    db.save_rejected_readings(std::slice::from_ref(&rejection)).await?;
                 }
-                if let Some(ph) = pool_reading.ph {
-                    println!("   🧪 pH: {:.2}", ph);
+                return Ok(result);
+            }
+            let mut result = ProcessingResult { records: 1, headers: Some(headers), ..ProcessingResult::empty() };
+            if dry_run.is_some_and(|detail| detail.keeps_readings()) {
+                result.readings.push(Reading::from_pool(&pool_reading, self.site.as_deref(), Provenance::email("blueriot", message_id, None)));
+            }
+            
+            if is_dry_run {
+                if show {
+                    println!("{}", self.locale.pool_metrics_title());
+                    if self.pools.is_multi_pool() {
+                        println!("   🏊 {}: {}", self.locale.label_pool(), pool_reading.pool_id);
+                    }
+                    if let Some(temp) = pool_reading.temperature {
+                        println!("   🌡️  {}: {}", self.locale.label_temperature(), self.units.format_temperature(temp));
+                    }
+                    if let Some(ph) = pool_reading.ph {
+                        println!("   🧪 pH: {:.2}", ph);
+                    }
+                    if let Some(orp) = pool_reading.orp {
+                        println!("   ⚡ ORP: {} mV", orp);
+                    }
+                    println!();
                 }
-                if let Some(orp) = pool_reading.orp {
-                    println!("   ⚡ ORP: {} mV", orp);
+                
+                // Compare with stored readings when the database is reachable
+                if let Some(db) = database {
+                    match db.pool_reading_exists(&pool_reading.pool_id, message_id).await {
+                        Ok(exists) => {
+                            let counts = result.sensors.entry(sensor_name.clone()).or_default();
+                            if exists {
+                                counts.duplicates += 1;
+                            } else {
+                                counts.inserted += 1;
+                            }
+                            if show {
+                                println!("{}", self.locale.dry_run_diff_title());
+                                println!("{}\n", self.locale.dry_run_diff_line(&sensor_name, counts.inserted, counts.duplicates));
+                            }
+                        }
+                        Err(e) if show => {
+                            println!("{}", self.locale.dry_run_diff_failed(&e.to_string()));
+                        }
+                        Err(_) => {}
+                    }
                 }
-                println!();
             } else {
                 // Save to database
                 if let Some(db) = database {
-                    db.save_pool_reading(&pool_reading, message_id).await?;
+                    let inserted = db.save_pool_reading(&pool_reading, message_id).await?;
+                    info!(source = "blueriot", email_id = message_id, sensor = sensor_name.as_str();
+                          "Pool reading of {} {}", sensor_name, if inserted { "saved" } else { "already stored" });
+                    if let Err(e) = db.register_sensor(&pool_reading.sensor_id(), SensorType::Pool).await {
+                        warn!("Unable to register sensor {}: {:#}", pool_reading.sensor_id(), e);
+                    }
+                    let counts = result.sensors.entry(sensor_name.clone()).or_default();
+                    if inserted {
+                        counts.inserted += 1;
+                    } else {
+                        counts.duplicates += 1;
+                    }
+                    result.readings.push(Reading::from_pool(&pool_reading, self.site.as_deref(), Provenance::email("blueriot", message_id, None)));
                     
                     // Send Slack notification
                     if let Some(slack) = slack {
                         let mut metrics = Vec::new();
                         if let Some(temp) = pool_reading.temperature {
-                            metrics.push(format!("🌡️ {}°C", temp));
+                            metrics.push(format!("🌡️ {}", self.units.format_temperature(temp)));
                         }
                         if let Some(ph) = pool_reading.ph {
                             metrics.push(format!("🧪 pH {:.2}", ph));
@@ -110,14 +250,21 @@ impl EmailProcessingStrategy for BlueRiotStrategy {
                             metrics.push(format!("⚡ {} mV", orp));
                         }
                         
-                        let message = format!(
-                            "🏊 New pool reading: {}\nFrom: {}",
-                            metrics.join(" | "),
-                            subject
-                        );
+                        let context = minijinja::context! {
+                            pool_id => &pool_reading.pool_id,
+                            temperature => pool_reading.temperature.map(|t| self.units.temperature(t)),
+                            ph => pool_reading.ph,
+                            orp => pool_reading.orp,
+                            metrics => metrics.join(" | "),
+                            subject => &subject,
+                        };
+                        let message = self.templates.render_or(NotificationEvent::PoolReading, context, || {
+                            let pool = self.pools.is_multi_pool().then_some(pool_reading.pool_id.as_str());
+                            self.locale.notify_pool_reading(pool, &metrics.join(" | "), &subject)
+                        });
                         
                         info!("Sending Slack notification for Blue Riot reading");
-                        if let Err(e) = slack.send_message(&message).await {
+                        if let Err(e) = slack.notify(NotificationSource::BlueRiot, Severity::Info, NotificationKind::Readings, Some(message_id), &message).await {
                             debug!("Failed to send Slack notification: {}", e);
                         } else {
                             debug!("Slack notification sent successfully");
@@ -126,8 +273,7 @@ impl EmailProcessingStrategy for BlueRiotStrategy {
                 }
             }
             
-            // Return 1 to indicate one record processed (the pool reading)
-            Ok(1)
+            Ok(result)
         })
     }
     
@@ -136,7 +282,7 @@ impl EmailProcessingStrategy for BlueRiotStrategy {
         gmail: &'b GmailClient,
         message_id: &'a str,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
-        Box::pin(gmail.mark_pool_email_as_processed(message_id))
+        Box::pin(gmail.mark_processed(message_id, "blueriot", &self.after_processing))
     }
     
     fn processor_name(&self) -> &str {
@@ -144,7 +290,23 @@ impl EmailProcessingStrategy for BlueRiotStrategy {
     }
     
     fn label_name(&self) -> &str {
-        "homemetrics/todo/blueriot"
+        &self.todo_label
+    }
+    
+    fn email_filter(&self) -> &EmailFilter {
+        &self.filter
+    }
+    
+    fn saves_attachments(&self) -> bool {
+        self.save_attachments
+    }
+    
+    fn processed_label_changes(&self, gmail: &GmailClient) -> (Vec<String>, Vec<String>) {
+        gmail.processed_label_changes("blueriot", &self.after_processing)
+    }
+    
+    fn notification_source(&self) -> NotificationSource {
+        NotificationSource::BlueRiot
     }
 }
 
@@ -155,20 +317,50 @@ pub struct BlueRiotEmailProcessor {
 
 impl BlueRiotEmailProcessor {
     pub async fn new(config: &Config, dry_run: bool) -> Result<Self> {
+        let config = &config.for_source(EmailSource::BlueRiot);
         let processor = if dry_run {
             BlueRiotEmailProcessor {
-                base: BaseEmailProcessor::new_dry_run(config.clone(), BlueRiotStrategy)?,
+                base: BaseEmailProcessor::new_dry_run(config.clone(), BlueRiotStrategy::new(config)?).await?,
             }
         } else {
             BlueRiotEmailProcessor {
-                base: BaseEmailProcessor::new(config.clone(), BlueRiotStrategy).await?,
+                base: BaseEmailProcessor::new(config.clone(), BlueRiotStrategy::new(config)?).await?,
             }
         };
         Ok(processor)
     }
     
-    pub async fn process_emails(&self, limit: Option<usize>) -> Result<()> {
-        self.base.process_emails(limit).await?;
-        Ok(())
+    pub async fn new_history(config: &Config) -> Result<Self> {
+        let config = &config.for_source(EmailSource::BlueRiot);
+        Ok(BlueRiotEmailProcessor {
+            base: BaseEmailProcessor::new_history(config.clone(), BlueRiotStrategy::new(config)?).await?,
+        })
     }
+    
+    pub async fn new_sandbox(config: &Config, sqlite_path: &Path) -> Result<Self> {
+        let config = &config.for_source(EmailSource::BlueRiot);
+        Ok(BlueRiotEmailProcessor {
+            base: BaseEmailProcessor::new_sandbox(config.clone(), BlueRiotStrategy::new(config)?, sqlite_path).await?,
+        })
+    }
+    
+    pub async fn new_backfill(config: &Config) -> Result<Self> {
+        let config = &config.for_source(EmailSource::BlueRiot);
+        Ok(BlueRiotEmailProcessor {
+            base: BaseEmailProcessor::new_backfill(config.clone(), BlueRiotStrategy::new(config)?).await?,
+        })
+    }
+    
+    pub async fn process_emails(&self, gmail: &GmailClient, limits: RunLimits) -> Result<RunSummary> {
+        self.base.process_emails(gmail, limits).await
+    }
+    
+    pub async fn process_emails_dry_run(&self, gmail: &GmailClient, limits: RunLimits, detail: DryRunDetail) -> Result<RunSummary> {
+        self.base.process_emails_dry_run(gmail, limits, detail).await
+    }
+    
+    pub async fn reprocess(&self, gmail: &GmailClient, message_ids: &[String], dry_run: Option<DryRunDetail>) -> Result<RunSummary> {
+        self.base.reprocess(gmail, message_ids, dry_run).await
+    }
+
 }
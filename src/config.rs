@@ -1,25 +1,196 @@
-use anyhow::Result;
+use anyhow::{Result, Context};
+use chrono::NaiveDate;
 use serde::Deserialize;
 
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::alerts::{parse_humidity_rules, parse_rules, AlertRule, HumidityRule};
+use crate::archive::ArchiveLayout;
+use crate::blueriot::pools::parse_pools;
+use crate::email::ProcessingOrder;
+use crate::gmail_client::PostAction;
+use crate::hooks::HooksConfig;
+use crate::health::{parse_freshness_rules, FreshnessRule};
+use crate::pipeline::{EmailSource, PipelineMode};
+use crate::transforms::TransformChain;
+use crate::i18n::Locale;
+use crate::slack_notifier::{parse_routes, SlackMode};
+use crate::storage::StorageLayout;
+use crate::units::UnitSystem;
+use crate::xsense::{CsvColumn, CsvColumns};
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
+    pub profile: Option<String>, // Name from PROFILES, None for a single-home setup
     pub gmail: GmailConfig,
     pub database: DatabaseConfig,
     pub data_dir: String,
+    pub data_dir_layout: ArchiveLayout, // Subdirectories of the saved attachments under data_dir
+    pub site: Option<String>, // Home label stamped on stored sensors and readings
     pub scheduler: SchedulerConfig,
     pub slack: Option<SlackConfig>,
+    pub notification_templates_dir: Option<String>, // User templates overriding notification bodies
+    pub server: ServerConfig,
+    pub receiver: ReceiverConfig,
+    pub processing_order: ProcessingOrder,
+    pub batch_size: Option<usize>, // Emails per source and run at most, the next run continues after the checkpoint
+    pub email_timeout: Option<Duration>, // Longest processing of one email before it is cancelled and retried, None disables it
+    pub pipeline: PipelineConfig,
+    pub retry: RetryConfig,
+    pub cleanup: CleanupConfig,
+    pub forecast: ForecastConfig,
+    pub alerts: AlertConfig,
+    pub health: HealthConfig,
+    pub hooks: HooksConfig,
+    pub mold_alert_after_hours: u32, // Hours a room stays in the mold risk zone before a warning, 0 disables it
+    pub xsense: SourceConfig,
+    pub blueriot: SourceConfig,
+    pub pools: Vec<(String, String)>, // Blue Riot pool id → subject/body pattern, tried in order
+    pub units: UnitSystem, // Display units only, storage stays in Celsius
+    pub locale: Locale,    // Language of console output and notifications
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SchedulerConfig {
     pub enabled: bool,
-    pub schedule_times: Vec<String>, // Format: "HH:MM" (e.g., ["02:00", "14:00"])
+    pub schedule_times: Vec<String>, // Format: "HH:MM" (e.g., ["02:00", "14:00"]), "*:MM" every hour
+    pub source_times: Vec<(EmailSource, Vec<String>)>, // <SOURCE>_SCHEDULER_TIMES, instead of schedule_times for that source
+    pub cron: Vec<String>, // SCHEDULER_CRON expressions, run along schedule_times
+    pub retry_attempts: u32, // Retries of a scheduled run failing with a transient error
+    pub retry_delay_seconds: u64, // Wait before the first retry, doubled on each one
+    pub run_on_start: bool, // One run when the daemon starts, before the first scheduled time
+    pub jitter: Option<Duration>, // Random delay of each scheduled run, at most this long
+    pub catch_up: bool, // Run at once the scheduled times missed while the daemon was down or asleep
+    pub control_socket: Option<String>, // Unix socket answering `homemetrics status`, None disables it
+}
+
+impl SchedulerConfig {
+    /// Times of a source: `<SOURCE>_SCHEDULER_TIMES`, or `SCHEDULER_TIMES`
+    pub fn times_for(&self, source: EmailSource) -> &[String] {
+        self.source_times.iter()
+            .find(|(candidate, _)| *candidate == source)
+            .map_or(&self.schedule_times, |(_, times)| times)
+    }
+    
+    /// Each scheduled time or cron expression with the sources run then, kept in the order of `sources`
+    ///
+    /// Sources with their own times don't follow `SCHEDULER_CRON`.
+    pub fn runs(&self, sources: &[EmailSource]) -> Vec<(String, Vec<EmailSource>)> {
+        let mut runs: Vec<(String, Vec<EmailSource>)> = Vec::new();
+        for &source in sources {
+            let own_times = self.source_times.iter().any(|(candidate, _)| *candidate == source);
+            let cron: &[String] = if own_times { &[] } else { &self.cron };
+            for time in self.times_for(source).iter().chain(cron) {
+                match runs.iter_mut().find(|(candidate, _)| candidate == time) {
+                    Some((_, sources)) => sources.push(source),
+                    None => runs.push((time.clone(), vec![source])),
+                }
+            }
+        }
+        runs
+    }
+}
+
+/// Cron expressions separated by ';', since they contain commas
+///
+/// Each one is checked now rather than when the daemon schedules it.
+fn parse_cron(value: &str) -> Result<Vec<String>> {
+    value.split(';')
+        .map(str::trim)
+        .filter(|expression| !expression.is_empty())
+        .map(|expression| {
+            normalize_cron(expression)?;
+            Ok(expression.to_string())
+        })
+        .collect()
+}
+
+/// Six-field form (seconds first) of a standard 5-field cron expression;
+/// expressions with seconds (6 or 7 fields) are kept
+///
+/// The scheduler counts weekdays from 1 for Sunday: the weekdays of a
+/// 5-field expression (0 or 7 for Sunday, 1-5 for Monday to Friday) are
+/// written as names.
+pub fn normalize_cron(expression: &str) -> Result<String> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let expression = match fields.len() {
+        5 => format!("0 {} {}", fields[..4].join(" "), weekday_names(fields[4])),
+        6 | 7 => fields.join(" "),
+        _ => anyhow::bail!("Invalid cron expression '{}' (expected 5 fields: minute hour day month weekday)", expression),
+    };
+    expression.parse::<cron::Schedule>()
+        .map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expression, e))?;
+    Ok(expression)
+}
+
+/// Weekday field with its numbers written as names (`1-5/2` → `MON-FRI/2`)
+fn weekday_names(field: &str) -> String {
+    const NAMES: [&str; 8] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT", "SUN"];
+    let name = |day: &str| day.parse::<usize>().ok().and_then(|day| NAMES.get(day)).map_or(day.to_string(), |name| name.to_string());
+    field.split(',')
+        .map(|item| {
+            // The step after '/' is a count, not a day
+            let (days, step) = item.split_once('/').map_or((item, None), |(days, step)| (days, Some(step)));
+            let days: Vec<String> = days.split('-').map(name).collect();
+            match step {
+                Some(step) => format!("{}/{}", days.join("-"), step),
+                None => days.join("-"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Comma-separated scheduling times
+fn parse_times(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct GmailConfig {
     pub credentials_path: String,
     pub token_cache_path: String,
+    pub label_prefix: String, // Labels are <prefix>/todo/<source> and <prefix>/done/<source>
+    pub daily_call_limit: u64, // Gmail API requests allowed per day before warning, 0 disables it
+    pub access: GmailAccess, // OAuth2 scope requested (GMAIL_SCOPE)
+    pub label_changes: bool, // Cleared by --no-label-changes: labels left as they are, even with modify access
+    pub search_since: Option<NaiveDate>, // Set by --since: only emails received from this day are searched
+    pub search_until: Option<NaiveDate>, // Set by --until: only emails received up to this day (included)
+}
+
+impl GmailConfig {
+    /// Whether todo/done/error labels are changed after processing
+    pub fn modifies_labels(&self) -> bool {
+        self.access == GmailAccess::Modify && self.label_changes
+    }
+}
+
+/// Gmail OAuth2 scope requested (`GMAIL_SCOPE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GmailAccess {
+    /// `gmail.modify`: todo labels are replaced by done labels after processing
+    #[default]
+    Modify,
+    /// `gmail.readonly`: the mailbox is never changed, processed emails are
+    /// remembered in the `processed_emails` table instead
+    ReadOnly,
+}
+
+impl GmailAccess {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "").as_str() {
+            "modify" | "gmail.modify" => Some(GmailAccess::Modify),
+            "readonly" | "gmail.readonly" => Some(GmailAccess::ReadOnly),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -29,68 +200,732 @@ pub struct DatabaseConfig {
     pub database: String,
     pub username: String,
     pub password: String,
+    pub layout: StorageLayout, // Per-domain tables or a single generic readings table
+    pub sensor_partitions: u32, // Readings tables partitioned by sensor_id, 0 = disabled
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ServerConfig {
+    pub enabled: bool,
+    pub bind_address: String, // Format: "host:port" (e.g., "0.0.0.0:8080")
+    pub ingest_token: Option<String>, // Bearer token required by POST /ingest
+    pub cache_ttl_secs: u64, // Lifetime of cached GET responses, 0 disables the cache; bounds the staleness left by other processes' writes
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReceiverConfig {
+    pub drop_dir: Option<String>, // Directory watched for dropped export files
+    pub poll_interval_secs: u64,
+}
+
+/// Email sources processed on each run, sharing one Gmail connection
+#[derive(Debug, Deserialize, Clone)]
+pub struct PipelineConfig {
+    pub sources: Vec<EmailSource>, // Run in this order when sequential
+    pub mode: PipelineMode,
+}
+
+/// Backoff of the failed email retry queue
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryConfig {
+    pub base_delay_minutes: u64, // Wait before the first retry, doubled on each failure
+    pub max_delay_hours: u64,
+    pub max_attempts: u32, // Failed attempts before the email is dead-lettered
+}
+
+/// Clean-up of processed emails (`<prefix>/done/*`) once old enough
+#[derive(Debug, Deserialize, Clone)]
+pub struct CleanupConfig {
+    pub after_days: u32, // Age of done emails before clean-up, 0 disables it
+    pub action: CleanupAction,
+}
+
+/// Temperature alert rules and their webhook actions, humidity rules
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlertConfig {
+    pub rules: Vec<AlertRule>,
+    pub humidity_rules: Vec<HumidityRule>, // Too dry/too humid thresholds per room, with a minimum duration
+    pub webhook_retries: u32, // Retries of a failed webhook call, with a doubling delay
+}
+
+/// Freshness SLA checked by `GET /health` and `homemetrics health`
+#[derive(Debug, Deserialize, Clone)]
+pub struct HealthConfig {
+    pub sensors: Vec<FreshnessRule>, // Required sensors and the maximum age of their newest reading
+}
+
+/// Daily forecast digest of pool and room temperatures
+#[derive(Debug, Deserialize, Clone)]
+pub struct ForecastConfig {
+    pub time: Option<String>, // "HH:MM" of the digest in daemon mode, None disables it
+    pub lookback_days: u32, // Days of history the trend is fitted on
+    pub freeze_threshold: f64, // °C, rooms whose forecast minimum reaches it are flagged
+}
+
+/// What the clean-up does with an old processed email (`CLEANUP_ACTION`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupAction {
+    /// Move to the trash, emptied by Gmail after 30 days
+    #[default]
+    Trash,
+    /// Only remove the done label, the email stays in the mailbox
+    Unlabel,
+}
+
+impl CleanupAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "trash" => Some(CleanupAction::Trash),
+            "unlabel" => Some(CleanupAction::Unlabel),
+            _ => None,
+        }
+    }
+}
+
+/// Settings specific to one email source (X-Sense, Blue Riot)
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SourceConfig {
+    pub filter: EmailFilterConfig,
+    pub csv_columns: CsvColumns, // Where CSV attachments keep timestamp/temperature/humidity
+    pub alert_after_days: u32, // Days without emails before a warning, 0 disables it
+    pub after_processing: Vec<PostAction>, // Done label, mark read, archive, trash
+    pub sender: Option<String>, // Address matched by the filter of `gmail setup-filters`
+    pub save_attachments: bool, // Keep the attachments of processed emails under DATA_DIR
+    pub transforms: TransformChain, // Renames, calibrations... applied to the readings before storage
+}
+
+/// Regex filters on subject/sender, e.g. XSENSE_SUBJECT_EXCLUDE
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct EmailFilterConfig {
+    pub subject_include: Option<String>,
+    pub subject_exclude: Option<String>,
+    pub sender_include: Option<String>,
+    pub sender_exclude: Option<String>,
+}
+
+impl SourceConfig {
+    /// Read the settings of a source from `<PREFIX>_*` variables
+    fn from_env(env: &Env, prefix: &str, default_actions: &[PostAction], default_sender: Option<&str>, default_save: bool) -> Self {
+        let var = |name: &str| env.var(&format!("{}_{}", prefix, name))
+            .ok()
+            .filter(|value| !value.is_empty());
+        
+        let defaults = CsvColumns::default();
+        let column = |name: &str, default: CsvColumn| match var(name) {
+            Some(value) => CsvColumn::parse(&value).unwrap_or_else(|| {
+                log::warn!("Invalid {}_{} value '{}' (expected a column number or header name) - using default", 
+                           prefix, name, value);
+                default
+            }),
+            None => default,
+        };
+        
+        SourceConfig {
+            filter: EmailFilterConfig {
+                subject_include: var("SUBJECT_INCLUDE"),
+                subject_exclude: var("SUBJECT_EXCLUDE"),
+                sender_include: var("SENDER_INCLUDE"),
+                sender_exclude: var("SENDER_EXCLUDE"),
+            },
+            csv_columns: CsvColumns {
+                timestamp: column("CSV_TIMESTAMP", defaults.timestamp),
+                temperature: column("CSV_TEMPERATURE", defaults.temperature),
+                humidity: match var("CSV_HUMIDITY") {
+                    Some(value) if value.trim().eq_ignore_ascii_case("none") => None,
+                    Some(value) => CsvColumn::parse(&value).or(defaults.humidity),
+                    None => defaults.humidity,
+                },
+            },
+            alert_after_days: match var("ALERT_AFTER_DAYS") {
+                Some(value) => value.trim().parse().unwrap_or_else(|_| {
+                    log::warn!("Invalid {}_ALERT_AFTER_DAYS value '{}' - alert disabled", prefix, value);
+                    0
+                }),
+                None => 0,
+            },
+            after_processing: match var("AFTER_PROCESSING") {
+                Some(value) => PostAction::parse_list(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid {}_AFTER_PROCESSING value '{}' (expected done_label, mark_read, archive, trash) - using default",
+                               prefix, value);
+                    default_actions.to_vec()
+                }),
+                None => default_actions.to_vec(),
+            },
+            sender: var("SENDER").or_else(|| default_sender.map(str::to_string)),
+            save_attachments: match var("SAVE_ATTACHMENTS") {
+                Some(value) => value.trim().parse().unwrap_or_else(|_| {
+                    log::warn!("Invalid {}_SAVE_ATTACHMENTS value '{}' (expected true or false) - using default", prefix, value);
+                    default_save
+                }),
+                None => default_save,
+            },
+            transforms: var("TRANSFORMS").map(|value| TransformChain::parse(&value)).unwrap_or_default(),
+        }
+    }
+}
+
+/// Configuration file read when present in the working directory and `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = "homemetrics.toml";
+
+/// Configuration file in use, with the variables it set (overridden by the file on reload)
+static CONFIG_FILE: Mutex<Option<(PathBuf, HashSet<String>)>> = Mutex::new(None);
+
+/// Files the configuration is read from: `.env` and the configuration file in use
+///
+/// Watched by the daemon, which reloads its configuration when one of them changes.
+pub fn watched_files() -> Vec<PathBuf> {
+    let mut files = vec![PathBuf::from(".env")];
+    if let Some((path, _)) = CONFIG_FILE.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        files.push(path.clone());
+    }
+    files
+}
+
+/// Values of a TOML configuration file, named after the environment variables they stand for
+///
+/// Tables prefix their keys: `[slack] bot_token` is `SLACK_BOT_TOKEN` and
+/// `[chalet.gmail] token_cache_path` is `CHALET_GMAIL_TOKEN_CACHE_PATH`.
+/// Arrays are joined with commas (`times = ["02:00", "14:00"]`), cron
+/// expressions with ';'.
+pub fn file_variables(content: &str) -> Result<BTreeMap<String, String>> {
+    fn flatten(prefix: &str, table: &toml::Table, variables: &mut BTreeMap<String, String>) -> Result<()> {
+        for (key, value) in table {
+            let name = match prefix {
+                "" => key.to_uppercase().replace('-', "_"),
+                prefix => format!("{}_{}", prefix, key.to_uppercase().replace('-', "_")),
+            };
+            match value {
+                toml::Value::Table(table) => flatten(&name, table, variables)?,
+                toml::Value::Array(items) => {
+                    let items: Vec<String> = items.iter().map(|item| scalar(&name, item)).collect::<Result<_>>()?;
+                    // Cron expressions contain commas
+                    let separator = if name.ends_with("SCHEDULER_CRON") { ";" } else { "," };
+                    variables.insert(name, items.join(separator));
+                }
+                value => {
+                    let value = scalar(&name, value)?;
+                    variables.insert(name, value);
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn scalar(name: &str, value: &toml::Value) -> Result<String> {
+        match value {
+            toml::Value::String(value) => Ok(value.clone()),
+            toml::Value::Integer(value) => Ok(value.to_string()),
+            toml::Value::Float(value) => Ok(value.to_string()),
+            toml::Value::Boolean(value) => Ok(value.to_string()),
+            toml::Value::Datetime(value) => Ok(value.to_string()),
+            _ => anyhow::bail!("Unsupported value for {} (expected a string, number, boolean or list of them)", name),
+        }
+    }
+    
+    let table: toml::Table = content.parse().context("Invalid TOML")?;
+    let mut variables = BTreeMap::new();
+    flatten("", &table, &mut variables)?;
+    Ok(variables)
+}
+
+/// Use a TOML configuration file (`--config`) as defaults of the environment variables
+///
+/// Variables of the environment and `.env` win over the file. Returns how
+/// many values of the file are in use.
+pub fn load_file(path: &Path) -> Result<usize> {
+    apply_file(path, &HashSet::new())
+}
+
+/// `load_file`, where `from_env` lists the variables just set from `.env`
+fn apply_file(path: &Path, from_env: &HashSet<String>) -> Result<usize> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read configuration file {}", path.display()))?;
+    let variables = file_variables(&content)
+        .with_context(|| format!("Invalid configuration file {}", path.display()))?;
+    
+    let mut state = CONFIG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = state.take().map(|(_, names)| names).unwrap_or_default();
+    let mut applied = HashSet::new();
+    for (name, value) in variables {
+        let from_file = previous.contains(&name) && !from_env.contains(&name);
+        if from_file || std::env::var_os(&name).is_none() {
+            std::env::set_var(&name, value);
+            applied.insert(name);
+        }
+    }
+    let count = applied.len();
+    *state = Some((path.to_path_buf(), applied));
+    Ok(count)
+}
+
+/// Environment lookup for one profile: `<PREFIX>_<VAR>`, then `<VAR>`
+#[derive(Debug, Default)]
+struct Env {
+    prefix: Option<String>,
+}
+
+impl Env {
+    fn var(&self, name: &str) -> Result<String, std::env::VarError> {
+        if let Some(prefix) = &self.prefix {
+            if let Ok(value) = std::env::var(format!("{}_{}", prefix, name)) {
+                return Ok(value);
+            }
+        }
+        std::env::var(name)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SlackConfig {
     pub bot_token: String,
-    pub channel_id: String,
+    pub channel_id: String, // Default channel when no route matches
+    pub routes: BTreeMap<String, String>, // "source", "source.severity" or "*.severity" → channel
+    pub mode: SlackMode, // One message per email, or one summary per run
+    pub retry_delay_seconds: u64, // Wait before redelivering a failed message, doubled on each failure
+    pub retry_expiry_hours: u64, // Age after which an undelivered message is given up
 }
 
 impl Config {
     pub fn new() -> Result<Self> {
+        Self::load(&Env::default())
+    }
+    
+    /// Load every profile listed in `PROFILES`, or the single default configuration
+    ///
+    /// Each profile reads `<PROFILE>_<VAR>` first and falls back to `<VAR>`, so
+    /// only what differs between homes (Gmail account, labels, schedule, Slack
+    /// channel, ...) needs to be repeated. The site defaults to the profile name.
+    pub fn load_profiles() -> Result<Vec<Self>> {
+        let names: Vec<String> = std::env::var("PROFILES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        
+        if names.is_empty() {
+            return Ok(vec![Self::new()?]);
+        }
+        
+        names.iter()
+            .map(|name| {
+                let env = Env { prefix: Some(name.to_uppercase().replace('-', "_")) };
+                let mut config = Self::load(&env)
+                    .with_context(|| format!("Invalid configuration for profile '{}'", name))?;
+                config.site.get_or_insert_with(|| name.clone());
+                config.profile = Some(name.clone());
+                Ok(config)
+            })
+            .collect()
+    }
+    
+    /// Re-read the `.env` file, overriding the variables it sets, then the
+    /// configuration file, then load every profile again
+    ///
+    /// Used by the daemon on SIGHUP or when a file changes. Variables removed from the files keep their
+    /// previous value until the next restart.
+    pub fn reload_profiles() -> Result<Vec<Self>> {
+        let mut from_env = HashSet::new();
+        // dotenv only overrides existing variables through its (deprecated) iterator
+        #[allow(deprecated)]
+        if let Ok(items) = dotenv::dotenv_iter() {
+            for item in items {
+                let (name, value) = item.context("Invalid .env file")?;
+                std::env::set_var(&name, value);
+                from_env.insert(name);
+            }
+        }
+        let file = CONFIG_FILE.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|(path, _)| path.clone());
+        if let Some(path) = file {
+            apply_file(&path, &from_env)?;
+        }
+        Self::load_profiles()
+    }
+    
+    fn load(env: &Env) -> Result<Self> {
         // Check that essential variables are defined
-        Self::check_required_env_vars()?;
+        Self::check_required_env_vars(env)?;
+        
+        // A schedule that can't run is refused at startup, not when the daemon adds its jobs
+        let cron = parse_cron(&env.var("SCHEDULER_CRON").unwrap_or_default()).context("Invalid SCHEDULER_CRON")?;
+        
+        let data_dir = env.var("DATA_DIR")
+            .unwrap_or_else(|_| "./data".to_string());
         
         // Configuration loaded from environment variables
-        Ok(Config {
+        let config = Config {
+            profile: None,
             gmail: GmailConfig {
-                credentials_path: std::env::var("GMAIL_CREDENTIALS_PATH")
+                credentials_path: env.var("GMAIL_CREDENTIALS_PATH")
                     .expect("GMAIL_CREDENTIALS_PATH must be defined"),
-                token_cache_path: std::env::var("GMAIL_TOKEN_CACHE_PATH")
+                token_cache_path: env.var("GMAIL_TOKEN_CACHE_PATH")
                     .unwrap_or_else(|_| "./gmail-token-cache.json".to_string()),
+                label_prefix: env.var("GMAIL_LABEL_PREFIX")
+                    .map(|prefix| prefix.trim_matches('/').to_string())
+                    .ok()
+                    .filter(|prefix| !prefix.is_empty())
+                    .unwrap_or_else(|| "homemetrics".to_string()),
+                daily_call_limit: match env.var("GMAIL_DAILY_CALL_LIMIT") {
+                    Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                        log::warn!("Invalid GMAIL_DAILY_CALL_LIMIT value '{}' - quota warning disabled", value);
+                        0
+                    }),
+                    Err(_) => 0,
+                },
+                access: match env.var("GMAIL_SCOPE") {
+                    Ok(value) => GmailAccess::parse(&value).unwrap_or_else(|| {
+                        log::warn!("Invalid GMAIL_SCOPE value '{}' (expected modify or readonly) - using modify", value);
+                        GmailAccess::Modify
+                    }),
+                    Err(_) => GmailAccess::Modify,
+                },
+                label_changes: true,
+                search_since: None,
+                search_until: None,
             },
             database: DatabaseConfig {
-                host: std::env::var("DB_HOST")
+                host: env.var("DB_HOST")
                     .unwrap_or_else(|_| "localhost".to_string()),
-                port: std::env::var("DB_PORT")
+                port: env.var("DB_PORT")
                     .unwrap_or_else(|_| "5432".to_string())
                     .parse()
                     .unwrap_or(5432),
-                database: std::env::var("DB_NAME")
+                database: env.var("DB_NAME")
                     .unwrap_or_else(|_| "homemetrics".to_string()),
-                username: std::env::var("DB_USERNAME")
+                username: env.var("DB_USERNAME")
                     .unwrap_or_else(|_| "postgres".to_string()),
-                password: std::env::var("DB_PASSWORD")
+                password: env.var("DB_PASSWORD")
                     .expect("DB_PASSWORD must be defined"),
+                layout: match env.var("STORAGE_LAYOUT") {
+                    Ok(value) => StorageLayout::parse(&value).unwrap_or_else(|| {
+                        log::warn!("Invalid STORAGE_LAYOUT value '{}' (expected domain or generic) - using domain", value);
+                        StorageLayout::Domain
+                    }),
+                    Err(_) => StorageLayout::Domain,
+                },
+                sensor_partitions: match env.var("DB_SENSOR_PARTITIONS") {
+                    Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                        log::warn!("Invalid DB_SENSOR_PARTITIONS value '{}' (expected a number of partitions) - partitioning disabled", value);
+                        0
+                    }),
+                    Err(_) => 0,
+                },
+            },
+            data_dir: data_dir.clone(),
+            data_dir_layout: match env.var("DATA_DIR_LAYOUT") {
+                Ok(value) => ArchiveLayout::parse(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid DATA_DIR_LAYOUT value '{}' (placeholders: {{source}}, {{year}}, {{month}}, {{day}}) - using default", value);
+                    ArchiveLayout::default()
+                }),
+                Err(_) => ArchiveLayout::default(),
             },
-            data_dir: std::env::var("DATA_DIR")
-                .unwrap_or_else(|_| "./data".to_string()),
+            site: env.var("SITE")
+                .ok()
+                .map(|site| site.trim().to_string())
+                .filter(|site| !site.is_empty()),
             scheduler: SchedulerConfig {
-                enabled: std::env::var("SCHEDULER_ENABLED")
+                enabled: env.var("SCHEDULER_ENABLED")
                     .unwrap_or_else(|_| "false".to_string())
                     .parse()
                     .unwrap_or(false),
-                schedule_times: std::env::var("SCHEDULER_TIMES")
-                    .unwrap_or_else(|_| "02:00".to_string())
-                    .split(',')
-                    .map(|s| s.trim().to_string())
+                // The 02:00 default only applies without SCHEDULER_CRON
+                schedule_times: parse_times(&env.var("SCHEDULER_TIMES")
+                    .unwrap_or_else(|_| if cron.is_empty() { "02:00" } else { "" }.to_string())),
+                cron,
+                source_times: EmailSource::ALL.iter()
+                    .filter_map(|source| {
+                        let times = env.var(&format!("{}_SCHEDULER_TIMES", source.key().to_uppercase())).ok()?;
+                        Some((*source, parse_times(&times))).filter(|(_, times)| !times.is_empty())
+                    })
                     .collect(),
+                retry_attempts: env.var("SCHEDULER_RETRY_ATTEMPTS")
+                    .unwrap_or_else(|_| "3".to_string())
+                    .parse()
+                    .unwrap_or(3),
+                retry_delay_seconds: env.var("SCHEDULER_RETRY_DELAY_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+                run_on_start: env.var("SCHEDULER_RUN_ON_START")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .unwrap_or(true),
+                jitter: match env.var("SCHEDULER_JITTER") {
+                    Ok(value) => humantime::parse_duration(value.trim()).map(Some).unwrap_or_else(|_| {
+                        log::warn!("Invalid SCHEDULER_JITTER value '{}' (expected a duration such as 5m) - jitter disabled", value);
+                        None
+                    }),
+                    Err(_) => None,
+                }.filter(|jitter| !jitter.is_zero()),
+                catch_up: env.var("SCHEDULER_CATCH_UP")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .unwrap_or(true),
+                control_socket: match env.var("CONTROL_SOCKET") {
+                    Ok(value) if matches!(value.trim().to_lowercase().as_str(), "" | "off" | "none") => None,
+                    Ok(value) => Some(value.trim().to_string()),
+                    Err(_) => Some(format!("{}/homemetrics.sock", data_dir.trim_end_matches('/'))),
+                },
             },
-            slack: match (std::env::var("SLACK_BOT_TOKEN"), std::env::var("SLACK_CHANNEL_ID")) {
+            slack: match (env.var("SLACK_BOT_TOKEN"), env.var("SLACK_CHANNEL_ID")) {
                 (Ok(bot_token), Ok(channel_id)) => Some(SlackConfig {
                     bot_token,
                     channel_id,
+                    routes: env.var("SLACK_ROUTES")
+                        .map(|routes| parse_routes(&routes))
+                        .unwrap_or_default(),
+                    mode: match env.var("SLACK_MODE") {
+                        Ok(value) => SlackMode::parse(&value).unwrap_or_else(|| {
+                            log::warn!("Invalid SLACK_MODE value '{}' (expected email or run) - using email", value);
+                            SlackMode::Email
+                        }),
+                        Err(_) => SlackMode::Email,
+                    },
+                    retry_delay_seconds: env.var("SLACK_RETRY_DELAY_SECONDS")
+                        .unwrap_or_else(|_| "30".to_string())
+                        .parse()
+                        .unwrap_or(30),
+                    retry_expiry_hours: env.var("SLACK_RETRY_EXPIRY_HOURS")
+                        .unwrap_or_else(|_| "24".to_string())
+                        .parse()
+                        .unwrap_or(24),
                 }),
                 _ => {
                     log::warn!("SLACK_BOT_TOKEN or SLACK_CHANNEL_ID not defined - Slack notifications disabled");
                     None
                 }
             },
-        })
+            notification_templates_dir: env.var("NOTIFICATION_TEMPLATES_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty()),
+            server: ServerConfig {
+                enabled: env.var("HTTP_SERVER_ENABLED")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse()
+                    .unwrap_or(false),
+                bind_address: env.var("HTTP_BIND_ADDRESS")
+                    .unwrap_or_else(|_| "0.0.0.0:8080".to_string()),
+                ingest_token: env.var("HTTP_INGEST_TOKEN")
+                    .ok()
+                    .filter(|token| !token.is_empty()),
+                cache_ttl_secs: env.var("HTTP_CACHE_TTL")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10),
+            },
+            receiver: ReceiverConfig {
+                drop_dir: env.var("RECEIVER_DROP_DIR")
+                    .ok()
+                    .filter(|dir| !dir.is_empty()),
+                poll_interval_secs: env.var("RECEIVER_POLL_INTERVAL")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+            },
+            processing_order: match env.var("PROCESSING_ORDER") {
+                Ok(value) => ProcessingOrder::parse(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid PROCESSING_ORDER value '{}' (expected oldest or newest) - using oldest", value);
+                    ProcessingOrder::Oldest
+                }),
+                Err(_) => ProcessingOrder::Oldest,
+            },
+            batch_size: match env.var("BATCH_SIZE") {
+                Ok(value) => value.trim().parse().ok().filter(|size| *size > 0).or_else(|| {
+                    if value.trim() != "0" {
+                        log::warn!("Invalid BATCH_SIZE value '{}' - no batch limit", value);
+                    }
+                    None
+                }),
+                Err(_) => None,
+            },
+            email_timeout: match env.var("EMAIL_TIMEOUT_SECONDS") {
+                Ok(value) => match value.trim().parse::<u64>() {
+                    Ok(0) => None,
+                    Ok(seconds) => Some(Duration::from_secs(seconds)),
+                    Err(_) => {
+                        log::warn!("Invalid EMAIL_TIMEOUT_SECONDS value '{}' - using 300", value);
+                        Some(Duration::from_secs(300))
+                    }
+                },
+                Err(_) => Some(Duration::from_secs(300)),
+            },
+            pipeline: PipelineConfig {
+                sources: match env.var("PIPELINE_SOURCES") {
+                    Ok(value) => EmailSource::parse_list(&value).unwrap_or_else(|| {
+                        log::warn!("Invalid PIPELINE_SOURCES value '{}' (expected xsense and/or blueriot) - using both", value);
+                        EmailSource::ALL.to_vec()
+                    }),
+                    Err(_) => EmailSource::ALL.to_vec(),
+                },
+                mode: match env.var("PIPELINE_MODE") {
+                    Ok(value) => PipelineMode::parse(&value).unwrap_or_else(|| {
+                        log::warn!("Invalid PIPELINE_MODE value '{}' (expected concurrent or sequential) - using concurrent", value);
+                        PipelineMode::Concurrent
+                    }),
+                    Err(_) => PipelineMode::Concurrent,
+                },
+            },
+            retry: RetryConfig {
+                base_delay_minutes: env.var("RETRY_BASE_DELAY_MINUTES")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                max_delay_hours: env.var("RETRY_MAX_DELAY_HOURS")
+                    .unwrap_or_else(|_| "72".to_string())
+                    .parse()
+                    .unwrap_or(72),
+                max_attempts: env.var("RETRY_MAX_ATTEMPTS")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+            },
+            cleanup: CleanupConfig {
+                after_days: match env.var("CLEANUP_AFTER_DAYS") {
+                    Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                        log::warn!("Invalid CLEANUP_AFTER_DAYS value '{}' - clean-up disabled", value);
+                        0
+                    }),
+                    Err(_) => 0,
+                },
+                action: match env.var("CLEANUP_ACTION") {
+                    Ok(value) => CleanupAction::parse(&value).unwrap_or_else(|| {
+                        log::warn!("Invalid CLEANUP_ACTION value '{}' (expected trash or unlabel) - using trash", value);
+                        CleanupAction::Trash
+                    }),
+                    Err(_) => CleanupAction::Trash,
+                },
+            },
+            forecast: ForecastConfig {
+                time: env.var("FORECAST_TIME")
+                    .ok()
+                    .map(|time| time.trim().to_string())
+                    .filter(|time| !time.is_empty()),
+                lookback_days: match env.var("FORECAST_LOOKBACK_DAYS") {
+                    Ok(value) => value.trim().parse().ok().filter(|days| *days >= 3).unwrap_or_else(|| {
+                        log::warn!("Invalid FORECAST_LOOKBACK_DAYS value '{}' (at least 3) - using 14", value);
+                        14
+                    }),
+                    Err(_) => 14,
+                },
+                freeze_threshold: match env.var("FORECAST_FREEZE_THRESHOLD") {
+                    Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                        log::warn!("Invalid FORECAST_FREEZE_THRESHOLD value '{}' - using 3 °C", value);
+                        3.0
+                    }),
+                    Err(_) => 3.0,
+                },
+            },
+            alerts: AlertConfig {
+                rules: match env.var("ALERT_RULES") {
+                    Ok(rules) => parse_rules(&rules, &env.var("ALERT_WEBHOOKS").unwrap_or_default()),
+                    Err(_) => Vec::new(),
+                },
+                humidity_rules: match env.var("HUMIDITY_ALERT_RULES") {
+                    Ok(rules) => parse_humidity_rules(&rules),
+                    Err(_) => Vec::new(),
+                },
+                webhook_retries: match env.var("ALERT_WEBHOOK_RETRIES") {
+                    Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                        log::warn!("Invalid ALERT_WEBHOOK_RETRIES value '{}' - using 3", value);
+                        3
+                    }),
+                    Err(_) => 3,
+                },
+            },
+            health: HealthConfig {
+                sensors: match env.var("HEALTH_SENSORS") {
+                    Ok(sensors) => {
+                        let default_max_age = match env.var("HEALTH_DEFAULT_MAX_AGE") {
+                            Ok(value) => humantime::parse_duration(value.trim()).unwrap_or_else(|_| {
+                                log::warn!("Invalid HEALTH_DEFAULT_MAX_AGE value '{}' - using 26h", value);
+                                Duration::from_secs(26 * 3600)
+                            }),
+                            Err(_) => Duration::from_secs(26 * 3600),
+                        };
+                        parse_freshness_rules(&sensors, default_max_age)
+                    }
+                    Err(_) => Vec::new(),
+                },
+            },
+            hooks: HooksConfig {
+                on_run_complete: env.var("HOOK_ON_RUN_COMPLETE")
+                    .ok()
+                    .map(|command| command.trim().to_string())
+                    .filter(|command| !command.is_empty()),
+                on_alert: env.var("HOOK_ON_ALERT")
+                    .ok()
+                    .map(|command| command.trim().to_string())
+                    .filter(|command| !command.is_empty()),
+                on_error: env.var("HOOK_ON_ERROR")
+                    .ok()
+                    .map(|command| command.trim().to_string())
+                    .filter(|command| !command.is_empty()),
+                timeout: match env.var("HOOK_TIMEOUT_SECS") {
+                    Ok(value) => value.trim().parse().map(Duration::from_secs).unwrap_or_else(|_| {
+                        log::warn!("Invalid HOOK_TIMEOUT_SECS value '{}' - using 30", value);
+                        Duration::from_secs(30)
+                    }),
+                    Err(_) => Duration::from_secs(30),
+                },
+            },
+            mold_alert_after_hours: match env.var("MOLD_ALERT_AFTER_HOURS") {
+                Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                    log::warn!("Invalid MOLD_ALERT_AFTER_HOURS value '{}' - alert disabled", value);
+                    0
+                }),
+                Err(_) => 0,
+            },
+            xsense: SourceConfig::from_env(env, "XSENSE", &[PostAction::DoneLabel], Some("support@x-sense.com"), true),
+            blueriot: SourceConfig::from_env(env, "BLUERIOT", &[PostAction::DoneLabel, PostAction::MarkRead, PostAction::Archive], None, false),
+            pools: env.var("BLUERIOT_POOLS")
+                .map(|pools| parse_pools(&pools))
+                .unwrap_or_default(),
+            units: match env.var("UNITS") {
+                Ok(value) => UnitSystem::parse(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid UNITS value '{}' (expected metric or imperial) - using metric", value);
+                    UnitSystem::Metric
+                }),
+                Err(_) => UnitSystem::Metric,
+            },
+            locale: match env.var("LOCALE") {
+                Ok(value) => Locale::parse(&value).unwrap_or_else(|| {
+                    log::warn!("Unsupported LOCALE value '{}' (expected en or fr) - using en", value);
+                    Locale::En
+                }),
+                Err(_) => Locale::En,
+            },
+        };
+        
+        // Masked in logs, error messages and Slack notifications from now on
+        crate::redact::register_secrets(config.secrets());
+        Ok(config)
+    }
+    
+    /// Configuration of a source's processor, whose `site:` transform replaces SITE
+    pub fn for_source(&self, source: EmailSource) -> Config {
+        let transforms = match source {
+            EmailSource::XSense => &self.xsense.transforms,
+            EmailSource::BlueRiot => &self.blueriot.transforms,
+        };
+        let mut config = self.clone();
+        if let Some(site) = transforms.site() {
+            config.site = Some(site.to_string());
+        }
+        config
     }
     
-    fn check_required_env_vars() -> Result<()> {
+    /// Passwords, tokens and webhook URLs of this configuration
+    pub fn secrets(&self) -> Vec<&str> {
+        let mut secrets = vec![self.database.password.as_str()];
+        secrets.extend(self.slack.as_ref().map(|slack| slack.bot_token.as_str()));
+        secrets.extend(self.server.ingest_token.as_deref());
+        // Slack and Home Assistant webhook URLs are enough to call them
+        secrets.extend(self.alerts.rules.iter().filter_map(|rule| rule.webhook.as_deref()));
+        secrets
+    }
+    
+    fn check_required_env_vars(env: &Env) -> Result<()> {
         let required_vars = [
             "GMAIL_CREDENTIALS_PATH",
         ];
@@ -98,7 +933,7 @@ impl Config {
         let mut missing_vars = Vec::new();
         
         for var in &required_vars {
-            if std::env::var(var).is_err() {
+            if env.var(var).is_err() {
                 missing_vars.push(*var);
             }
         }
@@ -115,7 +950,7 @@ impl Config {
                  2. Or set variables manually:\n\
                     export GMAIL_CREDENTIALS_PATH=/path/to/client_credentials.json\n\
                     export GMAIL_TOKEN_CACHE_PATH=./gmail-token-cache.json\n\
-                    cargo run -- --dry-run\n\
+                    cargo run -- fetch --dry-run\n\
                  \n\
                  3. See GMAIL_API_MIGRATION.md for more information",
                 missing_vars.join(", ")
@@ -124,4 +959,88 @@ impl Config {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduled_runs() {
+        let scheduler = SchedulerConfig {
+            enabled: true,
+            schedule_times: vec!["02:00".to_string(), "14:00".to_string()],
+            source_times: vec![(EmailSource::BlueRiot, vec!["*:00".to_string(), "14:00".to_string()])],
+            cron: vec!["0 */4 * * 1-5".to_string()],
+            retry_attempts: 3,
+            retry_delay_seconds: 60,
+            run_on_start: true,
+            jitter: None,
+            catch_up: true,
+            control_socket: None,
+        };
+        assert_eq!(scheduler.times_for(EmailSource::XSense), ["02:00", "14:00"]);
+        assert_eq!(scheduler.runs(&[EmailSource::XSense, EmailSource::BlueRiot]), vec![
+            ("02:00".to_string(), vec![EmailSource::XSense]),
+            ("14:00".to_string(), vec![EmailSource::XSense, EmailSource::BlueRiot]),
+            ("0 */4 * * 1-5".to_string(), vec![EmailSource::XSense]),
+            ("*:00".to_string(), vec![EmailSource::BlueRiot]),
+        ]);
+        assert_eq!(scheduler.runs(&[EmailSource::XSense]).len(), 3);
+
+        assert_eq!(parse_cron("0 */4 * * *; 30 8 * * 1-5").unwrap(), ["0 */4 * * *", "30 8 * * 1-5"]);
+        assert_eq!(normalize_cron("0 */4 * * *").unwrap(), "0 0 */4 * * *");
+        assert_eq!(normalize_cron("30 8 * * 1-5,0").unwrap(), "0 30 8 * * MON-FRI,SUN");
+        assert!(parse_cron("0 25 * * *").is_err());
+        assert!(parse_cron("every 4 hours").is_err());
+    }
+    
+    #[test]
+    fn test_secrets() {
+        // Prefixed like a profile, so no other test reads these variables
+        for (name, value) in [
+            ("SECRETSTEST_GMAIL_CREDENTIALS_PATH", "credentials.json"),
+            ("SECRETSTEST_DB_PASSWORD", "pg-secret-41"),
+            ("SECRETSTEST_ALERT_RULES", "cabane_gel:cabane<2"),
+            ("SECRETSTEST_ALERT_WEBHOOKS", "cabane_gel=http://ha.local:8123/api/webhook/chauffage-7f3e"),
+        ] {
+            std::env::set_var(name, value);
+        }
+        let config = Config::load(&Env { prefix: Some("SECRETSTEST".to_string()) }).unwrap();
+        let secrets = config.secrets();
+        assert!(secrets.contains(&"pg-secret-41"));
+        assert!(secrets.contains(&"http://ha.local:8123/api/webhook/chauffage-7f3e"));
+
+        let rendered = crate::debug_bundle::render_config(&config);
+        assert!(!rendered.contains("pg-secret-41") && !rendered.contains("chauffage-7f3e"));
+    }
+    
+    #[test]
+    fn test_file_variables() {
+        let content = r#"
+            profiles = ["maison", "chalet"]
+            data-dir = "./data"
+
+            [scheduler]
+            enabled = true
+            times = ["02:00", "14:00"]
+            cron = ["0 */4 * * 1-5", "0 9 * * 0,6"]
+            retry_attempts = 3
+
+            [chalet.gmail]
+            token_cache_path = "chalet-token.json"
+        "#;
+        let variables = file_variables(content).unwrap();
+        assert_eq!(variables["PROFILES"], "maison,chalet");
+        assert_eq!(variables["DATA_DIR"], "./data");
+        assert_eq!(variables["SCHEDULER_ENABLED"], "true");
+        assert_eq!(variables["SCHEDULER_TIMES"], "02:00,14:00");
+        assert_eq!(variables["SCHEDULER_CRON"], "0 */4 * * 1-5;0 9 * * 0,6");
+        assert_eq!(variables["SCHEDULER_RETRY_ATTEMPTS"], "3");
+        assert_eq!(variables["CHALET_GMAIL_TOKEN_CACHE_PATH"], "chalet-token.json");
+        assert_eq!(variables.len(), 7);
+
+        assert!(file_variables("[slack]\nroutes = [{ alert = \"C1\" }]").is_err());
+        assert!(file_variables("slack = ").is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::auth;
+use crate::config::Config;
+use crate::email::RunSummary;
+use crate::i18n::Locale;
+use crate::pipeline::EmailSource;
+use crate::redact::redact;
+use crate::scheduler;
+
+/// When the daemon started, set by the first `set_profiles`
+static STARTED_AT: OnceLock<DateTime<Utc>> = OnceLock::new();
+
+/// Profiles the daemon runs, replaced on each configuration reload
+static PROFILES: Mutex<Vec<Config>> = Mutex::new(Vec::new());
+
+/// Last result of each profile and source
+static LAST_RUNS: Mutex<Vec<LastRun>> = Mutex::new(Vec::new());
+
+/// Result of the last run of a source in the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastRun {
+    pub profile: String,
+    /// "xsense" or "blueriot"
+    pub source: String,
+    /// "start", "scheduled" or "catch-up"
+    pub trigger: String,
+    pub finished_at: DateTime<Utc>,
+    pub duration_secs: u64,
+    pub processed: usize,
+    pub failed: usize,
+    pub records: usize,
+    /// Emails left for the next run by a budget
+    pub deferred: usize,
+    /// Error that stopped the whole run, redacted
+    pub error: Option<String>,
+}
+
+/// Next run of a daemon job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NextRun {
+    pub profile: String,
+    /// "fetch" or "forecast"
+    pub job: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Gmail OAuth2 token cached by a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenStatus {
+    pub profile: String,
+    /// Last write of GMAIL_TOKEN_CACHE_PATH, that is the last refresh
+    pub refreshed_at: Option<DateTime<Utc>>,
+    /// Expiry of the cached access token
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What `homemetrics status` gets from a running daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub version: String,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub now: DateTime<Utc>,
+    pub last_runs: Vec<LastRun>,
+    pub next_runs: Vec<NextRun>,
+    pub tokens: Vec<TokenStatus>,
+}
+
+impl DaemonStatus {
+    /// Keep the entries of one profile (`status --profile`)
+    pub fn retain_profile(&mut self, profile: &str) {
+        self.last_runs.retain(|run| run.profile == profile);
+        self.next_runs.retain(|run| run.profile == profile);
+        self.tokens.retain(|token| token.profile == profile);
+    }
+}
+
+fn profile_name(config: &Config) -> String {
+    config.profile.clone().unwrap_or_else(|| "default".to_string())
+}
+
+/// Profiles whose status is served, at startup and after each reload
+pub fn set_profiles(profiles: &[Config]) {
+    STARTED_AT.get_or_init(Utc::now);
+    *PROFILES.lock().unwrap_or_else(|e| e.into_inner()) = profiles.to_vec();
+}
+
+/// Keep the result of a run of `config`'s sources, replacing the previous one of each source
+pub fn record_run(config: &Config, trigger: &str, finished_at: DateTime<Utc>, duration: Duration, result: &Result<Vec<RunSummary>>) {
+    let runs = last_runs(&profile_name(config), &config.pipeline.sources, trigger, finished_at, duration, result);
+    let mut last = LAST_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    last.retain(|run| !runs.iter().any(|new| new.profile == run.profile && new.source == run.source));
+    last.extend(runs);
+    last.sort_by(|a, b| (&a.profile, &a.source).cmp(&(&b.profile, &b.source)));
+}
+
+/// One entry per summary, or per source of a run that failed as a whole
+fn last_runs(profile: &str, sources: &[EmailSource], trigger: &str, finished_at: DateTime<Utc>, duration: Duration,
+             result: &Result<Vec<RunSummary>>) -> Vec<LastRun> {
+    let run = |source: &str| LastRun {
+        profile: profile.to_string(),
+        source: source.to_string(),
+        trigger: trigger.to_string(),
+        finished_at,
+        duration_secs: duration.as_secs(),
+        processed: 0,
+        failed: 0,
+        records: 0,
+        deferred: 0,
+        error: None,
+    };
+    match result {
+        Ok(summaries) => summaries.iter().map(|summary| {
+            let source = EmailSource::ALL.iter()
+                .find(|source| source.processor_name() == summary.processor)
+                .map_or(summary.processor.as_str(), |source| source.key());
+            LastRun {
+                duration_secs: summary.duration.as_secs(),
+                processed: summary.processed_count(),
+                failed: summary.failed_count(),
+                records: summary.total_records(),
+                deferred: summary.deferred,
+                ..run(source)
+            }
+        }).collect(),
+        Err(e) => sources.iter().map(|source| LastRun {
+            error: Some(redact(&format!("{:#}", e)).into_owned()),
+            ..run(source.key())
+        }).collect(),
+    }
+}
+
+/// Status of the daemon now: last runs, next runs in UTC like the scheduler, cached tokens
+pub fn status(now: DateTime<Utc>) -> DaemonStatus {
+    let profiles = PROFILES.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let mut next_runs = Vec::new();
+    let mut tokens = Vec::new();
+    for config in &profiles {
+        next_runs.extend(scheduler::upcoming_runs(config, now.naive_utc()).into_iter().map(|run| NextRun {
+            profile: profile_name(config),
+            job: run.job.to_string(),
+            at: run.at.and_utc(),
+        }));
+
+        let path = Path::new(&config.gmail.token_cache_path);
+        let refreshed_at = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok().map(DateTime::<Utc>::from);
+        let expires_at = auth::read_token_cache(path).ok()
+            .and_then(|tokens| tokens.into_iter().filter_map(|token| token.expires_at).max());
+        tokens.push(TokenStatus { profile: profile_name(config), refreshed_at, expires_at });
+    }
+    next_runs.sort_by_key(|run| run.at);
+
+    DaemonStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pid: std::process::id(),
+        started_at: *STARTED_AT.get_or_init(Utc::now),
+        now,
+        last_runs: LAST_RUNS.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        next_runs,
+        tokens,
+    }
+}
+
+/// Answer each connection to the control socket with the status as JSON (`CONTROL_SOCKET`)
+///
+/// A socket file left by a daemon that did not stop cleanly is replaced; one
+/// that still answers belongs to another daemon, and is left alone.
+pub async fn serve(path: &Path) -> Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            anyhow::bail!("Control socket {} is used by another daemon", path.display());
+        }
+        std::fs::remove_file(path).with_context(|| format!("Unable to remove stale control socket {}", path.display()))?;
+    }
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Unable to create {}", parent.display()))?;
+    }
+    let listener = UnixListener::bind(path).with_context(|| format!("Unable to listen on control socket {}", path.display()))?;
+    // Owner only: the status names profiles, sources and errors
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    info!("🎛️  Control socket listening on {} (homemetrics status)", path.display());
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let document = serde_json::to_vec(&status(Utc::now()))?;
+        tokio::spawn(async move {
+            if let Err(e) = stream.write_all(&document).await {
+                debug!("Control socket client left early: {}", e);
+            }
+        });
+    }
+}
+
+/// Status of the daemon listening on `path`
+pub async fn query(path: &Path) -> Result<DaemonStatus> {
+    let mut stream = match UnixStream::connect(path).await {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused) => {
+            anyhow::bail!("No daemon answering on {} (is `homemetrics daemon` running with the same configuration?)", path.display())
+        }
+        Err(e) => return Err(e).with_context(|| format!("Unable to connect to control socket {}", path.display())),
+    };
+    let mut document = Vec::new();
+    stream.read_to_end(&mut document).await.context("Unable to read the daemon status")?;
+    serde_json::from_slice(&document).context("Invalid daemon status")
+}
+
+pub fn render_status(status: &DaemonStatus, locale: Locale) -> String {
+    let format = |at: DateTime<Utc>| at.format("%Y-%m-%d %H:%M UTC").to_string();
+    let age = |at: DateTime<Utc>| humantime::format_duration(Duration::from_secs((status.now - at).num_seconds().max(0) as u64)).to_string();
+    let mut lines = vec![locale.status_header(&format(status.started_at), &age(status.started_at), status.pid, &status.version)];
+
+    lines.push(locale.status_runs_title().to_string());
+    if status.last_runs.is_empty() {
+        lines.push(format!("  {}", locale.status_no_runs()));
+    }
+    for run in &status.last_runs {
+        let outcome = match &run.error {
+            Some(error) => format!("❌ {}", error),
+            None => locale.status_run_counts(run.processed, run.failed, run.records, run.deferred),
+        };
+        lines.push(locale.status_run_line(&run.profile, &run.source, &format(run.finished_at), &run.trigger, &outcome));
+    }
+
+    lines.push(locale.status_next_title().to_string());
+    if status.next_runs.is_empty() {
+        lines.push(format!("  {}", locale.tui_no_schedule()));
+    }
+    for run in &status.next_runs {
+        lines.push(format!("  [{}] {} {}", run.profile, run.job, format(run.at)));
+    }
+
+    lines.push(locale.status_token_title().to_string());
+    for token in &status.tokens {
+        lines.push(locale.status_token_line(&token.profile, token.refreshed_at.map(age).as_deref(),
+                                            token.expires_at.map(format).as_deref()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_status() {
+        let at = |hour| Utc.with_ymd_and_hms(2025, 3, 10, hour, 0, 0).unwrap();
+        let mut summary = RunSummary::new("X-Sense");
+        summary.deferred = 2;
+        let mut runs = last_runs("default", &[EmailSource::XSense], "scheduled", at(12), Duration::from_secs(5), &Ok(vec![summary]));
+        let error = anyhow::anyhow!("Unable to connect to postgres://homemetrics:s3cr3t@db/metrics");
+        runs.extend(last_runs("default", &[EmailSource::BlueRiot], "catch-up", at(13), Duration::ZERO, &Err(error)));
+        assert_eq!(runs[0].source, "xsense");
+
+        let mut status = DaemonStatus {
+            version: "0.1.0".to_string(),
+            pid: 42,
+            started_at: at(8),
+            now: at(14),
+            last_runs: runs,
+            next_runs: vec![NextRun { profile: "default".to_string(), job: "fetch".to_string(), at: at(18) }],
+            tokens: vec![TokenStatus { profile: "default".to_string(), refreshed_at: Some(at(13)), expires_at: Some(at(15)) }],
+        };
+        assert_eq!(render_status(&status, Locale::En), "\
+🟢 Daemon running since 2025-03-10 08:00 UTC (6h), pid 42, homemetrics 0.1.0
+Last runs:
+  [default] xsense at 2025-03-10 12:00 UTC (scheduled): 0 processed, 0 failed, 0 record(s), 2 deferred
+  [default] blueriot at 2025-03-10 13:00 UTC (catch-up): ❌ Unable to connect to postgres://homemetrics:***@db/metrics
+Next runs:
+  [default] fetch 2025-03-10 18:00 UTC
+Gmail token:
+  [default] refreshed 1h ago, access token valid until 2025-03-10 15:00 UTC");
+
+        status.retain_profile("chalet");
+        assert!(status.last_runs.is_empty() && status.next_runs.is_empty() && status.tokens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_control_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("homemetrics.sock");
+        std::fs::write(&path, "stale").unwrap();
+
+        let server_path = path.clone();
+        tokio::spawn(async move { serve(&server_path).await });
+        let mut status = None;
+        for _ in 0..50 {
+            if let Ok(found) = query(&path).await {
+                status = Some(found);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(status.map(|status| status.pid), Some(std::process::id()));
+        assert!(serve(&path).await.is_err());
+        assert!(query(&dir.path().join("missing.sock")).await.is_err());
+    }
+}
@@ -1,43 +1,215 @@
 use anyhow::{Result, Context};
 use log::{info, debug, warn};
 use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::config::DatabaseConfig;
+use crate::email::common::{pool_metrics, temperature_metrics};
+use crate::email::SensorCounts;
 use crate::xsense::TemperatureReading;
 use crate::blueriot::PoolReading;
+use crate::mold::{mold_risk, RISK_THRESHOLD};
+use crate::validation::RejectedReading;
+use crate::trace::parse_hash;
+use crate::storage::{self, watermarks};
+use crate::storage::{activity_from_row, api_usage_from_row, bucket_from_row, pool_from_row, sensor_from_row, PoolRow, SensorInfo, SensorRow, delta_checks, Bucket, BucketRow, ReadingBucket, reading_page, ReadingPage, ReadingRange, ReadingRow, retry_from_row, ActivityRow, ApiUsage, BatchCheckpoint, ApiUsageRow, DeltaCheck, EmailRetry, RetryRow, FailedExtraction, notification_from_row, NotificationRecord, NotificationRow, DeleteScope, DeletedReadings, provenance_from_row, ProvenanceRecord, ProvenanceRow, SpanRow, SensorType, SourceActivity, Storage, StorageError, StorageFuture, StorageLayout};
 
+/// Version of the schema created by this binary, recorded in `schema_migrations`
+///
+/// Bump it whenever `create_tables_if_not_exists` adds a table, a column or an index.
+pub const SCHEMA_VERSION: i64 = 6;
+
+/// Database schema other than the one this binary was built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    /// Version recorded in the database, None for a schema created before versioning
+    pub found: Option<i64>,
+}
+
+impl std::fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.found {
+            Some(found) if found > SCHEMA_VERSION => write!(f,
+                "Database schema version {} is newer than the one of this binary ({}): upgrade homemetrics",
+                found, SCHEMA_VERSION),
+            Some(found) => write!(f,
+                "Database schema version {} is older than the one of this binary ({}): run `homemetrics db migrate`",
+                found, SCHEMA_VERSION),
+            None => write!(f,
+                "Database schema has no version (created by an older homemetrics): run `homemetrics db migrate`"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+#[derive(Clone)]
 pub struct Database {
     pool: PgPool,
+    site: Option<String>, // Stamped on every sensor and reading written
+    layout: StorageLayout,
+    address: String, // host:port/database, scopes the in-process batch marks
+    timescaledb: bool, // Extension installed: buckets use time_bucket()
+    replace_existing: bool, // Backfill: readings already stored are rewritten instead of skipped
+    skip_history: bool, // Scheduled Gmail run: readings before the boundary window are assumed stored
+}
+
+/// Result of saving a batch of readings
+#[derive(Debug, Clone, Default)]
+pub struct SaveStats {
+    pub inserted: usize,
+    pub duplicates: usize,
+    pub per_sensor: BTreeMap<String, SensorCounts>,
+}
+
+impl SaveStats {
+    /// Total readings handled (new + already present)
+    pub fn total(&self) -> usize {
+        self.inserted + self.duplicates
+    }
 }
 
 impl Database {
+    /// Connect, creating the tables of a fresh database
+    ///
+    /// Fails with `SchemaMismatch` when the schema version differs from
+    /// `SCHEMA_VERSION`, so an upgraded binary never writes into outdated tables.
     pub async fn new(config: &DatabaseConfig) -> Result<Self> {
-        info!("Connecting to TimescaleDB database");
+        let mut db = Self::connect(config).await?;
+        let fresh = db.check_schema_version().await?;
         
-        let database_url = format!(
-            "postgres://{}:{}@{}:{}/{}",
-            config.username, config.password, config.host, config.port, config.database
-        );
+        // Create tables if they don't exist
+        db.create_tables_if_not_exists(config.sensor_partitions).await?;
+        if fresh {
+            db.record_schema_version().await?;
+        }
+        db.timescaledb = db.has_timescaledb().await;
+        
+        Ok(db)
+    }
+    
+    /// Bring the schema up to `SCHEMA_VERSION` (`homemetrics db migrate`)
+    ///
+    /// Creates the missing tables, columns and indexes, then records the
+    /// version. Returns the version found before, None before versioning.
+    pub async fn migrate(config: &DatabaseConfig) -> Result<Option<i64>> {
+        let db = Self::connect(config).await?;
+        let found = db.schema_version().await?;
+        if found.is_some_and(|found| found > SCHEMA_VERSION) {
+            return Err(SchemaMismatch { found }.into());
+        }
         
-        let pool = PgPool::connect(&database_url)
+        db.create_tables_if_not_exists(config.sensor_partitions).await?;
+        db.record_schema_version().await?;
+        Ok(found)
+    }
+    
+    async fn connect(config: &DatabaseConfig) -> Result<Self> {
+        info!("Connecting to TimescaleDB database");
+        
+        let pool = PgPool::connect(&Self::database_url(config))
             .await
-            .context("Impossible de se connecter à la base de données")?;
+            .map_err(StorageError::Unavailable)?;
         
         info!("Database connection established");
+        Ok(Database { pool, site: None, layout: config.layout, address: Self::address(config), timescaledb: false, replace_existing: false, skip_history: false })
+    }
+    
+    /// Connect without creating tables, for read-only comparisons in dry-run mode
+    pub async fn connect_read_only(config: &DatabaseConfig) -> Result<Self> {
+        info!("Connecting to TimescaleDB database (read-only)");
         
-        let db = Database { pool };
-        
-        // Create tables if they don't exist
-        db.create_tables_if_not_exists().await?;
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(5))
+            .connect(&Self::database_url(config))
+            .await
+            .map_err(StorageError::Unavailable)?;
         
+        let mut db = Database { pool, site: None, layout: config.layout, address: Self::address(config), timescaledb: false, replace_existing: false, skip_history: false };
+        db.check_schema_version().await?;
+        db.timescaledb = db.has_timescaledb().await;
         Ok(db)
     }
     
-    async fn create_tables_if_not_exists(&self) -> Result<()> {
+    /// Schema version recorded in `schema_migrations`, None before versioning
+    async fn schema_version(&self) -> Result<Option<i64>> {
+        if !self.table_exists("schema_migrations").await? {
+            return Ok(None);
+        }
+        sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM schema_migrations")
+            .fetch_one(&self.pool)
+            .await
+            .context("Unable to read the schema version")
+    }
+    
+    /// Fail unless the schema is the one of this binary; true for a fresh database (no tables yet)
+    async fn check_schema_version(&self) -> Result<bool> {
+        match self.schema_version().await? {
+            Some(SCHEMA_VERSION) => Ok(false),
+            None if !self.table_exists("sensors").await? => Ok(true),
+            found => Err(SchemaMismatch { found }.into()),
+        }
+    }
+    
+    async fn record_schema_version(&self) -> Result<()> {
+        sqlx::query("INSERT INTO schema_migrations (version) VALUES ($1) ON CONFLICT (version) DO NOTHING")
+            .bind(SCHEMA_VERSION)
+            .execute(&self.pool)
+            .await
+            .context("Unable to record the schema version")?;
+        info!("Database schema at version {}", SCHEMA_VERSION);
+        Ok(())
+    }
+    
+    async fn has_timescaledb(&self) -> bool {
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM pg_extension WHERE extname = 'timescaledb')")
+            .fetch_one(&self.pool)
+            .await
+            .unwrap_or(false)
+    }
+    
+    /// Stamp written rows with this site and scope duplicate checks to it
+    pub fn with_site(mut self, site: Option<String>) -> Self {
+        self.site = site;
+        self
+    }
+    
+    /// Rewrite readings already stored with the values being saved (`homemetrics backfill`)
+    ///
+    /// Every reading is checked against the database, the history skip included: replaced
+    /// readings count as duplicates in the save statistics, missing ones as inserted.
+    pub fn with_replace_existing(mut self, replace_existing: bool) -> Self {
+        self.replace_existing = replace_existing;
+        self
+    }
+    
+    /// Assume readings older than the boundary window of their sensor are stored, without
+    /// querying them (scheduled Gmail runs, whose exports overlap the previous ones)
+    pub fn with_history_skip(mut self, skip_history: bool) -> Self {
+        self.skip_history = skip_history;
+        self
+    }
+    
+    fn address(config: &DatabaseConfig) -> String {
+        format!("{}:{}/{}", config.host, config.port, config.database)
+    }
+    
+    fn database_url(config: &DatabaseConfig) -> String {
+        format!(
+            "postgres://{}:{}@{}:{}/{}",
+            config.username, config.password, config.host, config.port, config.database
+        )
+    }
+    
+    async fn create_tables_if_not_exists(&self, sensor_partitions: u32) -> Result<()> {
         info!("Checking/creating database tables");
         
         // Try to create TimescaleDB extension if available
-        let _timescaledb_available = match sqlx::query("CREATE EXTENSION IF NOT EXISTS timescaledb CASCADE")
+        let timescaledb_available = match sqlx::query("CREATE EXTENSION IF NOT EXISTS timescaledb CASCADE")
             .execute(&self.pool)
             .await {
                 Ok(_) => {
@@ -50,6 +222,19 @@ impl Database {
                 }
             };
         
+        // Schema versions applied (`homemetrics db migrate`)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                version BIGINT PRIMARY KEY,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create schema_migrations table")?;
+        
         // Create sensors table
         sqlx::query(
             r#"
@@ -57,6 +242,9 @@ impl Database {
                 id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
                 sensor_id VARCHAR(255) UNIQUE NOT NULL,
                 location VARCHAR(255),
+                site VARCHAR(255),
+                sensor_type VARCHAR(32),
+                unit VARCHAR(16),
                 created_at TIMESTAMPTZ DEFAULT NOW(),
                 updated_at TIMESTAMPTZ DEFAULT NOW()
             )
@@ -66,21 +254,30 @@ impl Database {
         .await
         .context("Unable to create sensors table")?;
         
-        // Create temperature readings table
-        sqlx::query(
+        // Create temperature readings table, hash partitioned by sensor on plain PostgreSQL
+        let partitioned = !timescaledb_available && sensor_partitions > 0
+            && !self.table_exists("temperature_readings").await?;
+        let (primary_key, partition_key, partition_by) = if partitioned {
+            ("", ",\n                PRIMARY KEY (id, sensor_id)", " PARTITION BY HASH (sensor_id)")
+        } else {
+            ("PRIMARY KEY ", "", "")
+        };
+        sqlx::query(&format!(
             r#"
             CREATE TABLE IF NOT EXISTS temperature_readings (
-                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                id UUID {primary_key}DEFAULT gen_random_uuid(),
                 sensor_id VARCHAR(255) NOT NULL,
                 timestamp TIMESTAMPTZ NOT NULL,
                 temperature DOUBLE PRECISION NOT NULL,
                 humidity DOUBLE PRECISION,
+                mold_risk DOUBLE PRECISION,
                 location VARCHAR(255),
+                site VARCHAR(255),
                 processed_at TIMESTAMPTZ DEFAULT NOW(),
-                FOREIGN KEY (sensor_id) REFERENCES sensors(sensor_id) ON DELETE CASCADE
-            )
+                FOREIGN KEY (sensor_id) REFERENCES sensors(sensor_id) ON DELETE CASCADE{partition_key}
+            ){partition_by}
             "#
-        )
+        ))
         .execute(&self.pool)
         .await
         .context("Unable to create temperature_readings table")?;
@@ -93,20 +290,22 @@ impl Database {
         .await;
         // Ignore error if hypertable already exists
         
+        self.partition_by_sensor("temperature_readings", sensor_partitions, timescaledb_available).await?;
+        
         // Create indexes to optimize queries
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_temp_readings_sensor_time ON temperature_readings (sensor_id, timestamp DESC)"
         )
         .execute(&self.pool)
         .await
-        .context("Unable to create index sur sensor_id et timestamp")?;
+        .context("Unable to create index on sensor_id and timestamp")?;
         
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_temp_readings_timestamp ON temperature_readings (timestamp DESC)"
         )
         .execute(&self.pool)
         .await
-        .context("Unable to create index sur timestamp")?;
+        .context("Unable to create index on timestamp")?;
         
         // Create pool_readings table for Blue Riot pool monitoring
         sqlx::query(
@@ -118,6 +317,8 @@ impl Database {
                 ph NUMERIC(4,2),
                 orp INTEGER,
                 email_id VARCHAR(255),
+                pool_id VARCHAR(255) NOT NULL DEFAULT 'main',
+                site VARCHAR(255),
                 created_at TIMESTAMPTZ DEFAULT NOW(),
                 PRIMARY KEY (id, timestamp)
             )
@@ -127,6 +328,14 @@ impl Database {
         .await
         .context("Unable to create pool_readings table")?;
         
+        // Tables created before multi-pool support
+        sqlx::query(
+            "ALTER TABLE pool_readings ADD COLUMN IF NOT EXISTS pool_id VARCHAR(255) NOT NULL DEFAULT 'main'"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to add pool_id column to pool_readings")?;
+        
         // Create TimescaleDB hypertable for pool readings
         let _result = sqlx::query(
             "SELECT create_hypertable('pool_readings', 'timestamp', if_not_exists => TRUE)"
@@ -150,150 +359,1750 @@ impl Database {
         .await
         .context("Unable to create index on pool_readings email_id")?;
         
-        info!("Database tables checked/created successfully");
-        Ok(())
-    }
-    
-    pub async fn save_temperature_readings(&self, readings: &[TemperatureReading]) -> Result<usize> {
-        if readings.is_empty() {
-            return Ok(0);
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_pool_readings_pool_timestamp ON pool_readings (pool_id, timestamp DESC)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on pool_readings pool_id")?;
+        
+        // Site label, added to tables created before it existed
+        for table in ["sensors", "temperature_readings", "pool_readings"] {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS site VARCHAR(255)", table))
+                .execute(&self.pool)
+                .await
+                .with_context(|| format!("Unable to add site column to {}", table))?;
         }
         
-        info!("Saving {} temperature readings", readings.len());
+        // Mold risk score derived from temperature and humidity, added to tables created before it existed
+        sqlx::query("ALTER TABLE temperature_readings ADD COLUMN IF NOT EXISTS mold_risk DOUBLE PRECISION")
+            .execute(&self.pool)
+            .await
+            .context("Unable to add mold_risk column to temperature_readings")?;
         
-        let mut transaction = self.pool.begin()
+        // Sensor taxonomy, added to tables created before it existed
+        sqlx::query("ALTER TABLE sensors ADD COLUMN IF NOT EXISTS sensor_type VARCHAR(32)")
+            .execute(&self.pool)
             .await
-            .context("Unable to start transaction")?;
+            .context("Unable to add sensor_type column to sensors")?;
+        
+        sqlx::query("ALTER TABLE sensors ADD COLUMN IF NOT EXISTS unit VARCHAR(16)")
+            .execute(&self.pool)
+            .await
+            .context("Unable to add unit column to sensors")?;
         
-        let mut saved_count = 0;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_temp_readings_site_time ON temperature_readings (site, timestamp DESC)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on temperature_readings site")?;
         
-        for reading in readings {
-            // First, make sure the sensor exists
-            self.ensure_sensor_exists(&mut transaction, &reading.sensor_id, &reading.location).await?;
-            
-            // Check if this reading already exists (avoid duplicates)
-            let exists = sqlx::query(
-                "SELECT 1 FROM temperature_readings WHERE sensor_id = $1 AND timestamp = $2"
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_pool_readings_site_time ON pool_readings (site, timestamp DESC)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on pool_readings site")?;
+        
+        // Readings refused by the validation stage
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rejected_readings (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                source VARCHAR(32) NOT NULL,
+                sensor_id VARCHAR(255),
+                timestamp TIMESTAMPTZ,
+                payload JSONB,
+                reason TEXT NOT NULL,
+                email_id VARCHAR(255),
+                site VARCHAR(255),
+                rejected_at TIMESTAMPTZ DEFAULT NOW()
             )
-            .bind(&reading.sensor_id)
-            .bind(reading.timestamp)
-            .fetch_optional(&mut *transaction)
-            .await
-            .context("Error checking for duplicates")?;
-            
-            if exists.is_some() {
-                saved_count += 1; // We count duplicates as "saved" to reflect total processed
-                debug!("Existing reading skipped: {} à {}", reading.sensor_id, reading.timestamp);
-                continue;
-            }
-            
-            // Insert new reading
-            sqlx::query(
-                r#"
-                INSERT INTO temperature_readings 
-                (sensor_id, timestamp, temperature, humidity, location)
-                VALUES ($1, $2, $3, $4, $5)
-                "#
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create rejected_readings table")?;
+        
+        // Raw content the extractors could not parse
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS failed_extractions (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                source VARCHAR(32) NOT NULL,
+                email_id VARCHAR(255) NOT NULL,
+                filename VARCHAR(255) NOT NULL DEFAULT '',
+                content BYTEA NOT NULL,
+                error TEXT NOT NULL,
+                site VARCHAR(255),
+                failed_at TIMESTAMPTZ DEFAULT NOW(),
+                UNIQUE (source, email_id, filename)
             )
-            .bind(&reading.sensor_id)
-            .bind(reading.timestamp)
-            .bind(reading.temperature)
-            .bind(reading.humidity)
-            .bind(&reading.location)
-            .execute(&mut *transaction)
-            .await
-            .context("Error inserting temperature reading")?;
-            
-            saved_count += 1;
-            
-            debug!("Reading saved: {} = {}°C à {}", 
-                   reading.sensor_id, reading.temperature, reading.timestamp);
-        }
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create failed_extractions table")?;
         
-        transaction.commit()
-            .await
-            .context("Error committing transaction")?;
+        // Failed emails waiting for their next attempt
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS email_retries (
+                source VARCHAR(32) NOT NULL,
+                email_id VARCHAR(255) NOT NULL,
+                attempts INTEGER NOT NULL,
+                next_attempt_at TIMESTAMPTZ NOT NULL,
+                last_error TEXT NOT NULL,
+                dead_lettered_at TIMESTAMPTZ,
+                site VARCHAR(255),
+                updated_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (source, email_id)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create email_retries table")?;
         
-        info!("Save completed: {} new readings out of {} processed", saved_count, readings.len());
-        Ok(saved_count)
-    }
-    
-    async fn ensure_sensor_exists(
-        &self, 
-        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-        sensor_id: &str,
-        location: &Option<String>
-    ) -> Result<()> {
-        let exists = sqlx::query("SELECT 1 FROM sensors WHERE sensor_id = $1")
-            .bind(sensor_id)
-            .fetch_optional(&mut **transaction)
+        // Retry queues created before dead-letter handling
+        sqlx::query("ALTER TABLE email_retries ADD COLUMN IF NOT EXISTS dead_lettered_at TIMESTAMPTZ")
+            .execute(&self.pool)
             .await
-            .context("Error checking sensor existence")?;
+            .context("Unable to add dead_lettered_at column to email_retries")?;
         
-        if exists.is_none() {
-            sqlx::query(
-                "INSERT INTO sensors (sensor_id, location) VALUES ($1, $2)"
+        // One row per source and site ('' without SITE) so it can be the primary key
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS source_activity (
+                source VARCHAR(32) NOT NULL,
+                site VARCHAR(255) NOT NULL DEFAULT '',
+                last_email_at TIMESTAMPTZ NOT NULL,
+                empty_runs INTEGER NOT NULL DEFAULT 0,
+                alerted_at TIMESTAMPTZ,
+                updated_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (source, site)
             )
-            .bind(sensor_id)
-            .bind(location)
-            .execute(&mut **transaction)
-            .await
-            .context("Error inserting sensor")?;
-            
-            debug!("New sensor created: {}", sensor_id);
-        } else if let Some(loc) = location {
-            // Update location if provided
-            sqlx::query(
-                "UPDATE sensors SET location = $2, updated_at = NOW() WHERE sensor_id = $1 AND location IS NULL"
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create source_activity table")?;
+        
+        // Stays in the mold risk zone already alerted, by first reading of the stay
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mold_alerts (
+                sensor_id VARCHAR(255) NOT NULL,
+                site VARCHAR(255) NOT NULL DEFAULT '',
+                since TIMESTAMPTZ NOT NULL,
+                alerted_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (sensor_id, site, since)
             )
-            .bind(sensor_id)
-            .bind(loc)
-            .execute(&mut **transaction)
-            .await
-            .context("Error updating sensor location")?;
-        }
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create mold_alerts table")?;
         
-        Ok(())
-    }
-    
-    /// Save a pool reading to the database
-    pub async fn save_pool_reading(&self, reading: &PoolReading, email_id: &str) -> Result<()> {
-        debug!("Saving pool reading: temp={:?}°C, pH={:?}, ORP={:?} mV", 
-               reading.temperature, reading.ph, reading.orp);
+        // Emails processed with read-only Gmail access, whose todo label stays in place
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS processed_emails (
+                source VARCHAR(32) NOT NULL,
+                email_id VARCHAR(255) NOT NULL,
+                site VARCHAR(255) NOT NULL DEFAULT '',
+                processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (source, email_id, site)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create processed_emails table")?;
         
-        // Check if this reading already exists (by email_id)
-        let exists = sqlx::query_scalar::<_, bool>(
-            "SELECT EXISTS(SELECT 1 FROM pool_readings WHERE email_id = $1)"
+        // Last email examined by a run capped by BATCH_SIZE, one row per source and site
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS batch_checkpoints (
+                source VARCHAR(32) NOT NULL,
+                site VARCHAR(255) NOT NULL DEFAULT '',
+                email_id VARCHAR(255) NOT NULL,
+                email_date BIGINT NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (source, site)
+            )
+            "#
         )
-        .bind(email_id)
-        .fetch_one(&self.pool)
+        .execute(&self.pool)
         .await
-        .context("Failed to check for duplicate pool reading")?;
+        .context("Unable to create batch_checkpoints table")?;
         
-        if exists {
-            info!("Pool reading from email {} already exists, skipping", email_id);
-            return Ok(());
-        }
+        // Daily Gmail API requests, one row per day, source and site ('' without SITE)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_usage (
+                day DATE NOT NULL,
+                source VARCHAR(32) NOT NULL,
+                site VARCHAR(255) NOT NULL DEFAULT '',
+                calls BIGINT NOT NULL DEFAULT 0,
+                runs INTEGER NOT NULL DEFAULT 0,
+                updated_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (day, source, site)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create api_usage table")?;
         
+        // Outbound Slack messages with their delivery result
         sqlx::query(
             r#"
-            INSERT INTO pool_readings (timestamp, temperature, ph, orp, email_id)
-            VALUES ($1, $2, $3, $4, $5)
+            CREATE TABLE IF NOT EXISTS notifications (
+                id BIGSERIAL PRIMARY KEY,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                channel VARCHAR(255) NOT NULL,
+                kind VARCHAR(32) NOT NULL,
+                source VARCHAR(32),
+                related_id VARCHAR(255),
+                text TEXT NOT NULL,
+                thread_ts VARCHAR(64),
+                status VARCHAR(16) NOT NULL,
+                error TEXT,
+                message_ts VARCHAR(64),
+                attempts INTEGER NOT NULL DEFAULT 1,
+                next_attempt_at TIMESTAMPTZ,
+                site VARCHAR(255)
+            )
             "#
         )
-        .bind(reading.timestamp)
-        .bind(reading.temperature)
-        .bind(reading.ph)
-        .bind(reading.orp)
-        .bind(email_id)
         .execute(&self.pool)
         .await
-        .context("Failed to insert pool reading")?;
+        .context("Unable to create notifications table")?;
+        
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_notifications_status ON notifications (status, created_at DESC)")
+            .execute(&self.pool)
+            .await
+            .context("Unable to create index on notifications")?;
         
-        info!("✅ Pool reading saved: temp={:?}°C, pH={:?}, ORP={:?} mV", 
-              reading.temperature, reading.ph, reading.orp);
+        // Redelivery queue, added to tables created before it existed
+        sqlx::query("ALTER TABLE notifications ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 1")
+            .execute(&self.pool)
+            .await
+            .context("Unable to add attempts column to notifications")?;
         
-        Ok(())
-    }
-    
-}
\ No newline at end of file
+        sqlx::query("ALTER TABLE notifications ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ")
+            .execute(&self.pool)
+            .await
+            .context("Unable to add next_attempt_at column to notifications")?;
+        
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_notifications_retry ON notifications (next_attempt_at) WHERE status = 'failed'")
+            .execute(&self.pool)
+            .await
+            .context("Unable to create retry index on notifications")?;
+        
+        // Email and attachment of the stored readings, one row per sensor, attachment and run
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS reading_provenance (
+                id BIGSERIAL PRIMARY KEY,
+                sensor_id VARCHAR(255) NOT NULL,
+                source VARCHAR(32) NOT NULL,
+                email_id VARCHAR(255) NOT NULL,
+                file VARCHAR(255),
+                file_hash VARCHAR(64),
+                first_timestamp TIMESTAMPTZ NOT NULL,
+                last_timestamp TIMESTAMPTZ NOT NULL,
+                readings INTEGER NOT NULL,
+                run_started_at TIMESTAMPTZ NOT NULL,
+                site VARCHAR(255),
+                recorded_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create reading_provenance table")?;
+        
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_reading_provenance_sensor ON reading_provenance (sensor_id, first_timestamp)")
+            .execute(&self.pool)
+            .await
+            .context("Unable to create index on reading_provenance")?;
+        
+        // Attachment hash (`db delete --attachment`), added to tables created before it existed
+        sqlx::query("ALTER TABLE reading_provenance ADD COLUMN IF NOT EXISTS file_hash VARCHAR(64)")
+            .execute(&self.pool)
+            .await
+            .context("Unable to add file_hash column to reading_provenance")?;
+        
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_reading_provenance_email ON reading_provenance (email_id)")
+            .execute(&self.pool)
+            .await
+            .context("Unable to create email index on reading_provenance")?;
+        
+        if self.layout == StorageLayout::Generic {
+            self.create_generic_table(sensor_partitions, timescaledb_available).await?;
+        }
+        
+        info!("Database tables checked/created successfully");
+        Ok(())
+    }
+    
+    /// Single metric/value table used by `STORAGE_LAYOUT=generic`
+    async fn create_generic_table(&self, sensor_partitions: u32, timescaledb_available: bool) -> Result<()> {
+        let partitioned = !timescaledb_available && sensor_partitions > 0
+            && !self.table_exists("readings").await?;
+        let partition_by = if partitioned { " PARTITION BY HASH (sensor_id)" } else { "" };
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS readings (
+                sensor_id VARCHAR(255) NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                metric VARCHAR(64) NOT NULL,
+                value DOUBLE PRECISION NOT NULL,
+                site VARCHAR(255),
+                email_id VARCHAR(255),
+                created_at TIMESTAMPTZ DEFAULT NOW()
+            ){partition_by}
+            "#
+        ))
+        .execute(&self.pool)
+        .await
+        .context("Unable to create readings table")?;
+        
+        let _result = sqlx::query(
+            "SELECT create_hypertable('readings', 'timestamp', if_not_exists => TRUE)"
+        )
+        .execute(&self.pool)
+        .await;
+        // Ignore error if hypertable already exists
+        
+        self.partition_by_sensor("readings", sensor_partitions, timescaledb_available).await?;
+        
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_readings_sensor_metric_time ON readings (sensor_id, metric, timestamp DESC)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on readings")?;
+        
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_readings_email ON readings (email_id)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on readings email_id")?;
+        
+        Ok(())
+    }
+    
+    async fn table_exists(&self, table: &str) -> Result<bool> {
+        sqlx::query_scalar::<_, bool>("SELECT to_regclass($1) IS NOT NULL")
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await
+            .with_context(|| format!("Unable to check whether {} exists", table))
+    }
+    
+    /// Spread a readings table over `partitions` partitions by sensor_id (`DB_SENSOR_PARTITIONS`)
+    ///
+    /// TimescaleDB adds a hash dimension to the hypertable, which it only accepts
+    /// while the hypertable is still empty. On plain PostgreSQL the table must
+    /// have been created `PARTITION BY HASH (sensor_id)`: the hash partitions are
+    /// created here, a table created before the option is left as is.
+    async fn partition_by_sensor(&self, table: &str, partitions: u32, timescaledb: bool) -> Result<()> {
+        if partitions == 0 {
+            return Ok(());
+        }
+        
+        if timescaledb {
+            let query = format!(
+                "SELECT add_dimension('{}', 'sensor_id', number_partitions => {}, if_not_exists => TRUE)",
+                table, partitions
+            );
+            match sqlx::query(&query).execute(&self.pool).await {
+                Ok(_) => debug!("{} space partitioned by sensor_id ({} partitions)", table, partitions),
+                Err(e) => warn!("⚠️  Unable to partition {} by sensor_id (only possible while the hypertable is empty): {}", table, e),
+            }
+            return Ok(());
+        }
+        
+        let partitioned = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM pg_partitioned_table WHERE partrelid = to_regclass($1))"
+        )
+        .bind(table)
+        .fetch_one(&self.pool)
+        .await
+        .with_context(|| format!("Unable to check partitioning of {}", table))?;
+        if !partitioned {
+            warn!("⚠️  {} was created without partitions, DB_SENSOR_PARTITIONS only applies to new tables", table);
+            return Ok(());
+        }
+        
+        for remainder in 0..partitions {
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {table}_p{remainder} PARTITION OF {table} FOR VALUES WITH (MODULUS {partitions}, REMAINDER {remainder})"
+            ))
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Unable to create partition {} of {} (was DB_SENSOR_PARTITIONS changed?)", remainder, table))?;
+        }
+        Ok(())
+    }
+    
+    /// Duplicate check of a temperature reading (sensor, timestamp, site) in the active layout
+    fn temperature_exists_query(&self) -> &'static str {
+        match self.layout {
+            StorageLayout::Domain => "SELECT EXISTS(SELECT 1 FROM temperature_readings WHERE sensor_id = $1 AND timestamp = $2 AND site IS NOT DISTINCT FROM $3)",
+            StorageLayout::Generic => "SELECT EXISTS(SELECT 1 FROM readings WHERE sensor_id = $1 AND timestamp = $2 AND metric = 'temperature' AND site IS NOT DISTINCT FROM $3)",
+        }
+    }
+    
+    /// Latest stored temperature timestamp of each sensor of the batch
+    async fn latest_temperature_timestamps(&self, readings: &[TemperatureReading]) -> Result<HashMap<String, DateTime<Utc>>> {
+        let query = match self.layout {
+            StorageLayout::Domain => "SELECT MAX(timestamp) FROM temperature_readings WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2",
+            StorageLayout::Generic => "SELECT MAX(timestamp) FROM readings WHERE sensor_id = $1 AND metric = 'temperature' AND site IS NOT DISTINCT FROM $2",
+        };
+        
+        let sensors: HashSet<&str> = readings.iter().map(|reading| reading.sensor_id.as_str()).collect();
+        let mut latest = HashMap::new();
+        for sensor_id in sensors {
+            let timestamp = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(query)
+                .bind(sensor_id)
+                .bind(&self.site)
+                .fetch_one(&self.pool)
+                .await
+                .context("Error reading the latest sensor timestamp")?;
+            if let Some(timestamp) = timestamp {
+                latest.insert(sensor_id.to_string(), timestamp);
+            }
+        }
+        Ok(latest)
+    }
+    
+    /// Scope of the per-sensor batch marks: same database, layout and site
+    fn watermark_scope(&self) -> String {
+        format!("{}|{:?}|{}", self.address, self.layout, self.site.as_deref().unwrap_or(""))
+    }
+    
+    pub async fn save_temperature_readings(&self, readings: &[TemperatureReading]) -> Result<SaveStats> {
+        // Sensors whose readings an earlier run already stored are skipped without any query
+        let scope = self.watermark_scope();
+        let stored = if self.replace_existing { HashSet::new() } else { watermarks::stored_sensors(&scope, readings) };
+        let (cached, readings): (Vec<TemperatureReading>, Vec<TemperatureReading>) = readings
+            .iter()
+            .cloned()
+            .partition(|reading| stored.contains(&reading.sensor_id));
+        if !cached.is_empty() {
+            debug!("{} reading(s) of {} sensor(s) already stored by an earlier run", cached.len(), stored.len());
+        }
+        
+        let mut stats = match self.insert_temperature_readings(&readings).await {
+            Ok(stats) => {
+                watermarks::record(&scope, &readings);
+                stats
+            }
+            Err(e) => {
+                watermarks::invalidate(&scope, &readings);
+                return Err(e);
+            }
+        };
+        for reading in &cached {
+            stats.duplicates += 1;
+            stats.per_sensor.entry(reading.sensor_id.clone()).or_default().duplicates += 1;
+        }
+        Ok(stats)
+    }
+    
+    /// Insert the new readings of a batch
+    async fn insert_temperature_readings(&self, readings: &[TemperatureReading]) -> Result<SaveStats> {
+        let mut stats = SaveStats::default();
+        
+        if readings.is_empty() {
+            return Ok(stats);
+        }
+        
+        info!("Saving {} temperature readings", readings.len());
+        
+        // Readings after the latest stored timestamp need no duplicate query
+        let latest = self.latest_temperature_timestamps(readings).await?;
+        let checks = delta_checks(readings, &latest, self.skip_history && !self.replace_existing);
+        
+        let mut transaction = self.pool.begin()
+            .await
+            .context("Unable to start transaction")?;
+        
+        for (reading, check) in readings.iter().zip(checks) {
+            // First, make sure the sensor exists
+            self.ensure_sensor_exists(&mut transaction, &reading.sensor_id, &reading.location).await?;
+            
+            // Check if this reading already exists (avoid duplicates); a replacement
+            // is only counted as such when the row is really there
+            let exists = match check {
+                DeltaCheck::New => false,
+                DeltaCheck::Known if !self.replace_existing => true,
+                DeltaCheck::Known | DeltaCheck::Check => sqlx::query_scalar::<_, bool>(self.temperature_exists_query())
+                    .bind(&reading.sensor_id)
+                    .bind(reading.timestamp)
+                    .bind(&self.site)
+                    .fetch_one(&mut *transaction)
+                    .await
+                    .context("Error checking for duplicates")?,
+            };
+            
+            if exists {
+                stats.duplicates += 1;
+                stats.per_sensor.entry(reading.sensor_id.clone()).or_default().duplicates += 1;
+                if !self.replace_existing {
+                    debug!("Existing reading skipped: {} at {}", reading.sensor_id, reading.timestamp);
+                    continue;
+                }
+                self.delete_temperature_reading(&mut transaction, reading).await?;
+                debug!("Existing reading replaced: {} at {}", reading.sensor_id, reading.timestamp);
+            }
+            
+            // Insert new reading
+            match self.layout {
+                StorageLayout::Domain => {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO temperature_readings 
+                        (sensor_id, timestamp, temperature, humidity, mold_risk, location, site)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7)
+                        "#
+                    )
+                    .bind(&reading.sensor_id)
+                    .bind(reading.timestamp)
+                    .bind(reading.temperature)
+                    .bind(reading.humidity)
+                    .bind(mold_risk(reading))
+                    .bind(&reading.location)
+                    .bind(&self.site)
+                    .execute(&mut *transaction)
+                    .await
+                    .context("Error inserting temperature reading")?;
+                }
+                StorageLayout::Generic => {
+                    for (metric, value) in temperature_metrics(reading) {
+                        let metric = metric.key();
+                        sqlx::query(
+                            "INSERT INTO readings (sensor_id, timestamp, metric, value, site) VALUES ($1, $2, $3, $4, $5)"
+                        )
+                        .bind(&reading.sensor_id)
+                        .bind(reading.timestamp)
+                        .bind(metric)
+                        .bind(value)
+                        .bind(&self.site)
+                        .execute(&mut *transaction)
+                        .await
+                        .context("Error inserting temperature reading")?;
+                    }
+                }
+            }
+            
+            if exists {
+                continue;
+            }
+            stats.inserted += 1;
+            stats.per_sensor.entry(reading.sensor_id.clone()).or_default().inserted += 1;
+            
+            debug!("Reading saved: {} = {}°C at {}", 
+                   reading.sensor_id, reading.temperature, reading.timestamp);
+        }
+        
+        transaction.commit()
+            .await
+            .context("Error committing transaction")?;
+        storage::record_reading_write();
+        
+        info!("Save completed: {} new readings out of {} processed ({} duplicates)", 
+              stats.inserted, readings.len(), stats.duplicates);
+        Ok(stats)
+    }
+    
+    /// Remove a stored temperature reading before it is written again
+    async fn delete_temperature_reading(&self, transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+                                        reading: &TemperatureReading) -> Result<()> {
+        let query = match self.layout {
+            StorageLayout::Domain => "DELETE FROM temperature_readings WHERE sensor_id = $1 AND timestamp = $2 AND site IS NOT DISTINCT FROM $3",
+            // Every metric of the reading, mold_risk included, is written again
+            StorageLayout::Generic => "DELETE FROM readings WHERE sensor_id = $1 AND timestamp = $2 AND site IS NOT DISTINCT FROM $3",
+        };
+        sqlx::query(query)
+            .bind(&reading.sensor_id)
+            .bind(reading.timestamp)
+            .bind(&self.site)
+            .execute(&mut **transaction)
+            .await
+            .context("Error replacing temperature reading")?;
+        Ok(())
+    }
+    
+    /// Compare readings with the database without writing anything
+    /// 
+    /// `inserted` counts readings that would be new, `duplicates` those already present.
+    pub async fn diff_temperature_readings(&self, readings: &[TemperatureReading]) -> Result<SaveStats> {
+        let mut stats = SaveStats::default();
+        let checks = delta_checks(readings, &self.latest_temperature_timestamps(readings).await?, self.skip_history);
+        
+        for (reading, check) in readings.iter().zip(checks) {
+            let exists = match check {
+                DeltaCheck::New => false,
+                DeltaCheck::Known => true,
+                DeltaCheck::Check => sqlx::query_scalar::<_, bool>(self.temperature_exists_query())
+                    .bind(&reading.sensor_id)
+                    .bind(reading.timestamp)
+                    .bind(&self.site)
+                    .fetch_one(&self.pool)
+                    .await
+                    .context("Error checking for existing readings")?,
+            };
+            
+            let counts = stats.per_sensor.entry(reading.sensor_id.clone()).or_default();
+            if exists {
+                stats.duplicates += 1;
+                counts.duplicates += 1;
+            } else {
+                stats.inserted += 1;
+                counts.inserted += 1;
+            }
+        }
+        
+        debug!("Dry-run comparison: {} new, {} already present", stats.inserted, stats.duplicates);
+        Ok(stats)
+    }
+    
+    async fn ensure_sensor_exists(
+        &self, 
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        sensor_id: &str,
+        location: &Option<String>
+    ) -> Result<()> {
+        let exists = sqlx::query("SELECT 1 FROM sensors WHERE sensor_id = $1")
+            .bind(sensor_id)
+            .fetch_optional(&mut **transaction)
+            .await
+            .context("Error checking sensor existence")?;
+        
+        if exists.is_none() {
+            sqlx::query(
+                "INSERT INTO sensors (sensor_id, location, site) VALUES ($1, $2, $3)"
+            )
+            .bind(sensor_id)
+            .bind(location)
+            .bind(&self.site)
+            .execute(&mut **transaction)
+            .await
+            .context("Error inserting sensor")?;
+            
+            debug!("New sensor created: {}", sensor_id);
+        } else if let Some(loc) = location {
+            // Update location if provided
+            sqlx::query(
+                "UPDATE sensors SET location = $2, updated_at = NOW() WHERE sensor_id = $1 AND location IS NULL"
+            )
+            .bind(sensor_id)
+            .bind(loc)
+            .execute(&mut **transaction)
+            .await
+            .context("Error updating sensor location")?;
+        }
+        
+        if exists.is_some() && self.site.is_some() {
+            // Sensors created before the site was configured
+            sqlx::query(
+                "UPDATE sensors SET site = $2, updated_at = NOW() WHERE sensor_id = $1 AND site IS NULL"
+            )
+            .bind(sensor_id)
+            .bind(&self.site)
+            .execute(&mut **transaction)
+            .await
+            .context("Error updating sensor site")?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Create the sensor if needed and set its type and unit
+    pub async fn register_sensor(&self, sensor_id: &str, sensor_type: SensorType) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sensors (sensor_id, site, sensor_type, unit) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (sensor_id) DO UPDATE SET
+                site = COALESCE(sensors.site, EXCLUDED.site),
+                sensor_type = EXCLUDED.sensor_type,
+                unit = EXCLUDED.unit,
+                updated_at = NOW()
+            WHERE sensors.sensor_type IS DISTINCT FROM EXCLUDED.sensor_type
+               OR sensors.unit IS DISTINCT FROM EXCLUDED.unit
+               OR sensors.site IS NULL
+            "#
+        )
+        .bind(sensor_id)
+        .bind(&self.site)
+        .bind(sensor_type.as_str())
+        .bind(sensor_type.unit())
+        .execute(&self.pool)
+        .await
+        .context("Error registering sensor type")?;
+        
+        Ok(())
+    }
+    
+    /// Keep readings refused by validation, with the reason
+    pub async fn save_rejected_readings(&self, rejected: &[RejectedReading]) -> Result<usize> {
+        for reading in rejected {
+            sqlx::query(
+                r#"
+                INSERT INTO rejected_readings (source, sensor_id, timestamp, payload, reason, email_id, site)
+                VALUES ($1, $2, $3, $4::jsonb, $5, $6, $7)
+                "#
+            )
+            .bind(&reading.source)
+            .bind(&reading.sensor_id)
+            .bind(reading.timestamp)
+            .bind(&reading.payload)
+            .bind(&reading.reason)
+            .bind(&reading.email_id)
+            .bind(&self.site)
+            .execute(&self.pool)
+            .await
+            .context("Error inserting rejected reading")?;
+        }
+        
+        if !rejected.is_empty() {
+            warn!("⚠️  {} reading(s) rejected by validation", rejected.len());
+        }
+        Ok(rejected.len())
+    }
+    
+    /// Keep the raw content of a failed extraction (one row per email and file)
+    pub async fn save_failed_extraction(&self, failure: &FailedExtraction) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO failed_extractions (source, email_id, filename, content, error, site)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (source, email_id, filename) DO UPDATE SET
+                content = EXCLUDED.content,
+                error = EXCLUDED.error,
+                failed_at = NOW()
+            "#
+        )
+        .bind(&failure.source)
+        .bind(&failure.email_id)
+        .bind(&failure.filename)
+        .bind(&failure.content)
+        .bind(&failure.error)
+        .bind(&self.site)
+        .execute(&self.pool)
+        .await
+        .context("Error saving failed extraction")?;
+        
+        info!("💾 Raw content of failed extraction kept for email {} ({})", failure.email_id,
+              if failure.filename.is_empty() { "body" } else { failure.filename.as_str() });
+        Ok(())
+    }
+    
+    /// Emails of a source waiting in the retry queue, next attempt first
+    pub async fn email_retries(&self, source: &str) -> Result<Vec<EmailRetry>> {
+        let rows: Vec<RetryRow> = sqlx::query_as(
+            r#"
+            SELECT source, email_id, attempts::BIGINT, next_attempt_at, last_error, dead_lettered_at FROM email_retries
+            WHERE source = $1 AND site IS NOT DISTINCT FROM $2
+            ORDER BY next_attempt_at
+            "#
+        )
+        .bind(source)
+        .bind(&self.site)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading email retry queue")?;
+        
+        Ok(rows.into_iter().map(retry_from_row).collect())
+    }
+    
+    /// Insert or update the retry entry of a failed email
+    pub async fn schedule_email_retry(&self, retry: &EmailRetry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_retries (source, email_id, attempts, next_attempt_at, last_error, site, dead_lettered_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (source, email_id) DO UPDATE SET
+                attempts = EXCLUDED.attempts,
+                next_attempt_at = EXCLUDED.next_attempt_at,
+                last_error = EXCLUDED.last_error,
+                dead_lettered_at = EXCLUDED.dead_lettered_at,
+                updated_at = NOW()
+            "#
+        )
+        .bind(&retry.source)
+        .bind(&retry.email_id)
+        .bind(retry.attempts as i32)
+        .bind(retry.next_attempt_at)
+        .bind(&retry.last_error)
+        .bind(&self.site)
+        .bind(retry.dead_lettered_at)
+        .execute(&self.pool)
+        .await
+        .context("Error scheduling email retry")?;
+        
+        info!("🔁 Email {} scheduled for retry at {} (attempt {})", 
+              retry.email_id, retry.next_attempt_at, retry.attempts);
+        Ok(())
+    }
+    
+    /// Remove an email from the retry queue once processed
+    pub async fn clear_email_retry(&self, source: &str, email_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM email_retries WHERE source = $1 AND email_id = $2")
+            .bind(source)
+            .bind(email_id)
+            .execute(&self.pool)
+            .await
+            .context("Error clearing email retry")?;
+        
+        Ok(())
+    }
+    
+    /// Dead-lettered emails of every source, most recent first
+    pub async fn dead_letters(&self) -> Result<Vec<EmailRetry>> {
+        let rows: Vec<RetryRow> = sqlx::query_as(
+            r#"
+            SELECT source, email_id, attempts::BIGINT, next_attempt_at, last_error, dead_lettered_at FROM email_retries
+            WHERE dead_lettered_at IS NOT NULL AND site IS NOT DISTINCT FROM $1
+            ORDER BY dead_lettered_at DESC
+            "#
+        )
+        .bind(&self.site)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading dead-lettered emails")?;
+        
+        Ok(rows.into_iter().map(retry_from_row).collect())
+    }
+    
+    /// Put a dead-lettered email back in the queue, due now with its attempts reset
+    pub async fn requeue_email(&self, email_id: &str) -> Result<Option<EmailRetry>> {
+        let row: Option<RetryRow> = sqlx::query_as(
+            r#"
+            UPDATE email_retries SET attempts = 0, next_attempt_at = NOW(), dead_lettered_at = NULL, updated_at = NOW()
+            WHERE email_id = $1 AND dead_lettered_at IS NOT NULL AND site IS NOT DISTINCT FROM $2
+            RETURNING source, email_id, attempts::BIGINT, next_attempt_at, last_error, dead_lettered_at
+            "#
+        )
+        .bind(email_id)
+        .bind(&self.site)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Error requeuing email")?;
+        
+        Ok(row.map(retry_from_row))
+    }
+    
+    /// Record a run of a source: emails found reset the activity, otherwise an empty run is counted
+    pub async fn record_source_run(&self, source: &str, found_emails: bool) -> Result<SourceActivity> {
+        let row: ActivityRow = sqlx::query_as(
+            r#"
+            INSERT INTO source_activity (source, site, last_email_at, empty_runs)
+            VALUES ($1, $2, NOW(), CASE WHEN $3 THEN 0 ELSE 1 END)
+            ON CONFLICT (source, site) DO UPDATE SET
+                last_email_at = CASE WHEN $3 THEN NOW() ELSE source_activity.last_email_at END,
+                empty_runs = CASE WHEN $3 THEN 0 ELSE source_activity.empty_runs + 1 END,
+                alerted_at = CASE WHEN $3 THEN NULL ELSE source_activity.alerted_at END,
+                updated_at = NOW()
+            RETURNING source, last_email_at, empty_runs::BIGINT, alerted_at, updated_at
+            "#
+        )
+        .bind(source)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .bind(found_emails)
+        .fetch_one(&self.pool)
+        .await
+        .context("Error recording source activity")?;
+        
+        Ok(activity_from_row(row))
+    }
+    
+    /// Activity of every source of the site, by source name
+    pub async fn source_activities(&self) -> Result<Vec<SourceActivity>> {
+        let rows: Vec<ActivityRow> = sqlx::query_as(
+            r#"
+            SELECT source, last_email_at, empty_runs::BIGINT, alerted_at, updated_at
+            FROM source_activity
+            WHERE site = $1
+            ORDER BY source
+            "#
+        )
+        .bind(self.site.as_deref().unwrap_or(""))
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading source activity")?;
+        
+        Ok(rows.into_iter().map(activity_from_row).collect())
+    }
+    
+    /// Remember that the "no emails" warning of a source was sent
+    pub async fn mark_source_alerted(&self, source: &str) -> Result<()> {
+        sqlx::query("UPDATE source_activity SET alerted_at = NOW() WHERE source = $1 AND site = $2")
+            .bind(source)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .execute(&self.pool)
+            .await
+            .context("Error updating source activity")?;
+        
+        Ok(())
+    }
+    
+    /// Remember an email processed without changing its labels (read-only Gmail access)
+    pub async fn record_processed_email(&self, source: &str, email_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO processed_emails (source, email_id, site) VALUES ($1, $2, $3)
+            ON CONFLICT (source, email_id, site) DO UPDATE SET processed_at = NOW()
+            "#
+        )
+        .bind(source)
+        .bind(email_id)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .execute(&self.pool)
+        .await
+        .context("Error recording processed email")?;
+        
+        Ok(())
+    }
+    
+    /// Write a `processed_emails` row and read it back in a transaction rolled back (`homemetrics doctor`)
+    pub async fn check_write(&self) -> Result<()> {
+        let mut transaction = self.pool.begin()
+            .await
+            .context("Unable to start transaction")?;
+
+        sqlx::query("INSERT INTO processed_emails (source, email_id, site) VALUES ('doctor', 'doctor', $1) ON CONFLICT DO NOTHING")
+            .bind(self.site.as_deref().unwrap_or(""))
+            .execute(&mut *transaction)
+            .await
+            .context("Unable to write to processed_emails")?;
+        let found: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM processed_emails WHERE source = 'doctor' AND email_id = 'doctor' AND site = $1)")
+            .bind(self.site.as_deref().unwrap_or(""))
+            .fetch_one(&mut *transaction)
+            .await
+            .context("Unable to read back processed_emails")?;
+
+        transaction.rollback()
+            .await
+            .context("Unable to roll back the test write")?;
+        anyhow::ensure!(found, "Row written to processed_emails not found");
+        Ok(())
+    }
+
+    /// Emails of a source recorded by `record_processed_email`
+    pub async fn processed_emails(&self, source: &str) -> Result<HashSet<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT email_id FROM processed_emails WHERE source = $1 AND site = $2")
+            .bind(source)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading processed emails")?;
+        
+        Ok(rows.into_iter().map(|(email_id,)| email_id).collect())
+    }
+    
+    /// Where the previous batch of a source stopped, None when it reached the end
+    pub async fn batch_checkpoint(&self, source: &str) -> Result<Option<BatchCheckpoint>> {
+        let row: Option<(String, i64)> = sqlx::query_as(
+            "SELECT email_id, email_date FROM batch_checkpoints WHERE source = $1 AND site = $2"
+        )
+        .bind(source)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .fetch_optional(&self.pool)
+        .await
+        .context("Error reading batch checkpoint")?;
+        
+        Ok(row.map(|(email_id, email_date)| BatchCheckpoint { source: source.to_string(), email_id, email_date }))
+    }
+    
+    /// Record the last email examined by a run capped by `BATCH_SIZE`
+    pub async fn save_batch_checkpoint(&self, checkpoint: &BatchCheckpoint) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO batch_checkpoints (source, site, email_id, email_date) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (source, site) DO UPDATE SET
+                email_id = excluded.email_id,
+                email_date = excluded.email_date,
+                updated_at = NOW()
+            "#
+        )
+        .bind(&checkpoint.source)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .bind(&checkpoint.email_id)
+        .bind(checkpoint.email_date)
+        .execute(&self.pool)
+        .await
+        .context("Error saving batch checkpoint")?;
+        
+        Ok(())
+    }
+    
+    /// Forget the checkpoint of a source
+    pub async fn clear_batch_checkpoint(&self, source: &str) -> Result<()> {
+        sqlx::query("DELETE FROM batch_checkpoints WHERE source = $1 AND site = $2")
+            .bind(source)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .execute(&self.pool)
+            .await
+            .context("Error clearing batch checkpoint")?;
+        
+        Ok(())
+    }
+    
+    /// Add the Gmail API requests of a run to today's usage, returns today's total for the site
+    pub async fn record_api_calls(&self, source: &str, calls: u64) -> Result<u64> {
+        let today = Utc::now().date_naive();
+        let site = self.site.as_deref().unwrap_or("");
+        
+        sqlx::query(
+            r#"
+            INSERT INTO api_usage (day, source, site, calls, runs)
+            VALUES ($1, $2, $3, $4, 1)
+            ON CONFLICT (day, source, site) DO UPDATE SET
+                calls = api_usage.calls + excluded.calls,
+                runs = api_usage.runs + 1,
+                updated_at = NOW()
+            "#
+        )
+        .bind(today)
+        .bind(source)
+        .bind(site)
+        .bind(calls as i64)
+        .execute(&self.pool)
+        .await
+        .context("Error recording Gmail API usage")?;
+        
+        let (total,): (i64,) = sqlx::query_as("SELECT COALESCE(SUM(calls), 0)::BIGINT FROM api_usage WHERE day = $1 AND site = $2")
+            .bind(today)
+            .bind(site)
+            .fetch_one(&self.pool)
+            .await
+            .context("Error reading Gmail API usage")?;
+        
+        Ok(total.max(0) as u64)
+    }
+    
+    /// Gmail API usage of the last `days` days, most recent first
+    pub async fn api_usage(&self, days: u32) -> Result<Vec<ApiUsage>> {
+        let since = Utc::now().date_naive() - chrono::Duration::days(days.saturating_sub(1) as i64);
+        
+        let rows: Vec<ApiUsageRow> = sqlx::query_as(
+            r#"
+            SELECT day, source, calls, runs::BIGINT FROM api_usage
+            WHERE day >= $1 AND site = $2
+            ORDER BY day DESC, source
+            "#
+        )
+        .bind(since)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading Gmail API usage")?;
+        
+        Ok(rows.into_iter().map(api_usage_from_row).collect())
+    }
+    
+    /// Keep an outbound notification with its delivery result, returns its id
+    pub async fn record_notification(&self, notification: &NotificationRecord) -> Result<i64> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO notifications (created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts,
+                                       attempts, next_attempt_at, site)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id
+            "#
+        )
+        .bind(notification.created_at)
+        .bind(&notification.channel)
+        .bind(&notification.kind)
+        .bind(&notification.source)
+        .bind(&notification.related_id)
+        .bind(&notification.text)
+        .bind(&notification.thread_ts)
+        .bind(notification.status.key())
+        .bind(&notification.error)
+        .bind(&notification.message_ts)
+        .bind(notification.attempts as i32)
+        .bind(notification.next_attempt_at)
+        .bind(&self.site)
+        .fetch_one(&self.pool)
+        .await
+        .context("Error recording notification")
+    }
+    
+    /// Latest notifications, most recent first, only the failed ones with `failed_only`
+    pub async fn notifications(&self, failed_only: bool, limit: usize) -> Result<Vec<NotificationRecord>> {
+        let rows: Vec<NotificationRow> = sqlx::query_as(
+            r#"
+            SELECT id, created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts, attempts, next_attempt_at
+            FROM notifications
+            WHERE site IS NOT DISTINCT FROM $1 AND (NOT $2 OR status = 'failed')
+            ORDER BY created_at DESC, id DESC
+            LIMIT $3
+            "#
+        )
+        .bind(&self.site)
+        .bind(failed_only)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading notifications")?;
+        
+        Ok(rows.into_iter().map(notification_from_row).collect())
+    }
+    
+    /// Failed notifications due at `now`, their next attempt pushed to `now + lease`
+    pub async fn claim_notifications(&self, now: DateTime<Utc>, lease: TimeDelta, limit: usize) -> Result<Vec<NotificationRecord>> {
+        let rows: Vec<NotificationRow> = sqlx::query_as(
+            r#"
+            UPDATE notifications SET next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM notifications
+                WHERE site IS NOT DISTINCT FROM $1 AND status = 'failed' AND next_attempt_at <= $3
+                ORDER BY created_at, id
+                LIMIT $4
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts, attempts, next_attempt_at
+            "#
+        )
+        .bind(&self.site)
+        .bind(now + lease)
+        .bind(now)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error claiming notifications to retry")?;
+        
+        let mut notifications: Vec<NotificationRecord> = rows.into_iter().map(notification_from_row).collect();
+        notifications.sort_by_key(|notification| (notification.created_at, notification.id));
+        Ok(notifications)
+    }
+    
+    /// Store the outcome of a redelivery
+    pub async fn update_notification(&self, notification: &NotificationRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE notifications
+            SET status = $2, error = $3, message_ts = $4, attempts = $5, next_attempt_at = $6
+            WHERE id = $1
+            "#
+        )
+        .bind(notification.id)
+        .bind(notification.status.key())
+        .bind(&notification.error)
+        .bind(&notification.message_ts)
+        .bind(notification.attempts as i32)
+        .bind(notification.next_attempt_at)
+        .execute(&self.pool)
+        .await
+        .context("Error updating notification")?;
+        Ok(())
+    }
+    
+    /// Known sensors, sorted by id
+    pub async fn sensors(&self) -> Result<Vec<SensorInfo>> {
+        let rows: Vec<SensorRow> = sqlx::query_as(
+            "SELECT sensor_id, location, site, sensor_type, unit FROM sensors ORDER BY sensor_id"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Error listing sensors")?;
+        
+        Ok(rows.into_iter().map(sensor_from_row).collect())
+    }
+    
+    /// Pool readings over `[from, to)` of every pool, oldest first
+    pub async fn pool_readings(&self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<Vec<PoolReading>> {
+        let query = match self.layout {
+            StorageLayout::Domain => r#"
+                SELECT pool_id, timestamp, temperature::DOUBLE PRECISION, ph::DOUBLE PRECISION, orp
+                FROM pool_readings
+                WHERE site IS NOT DISTINCT FROM $1
+                  AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                  AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+                ORDER BY timestamp, pool_id
+                "#,
+            StorageLayout::Generic => r#"
+                SELECT SUBSTRING(sensor_id FROM 6) AS pool_id, timestamp,
+                       MAX(CASE WHEN metric = 'temperature' THEN value END),
+                       MAX(CASE WHEN metric = 'ph' THEN value END),
+                       MAX(CASE WHEN metric = 'orp' THEN value END)::INTEGER
+                FROM readings
+                WHERE sensor_id LIKE 'pool/%' AND site IS NOT DISTINCT FROM $1
+                  AND ($2::TIMESTAMPTZ IS NULL OR timestamp >= $2)
+                  AND ($3::TIMESTAMPTZ IS NULL OR timestamp < $3)
+                GROUP BY sensor_id, timestamp
+                ORDER BY timestamp, pool_id
+                "#,
+        };
+        
+        let rows: Vec<PoolRow> = sqlx::query_as(query)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading pool readings")?;
+        
+        Ok(rows.into_iter().map(pool_from_row).collect())
+    }
+    
+    /// One page of the temperature readings of a sensor over `[from, to)`
+    pub async fn get_readings_range(&self, range: &ReadingRange) -> Result<ReadingPage> {
+        let (direction, after) = range.order.sql();
+        let query = match self.layout {
+            StorageLayout::Domain => format!(
+                r#"
+                SELECT sensor_id, timestamp, temperature, humidity, location FROM temperature_readings
+                WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2
+                  AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                  AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+                  AND ($5::TIMESTAMPTZ IS NULL OR timestamp {after} $5)
+                ORDER BY timestamp {direction}
+                LIMIT $6
+                "#
+            ),
+            StorageLayout::Generic => format!(
+                r#"
+                SELECT sensor_id, timestamp,
+                       MAX(CASE WHEN metric = 'temperature' THEN value END) AS temperature,
+                       MAX(CASE WHEN metric = 'humidity' THEN value END) AS humidity,
+                       NULL::VARCHAR AS location
+                FROM readings
+                WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2
+                  AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                  AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+                  AND ($5::TIMESTAMPTZ IS NULL OR timestamp {after} $5)
+                GROUP BY sensor_id, timestamp
+                HAVING MAX(CASE WHEN metric = 'temperature' THEN value END) IS NOT NULL
+                ORDER BY timestamp {direction}
+                LIMIT $6
+                "#
+            ),
+        };
+        
+        let page_size = range.page_size();
+        let rows: Vec<ReadingRow> = sqlx::query_as(&query)
+            .bind(&range.sensor_id)
+            .bind(&self.site)
+            .bind(range.from)
+            .bind(range.to)
+            .bind(range.after)
+            .bind(page_size as i64 + 1)
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading sensor readings")?;
+        
+        Ok(reading_page(rows, page_size))
+    }
+    
+    /// Min/max/average temperature of a sensor per bucket over `[from, to)`
+    ///
+    /// Uses `time_bucket` with TimescaleDB, `date_trunc` in UTC otherwise (same buckets).
+    pub async fn get_bucketed_readings(&self, sensor_id: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>,
+                                       bucket: Bucket) -> Result<Vec<ReadingBucket>> {
+        let start = if self.timescaledb {
+            format!("time_bucket(INTERVAL '1 {}', timestamp)", bucket.unit())
+        } else {
+            format!("date_trunc('{}', timestamp AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'", bucket.unit())
+        };
+        let (value, table, metric_filter) = match self.layout {
+            StorageLayout::Domain => ("temperature", "temperature_readings", ""),
+            StorageLayout::Generic => ("value", "readings", "AND metric = 'temperature'"),
+        };
+        let query = format!(
+            r#"
+            SELECT {start} AS bucket, COUNT(*)::BIGINT, MIN({value}), MAX({value}), AVG({value})
+            FROM {table}
+            WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2 {metric_filter}
+              AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+            GROUP BY 1
+            ORDER BY 1
+            "#
+        );
+        
+        let rows: Vec<BucketRow> = sqlx::query_as(&query)
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading bucketed readings")?;
+        
+        Ok(rows.into_iter().map(bucket_from_row).collect())
+    }
+    
+    /// First and latest reading of the sensor's current stay in the mold risk zone, None outside it
+    pub async fn mold_risk_streak(&self, sensor_id: &str) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let (table, risk, metric_filter) = match self.layout {
+            StorageLayout::Domain => ("temperature_readings", "mold_risk", ""),
+            StorageLayout::Generic => ("readings", "value", "AND metric = 'mold_risk'"),
+        };
+        // Readings after the latest one outside the risk zone (or without humidity)
+        let query = format!(
+            r#"
+            SELECT MIN(timestamp), MAX(timestamp)
+            FROM {table}
+            WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2 {metric_filter}
+              AND timestamp > COALESCE((
+                  SELECT MAX(timestamp) FROM {table}
+                  WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2 {metric_filter}
+                    AND ({risk} IS NULL OR {risk} < $3)
+              ), '-infinity'::TIMESTAMPTZ)
+            "#
+        );
+        
+        let (since, latest): (Option<DateTime<Utc>>, Option<DateTime<Utc>>) = sqlx::query_as(&query)
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(RISK_THRESHOLD)
+            .fetch_one(&self.pool)
+            .await
+            .context("Error reading mold risk")?;
+        
+        Ok(since.zip(latest))
+    }
+    
+    /// Record the mold alert of a stay in the risk zone, returns false when it was already sent
+    pub async fn claim_mold_alert(&self, sensor_id: &str, since: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO mold_alerts (sensor_id, site, since) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+        )
+        .bind(sensor_id)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .bind(since)
+        .execute(&self.pool)
+        .await
+        .context("Error recording mold alert")?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    /// Keep which email and attachment brought the readings stored by a run
+    pub async fn record_provenance(&self, records: &[ProvenanceRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(StorageError::Unavailable)?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO reading_provenance (sensor_id, source, email_id, file, file_hash, first_timestamp, last_timestamp,
+                                                readings, run_started_at, site)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#
+            )
+            .bind(&record.sensor_id)
+            .bind(&record.source)
+            .bind(&record.email_id)
+            .bind(&record.file)
+            .bind(&record.file_hash)
+            .bind(record.first_timestamp)
+            .bind(record.last_timestamp)
+            .bind(record.readings as i32)
+            .bind(record.run_started_at)
+            .bind(&self.site)
+            .execute(&mut *tx)
+            .await
+            .context("Error recording reading provenance")?;
+        }
+        tx.commit().await.context("Error recording reading provenance")?;
+        Ok(())
+    }
+    
+    /// Provenance of the readings of a sensor overlapping `[from, to]`, oldest run first
+    pub async fn provenance(&self, sensor_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<ProvenanceRecord>> {
+        let rows: Vec<ProvenanceRow> = sqlx::query_as(
+            r#"
+            SELECT sensor_id, source, email_id, file, file_hash, first_timestamp, last_timestamp, readings::BIGINT, run_started_at
+            FROM reading_provenance
+            WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2 AND first_timestamp <= $4 AND last_timestamp >= $3
+            ORDER BY run_started_at, id
+            "#
+        )
+        .bind(sensor_id)
+        .bind(&self.site)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading reading provenance")?;
+        
+        Ok(rows.into_iter().map(provenance_from_row).collect())
+    }
+    
+    /// Delete the readings of a scope in one transaction, rolled back unless `apply`
+    pub async fn delete_readings(&self, scope: &DeleteScope, apply: bool) -> Result<DeletedReadings> {
+        let mut tx = self.pool.begin().await.map_err(StorageError::Unavailable)?;
+        let mut deleted = DeletedReadings::default();
+        
+        let (condition, value) = match scope {
+            DeleteScope::Email(email_id) => ("email_id = $2", email_id.clone()),
+            // Compared as text, never as a LIKE pattern: an empty prefix or a '%' would match every attachment
+            DeleteScope::Attachment(hash) => ("substr(file_hash, 1, length($2::text)) = $2", parse_hash(hash).map_err(anyhow::Error::msg)?),
+            DeleteScope::Sensor { sensor_id, .. } => ("sensor_id = $2", sensor_id.clone()),
+        };
+        let spans: Vec<SpanRow> = match scope {
+            DeleteScope::Sensor { sensor_id, from, to } => vec![(sensor_id.clone(), None, *from, *to)],
+            _ => sqlx::query_as(&format!(
+                "SELECT sensor_id, email_id, first_timestamp, last_timestamp FROM reading_provenance WHERE site IS NOT DISTINCT FROM $1 AND {}",
+                condition
+            ))
+            .bind(&self.site)
+            .bind(&value)
+            .fetch_all(&mut *tx)
+            .await
+            .context("Error reading reading provenance")?,
+        };
+        for (sensor_id, email_id, from, to) in &spans {
+            deleted.add(self.delete_span(&mut tx, sensor_id, email_id.as_deref(), *from, *to).await?);
+        }
+        
+        // Pool readings carry their email, even those stored before their provenance was recorded
+        if let DeleteScope::Email(email_id) = scope {
+            let query = match self.layout {
+                StorageLayout::Domain => "DELETE FROM pool_readings WHERE email_id = $1 AND site IS NOT DISTINCT FROM $2 RETURNING 'pool/' || pool_id, timestamp",
+                StorageLayout::Generic => "DELETE FROM readings WHERE email_id = $1 AND site IS NOT DISTINCT FROM $2 RETURNING sensor_id, timestamp",
+            };
+            deleted.add(sqlx::query_as(query)
+                .bind(email_id)
+                .bind(&self.site)
+                .fetch_all(&mut *tx)
+                .await
+                .context("Error deleting pool readings")?);
+        }
+        
+        let provenance = match scope {
+            DeleteScope::Sensor { from, to, .. } => sqlx::query(
+                "DELETE FROM reading_provenance WHERE site IS NOT DISTINCT FROM $1 AND sensor_id = $2 AND first_timestamp >= $3 AND last_timestamp <= $4"
+            )
+            .bind(&self.site)
+            .bind(&value)
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await,
+            _ => sqlx::query(&format!("DELETE FROM reading_provenance WHERE site IS NOT DISTINCT FROM $1 AND {}", condition))
+                .bind(&self.site)
+                .bind(&value)
+                .execute(&mut *tx)
+                .await,
+        };
+        deleted.provenance = provenance.context("Error deleting reading provenance")?.rows_affected() as usize;
+        
+        if apply {
+            tx.commit().await.context("Error deleting readings")?;
+            storage::record_reading_write();
+        } else {
+            tx.rollback().await.context("Error counting readings to delete")?;
+        }
+        Ok(deleted)
+    }
+    
+    /// Readings of a sensor over `[from, to]`, only those of `email_id` for a pool when given
+    async fn delete_span(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, sensor_id: &str, email_id: Option<&str>,
+                         from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let query = match (self.layout, sensor_id.strip_prefix("pool/")) {
+            (StorageLayout::Domain, Some(pool_id)) => sqlx::query_as(
+                r#"
+                DELETE FROM pool_readings
+                WHERE pool_id = $1 AND site IS NOT DISTINCT FROM $2 AND timestamp BETWEEN $3 AND $4
+                  AND ($5::VARCHAR IS NULL OR email_id = $5)
+                RETURNING 'pool/' || pool_id, timestamp
+                "#
+            )
+            .bind(pool_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .bind(email_id),
+            (StorageLayout::Domain, None) => sqlx::query_as(
+                r#"
+                DELETE FROM temperature_readings
+                WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2 AND timestamp BETWEEN $3 AND $4
+                RETURNING sensor_id, timestamp
+                "#
+            )
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to),
+            // Room readings have no email in the generic table
+            (StorageLayout::Generic, _) => sqlx::query_as(
+                r#"
+                DELETE FROM readings
+                WHERE sensor_id = $1 AND site IS NOT DISTINCT FROM $2 AND timestamp BETWEEN $3 AND $4
+                  AND ($5::VARCHAR IS NULL OR email_id IS NULL OR email_id = $5)
+                RETURNING sensor_id, timestamp
+                "#
+            )
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .bind(email_id),
+        };
+        query.fetch_all(&mut **tx)
+            .await
+            .with_context(|| format!("Error deleting readings of {}", sensor_id))
+    }
+    
+    /// Check whether a reading of this pool from this email is already stored
+    pub async fn pool_reading_exists(&self, pool_id: &str, email_id: &str) -> Result<bool> {
+        let (query, pool_key) = match self.layout {
+            StorageLayout::Domain => (
+                "SELECT EXISTS(SELECT 1 FROM pool_readings WHERE email_id = $1 AND pool_id = $2 AND site IS NOT DISTINCT FROM $3)",
+                pool_id.to_string(),
+            ),
+            StorageLayout::Generic => (
+                "SELECT EXISTS(SELECT 1 FROM readings WHERE email_id = $1 AND sensor_id = $2 AND site IS NOT DISTINCT FROM $3)",
+                format!("pool/{}", pool_id),
+            ),
+        };
+        
+        sqlx::query_scalar::<_, bool>(query)
+        .bind(email_id)
+        .bind(pool_key)
+        .bind(&self.site)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to check for existing pool reading")
+    }
+    
+    /// Save a pool reading to the database
+    /// 
+    /// Returns false when a reading of the same pool from the same email already exists
+    /// (skipped, or rewritten with `with_replace_existing`).
+    pub async fn save_pool_reading(&self, reading: &PoolReading, email_id: &str) -> Result<bool> {
+        debug!("Saving pool reading ({}): temp={:?}°C, pH={:?}, ORP={:?} mV", 
+               reading.pool_id, reading.temperature, reading.ph, reading.orp);
+        
+        // Check if this reading already exists (by pool and email_id)
+        let exists = self.pool_reading_exists(&reading.pool_id, email_id).await?;
+        if exists && !self.replace_existing {
+            info!("Pool reading ({}) from email {} already exists, skipping", reading.pool_id, email_id);
+            return Ok(false);
+        }
+        
+        let mut transaction = self.pool.begin()
+            .await
+            .context("Unable to start transaction")?;
+        if exists {
+            let (query, pool_key) = match self.layout {
+                StorageLayout::Domain => (
+                    "DELETE FROM pool_readings WHERE email_id = $1 AND pool_id = $2 AND site IS NOT DISTINCT FROM $3",
+                    reading.pool_id.clone(),
+                ),
+                StorageLayout::Generic => (
+                    "DELETE FROM readings WHERE email_id = $1 AND sensor_id = $2 AND site IS NOT DISTINCT FROM $3",
+                    reading.sensor_id(),
+                ),
+            };
+            sqlx::query(query)
+                .bind(email_id)
+                .bind(pool_key)
+                .bind(&self.site)
+                .execute(&mut *transaction)
+                .await
+                .context("Failed to replace pool reading")?;
+        }
+        
+        match self.layout {
+            StorageLayout::Domain => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO pool_readings (timestamp, temperature, ph, orp, email_id, pool_id, site)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#
+                )
+                .bind(reading.timestamp)
+                .bind(reading.temperature)
+                .bind(reading.ph)
+                .bind(reading.orp)
+                .bind(email_id)
+                .bind(&reading.pool_id)
+                .bind(&self.site)
+                .execute(&mut *transaction)
+                .await
+                .context("Failed to insert pool reading")?;
+            }
+            StorageLayout::Generic => {
+                for (metric, value) in pool_metrics(reading) {
+                    let metric = metric.key();
+                    sqlx::query(
+                        "INSERT INTO readings (sensor_id, timestamp, metric, value, site, email_id) VALUES ($1, $2, $3, $4, $5, $6)"
+                    )
+                    .bind(reading.sensor_id())
+                    .bind(reading.timestamp)
+                    .bind(metric)
+                    .bind(value)
+                    .bind(&self.site)
+                    .bind(email_id)
+                    .execute(&mut *transaction)
+                    .await
+                    .context("Failed to insert pool reading")?;
+                }
+            }
+        }
+        transaction.commit()
+            .await
+            .context("Error committing transaction")?;
+        storage::record_reading_write();
+        
+        info!("✅ Pool reading {} ({}): temp={:?}°C, pH={:?}, ORP={:?} mV", 
+              if exists { "replaced" } else { "saved" }, reading.pool_id, reading.temperature, reading.ph, reading.orp);
+        
+        Ok(!exists)
+    }
+    
+}
+
+impl Storage for Database {
+    fn save_temperature_readings<'a>(&'a self, readings: &'a [TemperatureReading]) -> StorageFuture<'a, SaveStats> {
+        Box::pin(Database::save_temperature_readings(self, readings))
+    }
+    
+    fn save_pool_reading<'a>(&'a self, reading: &'a PoolReading, email_id: &'a str) -> StorageFuture<'a, bool> {
+        Box::pin(Database::save_pool_reading(self, reading, email_id))
+    }
+    
+    fn diff_temperature_readings<'a>(&'a self, readings: &'a [TemperatureReading]) -> StorageFuture<'a, SaveStats> {
+        Box::pin(Database::diff_temperature_readings(self, readings))
+    }
+    
+    fn pool_reading_exists<'a>(&'a self, pool_id: &'a str, email_id: &'a str) -> StorageFuture<'a, bool> {
+        Box::pin(Database::pool_reading_exists(self, pool_id, email_id))
+    }
+    
+    fn register_sensor<'a>(&'a self, sensor_id: &'a str, sensor_type: SensorType) -> StorageFuture<'a, ()> {
+        Box::pin(Database::register_sensor(self, sensor_id, sensor_type))
+    }
+    
+    fn save_rejected_readings<'a>(&'a self, rejected: &'a [RejectedReading]) -> StorageFuture<'a, usize> {
+        Box::pin(Database::save_rejected_readings(self, rejected))
+    }
+    
+    fn save_failed_extraction<'a>(&'a self, failure: &'a FailedExtraction) -> StorageFuture<'a, ()> {
+        Box::pin(Database::save_failed_extraction(self, failure))
+    }
+    
+    fn email_retries<'a>(&'a self, source: &'a str) -> StorageFuture<'a, Vec<EmailRetry>> {
+        Box::pin(Database::email_retries(self, source))
+    }
+    
+    fn schedule_email_retry<'a>(&'a self, retry: &'a EmailRetry) -> StorageFuture<'a, ()> {
+        Box::pin(Database::schedule_email_retry(self, retry))
+    }
+    
+    fn clear_email_retry<'a>(&'a self, source: &'a str, email_id: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(Database::clear_email_retry(self, source, email_id))
+    }
+    
+    fn dead_letters<'a>(&'a self) -> StorageFuture<'a, Vec<EmailRetry>> {
+        Box::pin(Database::dead_letters(self))
+    }
+    
+    fn requeue_email<'a>(&'a self, email_id: &'a str) -> StorageFuture<'a, Option<EmailRetry>> {
+        Box::pin(Database::requeue_email(self, email_id))
+    }
+    
+    fn record_source_run<'a>(&'a self, source: &'a str, found_emails: bool) -> StorageFuture<'a, SourceActivity> {
+        Box::pin(Database::record_source_run(self, source, found_emails))
+    }
+    
+    fn source_activities<'a>(&'a self) -> StorageFuture<'a, Vec<SourceActivity>> {
+        Box::pin(Database::source_activities(self))
+    }
+    
+    fn mark_source_alerted<'a>(&'a self, source: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(Database::mark_source_alerted(self, source))
+    }
+    
+    fn record_processed_email<'a>(&'a self, source: &'a str, email_id: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(Database::record_processed_email(self, source, email_id))
+    }
+    
+    fn processed_emails<'a>(&'a self, source: &'a str) -> StorageFuture<'a, HashSet<String>> {
+        Box::pin(Database::processed_emails(self, source))
+    }
+    
+    fn batch_checkpoint<'a>(&'a self, source: &'a str) -> StorageFuture<'a, Option<BatchCheckpoint>> {
+        Box::pin(Database::batch_checkpoint(self, source))
+    }
+    
+    fn save_batch_checkpoint<'a>(&'a self, checkpoint: &'a BatchCheckpoint) -> StorageFuture<'a, ()> {
+        Box::pin(Database::save_batch_checkpoint(self, checkpoint))
+    }
+    
+    fn clear_batch_checkpoint<'a>(&'a self, source: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(Database::clear_batch_checkpoint(self, source))
+    }
+    
+    fn record_api_calls<'a>(&'a self, source: &'a str, calls: u64) -> StorageFuture<'a, u64> {
+        Box::pin(Database::record_api_calls(self, source, calls))
+    }
+    
+    fn api_usage<'a>(&'a self, days: u32) -> StorageFuture<'a, Vec<ApiUsage>> {
+        Box::pin(Database::api_usage(self, days))
+    }
+    
+    fn record_notification<'a>(&'a self, notification: &'a NotificationRecord) -> StorageFuture<'a, i64> {
+        Box::pin(Database::record_notification(self, notification))
+    }
+    
+    fn notifications<'a>(&'a self, failed_only: bool, limit: usize) -> StorageFuture<'a, Vec<NotificationRecord>> {
+        Box::pin(Database::notifications(self, failed_only, limit))
+    }
+    
+    fn claim_notifications<'a>(&'a self, now: DateTime<Utc>, lease: TimeDelta, limit: usize) -> StorageFuture<'a, Vec<NotificationRecord>> {
+        Box::pin(Database::claim_notifications(self, now, lease, limit))
+    }
+    
+    fn update_notification<'a>(&'a self, notification: &'a NotificationRecord) -> StorageFuture<'a, ()> {
+        Box::pin(Database::update_notification(self, notification))
+    }
+
+    fn sensors<'a>(&'a self) -> StorageFuture<'a, Vec<SensorInfo>> {
+        Box::pin(Database::sensors(self))
+    }
+    
+    fn pool_readings<'a>(&'a self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> StorageFuture<'a, Vec<PoolReading>> {
+        Box::pin(Database::pool_readings(self, from, to))
+    }
+    
+    fn get_readings_range<'a>(&'a self, range: &'a ReadingRange) -> StorageFuture<'a, ReadingPage> {
+        Box::pin(Database::get_readings_range(self, range))
+    }
+
+    fn get_bucketed_readings<'a>(&'a self, sensor_id: &'a str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>,
+                                 bucket: Bucket) -> StorageFuture<'a, Vec<ReadingBucket>> {
+        Box::pin(Database::get_bucketed_readings(self, sensor_id, from, to, bucket))
+    }
+    
+    fn mold_risk_streak<'a>(&'a self, sensor_id: &'a str) -> StorageFuture<'a, Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        Box::pin(Database::mold_risk_streak(self, sensor_id))
+    }
+    
+    fn claim_mold_alert<'a>(&'a self, sensor_id: &'a str, since: DateTime<Utc>) -> StorageFuture<'a, bool> {
+        Box::pin(Database::claim_mold_alert(self, sensor_id, since))
+    }
+    
+    fn record_provenance<'a>(&'a self, records: &'a [ProvenanceRecord]) -> StorageFuture<'a, ()> {
+        Box::pin(Database::record_provenance(self, records))
+    }
+    
+    fn provenance<'a>(&'a self, sensor_id: &'a str, from: DateTime<Utc>, to: DateTime<Utc>) -> StorageFuture<'a, Vec<ProvenanceRecord>> {
+        Box::pin(Database::provenance(self, sensor_id, from, to))
+    }
+    
+    fn delete_readings<'a>(&'a self, scope: &'a DeleteScope, apply: bool) -> StorageFuture<'a, DeletedReadings> {
+        Box::pin(Database::delete_readings(self, scope, apply))
+    }
+}
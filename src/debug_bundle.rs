@@ -0,0 +1,263 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::database::{Database, SCHEMA_VERSION};
+use crate::logging::LogConfig;
+use crate::redact::{redact, MASK};
+use crate::storage::{ApiUsage, EmailRetry, NotificationRecord, SourceActivity, Storage};
+
+/// Days of Gmail API usage and runs put in the bundle
+const USAGE_DAYS: u32 = 14;
+
+/// Failed notifications put in the bundle
+const NOTIFICATIONS: usize = 20;
+
+/// Last lines of `LOG_FILE` put in the bundle
+const LOG_LINES: usize = 500;
+
+/// One file of the support archive, already redacted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleFile {
+    /// Path inside the archive
+    pub name: String,
+    pub content: String,
+}
+
+impl BundleFile {
+    fn new(name: String, content: &str) -> Self {
+        BundleFile { name, content: redact(content).into_owned() }
+    }
+}
+
+/// Gather what a bug report needs (`homemetrics debug-bundle`)
+///
+/// Each profile gets its effective configuration, the schema version, the
+/// recent runs and the last errors; the environment and the end of
+/// `LOG_FILE` are shared. A database that cannot be read is reported in the
+/// bundle instead of failing it. Every file is redacted.
+pub async fn collect(profiles: &[Config], log: &LogConfig, config_file: Option<&Path>) -> Vec<BundleFile> {
+    let mut files = vec![BundleFile::new("environment.txt".to_string(), &render_environment(profiles, log, config_file, Utc::now()))];
+    for config in profiles {
+        let dir = config.profile.as_ref().map_or(String::new(), |profile| format!("{}/", profile));
+        files.push(BundleFile::new(format!("{}config.txt", dir), &render_config(config)));
+
+        let (database, state) = match Database::connect_read_only(&config.database).await {
+            Ok(database) => {
+                let database = database.with_site(config.site.clone());
+                (render_database(Ok(())), render_state(&database, config).await)
+            }
+            Err(e) => {
+                let state = format!("Not read: {:#}\n", e);
+                (render_database(Err(e)), state)
+            }
+        };
+        files.push(BundleFile::new(format!("{}database.txt", dir), &database));
+        files.push(BundleFile::new(format!("{}state.txt", dir), &state));
+    }
+    if let Some(path) = &log.file {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => tail(&content, LOG_LINES),
+            Err(e) => format!("Unable to read {}: {}\n", path.display(), e),
+        };
+        files.push(BundleFile::new("log-tail.txt".to_string(), &content));
+    }
+    files
+}
+
+/// `homemetrics-debug-<YYYYMMDD-HHMMSS>.tar.gz` in the current directory
+pub fn default_path(now: DateTime<Local>) -> PathBuf {
+    PathBuf::from(format!("homemetrics-debug-{}.tar.gz", now.format("%Y%m%d-%H%M%S")))
+}
+
+/// Write the files under a `homemetrics-debug/` directory of a gzipped tar archive, with the tar command
+pub fn write_archive(files: &[BundleFile], output: &Path) -> Result<()> {
+    let staging = tempfile::tempdir().context("Unable to create a temporary directory")?;
+    let root = staging.path().join("homemetrics-debug");
+    for file in files {
+        let path = root.join(&file.name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, &file.content).with_context(|| format!("Unable to write {}", path.display()))?;
+    }
+
+    let output = std::path::absolute(output).with_context(|| format!("Invalid output path {}", output.display()))?;
+    let status = match std::process::Command::new("tar")
+        .arg("-czf").arg(&output)
+        .arg("-C").arg(staging.path())
+        .arg("homemetrics-debug")
+        .status()
+    {
+        Ok(status) => status,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("The debug bundle needs the tar command in the PATH")
+        }
+        Err(e) => return Err(e).context("Unable to run tar"),
+    };
+    if !status.success() {
+        anyhow::bail!("tar failed to write {} ({})", output.display(), status);
+    }
+    Ok(())
+}
+
+fn render_environment(profiles: &[Config], log: &LogConfig, config_file: Option<&Path>, now: DateTime<Utc>) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "homemetrics {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(text, "Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(text, "Generated: {} (local offset {})", now.format("%Y-%m-%d %H:%M:%S UTC"), Local::now().format("%:z"));
+    let _ = writeln!(text, "Schema version of this binary: {}", SCHEMA_VERSION);
+    let _ = writeln!(text, "Configuration file: {}", config_file.map_or("none".to_string(), |path| path.display().to_string()));
+    let names: Vec<&str> = profiles.iter().map(|config| config.profile.as_deref().unwrap_or("default")).collect();
+    let _ = writeln!(text, "Profiles: {}", names.join(", "));
+    let _ = writeln!(text, "Logging: {:?}, file {}", log.backend, log.file.as_ref().map_or("none".to_string(), |path| path.display().to_string()));
+    for name in ["RUST_LOG", "TZ", "INVOCATION_ID"] {
+        let _ = writeln!(text, "{}: {}", name, std::env::var(name).as_deref().unwrap_or("unset"));
+    }
+    text
+}
+
+/// Effective configuration of a profile, passwords, tokens and webhook URLs masked
+pub fn render_config(config: &Config) -> String {
+    let mut config = config.clone();
+    config.database.password = MASK.to_string();
+    if let Some(slack) = config.slack.as_mut() {
+        slack.bot_token = MASK.to_string();
+    }
+    if let Some(token) = config.server.ingest_token.as_mut() {
+        *token = MASK.to_string();
+    }
+    for webhook in config.alerts.rules.iter_mut().filter_map(|rule| rule.webhook.as_mut()) {
+        *webhook = MASK.to_string();
+    }
+    format!("{:#?}\n", config)
+}
+
+fn render_database(connected: Result<()>) -> String {
+    match connected {
+        Ok(()) => format!("Schema version: {} (up to date)\n", SCHEMA_VERSION),
+        // SchemaMismatch says which version was found
+        Err(e) => format!("Schema version expected: {}\nConnection or schema check failed: {:#}\n", SCHEMA_VERSION, e),
+    }
+}
+
+async fn render_state(storage: &dyn Storage, config: &Config) -> String {
+    let mut text = String::new();
+    match storage.source_activities().await {
+        Ok(activities) => text.push_str(&render_runs(&activities)),
+        Err(e) => { let _ = writeln!(text, "Runs not read: {:#}", e); }
+    }
+    match storage.api_usage(USAGE_DAYS).await {
+        Ok(usage) => text.push_str(&render_usage(&usage)),
+        Err(e) => { let _ = writeln!(text, "API usage not read: {:#}", e); }
+    }
+
+    // Dead-lettered emails included
+    let mut retries = Vec::new();
+    for source in &config.pipeline.sources {
+        match storage.email_retries(source.key()).await {
+            Ok(pending) => retries.extend(pending),
+            Err(e) => { let _ = writeln!(text, "Retries of {} not read: {:#}", source.key(), e); }
+        }
+    }
+    let notifications = storage.notifications(true, NOTIFICATIONS).await.unwrap_or_else(|e| {
+        let _ = writeln!(text, "Notifications not read: {:#}", e);
+        Vec::new()
+    });
+    text.push_str(&render_errors(&retries, &notifications));
+    text
+}
+
+fn render_runs(activities: &[SourceActivity]) -> String {
+    let mut text = "\nRecent runs\n".to_string();
+    if activities.is_empty() {
+        text.push_str("  none recorded\n");
+    }
+    let format = |at: DateTime<Utc>| at.format("%Y-%m-%d %H:%M UTC").to_string();
+    for activity in activities {
+        let _ = writeln!(text, "  {}: last run {}, last email {}, {} empty run(s) since{}",
+                         activity.source, format(activity.last_run_at), format(activity.last_email_at), activity.empty_runs,
+                         activity.alerted_at.map_or(String::new(), |at| format!(", alerted {}", format(at))));
+    }
+    text
+}
+
+fn render_usage(usage: &[ApiUsage]) -> String {
+    let mut text = format!("\nGmail API usage ({} days)\n", USAGE_DAYS);
+    if usage.is_empty() {
+        text.push_str("  none recorded\n");
+    }
+    for day in usage {
+        let _ = writeln!(text, "  {} {}: {} request(s) in {} run(s)", day.day, day.source, day.calls, day.runs);
+    }
+    text
+}
+
+/// Emails waiting for a retry or dead-lettered, and failed notifications
+pub fn render_errors(retries: &[EmailRetry], notifications: &[NotificationRecord]) -> String {
+    let format = |at: DateTime<Utc>| at.format("%Y-%m-%d %H:%M UTC").to_string();
+    let mut text = "\nLast errors\n".to_string();
+    if retries.is_empty() && notifications.is_empty() {
+        text.push_str("  none recorded\n");
+    }
+    for retry in retries {
+        let state = match retry.dead_lettered_at {
+            Some(at) => format!("dead-lettered {}", format(at)),
+            None => format!("next attempt {}", format(retry.next_attempt_at)),
+        };
+        let _ = writeln!(text, "  {} email {} ({} attempt(s), {}): {}", retry.source, retry.email_id, retry.attempts, state, retry.last_error);
+    }
+    for notification in notifications {
+        let _ = writeln!(text, "  {} notification {} to {} ({} attempt(s)): {}", format(notification.created_at), notification.kind,
+                         notification.channel, notification.attempts, notification.error.as_deref().unwrap_or("-"));
+    }
+    text
+}
+
+/// The last `count` lines of a text
+fn tail(content: &str, count: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut text = lines[lines.len().saturating_sub(count)..].join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_errors() {
+        let at = Utc.with_ymd_and_hms(2024, 5, 1, 14, 0, 0).unwrap();
+        let retries = vec![
+            EmailRetry {
+                source: "xsense".to_string(),
+                email_id: "18c2".to_string(),
+                attempts: 2,
+                next_attempt_at: at,
+                last_error: "connection to postgres://homemetrics:s3cr3t@db/metrics refused".to_string(),
+                dead_lettered_at: None,
+            },
+            EmailRetry {
+                source: "blueriot".to_string(),
+                email_id: "18c3".to_string(),
+                attempts: 5,
+                next_attempt_at: at,
+                last_error: "no readings".to_string(),
+                dead_lettered_at: Some(at),
+            },
+        ];
+        let text = BundleFile::new("state.txt".to_string(), &render_errors(&retries, &[])).content;
+        assert_eq!(text, "
+Last errors
+  xsense email 18c2 (2 attempt(s), next attempt 2024-05-01 14:00 UTC): connection to postgres://homemetrics:***@db/metrics refused
+  blueriot email 18c3 (5 attempt(s), dead-lettered 2024-05-01 14:00 UTC): no readings
+");
+        assert!(render_errors(&[], &[]).ends_with("none recorded\n"));
+        assert_eq!(tail("a\nb\nc", 2), "b\nc\n");
+    }
+}
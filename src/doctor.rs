@@ -0,0 +1,132 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::auth;
+use crate::config::Config;
+use crate::database::Database;
+use crate::gmail_client::GmailClient;
+use crate::i18n::Locale;
+use crate::redact::redact;
+use crate::slack_notifier::SlackNotifier;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Failed, with the error
+    Fail(String),
+    /// Not run, with the reason
+    Skip(String),
+}
+
+/// One line of the `homemetrics doctor` report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+}
+
+impl CheckResult {
+    fn new(name: String, result: Result<()>) -> Self {
+        let status = match result {
+            Ok(()) => CheckStatus::Pass,
+            Err(e) => CheckStatus::Fail(redact(&format!("{:#}", e)).into_owned()),
+        };
+        CheckResult { name, status }
+    }
+
+    fn skip(name: String, reason: &str) -> Self {
+        CheckResult { name, status: CheckStatus::Skip(reason.to_string()) }
+    }
+
+    pub fn failed(&self) -> bool {
+        matches!(self.status, CheckStatus::Fail(_))
+    }
+}
+
+/// Check the services of a profile for real, unlike `homemetrics check`
+///
+/// The OAuth2 token is refreshed, the todo and done labels of each enabled
+/// source are looked up, a row is written to the database and rolled back,
+/// and a test message is posted to the default Slack channel.
+pub async fn run_checks(config: &Config) -> Vec<CheckResult> {
+    let locale = config.locale;
+    let mut results = Vec::new();
+
+    // Without a cached token the authenticator would start the consent flow
+    let (gmail, oauth) = match gmail_client(config).await {
+        Ok(gmail) => (Some(gmail), Ok(())),
+        Err(e) => (None, Err(e)),
+    };
+    results.push(CheckResult::new(locale.doctor_oauth(), oauth));
+    for source in &config.pipeline.sources {
+        for state in ["todo", "done"] {
+            let label = format!("{}/{}/{}", config.gmail.label_prefix, state, source.key());
+            let name = locale.doctor_label(&label);
+            results.push(match &gmail {
+                Some(gmail) if gmail.label_exists(&label).await => CheckResult::new(name, Ok(())),
+                Some(_) => CheckResult::new(name, Err(anyhow::anyhow!(locale.doctor_label_missing()))),
+                None => CheckResult::skip(name, locale.doctor_skipped_oauth()),
+            });
+        }
+    }
+
+    let database = async {
+        let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+        database.check_write().await
+    };
+    results.push(CheckResult::new(locale.doctor_database(), database.await));
+
+    results.push(match &config.slack {
+        Some(slack) => {
+            let name = locale.doctor_slack(&slack.channel_id);
+            let sent = async {
+                SlackNotifier::new(slack)?.send_test(locale.doctor_slack_message()).await
+            };
+            CheckResult::new(name, sent.await)
+        }
+        None => CheckResult::skip(locale.doctor_slack("-"), locale.doctor_skipped_slack()),
+    });
+    results
+}
+
+async fn gmail_client(config: &Config) -> Result<GmailClient> {
+    let path = &config.gmail.token_cache_path;
+    if auth::read_token_cache(Path::new(path))?.is_empty() {
+        anyhow::bail!("No cached token in {} (run `homemetrics auth login`)", path);
+    }
+    let gmail = GmailClient::new(&config.gmail).await?;
+    gmail.refresh_token().await?;
+    Ok(gmail)
+}
+
+/// One line per check, then the number of failures
+pub fn render_doctor(results: &[CheckResult], locale: Locale) -> String {
+    let mut lines: Vec<String> = results.iter().map(|result| match &result.status {
+        CheckStatus::Pass => format!("✅ {}", result.name),
+        CheckStatus::Fail(error) => format!("❌ {}: {}", result.name, error),
+        CheckStatus::Skip(reason) => format!("⏭️  {}: {}", result.name, reason),
+    }).collect();
+    lines.push(locale.doctor_status(results.iter().filter(|result| result.failed()).count()));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_doctor() {
+        let results = vec![
+            CheckResult::new("OAuth2".to_string(), Ok(())),
+            CheckResult::new("Database".to_string(), Err(anyhow::anyhow!("connection refused"))),
+            CheckResult::skip("Slack".to_string(), "not configured"),
+        ];
+        assert_eq!(results.iter().filter(|result| result.failed()).count(), 1);
+        assert_eq!(render_doctor(&results, Locale::En), "\
+✅ OAuth2
+❌ Database: connection refused
+⏭️  Slack: not configured
+💔 1 check(s) failed");
+        assert!(render_doctor(&results[..1], Locale::En).ends_with("💚 All checks passed"));
+    }
+}
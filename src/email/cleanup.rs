@@ -0,0 +1,51 @@
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::config::{CleanupAction, Config};
+use crate::gmail_client::GmailClient;
+
+/// Sources whose processed emails are cleaned up
+const SOURCES: [&str; 2] = ["xsense", "blueriot"];
+
+/// Trash (or unlabel) processed emails older than `CLEANUP_AFTER_DAYS`
+///
+/// Only emails carrying a done label are considered. Nothing is done when the
+/// clean-up is disabled or labels must stay untouched (read-only access,
+/// `--no-label-changes`). Returns the number of emails cleaned up.
+pub async fn cleanup_processed_emails(config: &Config, gmail: &GmailClient) -> Result<usize> {
+    let cleanup = &config.cleanup;
+    if cleanup.after_days == 0 || !config.gmail.modifies_labels() {
+        return Ok(0);
+    }
+    
+    let mut cleaned = 0;
+    for source in SOURCES {
+        let message_ids = gmail.search_done_emails(source, cleanup.after_days).await?;
+        if message_ids.is_empty() {
+            continue;
+        }
+        
+        let count = match cleanup.action {
+            CleanupAction::Trash => {
+                let mut trashed = 0;
+                for message_id in &message_ids {
+                    match gmail.trash_email(message_id).await {
+                        Ok(()) => trashed += 1,
+                        Err(e) => warn!("Failed to move email {} to the trash: {}", message_id, e),
+                    }
+                }
+                trashed
+            }
+            CleanupAction::Unlabel => {
+                gmail.remove_label(&message_ids, &format!("done/{}", source)).await?;
+                message_ids.len()
+            }
+        };
+        
+        info!("🧹 {} processed {} email(s) older than {} days cleaned up ({:?})", 
+              count, source, cleanup.after_days, cleanup.action);
+        cleaned += count;
+    }
+    
+    Ok(cleaned)
+}
@@ -0,0 +1,605 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+
+use crate::blueriot::PoolReading;
+use crate::error::ErrorClass;
+use crate::i18n::Locale;
+use crate::mold::mold_risk;
+use crate::xsense::TemperatureReading;
+
+/// Content of an email that cannot be turned into readings
+///
+/// Always permanent: the same email fails the same way until the extractors change.
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    /// The raw message cannot be parsed
+    #[error("Unable to parse email: {0}")]
+    Email(String),
+    /// Every attachment failed to parse
+    #[error("All attachments failed: {0}")]
+    Attachments(String),
+    /// Nothing recognized in the email text
+    #[error("{0}")]
+    NoData(String),
+}
+
+impl ExtractError {
+    pub fn class(&self) -> ErrorClass {
+        ErrorClass::Permanent
+    }
+}
+
+/// Readings written for one sensor
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SensorCounts {
+    pub inserted: usize,
+    pub duplicates: usize,
+    /// Readings refused by validation (stored in `rejected_readings`)
+    pub rejected: usize,
+}
+
+impl SensorCounts {
+    pub fn add(&mut self, other: &SensorCounts) {
+        self.inserted += other.inserted;
+        self.duplicates += other.duplicates;
+        self.rejected += other.rejected;
+    }
+}
+
+/// Merge per-sensor counts into an accumulator
+pub fn merge_sensor_counts(target: &mut BTreeMap<String, SensorCounts>, source: &BTreeMap<String, SensorCounts>) {
+    for (sensor, counts) in source {
+        target.entry(sensor.clone()).or_default().add(counts);
+    }
+}
+
+/// Order in which found emails are processed (`PROCESSING_ORDER`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessingOrder {
+    /// Oldest first, so `--limit` works through a backlog chronologically
+    #[default]
+    Oldest,
+    /// Newest first (Gmail search order, no extra API call)
+    Newest,
+}
+
+impl ProcessingOrder {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "oldest" | "oldest-first" | "asc" => Some(ProcessingOrder::Oldest),
+            "newest" | "newest-first" | "desc" => Some(ProcessingOrder::Newest),
+            _ => None,
+        }
+    }
+}
+
+/// Amount of per-email output printed in dry-run (`--dry-run-detail`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DryRunDetail {
+    /// Only the end-of-run table, one line per email
+    Summary,
+    /// Headers, attachments and first/last reading of each sensor
+    #[default]
+    Normal,
+    /// Everything, including every reading and the whole email text
+    Full,
+    /// No text at all: headers and extracted readings are kept in the run
+    /// summary, printed as one JSON document (`fetch --dry-run --output json`)
+    #[value(skip)]
+    Json,
+}
+
+impl DryRunDetail {
+    /// Whether each email is detailed as it is analyzed
+    pub fn per_email(&self) -> bool {
+        matches!(self, DryRunDetail::Normal | DryRunDetail::Full)
+    }
+
+    /// Whether the run banner and totals are printed
+    pub fn prints_text(&self) -> bool {
+        *self != DryRunDetail::Json
+    }
+
+    /// Whether extracted readings are kept in the run summary
+    pub fn keeps_readings(&self) -> bool {
+        *self == DryRunDetail::Json
+    }
+}
+
+/// Bounds applied to one processor run (`--limit`, `--max-duration`, `--max-api-calls`, `--max-db-rows`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunLimits {
+    pub max_emails: Option<usize>,
+    /// No new email is started once this much time has elapsed
+    pub max_duration: Option<Duration>,
+    /// No new email is started once this many Gmail API requests were issued
+    pub max_api_calls: Option<usize>,
+    /// No new email is started once this many rows were inserted
+    pub max_db_rows: Option<usize>,
+}
+
+impl RunLimits {
+    /// First budget spent by the run so far, if any
+    pub fn exceeded(&self, elapsed: Duration, api_calls: usize, db_rows: usize) -> Option<RunBudget> {
+        if self.max_duration.is_some_and(|max| elapsed >= max) {
+            Some(RunBudget::Duration)
+        } else if self.max_api_calls.is_some_and(|max| api_calls >= max) {
+            Some(RunBudget::ApiCalls)
+        } else if self.max_db_rows.is_some_and(|max| db_rows >= max) {
+            Some(RunBudget::DbRows)
+        } else {
+            None
+        }
+    }
+}
+
+/// Budget that stopped a run before all its emails were processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunBudget {
+    Duration,
+    ApiCalls,
+    DbRows,
+    /// `BATCH_SIZE` emails were examined, the next run continues after the checkpoint
+    Batch,
+    /// Gmail refused a request for quota, or `GMAIL_DAILY_CALL_LIMIT` was reached
+    Quota,
+}
+
+/// Result of processing a single email by a strategy
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessingResult {
+    /// Number of records extracted (0 means nothing usable, email left untouched)
+    pub records: usize,
+    /// Per-sensor insert/duplicate counts (empty in dry-run without database)
+    pub sensors: BTreeMap<String, SensorCounts>,
+    /// Per-attachment detail, in email order
+    pub attachments: Vec<AttachmentResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<EmailHeaders>,
+    /// Valid readings extracted: shown by the JSON dry-run output, their
+    /// provenance recorded by real runs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub readings: Vec<Reading>,
+}
+
+impl ProcessingResult {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Attachments that could not be extracted or saved (email kept for retry)
+    pub fn failed_attachments(&self) -> impl Iterator<Item = &AttachmentResult> {
+        self.attachments.iter().filter(|attachment| attachment.error.is_some())
+    }
+
+    /// Failed attachments as "file: error; file: error", for statuses and notifications
+    pub fn failures_summary(&self) -> String {
+        self.failed_attachments()
+            .map(|attachment| format!("{}: {}", attachment.filename, attachment.error.as_deref().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Quantity measured by a reading, the `metric` column of the generic layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// °C
+    Temperature,
+    /// % relative humidity
+    Humidity,
+    /// 0-100 score derived from temperature and humidity
+    MoldRisk,
+    Ph,
+    /// mV
+    Orp,
+}
+
+impl Metric {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Humidity => "humidity",
+            Metric::MoldRisk => "mold_risk",
+            Metric::Ph => "ph",
+            Metric::Orp => "orp",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [Metric::Temperature, Metric::Humidity, Metric::MoldRisk, Metric::Ph, Metric::Orp]
+            .into_iter()
+            .find(|metric| metric.key().eq_ignore_ascii_case(value))
+    }
+}
+
+/// Metrics of one reading, in `Metric` order
+pub type MetricSet = BTreeMap<Metric, f64>;
+
+/// Metrics of a room reading: temperature, then humidity and mold risk when known
+pub fn temperature_metrics(reading: &TemperatureReading) -> MetricSet {
+    let mut metrics = MetricSet::from([(Metric::Temperature, reading.temperature)]);
+    if let Some(humidity) = reading.humidity {
+        metrics.insert(Metric::Humidity, humidity);
+    }
+    if let Some(risk) = mold_risk(reading) {
+        metrics.insert(Metric::MoldRisk, risk);
+    }
+    metrics
+}
+
+/// Metrics of a pool reading, those found in the report only
+pub fn pool_metrics(reading: &PoolReading) -> MetricSet {
+    [
+        (Metric::Temperature, reading.temperature),
+        (Metric::Ph, reading.ph),
+        (Metric::Orp, reading.orp.map(f64::from)),
+    ]
+    .into_iter()
+    .filter_map(|(metric, value)| value.map(|value| (metric, value)))
+    .collect()
+}
+
+/// Where a reading was read from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    /// "xsense", "blueriot" or "ingest"
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_id: Option<String>,
+    /// Attachment or dropped file holding the reading
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// SHA-256 of the file content, lowercase hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_hash: Option<String>,
+}
+
+impl Provenance {
+    pub fn email(source: &str, email_id: &str, file: Option<&str>) -> Self {
+        Provenance {
+            source: source.to_string(),
+            email_id: Some(email_id.to_string()),
+            file: file.map(str::to_string),
+            file_hash: None,
+        }
+    }
+
+    /// Identify the file by its content, which survives renames and repeated names
+    pub fn with_content(mut self, content: &[u8]) -> Self {
+        self.file_hash = Some(format!("{:x}", Sha256::digest(content)));
+        self
+    }
+}
+
+/// A timestamped set of metrics of one sensor, whatever its source
+///
+/// Extractors keep their source types (`TemperatureReading` for rooms,
+/// `PoolReading` for pools) and convert to this model wherever readings of
+/// every source are handled alike: the generic storage layout and the JSON
+/// dry-run output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reading {
+    /// Room sensor ID, or `pool/<id>` for a pool
+    pub sensor_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub metrics: MetricSet,
+    pub provenance: Provenance,
+}
+
+impl Reading {
+    pub fn from_temperature(reading: &TemperatureReading, site: Option<&str>, provenance: Provenance) -> Self {
+        Reading {
+            sensor_id: reading.sensor_id.clone(),
+            site: site.map(str::to_string),
+            timestamp: reading.timestamp,
+            metrics: temperature_metrics(reading),
+            provenance,
+        }
+    }
+
+    pub fn from_pool(reading: &PoolReading, site: Option<&str>, provenance: Provenance) -> Self {
+        Reading {
+            sensor_id: reading.sensor_id(),
+            site: site.map(str::to_string),
+            timestamp: reading.timestamp,
+            metrics: pool_metrics(reading),
+            provenance,
+        }
+    }
+}
+
+/// Headers of a processed email
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmailHeaders {
+    pub subject: String,
+    pub from: String,
+    pub date: DateTime<Utc>,
+}
+
+/// What happened to one attachment of an email
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AttachmentResult {
+    pub filename: String,
+    /// Readings parsed from the file, before validation
+    pub extracted: usize,
+    /// New readings written (would be written, in dry-run)
+    pub saved: usize,
+    pub duplicates: usize,
+    /// Readings refused by validation
+    pub rejected: usize,
+    /// Extraction or storage error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AttachmentResult {
+    pub fn new(filename: &str) -> Self {
+        AttachmentResult {
+            filename: filename.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Final status of an email in a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "error")]
+pub enum EmailStatus {
+    Processed,
+    NoData,
+    /// Left untouched by a subject/sender filter (reason)
+    Skipped(String),
+    /// Some attachments failed (files and errors): readings of the others are
+    /// saved, the email keeps its todo label and is retried
+    Partial(String),
+    Failed(String),
+}
+
+/// Outcome of one email, as listed in the run summary
+#[derive(Debug, Clone, Serialize)]
+pub struct EmailOutcome {
+    pub message_id: String,
+    #[serde(flatten)]
+    pub status: EmailStatus,
+    pub records: usize,
+    pub attachments: Vec<AttachmentResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<EmailHeaders>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub readings: Vec<Reading>,
+    #[serde(serialize_with = "serialize_duration_secs")]
+    pub duration: Duration,
+}
+
+impl EmailOutcome {
+    /// Outcome of an email read by its strategy
+    pub fn from_result(message_id: &str, status: EmailStatus, result: ProcessingResult, duration: Duration) -> Self {
+        EmailOutcome {
+            message_id: message_id.to_string(),
+            status,
+            records: result.records,
+            attachments: result.attachments,
+            headers: result.headers,
+            readings: result.readings,
+            duration,
+        }
+    }
+}
+
+/// Summary of one processor run (one strategy, one Gmail session)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSummary {
+    pub processor: String,
+    pub emails: Vec<EmailOutcome>,
+    pub sensors: BTreeMap<String, SensorCounts>,
+    pub api_calls: usize,
+    /// Emails left for the next run because a budget or the batch size was reached
+    pub deferred: usize,
+    /// Budget that stopped the run early
+    pub stopped_by: Option<RunBudget>,
+    #[serde(serialize_with = "serialize_duration_secs")]
+    pub duration: Duration,
+}
+
+impl RunSummary {
+    pub fn new(processor: &str) -> Self {
+        RunSummary {
+            processor: processor.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Number of emails successfully processed (with data)
+    pub fn processed_count(&self) -> usize {
+        self.emails.iter().filter(|e| e.status == EmailStatus::Processed).count()
+    }
+
+    /// Number of emails that failed, entirely or for some attachments
+    pub fn failed_count(&self) -> usize {
+        self.emails.iter().filter(|e| matches!(e.status, EmailStatus::Failed(_) | EmailStatus::Partial(_))).count()
+    }
+
+    /// Total records extracted from all emails
+    pub fn total_records(&self) -> usize {
+        self.emails.iter().map(|e| e.records).sum()
+    }
+
+    /// Rows inserted in the database so far
+    pub fn inserted_rows(&self) -> usize {
+        self.sensors.values().map(|counts| counts.inserted).sum()
+    }
+}
+
+fn serialize_duration_secs<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Render a compact end-of-run table for one or more processor runs
+pub fn render_summary_table(summaries: &[RunSummary], total_duration: Duration, locale: Locale) -> String {
+    let mut out = String::new();
+    let line = "─".repeat(72);
+
+    out.push_str(&format!("{}\n{}\n{}\n", line, locale.summary_title(), line));
+
+    // Per-email status
+    out.push_str(&format!("{:<12} {:<24} {:<16} {:>8}\n",
+                          locale.summary_col_source(), locale.summary_col_email(),
+                          locale.summary_col_status(), locale.summary_col_records()));
+    let mut any_email = false;
+    for summary in summaries {
+        for email in &summary.emails {
+            any_email = true;
+            out.push_str(&format!("{:<12} {:<24} {:<16} {:>8}\n",
+                                  summary.processor, email.message_id,
+                                  locale.email_status(&email.status), email.records));
+        }
+    }
+    if !any_email {
+        out.push_str(&format!("{}\n", locale.summary_no_emails()));
+    }
+
+    // Per-sensor readings
+    let mut sensors: BTreeMap<String, SensorCounts> = BTreeMap::new();
+    for summary in summaries {
+        merge_sensor_counts(&mut sensors, &summary.sensors);
+    }
+    if !sensors.is_empty() {
+        out.push('\n');
+        out.push_str(&format!("{:<27} {:>14} {:>14} {:>14}\n",
+                              locale.summary_col_sensor(), locale.summary_col_inserted(),
+                              locale.summary_col_duplicates(), locale.summary_col_rejected()));
+        for (sensor, counts) in &sensors {
+            out.push_str(&format!("{:<27} {:>14} {:>14} {:>14}\n",
+                                  sensor, counts.inserted, counts.duplicates, counts.rejected));
+        }
+    }
+
+    // Totals
+    let api_calls: usize = summaries.iter().map(|s| s.api_calls).sum();
+    out.push('\n');
+    out.push_str(&format!("{}\n", locale.summary_totals(
+        summaries.iter().map(|s| s.processed_count()).sum(),
+        summaries.iter().map(|s| s.failed_count()).sum(),
+        total_duration.as_secs_f64(),
+        api_calls,
+    )));
+    let deferred: usize = summaries.iter().map(|s| s.deferred).sum();
+    if let Some(budget) = summaries.iter().find_map(|s| s.stopped_by) {
+        out.push_str(&format!("{}\n", locale.summary_deferred(deferred, budget)));
+    }
+    out.push_str(&line);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processing_order_parse() {
+        assert_eq!(ProcessingOrder::parse("Oldest"), Some(ProcessingOrder::Oldest));
+        assert_eq!(ProcessingOrder::parse("newest-first"), Some(ProcessingOrder::Newest));
+        assert_eq!(ProcessingOrder::parse("random"), None);
+    }
+
+    #[test]
+    fn test_reading_from_sources() {
+        let timestamp = Utc::now();
+        let room = TemperatureReading {
+            sensor_id: "salon".to_string(),
+            timestamp,
+            temperature: 19.5,
+            humidity: None,
+            location: None,
+        };
+        let reading = Reading::from_temperature(&room, Some("home"), Provenance::email("xsense", "18c1", Some("export.csv")));
+        assert_eq!(reading.metrics, MetricSet::from([(Metric::Temperature, 19.5)]));
+        assert_eq!(reading.site.as_deref(), Some("home"));
+
+        let pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp,
+            temperature: Some(26.0),
+            ph: Some(7.2),
+            orp: Some(650),
+        };
+        let reading = Reading::from_pool(&pool, None, Provenance::email("blueriot", "18c2", None));
+        assert_eq!(reading.sensor_id, "pool/main");
+        let keys: Vec<_> = reading.metrics.keys().map(Metric::key).collect();
+        assert_eq!(keys, ["temperature", "ph", "orp"]);
+
+        let json = serde_json::to_value(&reading).unwrap();
+        assert_eq!(json["metrics"]["orp"], 650.0);
+        assert_eq!(json["provenance"], serde_json::json!({"source": "blueriot", "email_id": "18c2"}));
+        assert!(json.get("site").is_none());
+    }
+
+    #[test]
+    fn test_run_limits_exceeded() {
+        let limits = RunLimits {
+            max_duration: Some(Duration::from_secs(60)),
+            max_api_calls: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(limits.exceeded(Duration::from_secs(10), 99, 1_000_000), None);
+        assert_eq!(limits.exceeded(Duration::from_secs(10), 100, 0), Some(RunBudget::ApiCalls));
+        assert_eq!(limits.exceeded(Duration::from_secs(60), 100, 0), Some(RunBudget::Duration));
+
+        let limits = RunLimits { max_db_rows: Some(500), ..Default::default() };
+        assert_eq!(limits.exceeded(Duration::from_secs(3600), 10_000, 499), None);
+        assert_eq!(limits.exceeded(Duration::ZERO, 0, 500), Some(RunBudget::DbRows));
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let mut summary = RunSummary::new("X-Sense");
+        summary.emails.push(EmailOutcome {
+            message_id: "18c1".to_string(),
+            status: EmailStatus::Processed,
+            records: 3,
+            attachments: vec![AttachmentResult::new("export.csv")],
+            headers: None,
+            readings: Vec::new(),
+            duration: Duration::from_millis(300),
+        });
+        summary.emails.push(EmailOutcome {
+            message_id: "18c2".to_string(),
+            status: EmailStatus::Failed("boom".to_string()),
+            records: 0,
+            attachments: Vec::new(),
+            headers: None,
+            readings: Vec::new(),
+            duration: Duration::from_millis(100),
+        });
+        summary.emails.push(EmailOutcome {
+            message_id: "18c3".to_string(),
+            status: EmailStatus::Partial("b.csv: bad header".to_string()),
+            records: 2,
+            attachments: vec![AttachmentResult::new("a.csv"), AttachmentResult::new("b.csv")],
+            headers: None,
+            readings: Vec::new(),
+            duration: Duration::from_millis(100),
+        });
+        summary.sensors.insert("cabane".to_string(), SensorCounts { inserted: 2, duplicates: 1, rejected: 0 });
+        summary.api_calls = 7;
+
+        assert_eq!(summary.processed_count(), 1);
+        assert_eq!(summary.failed_count(), 2);
+
+        let table = render_summary_table(&[summary], Duration::from_secs(2), Locale::En);
+        assert!(table.contains("18c1"));
+        assert!(table.contains("partial"));
+        assert!(table.contains("cabane"));
+        assert!(table.contains("7 API call(s)"));
+    }
+}
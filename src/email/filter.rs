@@ -0,0 +1,85 @@
+use anyhow::{Result, Context};
+use regex::Regex;
+
+use crate::config::EmailFilterConfig;
+
+/// Subject/sender include and exclude rules applied after the label search
+///
+/// An email is skipped when an include pattern is set and does not match,
+/// or when an exclude pattern matches. Patterns are case-insensitive regexes.
+#[derive(Debug, Clone, Default)]
+pub struct EmailFilter {
+    subject_include: Option<Regex>,
+    subject_exclude: Option<Regex>,
+    sender_include: Option<Regex>,
+    sender_exclude: Option<Regex>,
+}
+
+impl EmailFilter {
+    pub fn from_config(config: &EmailFilterConfig) -> Result<Self> {
+        Ok(EmailFilter {
+            subject_include: compile(config.subject_include.as_deref())?,
+            subject_exclude: compile(config.subject_exclude.as_deref())?,
+            sender_include: compile(config.sender_include.as_deref())?,
+            sender_exclude: compile(config.sender_exclude.as_deref())?,
+        })
+    }
+
+    /// True when no rule is configured (no metadata fetch needed)
+    pub fn is_empty(&self) -> bool {
+        self.subject_include.is_none() && self.subject_exclude.is_none()
+            && self.sender_include.is_none() && self.sender_exclude.is_none()
+    }
+
+    /// Returns the reason to skip the email, or None to process it
+    pub fn skip_reason(&self, subject: &str, from: &str) -> Option<String> {
+        if let Some(re) = &self.subject_include {
+            if !re.is_match(subject) {
+                return Some(format!("subject does not match include pattern '{}'", re.as_str()));
+            }
+        }
+        if let Some(re) = &self.subject_exclude {
+            if re.is_match(subject) {
+                return Some(format!("subject matches exclude pattern '{}'", re.as_str()));
+            }
+        }
+        if let Some(re) = &self.sender_include {
+            if !re.is_match(from) {
+                return Some(format!("sender does not match include pattern '{}'", re.as_str()));
+            }
+        }
+        if let Some(re) = &self.sender_exclude {
+            if re.is_match(from) {
+                return Some(format!("sender matches exclude pattern '{}'", re.as_str()));
+            }
+        }
+        None
+    }
+}
+
+fn compile(pattern: Option<&str>) -> Result<Option<Regex>> {
+    pattern
+        .map(|p| Regex::new(&format!("(?i){}", p)).with_context(|| format!("Invalid email filter pattern '{}'", p)))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_reason() {
+        let filter = EmailFilter::from_config(&EmailFilterConfig {
+            subject_include: Some("export".to_string()),
+            subject_exclude: Some("promo|newsletter".to_string()),
+            sender_include: Some(r"@x-sense\.com".to_string()),
+            sender_exclude: None,
+        }).unwrap();
+
+        assert!(filter.skip_reason("Thermo-cabane Export data", "noreply@x-sense.com").is_none());
+        assert!(filter.skip_reason("Weekly report", "noreply@x-sense.com").is_some());
+        assert!(filter.skip_reason("Export PROMO -20%", "noreply@x-sense.com").is_some());
+        assert!(filter.skip_reason("Export data", "someone@example.com").is_some());
+        assert!(EmailFilter::default().is_empty());
+    }
+}
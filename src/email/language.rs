@@ -0,0 +1,100 @@
+use regex::Regex;
+
+/// Extraction patterns of one language, with the words used to recognize it
+///
+/// Adding a language to an extractor only takes one more `PatternSet` in its
+/// list; a set without markers is never detected and acts as a fallback.
+pub struct PatternSet {
+    /// Language code logged on detection, e.g. "fr"
+    pub language: &'static str,
+    /// Frequent lowercase words of the language, counted to detect it
+    pub markers: &'static [&'static str],
+    /// Case-insensitive regexes per field; the first capture group holds the value
+    pub fields: &'static [(&'static str, &'static [&'static str])],
+}
+
+struct CompiledSet {
+    language: &'static str,
+    markers: &'static [&'static str],
+    fields: Vec<(&'static str, Vec<Regex>)>,
+}
+
+/// Compiled pattern sets of one extractor, in declaration order
+pub struct LanguagePatterns {
+    sets: Vec<CompiledSet>,
+}
+
+impl LanguagePatterns {
+    pub fn new(sets: &[PatternSet]) -> Self {
+        let sets = sets.iter().map(|set| CompiledSet {
+            language: set.language,
+            markers: set.markers,
+            fields: set.fields.iter().map(|(field, patterns)| {
+                let regexes = patterns.iter()
+                    .map(|pattern| Regex::new(&format!("(?i){}", pattern)).expect("valid extraction pattern"))
+                    .collect();
+                (*field, regexes)
+            }).collect(),
+        }).collect();
+        Self { sets }
+    }
+
+    /// Language whose markers appear most often in the text
+    ///
+    /// None when no marker is found or two languages tie.
+    pub fn detect(&self, text: &str) -> Option<&'static str> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let mut scores: Vec<(&'static str, usize)> = self.sets.iter()
+            .filter(|set| !set.markers.is_empty())
+            .map(|set| (set.language, words.iter().filter(|word| set.markers.contains(&word.as_str())).count()))
+            .collect();
+        scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        match scores.as_slice() {
+            [(language, best), rest @ ..] if *best > 0 && rest.first().is_none_or(|(_, next)| next < best) => Some(language),
+            _ => None,
+        }
+    }
+
+    /// Captured values of a field, best candidates first
+    ///
+    /// Patterns of `language` come first, then those of the other sets in
+    /// declaration order, so callers can skip values failing validation.
+    pub fn captures<'t>(&self, text: &'t str, language: Option<&str>, field: &str) -> Vec<&'t str> {
+        let detected = self.sets.iter().filter(|set| Some(set.language) == language);
+        let others = self.sets.iter().filter(|set| Some(set.language) != language);
+        detected.chain(others)
+            .flat_map(|set| set.fields.iter().filter(|(name, _)| *name == field).flat_map(|(_, regexes)| regexes))
+            .filter_map(|re| re.captures(text).and_then(|caps| caps.get(1)))
+            .map(|value| value.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETS: &[PatternSet] = &[
+        PatternSet { language: "en", markers: &["the", "water"], fields: &[("level", &[r"level:\s*([0-9]+)"])] },
+        PatternSet { language: "fr", markers: &["le", "eau"], fields: &[("level", &[r"niveau[^:]*:\s*([0-9]+)"])] },
+        PatternSet { language: "", markers: &[], fields: &[("level", &[r"([0-9]+)\s*cm"])] },
+    ];
+
+    #[test]
+    fn test_detect_and_captures() {
+        let patterns = LanguagePatterns::new(SETS);
+        assert_eq!(patterns.detect("Le niveau de l'eau : 12 cm (level: 3)"), Some("fr"));
+        assert_eq!(patterns.detect("The water level: 40"), Some("en"));
+        assert_eq!(patterns.detect("12 cm"), None);
+        assert_eq!(patterns.detect("the eau"), None);
+
+        let text = "Le niveau de l'eau : 12 cm (level: 3)";
+        assert_eq!(patterns.captures(text, Some("fr"), "level"), ["12", "3", "12"]);
+        assert_eq!(patterns.captures(text, None, "level"), ["3", "12", "12"]);
+        assert!(patterns.captures(text, Some("fr"), "unknown").is_empty());
+    }
+}
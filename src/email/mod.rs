@@ -1,4 +1,13 @@
+pub mod cleanup;
+pub mod common;
+pub mod filter;
+pub mod language;
 pub mod processor_base;
+pub mod retry;
 
 // Re-export commonly used items
+pub use common::{AttachmentResult, DryRunDetail, EmailHeaders, EmailOutcome, ExtractError, EmailStatus, Metric, MetricSet, ProcessingOrder, ProcessingResult, Provenance, Reading, RunBudget, RunLimits, RunSummary, SensorCounts};
+pub use filter::EmailFilter;
+pub use language::{LanguagePatterns, PatternSet};
 pub use processor_base::{EmailProcessingStrategy, BaseEmailProcessor};
+pub use retry::RetryPolicy;
@@ -1,10 +1,37 @@
 use anyhow::{Result, Context};
 use log::{info, error, warn};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
 
+use crate::archive::AttachmentArchive;
 use crate::config::Config;
+use crate::error::{classify, is_quota_exceeded, ErrorClass};
 use crate::gmail_client::GmailClient;
 use crate::database::Database;
-use crate::slack_notifier::SlackNotifier;
+use crate::storage::{provenance_records, BatchCheckpoint, EmailRetry, SqliteStorage, Storage};
+use std::path::Path;
+use crate::slack_notifier::{NotificationKind, NotificationSource, Severity, SlackMode, SlackNotifier};
+use crate::hooks::{self, HookEvent};
+use crate::templates::{NotificationEvent, NotificationTemplates};
+use super::filter::EmailFilter;
+use super::retry::RetryPolicy;
+use super::common::{DryRunDetail, ProcessingOrder, RunBudget, RunLimits, merge_sensor_counts, EmailOutcome, EmailStatus, ProcessingResult, Reading, RunSummary};
+
+/// Share of GMAIL_DAILY_CALL_LIMIT from which each run logs a warning
+const API_QUOTA_WARNING_PERCENT: u64 = 80;
+
+/// Processing of one email, cancelled after `EMAIL_TIMEOUT_SECONDS`
+///
+/// A stalled download or a huge attachment then fails like any transient
+/// error: the email goes to the retry queue and the run moves on.
+async fn with_timeout<T>(timeout: Option<Duration>, processing: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return processing.await;
+    };
+    tokio::time::timeout(timeout, processing).await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Processing cancelled after {:?} (EMAIL_TIMEOUT_SECONDS)", timeout)))
+}
 
 /// Trait that defines the specific processing logic for each email type
 pub trait EmailProcessingStrategy: Send {
@@ -12,15 +39,19 @@ pub trait EmailProcessingStrategy: Send {
     fn search_emails<'a, 'b: 'a>(&'a self, gmail: &'b GmailClient) -> 
         std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<String>>> + Send + 'a>>;
     
-    /// Process a single email and return the number of records processed
+    /// Process a single email and return the records processed (with per-sensor counts)
+    ///
+    /// `archive` is set when the attachments of the email are to be kept
+    /// (`<SOURCE>_SAVE_ATTACHMENTS`), never in dry-run.
     fn process_single_email<'a, 'b: 'a, 'c: 'a>(
         &'a self,
         gmail: &'b GmailClient,
-        database: Option<&'c Database>,
+        database: Option<&'c dyn Storage>,
         slack: Option<&'c SlackNotifier>,
+        archive: Option<&'c AttachmentArchive>,
         message_id: &'a str,
-        is_dry_run: bool,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize>> + Send + 'a>>;
+        dry_run: Option<DryRunDetail>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ProcessingResult>> + Send + 'a>>;
     
     /// Mark email as processed (labels, archive, etc.)
     fn mark_email_processed<'a, 'b: 'a>(
@@ -34,178 +65,1049 @@ pub trait EmailProcessingStrategy: Send {
     
     /// Get the label name (for logging)
     fn label_name(&self) -> &str;
+    
+    /// Source used to route notifications to a Slack channel
+    fn notification_source(&self) -> NotificationSource;
+    
+    /// Subject/sender filter applied to search results
+    fn email_filter(&self) -> &EmailFilter;
+    
+    /// Whether the attachments of processed emails are saved under DATA_DIR
+    fn saves_attachments(&self) -> bool;
+    
+    /// Labels removed and added by `mark_email_processed`, as shown by the dry-run
+    fn processed_label_changes(&self, gmail: &GmailClient) -> (Vec<String>, Vec<String>);
 }
 
 /// Base email processor that handles common logic
 pub struct BaseEmailProcessor<S: EmailProcessingStrategy> {
     config: Config,
-    database: Option<Database>,
+    database: Option<Box<dyn Storage>>,
     slack: Option<SlackNotifier>,
     strategy: S,
+    /// Whether Gmail labels are updated after processing
+    modify_labels: bool,
+    /// Whether processed emails are remembered in `processed_emails` (labels left unchanged)
+    track_processed: bool,
+    templates: NotificationTemplates,
+    /// Where attachments are saved, None in dry-run or when the source keeps none
+    archive: Option<AttachmentArchive>,
 }
 
 impl<S: EmailProcessingStrategy> BaseEmailProcessor<S> {
+    /// Processor of the scheduled runs: each export repeats the previous ones, so
+    /// the history before the boundary window of a sensor is not queried again
     pub async fn new(config: Config, strategy: S) -> Result<Self> {
+        Self::connect(config, strategy, true).await
+    }
+    
+    /// Processor of `backfill-gmail`: emails come oldest first, so every reading
+    /// is checked against the database, whatever is already stored after it
+    pub async fn new_history(config: Config, strategy: S) -> Result<Self> {
+        Self::connect(config, strategy, false).await
+    }
+    
+    async fn connect(config: Config, strategy: S, skip_history: bool) -> Result<Self> {
         info!("Initializing {} email processor", strategy.processor_name());
         
         // Initialize database connection
         let database = Database::new(&config.database).await
-            .context("Unable to initialize database")?;
-        
-        // Initialize Slack notifier if configured
-        let slack = if let Some(slack_config) = &config.slack {
-            match SlackNotifier::new(slack_config) {
-                Ok(notifier) => {
-                    info!("✅ Slack notifications enabled");
-                    Some(notifier)
-                },
-                Err(e) => {
-                    warn!("⚠️  Unable to initialize Slack notifier: {} - notifications disabled", e);
-                    None
-                }
-            }
-        } else {
-            info!("ℹ️  Slack notifications not configured");
-            None
-        };
+            .context("Unable to initialize database")?
+            .with_site(config.site.clone())
+            .with_history_skip(skip_history);
         
+        // Initialize Slack notifier if configured, its messages recorded in `notifications`
+        let slack = SlackNotifier::from_config(config.slack.as_ref())
+            .map(|slack| slack.with_log(Box::new(database.clone())));
+        
+        let templates = NotificationTemplates::from_config(&config)?;
+        
+        // Read-only Gmail access or --no-label-changes: the todo label stays,
+        // the database remembers the email instead
+        let modify_labels = config.gmail.modifies_labels();
+        
+        let archive = Self::attachment_archive(&config, &strategy);
         Ok(BaseEmailProcessor {
             config,
-            database: Some(database),
+            database: Some(Box::new(database)),
             slack,
             strategy,
+            modify_labels,
+            track_processed: !modify_labels,
+            templates,
+            archive,
         })
     }
     
-    pub fn new_dry_run(config: Config, strategy: S) -> Result<Self> {
-        info!("🧪 Initializing {} email processor in dry-run mode (without database)", strategy.processor_name());
+    /// Dry-run processor: nothing is written, but the database is used (read-only)
+    /// when reachable to report which readings would be new
+    pub async fn new_dry_run(config: Config, strategy: S) -> Result<Self> {
+        info!("🧪 Initializing {} email processor in dry-run mode", strategy.processor_name());
+        
+        let database: Option<Box<dyn Storage>> = match Database::connect_read_only(&config.database).await {
+            Ok(db) => Some(Box::new(db.with_site(config.site.clone()))),
+            Err(e) => {
+                warn!("⚠️  Database not reachable, dry-run will not compare with stored readings: {}", e);
+                None
+            }
+        };
+        
+        let templates = NotificationTemplates::from_config(&config)?;
+        let track_processed = !config.gmail.modifies_labels();
         
         Ok(BaseEmailProcessor {
             config,
-            database: None,
+            database,
             slack: None,  // No Slack notifications in dry-run mode
             strategy,
+            modify_labels: false,
+            track_processed,
+            templates,
+            archive: None,
         })
     }
     
-    pub async fn process_emails(&self, limit: Option<usize>) -> Result<usize> {
+    /// Sandbox processor: the full pipeline runs, including inserts, but into
+    /// a SQLite file instead of TimescaleDB, without Slack or label changes
+    pub async fn new_sandbox(config: Config, strategy: S, sqlite_path: &Path) -> Result<Self> {
+        info!("🧪 Initializing {} email processor in sandbox mode ({})", 
+              strategy.processor_name(), sqlite_path.display());
+        
+        let storage = SqliteStorage::open(sqlite_path).await?
+            .with_site(config.site.clone())
+            .with_layout(config.database.layout)
+            .with_history_skip(true);
+        
+        let templates = NotificationTemplates::from_config(&config)?;
+        
+        let archive = Self::attachment_archive(&config, &strategy);
+        Ok(BaseEmailProcessor {
+            config,
+            database: Some(Box::new(storage)),
+            slack: None,
+            strategy,
+            modify_labels: false,
+            track_processed: false,
+            templates,
+            archive,
+        })
+    }
+    
+    /// Backfill processor: readings already stored for an email are replaced by
+    /// the ones extracted again, without Slack or label changes
+    pub async fn new_backfill(config: Config, strategy: S) -> Result<Self> {
+        info!("Initializing {} email processor for backfill", strategy.processor_name());
+        
+        let database = Database::new(&config.database).await
+            .context("Unable to initialize database")?
+            .with_site(config.site.clone())
+            .with_replace_existing(true);
+        
+        let templates = NotificationTemplates::from_config(&config)?;
+        
+        let archive = Self::attachment_archive(&config, &strategy);
+        Ok(BaseEmailProcessor {
+            config,
+            database: Some(Box::new(database)),
+            slack: None,
+            strategy,
+            modify_labels: false,
+            track_processed: false,
+            templates,
+            archive,
+        })
+    }
+    
+    fn attachment_archive(config: &Config, strategy: &S) -> Option<AttachmentArchive> {
+        strategy.saves_attachments().then(|| AttachmentArchive::new(
+            &config.data_dir,
+            config.data_dir_layout.clone(),
+            strategy.notification_source().key(),
+        ))
+    }
+    
+    /// Process the emails of this source with a Gmail connection shared by the sources
+    pub async fn process_emails(&self, gmail_client: &GmailClient, limits: RunLimits) -> Result<RunSummary> {
         info!("Starting {} email processing", self.strategy.processor_name());
-        self.process_emails_common(limit, false).await
+        self.process_emails_common(gmail_client, limits, None).await
+    }
+    
+    pub async fn process_emails_dry_run(&self, gmail_client: &GmailClient, limits: RunLimits, detail: DryRunDetail) -> Result<RunSummary> {
+        if detail.prints_text() {
+            println!("\n{}", "=".repeat(80));
+            println!("{}", self.config.locale.dry_run_banner(self.strategy.processor_name()));
+            println!("{}", "=".repeat(80));
+        }
+        
+        self.process_emails_common(gmail_client, limits, Some(detail)).await
     }
     
-    pub async fn process_emails_dry_run(&self, limit: Option<usize>) -> Result<usize> {
-        println!("\n{}", "=".repeat(80));
-        println!("🧪 MODE DRY-RUN - {} ANALYSIS", self.strategy.processor_name().to_uppercase());
-        println!("{}", "=".repeat(80));
+    /// Extract the given emails again (`backfill`), whatever their labels
+    ///
+    /// No retry queue, notification or hook: emails are only re-read and
+    /// their readings saved again. Labels are left alone by backfill
+    /// processors; the others mark emails with readings as processed, as a
+    /// run would (`backfill-gmail`). A Gmail quota error stops the emails
+    /// that follow, reported as deferred.
+    pub async fn reprocess(&self, gmail_client: &GmailClient, message_ids: &[String], dry_run: Option<DryRunDetail>) -> Result<RunSummary> {
+        let (started_at, started) = (Utc::now(), Instant::now());
+        let calls_before = gmail_client.api_call_count();
+        let mut summary = RunSummary::new(self.strategy.processor_name());
+        let show_emails = dry_run.is_some_and(|detail| detail.per_email());
+        let locale = self.config.locale;
+        
+        for (index, message_id) in message_ids.iter().enumerate() {
+            if show_emails {
+                println!("{}", locale.email_progress(index + 1, message_ids.len(), message_id));
+            }
+            let email_started = Instant::now();
+            let outcome = match with_timeout(self.config.email_timeout, self.strategy.process_single_email(
+                gmail_client,
+                self.database.as_deref(),
+                None,
+                self.archive.as_ref().filter(|_| dry_run.is_none()),
+                message_id,
+                dry_run
+            )).await {
+                Ok(mut result) => {
+                    if dry_run.is_none() {
+                        self.record_provenance(started_at, std::mem::take(&mut result.readings)).await;
+                    }
+                    merge_sensor_counts(&mut summary.sensors, &result.sensors);
+                    let status = if result.failed_attachments().next().is_some() {
+                        EmailStatus::Partial(result.failures_summary())
+                    } else if result.records == 0 {
+                        EmailStatus::NoData
+                    } else {
+                        EmailStatus::Processed
+                    };
+                    if status == EmailStatus::Processed && dry_run.is_none() {
+                        self.mark_processed(gmail_client, message_id).await;
+                    }
+                    EmailOutcome::from_result(message_id, status, result, email_started.elapsed())
+                }
+                Err(e) => {
+                    // Auth failures would repeat for every email
+                    if classify(&e) == ErrorClass::Auth {
+                        return Err(e.context(format!("Backfill stopped at email {}", message_id)));
+                    }
+                    // So would quota ones until the quota is replenished
+                    if is_quota_exceeded(&e) {
+                        summary.deferred = message_ids.len() - index;
+                        summary.stopped_by = Some(RunBudget::Quota);
+                        warn!("⏸️  Gmail API quota exceeded at email {}, {} email(s) left: {}", message_id, summary.deferred, e);
+                        break;
+                    }
+                    error!("Error reprocessing email {}: {}", message_id, e);
+                    EmailOutcome {
+                        message_id: message_id.clone(),
+                        status: EmailStatus::Failed(e.to_string()),
+                        records: 0,
+                        attachments: Vec::new(),
+                        headers: None,
+                        readings: Vec::new(),
+                        duration: email_started.elapsed(),
+                    }
+                }
+            };
+            info!("Email {} reprocessed ({}/{}): {} record(s)", message_id, index + 1, message_ids.len(), outcome.records);
+            summary.emails.push(outcome);
+        }
         
-        self.process_emails_common(limit, true).await
+        summary.api_calls = gmail_client.api_call_count() - calls_before;
+        summary.duration = started.elapsed();
+        if dry_run.is_none() {
+            self.track_api_usage(summary.api_calls).await;
+        }
+        Ok(summary)
     }
     
     /// Common processing logic for both normal and dry-run modes
-    async fn process_emails_common(&self, limit: Option<usize>, is_dry_run: bool) -> Result<usize> {
-        // 1. Connect to Gmail API
-        let gmail_client = GmailClient::new(&self.config.gmail).await
-            .context("Unable to connect to Gmail API")?;
+    async fn process_emails_common(&self, gmail_client: &GmailClient, limits: RunLimits, dry_run: Option<DryRunDetail>) -> Result<RunSummary> {
+        let is_dry_run = dry_run.is_some();
+        // Per-email dry-run output, silenced by `--dry-run-detail summary`
+        let show_emails = dry_run.is_some_and(|detail| detail.per_email());
+        // Banner and totals, silenced by `--output json`
+        let show_totals = dry_run.is_some_and(|detail| detail.prints_text());
+        // Structured `source` field of per-email log records (journald)
+        let source = self.strategy.notification_source().key();
+        let (started_at, started) = (Utc::now(), Instant::now());
+        let mut summary = RunSummary::new(self.strategy.processor_name());
+        
+        // 1. The connection may be shared: only the requests made from here count for this source
+        let calls_before = gmail_client.api_call_count();
+        let api_calls = || gmail_client.api_call_count() - calls_before;
         
         // 2. Search for emails using strategy
-        let message_ids = self.strategy.search_emails(&gmail_client).await
+        let message_ids = self.strategy.search_emails(gmail_client).await
             .context("Error searching for emails")?;
+        let message_ids = self.skip_processed(message_ids).await;
+        
+        if !is_dry_run {
+            self.track_source_activity(!message_ids.is_empty()).await;
+        }
+        
+        // Failed emails come back once their backoff has elapsed, even without the todo label
+        let now = Utc::now();
+        let retries = self.load_retries().await;
+        let message_ids = self.merge_due_retries(message_ids, &retries, now);
+        
+        let locale = self.config.locale;
         
         if message_ids.is_empty() {
-            if is_dry_run {
-                println!("❌ No emails found with label '{}'", self.strategy.label_name());
-                println!("   Hint: Add the label '{}' to emails to process", self.strategy.label_name());
-            } else {
+            if show_totals {
+                println!("{}", locale.no_emails_found(self.strategy.label_name()));
+                println!("{}", locale.no_emails_hint(self.strategy.label_name()));
+            } else if !is_dry_run {
                 info!("No emails found with label '{}'", self.strategy.label_name());
             }
-            return Ok(0);
+            summary.api_calls = api_calls();
+            summary.duration = started.elapsed();
+            if !is_dry_run {
+                self.track_api_usage(summary.api_calls).await;
+            }
+            return Ok(summary);
         }
         
-        if is_dry_run {
-            println!("✅ Found {} email(s) matching criteria\n", message_ids.len());
+        if show_totals {
+            println!("{}\n", locale.emails_found(message_ids.len()));
         }
         
+        // BATCH_SIZE caps real runs only; oldest-first runs continue after the checkpoint
+        let batch_size = self.config.batch_size.filter(|_| !is_dry_run);
+        let checkpointed = batch_size.is_some() && self.config.processing_order == ProcessingOrder::Oldest;
+        let checkpoint = if checkpointed { self.load_batch_checkpoint().await } else { None };
+        let max_emails = match (limits.max_emails, batch_size) {
+            (Some(limit), Some(batch_size)) => Some(limit.min(batch_size)),
+            (limit, batch_size) => limit.or(batch_size),
+        };
+        
+        // 3. Sort (oldest first by default) then skip emails rejected by the subject/sender filter
+        let (message_ids, dates) = self.sort_emails(gmail_client, message_ids).await;
+        let message_ids = self.resume_after_checkpoint(message_ids, &dates, checkpoint.as_ref(), &retries);
+        let message_ids = self.skip_pending_retries(message_ids, &retries, now, show_emails, &mut summary);
+        let candidates = if batch_size.is_some() { message_ids.clone() } else { Vec::new() };
+        let message_ids = self.filter_emails(gmail_client, message_ids, max_emails, show_emails, &mut summary).await;
+        
         let mut total_processed = 0;
         let mut total_records_saved = 0;
+        // Emails handled before the run stopped, the last one is the batch checkpoint
+        let mut examined = 0;
         
-        // 3. Process each found email (with optional limit)
-        let emails_to_process = if let Some(limit) = limit {
+        // 4. Process each found email (with optional limit)
+        let emails_to_process = if let Some(limit) = max_emails {
             message_ids.into_iter().take(limit).collect()
         } else {
             message_ids
         };
         
         for (index, message_id) in emails_to_process.iter().enumerate() {
-            if is_dry_run {
-                println!("📧 Email {}/{} (ID: {})", index + 1, emails_to_process.len(), message_id);
+            // Stop cleanly once a budget is spent: remaining emails keep their
+            // label and are picked up first by the next (oldest-first) run
+            if let Some(budget) = limits.exceeded(started.elapsed(), api_calls(), summary.inserted_rows()) {
+                summary.deferred = emails_to_process.len() - index;
+                summary.stopped_by = Some(budget);
+                warn!("⏸️  Run budget reached ({:?}), {} {} email(s) deferred to the next run", 
+                      budget, summary.deferred, self.strategy.processor_name());
+                break;
+            }
+            examined = index + 1;
+            
+            if show_emails {
+                println!("{}", locale.email_progress(index + 1, emails_to_process.len(), message_id));
                 println!("{}", "-".repeat(60));
             }
             
-            match self.strategy.process_single_email(
-                &gmail_client,
-                self.database.as_ref(),
+            let email_started = Instant::now();
+            
+            match with_timeout(self.config.email_timeout, self.strategy.process_single_email(
+                gmail_client,
+                self.database.as_deref(),
                 self.slack.as_ref(),
+                self.archive.as_ref().filter(|_| dry_run.is_none()),
                 message_id,
-                is_dry_run
-            ).await {
-                Ok(records_count) => {
+                dry_run
+            )).await {
+                Ok(mut result) => {
+                    if !is_dry_run {
+                        self.record_provenance(started_at, std::mem::take(&mut result.readings)).await;
+                    }
+                    let records_count = result.records;
                     total_processed += 1;
+                    merge_sensor_counts(&mut summary.sensors, &result.sensors);
+                    
+                    // Some attachments failed: readings of the others are kept, but the
+                    // email is not marked as processed and goes to the retry queue
+                    if result.failed_attachments().next().is_some() {
+                        let failures = result.failures_summary();
+                        total_records_saved += records_count;
+                        summary.emails.push(EmailOutcome::from_result(message_id, EmailStatus::Partial(failures.clone()), result, email_started.elapsed()));
+                        
+                        if is_dry_run {
+                            if show_emails {
+                                println!("{}", locale.email_partial(message_id, records_count, &failures));
+                                self.preview_label_changes(gmail_client, retries.get(message_id), false).await;
+                                println!();
+                            }
+                        } else {
+                            warn!(source = source, email_id = message_id.as_str(); "Email {} partially processed ({} record(s)), failed attachment(s): {}", 
+                                  message_id, records_count, failures);
+                            let retry = self.schedule_retry(gmail_client, message_id, retries.get(message_id), &failures, ErrorClass::Transient).await;
+                            if retry.as_ref().is_some_and(|retry| retry.dead_lettered_at.is_some()) {
+                                self.notify_failure(message_id, &failures, ErrorClass::Transient, retry.as_ref()).await;
+                            }
+                        }
+                        continue;
+                    }
+                    
+                    if !is_dry_run && retries.contains_key(message_id) {
+                        self.clear_retry(message_id).await;
+                    }
                     
                     if records_count == 0 {
+                        summary.emails.push(EmailOutcome::from_result(message_id, EmailStatus::NoData, result, email_started.elapsed()));
+                        
                         // Special case: email skipped (no data extracted)
                         if is_dry_run {
-                            println!("⚠️  Email {} analyzed but no data extracted\n", message_id);
+                            if show_emails {
+                                println!("{}\n", locale.email_no_data(message_id));
+                            }
                         } else {
-                            warn!("Email {} processed but no data extracted", message_id);
+                            warn!(source = source, email_id = message_id.as_str(); "Email {} processed but no data extracted", message_id);
                         }
                         continue; // Skip marking as processed if no data
                     }
                     
                     total_records_saved += records_count;
+                    summary.emails.push(EmailOutcome::from_result(message_id, EmailStatus::Processed, result, email_started.elapsed()));
                     
                     // Mark email as processed (unless dry-run)
                     if !is_dry_run {
-                        if let Err(e) = self.strategy.mark_email_processed(&gmail_client, message_id).await {
-                            error!("Failed to mark email {} as processed: {}", message_id, e);
-                        }
+                        self.mark_processed(gmail_client, message_id).await;
                     }
                     
                     if is_dry_run {
-                        println!("✅ Email {} analyzed successfully ({} record(s))\n", message_id, records_count);
+                        if show_emails {
+                            println!("{}", locale.email_analyzed(message_id, records_count));
+                            self.preview_label_changes(gmail_client, retries.get(message_id), true).await;
+                            println!();
+                        }
                     } else {
-                        info!("Email {} processed successfully: {} record(s) saved", message_id, records_count);
+                        info!(source = source, email_id = message_id.as_str(); "Email {} processed successfully: {} record(s) saved", message_id, records_count);
                     }
                 }
                 Err(e) => {
+                    summary.emails.push(EmailOutcome {
+                        message_id: message_id.clone(),
+                        status: EmailStatus::Failed(e.to_string()),
+                        records: 0,
+                        attachments: Vec::new(),
+                        headers: None,
+                        readings: Vec::new(),
+                        duration: email_started.elapsed(),
+                    });
+                    
                     if is_dry_run {
-                        println!("❌ Error analyzing email {}: {}\n", message_id, e);
+                        if show_emails {
+                            println!("{}", locale.email_analysis_error(message_id, &e.to_string()));
+                            self.preview_label_changes(gmail_client, retries.get(message_id), false).await;
+                            println!();
+                        }
                     } else {
-                        error!("Error processing email {}: {}", message_id, e);
+                        let class = classify(&e);
+                        error!(source = source, email_id = message_id.as_str(), error_class = class.key(); "Error processing email {}: {}", message_id, e);
                         
-                        // Send error notification to Slack
-                        if let Some(slack) = &self.slack {
-                            let _ = slack.send_message(&format!(
-                                "❌ Error processing {} email {}: {}",
-                                self.strategy.processor_name(),
-                                message_id,
-                                e
-                            )).await;
+                        // The following emails would fail the same way: they keep their
+                        // label for the next run, and this one is not held against the email
+                        if class == ErrorClass::Auth {
+                            self.notify_failure(message_id, &e.to_string(), class, None).await;
+                            examined = index;
+                            summary.deferred = emails_to_process.len() - index - 1;
+                            warn!("⏸️  Gmail authorization failed, {} {} email(s) deferred to the next run",
+                                  summary.deferred, self.strategy.processor_name());
+                            break;
                         }
+                        
+                        let retry = self.schedule_retry(gmail_client, message_id, retries.get(message_id), &e.to_string(), class).await;
+                        self.notify_failure(message_id, &e.to_string(), class, retry.as_ref()).await;
                     }
                 }
             }
         }
         
-        if is_dry_run {
+        if show_totals {
             println!("{}", "=".repeat(80));
-            println!("🏁 Analysis completed: {} emails analyzed out of {}", total_processed, emails_to_process.len());
-            println!("📊 Total records: {}", total_records_saved);
+            println!("{}", locale.analysis_completed(total_processed, emails_to_process.len()));
+            println!("{}", locale.total_records(total_records_saved));
             println!("{}", "=".repeat(80));
-        } else {
+        } else if !is_dry_run {
             info!("Processing completed: {} emails processed, {} records saved", 
                   total_processed, total_records_saved);
         }
         
-        Ok(total_processed)
+        if batch_size.is_some() {
+            let last_examined = examined.checked_sub(1).map(|index| &emails_to_process[index]);
+            self.update_batch_checkpoint(&candidates, &dates, last_examined, checkpointed, checkpoint.is_some(), &mut summary).await;
+        }
+        
+        summary.api_calls = api_calls();
+        summary.duration = started.elapsed();
+        
+        if !is_dry_run {
+            self.track_api_usage(summary.api_calls).await;
+            self.notify_run_summary(&summary).await;
+            if let Some(slack) = &self.slack {
+                slack.retry_failed().await;
+            }
+            self.run_complete_hook(&summary).await;
+        }
+        Ok(summary)
+    }
+    
+    /// Count empty runs and warn once the source stayed without emails for `<SOURCE>_ALERT_AFTER_DAYS`
+    async fn track_source_activity(&self, found_emails: bool) {
+        let Some(database) = &self.database else {
+            return;
+        };
+        
+        let source = self.strategy.notification_source();
+        let alert_after_days = match source {
+            NotificationSource::XSense => self.config.xsense.alert_after_days,
+            NotificationSource::BlueRiot => self.config.blueriot.alert_after_days,
+            NotificationSource::Ingest => 0,
+        };
+        
+        let activity = match database.record_source_run(source.key(), found_emails).await {
+            Ok(activity) => activity,
+            Err(e) => {
+                warn!("Failed to record {} activity: {}", source.key(), e);
+                return;
+            }
+        };
+        
+        let days = activity.days_without_email(Utc::now());
+        if alert_after_days == 0 || found_emails || activity.alerted_at.is_some() || days < alert_after_days as i64 {
+            return;
+        }
+        
+        let (processor, label) = (self.strategy.processor_name(), self.strategy.label_name());
+        warn!("⚠️  No {} email found for {} days ({} empty runs)", processor, days, activity.empty_runs);
+        
+        let Some(slack) = &self.slack else {
+            return;
+        };
+        let context = minijinja::context! {
+            processor => processor,
+            label => label,
+            days => days,
+            empty_runs => activity.empty_runs,
+            last_email_at => activity.last_email_at.to_rfc3339(),
+        };
+        let message = self.templates.render_or(NotificationEvent::NoEmails, context, || {
+            self.config.locale.notify_no_emails(processor, label, days, activity.empty_runs)
+        });
+        if slack.notify(source, Severity::Error, NotificationKind::NoEmails, None, &message).await.is_ok() {
+            if let Err(e) = database.mark_source_alerted(source.key()).await {
+                warn!("Failed to record the {} no-email alert: {}", source.key(), e);
+            }
+        }
+    }
+    
+    /// Add the Gmail API requests of this run to today's usage, warn when nearing `GMAIL_DAILY_CALL_LIMIT`
+    async fn track_api_usage(&self, calls: usize) {
+        let Some(database) = &self.database else {
+            return;
+        };
+        
+        let source = self.strategy.notification_source().key();
+        let today = match database.record_api_calls(source, calls as u64).await {
+            Ok(today) => today,
+            Err(e) => {
+                warn!("Failed to record {} Gmail API usage: {}", source, e);
+                return;
+            }
+        };
+        
+        let limit = self.config.gmail.daily_call_limit;
+        if limit > 0 && today * 100 >= limit * API_QUOTA_WARNING_PERCENT {
+            warn!("⚠️  {} Gmail API requests today, {}% of GMAIL_DAILY_CALL_LIMIT ({})",
+                  today, today * 100 / limit, limit);
+        }
+    }
+    
+    /// Post the run summary with the per-email messages in its thread (`SLACK_MODE=run`)
+    async fn notify_run_summary(&self, summary: &RunSummary) {
+        let Some(slack) = self.slack.as_ref().filter(|slack| slack.mode() == SlackMode::Run) else {
+            return;
+        };
+        if summary.emails.is_empty() {
+            return;
+        }
+        
+        let context = minijinja::context! {
+            processor => &summary.processor,
+            processed => summary.processed_count(),
+            failed => summary.failed_count(),
+            records => summary.total_records(),
+            emails => &summary.emails,
+            sensors => &summary.sensors,
+        };
+        let message = self.templates.render_or(NotificationEvent::RunSummary, context, || {
+            self.config.locale.notify_run_summary(summary)
+        });
+        if let Err(e) = slack.post_run_summary(self.strategy.notification_source(), &message).await {
+            warn!("Failed to send the run summary to Slack: {}", e);
+        }
+    }
+    
+    /// Run the `HOOK_ON_RUN_COMPLETE` command with the run summary and its totals
+    async fn run_complete_hook(&self, summary: &RunSummary) {
+        let mut data = serde_json::to_value(summary).unwrap_or_default();
+        if let Some(fields) = data.as_object_mut() {
+            fields.insert("profile".to_string(), self.config.profile.clone().into());
+            fields.insert("processed".to_string(), summary.processed_count().into());
+            fields.insert("failed".to_string(), summary.failed_count().into());
+            fields.insert("records".to_string(), summary.total_records().into());
+        }
+        hooks::run(&self.config.hooks, HookEvent::RunComplete, data).await;
+    }
+    
+    /// Retry queue entries of this source, keyed by email id
+    async fn load_retries(&self) -> HashMap<String, EmailRetry> {
+        let Some(database) = &self.database else {
+            return HashMap::new();
+        };
+        
+        match database.email_retries(self.strategy.notification_source().key()).await {
+            Ok(retries) => retries.into_iter()
+                .map(|retry| (retry.email_id.clone(), retry))
+                .collect(),
+            Err(e) => {
+                warn!("Unable to read the email retry queue: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+    
+    /// Add queued emails whose next attempt is due but which no longer carry the todo label
+    fn merge_due_retries(&self, mut message_ids: Vec<String>, retries: &HashMap<String, EmailRetry>, 
+                         now: DateTime<Utc>) -> Vec<String> {
+        let mut due: Vec<&EmailRetry> = retries.values()
+            .filter(|retry| retry.is_due(now) && !message_ids.contains(&retry.email_id))
+            .collect();
+        due.sort_by_key(|retry| retry.next_attempt_at);
+        
+        for retry in due {
+            info!("🔁 Retrying {} email {} (attempt {})", 
+                  self.strategy.processor_name(), retry.email_id, retry.attempts + 1);
+            message_ids.push(retry.email_id.clone());
+        }
+        message_ids
+    }
+    
+    /// Leave queued emails alone until their next attempt is due (never once dead-lettered)
+    fn skip_pending_retries(
+        &self,
+        message_ids: Vec<String>,
+        retries: &HashMap<String, EmailRetry>,
+        now: DateTime<Utc>,
+        show_skipped: bool,
+        summary: &mut RunSummary,
+    ) -> Vec<String> {
+        message_ids.into_iter()
+            .filter(|message_id| {
+                let Some(retry) = retries.get(message_id).filter(|retry| !retry.is_due(now)) else {
+                    return true;
+                };
+                
+                let reason = match retry.dead_lettered_at {
+                    Some(_) => format!("dead-lettered after {} failed attempt(s): {}", retry.attempts, retry.last_error),
+                    None => format!("next retry at {} after {} failed attempt(s): {}", 
+                                    retry.next_attempt_at.format("%Y-%m-%d %H:%M UTC"), retry.attempts, retry.last_error),
+                };
+                info!("Skipping {} email {}: {}", self.strategy.processor_name(), message_id, reason);
+                if show_skipped {
+                    println!("{}", self.config.locale.email_skipped(message_id, "-", &reason));
+                }
+                summary.emails.push(EmailOutcome {
+                    message_id: message_id.clone(),
+                    status: EmailStatus::Skipped(reason),
+                    records: 0,
+                    attachments: Vec::new(),
+                    headers: None,
+                    readings: Vec::new(),
+                    duration: Duration::ZERO,
+                });
+                false
+            })
+            .collect()
+    }
+    
+    /// Queue a failed email for another attempt with exponential backoff
+    /// 
+    /// Once `RETRY_MAX_ATTEMPTS` is reached, or at once for a permanent error,
+    /// the email is dead-lettered instead: it moves from the todo label to
+    /// `<prefix>/error/<source>` and is no longer retried until requeued with
+    /// `homemetrics requeue`. Returns the recorded entry.
+    async fn schedule_retry(&self, gmail_client: &GmailClient, message_id: &str, 
+                            previous: Option<&EmailRetry>, error: &str, class: ErrorClass) -> Option<EmailRetry> {
+        let database = self.database.as_ref()?;
+        
+        let policy = RetryPolicy::from_config(&self.config.retry);
+        let source = self.strategy.notification_source().key();
+        let attempts = previous.map_or(0, |retry| retry.attempts) + 1;
+        let now = Utc::now();
+        let retry = EmailRetry {
+            source: source.to_string(),
+            email_id: message_id.to_string(),
+            attempts,
+            next_attempt_at: policy.next_attempt(attempts, now),
+            last_error: error.to_string(),
+            dead_lettered_at: (!class.is_retryable() || policy.is_exhausted(attempts)).then_some(now),
+        };
+        if let Err(e) = database.schedule_email_retry(&retry).await {
+            error!("Failed to queue email {} for retry: {}", message_id, e);
+            return None;
+        }
+        
+        if retry.dead_lettered_at.is_some() {
+            warn!(source = source, email_id = message_id; "☠️  {} email {} dead-lettered after {} failed attempts", 
+                  self.strategy.processor_name(), message_id, attempts);
+            if self.modify_labels {
+                let (todo, error_label) = (format!("todo/{}", source), format!("error/{}", source));
+                if let Err(e) = gmail_client.move_email(message_id, &todo, &error_label).await {
+                    error!("Failed to move email {} to the error label: {}", message_id, e);
+                }
+            }
+        }
+        Some(retry)
+    }
+    
+    /// Dry-run: print the labels a real run would remove and add for this email
+    /// 
+    /// A failed email only changes labels when this failure dead-letters it.
+    async fn preview_label_changes(&self, gmail_client: &GmailClient, previous: Option<&EmailRetry>, succeeded: bool) {
+        let locale = self.config.locale;
+        if !self.config.gmail.modifies_labels() {
+            println!("{}", locale.labels_unchanged(succeeded));
+            return;
+        }
+        
+        let (remove, add) = if succeeded {
+            self.strategy.processed_label_changes(gmail_client)
+        } else {
+            let policy = RetryPolicy::from_config(&self.config.retry);
+            let attempts = previous.map_or(0, |retry| retry.attempts) + 1;
+            if !policy.is_exhausted(attempts) {
+                println!("{}", locale.labels_unchanged_retry(attempts));
+                return;
+            }
+            let source = self.strategy.notification_source().key();
+            (vec![gmail_client.label(&format!("todo/{}", source))], vec![gmail_client.label(&format!("error/{}", source))])
+        };
+        
+        println!("{}", locale.label_changes(&remove.join(", "), &add.join(", ")));
+        for label in remove.iter().chain(&add) {
+            if !gmail_client.label_exists(label).await {
+                println!("{}", locale.label_missing(label));
+            }
+        }
+    }
+    
+    /// Send the Slack error notification of a failed email, or its dead-letter summary
+    /// 
+    /// The `HOOK_ON_ERROR` command is run first, Slack configured or not.
+    async fn notify_failure(&self, message_id: &str, error: &str, class: ErrorClass, retry: Option<&EmailRetry>) {
+        let (processor, locale) = (self.strategy.processor_name(), self.config.locale);
+        let data = serde_json::json!({
+            "profile": self.config.profile,
+            "processor": processor,
+            "message_id": message_id,
+            "error": error,
+            "error_class": class,
+            "attempts": retry.map(|retry| retry.attempts),
+            "dead_lettered": retry.is_some_and(|retry| retry.dead_lettered_at.is_some()),
+        });
+        hooks::run(&self.config.hooks, HookEvent::Error, data).await;
+        
+        let Some(slack) = &self.slack else {
+            return;
+        };
+        
+        let message = match retry.filter(|retry| retry.dead_lettered_at.is_some()) {
+            Some(retry) => {
+                let context = minijinja::context! {
+                    processor => processor,
+                    message_id => message_id,
+                    attempts => retry.attempts,
+                    error => error,
+                    error_class => class.key(),
+                };
+                self.templates.render_or(NotificationEvent::DeadLetter, context, || {
+                    locale.notify_dead_letter(processor, message_id, retry.attempts, error)
+                })
+            }
+            None => {
+                let context = minijinja::context! {
+                    processor => processor,
+                    message_id => message_id,
+                    error => error,
+                    error_class => class.key(),
+                };
+                self.templates.render_or(NotificationEvent::ProcessingError, context, || match class {
+                    ErrorClass::Auth => locale.notify_auth_error(processor, error),
+                    _ => locale.notify_processing_error(processor, message_id, error),
+                })
+            }
+        };
+        let _ = slack.notify(self.strategy.notification_source(), Severity::Error, NotificationKind::Error, Some(message_id), &message).await;
+    }
+    
+    /// Drop emails already recorded in `processed_emails`, whose todo label is never removed
+    async fn skip_processed(&self, mut message_ids: Vec<String>) -> Vec<String> {
+        let Some(database) = self.database.as_ref().filter(|_| self.track_processed) else {
+            return message_ids;
+        };
+        
+        let source = self.strategy.notification_source().key();
+        match database.processed_emails(source).await {
+            Ok(processed) => {
+                let found = message_ids.len();
+                message_ids.retain(|message_id| !processed.contains(message_id));
+                if message_ids.len() < found {
+                    info!("Skipping {} {} email(s) already processed", found - message_ids.len(), self.strategy.processor_name());
+                }
+            }
+            Err(e) => warn!("Failed to read processed {} emails, all of them will be processed: {}", source, e),
+        }
+        message_ids
+    }
+    
+    /// Move the email to the done label, or remember it when labels can't be changed
+    async fn mark_processed(&self, gmail_client: &GmailClient, message_id: &str) {
+        if self.modify_labels {
+            if let Err(e) = self.strategy.mark_email_processed(gmail_client, message_id).await {
+                let source = self.strategy.notification_source().key();
+                error!(source = source, email_id = message_id; "Failed to mark email {} as processed: {}", message_id, e);
+            }
+        } else if self.track_processed {
+            self.record_processed(message_id).await;
+        }
+    }
+    
+    async fn record_processed(&self, message_id: &str) {
+        if let Some(database) = &self.database {
+            if let Err(e) = database.record_processed_email(self.strategy.notification_source().key(), message_id).await {
+                error!("Failed to record email {} as processed: {}", message_id, e);
+            }
+        }
+    }
+    
+    /// Keep the email and attachment of the readings stored, for `homemetrics trace`
+    async fn record_provenance(&self, run_started_at: DateTime<Utc>, readings: Vec<Reading>) {
+        let Some(database) = &self.database else {
+            return;
+        };
+        let records = provenance_records(&readings, run_started_at);
+        if records.is_empty() {
+            return;
+        }
+        if let Err(e) = database.record_provenance(&records).await {
+            warn!("Failed to record the provenance of {} reading(s): {:#}", readings.len(), e);
+        }
+    }
+    
+    async fn clear_retry(&self, message_id: &str) {
+        if let Some(database) = &self.database {
+            if let Err(e) = database.clear_email_retry(self.strategy.notification_source().key(), message_id).await {
+                warn!("Failed to remove email {} from the retry queue: {}", message_id, e);
+            }
+        }
+    }
+    
+    /// Order emails by Gmail internal date according to `PROCESSING_ORDER`
+    /// 
+    /// Gmail search returns newest first; emails whose date cannot be read keep
+    /// their relative position at the end. Also returns the dates read, by email.
+    async fn sort_emails(&self, gmail_client: &GmailClient, message_ids: Vec<String>) -> (Vec<String>, HashMap<String, i64>) {
+        if self.config.processing_order == ProcessingOrder::Newest || message_ids.len() < 2 {
+            return (message_ids, HashMap::new());
+        }
+        
+        let mut dated = Vec::with_capacity(message_ids.len());
+        let mut dates = HashMap::with_capacity(message_ids.len());
+        for message_id in message_ids {
+            let date = match gmail_client.fetch_internal_date(&message_id).await {
+                Ok(date) => {
+                    dates.insert(message_id.clone(), date);
+                    date
+                }
+                Err(e) => {
+                    warn!("Unable to read date of email {}: {}", message_id, e);
+                    i64::MAX
+                }
+            };
+            dated.push((date, message_id));
+        }
+        
+        // Stable sort keeps Gmail order for equal dates
+        dated.sort_by_key(|(date, _)| *date);
+        (dated.into_iter().map(|(_, message_id)| message_id).collect(), dates)
+    }
+    
+    async fn load_batch_checkpoint(&self) -> Option<BatchCheckpoint> {
+        let database = self.database.as_ref()?;
+        match database.batch_checkpoint(self.strategy.notification_source().key()).await {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                warn!("Unable to read the {} batch checkpoint, starting from the oldest email: {}", 
+                      self.strategy.processor_name(), e);
+                None
+            }
+        }
+    }
+    
+    /// Skip emails up to the checkpoint of the previous batch, queued retries excepted
+    /// 
+    /// Once no email is left after the checkpoint the run starts over from the oldest
+    /// one, so emails that kept their label (no data, filtered out) are looked at again.
+    fn resume_after_checkpoint(&self, message_ids: Vec<String>, dates: &HashMap<String, i64>,
+                               checkpoint: Option<&BatchCheckpoint>, retries: &HashMap<String, EmailRetry>) -> Vec<String> {
+        let Some(checkpoint) = checkpoint else {
+            return message_ids;
+        };
+        
+        // Emails whose date could not be read are kept
+        let after_checkpoint = |message_id: &String| {
+            dates.get(message_id).is_none_or(|date| *date > checkpoint.email_date)
+        };
+        if !message_ids.iter().any(|message_id| !retries.contains_key(message_id) && after_checkpoint(message_id)) {
+            info!("No {} email after the batch checkpoint, starting over from the oldest one", self.strategy.processor_name());
+            return message_ids;
+        }
+        
+        let found = message_ids.len();
+        let message_ids: Vec<String> = message_ids.into_iter()
+            .filter(|message_id| retries.contains_key(message_id) || after_checkpoint(message_id))
+            .collect();
+        info!("Resuming {} emails after {}: {} email(s) already examined by the previous batch", 
+              self.strategy.processor_name(), checkpoint.email_id, found - message_ids.len());
+        message_ids
+    }
+    
+    /// Report the emails left by a run capped by `BATCH_SIZE` and move the checkpoint
+    /// 
+    /// The checkpoint is cleared once a run examines every remaining email.
+    async fn update_batch_checkpoint(&self, candidates: &[String], dates: &HashMap<String, i64>, last_examined: Option<&String>,
+                                     checkpointed: bool, had_checkpoint: bool, summary: &mut RunSummary) {
+        // Nothing examined (budget or authorization): the checkpoint stays where it was
+        let Some(last_examined) = last_examined else {
+            return;
+        };
+        let remaining = candidates.iter()
+            .position(|message_id| message_id == last_examined)
+            .map_or(0, |position| candidates.len() - position - 1);
+        
+        if remaining > 0 && summary.stopped_by.is_none() {
+            summary.deferred = remaining;
+            summary.stopped_by = Some(RunBudget::Batch);
+            info!("⏸️  Batch size reached, {} {} email(s) left for the next run", remaining, self.strategy.processor_name());
+        }
+        
+        let Some(database) = self.database.as_ref().filter(|_| checkpointed) else {
+            return;
+        };
+        let source = self.strategy.notification_source().key();
+        let result = if remaining > 0 {
+            let Some(&email_date) = dates.get(last_examined) else {
+                warn!("Unable to save the {} batch checkpoint: date of email {} unknown", source, last_examined);
+                return;
+            };
+            database.save_batch_checkpoint(&BatchCheckpoint {
+                source: source.to_string(),
+                email_id: last_examined.clone(),
+                email_date,
+            }).await
+        } else if had_checkpoint {
+            database.clear_batch_checkpoint(source).await
+        } else {
+            return;
+        };
+        if let Err(e) = result {
+            warn!("Failed to update the {} batch checkpoint: {}", source, e);
+        }
+    }
+    
+    /// Apply the strategy's subject/sender filter, stopping once `limit` emails are kept
+    /// 
+    /// Skipped emails keep their label and are recorded in the summary with the reason.
+    async fn filter_emails(
+        &self,
+        gmail_client: &GmailClient,
+        message_ids: Vec<String>,
+        limit: Option<usize>,
+        show_skipped: bool,
+        summary: &mut RunSummary,
+    ) -> Vec<String> {
+        let filter = self.strategy.email_filter();
+        if filter.is_empty() {
+            return message_ids;
+        }
+        
+        let mut kept = Vec::new();
+        for message_id in message_ids {
+            if limit.is_some_and(|limit| kept.len() >= limit) {
+                break;
+            }
+            
+            let (subject, from) = match gmail_client.fetch_email_metadata(&message_id).await {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    // Let the processing step report the error
+                    warn!("Unable to fetch metadata of email {} for filtering: {}", message_id, e);
+                    kept.push(message_id);
+                    continue;
+                }
+            };
+            
+            match filter.skip_reason(&subject, &from) {
+                Some(reason) => {
+                    info!("Skipping {} email {} ({} / {}): {}", 
+                          self.strategy.processor_name(), message_id, subject, from, reason);
+                    if show_skipped {
+                        println!("{}", self.config.locale.email_skipped(&message_id, &subject, &reason));
+                    }
+                    summary.emails.push(EmailOutcome {
+                        message_id,
+                        status: EmailStatus::Skipped(reason),
+                        records: 0,
+                        attachments: Vec::new(),
+                        headers: None,
+                        readings: Vec::new(),
+                        duration: Duration::ZERO,
+                    });
+                }
+                None => kept.push(message_id),
+            }
+        }
+        
+        kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_with_timeout() {
+        let stalled = async {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            Ok(1)
+        };
+        let error = with_timeout(Some(Duration::from_millis(20)), stalled).await.unwrap_err();
+        assert_eq!(error.to_string(), "Processing cancelled after 20ms (EMAIL_TIMEOUT_SECONDS)");
+        assert_eq!(classify(&error), ErrorClass::Transient);
+        assert_eq!(with_timeout(None, async { Ok(2) }).await.unwrap(), 2);
     }
 }
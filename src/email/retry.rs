@@ -0,0 +1,63 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::RetryConfig;
+
+/// Exponential backoff applied to emails whose processing failed
+///
+/// The first retry waits `base_delay`, each further failure doubles the wait,
+/// capped at `max_delay` (`RETRY_BASE_DELAY_MINUTES`, `RETRY_MAX_DELAY_HOURS`).
+/// After `max_attempts` failures (`RETRY_MAX_ATTEMPTS`) the email is dead-lettered.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    base_delay: Duration,
+    max_delay: Duration,
+    max_attempts: u32,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &RetryConfig) -> Self {
+        RetryPolicy {
+            base_delay: Duration::minutes(config.base_delay_minutes as i64),
+            max_delay: Duration::hours(config.max_delay_hours as i64),
+            max_attempts: config.max_attempts.max(1),
+        }
+    }
+
+    /// Delay before the next attempt once an email has failed `attempts` times
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 1i32.checked_shl(attempts.saturating_sub(1)).unwrap_or(i32::MAX);
+        self.base_delay.checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// Whether an email that failed `attempts` times goes to the dead-letter state
+    pub fn is_exhausted(&self, attempts: u32) -> bool {
+        attempts >= self.max_attempts
+    }
+
+    pub fn next_attempt(&self, attempts: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + self.delay(attempts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::from_config(&RetryConfig {
+            base_delay_minutes: 30,
+            max_delay_hours: 24,
+            max_attempts: 5,
+        });
+
+        assert_eq!(policy.delay(1), Duration::minutes(30));
+        assert_eq!(policy.delay(2), Duration::hours(1));
+        assert_eq!(policy.delay(4), Duration::hours(4));
+        assert_eq!(policy.delay(10), Duration::hours(24));
+        assert_eq!(policy.delay(100), Duration::hours(24));
+        assert!(!policy.is_exhausted(4));
+        assert!(policy.is_exhausted(5));
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Serialize;
+
+use crate::email::ExtractError;
+use crate::gmail_client::GmailError;
+use crate::storage::StorageError;
+
+/// How a failure is handled by the retry queue and the notifications
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorClass {
+    /// Network, quota or database outage: the same email may succeed later
+    Transient,
+    /// Gmail token missing, expired or revoked: every email fails until re-authorized
+    Auth,
+    /// The email itself cannot be processed: retrying it cannot help
+    Permanent,
+}
+
+impl ErrorClass {
+    pub fn key(&self) -> &'static str {
+        match self {
+            ErrorClass::Transient => "transient",
+            ErrorClass::Auth => "auth",
+            ErrorClass::Permanent => "permanent",
+        }
+    }
+
+    /// Whether retrying the same work later may succeed
+    pub fn is_retryable(&self) -> bool {
+        *self == ErrorClass::Transient
+    }
+}
+
+/// Class of an error, from the first typed error of its chain
+///
+/// Errors without a typed cause (I/O, unexpected failures) count as transient,
+/// so they keep the usual retry backoff.
+pub fn classify(error: &anyhow::Error) -> ErrorClass {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<GmailError>() {
+            return e.class();
+        }
+        if let Some(e) = cause.downcast_ref::<ExtractError>() {
+            return e.class();
+        }
+        if let Some(e) = cause.downcast_ref::<StorageError>() {
+            return e.class();
+        }
+        if let Some(e) = cause.downcast_ref::<sqlx::Error>() {
+            return StorageError::sqlx_class(e);
+        }
+    }
+    ErrorClass::Transient
+}
+
+/// Whether Gmail refused a request of the chain for quota or rate limit
+pub fn is_quota_exceeded(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| matches!(cause.downcast_ref::<GmailError>(), Some(GmailError::Quota(_))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_classify_through_context() {
+        let auth: anyhow::Result<()> = Err(GmailError::Auth("invalid_grant".to_string()).into());
+        let auth = auth.context("Error searching for emails").unwrap_err();
+        assert_eq!(classify(&auth), ErrorClass::Auth);
+
+        let quota = anyhow::Error::from(GmailError::Quota("rateLimitExceeded".to_string()));
+        assert_eq!(classify(&quota), ErrorClass::Transient);
+        assert!(is_quota_exceeded(&quota.context("Error reading email")));
+        assert!(!is_quota_exceeded(&auth));
+
+        let extract = anyhow::Error::from(ExtractError::NoData("No pool metrics found in email text".to_string()))
+            .context("Failed to extract pool metrics from email");
+        assert_eq!(classify(&extract), ErrorClass::Permanent);
+
+        let storage = anyhow::Error::from(StorageError::Unavailable(sqlx::Error::PoolTimedOut));
+        assert_eq!(classify(&storage), ErrorClass::Transient);
+        assert_eq!(classify(&anyhow::Error::from(sqlx::Error::PoolClosed)), ErrorClass::Transient);
+
+        assert_eq!(classify(&anyhow::anyhow!("unexpected")), ErrorClass::Transient);
+    }
+
+    #[test]
+    fn test_gmail_status_classes() {
+        assert_eq!(GmailError::from_status(401, "authError", String::new()).class(), ErrorClass::Auth);
+        assert_eq!(GmailError::from_status(403, "insufficientPermissions", String::new()).class(), ErrorClass::Auth);
+        assert_eq!(GmailError::from_status(403, "userRateLimitExceeded", String::new()).class(), ErrorClass::Transient);
+        assert_eq!(GmailError::from_status(429, "", String::new()).class(), ErrorClass::Transient);
+        assert_eq!(GmailError::from_status(503, "backendError", String::new()).class(), ErrorClass::Transient);
+        assert_eq!(GmailError::from_status(404, "notFound", String::new()).class(), ErrorClass::Permanent);
+    }
+}
@@ -0,0 +1,473 @@
+use anyhow::{Result, Context};
+use arrow::array::{ArrayRef, Float64Array, Int32Array, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::storage::{ReadingRange, SensorInfo, Storage, MAX_PAGE_SIZE};
+
+/// Columns of the readings partitions, with their DuckDB types
+const READING_COLUMNS: [(&str, &str); 4] = [
+    ("timestamp", "TIMESTAMPTZ"), ("temperature", "DOUBLE"), ("humidity", "DOUBLE"), ("location", "VARCHAR"),
+];
+
+const POOL_COLUMNS: [(&str, &str); 5] = [
+    ("pool_id", "VARCHAR"), ("timestamp", "TIMESTAMPTZ"), ("temperature", "DOUBLE"), ("ph", "DOUBLE"), ("orp", "INTEGER"),
+];
+
+const SENSOR_COLUMNS: [(&str, &str); 5] = [
+    ("sensor_id", "VARCHAR"), ("location", "VARCHAR"), ("site", "VARCHAR"), ("sensor_type", "VARCHAR"), ("unit", "VARCHAR"),
+];
+
+/// Rows of a Parquet row group, written as one Arrow record batch
+const PARQUET_BATCH_ROWS: usize = 10_000;
+
+/// File format of an export (`--format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// CSV with a header line
+    #[default]
+    Csv,
+    /// JSON lines, one object per reading
+    Json,
+    /// Apache Parquet (Snappy), typed columns
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    /// DuckDB table function reading files of this format
+    fn duckdb_reader(&self, pattern: &str, columns: &[(&str, &str)], hive: bool) -> String {
+        let hive = if hive { ", hive_partitioning = true" } else { "" };
+        let columns = columns.iter()
+            .map(|(name, kind)| format!("'{}': '{}'", name, kind))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self {
+            ExportFormat::Csv => format!("read_csv('{}', header = true{},\n              columns = {{{}}})", pattern, hive, columns),
+            ExportFormat::Json => format!("read_json('{}', format = 'newline_delimited'{},\n              columns = {{{}}})", pattern, hive, columns),
+            ExportFormat::Parquet => format!("read_parquet('{}'{})", pattern, hive),
+        }
+    }
+}
+
+/// Rows of one exported file
+enum RowWriter {
+    Csv(Box<csv::Writer<File>>),
+    Json(BufWriter<File>, Vec<&'static str>),
+    Parquet(Box<ParquetRows>),
+}
+
+/// Rows waiting for the next record batch of a Parquet file
+struct ParquetRows {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    rows: Vec<Vec<Value>>,
+}
+
+impl RowWriter {
+    /// Create `path` (parent directories included) and write the CSV header
+    fn create(path: &Path, format: ExportFormat, columns: &[(&'static str, &str)]) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create directory {}", dir.display()))?;
+        }
+        let names: Vec<&'static str> = columns.iter().map(|(name, _)| *name).collect();
+        match format {
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_path(path)
+                    .with_context(|| format!("Unable to create {}", path.display()))?;
+                writer.write_record(&names)?;
+                Ok(RowWriter::Csv(Box::new(writer)))
+            }
+            ExportFormat::Json => {
+                let file = File::create(path).with_context(|| format!("Unable to create {}", path.display()))?;
+                Ok(RowWriter::Json(BufWriter::new(file), names))
+            }
+            ExportFormat::Parquet => {
+                let file = File::create(path).with_context(|| format!("Unable to create {}", path.display()))?;
+                let schema = parquet_schema(columns);
+                let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+                Ok(RowWriter::Parquet(Box::new(ParquetRows { writer, schema, rows: Vec::new() })))
+            }
+        }
+    }
+
+    /// One row, values in column order (null as an empty CSV field)
+    fn write(&mut self, values: &[Value]) -> Result<()> {
+        match self {
+            RowWriter::Csv(writer) => {
+                writer.write_record(values.iter().map(|value| match value {
+                    Value::Null => String::new(),
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                }))?;
+            }
+            RowWriter::Json(writer, names) => {
+                // Fields in column order (serde_json maps are sorted)
+                let fields: Vec<String> = names.iter()
+                    .zip(values)
+                    .map(|(name, value)| format!("{}:{}", Value::from(*name), value))
+                    .collect();
+                writeln!(writer, "{{{}}}", fields.join(","))?;
+            }
+            RowWriter::Parquet(parquet) => {
+                parquet.rows.push(values.to_vec());
+                if parquet.rows.len() >= PARQUET_BATCH_ROWS {
+                    parquet.write_batch()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            RowWriter::Csv(mut writer) => writer.flush()?,
+            RowWriter::Json(mut writer, _) => writer.flush()?,
+            RowWriter::Parquet(mut parquet) => {
+                parquet.write_batch()?;
+                parquet.writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ParquetRows {
+    /// Write the buffered rows as one record batch, column by column
+    fn write_batch(&mut self) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let columns: Vec<ArrayRef> = self.schema.fields().iter().enumerate().map(|(index, field)| {
+            let values = rows.iter().map(|row| &row[index]);
+            let array: ArrayRef = match field.data_type() {
+                DataType::Timestamp(..) => Arc::new(TimestampMicrosecondArray::from(values.map(|value| {
+                    value.as_str().and_then(|at| DateTime::parse_from_rfc3339(at).ok()).map(|at| at.timestamp_micros())
+                }).collect::<Vec<_>>()).with_timezone("UTC")),
+                DataType::Float64 => Arc::new(values.map(Value::as_f64).collect::<Float64Array>()),
+                DataType::Int32 => Arc::new(values.map(|value| value.as_i64().map(|value| value as i32)).collect::<Int32Array>()),
+                _ => Arc::new(values.map(|value| match value {
+                    Value::Null => None,
+                    Value::String(text) => Some(text.clone()),
+                    other => Some(other.to_string()),
+                }).collect::<StringArray>()),
+            };
+            array
+        }).collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+}
+
+/// Arrow schema of a file, from the DuckDB types of its columns
+fn parquet_schema(columns: &[(&str, &str)]) -> SchemaRef {
+    let fields: Vec<Field> = columns.iter().map(|(name, kind)| {
+        let data_type = match *kind {
+            "TIMESTAMPTZ" => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            "DOUBLE" => DataType::Float64,
+            "INTEGER" => DataType::Int32,
+            _ => DataType::Utf8,
+        };
+        Field::new(*name, data_type, true)
+    }).collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// Files and readings written by `export_readings`
+#[derive(Debug, Clone, Default)]
+pub struct ExportStats {
+    pub readings: usize,
+    pub files: Vec<PathBuf>,
+}
+
+/// Write the temperature readings of each sensor under
+/// `<output>/sensor=<id>/month=<YYYY-MM>/readings.<csv|json|parquet>`
+///
+/// Hive-style partitions, so DuckDB (`read_csv('<output>/**/*.csv', hive_partitioning = true)`)
+/// or pandas can analyze the history without querying the production database.
+/// Readings are read one page at a time; existing files of an exported month are replaced.
+pub async fn export_readings(
+    storage: &dyn Storage,
+    sensors: &[String],
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    output: &Path,
+    format: ExportFormat,
+) -> Result<ExportStats> {
+    let mut stats = ExportStats::default();
+
+    for sensor_id in sensors {
+        let mut range = ReadingRange {
+            sensor_id: sensor_id.clone(),
+            from,
+            to,
+            page_size: MAX_PAGE_SIZE,
+            ..Default::default()
+        };
+        let mut current: Option<(String, RowWriter)> = None;
+
+        loop {
+            let page = storage.get_readings_range(&range).await?;
+            for reading in &page.readings {
+                let month = reading.timestamp.format("%Y-%m").to_string();
+                if current.as_ref().map(|(current_month, _)| current_month) != Some(&month) {
+                    if let Some((_, writer)) = current.take() {
+                        writer.finish()?;
+                    }
+                    let path = partition_path(output, sensor_id, &month, format);
+                    current = Some((month, RowWriter::create(&path, format, &READING_COLUMNS)?));
+                    stats.files.push(path);
+                }
+
+                let Some((_, writer)) = current.as_mut() else { continue };
+                writer.write(&[
+                    json!(reading.timestamp.to_rfc3339()),
+                    json!(reading.temperature),
+                    json!(reading.humidity),
+                    json!(reading.location),
+                ])?;
+                stats.readings += 1;
+            }
+
+            match page.next {
+                Some(next) => range.after = Some(next),
+                None => break,
+            }
+        }
+
+        if let Some((_, writer)) = current {
+            writer.finish()?;
+        }
+        debug!("Sensor {} exported", sensor_id);
+    }
+
+    info!("📦 {} reading(s) exported to {} file(s) under {}", stats.readings, stats.files.len(), output.display());
+    Ok(stats)
+}
+
+/// Write the sensors to `<output>/sensors.<csv|json|parquet>`
+pub fn export_sensors(sensors: &[SensorInfo], output: &Path, format: ExportFormat) -> Result<PathBuf> {
+    let path = output.join(format!("sensors.{}", format.extension()));
+    let mut writer = RowWriter::create(&path, format, &SENSOR_COLUMNS)?;
+    for sensor in sensors {
+        writer.write(&[
+            json!(sensor.sensor_id),
+            json!(sensor.location),
+            json!(sensor.site),
+            json!(sensor.sensor_type),
+            json!(sensor.unit),
+        ])?;
+    }
+    writer.finish()?;
+    Ok(path)
+}
+
+/// Write the readings of every pool over `[from, to)` to `<output>/pools.<csv|json|parquet>`
+pub async fn export_pool_readings(
+    storage: &dyn Storage,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    output: &Path,
+    format: ExportFormat,
+) -> Result<(usize, PathBuf)> {
+    let readings = storage.pool_readings(from, to).await?;
+    let path = output.join(format!("pools.{}", format.extension()));
+    let mut writer = RowWriter::create(&path, format, &POOL_COLUMNS)?;
+    for reading in &readings {
+        writer.write(&[
+            json!(reading.pool_id),
+            json!(reading.timestamp.to_rfc3339()),
+            json!(reading.temperature),
+            json!(reading.ph),
+            json!(reading.orp),
+        ])?;
+    }
+    writer.finish()?;
+    Ok((readings.len(), path))
+}
+
+/// SQL script loading an export into DuckDB: `sensors`, `readings` and `pool_readings`
+/// tables, plus `daily_readings` and `daily_pool_readings` views
+pub fn duckdb_script(output: &Path, format: ExportFormat) -> String {
+    let dir = output.display().to_string().replace('\'', "''");
+    let ext = format.extension();
+    let sensors = format.duckdb_reader(&format!("{dir}/sensors.{ext}"), &SENSOR_COLUMNS, false);
+    let readings = format.duckdb_reader(&format!("{dir}/sensor=*/month=*/readings.{ext}"), &READING_COLUMNS, true);
+    let pools = format.duckdb_reader(&format!("{dir}/pools.{ext}"), &POOL_COLUMNS, false);
+    format!(
+        r#"SET TimeZone = 'UTC';
+
+CREATE OR REPLACE TABLE sensors AS
+SELECT * FROM {sensors};
+
+CREATE OR REPLACE TABLE readings AS
+SELECT sensor, timestamp, temperature, humidity, location
+FROM {readings};
+
+CREATE OR REPLACE TABLE pool_readings AS
+SELECT * FROM {pools};
+
+CREATE OR REPLACE VIEW daily_readings AS
+SELECT sensor, CAST(timestamp AS DATE) AS day, COUNT(*) AS readings,
+       MIN(temperature) AS min_temperature, MAX(temperature) AS max_temperature,
+       AVG(temperature) AS avg_temperature, AVG(humidity) AS avg_humidity
+FROM readings
+GROUP BY ALL
+ORDER BY sensor, day;
+
+CREATE OR REPLACE VIEW daily_pool_readings AS
+SELECT pool_id, CAST(timestamp AS DATE) AS day, COUNT(*) AS readings,
+       AVG(temperature) AS avg_temperature, AVG(ph) AS avg_ph, AVG(orp) AS avg_orp
+FROM pool_readings
+GROUP BY ALL
+ORDER BY pool_id, day;
+"#
+    )
+}
+
+/// Write `<output>/duckdb.sql`, referencing the export by its absolute path
+pub fn write_duckdb_script(output: &Path, format: ExportFormat) -> Result<PathBuf> {
+    let dir = std::fs::canonicalize(output).unwrap_or_else(|_| output.to_path_buf());
+    let script = output.join("duckdb.sql");
+    std::fs::write(&script, duckdb_script(&dir, format))
+        .with_context(|| format!("Unable to write {}", script.display()))?;
+    Ok(script)
+}
+
+/// Run `script` with the `duckdb` command line client against `database`
+///
+/// Returns false when the client is not installed.
+pub fn load_into_duckdb(script: &Path, database: &Path) -> Result<bool> {
+    let input = File::open(script)
+        .with_context(|| format!("Unable to read {}", script.display()))?;
+    let status = match std::process::Command::new("duckdb").arg(database).stdin(input).status() {
+        Ok(status) => status,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).context("Unable to run duckdb"),
+    };
+    if !status.success() {
+        anyhow::bail!("duckdb failed to load {} ({})", script.display(), status);
+    }
+    info!("🦆 Export loaded into {}", database.display());
+    Ok(true)
+}
+
+/// `sensor=<id>/month=<YYYY-MM>/readings.<ext>`, path separators of the sensor id replaced
+fn partition_path(output: &Path, sensor_id: &str, month: &str, format: ExportFormat) -> PathBuf {
+    let sensor = sensor_id.replace(['/', '\\'], "_");
+    output
+        .join(format!("sensor={}", sensor))
+        .join(format!("month={}", month))
+        .join(format!("readings.{}", format.extension()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueriot::PoolReading;
+    use crate::storage::SqliteStorage;
+    use crate::xsense::TemperatureReading;
+    use chrono::TimeZone;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[tokio::test]
+    async fn test_export_readings() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let readings: Vec<_> = [(1, 31), (2, 1), (2, 2)]
+            .into_iter()
+            .map(|(month, day)| TemperatureReading {
+                sensor_id: "pool/main".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2025, month, day, 8, 0, 0).unwrap(),
+                temperature: 26.5,
+                humidity: None,
+                location: None,
+            })
+            .collect();
+        storage.save_temperature_readings(&readings).await.unwrap();
+
+        let output = dir.path().join("export");
+        let sensors = ["pool/main".to_string(), "garage".to_string()];
+        let stats = export_readings(&storage, &sensors, None, None, &output, ExportFormat::Csv).await.unwrap();
+        assert_eq!(stats.readings, 3);
+        assert_eq!(stats.files, [
+            output.join("sensor=pool_main/month=2025-01/readings.csv"),
+            output.join("sensor=pool_main/month=2025-02/readings.csv"),
+        ]);
+
+        let february = std::fs::read_to_string(&stats.files[1]).unwrap();
+        assert_eq!(february, "timestamp,temperature,humidity,location\n\
+                              2025-02-01T08:00:00+00:00,26.5,,\n\
+                              2025-02-02T08:00:00+00:00,26.5,,\n");
+
+        let stats = export_readings(&storage, &sensors[..1], None, None, &output, ExportFormat::Json).await.unwrap();
+        assert_eq!(stats.files[0], output.join("sensor=pool_main/month=2025-01/readings.json"));
+        let january = std::fs::read_to_string(&stats.files[0]).unwrap();
+        assert_eq!(january, "{\"timestamp\":\"2025-01-31T08:00:00+00:00\",\"temperature\":26.5,\"humidity\":null,\"location\":null}\n");
+
+        let stats = export_readings(&storage, &sensors[..1], None, None, &output, ExportFormat::Parquet).await.unwrap();
+        assert_eq!(stats.files[1], output.join("sensor=pool_main/month=2025-02/readings.parquet"));
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(File::open(&stats.files[1]).unwrap()).unwrap()
+            .build().unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        let february = &batches[0];
+        assert_eq!(february.num_rows(), 2);
+        assert_eq!(february.schema().field(0).data_type(), &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())));
+        let timestamps = february.column(0).as_any().downcast_ref::<TimestampMicrosecondArray>().unwrap();
+        assert_eq!(timestamps.value(1), Utc.with_ymd_and_hms(2025, 2, 2, 8, 0, 0).unwrap().timestamp_micros());
+        let temperatures = february.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(temperatures.value(0), 26.5);
+        assert!(february.column(2).is_null(0));
+    }
+
+    #[tokio::test]
+    async fn test_export_pool_readings() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let reading = PoolReading {
+            pool_id: "spa".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 6, 1, 9, 30, 0).unwrap(),
+            temperature: Some(34.5),
+            ph: Some(7.2),
+            orp: None,
+        };
+        storage.save_pool_reading(&reading, "msg-1").await.unwrap();
+
+        let (count, path) = export_pool_readings(&storage, None, None, dir.path(), ExportFormat::Csv).await.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(std::fs::read_to_string(path).unwrap(),
+                   "pool_id,timestamp,temperature,ph,orp\nspa,2025-06-01T09:30:00+00:00,34.5,7.2,\n");
+    }
+
+    #[test]
+    fn test_duckdb_script() {
+        let script = duckdb_script(Path::new("/srv/export/l'été"), ExportFormat::Csv);
+        assert!(script.contains("read_csv('/srv/export/l''été/sensor=*/month=*/readings.csv'"));
+        assert!(script.contains("columns = {'timestamp': 'TIMESTAMPTZ',"));
+        assert!(script.contains("CREATE OR REPLACE VIEW daily_readings AS"));
+
+        let script = duckdb_script(Path::new("/srv/export"), ExportFormat::Parquet);
+        assert!(script.contains("FROM read_parquet('/srv/export/sensor=*/month=*/readings.parquet', hive_partitioning = true);"));
+        assert!(duckdb_script(Path::new("/srv/export"), ExportFormat::Json).contains("read_json('/srv/export/pools.json', format = 'newline_delimited',"));
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+use std::collections::BTreeMap;
+
+use crate::config::ForecastConfig;
+use crate::i18n::Locale;
+use crate::storage::{Bucket, SensorType, Storage};
+use crate::units::UnitSystem;
+
+/// Smoothing of the level and of the trend (Holt's linear method)
+const LEVEL_SMOOTHING: f64 = 0.5;
+const TREND_SMOOTHING: f64 = 0.3;
+
+/// Days with readings needed before a series is forecast
+const MIN_DAYS: usize = 3;
+
+/// Forecast of one daily series (pool average or room minimum)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyForecast {
+    pub id: String,
+    pub location: Option<String>,
+    /// Value of the last complete day with readings
+    pub last: f64,
+    /// Value expected for the forecast day
+    pub expected: f64,
+    /// Smoothed change per day
+    pub trend: f64,
+}
+
+/// Daily digest: tomorrow's pool temperatures and rooms heading to the freeze threshold
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    pub day: NaiveDate,
+    pub freeze_threshold: f64,
+    /// Average temperature of each pool
+    pub pools: Vec<DailyForecast>,
+    /// Minimum temperature of rooms whose expected minimum reaches the freeze threshold
+    pub freezing: Vec<DailyForecast>,
+}
+
+/// Holt's linear trend fitted on `series`, as (level, trend) after its last value
+///
+/// Needs at least two values.
+pub fn holt(series: &[f64]) -> Option<(f64, f64)> {
+    let [first, second, ..] = series else {
+        return None;
+    };
+    let (mut level, mut trend) = (*first, second - first);
+    for value in &series[1..] {
+        let previous = level;
+        level = LEVEL_SMOOTHING * value + (1.0 - LEVEL_SMOOTHING) * (level + trend);
+        trend = TREND_SMOOTHING * (level - previous) + (1.0 - TREND_SMOOTHING) * trend;
+    }
+    Some((level, trend))
+}
+
+/// Forecast `day` from one value per past day, days without readings skipped
+fn forecast_series(id: String, location: Option<String>, days: &BTreeMap<NaiveDate, f64>, day: NaiveDate) -> Option<DailyForecast> {
+    if days.len() < MIN_DAYS {
+        return None;
+    }
+    let values: Vec<f64> = days.values().copied().collect();
+    let (level, trend) = holt(&values)?;
+    let (last_day, last) = days.iter().next_back()?;
+    let steps = (day - *last_day).num_days() as f64;
+    Some(DailyForecast { id, location, last: *last, expected: level + steps * trend, trend })
+}
+
+/// Forecast tomorrow (UTC) from the complete days of the last `lookback_days`
+pub async fn forecast(storage: &dyn Storage, now: DateTime<Utc>, config: &ForecastConfig) -> Result<Forecast> {
+    let today = now.date_naive();
+    let to = today.and_time(NaiveTime::MIN).and_utc();
+    let from = to - TimeDelta::days(i64::from(config.lookback_days));
+    let day = today + TimeDelta::days(1);
+
+    // Rooms: forecast of the daily minimum, pools have their own readings
+    let pool_type = Some(SensorType::Pool.as_str());
+    let mut freezing = Vec::new();
+    for sensor in storage.sensors().await?.into_iter().filter(|sensor| sensor.sensor_type.as_deref() != pool_type) {
+        let minima: BTreeMap<NaiveDate, f64> = storage
+            .get_bucketed_readings(&sensor.sensor_id, Some(from), Some(to), Bucket::Day)
+            .await?
+            .into_iter()
+            .map(|bucket| (bucket.start.date_naive(), bucket.min))
+            .collect();
+        if let Some(room) = forecast_series(sensor.sensor_id, sensor.location, &minima, day) {
+            if room.expected <= config.freeze_threshold {
+                freezing.push(room);
+            }
+        }
+    }
+
+    // Pools: forecast of the daily average
+    let mut pool_days: BTreeMap<String, BTreeMap<NaiveDate, (f64, u32)>> = BTreeMap::new();
+    for reading in storage.pool_readings(Some(from), Some(to)).await? {
+        let Some(temperature) = reading.temperature else { continue };
+        let sum = pool_days.entry(reading.pool_id).or_default().entry(reading.timestamp.date_naive()).or_default();
+        sum.0 += temperature;
+        sum.1 += 1;
+    }
+    let pools = pool_days
+        .into_iter()
+        .filter_map(|(pool_id, days)| {
+            let averages = days.into_iter().map(|(date, (sum, count))| (date, sum / f64::from(count))).collect();
+            forecast_series(pool_id, None, &averages, day)
+        })
+        .collect();
+
+    Ok(Forecast { day, freeze_threshold: config.freeze_threshold, pools, freezing })
+}
+
+/// Text of the daily digest, temperatures in the display units
+pub fn render_forecast(forecast: &Forecast, locale: Locale, units: UnitSystem) -> String {
+    // Per-day change, converted without the Fahrenheit offset
+    let trend = |trend: f64| format!("{:+.1}{}", units.temperature(trend) - units.temperature(0.0), units.temperature_symbol());
+
+    let mut lines = vec![locale.forecast_header(&forecast.day.to_string())];
+    if forecast.pools.is_empty() {
+        lines.push(locale.forecast_no_pool().to_string());
+    }
+    for pool in &forecast.pools {
+        lines.push(locale.forecast_pool_line(&pool.id, &units.format_temperature(pool.expected),
+                                             &units.format_temperature(pool.last), &trend(pool.trend)));
+    }
+
+    if forecast.freezing.is_empty() {
+        lines.push(locale.forecast_no_freeze(&units.format_temperature(forecast.freeze_threshold)));
+    }
+    for room in &forecast.freezing {
+        let name = room.location.as_deref().unwrap_or(&room.id);
+        lines.push(locale.forecast_freeze_line(name, &units.format_temperature(room.expected), &trend(room.trend)));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueriot::PoolReading;
+    use crate::storage::SqliteStorage;
+    use crate::xsense::TemperatureReading;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_holt() {
+        assert_eq!(holt(&[20.0]), None);
+        let (level, trend) = holt(&[10.0, 9.0, 8.0, 7.0]).unwrap();
+        assert!((level - 7.0).abs() < 1e-9 && (trend + 1.0).abs() < 1e-9, "{} {}", level, trend);
+        let (level, trend) = holt(&[20.0, 20.0, 20.0]).unwrap();
+        assert_eq!((level, trend), (20.0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_forecast() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        // Garage cooling by 2 °C a day, living room steady
+        let mut readings = Vec::new();
+        for day in 1..=5 {
+            for (sensor_id, minimum) in [("garage", 14.0 - 2.0 * f64::from(day)), ("salon", 19.0)] {
+                for (hour, offset) in [(5, 0.0), (15, 3.0)] {
+                    readings.push(TemperatureReading {
+                        sensor_id: sensor_id.to_string(),
+                        timestamp: Utc.with_ymd_and_hms(2025, 1, day, hour, 0, 0).unwrap(),
+                        temperature: minimum + offset,
+                        humidity: None,
+                        location: None,
+                    });
+                }
+            }
+            let pool = PoolReading {
+                pool_id: "main".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2025, 1, day, 9, 0, 0).unwrap(),
+                temperature: Some(20.0 + f64::from(day) / 2.0),
+                ph: None,
+                orp: None,
+            };
+            storage.save_pool_reading(&pool, &format!("email-{}", day)).await.unwrap();
+        }
+        storage.save_temperature_readings(&readings).await.unwrap();
+
+        let config = ForecastConfig { time: None, lookback_days: 14, freeze_threshold: 3.0 };
+        // Today's readings are incomplete and ignored
+        let now = Utc.with_ymd_and_hms(2025, 1, 6, 10, 0, 0).unwrap();
+        let forecast = forecast(&storage, now, &config).await.unwrap();
+        assert_eq!(forecast.day, NaiveDate::from_ymd_opt(2025, 1, 7).unwrap());
+
+        assert_eq!(forecast.freezing.len(), 1);
+        let garage = &forecast.freezing[0];
+        assert_eq!((garage.id.as_str(), garage.last), ("garage", 4.0));
+        assert!((garage.expected - 0.0).abs() < 1e-9, "{:?}", garage);
+
+        assert_eq!(forecast.pools.len(), 1);
+        assert!((forecast.pools[0].expected - 23.5).abs() < 1e-9, "{:?}", forecast.pools);
+
+        let text = render_forecast(&forecast, Locale::En, UnitSystem::Metric);
+        assert!(text.contains("garage"), "{}", text);
+        assert!(text.contains("23.5°C"), "{}", text);
+    }
+}
@@ -1,16 +1,129 @@
 use anyhow::{Result, Context};
+use chrono::NaiveDate;
 use google_gmail1::{Gmail, hyper, hyper_rustls, oauth2};
 use log::{info, debug, warn};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::{RwLock, Mutex};
 
-use crate::config::GmailConfig;
+use crate::config::{GmailAccess, GmailConfig};
+use crate::email::ExtractError;
+use crate::error::ErrorClass;
+use crate::i18n::Locale;
+
+/// Gmail API failure, classified from what Google returned
+#[derive(Debug, thiserror::Error)]
+pub enum GmailError {
+    /// Token missing, expired or revoked, or scope not granted
+    #[error("Gmail authorization failed: {0}")]
+    Auth(String),
+    /// Daily quota or per-user rate limit exceeded
+    #[error("Gmail API quota exceeded: {0}")]
+    Quota(String),
+    /// Network failure or Google server error
+    #[error("Gmail API unavailable: {0}")]
+    Unavailable(String),
+    /// Request refused for this email (deleted message, invalid request...)
+    #[error("Gmail API error: {0}")]
+    Api(String),
+}
+
+impl GmailError {
+    /// Classify an HTTP error status with the reason of its JSON body (e.g. `rateLimitExceeded`)
+    pub fn from_status(status: u16, reason: &str, message: String) -> Self {
+        // rateLimitExceeded, userRateLimitExceeded, dailyLimitExceeded, quotaExceeded
+        let quota = reason.contains("Limit") || reason.contains("quota") || reason == "RESOURCE_EXHAUSTED";
+        match status {
+            429 => GmailError::Quota(message),
+            403 if quota => GmailError::Quota(message),
+            401 | 403 => GmailError::Auth(message),
+            500.. => GmailError::Unavailable(message),
+            _ => GmailError::Api(message),
+        }
+    }
+    
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            GmailError::Auth(_) => ErrorClass::Auth,
+            GmailError::Quota(_) | GmailError::Unavailable(_) => ErrorClass::Transient,
+            GmailError::Api(_) => ErrorClass::Permanent,
+        }
+    }
+}
+
+impl From<google_gmail1::Error> for GmailError {
+    fn from(error: google_gmail1::Error) -> Self {
+        use google_gmail1::Error;
+        
+        let message = error.to_string();
+        match &error {
+            Error::MissingToken(_) | Error::MissingAPIKey => GmailError::Auth(message),
+            Error::HttpError(_) | Error::Io(_) => GmailError::Unavailable(message),
+            Error::BadRequest(body) => {
+                let status = body["error"]["code"].as_u64().unwrap_or(400) as u16;
+                let reason = body["error"]["errors"][0]["reason"].as_str()
+                    .or_else(|| body["error"]["status"].as_str())
+                    .unwrap_or_default();
+                GmailError::from_status(status, reason, message)
+            }
+            Error::Failure(response) => GmailError::from_status(response.status().as_u16(), "", message),
+            _ => GmailError::Api(message),
+        }
+    }
+}
+
+/// Action applied to an email once processed (`<SOURCE>_AFTER_PROCESSING`)
+///
+/// The todo label is always removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostAction {
+    /// Add the `<prefix>/done/<source>` label
+    DoneLabel,
+    /// Remove UNREAD
+    MarkRead,
+    /// Remove INBOX
+    Archive,
+    /// Move to the trash (emptied by Gmail after 30 days)
+    Trash,
+}
+
+impl PostAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "done_label" | "done" => Some(PostAction::DoneLabel),
+            "mark_read" | "read" => Some(PostAction::MarkRead),
+            "archive" => Some(PostAction::Archive),
+            "trash" => Some(PostAction::Trash),
+            _ => None,
+        }
+    }
+    
+    /// Comma-separated list, e.g. "done_label,mark_read,archive"; None if an entry is invalid
+    pub fn parse_list(value: &str) -> Option<Vec<Self>> {
+        value.split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(PostAction::parse)
+            .collect()
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostAction::DoneLabel => "done_label",
+            PostAction::MarkRead => "mark_read",
+            PostAction::Archive => "archive",
+            PostAction::Trash => "trash",
+        }
+    }
+}
 
 pub struct EmailInfo {
     pub content: Vec<u8>,
     pub date: chrono::DateTime<chrono::Utc>,
-    pub headers: String,
+    pub from: String,
+    pub subject: String,
 }
 
 /// Cache for Gmail labels to avoid repeated API calls
@@ -40,6 +153,14 @@ impl LabelCache {
         debug!("Label cache updated with {} labels", cache.len());
     }
 
+    /// Names of label IDs, IDs missing from the cache kept as they are (system labels such as INBOX)
+    async fn names(&self, ids: &[String]) -> Vec<String> {
+        let cache = self.labels.read().await;
+        ids.iter()
+            .map(|id| cache.iter().find(|(_, candidate)| *candidate == id).map_or(id.clone(), |(name, _)| name.clone()))
+            .collect()
+    }
+
     /// Get the number of cached labels
     async fn len(&self) -> usize {
         let cache = self.labels.read().await;
@@ -51,29 +172,74 @@ pub struct GmailClient {
     hub: Gmail<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
     label_cache: LabelCache,
     // Keep a reference to the authenticator for forcing token refresh
-    auth: Arc<Mutex<oauth2::authenticator::Authenticator<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>>>,
+    auth: Arc<Mutex<Authenticator>>,
+    // Number of Gmail API requests issued by this client
+    api_calls: AtomicUsize,
+    // Root of the todo/done labels (GMAIL_LABEL_PREFIX)
+    label_prefix: String,
+    // Scope requested on every call
+    access: GmailAccess,
+    // Label changes are skipped when read-only or with --no-label-changes
+    modify_labels: bool,
+    // Reception window of the todo searches (--since/--until)
+    search_since: Option<NaiveDate>,
+    search_until: Option<NaiveDate>,
+}
+
+/// OAuth2 authenticator of the Gmail hub
+pub type Authenticator = oauth2::authenticator::Authenticator<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>;
+
+/// Build the installed-flow authenticator persisting its token to GMAIL_TOKEN_CACHE_PATH
+///
+/// No request is made: the consent flow runs on the first token request
+/// when the cache holds no usable token.
+pub async fn build_authenticator(config: &GmailConfig) -> Result<Authenticator> {
+    // Read OAuth2 client credentials from file
+    let secret = oauth2::read_application_secret(&config.credentials_path)
+        .await
+        .context("Unable to read OAuth2 client credentials file")?;
+    
+    // Create authenticator with token persistence
+    // Note: tokens are cached per scope, so switching GMAIL_SCOPE runs the consent flow again
+    oauth2::InstalledFlowAuthenticator::builder(
+        secret,
+        oauth2::InstalledFlowReturnMethod::HTTPRedirect,
+    )
+    .persist_tokens_to_disk(&config.token_cache_path)
+    .build()
+    .await
+    .context("Unable to create OAuth2 authenticator")
+}
+
+/// Build a device-flow authenticator sharing the token cache of `build_authenticator`
+///
+/// The consent runs on another device: a URL and a code are printed, no
+/// local browser or redirect port is needed (containers, headless servers).
+pub async fn build_device_authenticator(config: &GmailConfig) -> Result<Authenticator> {
+    let secret = oauth2::read_application_secret(&config.credentials_path)
+        .await
+        .context("Unable to read OAuth2 client credentials file")?;
+    
+    oauth2::DeviceFlowAuthenticator::builder(secret)
+        .persist_tokens_to_disk(&config.token_cache_path)
+        .build()
+        .await
+        .context("Unable to create OAuth2 device flow authenticator")
+}
+
+/// Scope requested for an access: `gmail.modify` (read and change labels) or `gmail.readonly`
+pub fn access_scope(access: GmailAccess) -> google_gmail1::api::Scope {
+    match access {
+        GmailAccess::Modify => google_gmail1::api::Scope::Modify,
+        GmailAccess::ReadOnly => google_gmail1::api::Scope::Readonly,
+    }
 }
 
 impl GmailClient {
     pub async fn new(config: &GmailConfig) -> Result<Self> {
         info!("Connecting to Gmail API via OAuth2");
         
-        // Read OAuth2 client credentials from file
-        let secret = oauth2::read_application_secret(&config.credentials_path)
-            .await
-            .context("Unable to read OAuth2 client credentials file")?;
-        
-        // Create authenticator with token persistence
-        // Note: We use Scope::Modify on all API calls, which is the broadest scope available
-        // in google-gmail1 (covers reading, modifying labels, and managing emails)
-        let auth = oauth2::InstalledFlowAuthenticator::builder(
-            secret,
-            oauth2::InstalledFlowReturnMethod::HTTPRedirect,
-        )
-        .persist_tokens_to_disk(&config.token_cache_path)
-        .build()
-        .await
-        .context("Unable to create OAuth2 authenticator")?;
+        let auth = build_authenticator(config).await?;
         
         // Create HTTP client
         let connector = hyper_rustls::HttpsConnectorBuilder::new()
@@ -96,6 +262,12 @@ impl GmailClient {
             hub,
             label_cache: LabelCache::new(),
             auth: auth_arc,
+            api_calls: AtomicUsize::new(0),
+            label_prefix: config.label_prefix.clone(),
+            access: config.access,
+            modify_labels: config.modifies_labels(),
+            search_since: config.search_since,
+            search_until: config.search_until,
         };
         
         // Initialize label cache on startup
@@ -117,20 +289,40 @@ impl GmailClient {
         // Force token refresh by calling .token() which will refresh if expired or close to expiration
         // This ensures the token cache file is updated
         let auth = self.auth.lock().await;
-        let scopes = &[google_gmail1::api::Scope::Modify.as_ref()];
+        let scope = self.scope();
         
-        match auth.force_refreshed_token(scopes).await {
+        match auth.force_refreshed_token(&[scope.as_ref()]).await {
             Ok(_token) => {
                 info!("✅ Token refreshed successfully and persisted to cache");
                 Ok(())
             }
             Err(e) => {
                 warn!("⚠️  Error refreshing token: {}", e);
-                Err(anyhow::anyhow!("Token refresh failed: {}", e))
+                Err(GmailError::Auth(format!("token refresh failed: {}", e)).into())
             }
         }
     }
 
+    /// OAuth2 scope of the configured access (`GMAIL_SCOPE`)
+    pub fn scope(&self) -> google_gmail1::api::Scope {
+        access_scope(self.access)
+    }
+    
+    /// Whether labels may be changed (false with `GMAIL_SCOPE=readonly` or `--no-label-changes`)
+    pub fn can_modify(&self) -> bool {
+        self.modify_labels
+    }
+    
+    /// Number of Gmail API requests issued since this client was created
+    pub fn api_call_count(&self) -> usize {
+        self.api_calls.load(Ordering::Relaxed)
+    }
+    
+    /// Record one Gmail API request
+    fn track_call(&self) {
+        self.api_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Refresh the label cache from Gmail API
     /// Should be called at startup and before processing each batch of emails
     async fn refresh_label_cache(&self) -> Result<()> {
@@ -138,12 +330,15 @@ impl GmailClient {
         
         let user_id = "me";
         
+        self.track_call();
+        
         let result = self.hub
             .users()
             .labels_list(user_id)
-            .add_scope(google_gmail1::api::Scope::Modify)
+            .add_scope(self.scope())
             .doit()
             .await
+            .map_err(GmailError::from)
             .context("Unable to list labels")?;
         
         let labels = result.1.labels.unwrap_or_default();
@@ -167,6 +362,11 @@ impl GmailClient {
         Ok(())
     }
 
+    /// Whether a label exists in the mailbox (labels under the prefix are created by hand in Gmail)
+    pub async fn label_exists(&self, label_name: &str) -> bool {
+        self.get_label_id(label_name).await.is_some()
+    }
+    
     /// Get a label ID from cache, with fallback to refresh if not found
     async fn get_label_id(&self, label_name: &str) -> Option<String> {
         // Try cache first
@@ -183,14 +383,20 @@ impl GmailClient {
         None
     }
     
+    /// Full name of a label under the configured prefix, e.g. "homemetrics/todo/xsense"
+    pub fn label(&self, name: &str) -> String {
+        format!("{}/{}", self.label_prefix, name)
+    }
+    
     pub async fn search_xsense_emails(&self) -> Result<Vec<String>> {
-        info!("Searching for emails with label 'homemetrics/todo/xsense'");
+        let todo_label = self.label("todo/xsense");
+        info!("Searching for emails with label '{}'", todo_label);
         
         // Refresh label cache before searching
         self.refresh_label_cache().await?;
         
         let user_id = "me";
-        let query = "label:homemetrics/todo/xsense";
+        let query = label_search_query(&todo_label, self.search_since, self.search_until);
         
         debug!("Search criteria: {}", query);
         
@@ -202,11 +408,13 @@ impl GmailClient {
             page_count += 1;
             debug!("Fetching page {} of results...", page_count);
             
+            self.track_call();
+            
             let mut request = self.hub
                 .users()
                 .messages_list(user_id)
-                .q(query)
-                .add_scope(google_gmail1::api::Scope::Modify);
+                .q(&query)
+                .add_scope(self.scope());
             
             // Add page token if we have one
             if let Some(token) = page_token.as_ref() {
@@ -216,6 +424,7 @@ impl GmailClient {
             let result = request
                 .doit()
                 .await
+                .map_err(GmailError::from)
                 .context("Error searching for emails")?;
             
             let response = result.1;
@@ -240,34 +449,43 @@ impl GmailClient {
             info!("More results available, fetching next page...");
         }
         
-        info!("Found {} email(s) with label 'homemetrics/todo/xsense' across {} page(s)", 
-              all_message_ids.len(), page_count);
+        info!("Found {} email(s) with label '{}' across {} page(s)", 
+              all_message_ids.len(), todo_label, page_count);
         
         Ok(all_message_ids)
     }
     
-    /// List all Gmail labels with their IDs and names
-    pub async fn list_labels(&self) -> Result<()> {
+    /// Print the labels with their IDs, only those whose name contains `filter` if given
+    pub async fn list_labels(&self, locale: Locale, filter: Option<&str>) -> Result<()> {
         info!("Retrieving Gmail labels list");
         
         let user_id = "me";
         
+        self.track_call();
+        
         let result = self.hub
             .users()
             .labels_list(user_id)
-            .add_scope(google_gmail1::api::Scope::Modify)
+            .add_scope(self.scope())
             .doit()
             .await
+            .map_err(GmailError::from)
             .context("Unable to list labels")?;
         
-        let labels = result.1.labels.unwrap_or_default();
+        let mut labels = result.1.labels.unwrap_or_default();
+        if let Some(filter) = filter {
+            labels.retain(|label| label_matches(label.name.as_deref().unwrap_or(""), filter));
+        }
         
         if labels.is_empty() {
-            println!("No labels found.");
+            match filter {
+                Some(filter) => println!("{}", locale.no_labels_matching(filter)),
+                None => println!("{}", locale.no_labels_found()),
+            }
             return Ok(());
         }
         
-        println!("Found {} label(s):\n", labels.len());
+        println!("{}\n", locale.labels_found(labels.len()));
         println!("{:<40} {:<30} {:<15}", "Label Name", "Label ID", "Type");
         println!("{}", "=".repeat(85));
         
@@ -310,15 +528,17 @@ impl GmailClient {
         let user_id = "me";
         
         // Retrieve only headers with METADATA format
+        self.track_call();
         let result = self.hub
             .users()
             .messages_get(user_id, message_id)
             .format("metadata")
             .add_metadata_headers("From")
             .add_metadata_headers("Subject")
-            .add_scope(google_gmail1::api::Scope::Modify)
+            .add_scope(self.scope())
             .doit()
             .await
+            .map_err(GmailError::from)
             .context("Unable to retrieve email metadata")?;
         
         let message = result.1;
@@ -344,17 +564,51 @@ impl GmailClient {
         Ok((subject, from))
     }
     
+    /// Retrieve the Gmail internal date of an email (milliseconds since epoch)
+    pub async fn fetch_internal_date(&self, message_id: &str) -> Result<i64> {
+        self.track_call();
+        let result = self.hub
+            .users()
+            .messages_get("me", message_id)
+            .format("minimal")
+            .add_scope(self.scope())
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .context("Unable to retrieve email date")?;
+        
+        result.1.internal_date
+            .ok_or_else(|| anyhow::anyhow!("Email {} has no internal date", message_id))
+    }
+    
+    /// Names of the labels of an email
+    pub async fn fetch_label_names(&self, message_id: &str) -> Result<Vec<String>> {
+        self.track_call();
+        let result = self.hub
+            .users()
+            .messages_get("me", message_id)
+            .format("minimal")
+            .add_scope(self.scope())
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .with_context(|| format!("Unable to retrieve email {}", message_id))?;
+        
+        Ok(self.label_cache.names(&result.1.label_ids.unwrap_or_default()).await)
+    }
+    
     pub async fn fetch_email_complete(&self, message_id: &str) -> Result<EmailInfo> {
         debug!("Complete email retrieval for ID: {}", message_id);
         
         let user_id = "me";
         
         // Retrieve complete message with RAW format
+        self.track_call();
         let result = self.hub
             .users()
             .messages_get(user_id, message_id)
             .format("raw")
-            .add_scope(google_gmail1::api::Scope::Modify)
+            .add_scope(self.scope())
             .doit()
             .await;
         
@@ -362,7 +616,7 @@ impl GmailClient {
             Ok((_, msg)) => msg,
             Err(e) => {
                 warn!("Error retrieving in RAW format: {}", e);
-                return Err(anyhow::anyhow!("Unable to retrieve email: {}", e));
+                return Err(GmailError::from(e).into());
             }
         };
         
@@ -377,13 +631,13 @@ impl GmailClient {
         
         debug!("Email retrieved, size: {} bytes", raw_bytes.len());
         
-        // Parser le contenu avec mail-parser
+        // Parse the content with mail-parser
         let email_str = String::from_utf8_lossy(&raw_bytes);
         let parsed_email = mail_parser::MessageParser::default()
             .parse(email_str.as_bytes())
-            .context("Unable to parse email")?;
+            .ok_or_else(|| ExtractError::Email("not a valid RFC 822 message".to_string()))?;
         
-        // Extraire la date
+        // Extract the date
         let email_date = if let Some(date_header) = parsed_email.date() {
             chrono::DateTime::from_timestamp(date_header.to_timestamp(), 0)
                 .map(|dt| dt.with_timezone(&chrono::Utc))
@@ -393,7 +647,7 @@ impl GmailClient {
             chrono::Utc::now()
         };
         
-        // Extraire les headers principaux
+        // Extract the main headers
         let from = parsed_email.from()
             .and_then(|addrs| addrs.first())
             .map(|addr| {
@@ -409,53 +663,364 @@ impl GmailClient {
             .unwrap_or("No subject")
             .to_string();
         
-        let headers = format!("De: {}\nObjet: {}", from, subject);
-        
         Ok(EmailInfo {
             content: raw_bytes,
             date: email_date,
-            headers,
+            from,
+            subject,
         })
     }
     
-    pub async fn mark_email_as_processed(&self, message_id: &str) -> Result<()> {
-        info!("Marking email {} as processed", message_id);
+    /// Labels removed and added when an email of `source` is marked processed
+    /// 
+    /// The todo label is always removed; `TRASH` stands for the move to the trash.
+    pub fn processed_label_changes(&self, source: &str, actions: &[PostAction]) -> (Vec<String>, Vec<String>) {
+        let mut remove = vec![self.label(&format!("todo/{}", source))];
+        let mut add = Vec::new();
+        for action in actions {
+            match action {
+                PostAction::DoneLabel => add.push(self.label(&format!("done/{}", source))),
+                PostAction::MarkRead => remove.push("UNREAD".to_string()),
+                PostAction::Archive => remove.push("INBOX".to_string()),
+                PostAction::Trash => add.push("TRASH".to_string()),
+            }
+        }
+        (remove, add)
+    }
+    
+    /// Apply the post-processing actions of `source` (`<SOURCE>_AFTER_PROCESSING`) to an email
+    pub async fn mark_processed(&self, message_id: &str, source: &str, actions: &[PostAction]) -> Result<()> {
+        if !self.can_modify() {
+            debug!("Label changes disabled, labels of email {} left unchanged", message_id);
+            return Ok(());
+        }
+        
+        info!("Marking {} email {} as processed", source, message_id);
         
         let user_id = "me";
+        let (remove, add) = self.processed_label_changes(source, actions);
         
         // Get label IDs from cache
-        let todo_label_id = self.get_label_id("homemetrics/todo/xsense").await;
-        let done_label_id = self.get_label_id("homemetrics/done/xsense").await;
-        
-        // Create modification request
         let mut modify_request = google_gmail1::api::ModifyMessageRequest::default();
+        let mut remove_labels = Vec::new();
+        let mut add_labels = Vec::new();
         
-        // Supprimer le label "todo"
-        if let Some(todo_id) = todo_label_id {
-            modify_request.remove_label_ids = Some(vec![todo_id]);
-            debug!("Removing label 'homemetrics/todo/xsense'");
-        } else {
-            warn!("Label 'homemetrics/todo/xsense' not found");
+        for label in &remove {
+            match self.get_label_id(label).await {
+                Some(id) => {
+                    remove_labels.push(id);
+                    debug!("Removing label '{}'", label);
+                }
+                None => warn!("Label '{}' not found", label),
+            }
         }
         
-        // Ajouter le label "done"
-        if let Some(done_id) = done_label_id {
-            modify_request.add_label_ids = Some(vec![done_id]);
-            debug!("Adding label 'homemetrics/done/xsense'");
-        } else {
-            warn!("Label 'homemetrics/done/xsense' not found, it will need to be created in Gmail");
+        // The trash has its own endpoint
+        for label in add.iter().filter(|label| *label != "TRASH") {
+            match self.get_label_id(label).await {
+                Some(id) => {
+                    add_labels.push(id);
+                    debug!("Adding label '{}'", label);
+                }
+                None => warn!("Label '{}' not found, it will need to be created in Gmail", label),
+            }
         }
         
+        modify_request.remove_label_ids = Some(remove_labels);
+        modify_request.add_label_ids = Some(add_labels);
+        
         // Apply modifications
+        self.track_call();
         self.hub
             .users()
             .messages_modify(modify_request, user_id, message_id)
-            .add_scope(google_gmail1::api::Scope::Modify)
+            .add_scope(self.scope())
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .context("Unable to modify email labels")?;
+        
+        if actions.contains(&PostAction::Trash) {
+            self.trash_email(message_id).await?;
+        }
+        
+        info!("✅ Email {} marked as processed ({})", message_id,
+              actions.iter().map(|action| action.as_str()).collect::<Vec<_>>().join(", "));
+        Ok(())
+    }
+    
+    /// Processed emails of a source (done label) older than `days` days
+    pub async fn search_done_emails(&self, source: &str, days: u32) -> Result<Vec<String>> {
+        let done_label = self.label(&format!("done/{}", source));
+        let query = format!("label:{} older_than:{}d", done_label, days);
+        debug!("Search criteria: {}", query);
+        
+        let mut message_ids = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            self.track_call();
+            let mut request = self.hub
+                .users()
+                .messages_list("me")
+                .q(&query)
+                .add_scope(self.scope());
+            if let Some(token) = page_token.as_ref() {
+                request = request.page_token(token);
+            }
+            
+            let response = request.doit().await
+                .map_err(GmailError::from)
+                .context("Error searching for processed emails")?.1;
+            message_ids.extend(response.messages.unwrap_or_default().into_iter().filter_map(|msg| msg.id));
+            
+            page_token = response.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+        
+        Ok(message_ids)
+    }
+    
+    /// Emails carrying `label` (full name), received between `since` and `until` included
+    ///
+    /// Used by `backfill` to re-read emails already processed.
+    pub async fn search_label_emails(&self, label: &str, since: Option<NaiveDate>, until: Option<NaiveDate>) -> Result<Vec<String>> {
+        let query = label_search_query(label, since, until);
+        info!("Searching for emails: {}", query);
+        
+        let mut message_ids = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            self.track_call();
+            let mut request = self.hub
+                .users()
+                .messages_list("me")
+                .q(&query)
+                .add_scope(self.scope());
+            if let Some(token) = page_token.as_ref() {
+                request = request.page_token(token);
+            }
+            
+            let response = request.doit().await
+                .map_err(GmailError::from)
+                .context("Error searching for emails to backfill")?.1;
+            message_ids.extend(response.messages.unwrap_or_default().into_iter().filter_map(|msg| msg.id));
+            
+            page_token = response.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+        
+        Ok(message_ids)
+    }
+    
+    /// Every email carrying `label`, oldest first, received after `after` (seconds since the epoch)
+    ///
+    /// Used by `backfill-gmail`: pages of 500 IDs keep the listing of years
+    /// of history to a few requests.
+    pub async fn list_label_history(&self, label: &str, after: Option<i64>) -> Result<Vec<String>> {
+        let mut query = format!("label:{}", label);
+        if let Some(after) = after {
+            query.push_str(&format!(" after:{}", after));
+        }
+        info!("Listing emails: {}", query);
+        
+        let mut message_ids = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            self.track_call();
+            let mut request = self.hub
+                .users()
+                .messages_list("me")
+                .q(&query)
+                .max_results(500)
+                .add_scope(self.scope());
+            if let Some(token) = page_token.as_ref() {
+                request = request.page_token(token);
+            }
+            
+            let response = request.doit().await
+                .map_err(GmailError::from)
+                .context("Error listing emails to backfill")?.1;
+            message_ids.extend(response.messages.unwrap_or_default().into_iter().filter_map(|msg| msg.id));
+            
+            page_token = response.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+        
+        // Gmail lists the newest first
+        message_ids.reverse();
+        Ok(message_ids)
+    }
+    
+    /// Move an email to the trash (emptied by Gmail after 30 days)
+    pub async fn trash_email(&self, message_id: &str) -> Result<()> {
+        if !self.can_modify() {
+            debug!("Label changes disabled, email {} not moved to the trash", message_id);
+            return Ok(());
+        }
+        
+        self.track_call();
+        self.hub
+            .users()
+            .messages_trash("me", message_id)
+            .add_scope(self.scope())
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .context("Unable to move email to the trash")?;
+        Ok(())
+    }
+    
+    /// Remove a label under the prefix from many emails at once (up to 1000 per request)
+    pub async fn remove_label(&self, message_ids: &[String], name: &str) -> Result<()> {
+        if !self.can_modify() || message_ids.is_empty() {
+            return Ok(());
+        }
+        
+        let label = self.label(name);
+        let Some(label_id) = self.get_label_id(&label).await else {
+            warn!("Label '{}' not found", label);
+            return Ok(());
+        };
+        
+        for chunk in message_ids.chunks(1000) {
+            let request = google_gmail1::api::BatchModifyMessagesRequest {
+                ids: Some(chunk.to_vec()),
+                remove_label_ids: Some(vec![label_id.clone()]),
+                add_label_ids: None,
+            };
+            self.track_call();
+            self.hub
+                .users()
+                .messages_batch_modify(request, "me")
+                .add_scope(self.scope())
+                .doit()
+                .await
+                .map_err(GmailError::from)
+                .context("Unable to modify email labels")?;
+        }
+        Ok(())
+    }
+    
+    /// Create a label under the prefix when missing, returns whether it was created
+    pub async fn ensure_label(&self, name: &str) -> Result<bool> {
+        if self.get_label_id(&self.label(name)).await.is_some() {
+            return Ok(false);
+        }
+        self.get_or_create_label(name).await?;
+        Ok(true)
+    }
+    
+    /// ID of a label under the prefix, creating it when missing
+    async fn get_or_create_label(&self, name: &str) -> Result<String> {
+        let label = self.label(name);
+        if let Some(id) = self.get_label_id(&label).await {
+            return Ok(id);
+        }
+        
+        info!("Creating label '{}'", label);
+        let request = google_gmail1::api::Label {
+            name: Some(label.clone()),
+            label_list_visibility: Some("labelShow".to_string()),
+            message_list_visibility: Some("show".to_string()),
+            ..Default::default()
+        };
+        self.track_call();
+        let (_, created) = self.hub
+            .users()
+            .labels_create(request, "me")
+            .add_scope(self.scope())
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .with_context(|| format!("Unable to create label '{}'", label))?;
+        
+        self.refresh_label_cache().await?;
+        created.id.with_context(|| format!("Label '{}' created without an ID", label))
+    }
+    
+    /// Make sure a Gmail filter sends the emails of `sender` to the todo label of a source, skipping the inbox
+    ///
+    /// The todo and done labels are created when missing. Uses the `gmail.settings.basic`
+    /// scope, so the first call asks for an additional authorization.
+    pub async fn setup_filter(&self, source: &str, sender: &str) -> Result<FilterSetup> {
+        let todo_label_id = self.get_or_create_label(&format!("todo/{}", source)).await?;
+        self.get_or_create_label(&format!("done/{}", source)).await?;
+        
+        self.track_call();
+        let (_, existing) = self.hub
+            .users()
+            .settings_filters_list("me")
+            .add_scope(google_gmail1::api::Scope::SettingBasic)
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .context("Unable to list Gmail filters")?;
+        if existing.filter.unwrap_or_default().iter().any(|filter| filter_applies_label(filter, sender, &todo_label_id)) {
+            return Ok(FilterSetup::AlreadyExists);
+        }
+        
+        let filter = google_gmail1::api::Filter {
+            criteria: Some(google_gmail1::api::FilterCriteria {
+                from: Some(sender.to_string()),
+                ..Default::default()
+            }),
+            action: Some(google_gmail1::api::FilterAction {
+                add_label_ids: Some(vec![todo_label_id]),
+                remove_label_ids: Some(vec!["INBOX".to_string()]),
+                forward: None,
+            }),
+            id: None,
+        };
+        self.track_call();
+        self.hub
+            .users()
+            .settings_filters_create(filter, "me")
+            .add_scope(google_gmail1::api::Scope::SettingBasic)
+            .doit()
+            .await
+            .map_err(GmailError::from)
+            .with_context(|| format!("Unable to create the Gmail filter for {}", sender))?;
+        
+        info!("✅ Gmail filter created: from:{} → {}", sender, self.label(&format!("todo/{}", source)));
+        Ok(FilterSetup::Created)
+    }
+    
+    /// Move an email between two labels under the prefix, e.g. "todo/xsense" → "error/xsense"
+    pub async fn move_email(&self, message_id: &str, from: &str, to: &str) -> Result<()> {
+        if !self.can_modify() {
+            debug!("Label changes disabled, labels of email {} left unchanged", message_id);
+            return Ok(());
+        }
+        
+        let (from_label, to_label) = (self.label(from), self.label(to));
+        
+        let mut modify_request = google_gmail1::api::ModifyMessageRequest::default();
+        match self.get_label_id(&from_label).await {
+            Some(id) => modify_request.remove_label_ids = Some(vec![id]),
+            None => warn!("Label '{}' not found", from_label),
+        }
+        match self.get_label_id(&to_label).await {
+            Some(id) => modify_request.add_label_ids = Some(vec![id]),
+            None => warn!("Label '{}' not found, it will need to be created in Gmail", to_label),
+        }
+        
+        self.track_call();
+        self.hub
+            .users()
+            .messages_modify(modify_request, "me", message_id)
+            .add_scope(self.scope())
             .doit()
             .await
+            .map_err(GmailError::from)
             .context("Unable to modify email labels")?;
         
-        info!("✅ Email {} marked as processed with label 'homemetrics/done/xsense'", message_id);
+        info!("Email {} moved from '{}' to '{}'", message_id, from_label, to_label);
         Ok(())
     }
     
@@ -464,13 +1029,14 @@ impl GmailClient {
     // ============================================================================
     
     pub async fn search_pool_emails(&self) -> Result<Vec<String>> {
-        info!("Searching for emails with label 'homemetrics/todo/blueriot'");
+        let todo_label = self.label("todo/blueriot");
+        info!("Searching for emails with label '{}'", todo_label);
         
         // Refresh label cache before searching
         self.refresh_label_cache().await?;
         
         let user_id = "me";
-        let query = "label:homemetrics/todo/blueriot";
+        let query = label_search_query(&todo_label, self.search_since, self.search_until);
         
         debug!("Search criteria: {}", query);
         
@@ -482,11 +1048,13 @@ impl GmailClient {
             page_count += 1;
             debug!("Fetching page {} of results...", page_count);
             
+            self.track_call();
+            
             let mut request = self.hub
                 .users()
                 .messages_list(user_id)
-                .q(query)
-                .add_scope(google_gmail1::api::Scope::Modify);
+                .q(&query)
+                .add_scope(self.scope());
             
             // Add page token if we have one
             if let Some(token) = page_token.as_ref() {
@@ -496,6 +1064,7 @@ impl GmailClient {
             let result = request
                 .doit()
                 .await
+                .map_err(GmailError::from)
                 .context("Error searching for pool emails")?;
             
             let response = result.1;
@@ -520,69 +1089,96 @@ impl GmailClient {
             info!("More results available, fetching next page...");
         }
         
-        info!("Found {} email(s) with label 'homemetrics/todo/blueriot' across {} page(s)", 
-              all_message_ids.len(), page_count);
+        info!("Found {} email(s) with label '{}' across {} page(s)", 
+              all_message_ids.len(), todo_label, page_count);
         
         Ok(all_message_ids)
     }
     
-    pub async fn mark_pool_email_as_processed(&self, message_id: &str) -> Result<()> {
-        info!("Marking pool email {} as processed", message_id);
-        
-        let user_id = "me";
-        
-        // Get label IDs from cache
-        let todo_label_id = self.get_label_id("homemetrics/todo/blueriot").await;
-        let done_label_id = self.get_label_id("homemetrics/done/blueriot").await;
-        let inbox_label_id = self.get_label_id("INBOX").await;
-        let unread_label_id = self.get_label_id("UNREAD").await;
-        
-        // Create modification request
-        let mut modify_request = google_gmail1::api::ModifyMessageRequest::default();
-        let mut remove_labels = Vec::new();
-        let mut add_labels = Vec::new();
-        
-        // Remove "todo" label
-        if let Some(todo_id) = todo_label_id {
-            remove_labels.push(todo_id);
-            debug!("Removing label 'homemetrics/todo/blueriot'");
-        } else {
-            warn!("Label 'homemetrics/todo/blueriot' not found");
-        }
-        
-        // Remove INBOX
-        if let Some(inbox_id) = inbox_label_id {
-            remove_labels.push(inbox_id);
-            debug!("Removing from INBOX");
-        }
-        
-        // Remove UNREAD (mark as read)
-        if let Some(unread_id) = unread_label_id {
-            remove_labels.push(unread_id);
-            debug!("Marking as read");
-        }
-        
-        // Add "done" label
-        if let Some(done_id) = done_label_id {
-            add_labels.push(done_id);
-            debug!("Adding label 'homemetrics/done/blueriot'");
-        } else {
-            warn!("Label 'homemetrics/done/blueriot' not found, it will need to be created in Gmail");
-        }
-        
-        modify_request.remove_label_ids = Some(remove_labels);
-        modify_request.add_label_ids = Some(add_labels);
-        
-        // Apply modifications
-        self.hub
-            .users()
-            .messages_modify(modify_request, user_id, message_id)
-            .add_scope(google_gmail1::api::Scope::Modify)
-            .doit()
-            .await
-            .context("Unable to modify pool email labels")?;
-        
-        info!("✅ Pool email {} marked as processed (read, archived, labeled 'done')", message_id);
-        Ok(())
+}
+
+/// Outcome of `GmailClient::setup_filter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterSetup {
+    Created,
+    AlreadyExists,
+}
+
+/// Whether a filter already labels the emails of `sender` with `label_id`
+fn filter_applies_label(filter: &google_gmail1::api::Filter, sender: &str, label_id: &str) -> bool {
+    let from = filter.criteria.as_ref().and_then(|criteria| criteria.from.as_deref());
+    let labels = filter.action.as_ref().and_then(|action| action.add_label_ids.as_deref());
+    from.is_some_and(|from| from.eq_ignore_ascii_case(sender))
+        && labels.is_some_and(|labels| labels.iter().any(|id| id == label_id))
+}
+
+/// Gmail query for a label and an inclusive date range
+///
+/// Gmail's `before:` is exclusive, so the day after `until` is used.
+fn label_search_query(label: &str, since: Option<NaiveDate>, until: Option<NaiveDate>) -> String {
+    let mut query = format!("label:{}", label);
+    if let Some(since) = since {
+        query.push_str(&format!(" after:{}", since.format("%Y/%m/%d")));
+    }
+    if let Some(until) = until.and_then(|until| until.succ_opt()) {
+        query.push_str(&format!(" before:{}", until.format("%Y/%m/%d")));
+    }
+    query
+}
+
+/// Whether a label name contains `filter`, ignoring case (`labels list --filter`)
+fn label_matches(name: &str, filter: &str) -> bool {
+    name.to_lowercase().contains(&filter.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_action_parse_list() {
+        assert_eq!(PostAction::parse_list("done_label, mark-read,archive"),
+                   Some(vec![PostAction::DoneLabel, PostAction::MarkRead, PostAction::Archive]));
+        assert_eq!(PostAction::parse_list("trash"), Some(vec![PostAction::Trash]));
+        assert_eq!(PostAction::parse_list(""), Some(Vec::new()));
+        assert_eq!(PostAction::parse_list("done_label,delete"), None);
+    }
+    #[test]
+    fn test_filter_applies_label() {
+        let filter = google_gmail1::api::Filter {
+            criteria: Some(google_gmail1::api::FilterCriteria {
+                from: Some("Support@X-Sense.com".to_string()),
+                ..Default::default()
+            }),
+            action: Some(google_gmail1::api::FilterAction {
+                add_label_ids: Some(vec!["Label_12".to_string()]),
+                ..Default::default()
+            }),
+            id: Some("ANe1Bmj".to_string()),
+        };
+        assert!(filter_applies_label(&filter, "support@x-sense.com", "Label_12"));
+        assert!(!filter_applies_label(&filter, "support@x-sense.com", "Label_13"));
+        assert!(!filter_applies_label(&filter, "noreply@blueriiot.com", "Label_12"));
+        assert!(!filter_applies_label(&google_gmail1::api::Filter::default(), "support@x-sense.com", "Label_12"));
+    }
+
+    #[test]
+    fn test_label_matches() {
+        assert!(label_matches("homemetrics/todo/xsense", "homemetrics"));
+        assert!(label_matches("HomeMetrics/done/blueriot", "homemetrics/DONE"));
+        assert!(!label_matches("INBOX", "homemetrics"));
+        assert!(label_matches("INBOX", ""));
+    }
+
+    #[test]
+    fn test_label_search_query() {
+        assert_eq!(label_search_query("homemetrics/done/xsense", None, None), "label:homemetrics/done/xsense");
+        let since = NaiveDate::from_ymd_opt(2024, 1, 1);
+        let until = NaiveDate::from_ymd_opt(2024, 1, 31);
+        assert_eq!(
+            label_search_query("homemetrics/done/xsense", since, until),
+            "label:homemetrics/done/xsense after:2024/01/01 before:2024/02/01"
+        );
+        assert_eq!(label_search_query("homemetrics/todo/blueriot", None, until), "label:homemetrics/todo/blueriot before:2024/02/01");
     }
 }
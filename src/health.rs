@@ -0,0 +1,186 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::i18n::Locale;
+use crate::storage::{ReadingRange, SortOrder, Storage};
+
+/// Sensor whose newest reading must be younger than `max_age` (`HEALTH_SENSORS`)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FreshnessRule {
+    /// Sensor ID, or `pool/<id>` for a Blue Riot pool
+    pub sensor_id: String,
+    pub max_age: Duration,
+}
+
+/// Parse `sensor=duration` entries separated by ';' (`salon=6h;pool/main=2d`)
+///
+/// Entries without a duration use `default_max_age`. Invalid entries are
+/// logged and skipped.
+pub fn parse_freshness_rules(value: &str, default_max_age: Duration) -> Vec<FreshnessRule> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (sensor_id, max_age) = match entry.split_once('=') {
+                Some((sensor_id, max_age)) => match humantime::parse_duration(max_age.trim()) {
+                    Ok(max_age) => (sensor_id.trim(), max_age),
+                    Err(_) => {
+                        warn!("Invalid HEALTH_SENSORS entry '{}' (expected sensor=duration, e.g. salon=6h) - ignored", entry);
+                        return None;
+                    }
+                },
+                None => (entry, default_max_age),
+            };
+            if sensor_id.is_empty() {
+                warn!("Invalid HEALTH_SENSORS entry '{}' (missing sensor) - ignored", entry);
+                return None;
+            }
+            Some(FreshnessRule { sensor_id: sensor_id.to_string(), max_age })
+        })
+        .collect()
+}
+
+/// Freshness of one required sensor
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorFreshness {
+    pub sensor_id: String,
+    /// Configured SLA, in seconds
+    pub max_age_secs: u64,
+    /// Newest stored reading, None when the sensor has none
+    pub last_reading: Option<DateTime<Utc>>,
+    /// Age of the newest reading, in seconds
+    pub age_secs: Option<i64>,
+    pub healthy: bool,
+}
+
+/// Result of `GET /health` and `homemetrics health`
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub checked_at: DateTime<Utc>,
+    pub sensors: Vec<SensorFreshness>,
+}
+
+impl HealthReport {
+    /// Sensors whose newest reading is missing or older than their SLA
+    pub fn stale(&self) -> impl Iterator<Item = &SensorFreshness> {
+        self.sensors.iter().filter(|sensor| !sensor.healthy)
+    }
+}
+
+/// Compare the newest reading of each required sensor with its SLA
+///
+/// Without any rule the report is healthy as soon as the storage answers.
+pub async fn check_freshness(storage: &dyn Storage, rules: &[FreshnessRule], now: DateTime<Utc>) -> Result<HealthReport> {
+    if rules.is_empty() {
+        storage.sensors().await?;
+    }
+
+    // Pools have their own table: read it once for all the pool rules
+    let mut pools: BTreeMap<String, DateTime<Utc>> = BTreeMap::new();
+    if rules.iter().any(|rule| rule.sensor_id.starts_with("pool/")) {
+        for reading in storage.pool_readings(None, None).await? {
+            let latest = pools.entry(reading.pool_id).or_insert(reading.timestamp);
+            *latest = (*latest).max(reading.timestamp);
+        }
+    }
+
+    let mut sensors = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let last_reading = match rule.sensor_id.strip_prefix("pool/") {
+            Some(pool_id) => pools.get(pool_id).copied(),
+            None => {
+                let range = ReadingRange {
+                    sensor_id: rule.sensor_id.clone(),
+                    order: SortOrder::Desc,
+                    page_size: 1,
+                    ..Default::default()
+                };
+                storage.get_readings_range(&range).await?.readings.first().map(|reading| reading.timestamp)
+            }
+        };
+        let age = last_reading.map(|timestamp| now - timestamp);
+        let healthy = age.is_some_and(|age| age.to_std().unwrap_or_default() <= rule.max_age);
+        sensors.push(SensorFreshness {
+            sensor_id: rule.sensor_id.clone(),
+            max_age_secs: rule.max_age.as_secs(),
+            last_reading,
+            age_secs: age.map(|age| age.num_seconds()),
+            healthy,
+        });
+    }
+
+    Ok(HealthReport { healthy: sensors.iter().all(|sensor| sensor.healthy), checked_at: now, sensors })
+}
+
+/// One line per required sensor, then the overall status
+pub fn render_health(report: &HealthReport, locale: Locale) -> String {
+    let mut lines: Vec<String> = report.sensors.iter().map(|sensor| {
+        let max_age = humantime::format_duration(Duration::from_secs(sensor.max_age_secs)).to_string();
+        let age = sensor.age_secs.map(|secs| humantime::format_duration(Duration::from_secs(secs.max(0) as u64)).to_string());
+        locale.health_sensor_line(&sensor.sensor_id, sensor.healthy, age.as_deref(), &max_age)
+    }).collect();
+    lines.push(locale.health_status(report.healthy, report.stale().count()));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueriot::PoolReading;
+    use crate::storage::SqliteStorage;
+    use crate::xsense::TemperatureReading;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_freshness_rules() {
+        let rules = parse_freshness_rules("salon=6h; pool/main = 2d;cave;bad=soon;=1h", Duration::from_secs(3600));
+        assert_eq!(rules, vec![
+            FreshnessRule { sensor_id: "salon".to_string(), max_age: Duration::from_secs(6 * 3600) },
+            FreshnessRule { sensor_id: "pool/main".to_string(), max_age: Duration::from_secs(2 * 86400) },
+            FreshnessRule { sensor_id: "cave".to_string(), max_age: Duration::from_secs(3600) },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_check_freshness() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap();
+        let reading = |sensor_id: &str, hours_ago| TemperatureReading {
+            sensor_id: sensor_id.to_string(),
+            timestamp: now - chrono::TimeDelta::hours(hours_ago),
+            temperature: 19.0,
+            humidity: None,
+            location: None,
+        };
+        storage.save_temperature_readings(&[reading("salon", 30), reading("salon", 2), reading("cave", 30)]).await.unwrap();
+        let pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp: now - chrono::TimeDelta::hours(20),
+            temperature: Some(24.0),
+            ph: None,
+            orp: None,
+        };
+        storage.save_pool_reading(&pool, "email-1").await.unwrap();
+
+        let rules = parse_freshness_rules("salon=6h;pool/main=1d", Duration::from_secs(3600));
+        let report = check_freshness(&storage, &rules, now).await.unwrap();
+        assert!(report.healthy);
+        assert_eq!(report.sensors[0].age_secs, Some(2 * 3600));
+
+        let rules = parse_freshness_rules("salon=6h;cave=1d;grenier=1d", Duration::from_secs(3600));
+        let report = check_freshness(&storage, &rules, now).await.unwrap();
+        assert!(!report.healthy);
+        let stale: Vec<_> = report.stale().map(|sensor| sensor.sensor_id.as_str()).collect();
+        assert_eq!(stale, ["cave", "grenier"]);
+        assert_eq!(report.sensors[2].last_reading, None);
+
+        assert!(check_freshness(&storage, &[], now).await.unwrap().healthy);
+    }
+}
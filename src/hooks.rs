@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Processing event a hook command can be attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// A processor run finished (`HOOK_ON_RUN_COMPLETE`)
+    RunComplete,
+    /// An alert rule or a mold alert fired (`HOOK_ON_ALERT`)
+    Alert,
+    /// An email or a whole run failed (`HOOK_ON_ERROR`)
+    Error,
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::RunComplete => "run_complete",
+            HookEvent::Alert => "alert",
+            HookEvent::Error => "error",
+        }
+    }
+}
+
+/// Shell commands run on processing events
+#[derive(Debug, Deserialize, Clone)]
+pub struct HooksConfig {
+    pub on_run_complete: Option<String>,
+    pub on_alert: Option<String>,
+    pub on_error: Option<String>,
+    pub timeout: Duration, // Hook commands still running after it are killed
+}
+
+impl HooksConfig {
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::RunComplete => self.on_run_complete.as_deref(),
+            HookEvent::Alert => self.on_alert.as_deref(),
+            HookEvent::Error => self.on_error.as_deref(),
+        }
+    }
+}
+
+/// Run the hook command of an event, if any
+///
+/// The command gets the event as JSON on its standard input, and its scalar
+/// fields as `HOMEMETRICS_<FIELD>` environment variables. A failing hook is
+/// logged, it never fails the processing.
+pub async fn run(config: &HooksConfig, event: HookEvent, data: serde_json::Value) {
+    let Some(command) = config.command(event) else {
+        return;
+    };
+    match execute(command, event, data, config.timeout).await {
+        Ok(()) => info!("🪝 Hook {} run", event.name()),
+        Err(e) => warn!("❌ Hook {} failed: {:#}", event.name(), e),
+    }
+}
+
+/// Fields of the event payload exported as environment variables
+fn environment(payload: &serde_json::Map<String, serde_json::Value>) -> Vec<(String, String)> {
+    payload
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => value.clone(),
+                serde_json::Value::Number(value) => value.to_string(),
+                serde_json::Value::Bool(value) => value.to_string(),
+                _ => return None,
+            };
+            Some((format!("HOMEMETRICS_{}", key.to_uppercase()), value))
+        })
+        .collect()
+}
+
+async fn execute(command: &str, event: HookEvent, data: serde_json::Value, timeout: Duration) -> Result<()> {
+    let mut payload = serde_json::Map::new();
+    payload.insert("event".to_string(), event.name().into());
+    match data {
+        serde_json::Value::Object(fields) => payload.extend(fields),
+        serde_json::Value::Null => {}
+        value => {
+            payload.insert("data".to_string(), value);
+        }
+    }
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(environment(&payload))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Unable to start '{}'", command))?;
+
+    let mut stdin = child.stdin.take().context("No stdin")?;
+    let body = serde_json::Value::Object(payload).to_string();
+    let output = tokio::time::timeout(timeout, async move {
+        // Commands are free to ignore their input
+        if let Err(e) = stdin.write_all(body.as_bytes()).await {
+            debug!("Hook input not read: {}", e);
+        }
+        drop(stdin);
+        child.wait_with_output().await
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Timed out after {}", humantime::format_duration(timeout)))??;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        debug!("Hook {} output: {}", event.name(), stdout.trim());
+    }
+    if !output.status.success() {
+        anyhow::bail!("'{}' exited with {}: {}", command, output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_execute() {
+        let dir = tempfile::tempdir().unwrap();
+        let (input, env) = (dir.path().join("input.json"), dir.path().join("env.txt"));
+        let command = format!("cat > {}; echo \"$HOMEMETRICS_EVENT $HOMEMETRICS_PROCESSOR $HOMEMETRICS_RECORDS\" > {}",
+                              input.display(), env.display());
+        let data = serde_json::json!({ "processor": "X-Sense", "records": 42, "sensors": { "salon": 3 } });
+        execute(&command, HookEvent::RunComplete, data, Duration::from_secs(5)).await.unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&input).unwrap()).unwrap();
+        assert_eq!(payload["event"], "run_complete");
+        assert_eq!(payload["sensors"]["salon"], 3);
+        assert_eq!(std::fs::read_to_string(&env).unwrap().trim(), "run_complete X-Sense 42");
+
+        let error = execute("echo boom >&2; exit 3", HookEvent::Error, serde_json::Value::Null, Duration::from_secs(5))
+            .await.unwrap_err();
+        assert!(error.to_string().contains("boom"), "{}", error);
+        assert!(execute("sleep 5", HookEvent::Alert, serde_json::Value::Null, Duration::from_millis(100)).await.is_err());
+    }
+}
@@ -0,0 +1,1355 @@
+use serde::Deserialize;
+
+use crate::alerts::Condition;
+use crate::email::RunBudget;
+use crate::templates::SensorStats;
+
+/// Language used for all user-facing output (console, Slack messages, reports)
+///
+/// Log messages stay in English so they can be searched and shared in issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    /// Parse a `LOCALE` value ("en", "fr", "fr_FR.UTF-8", ...)
+    pub fn parse(value: &str) -> Option<Self> {
+        let language = value.trim()
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        match language.as_str() {
+            "en" | "english" => Some(Locale::En),
+            "fr" | "french" | "francais" | "français" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    fn pick(&self, en: &'static str, fr: &'static str) -> &'static str {
+        match self {
+            Locale::En => en,
+            Locale::Fr => fr,
+        }
+    }
+
+    // ========================================================================
+    // Common labels
+    // ========================================================================
+
+    pub fn label_from(&self) -> &'static str {
+        self.pick("From", "De")
+    }
+
+    pub fn label_subject(&self) -> &'static str {
+        self.pick("Subject", "Objet")
+    }
+
+    pub fn label_date(&self) -> &'static str {
+        self.pick("Date", "Date")
+    }
+
+    pub fn label_pool(&self) -> &'static str {
+        self.pick("Pool", "Bassin")
+    }
+
+    pub fn label_temperature(&self) -> &'static str {
+        self.pick("Temperature", "Température")
+    }
+
+    pub fn not_available(&self) -> &'static str {
+        self.pick("N/A", "N/D")
+    }
+
+    // ========================================================================
+    // Email processing (dry-run console output)
+    // ========================================================================
+
+    pub fn dry_run_banner(&self, processor: &str) -> String {
+        match self {
+            Locale::En => format!("🧪 DRY-RUN MODE - {} ANALYSIS", processor.to_uppercase()),
+            Locale::Fr => format!("🧪 MODE DRY-RUN - ANALYSE {}", processor.to_uppercase()),
+        }
+    }
+
+    pub fn no_emails_found(&self, label: &str) -> String {
+        match self {
+            Locale::En => format!("❌ No emails found with label '{}'", label),
+            Locale::Fr => format!("❌ Aucun email trouvé avec le label '{}'", label),
+        }
+    }
+
+    pub fn no_emails_hint(&self, label: &str) -> String {
+        match self {
+            Locale::En => format!("   Hint: Add the label '{}' to emails to process", label),
+            Locale::Fr => format!("   Astuce : ajoutez le label '{}' aux emails à traiter", label),
+        }
+    }
+
+    pub fn emails_found(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("✅ Found {} email(s) matching criteria", count),
+            Locale::Fr => format!("✅ {} email(s) correspondant(s) aux critères", count),
+        }
+    }
+
+    pub fn email_progress(&self, index: usize, total: usize, message_id: &str) -> String {
+        format!("📧 Email {}/{} (ID: {})", index, total, message_id)
+    }
+
+    pub fn email_no_data(&self, message_id: &str) -> String {
+        match self {
+            Locale::En => format!("⚠️  Email {} analyzed but no data extracted", message_id),
+            Locale::Fr => format!("⚠️  Email {} analysé mais aucune donnée extraite", message_id),
+        }
+    }
+
+    pub fn email_partial(&self, message_id: &str, records: usize, failures: &str) -> String {
+        match self {
+            Locale::En => format!("⚠️  Email {} partially analyzed ({} record(s)), failed attachments: {}",
+                                  message_id, records, failures),
+            Locale::Fr => format!("⚠️  Email {} analysé partiellement ({} enregistrement(s)), pièces jointes en échec : {}",
+                                  message_id, records, failures),
+        }
+    }
+
+    pub fn label_changes(&self, remove: &str, add: &str) -> String {
+        match self {
+            Locale::En => format!("   🏷️  Labels: remove {} / add {}", remove, add),
+            Locale::Fr => format!("   🏷️  Labels : retrait de {} / ajout de {}", remove, add),
+        }
+    }
+
+    pub fn label_missing(&self, label: &str) -> String {
+        match self {
+            Locale::En => format!("   ⚠️  Label '{}' does not exist in Gmail, create it before a real run", label),
+            Locale::Fr => format!("   ⚠️  Le label '{}' n'existe pas dans Gmail, créez-le avant une vraie exécution", label),
+        }
+    }
+
+    pub fn labels_unchanged(&self, recorded: bool) -> &'static str {
+        match (self, recorded) {
+            (Locale::En, true) => "   🏷️  Labels unchanged (read-only or --no-label-changes), email recorded in processed_emails",
+            (Locale::En, false) => "   🏷️  Labels unchanged (read-only or --no-label-changes)",
+            (Locale::Fr, true) => "   🏷️  Labels inchangés (lecture seule ou --no-label-changes), email mémorisé dans processed_emails",
+            (Locale::Fr, false) => "   🏷️  Labels inchangés (lecture seule ou --no-label-changes)",
+        }
+    }
+
+    pub fn labels_unchanged_retry(&self, attempts: u32) -> String {
+        match self {
+            Locale::En => format!("   🏷️  Labels unchanged, email retried later (failure #{})", attempts),
+            Locale::Fr => format!("   🏷️  Labels inchangés, email relancé plus tard (échec n°{})", attempts),
+        }
+    }
+
+    pub fn email_analyzed(&self, message_id: &str, records: usize) -> String {
+        match self {
+            Locale::En => format!("✅ Email {} analyzed successfully ({} record(s))", message_id, records),
+            Locale::Fr => format!("✅ Email {} analysé avec succès ({} enregistrement(s))", message_id, records),
+        }
+    }
+
+    pub fn email_skipped(&self, message_id: &str, subject: &str, reason: &str) -> String {
+        match self {
+            Locale::En => format!("⏭️  Email {} skipped ({}): {}", message_id, subject, reason),
+            Locale::Fr => format!("⏭️  Email {} ignoré ({}) : {}", message_id, subject, reason),
+        }
+    }
+
+    pub fn email_analysis_error(&self, message_id: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("❌ Error analyzing email {}: {}", message_id, error),
+            Locale::Fr => format!("❌ Erreur lors de l'analyse de l'email {} : {}", message_id, error),
+        }
+    }
+
+    pub fn analysis_completed(&self, analyzed: usize, total: usize) -> String {
+        match self {
+            Locale::En => format!("🏁 Analysis completed: {} emails analyzed out of {}", analyzed, total),
+            Locale::Fr => format!("🏁 Analyse terminée : {} emails analysés sur {}", analyzed, total),
+        }
+    }
+
+    pub fn total_records(&self, records: usize) -> String {
+        match self {
+            Locale::En => format!("📊 Total records: {}", records),
+            Locale::Fr => format!("📊 Total des enregistrements : {}", records),
+        }
+    }
+
+    pub fn headers_title(&self) -> &'static str {
+        self.pick("📋 Headers:", "📋 En-têtes :")
+    }
+
+    pub fn email_date(&self, date: &str) -> String {
+        match self {
+            Locale::En => format!("📅 Email date: {}", date),
+            Locale::Fr => format!("📅 Date de l'email : {}", date),
+        }
+    }
+
+    pub fn email_content_title(&self) -> &'static str {
+        self.pick("📄 Email content:", "📄 Contenu de l'email :")
+    }
+
+    pub fn email_size(&self, bytes: usize) -> String {
+        match self {
+            Locale::En => format!("   Size: {} bytes", bytes),
+            Locale::Fr => format!("   Taille : {} octets", bytes),
+        }
+    }
+
+    pub fn no_attachments(&self) -> &'static str {
+        self.pick("⚠️  No attachments found in this email", "⚠️  Aucune pièce jointe dans cet email")
+    }
+
+    pub fn attachments_found(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("📎 Found {} attachment(s):", count),
+            Locale::Fr => format!("📎 {} pièce(s) jointe(s) trouvée(s) :", count),
+        }
+    }
+
+    pub fn attachment_line(&self, index: usize, filename: &str, bytes: usize, content_type: &str) -> String {
+        match self {
+            Locale::En => format!("   {}. {} ({} bytes, type: {})", index, filename, bytes, content_type),
+            Locale::Fr => format!("   {}. {} ({} octets, type : {})", index, filename, bytes, content_type),
+        }
+    }
+
+    pub fn processing_attachment(&self, index: usize, total: usize, filename: &str) -> String {
+        match self {
+            Locale::En => format!("🔍 Processing attachment {}/{}: {}", index, total, filename),
+            Locale::Fr => format!("🔍 Traitement de la pièce jointe {}/{} : {}", index, total, filename),
+        }
+    }
+
+    pub fn attachment_result(&self, attachment: &crate::email::AttachmentResult) -> String {
+        if let Some(error) = &attachment.error {
+            return match self {
+                Locale::En => format!("   📎 {}: ❌ {}", attachment.filename, error),
+                Locale::Fr => format!("   📎 {} : ❌ {}", attachment.filename, error),
+            };
+        }
+        match self {
+            Locale::En => format!("   📎 {}: {} extracted, {} new, {} duplicate(s), {} rejected",
+                                  attachment.filename, attachment.extracted, attachment.saved,
+                                  attachment.duplicates, attachment.rejected),
+            Locale::Fr => format!("   📎 {} : {} extraites, {} nouvelles, {} doublon(s), {} rejetée(s)",
+                                  attachment.filename, attachment.extracted, attachment.saved,
+                                  attachment.duplicates, attachment.rejected),
+        }
+    }
+
+    pub fn extraction_failed(&self, error: &str) -> String {
+        match self {
+            Locale::En => format!("   ⚠️  Unable to extract data: {}", error),
+            Locale::Fr => format!("   ⚠️  Impossible d'extraire les données : {}", error),
+        }
+    }
+
+    pub fn no_valid_readings(&self) -> &'static str {
+        self.pick("   ⚠️  No valid readings extracted", "   ⚠️  Aucune mesure valide extraite")
+    }
+
+    pub fn readings_extracted(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("   ✅ Extracted {} reading(s):", count),
+            Locale::Fr => format!("   ✅ {} mesure(s) extraite(s) :", count),
+        }
+    }
+
+    pub fn sensor_title(&self, sensor_id: &str) -> String {
+        match self {
+            Locale::En => format!("   📡 Sensor: {}", sensor_id),
+            Locale::Fr => format!("   📡 Capteur : {}", sensor_id),
+        }
+    }
+
+    pub fn reading_line(&self, first: bool, timestamp: &str, temperature: &str, humidity: &str) -> String {
+        match self {
+            Locale::En => format!("      {} {} | Temp: {} | Humidity: {}",
+                                  if first { "First:" } else { "Last: " }, timestamp, temperature, humidity),
+            Locale::Fr => format!("      {} {} | Temp : {} | Humidité : {}",
+                                  if first { "Première :" } else { "Dernière :" }, timestamp, temperature, humidity),
+        }
+    }
+
+    /// One reading of the full listing (`--dry-run-detail full`)
+    pub fn reading_row(&self, timestamp: &str, temperature: &str, humidity: &str) -> String {
+        match self {
+            Locale::En => format!("      {} | Temp: {} | Humidity: {}", timestamp, temperature, humidity),
+            Locale::Fr => format!("      {} | Temp : {} | Humidité : {}", timestamp, temperature, humidity),
+        }
+    }
+
+    pub fn more_readings(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("      ... {} more readings", count),
+            Locale::Fr => format!("      ... {} autres mesures", count),
+        }
+    }
+
+    pub fn text_content_full(&self, text: &str) -> String {
+        match self {
+            Locale::En => format!("📄 Text content:\n{}", text),
+            Locale::Fr => format!("📄 Contenu texte :\n{}", text),
+        }
+    }
+
+    pub fn text_content_preview(&self, preview: &str) -> String {
+        match self {
+            Locale::En => format!("📄 Text content (first 500 chars):\n{}", preview),
+            Locale::Fr => format!("📄 Contenu texte (500 premiers caractères) :\n{}", preview),
+        }
+    }
+
+    pub fn pool_metrics_title(&self) -> &'static str {
+        self.pick("🏊 Pool Metrics Extracted:", "🏊 Mesures de la piscine extraites :")
+    }
+
+    pub fn reading_rejected(&self, sensor: &str, reason: &str) -> String {
+        match self {
+            Locale::En => format!("   🚫 Rejected {}: {}", sensor, reason),
+            Locale::Fr => format!("   🚫 Rejetée {} : {}", sensor, reason),
+        }
+    }
+
+    pub fn dry_run_diff_title(&self) -> &'static str {
+        self.pick("🔎 Compared with database:", "🔎 Comparaison avec la base de données :")
+    }
+
+    pub fn dry_run_diff_line(&self, sensor: &str, new: usize, existing: usize) -> String {
+        match self {
+            Locale::En => format!("   {}: {} new, {} already present", sensor, new, existing),
+            Locale::Fr => format!("   {} : {} nouvelle(s), {} déjà présente(s)", sensor, new, existing),
+        }
+    }
+
+    pub fn debug_bundle_written(&self, path: &str, files: usize) -> String {
+        match self {
+            Locale::En => format!("📦 Debug bundle written to {} ({} file(s), secrets masked: review it before sharing)", path, files),
+            Locale::Fr => format!("📦 Archive de diagnostic écrite dans {} ({} fichier(s), secrets masqués : relisez-la avant de la partager)", path, files),
+        }
+    }
+
+    pub fn sandbox_written(&self, path: &str) -> String {
+        match self {
+            Locale::En => format!("🧪 Sandbox rows written to {} (Gmail labels and Slack untouched)", path),
+            Locale::Fr => format!("🧪 Lignes du bac à sable écrites dans {} (libellés Gmail et Slack inchangés)", path),
+        }
+    }
+
+    pub fn dry_run_diff_failed(&self, error: &str) -> String {
+        match self {
+            Locale::En => format!("⚠️  Could not compare with database: {}", error),
+            Locale::Fr => format!("⚠️  Comparaison avec la base de données impossible : {}", error),
+        }
+    }
+
+    // ========================================================================
+    // End-of-run summary
+    // ========================================================================
+
+    pub fn summary_title(&self) -> &'static str {
+        self.pick("📋 Run summary", "📋 Résumé de l'exécution")
+    }
+
+    pub fn summary_col_source(&self) -> &'static str {
+        self.pick("Source", "Source")
+    }
+
+    pub fn summary_col_email(&self) -> &'static str {
+        self.pick("Email", "Email")
+    }
+
+    pub fn summary_col_status(&self) -> &'static str {
+        self.pick("Status", "Statut")
+    }
+
+    pub fn summary_col_records(&self) -> &'static str {
+        self.pick("Records", "Mesures")
+    }
+
+    pub fn summary_col_sensor(&self) -> &'static str {
+        self.pick("Sensor", "Capteur")
+    }
+
+    pub fn summary_col_inserted(&self) -> &'static str {
+        self.pick("Inserted", "Insérées")
+    }
+
+    pub fn summary_col_duplicates(&self) -> &'static str {
+        self.pick("Duplicates", "Doublons")
+    }
+
+    pub fn summary_col_rejected(&self) -> &'static str {
+        self.pick("Rejected", "Rejetées")
+    }
+
+    pub fn summary_no_emails(&self) -> &'static str {
+        self.pick("(no emails)", "(aucun email)")
+    }
+
+    pub fn email_status(&self, status: &crate::email::EmailStatus) -> &'static str {
+        use crate::email::EmailStatus;
+        match status {
+            EmailStatus::Processed => self.pick("✅ processed", "✅ traité"),
+            EmailStatus::NoData => self.pick("⚠️  no data", "⚠️  sans données"),
+            EmailStatus::Skipped(_) => self.pick("⏭️  skipped", "⏭️  ignoré"),
+            EmailStatus::Partial(_) => self.pick("⚠️  partial", "⚠️  partiel"),
+            EmailStatus::Failed(_) => self.pick("❌ failed", "❌ échec"),
+        }
+    }
+
+    pub fn summary_totals(&self, processed: usize, failed: usize, seconds: f64, api_calls: usize) -> String {
+        match self {
+            Locale::En => format!("⏱️  {} processed, {} failed in {:.1}s - {} API call(s)",
+                                  processed, failed, seconds, api_calls),
+            Locale::Fr => format!("⏱️  {} traité(s), {} en échec en {:.1}s - {} appel(s) API",
+                                  processed, failed, seconds, api_calls),
+        }
+    }
+
+    pub fn summary_deferred(&self, deferred: usize, budget: RunBudget) -> String {
+        let reason = match budget {
+            RunBudget::Duration => self.pick("Time limit reached", "Durée maximale atteinte"),
+            RunBudget::ApiCalls => self.pick("Gmail API call budget reached", "Budget d'appels API Gmail atteint"),
+            RunBudget::DbRows => self.pick("Database row budget reached", "Budget de lignes en base atteint"),
+            RunBudget::Batch => self.pick("Batch size reached", "Taille de lot atteinte"),
+            RunBudget::Quota => self.pick("Gmail API quota reached", "Quota de l'API Gmail atteint"),
+        };
+        match self {
+            Locale::En => format!("⏸️  {}: {} email(s) deferred to the next run", reason, deferred),
+            Locale::Fr => format!("⏸️  {} : {} email(s) reporté(s) à la prochaine exécution", reason, deferred),
+        }
+    }
+
+    // ========================================================================
+    // Run report (--report)
+    // ========================================================================
+
+    pub fn report_title(&self) -> &'static str {
+        self.pick("HomeMetrics run report", "Rapport d'exécution HomeMetrics")
+    }
+
+    pub fn report_mode(&self, mode: crate::report::RunMode) -> &'static str {
+        use crate::report::RunMode;
+        match mode {
+            RunMode::Normal => self.pick("normal", "normal"),
+            RunMode::DryRun => self.pick("dry-run", "dry-run"),
+            RunMode::Sandbox => self.pick("SQLite sandbox", "bac à sable SQLite"),
+        }
+    }
+
+    pub fn report_header(&self, started_at: &str, mode: &str, seconds: f64) -> String {
+        match self {
+            Locale::En => format!("Started {} - mode {} - {:.1}s", started_at, mode, seconds),
+            Locale::Fr => format!("Démarré le {} - mode {} - {:.1}s", started_at, mode, seconds),
+        }
+    }
+
+    pub fn report_col_attachments(&self) -> &'static str {
+        self.pick("Attachments", "Pièces jointes")
+    }
+
+    pub fn report_col_duration(&self) -> &'static str {
+        self.pick("Duration", "Durée")
+    }
+
+    pub fn report_errors_title(&self) -> &'static str {
+        self.pick("Errors", "Erreurs")
+    }
+
+    pub fn report_run_failed(&self, error: &str) -> String {
+        match self {
+            Locale::En => format!("Run aborted: {}", error),
+            Locale::Fr => format!("Exécution interrompue : {}", error),
+        }
+    }
+
+    // ========================================================================
+    // Slack notifications
+    // ========================================================================
+
+    pub fn notify_xsense_data(&self, readings: usize, from: &str, subject: &str, devices: &[SensorStats], units: &str) -> String {
+        let mut message = match self {
+            Locale::En => format!("📊 New X-Sense data: {} temperature readings\nFrom: {}\nSubject: {}",
+                                  readings, from, subject),
+            Locale::Fr => format!("📊 Nouvelles données X-Sense : {} mesures de température\nDe : {}\nObjet : {}",
+                                  readings, from, subject),
+        };
+        for device in devices {
+            message.push('\n');
+            message.push_str(&self.device_summary_line(device, units));
+            message.push_str(&self.device_trend(device, units));
+        }
+        message
+    }
+
+    /// Average of a device's new readings, and its change versus the previous period
+    pub fn device_trend(&self, device: &SensorStats, units: &str) -> String {
+        match (self, device.delta) {
+            (Locale::En, Some(delta)) => format!(", avg {:.1}{} ({:+.1}{} vs previous period)", device.avg, units, delta, units),
+            (Locale::Fr, Some(delta)) => format!(", moyenne {:.1}{} ({:+.1}{} par rapport à la période précédente)", device.avg, units, delta, units),
+            (Locale::En, None) => format!(", avg {:.1}{}", device.avg, units),
+            (Locale::Fr, None) => format!(", moyenne {:.1}{}", device.avg, units),
+        }
+    }
+
+    /// One device of a multi-file export: files, readings, time and temperature range
+    pub fn device_summary_line(&self, device: &SensorStats, units: &str) -> String {
+        match self {
+            Locale::En => format!("• {} ({} file(s)): {} readings, {} → {}, {:.1}{} → {:.1}{}",
+                                  device.name, device.files.len(), device.count, device.first, device.last,
+                                  device.min, units, device.max, units),
+            Locale::Fr => format!("• {} ({} fichier(s)) : {} mesures, {} → {}, {:.1}{} → {:.1}{}",
+                                  device.name, device.files.len(), device.count, device.first, device.last,
+                                  device.min, units, device.max, units),
+        }
+    }
+
+    pub fn device_summary_title(&self) -> &'static str {
+        self.pick("📟 Summary per device:", "📟 Bilan par appareil :")
+    }
+
+    /// `pool` is only given when several pools are configured
+    pub fn notify_pool_reading(&self, pool: Option<&str>, metrics: &str, subject: &str) -> String {
+        let pool = pool.map(|p| format!(" ({})", p)).unwrap_or_default();
+        match self {
+            Locale::En => format!("🏊 New pool reading{}: {}\nFrom: {}", pool, metrics, subject),
+            Locale::Fr => format!("🏊 Nouvelle mesure piscine{} : {}\nDe : {}", pool, metrics, subject),
+        }
+    }
+
+    /// Line appended to a data notification when some attachments failed
+    pub fn notify_failed_attachments(&self, failures: &str) -> String {
+        match self {
+            Locale::En => format!("⚠️ Failed attachments (email kept for retry): {}", failures),
+            Locale::Fr => format!("⚠️ Pièces jointes en échec (email conservé pour relance) : {}", failures),
+        }
+    }
+
+    pub fn notify_processing_error(&self, processor: &str, message_id: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("❌ Error processing {} email {}: {}", processor, message_id, error),
+            Locale::Fr => format!("❌ Erreur de traitement de l'email {} {} : {}", processor, message_id, error),
+        }
+    }
+
+    /// Gmail refused the token: the remaining emails wait for a new authorization
+    pub fn notify_auth_error(&self, processor: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("🔐 {} processing stopped, Gmail authorization failed (run `homemetrics auth login`): {}",
+                                  processor, error),
+            Locale::Fr => format!("🔐 Traitement {} interrompu, autorisation Gmail refusée (lancer `homemetrics auth login`) : {}",
+                                  processor, error),
+        }
+    }
+
+    /// Scheduled run failing with an error retries cannot fix
+    pub fn notify_scheduled_run_failed(&self, profile: &str, schedule_time: &str, error_class: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("❌ Scheduled run {} of profile {} failed ({} error), skipped until the next one: {}",
+                                  schedule_time, profile, error_class, error),
+            Locale::Fr => format!("❌ Exécution planifiée {} du profil {} en échec (erreur {}), reportée à la suivante : {}",
+                                  schedule_time, profile, error_class, error),
+        }
+    }
+
+    pub fn notify_dead_letter(&self, processor: &str, message_id: &str, attempts: u32, error: &str) -> String {
+        match self {
+            Locale::En => format!("☠️ {} email {} given up after {} failed attempts, moved to the error label: {}",
+                                  processor, message_id, attempts, error),
+            Locale::Fr => format!("☠️ Email {} {} abandonné après {} tentatives en échec, déplacé vers le label d'erreur : {}",
+                                  processor, message_id, attempts, error),
+        }
+    }
+
+    /// Warning sent when a source found no emails for `<SOURCE>_ALERT_AFTER_DAYS` days
+    pub fn notify_alert(&self, rule: &str, sensor: &str, temperature: &str, condition: &str, threshold: &str) -> String {
+        match self {
+            Locale::En => format!("🚨 Alert {}: {} at {} ({} {})", rule, sensor, temperature, condition, threshold),
+            Locale::Fr => format!("🚨 Alerte {} : {} à {} ({} {})", rule, sensor, temperature, condition, threshold),
+        }
+    }
+
+    pub fn notify_humidity_alert(&self, rule: &str, sensor: &str, humidity: f64, condition: Condition, hours: i64) -> String {
+        let threshold = condition.threshold();
+        match (self, condition) {
+            (Locale::En, Condition::Below(_)) => format!("💧 Alert {}: {} too dry at {:.0}% (< {:.0}%) for {} hour(s)", rule, sensor, humidity, threshold, hours),
+            (Locale::Fr, Condition::Below(_)) => format!("💧 Alerte {} : {} trop sec à {:.0} % (< {:.0} %) depuis {} heure(s)", rule, sensor, humidity, threshold, hours),
+            (Locale::En, Condition::Above(_)) => format!("💧 Alert {}: {} too humid at {:.0}% (> {:.0}%) for {} hour(s)", rule, sensor, humidity, threshold, hours),
+            (Locale::Fr, Condition::Above(_)) => format!("💧 Alerte {} : {} trop humide à {:.0} % (> {:.0} %) depuis {} heure(s)", rule, sensor, humidity, threshold, hours),
+        }
+    }
+
+    pub fn notify_webhook_failed(&self, rule: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("❌ Webhook of alert {} failed: {}", rule, error),
+            Locale::Fr => format!("❌ Échec du webhook de l'alerte {} : {}", rule, error),
+        }
+    }
+
+    pub fn notify_mold_risk(&self, sensor: &str, hours: i64, since: &str) -> String {
+        match self {
+            Locale::En => format!("🍄 {} has been at risk of mold/condensation for {} hour(s) (since {}) - ventilate or heat the room",
+                                  sensor, hours, since),
+            Locale::Fr => format!("🍄 {} est en zone de risque de moisissure/condensation depuis {} heure(s) (depuis le {}) - aérez ou chauffez la pièce",
+                                  sensor, hours, since),
+        }
+    }
+
+    pub fn notify_no_emails(&self, processor: &str, label: &str, days: i64, empty_runs: u32) -> String {
+        match self {
+            Locale::En => format!("⚠️ No {} email found with label '{}' for {} day(s) ({} empty run(s)) - check the export automation",
+                                  processor, label, days, empty_runs),
+            Locale::Fr => format!("⚠️ Aucun email {} trouvé avec le label '{}' depuis {} jour(s) ({} exécution(s) vide(s)) - vérifiez l'automatisation de l'export",
+                                  processor, label, days, empty_runs),
+        }
+    }
+
+    /// Run summary posted in `SLACK_MODE=run`: email counts, per-sensor totals and failures
+    pub fn notify_run_summary(&self, summary: &crate::email::RunSummary) -> String {
+        use crate::email::EmailStatus;
+        let skipped = summary.emails.iter().filter(|e| matches!(e.status, EmailStatus::Skipped(_))).count();
+        let mut message = match self {
+            Locale::En => format!("📋 {} run: {} email(s) processed, {} failed, {} skipped, {} record(s)",
+                                  summary.processor, summary.processed_count(), summary.failed_count(),
+                                  skipped, summary.total_records()),
+            Locale::Fr => format!("📋 Exécution {} : {} email(s) traité(s), {} en échec, {} ignoré(s), {} enregistrement(s)",
+                                  summary.processor, summary.processed_count(), summary.failed_count(),
+                                  skipped, summary.total_records()),
+        };
+        for (sensor, counts) in &summary.sensors {
+            message.push_str(&match self {
+                Locale::En => format!("\n• {}: {} new, {} duplicate(s), {} rejected",
+                                      sensor, counts.inserted, counts.duplicates, counts.rejected),
+                Locale::Fr => format!("\n• {} : {} nouvelle(s), {} doublon(s), {} rejetée(s)",
+                                      sensor, counts.inserted, counts.duplicates, counts.rejected),
+            });
+        }
+        for email in &summary.emails {
+            if let EmailStatus::Failed(error) | EmailStatus::Partial(error) = &email.status {
+                let error = error.lines().next().unwrap_or_default();
+                message.push_str(&format!("\n❌ {}: {}", email.message_id, error));
+            }
+        }
+        message
+    }
+
+    pub fn notify_pushed_data(&self, readings: usize, sensors: &str) -> String {
+        match self {
+            Locale::En => format!("📡 New pushed data: {} temperature readings\nSensors: {}", readings, sensors),
+            Locale::Fr => format!("📡 Nouvelles données reçues : {} mesures de température\nCapteurs : {}", readings, sensors),
+        }
+    }
+
+    pub fn notify_file_received(&self, readings: usize, filename: &str, sensors: &str) -> String {
+        match self {
+            Locale::En => format!("📂 New X-Sense export received: {} temperature readings\nFile: {}\nSensors: {}",
+                                  readings, filename, sensors),
+            Locale::Fr => format!("📂 Nouvel export X-Sense reçu : {} mesures de température\nFichier : {}\nCapteurs : {}",
+                                  readings, filename, sensors),
+        }
+    }
+
+    pub fn notify_file_error(&self, filename: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("❌ Error processing dropped file {}: {}", filename, error),
+            Locale::Fr => format!("❌ Erreur de traitement du fichier déposé {} : {}", filename, error),
+        }
+    }
+
+    // ========================================================================
+    // CLI commands
+    // ========================================================================
+
+    pub fn profile_header(&self, profile: &str) -> String {
+        match self {
+            Locale::En => format!("\n🏠 Profile {}", profile),
+            Locale::Fr => format!("\n🏠 Profil {}", profile),
+        }
+    }
+
+    pub fn listing_labels(&self) -> &'static str {
+        self.pick("📋 Listing Gmail labels...", "📋 Liste des labels Gmail...")
+    }
+
+    pub fn no_labels_found(&self) -> &'static str {
+        self.pick("No labels found.", "Aucun label trouvé.")
+    }
+
+    pub fn no_labels_matching(&self, filter: &str) -> String {
+        match self {
+            Locale::En => format!("No labels matching '{}'.", filter),
+            Locale::Fr => format!("Aucun label ne contient « {} ».", filter),
+        }
+    }
+
+    pub fn labels_found(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("Found {} label(s):", count),
+            Locale::Fr => format!("{} label(s) trouvé(s) :", count),
+        }
+    }
+
+    pub fn no_dead_letters(&self) -> &'static str {
+        self.pick("✅ No dead-lettered emails", "✅ Aucun email abandonné")
+    }
+
+    pub fn dead_letters_header(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("☠️  {} dead-lettered email(s):", count),
+            Locale::Fr => format!("☠️  {} email(s) abandonné(s) :", count),
+        }
+    }
+
+    pub fn dead_letter_line(&self, source: &str, message_id: &str, attempts: u32, since: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("  {:<10} {:<20} {} attempt(s), since {}: {}", source, message_id, attempts, since, error),
+            Locale::Fr => format!("  {:<10} {:<20} {} tentative(s), depuis le {} : {}", source, message_id, attempts, since, error),
+        }
+    }
+
+    pub fn email_requeued(&self, message_id: &str, source: &str) -> String {
+        match self {
+            Locale::En => format!("🔁 Email {} ({}) requeued, it will be processed on the next run", message_id, source),
+            Locale::Fr => format!("🔁 Email {} ({}) remis en file, il sera traité à la prochaine exécution", message_id, source),
+        }
+    }
+
+    pub fn email_not_dead_lettered(&self, message_id: &str) -> String {
+        match self {
+            Locale::En => format!("Email {} is not dead-lettered", message_id),
+            Locale::Fr => format!("L'email {} n'est pas abandonné", message_id),
+        }
+    }
+
+    pub fn filter_created(&self, sender: &str, label: &str) -> String {
+        match self {
+            Locale::En => format!("✅ Filter created: from:{} → {} (skip inbox)", sender, label),
+            Locale::Fr => format!("✅ Filtre créé : from:{} → {} (hors boîte de réception)", sender, label),
+        }
+    }
+
+    pub fn filter_exists(&self, sender: &str, label: &str) -> String {
+        match self {
+            Locale::En => format!("✓ Filter already present: from:{} → {}", sender, label),
+            Locale::Fr => format!("✓ Filtre déjà présent : from:{} → {}", sender, label),
+        }
+    }
+
+    pub fn filter_no_sender(&self, prefix: &str) -> String {
+        match self {
+            Locale::En => format!("⏭️  {}_SENDER not set, no filter created", prefix),
+            Locale::Fr => format!("⏭️  {}_SENDER non défini, aucun filtre créé", prefix),
+        }
+    }
+
+    pub fn export_done(&self, readings: usize, files: usize, dir: &str) -> String {
+        match self {
+            Locale::En => format!("📦 {} reading(s) exported to {} file(s) under {}", readings, files, dir),
+            Locale::Fr => format!("📦 {} mesure(s) exportée(s) dans {} fichier(s) sous {}", readings, files, dir),
+        }
+    }
+
+    pub fn export_pools_done(&self, readings: usize) -> String {
+        match self {
+            Locale::En => format!("📦 {} pool reading(s) exported to pools.csv", readings),
+            Locale::Fr => format!("📦 {} mesure(s) de bassin exportée(s) dans pools.csv", readings),
+        }
+    }
+
+    pub fn duckdb_loaded(&self, file: &str) -> String {
+        match self {
+            Locale::En => format!("🦆 DuckDB database written: {}", file),
+            Locale::Fr => format!("🦆 Base DuckDB écrite : {}", file),
+        }
+    }
+
+    pub fn duckdb_cli_missing(&self, script: &str, file: &str) -> String {
+        match self {
+            Locale::En => format!("⚠️  duckdb command not found, load the export with: duckdb {} < {}", file, script),
+            Locale::Fr => format!("⚠️  Commande duckdb introuvable, chargez l'export avec : duckdb {} < {}", file, script),
+        }
+    }
+
+    pub fn stats_header(&self, from: &str, to: &str) -> String {
+        match self {
+            Locale::En => format!("📈 Readings from {} to {}", from, to),
+            Locale::Fr => format!("📈 Mesures du {} au {}", from, to),
+        }
+    }
+
+    pub fn schema_migrated(&self, from: Option<i64>, to: i64) -> String {
+        match (self, from) {
+            (Locale::En, Some(from)) if from == to => format!("✅ Database schema already at version {}", to),
+            (Locale::Fr, Some(from)) if from == to => format!("✅ Schéma de la base déjà en version {}", to),
+            (Locale::En, Some(from)) => format!("✅ Database schema migrated from version {} to {}", from, to),
+            (Locale::Fr, Some(from)) => format!("✅ Schéma de la base migré de la version {} à la {}", from, to),
+            (Locale::En, None) => format!("✅ Database schema migrated to version {}", to),
+            (Locale::Fr, None) => format!("✅ Schéma de la base migré en version {}", to),
+        }
+    }
+
+    pub fn no_stats(&self) -> &'static str {
+        self.pick("No readings over this period", "Aucune mesure sur cette période")
+    }
+
+    pub fn stats_sensor_columns(&self, unit: &str) -> Vec<String> {
+        let columns = match self {
+            Locale::En => ["Sensor", "Location", "Readings", "Min", "Max", "Avg", "Latest", "At (UTC)"],
+            Locale::Fr => ["Capteur", "Emplacement", "Mesures", "Min", "Max", "Moy", "Dernière", "À (UTC)"],
+        };
+        columns.iter().enumerate()
+            .map(|(i, column)| if (3..7).contains(&i) { format!("{} ({})", column, unit) } else { column.to_string() })
+            .collect()
+    }
+
+    pub fn stats_pool_columns(&self, unit: &str) -> Vec<String> {
+        match self {
+            Locale::En => vec!["Pool".to_string(), "Readings".to_string(), format!("Temp. min/max/avg ({})", unit),
+                               "pH min/max/avg".to_string(), "ORP min/max/avg (mV)".to_string(), "Latest (UTC)".to_string()],
+            Locale::Fr => vec!["Bassin".to_string(), "Mesures".to_string(), format!("Temp. min/max/moy ({})", unit),
+                               "pH min/max/moy".to_string(), "ORP min/max/moy (mV)".to_string(), "Dernière (UTC)".to_string()],
+        }
+    }
+
+    pub fn health_sensor_line(&self, sensor_id: &str, healthy: bool, age: Option<&str>, max_age: &str) -> String {
+        let icon = if healthy { "✅" } else { "❌" };
+        match (self, age) {
+            (Locale::En, Some(age)) => format!("{} {}: last reading {} ago (max {})", icon, sensor_id, age, max_age),
+            (Locale::Fr, Some(age)) => format!("{} {} : dernière mesure il y a {} (max {})", icon, sensor_id, age, max_age),
+            (Locale::En, None) => format!("{} {}: no reading (max {})", icon, sensor_id, max_age),
+            (Locale::Fr, None) => format!("{} {} : aucune mesure (max {})", icon, sensor_id, max_age),
+        }
+    }
+
+    pub fn health_status(&self, healthy: bool, stale: usize) -> String {
+        match (self, healthy) {
+            (Locale::En, true) => "💚 Healthy".to_string(),
+            (Locale::Fr, true) => "💚 En bonne santé".to_string(),
+            (Locale::En, false) => format!("💔 Unhealthy: {} sensor(s) without fresh readings", stale),
+            (Locale::Fr, false) => format!("💔 En défaut : {} capteur(s) sans mesure récente", stale),
+        }
+    }
+
+    pub fn doctor_oauth(&self) -> String {
+        self.pick("Gmail OAuth2 token", "Jeton OAuth2 Gmail").to_string()
+    }
+
+    pub fn doctor_label(&self, label: &str) -> String {
+        match self {
+            Locale::En => format!("Gmail label {}", label),
+            Locale::Fr => format!("Libellé Gmail {}", label),
+        }
+    }
+
+    pub fn doctor_label_missing(&self) -> &'static str {
+        self.pick("missing (run `homemetrics init`)", "absent (lancer `homemetrics init`)")
+    }
+
+    pub fn doctor_skipped_oauth(&self) -> &'static str {
+        self.pick("skipped, Gmail is not reachable", "ignoré, Gmail inaccessible")
+    }
+
+    pub fn doctor_database(&self) -> String {
+        self.pick("Database write", "Écriture en base").to_string()
+    }
+
+    pub fn doctor_slack(&self, channel: &str) -> String {
+        match self {
+            Locale::En => format!("Slack post to {}", channel),
+            Locale::Fr => format!("Message Slack vers {}", channel),
+        }
+    }
+
+    pub fn doctor_skipped_slack(&self) -> &'static str {
+        self.pick("skipped, Slack is not configured", "ignoré, Slack non configuré")
+    }
+
+    pub fn doctor_slack_message(&self) -> &'static str {
+        self.pick("🩺 HomeMetrics doctor: Slack notifications work", "🩺 HomeMetrics doctor : les notifications Slack fonctionnent")
+    }
+
+    pub fn doctor_status(&self, failed: usize) -> String {
+        match (self, failed) {
+            (Locale::En, 0) => "💚 All checks passed".to_string(),
+            (Locale::Fr, 0) => "💚 Toutes les vérifications sont passées".to_string(),
+            (Locale::En, _) => format!("💔 {} check(s) failed", failed),
+            (Locale::Fr, _) => format!("💔 {} vérification(s) en échec", failed),
+        }
+    }
+
+    pub fn trace_reading(&self, sensor: &str, at: &str, values: &str) -> String {
+        match self {
+            Locale::En => format!("📍 {} at {}: {}", sensor, at, values),
+            Locale::Fr => format!("📍 {} le {} : {}", sensor, at, values),
+        }
+    }
+
+    pub fn trace_no_reading(&self, sensor: &str, at: &str) -> String {
+        match self {
+            Locale::En => format!("📭 No reading of {} stored around {}", sensor, at),
+            Locale::Fr => format!("📭 Aucun relevé de {} enregistré autour du {}", sensor, at),
+        }
+    }
+
+    pub fn trace_origin(&self, source: &str, email_id: &str, file: Option<&str>, readings: u32, span: &str, run: &str) -> String {
+        let file = file.unwrap_or(self.pick("email body", "corps de l'email"));
+        match self {
+            Locale::En => format!("  ✉️  {} email {}, {} ({} reading(s), {}) - run of {}", source, email_id, file, readings, span, run),
+            Locale::Fr => format!("  ✉️  Email {} {}, {} ({} relevé(s), {}) - exécution du {}", source, email_id, file, readings, span, run),
+        }
+    }
+
+    pub fn trace_no_provenance(&self) -> &'static str {
+        self.pick(
+            "  No provenance recorded (reading stored before schema version 5, imported from a file or received over HTTP)",
+            "  Aucune provenance enregistrée (relevé enregistré avant la version 5 du schéma, importé d'un fichier ou reçu en HTTP)",
+        )
+    }
+
+    /// Last line of `backfill-gmail`
+    pub fn backfill_gmail_status(&self, remaining: usize, stopped_by: Option<RunBudget>, interrupted: bool) -> String {
+        if remaining == 0 {
+            return self.pick("✅ Whole label history processed", "✅ Tout l'historique du libellé a été traité").to_string();
+        }
+        let reason = match (interrupted, stopped_by) {
+            (true, _) => self.pick("Interrupted", "Interrompu"),
+            (false, Some(RunBudget::Quota)) => self.pick("Gmail API quota reached", "Quota de l'API Gmail atteint"),
+            (false, Some(RunBudget::ApiCalls)) => self.pick("Gmail API call budget reached", "Budget d'appels API Gmail atteint"),
+            (false, _) => self.pick("Chunk done", "Lot terminé"),
+        };
+        match self {
+            Locale::En => format!("⏸️  {}: {} email(s) left, run the same command again to resume (--all to go on to the end)", reason, remaining),
+            Locale::Fr => format!("⏸️  {} : {} email(s) restant(s), relancez la même commande pour reprendre (--all pour aller jusqu'au bout)", reason, remaining),
+        }
+    }
+
+    pub fn delete_preview(&self, readings: usize, provenance: usize) -> String {
+        match self {
+            Locale::En => format!("🔎 {} reading(s) and {} provenance record(s) would be deleted (rerun with --yes to delete them)", readings, provenance),
+            Locale::Fr => format!("🔎 {} relevé(s) et {} provenance(s) seraient supprimés (relancez avec --yes pour les supprimer)", readings, provenance),
+        }
+    }
+
+    pub fn delete_done(&self, readings: usize, provenance: usize) -> String {
+        match self {
+            Locale::En => format!("🗑️  {} reading(s) and {} provenance record(s) deleted", readings, provenance),
+            Locale::Fr => format!("🗑️  {} relevé(s) et {} provenance(s) supprimés", readings, provenance),
+        }
+    }
+
+    pub fn delete_nothing(&self) -> &'static str {
+        self.pick("✅ No reading matches, nothing to delete", "✅ Aucun relevé ne correspond, rien à supprimer")
+    }
+
+    pub fn tui_sensors_title(&self, profile: Option<&str>) -> String {
+        let title = self.pick(" Sensors ", " Capteurs ");
+        match profile {
+            Some(profile) => format!("{}({}) ", title, profile),
+            None => title.to_string(),
+        }
+    }
+
+    pub fn tui_sensor_columns(&self, unit: &str) -> Vec<String> {
+        match self {
+            Locale::En => vec!["Sensor".to_string(), "Location".to_string(), unit.to_string(), "%RH".to_string(), "At".to_string()],
+            Locale::Fr => vec!["Capteur".to_string(), "Emplacement".to_string(), unit.to_string(), "%HR".to_string(), "À".to_string()],
+        }
+    }
+
+    pub fn tui_pools_title(&self) -> &'static str {
+        self.pick(" Pools ", " Bassins ")
+    }
+
+    pub fn tui_pool_columns(&self, unit: &str) -> Vec<String> {
+        match self {
+            Locale::En => vec!["Pool".to_string(), unit.to_string(), "pH".to_string(), "ORP".to_string(), "At".to_string()],
+            Locale::Fr => vec!["Bassin".to_string(), unit.to_string(), "pH".to_string(), "ORP".to_string(), "À".to_string()],
+        }
+    }
+
+    pub fn tui_runs_title(&self) -> &'static str {
+        self.pick(" Runs ", " Exécutions ")
+    }
+
+    pub fn tui_source_line(&self, source: &str, last_run: &str, last_email: &str, empty_runs: u32) -> String {
+        match self {
+            Locale::En => format!("{}: run {}, emails {} ({} empty run(s))", source, last_run, last_email, empty_runs),
+            Locale::Fr => format!("{} : exécution {}, emails {} ({} exécution(s) vide(s))", source, last_run, last_email, empty_runs),
+        }
+    }
+
+    pub fn tui_queue_line(&self, dead_letters: usize, failed_notifications: usize) -> String {
+        match self {
+            Locale::En => format!("{} dead letter(s), {} undelivered Slack message(s)", dead_letters, failed_notifications),
+            Locale::Fr => format!("{} email(s) abandonné(s), {} message(s) Slack non délivré(s)", dead_letters, failed_notifications),
+        }
+    }
+
+    pub fn tui_schedule_title(&self) -> &'static str {
+        self.pick(" Next runs ", " Prochaines exécutions ")
+    }
+
+    pub fn tui_no_schedule(&self) -> &'static str {
+        self.pick("Scheduler disabled", "Planificateur désactivé")
+    }
+
+    pub fn tui_footer(&self, refreshed_at: &str) -> String {
+        match self {
+            Locale::En => format!("Refreshed at {} - r: refresh, q: quit", refreshed_at),
+            Locale::Fr => format!("Actualisé à {} - r : actualiser, q : quitter", refreshed_at),
+        }
+    }
+
+    pub fn tui_load_error(&self, error: &str) -> String {
+        match self {
+            Locale::En => format!("Unable to read the database: {} - r: retry, q: quit", error),
+            Locale::Fr => format!("Lecture de la base impossible : {} - r : réessayer, q : quitter", error),
+        }
+    }
+
+    pub fn status_header(&self, since: &str, uptime: &str, pid: u32, version: &str) -> String {
+        match self {
+            Locale::En => format!("🟢 Daemon running since {} ({}), pid {}, homemetrics {}", since, uptime, pid, version),
+            Locale::Fr => format!("🟢 Daemon actif depuis {} ({}), pid {}, homemetrics {}", since, uptime, pid, version),
+        }
+    }
+
+    pub fn status_runs_title(&self) -> &'static str {
+        self.pick("Last runs:", "Dernières exécutions :")
+    }
+
+    pub fn status_no_runs(&self) -> &'static str {
+        self.pick("No run since the daemon started", "Aucune exécution depuis le démarrage du daemon")
+    }
+
+    pub fn status_run_line(&self, profile: &str, source: &str, at: &str, trigger: &str, outcome: &str) -> String {
+        match self {
+            Locale::En => format!("  [{}] {} at {} ({}): {}", profile, source, at, trigger, outcome),
+            Locale::Fr => format!("  [{}] {} à {} ({}) : {}", profile, source, at, trigger, outcome),
+        }
+    }
+
+    pub fn status_run_counts(&self, processed: usize, failed: usize, records: usize, deferred: usize) -> String {
+        let deferred = match (self, deferred) {
+            (_, 0) => String::new(),
+            (Locale::En, _) => format!(", {} deferred", deferred),
+            (Locale::Fr, _) => format!(", {} reporté(s)", deferred),
+        };
+        match self {
+            Locale::En => format!("{} processed, {} failed, {} record(s){}", processed, failed, records, deferred),
+            Locale::Fr => format!("{} traité(s), {} en échec, {} enregistrement(s){}", processed, failed, records, deferred),
+        }
+    }
+
+    pub fn status_next_title(&self) -> &'static str {
+        self.pick("Next runs:", "Prochaines exécutions :")
+    }
+
+    pub fn status_token_title(&self) -> &'static str {
+        self.pick("Gmail token:", "Jeton Gmail :")
+    }
+
+    pub fn status_token_line(&self, profile: &str, refreshed_ago: Option<&str>, expires_at: Option<&str>) -> String {
+        match (self, refreshed_ago, expires_at) {
+            (Locale::En, Some(ago), Some(at)) => format!("  [{}] refreshed {} ago, access token valid until {}", profile, ago, at),
+            (Locale::Fr, Some(ago), Some(at)) => format!("  [{}] rafraîchi il y a {}, jeton d'accès valide jusqu'à {}", profile, ago, at),
+            (Locale::En, Some(ago), None) => format!("  [{}] refreshed {} ago", profile, ago),
+            (Locale::Fr, Some(ago), None) => format!("  [{}] rafraîchi il y a {}", profile, ago),
+            (Locale::En, None, _) => format!("  [{}] no cached token", profile),
+            (Locale::Fr, None, _) => format!("  [{}] aucun jeton en cache", profile),
+        }
+    }
+
+    pub fn readings_header(&self, sensor: &str, count: usize) -> String {
+        match self {
+            Locale::En => format!("📊 {}: {} reading(s)", sensor, count),
+            Locale::Fr => format!("📊 {} : {} mesure(s)", sensor, count),
+        }
+    }
+
+    pub fn no_readings(&self, sensor: &str) -> String {
+        match self {
+            Locale::En => format!("📊 {}: no readings over this period", sensor),
+            Locale::Fr => format!("📊 {} : aucune mesure sur cette période", sensor),
+        }
+    }
+
+    pub fn readings_sensor_columns(&self, unit: &str) -> Vec<String> {
+        match self {
+            Locale::En => vec!["At (UTC)".to_string(), format!("Temperature ({})", unit), "Humidity (%)".to_string()],
+            Locale::Fr => vec!["À (UTC)".to_string(), format!("Température ({})", unit), "Humidité (%)".to_string()],
+        }
+    }
+
+    pub fn readings_pool_columns(&self, unit: &str) -> Vec<String> {
+        match self {
+            Locale::En => vec!["At (UTC)".to_string(), format!("Temperature ({})", unit), "pH".to_string(), "ORP (mV)".to_string()],
+            Locale::Fr => vec!["À (UTC)".to_string(), format!("Température ({})", unit), "pH".to_string(), "ORP (mV)".to_string()],
+        }
+    }
+
+    pub fn forecast_header(&self, day: &str) -> String {
+        match self {
+            Locale::En => format!("🔮 Forecast for {}", day),
+            Locale::Fr => format!("🔮 Prévisions pour le {}", day),
+        }
+    }
+
+    pub fn forecast_no_pool(&self) -> &'static str {
+        self.pick("🏊 Not enough pool readings for a forecast", "🏊 Pas assez de mesures de piscine pour une prévision")
+    }
+
+    pub fn forecast_pool_line(&self, pool: &str, expected: &str, last: &str, trend: &str) -> String {
+        match self {
+            Locale::En => format!("🏊 Pool {}: {} expected (last day {}, {}/day)", pool, expected, last, trend),
+            Locale::Fr => format!("🏊 Bassin {} : {} attendus (dernier jour {}, {}/jour)", pool, expected, last, trend),
+        }
+    }
+
+    pub fn forecast_no_freeze(&self, threshold: &str) -> String {
+        match self {
+            Locale::En => format!("✅ No room expected at or below {}", threshold),
+            Locale::Fr => format!("✅ Aucune pièce attendue à {} ou moins", threshold),
+        }
+    }
+
+    pub fn forecast_freeze_line(&self, room: &str, minimum: &str, trend: &str) -> String {
+        match self {
+            Locale::En => format!("🥶 {}: minimum of {} expected ({}/day)", room, minimum, trend),
+            Locale::Fr => format!("🥶 {} : minimum de {} attendu ({}/jour)", room, minimum, trend),
+        }
+    }
+
+    pub fn cleanup_disabled(&self) -> &'static str {
+        self.pick("Clean-up disabled, set CLEANUP_AFTER_DAYS to enable it",
+                  "Nettoyage désactivé, définissez CLEANUP_AFTER_DAYS pour l'activer")
+    }
+
+    pub fn cleanup_done(&self, count: usize, days: u32) -> String {
+        match self {
+            Locale::En => format!("🧹 {} processed email(s) older than {} days cleaned up", count, days),
+            Locale::Fr => format!("🧹 {} email(s) traité(s) de plus de {} jours nettoyé(s)", count, days),
+        }
+    }
+
+    pub fn no_api_usage(&self) -> &'static str {
+        self.pick("No Gmail API usage recorded yet", "Aucune utilisation de l'API Gmail enregistrée")
+    }
+
+    pub fn api_usage_header(&self, days: u32) -> String {
+        match self {
+            Locale::En => format!("📊 Gmail API requests over the last {} day(s) (UTC):", days),
+            Locale::Fr => format!("📊 Requêtes à l'API Gmail sur les {} dernier(s) jour(s) (UTC) :", days),
+        }
+    }
+
+    pub fn api_usage_line(&self, day: &str, source: &str, calls: u64, runs: u32) -> String {
+        match self {
+            Locale::En => format!("  {}  {:<10} {:>7} request(s) in {} run(s)", day, source, calls, runs),
+            Locale::Fr => format!("  {}  {:<10} {:>7} requête(s) en {} exécution(s)", day, source, calls, runs),
+        }
+    }
+
+    pub fn api_usage_today(&self, calls: u64, limit: u64) -> String {
+        match (self, limit) {
+            (Locale::En, 0) => format!("Today: {} request(s) (no GMAIL_DAILY_CALL_LIMIT)", calls),
+            (Locale::Fr, 0) => format!("Aujourd'hui : {} requête(s) (GMAIL_DAILY_CALL_LIMIT non défini)", calls),
+            (Locale::En, _) => format!("Today: {} / {} request(s) ({}%)", calls, limit, calls * 100 / limit),
+            (Locale::Fr, _) => format!("Aujourd'hui : {} / {} requête(s) ({} %)", calls, limit, calls * 100 / limit),
+        }
+    }
+
+    pub fn no_notifications(&self) -> &'static str {
+        self.pick("No Slack notification recorded", "Aucune notification Slack enregistrée")
+    }
+
+    pub fn notifications_header(&self, count: usize) -> String {
+        match self {
+            Locale::En => format!("📨 Last {} Slack notification(s):", count),
+            Locale::Fr => format!("📨 {} dernière(s) notification(s) Slack :", count),
+        }
+    }
+
+    pub fn notification_line(&self, sent_at: &str, sent: bool, channel: &str, kind: &str, related_id: Option<&str>, error: Option<&str>) -> String {
+        let status = if sent { "✅" } else { "❌" };
+        let related = related_id.unwrap_or("-");
+        match (self, error) {
+            (_, None) => format!("  {} {} {:<12} {:<12} {}", status, sent_at, channel, kind, related),
+            (Locale::En, Some(error)) => format!("  {} {} {:<12} {:<12} {} (failed: {})", status, sent_at, channel, kind, related, error),
+            (Locale::Fr, Some(error)) => format!("  {} {} {:<12} {:<12} {} (échec : {})", status, sent_at, channel, kind, related, error),
+        }
+    }
+
+    pub fn auth_login_started(&self) -> &'static str {
+        self.pick("🔐 Starting Gmail OAuth2 authorization (open the URL below if no browser opens)...",
+                  "🔐 Autorisation OAuth2 Gmail (ouvrez l'URL ci-dessous si aucun navigateur ne s'ouvre)...")
+    }
+
+    pub fn auth_no_token(&self, path: &str) -> String {
+        match self {
+            Locale::En => format!("No cached token in {} - run `homemetrics auth login`", path),
+            Locale::Fr => format!("Aucun token en cache dans {} - lancez `homemetrics auth login`", path),
+        }
+    }
+
+    pub fn auth_scopes(&self, scopes: &str) -> String {
+        match self {
+            Locale::En => format!("  Scopes: {}", scopes),
+            Locale::Fr => format!("  Scopes : {}", scopes),
+        }
+    }
+
+    pub fn auth_expiry(&self, expires_at: Option<&str>, expired: bool, refresh_token: bool) -> String {
+        let refresh = match (self, refresh_token) {
+            (Locale::En, true) => "refresh token present, renewed automatically",
+            (Locale::En, false) => "⚠️  no refresh token, run `homemetrics auth login --force`",
+            (Locale::Fr, true) => "refresh token présent, renouvelé automatiquement",
+            (Locale::Fr, false) => "⚠️  pas de refresh token, lancez `homemetrics auth login --force`",
+        };
+        match (self, expires_at, expired) {
+            (Locale::En, None, _) => format!("  Access token: no expiry ({})", refresh),
+            (Locale::En, Some(at), true) => format!("  Access token: expired since {} ({})", at, refresh),
+            (Locale::En, Some(at), false) => format!("  Access token: valid until {} ({})", at, refresh),
+            (Locale::Fr, None, _) => format!("  Access token : sans expiration ({})", refresh),
+            (Locale::Fr, Some(at), true) => format!("  Access token : expiré depuis le {} ({})", at, refresh),
+            (Locale::Fr, Some(at), false) => format!("  Access token : valide jusqu'au {} ({})", at, refresh),
+        }
+    }
+
+    pub fn auth_revoked(&self, path: &str) -> String {
+        match self {
+            Locale::En => format!("🗑️  Token revoked and {} deleted", path),
+            Locale::Fr => format!("🗑️  Token révoqué et {} supprimé", path),
+        }
+    }
+
+    pub fn refreshing_token(&self) -> &'static str {
+        self.pick("🔄 Refreshing Gmail OAuth2 token...", "🔄 Rafraîchissement du token OAuth2 Gmail...")
+    }
+
+    pub fn token_refreshed(&self) -> &'static str {
+        self.pick("✅ Token refreshed successfully and persisted to cache",
+                  "✅ Token rafraîchi avec succès et enregistré dans le cache")
+    }
+
+    pub fn import_file_done(&self, file: &str, extracted: usize, saved: usize) -> String {
+        match self {
+            Locale::En => format!("✅ {}: {} reading(s) extracted, {} new", file, extracted, saved),
+            Locale::Fr => format!("✅ {} : {} mesure(s) extraite(s), {} nouvelle(s)", file, extracted, saved),
+        }
+    }
+
+    pub fn import_file_failed(&self, file: &str, error: &str) -> String {
+        match self {
+            Locale::En => format!("❌ {}: {}", file, error),
+            Locale::Fr => format!("❌ {} : {}", file, error),
+        }
+    }
+
+    pub fn import_done(&self, files: usize, saved: usize, failed: usize) -> String {
+        match self {
+            Locale::En => format!("📥 Import completed: {} file(s), {} new reading(s), {} failed", files, saved, failed),
+            Locale::Fr => format!("📥 Import terminé : {} fichier(s), {} nouvelle(s) mesure(s), {} en échec", files, saved, failed),
+        }
+    }
+
+    pub fn init_label(&self, label: &str, created: bool) -> String {
+        match (self, created) {
+            (Locale::En, true) => format!("✅ Label created: {}", label),
+            (Locale::Fr, true) => format!("✅ Label créé : {}", label),
+            (Locale::En, false) => format!("✓ Label already present: {}", label),
+            (Locale::Fr, false) => format!("✓ Label déjà présent : {}", label),
+        }
+    }
+
+    pub fn init_labels_skipped(&self) -> &'static str {
+        self.pick("⚠️  Labels not created (GMAIL_SCOPE=readonly or --no-label-changes)",
+                  "⚠️  Labels non créés (GMAIL_SCOPE=readonly ou --no-label-changes)")
+    }
+
+    pub fn init_complete(&self) -> &'static str {
+        self.pick("🎉 Initialization complete, the daemon can start",
+                  "🎉 Initialisation terminée, le daemon peut démarrer")
+    }
+
+    pub fn config_valid(&self) -> &'static str {
+        self.pick("✅ Configuration is valid!", "✅ Configuration valide !")
+    }
+
+    pub fn config_line(&self, key: ConfigLabel, value: &str) -> String {
+        let label = match (self, key) {
+            (Locale::En, ConfigLabel::Credentials) => "🔑 Credentials",
+            (Locale::Fr, ConfigLabel::Credentials) => "🔑 Identifiants",
+            (_, ConfigLabel::TokenCache) => "💾 Token cache",
+            (Locale::En, ConfigLabel::DataDir) => "📁 Data directory",
+            (Locale::Fr, ConfigLabel::DataDir) => "📁 Répertoire de données",
+            (Locale::En, ConfigLabel::Labels) => "🏷️  Gmail labels",
+            (Locale::Fr, ConfigLabel::Labels) => "🏷️  Labels Gmail",
+            (Locale::En, ConfigLabel::Site) => "🏠 Site",
+            (Locale::Fr, ConfigLabel::Site) => "🏠 Site",
+            (Locale::En, ConfigLabel::Units) => "📏 Units",
+            (Locale::Fr, ConfigLabel::Units) => "📏 Unités",
+            (Locale::En, ConfigLabel::Language) => "🌍 Language",
+            (Locale::Fr, ConfigLabel::Language) => "🌍 Langue",
+            (Locale::En, ConfigLabel::Database) => "🗄️  Database",
+            (Locale::Fr, ConfigLabel::Database) => "🗄️  Base de données",
+            (Locale::En, ConfigLabel::HttpServer) => "🌐 HTTP server",
+            (Locale::Fr, ConfigLabel::HttpServer) => "🌐 Serveur HTTP",
+            (Locale::En, ConfigLabel::DropDir) => "📂 Drop directory",
+            (Locale::Fr, ConfigLabel::DropDir) => "📂 Répertoire de dépôt",
+            (Locale::En, ConfigLabel::Templates) => "📝 Notification templates",
+            (Locale::Fr, ConfigLabel::Templates) => "📝 Modèles de notifications",
+        };
+        match self {
+            Locale::En => format!("{}: {}", label, value),
+            Locale::Fr => format!("{} : {}", label, value),
+        }
+    }
+}
+
+/// Lines printed by `homemetrics check`
+#[derive(Debug, Clone, Copy)]
+pub enum ConfigLabel {
+    Credentials,
+    TokenCache,
+    DataDir,
+    Labels,
+    Site,
+    Units,
+    Language,
+    Database,
+    HttpServer,
+    DropDir,
+    Templates,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse("FR"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("fr_FR.UTF-8"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("de"), None);
+    }
+
+    #[test]
+    fn test_messages_follow_locale() {
+        assert_eq!(Locale::En.emails_found(2), "✅ Found 2 email(s) matching criteria");
+        assert_eq!(Locale::Fr.emails_found(2), "✅ 2 email(s) correspondant(s) aux critères");
+        assert_eq!(Locale::Fr.config_line(ConfigLabel::DataDir, "./data"), "📁 Répertoire de données : ./data");
+    }
+}
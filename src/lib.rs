@@ -1,13 +1,45 @@
 // Library exports for homemetrics crate
 // This allows tests and other crates to use the modules
 
+pub mod archive;
 pub mod attachment_parser;
 pub mod config;
+pub mod error;
+pub mod i18n;
+pub mod logging;
+pub mod redact;
 pub mod database;
+pub mod storage;
 pub mod gmail_client;
+pub mod auth;
 pub mod slack_notifier;
 pub mod email;
+pub mod pipeline;
 pub mod token_refresh;
+pub mod units;
+pub mod mold;
+pub mod alerts;
+pub mod hooks;
+pub mod report;
+pub mod export;
+pub mod stats;
+pub mod health;
+pub mod doctor;
+pub mod debug_bundle;
+pub mod control;
+pub mod trace;
+pub mod forecast;
+pub mod templates;
+pub mod validation;
+pub mod transforms;
+pub mod scheduler;
+pub mod tui;
+
+// HTTP server (push ingestion from LAN devices)
+pub mod server;
+
+// Local push receiver (files dropped over SFTP/HTTP)
+pub mod receiver;
 
 // X-Sense temperature monitoring module
 pub mod xsense;
@@ -0,0 +1,428 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use log::kv::{Key, Value, VisitSource};
+use log::{Level, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+use crate::redact::redact;
+
+/// Socket of the journald native protocol
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Where log records go (`LOG_BACKEND`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogBackend {
+    /// journald when started by systemd with its output captured by the journal, stderr otherwise
+    #[default]
+    Auto,
+    Stderr,
+    /// journald native protocol, with key-values as structured fields
+    Journald,
+}
+
+impl LogBackend {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Some(LogBackend::Auto),
+            "stderr" | "text" => Some(LogBackend::Stderr),
+            "journald" | "journal" => Some(LogBackend::Journald),
+            _ => None,
+        }
+    }
+}
+
+/// When the log file is rotated (`LOG_ROTATION`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    /// On the first write of a new (local) day
+    #[default]
+    Daily,
+    /// Once the file would exceed `LOG_MAX_SIZE_MB`
+    Size,
+    /// Never, the file keeps growing
+    Never,
+}
+
+impl LogRotation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "daily" | "day" => Some(LogRotation::Daily),
+            "size" => Some(LogRotation::Size),
+            "never" | "none" => Some(LogRotation::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Log destination (`LOG_BACKEND`) and optional rotated copy (`LOG_FILE`)
+///
+/// Read before the logger exists, so invalid values are reported on stderr.
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub backend: LogBackend,
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    pub max_size_bytes: u64,
+    /// Rotated files kept next to the current one, older ones are deleted
+    pub retention: usize,
+}
+
+impl LogConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+        let number = |name: &str, default: u64| match var(name) {
+            Some(value) => value.trim().parse().unwrap_or_else(|_| {
+                eprintln!("Invalid {} value '{}' - using {}", name, value, default);
+                default
+            }),
+            None => default,
+        };
+
+        LogConfig {
+            backend: match var("LOG_BACKEND") {
+                Some(value) => LogBackend::parse(&value).unwrap_or_else(|| {
+                    eprintln!("Invalid LOG_BACKEND value '{}' (expected auto, stderr or journald) - using auto", value);
+                    LogBackend::Auto
+                }),
+                None => LogBackend::Auto,
+            },
+            file: var("LOG_FILE").map(PathBuf::from),
+            rotation: match var("LOG_ROTATION") {
+                Some(value) => LogRotation::parse(&value).unwrap_or_else(|| {
+                    eprintln!("Invalid LOG_ROTATION value '{}' (expected daily, size or never) - using daily", value);
+                    LogRotation::Daily
+                }),
+                None => LogRotation::Daily,
+            },
+            max_size_bytes: number("LOG_MAX_SIZE_MB", 10).max(1) * 1024 * 1024,
+            retention: number("LOG_RETENTION", 7) as usize,
+        }
+    }
+}
+
+/// Initialize logging: stderr or journald, plus `LOG_FILE` when set
+///
+/// `RUST_LOG` filters records whatever the backend.
+pub fn init(config: &LogConfig) -> Result<()> {
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.format(format_record);
+    let file = config.file.as_deref()
+        .map(|path| RotatingFile::open(path, config.rotation, config.max_size_bytes, config.retention))
+        .transpose()?;
+
+    let journal = match config.backend {
+        LogBackend::Stderr => None,
+        LogBackend::Auto => std::env::var_os("JOURNAL_STREAM")
+            .and_then(|_| JournaldLogger::connect().ok()),
+        LogBackend::Journald => Some(JournaldLogger::connect()?),
+    };
+
+    let Some(mut journal) = journal else {
+        if let Some(file) = file {
+            builder.target(env_logger::Target::Pipe(Box::new(Tee { file })));
+        }
+        builder.init();
+        return Ok(());
+    };
+
+    // env_logger still filters, and formats the copy written to LOG_FILE
+    if let Some(file) = file {
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+        journal.file_copy = true;
+    }
+    journal.filter = Some(builder.build());
+    let max_level = journal.filter.as_ref().map_or(log::LevelFilter::Info, |filter| filter.filter());
+    log::set_boxed_logger(Box::new(journal)).context("Logger already initialized")?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// env_logger's default layout, with secrets masked
+fn format_record(buf: &mut env_logger::fmt::Formatter, record: &Record) -> io::Result<()> {
+    let style = buf.default_level_style(record.level());
+    writeln!(buf, "[{} {style}{:<5}{style:#} {}] {}",
+             buf.timestamp(), record.level(), record.target(), redact(&record.args().to_string()))
+}
+
+/// Logger speaking the journald native protocol
+///
+/// Each record is a datagram of `FIELD=value` lines: `MESSAGE`, `PRIORITY`,
+/// `SYSLOG_IDENTIFIER`, `TARGET`, `CODE_FILE`, `CODE_LINE`, plus one field per
+/// key-value of the record (`info!(source = "xsense", email_id = id; "...")`
+/// gives `SOURCE=xsense` and `EMAIL_ID=...`).
+pub struct JournaldLogger {
+    socket: UnixDatagram,
+    filter: Option<env_logger::Logger>,
+    /// Whether `filter` also writes each record to `LOG_FILE`
+    file_copy: bool,
+}
+
+impl JournaldLogger {
+    pub fn connect() -> Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(JOURNALD_SOCKET)
+            .with_context(|| format!("Unable to connect to journald ({})", JOURNALD_SOCKET))?;
+        Ok(JournaldLogger { socket, filter: None, file_copy: false })
+    }
+}
+
+impl Log for JournaldLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.as_ref().is_none_or(|filter| filter.matches(record)) {
+            return;
+        }
+        if self.file_copy {
+            if let Some(filter) = &self.filter {
+                filter.log(record);
+            }
+        }
+
+        let payload = journal_payload(record);
+        if self.socket.send(&payload).is_err() {
+            // Journal unavailable or record too large for a datagram: keep it readable
+            eprintln!("[{} {}] {}", record.level(), record.target(), redact(&record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(filter) = &self.filter {
+            filter.flush();
+        }
+    }
+}
+
+/// Serialize a record as a journald native protocol datagram
+pub fn journal_payload(record: &Record) -> Vec<u8> {
+    let priority = match record.level() {
+        Level::Error => "3",
+        Level::Warn => "4",
+        Level::Info => "6",
+        Level::Debug | Level::Trace => "7",
+    };
+
+    let mut payload = Vec::new();
+    journal_field(&mut payload, "MESSAGE", &redact(&record.args().to_string()));
+    journal_field(&mut payload, "PRIORITY", priority);
+    journal_field(&mut payload, "SYSLOG_IDENTIFIER", "homemetrics");
+    journal_field(&mut payload, "TARGET", record.target());
+    if let Some(file) = record.file() {
+        journal_field(&mut payload, "CODE_FILE", file);
+    }
+    if let Some(line) = record.line() {
+        journal_field(&mut payload, "CODE_LINE", &line.to_string());
+    }
+    let _ = record.key_values().visit(&mut JournalFields(&mut payload));
+    payload
+}
+
+/// Append one field, using the length-prefixed form for multi-line values
+fn journal_field(payload: &mut Vec<u8>, name: &str, value: &str) {
+    payload.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value.as_bytes());
+    payload.push(b'\n');
+}
+
+/// Journald field name of a record key: uppercase letters, digits and underscores
+fn journal_field_name(key: &str) -> String {
+    let name: String = key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    // Leading underscores are reserved for trusted fields set by journald
+    name.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit()).to_string()
+}
+
+struct JournalFields<'a>(&'a mut Vec<u8>);
+
+impl<'kvs> VisitSource<'kvs> for JournalFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let name = journal_field_name(key.as_str());
+        if !name.is_empty() {
+            journal_field(self.0, &name, &redact(&value.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Log file rotated by day or size
+///
+/// Rotated files are renamed `<file>.<YYYYmmdd-HHMMSS>` and only the
+/// `retention` most recent ones are kept.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened_on: NaiveDate,
+    rotation: LogRotation,
+    max_size: u64,
+    retention: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, rotation: LogRotation, max_size: u64, retention: usize) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create log directory {}", dir.display()))?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)
+            .with_context(|| format!("Unable to open log file {}", path.display()))?;
+        let metadata = file.metadata()?;
+        // A file left by a previous day is rotated on the first write
+        let opened_on = metadata.modified()
+            .map(|modified| DateTime::<Local>::from(modified).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            file,
+            size: metadata.len(),
+            opened_on,
+            rotation,
+            max_size,
+            retention,
+        })
+    }
+
+    fn should_rotate(&self, incoming: usize) -> bool {
+        match self.rotation {
+            LogRotation::Daily => Local::now().date_naive() != self.opened_on && self.size > 0,
+            LogRotation::Size => self.size > 0 && self.size + incoming as u64 > self.max_size,
+            LogRotation::Never => false,
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let stamp = Local::now().format("%Y%m%d-%H%M%S");
+        let mut target = self.rotated_path(&stamp.to_string());
+        let mut counter = 1;
+        while target.exists() {
+            target = self.rotated_path(&format!("{}-{}", stamp, counter));
+            counter += 1;
+        }
+        fs::rename(&self.path, &target)?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.opened_on = Local::now().date_naive();
+        self.prune()
+    }
+
+    fn rotated_path(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", suffix));
+        self.path.with_file_name(name)
+    }
+
+    /// Rotated files of this log, oldest first
+    pub fn rotated_files(&self) -> io::Result<Vec<PathBuf>> {
+        let prefix = format!("{}.", self.path.file_name().unwrap_or_default().to_string_lossy());
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix)))
+            .collect();
+        // Timestamp suffixes sort chronologically
+        files.sort();
+        Ok(files)
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let files = self.rotated_files()?;
+        let excess = files.len().saturating_sub(self.retention);
+        for file in &files[..excess] {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Copy of each log line to stderr and the log file
+struct Tee {
+    file: RotatingFile,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf)?;
+        self.file.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_rotation_and_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("homemetrics.log");
+        let mut file = RotatingFile::open(&path, LogRotation::Size, 100, 2).unwrap();
+
+        for i in 0..10 {
+            file.write_all(format!("{:>59}\n", i).as_bytes()).unwrap();
+        }
+
+        // Two 60-byte lines exceed 100 bytes: one line per file, only 2 rotated files kept
+        assert_eq!(fs::read_to_string(&path).unwrap().len(), 60);
+        assert_eq!(file.rotated_files().unwrap().len(), 2);
+        assert_eq!(LogRotation::parse("Size"), Some(LogRotation::Size));
+        assert!(LogRotation::parse("hourly").is_none());
+    }
+
+    #[test]
+    fn test_journal_payload() {
+        let record = Record::builder()
+            .args(format_args!("Email processed\nwith 3 records"))
+            .level(Level::Warn)
+            .target("homemetrics::email")
+            .key_values(&[("source", "xsense"), ("email_id", "18c2f0a9")])
+            .build();
+
+        let payload = journal_payload(&record);
+        let text = String::from_utf8_lossy(&payload);
+        assert!(payload.starts_with(b"MESSAGE\n"));
+        assert_eq!(&payload[8..16], &30u64.to_le_bytes());
+        assert!(text.contains("\nPRIORITY=4\n"));
+        assert!(text.contains("\nSOURCE=xsense\n"));
+        assert!(text.contains("\nEMAIL_ID=18c2f0a9\n"));
+        assert_eq!(journal_field_name("_sensor.id"), "SENSOR_ID");
+    }
+}
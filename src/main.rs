@@ -1,202 +1,1987 @@
 use anyhow::Result;
-use log::{info, error};
-use clap::Parser;
+use log::{debug, info, error, warn};
+use clap::{CommandFactory, Parser, Subcommand};
 
-mod config;
-mod gmail_client;
-mod attachment_parser;
-mod database;
-mod slack_notifier;
-mod email;
-mod token_refresh;
+use homemetrics::{auth, config, control, debug_bundle, doctor, forecast, gmail_client, health, logging, pipeline, receiver, scheduler, server, stats, token_refresh, trace, tui};
+use homemetrics::pipeline::{EmailSource, PipelineTarget};
+use homemetrics::slack_notifier::{NotificationKind, SlackNotifier};
+use homemetrics::gmail_client::FilterSetup;
+use homemetrics::config::Config;
+use homemetrics::database::{Database, SchemaMismatch, SCHEMA_VERSION};
+use homemetrics::error::{classify, ErrorClass};
+use homemetrics::i18n::ConfigLabel;
+use homemetrics::logging::LogConfig;
+use homemetrics::redact::redact;
+use homemetrics::email::{DryRunDetail, RunLimits, RunSummary};
+use homemetrics::email::cleanup::cleanup_processed_emails;
+use homemetrics::export::{self, export_readings, ExportFormat};
+use homemetrics::email::common::render_summary_table;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use homemetrics::i18n::Locale;
+use homemetrics::report::{RunMode, RunReport, SummaryFile};
+use homemetrics::storage::{DeleteScope, DeliveryStatus};
+use homemetrics::templates::NotificationTemplates;
 
-// X-Sense temperature monitoring module
-mod xsense;
+const ROOT_EXAMPLES: &str = "\
+Examples:
+  homemetrics check                          Validate the configuration
+  homemetrics --config /etc/homemetrics.toml check
+                                             Validate a TOML configuration file
+  homemetrics doctor                         Check Gmail, the database and Slack for real
+  homemetrics debug-bundle                   Write a redacted archive to attach to a bug report
+  homemetrics init                           Migrate, authorize Gmail and create the labels, then exit
+  homemetrics fetch --dry-run --limit 3      Analyze the 3 first emails without saving
+  homemetrics fetch --dry-run --output json  The same analysis as one JSON document, for scripts
+  homemetrics fetch -d --dry-run-detail summary
+                                             Preview a large backlog as a compact table
+  homemetrics fetch --dry-run-db sandbox.sqlite
+                                             Write the rows into a SQLite file instead
+  homemetrics fetch                          Process X-Sense and Blue Riot emails
+  homemetrics fetch --source blueriot        Process only the Blue Riot emails
+  homemetrics fetch --since 2025-03-01 --until 2025-03-31
+                                             Process only the emails received in March 2025
+  homemetrics fetch --report last-run.md     Process and write a Markdown run report
+  homemetrics fetch --no-label-changes       Process without touching Gmail labels (staging)
+  homemetrics daemon                         Run with the configured schedule
+  homemetrics status                         Last runs, next runs and token age of the running daemon
+  homemetrics daemon --max-duration 50m      Keep each scheduled run under 50 minutes
+  homemetrics fetch --max-api-calls 2000 --max-db-rows 100000
+                                             Backfill in bounded chunks, resuming on the next run
+  homemetrics serve                          Run only the HTTP ingest server
+  homemetrics import ~/Downloads/exports     Save the readings of downloaded .eml/CSV files
+  homemetrics --profile chalet fetch --dry-run
+                                             Analyze the emails of one profile only
+  homemetrics labels list --filter homemetrics
+                                             List the homemetrics Gmail labels with their IDs
+  homemetrics query --sensor patio --last 24h
+                                             Show the readings stored for a sensor
+  homemetrics query dead-letters             List emails given up after too many failures
+  homemetrics trace --sensor patio --at \"2024-05-01 14:00\"
+                                             Show the email and attachment of a stored reading
+  homemetrics inspect --message-id 18c2f0a9b3d4e5f6
+                                             Replay one email in dry-run and show every step
+  homemetrics requeue 18c2f0a9b3d4e5f6       Retry a dead-lettered email on the next run
+  homemetrics backfill --label homemetrics/done/xsense --since 2024-01-01
+                                             Extract processed emails again, replacing their readings
+  homemetrics backfill-gmail --source xsense --all
+                                             First install: process the whole label history, resumable
+  homemetrics cleanup                        Trash processed emails older than CLEANUP_AFTER_DAYS
+  homemetrics query api-usage --days 30      Show the Gmail API requests of the last 30 days
+  homemetrics auth status                    Check the Gmail OAuth2 token
+  homemetrics export -o ./export --from 2025-01-01
+                                             Export readings partitioned by sensor and month
+  homemetrics export -o ./export --sensor cabane --format parquet
+                                             Export one sensor as Parquet files
+  homemetrics export -o ./export --duckdb homemetrics.duckdb
+                                             Export and load into DuckDB (daily_readings view...)
+  homemetrics stats --period 7d              Min/max/avg/latest of each sensor and pool over 7 days
+  homemetrics tui                            Live dashboard of the latest readings and runs (q to quit)
+  homemetrics forecast --notify             Post tomorrow's forecast digest to Slack
+  homemetrics health                         Fail when a HEALTH_SENSORS sensor has no fresh reading
+  homemetrics db migrate                     Upgrade the database schema after installing a new version
+  homemetrics db delete --email-id 18c2f0a9b3d4e5f6 --yes
+                                             Delete the readings brought by a bad email
+  homemetrics completions bash > /etc/bash_completion.d/homemetrics";
 
-// Blue Riot pool monitoring module
-mod blueriot;
+const FETCH_EXAMPLES: &str = "\
+Examples:
+  homemetrics fetch                          Process the emails of PIPELINE_SOURCES once
+  homemetrics fetch --dry-run --limit 3      Analyze the 3 first emails without saving
+  homemetrics fetch --source xsense          Process only the X-Sense emails
+  homemetrics fetch --max-duration 10m       Leave the remaining emails for the next run
+  homemetrics fetch --dry-run-db sandbox.sqlite --limit 5
+                                             Run the full pipeline into a SQLite file";
 
-use config::Config;
-use xsense::XSenseEmailProcessor;
-use blueriot::BlueRiotEmailProcessor;
+const TRACE_EXAMPLES: &str = "\
+Examples:
+  homemetrics trace --sensor patio --at \"2024-05-01 14:00\"
+                                             Email, attachment and run of the closest reading
+  homemetrics trace --sensor pool/main --at 2024-05-01T08:00:00Z --within 6h
+                                             Trace a pool reading, searching 6 hours around";
+
+const INSPECT_EXAMPLES: &str = "\
+Examples:
+  homemetrics inspect --message-id 18c2f0a9b3d4e5f6
+                                             Every step of one email: headers, attachments, readings
+  homemetrics inspect --message-id 18c2f0a9b3d4e5f6 --source blueriot --detail normal
+                                             Force the source, without the whole email text";
+
+const BACKFILL_GMAIL_EXAMPLES: &str = "\
+Examples:
+  homemetrics backfill-gmail --source xsense Process the 50 oldest emails, then exit
+  homemetrics backfill-gmail --source xsense --all
+                                             Go through the whole history, resuming where it stopped
+  homemetrics backfill-gmail --source blueriot --all --chunk 100 --pause 1m --max-api-calls 5000
+                                             Bigger chunks, spaced out, within 5000 requests
+  homemetrics backfill-gmail --source xsense --label archives/xsense --restart
+                                             Start over on another label";
+
+const DB_EXAMPLES: &str = "\
+Examples:
+  homemetrics db migrate                     Upgrade the database schema
+  homemetrics db delete --email-id 18c2f0a9b3d4e5f6
+                                             Count the readings brought by an email
+  homemetrics db delete --attachment 3f9a1c0d2b7e --yes
+                                             Delete the readings of an attachment
+  homemetrics db delete --sensor patio --from \"2024-05-01 00:00\" --to \"2024-05-01 23:59\" --yes
+                                             Delete a day of readings of a sensor";
+
+const DAEMON_EXAMPLES: &str = "\
+Examples:
+  homemetrics daemon                         Process at each SCHEDULER_TIMES time
+  homemetrics daemon --dry-run               Analyze only, nothing saved
+  homemetrics daemon --report last-run.md    Rewrite the report after each scheduled run
+  homemetrics daemon --watch-interval 0      Reload the configuration on SIGHUP only
+  homemetrics daemon --run-on-start false    Wait for the first scheduled time instead of running at once
+  homemetrics status                         Ask the running daemon for its last and next runs";
+
+const AUTH_EXAMPLES: &str = "\
+Examples:
+  homemetrics auth login                     Authorize Gmail access before the first run
+  homemetrics auth login --force             Re-authorize (e.g. another Google account)
+  homemetrics auth login --device            Authorize from another device (headless server)
+  homemetrics auth status                    Show the scopes and expiry of the cached token
+  homemetrics auth refresh                   Refresh the access token now
+  homemetrics auth revoke                    Revoke the token and delete the cache";
+
+const QUERY_EXAMPLES: &str = "\
+Examples:
+  homemetrics query --sensor patio           Readings of the patio sensor over the last 24 hours
+  homemetrics query --sensor patio --sensor pool/main --last 7d
+                                             A sensor and a pool over the last 7 days
+  homemetrics query --sensor patio --output json
+                                             The same readings as JSON, for scripts
+  homemetrics query dead-letters             List emails given up after too many failures
+  homemetrics query api-usage --days 30      Show the Gmail API requests of the last 30 days
+  homemetrics query notifications --failed   List the Slack messages that could not be delivered";
+
+const GMAIL_EXAMPLES: &str = "\
+Examples:
+  homemetrics gmail setup-filters            Label X-Sense/Blue Riot emails automatically
+  homemetrics --profile chalet gmail setup-filters
+
+Senders come from XSENSE_SENDER and BLUERIOT_SENDER.";
+
+const COMPLETIONS_EXAMPLES: &str = "\
+Examples:
+  homemetrics completions bash > /etc/bash_completion.d/homemetrics
+  homemetrics completions zsh > \"${fpath[1]}/_homemetrics\"
+  homemetrics completions fish > ~/.config/fish/completions/homemetrics.fish";
+
+const SERVE_EXAMPLES: &str = "\
+Examples:
+  homemetrics serve                          Run the HTTP server and drop directory of the configuration
+  homemetrics serve -o /srv/homemetrics/data Keep the received attachments in another directory";
+
+const IMPORT_EXAMPLES: &str = "\
+Examples:
+  homemetrics import ~/Downloads/exports     Save every .eml/CSV/JSON file of a directory
+  homemetrics import \"Thermo-cabane_Export data_20251105.csv\"
+                                             Save one X-Sense export, older readings included";
+
+const LABELS_EXAMPLES: &str = "\
+Examples:
+  homemetrics labels list                    List every Gmail label with its ID
+  homemetrics labels list --filter homemetrics
+                                             Only the homemetrics labels";
+
+const REQUEUE_EXAMPLES: &str = "\
+Examples:
+  homemetrics query dead-letters             Find the Gmail ID of a given up email
+  homemetrics requeue 18c2f0a9b3d4e5f6       Retry it on the next run";
+
+const BACKFILL_EXAMPLES: &str = "\
+Examples:
+  homemetrics backfill --label homemetrics/done/xsense --since 2024-01-01
+                                             Extract processed emails again, replacing their readings
+  homemetrics backfill --source blueriot --limit 10 --dry-run
+                                             Preview the 10 first Blue Riot emails without saving";
+
+const CLEANUP_EXAMPLES: &str = "\
+Examples:
+  homemetrics cleanup                        Trash or unlabel done emails older than CLEANUP_AFTER_DAYS
+  homemetrics --profile chalet cleanup       Clean up the mailbox of one profile only";
+
+const CHECK_EXAMPLES: &str = "\
+Examples:
+  homemetrics check                          Validate .env and homemetrics.toml
+  homemetrics --config /etc/homemetrics.toml check
+                                             Validate another configuration file";
+
+const INIT_EXAMPLES: &str = "\
+Examples:
+  homemetrics init                           Migrate, authorize Gmail and create the labels, then exit
+  homemetrics init && exec homemetrics daemon
+                                             Container entrypoint: bootstrap, then run";
+
+const HEALTH_EXAMPLES: &str = "\
+Examples:
+  homemetrics health                         Exit with an error when a HEALTH_SENSORS sensor is stale
+  homemetrics --profile chalet health        Check the sensors of one profile only";
+
+const DOCTOR_EXAMPLES: &str = "\
+Examples:
+  homemetrics doctor                         Check Gmail, the database and Slack for real
+  homemetrics --profile chalet doctor        Check the accounts of one profile only";
+
+const DEBUG_BUNDLE_EXAMPLES: &str = "\
+Examples:
+  homemetrics debug-bundle                   Write homemetrics-debug-<date>-<time>.tar.gz
+  homemetrics debug-bundle -o /tmp/bug.tar.gz
+                                             Write the archive to another file";
+
+const STATUS_EXAMPLES: &str = "\
+Examples:
+  homemetrics status                         Last runs, next runs and token age of the running daemon
+  homemetrics status --output json           The same state as JSON, for scripts";
+
+const EXPORT_EXAMPLES: &str = "\
+Examples:
+  homemetrics export -o ./export --from 2025-01-01
+                                             Export readings partitioned by sensor and month
+  homemetrics export -o ./export --sensor cabane --format parquet
+                                             Export one sensor as Parquet files
+  homemetrics export -o ./export --duckdb homemetrics.duckdb
+                                             Export and load into DuckDB (daily_readings view...)";
+
+const STATS_EXAMPLES: &str = "\
+Examples:
+  homemetrics stats                          Min/max/avg/latest of each sensor and pool over 7 days
+  homemetrics stats --period 24h             The same over the last day";
+
+const TUI_EXAMPLES: &str = "\
+Examples:
+  homemetrics tui                            Live dashboard of the latest readings and runs (q to quit)
+  homemetrics tui --refresh 1m               Read the database once a minute";
+
+const FORECAST_EXAMPLES: &str = "\
+Examples:
+  homemetrics forecast                       Print tomorrow's pool temperature and freeze risks
+  homemetrics forecast --notify              Post the digest to Slack instead";
 
 #[derive(Parser)]
 #[command(name = "homemetrics")]
 #[command(about = "HomeMetrics mail client to retrieve X-Sense data")]
 #[command(version = "0.1.0")]
+#[command(after_long_help = ROOT_EXAMPLES)]
 struct Args {
+    #[command(subcommand)]
+    command: Command,
+    
+    /// TOML configuration file, environment variables overriding its values
+    /// (default: homemetrics.toml when present)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    
+    /// Only use this profile when several are defined (PROFILES)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    
+    /// Process and store readings but never change Gmail labels; processed emails are
+    /// remembered in the database instead (e.g. a staging instance on the same mailbox)
+    #[arg(long, global = true)]
+    no_label_changes: bool,
+}
+
+/// Options shared by the commands processing emails (`fetch`, `daemon`)
+#[derive(clap::Args)]
+struct RunArgs {
     /// Dry-run mode: analyze emails without saving to database
     #[arg(short, long)]
     dry_run: bool,
     
-    /// Daemon mode: run the program as a daemon with scheduling
-    #[arg(long)]
-    daemon: bool,
+    /// Dry-run output per email: summary (table only), normal, or full (every reading, whole text)
+    #[arg(long, value_enum, value_name = "LEVEL", default_value = "normal", requires = "dry_run")]
+    dry_run_detail: DryRunDetail,
     
-    /// Token refresh interval in minutes for daemon mode (default: 45)
-    #[arg(long, default_value = "45")]
-    token_refresh_interval: u64,
+    /// Write a report of each run to this file (JSON, or Markdown for .md)
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
     
-    /// Attachment save directory (default: ./data)
-    #[arg(short = 'o', long, default_value = "./data")]
-    data_dir: String,
+    /// Attachment save directory (default: DATA_DIR)
+    #[arg(short = 'o', long, value_name = "DIR")]
+    data_dir: Option<String>,
+    
+    /// Email source to process (repeatable, default: PIPELINE_SOURCES)
+    #[arg(long = "source", value_enum, value_name = "SOURCE")]
+    sources: Vec<SourceArg>,
     
     /// Limit the number of emails to process (default: unlimited)
     #[arg(short = 'l', long)]
     limit: Option<usize>,
     
-    /// List all Gmail labels with their IDs
-    #[arg(long)]
-    list_labels: bool,
+    /// Stop starting new emails after this duration (e.g. 10m, 1h30m); the rest waits for the next run
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    max_duration: Option<std::time::Duration>,
+    
+    /// Stop starting new emails once a processor issued this many Gmail API requests
+    #[arg(long, value_name = "N")]
+    max_api_calls: Option<usize>,
+    
+    /// Stop starting new emails once a processor inserted this many rows
+    #[arg(long, value_name = "N")]
+    max_db_rows: Option<usize>,
+    
+    /// Only process the emails received from this day (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    since: Option<NaiveDate>,
+    
+    /// Only process the emails received up to this day, included (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    until: Option<NaiveDate>,
+}
+
+impl RunArgs {
+    fn run_limits(&self) -> RunLimits {
+        RunLimits {
+            max_emails: self.limit,
+            max_duration: self.max_duration,
+            max_api_calls: self.max_api_calls,
+            max_db_rows: self.max_db_rows,
+        }
+    }
+    
+    /// Detail level of a dry-run, None for a normal run
+    fn dry_run(&self) -> Option<DryRunDetail> {
+        self.dry_run.then_some(self.dry_run_detail)
+    }
+    
+    /// Sources selected with `--source`, None to keep PIPELINE_SOURCES
+    fn sources(&self) -> Option<Vec<EmailSource>> {
+        if self.sources.is_empty() {
+            return None;
+        }
+        if self.sources.contains(&SourceArg::All) {
+            return Some(EmailSource::ALL.to_vec());
+        }
+        let mut sources = Vec::new();
+        for source in &self.sources {
+            let source = match source {
+                SourceArg::Xsense => EmailSource::XSense,
+                SourceArg::Blueriot => EmailSource::BlueRiot,
+                SourceArg::All => continue,
+            };
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        Some(sources)
+    }
+    
+    fn overrides(&self, no_label_changes: bool) -> Result<Overrides> {
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                anyhow::bail!("--since {} is after --until {}", since, until);
+            }
+        }
+        Ok(Overrides {
+            data_dir: self.data_dir.clone(),
+            sources: self.sources(),
+            no_label_changes,
+            since: self.since,
+            until: self.until,
+        })
+    }
+}
+
+/// `--source` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum SourceArg {
+    /// X-Sense temperature exports
+    Xsense,
+    /// Blue Riot pool reports
+    Blueriot,
+    /// Every source
+    All,
+}
+
+/// Settings given on the command line, applied over the configuration of every
+/// profile (again after a daemon reload)
+#[derive(Debug, Clone, Default)]
+struct Overrides {
+    data_dir: Option<String>,
+    sources: Option<Vec<EmailSource>>,
+    no_label_changes: bool,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+}
+
+impl Overrides {
+    fn apply(&self, profiles: &mut [Config]) {
+        for config in profiles {
+            if let Some(data_dir) = &self.data_dir {
+                config.data_dir = data_dir.clone();
+            }
+            if let Some(sources) = &self.sources {
+                config.pipeline.sources = sources.clone();
+            }
+            // Labels stay untouched everywhere, requeue included
+            if self.no_label_changes {
+                config.gmail.label_changes = false;
+            }
+            config.gmail.search_since = self.since;
+            config.gmail.search_until = self.until;
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct DaemonArgs {
+    #[command(flatten)]
+    run: RunArgs,
+    
+    /// Token refresh interval in minutes (default: 45)
+    #[arg(long, default_value = "45")]
+    token_refresh_interval: u64,
+    
+    /// Seconds between checks of .env and the configuration file for changes (0: reload on SIGHUP only)
+    #[arg(long, value_name = "SECONDS", default_value = "5")]
+    watch_interval: u64,
+    
+    /// Process the emails once at startup, before the first scheduled time
+    /// (default: SCHEDULER_RUN_ON_START, true; `--run-on-start false` waits)
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    run_on_start: Option<bool>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Process the emails of the enabled sources once, then exit
+    #[command(after_long_help = FETCH_EXAMPLES)]
+    Fetch {
+        #[command(flatten)]
+        run: RunArgs,
+        
+        /// Dry-run sandbox: run the full pipeline, inserts included, into this SQLite file
+        #[arg(long, value_name = "FILE", conflicts_with = "dry_run")]
+        dry_run_db: Option<PathBuf>,
+        
+        /// Dry-run output: table (text for a terminal) or json (emails, readings and would-be inserts)
+        #[arg(long, value_enum, default_value = "table", requires = "dry_run")]
+        output: OutputFormat,
+        
+        /// Write the counts of the run (emails, readings, errors, outcome) to this JSON file
+        #[arg(long, value_name = "PATH")]
+        summary_file: Option<PathBuf>,
+    },
+    
+    /// Run as a daemon, processing the emails at the configured times (SCHEDULER_TIMES, SCHEDULER_CRON)
+    #[command(after_long_help = DAEMON_EXAMPLES)]
+    Daemon(DaemonArgs),
+    
+    /// Run only the listeners (HTTP ingest/upload and drop directory)
+    #[command(after_long_help = SERVE_EXAMPLES)]
+    Serve {
+        /// Attachment save directory (default: DATA_DIR)
+        #[arg(short = 'o', long, value_name = "DIR")]
+        data_dir: Option<String>,
+    },
+    
+    /// Save the readings of local files (.eml or X-Sense exports), without Gmail
+    #[command(after_long_help = IMPORT_EXAMPLES)]
+    Import {
+        /// Files or directories (read recursively for .eml, .csv, .json, .xml and .txt files)
+        #[arg(required = true, value_name = "PATH")]
+        paths: Vec<PathBuf>,
+    },
+    
+    /// Inspect the Gmail labels
+    #[command(after_long_help = LABELS_EXAMPLES)]
+    Labels {
+        #[command(subcommand)]
+        action: LabelsAction,
+    },
+    
+    /// Read stored readings (--sensor) or the processing state from the database
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true, after_long_help = QUERY_EXAMPLES)]
+    Query {
+        #[command(subcommand)]
+        action: Option<QueryAction>,
+        
+        #[command(flatten)]
+        readings: ReadingsArgs,
+    },
+    
+    /// Show which email, attachment and run a stored reading comes from
+    #[command(after_long_help = TRACE_EXAMPLES)]
+    Trace {
+        /// Sensor ID, or pool/<id> for a pool
+        #[arg(long, value_name = "SENSOR")]
+        sensor: String,
+        
+        /// Time of the reading, UTC ("YYYY-MM-DD HH:MM")
+        #[arg(long, value_name = "TIME", value_parser = homemetrics::trace::parse_at)]
+        at: DateTime<Utc>,
+        
+        /// Search the closest reading this long before and after --at
+        #[arg(long, value_name = "DURATION", default_value = "1h", value_parser = humantime::parse_duration)]
+        within: Duration,
+    },
+    
+    /// Put a dead-lettered email back in the retry queue and restore its todo label
+    #[command(after_long_help = REQUEUE_EXAMPLES)]
+    Requeue {
+        /// Gmail ID of the email (see `query dead-letters`)
+        email_id: String,
+    },
+    
+    /// Extract already processed emails again, replacing the readings stored for them
+    #[command(after_long_help = BACKFILL_EXAMPLES)]
+    Backfill {
+        /// Gmail label of the emails (default: <prefix>/done/<source>)
+        #[arg(long, value_name = "LABEL")]
+        label: Option<String>,
+        
+        /// Source of the emails (default: last segment of --label)
+        #[arg(long, value_enum)]
+        source: Option<SourceArg>,
+        
+        /// First day of reception (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        since: Option<NaiveDate>,
+        
+        /// Last day of reception, included (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        until: Option<NaiveDate>,
+        
+        /// Maximum number of emails to reprocess
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        
+        /// Extract and print without saving
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+    
+    /// Replay one Gmail email in dry-run, whatever its labels, and print every step of its extraction
+    #[command(after_long_help = INSPECT_EXAMPLES)]
+    Inspect {
+        /// Gmail ID of the email (as shown by `fetch --dry-run` and the logs)
+        #[arg(long, value_name = "ID")]
+        message_id: String,
+        
+        /// Source of the email (default: from its todo, done or error label)
+        #[arg(long, value_enum)]
+        source: Option<SourceArg>,
+        
+        /// Output: summary (table only), normal, or full (every reading, whole text)
+        #[arg(long, value_enum, value_name = "LEVEL", default_value = "full")]
+        detail: DryRunDetail,
+    },
+    
+    /// First install: process the whole history of a label oldest first, in checkpointed chunks
+    #[command(after_long_help = BACKFILL_GMAIL_EXAMPLES)]
+    BackfillGmail {
+        /// Source of the emails
+        #[arg(long, value_enum)]
+        source: SourceArg,
+        
+        /// Gmail label of the emails (default: <prefix>/todo/<source>)
+        #[arg(long, value_name = "LABEL")]
+        label: Option<String>,
+        
+        /// Keep going until the whole history is processed (default: one chunk, then exit)
+        #[arg(long)]
+        all: bool,
+        
+        /// Emails processed between two checkpoints
+        #[arg(long, value_name = "N", default_value_t = 50)]
+        chunk: usize,
+        
+        /// Wait between two chunks with --all
+        #[arg(long, value_name = "DURATION", default_value = "30s", value_parser = humantime::parse_duration)]
+        pause: Duration,
+        
+        /// Stop before the next chunk once this many Gmail API requests were issued
+        #[arg(long, value_name = "N")]
+        max_api_calls: Option<usize>,
+        
+        /// Forget the checkpoint and start over from the oldest email
+        #[arg(long)]
+        restart: bool,
+    },
+    
+    /// Clean up processed emails older than CLEANUP_AFTER_DAYS
+    #[command(after_long_help = CLEANUP_EXAMPLES)]
+    Cleanup,
+    
+    /// Check the configuration without connecting
+    #[command(after_long_help = CHECK_EXAMPLES)]
+    Check,
+    
+    /// Bootstrap before the daemon starts: validate the configuration, migrate the database,
+    /// authorize Gmail (device flow) and create the labels, then exit (idempotent)
+    #[command(after_long_help = INIT_EXAMPLES)]
+    Init,
+    
+    /// Check that every sensor of HEALTH_SENSORS has a fresh reading (fails otherwise)
+    #[command(after_long_help = HEALTH_EXAMPLES)]
+    Health,
+    
+    /// Check the environment for real: Gmail token and labels, a database write, a Slack post
+    #[command(after_long_help = DOCTOR_EXAMPLES)]
+    Doctor,
+    
+    /// Write a redacted archive to attach to a bug report: configuration, schema version, recent runs,
+    /// last errors, environment and the end of LOG_FILE
+    #[command(after_long_help = DEBUG_BUNDLE_EXAMPLES)]
+    DebugBundle {
+        /// Archive to write (default: homemetrics-debug-<date>-<time>.tar.gz)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    
+    /// Ask the running daemon for its uptime, the last run of each source, the next
+    /// scheduled runs and the age of the Gmail token (CONTROL_SOCKET)
+    #[command(after_long_help = STATUS_EXAMPLES)]
+    Status {
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        output: OutputFormat,
+    },
+    
+    /// Generate shell completion script (bash, zsh, fish, elvish, powershell)
+    #[command(after_long_help = COMPLETIONS_EXAMPLES)]
+    Completions {
+        /// Target shell
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    
+    /// Manage the Gmail OAuth2 token (GMAIL_TOKEN_CACHE_PATH)
+    #[command(after_long_help = AUTH_EXAMPLES)]
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    
+    /// Export stored readings as CSV, JSON or Parquet files partitioned by sensor and month (for DuckDB, pandas)
+    #[command(after_long_help = EXPORT_EXAMPLES)]
+    Export {
+        /// Output directory, written as <DIR>/sensor=<id>/month=<YYYY-MM>/readings.<csv|json|parquet>
+        #[arg(short, long, value_name = "DIR")]
+        output: PathBuf,
+        
+        /// Only export this sensor (repeatable), all sensors by default
+        #[arg(long = "sensor", value_name = "ID")]
+        sensors: Vec<String>,
+        
+        /// First day to export (YYYY-MM-DD, UTC)
+        #[arg(long, value_name = "DATE")]
+        from: Option<NaiveDate>,
+        
+        /// Day after the last one to export (YYYY-MM-DD, UTC, exclusive)
+        #[arg(long, value_name = "DATE")]
+        to: Option<NaiveDate>,
+        
+        /// File format
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+        
+        /// Also load the export into this DuckDB file (tables and daily views), with the duckdb CLI
+        #[arg(long, value_name = "FILE")]
+        duckdb: Option<PathBuf>,
+    },
+    
+    /// Print min/max/avg/latest of each sensor and pool over a recent period
+    #[command(after_long_help = STATS_EXAMPLES)]
+    Stats {
+        /// Period ending now (e.g. 24h, 7d, 30d)
+        #[arg(long, value_name = "DURATION", default_value = "7d", value_parser = humantime::parse_duration)]
+        period: std::time::Duration,
+    },
+    
+    /// Live dashboard in the terminal: latest readings, pools, runs and next scheduled times
+    #[command(after_long_help = TUI_EXAMPLES)]
+    Tui {
+        /// Delay between two reads of the database
+        #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = humantime::parse_duration)]
+        refresh: std::time::Duration,
+    },
+    
+    /// Forecast tomorrow's pool temperature and rooms heading to FORECAST_FREEZE_THRESHOLD
+    #[command(after_long_help = FORECAST_EXAMPLES)]
+    Forecast {
+        /// Post the digest to Slack instead of printing it
+        #[arg(long)]
+        notify: bool,
+    },
     
-    /// Refresh Gmail OAuth2 token and exit
-    #[arg(long)]
-    refresh_token: bool,
+    /// Manage the database schema and stored readings
+    #[command(after_long_help = DB_EXAMPLES)]
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    
+    /// Set up the Gmail mailbox for HomeMetrics
+    #[command(after_long_help = GMAIL_EXAMPLES)]
+    Gmail {
+        #[command(subcommand)]
+        action: GmailAction,
+    },
+}
+
+/// `homemetrics query --sensor ID --last 24h`
+#[derive(clap::Args)]
+struct ReadingsArgs {
+    /// Sensor to read back, `pool/<id>` for a Blue Riot pool (repeatable)
+    #[arg(long = "sensor", value_name = "ID", required = true)]
+    sensors: Vec<String>,
+    
+    /// Period to read, ending now
+    #[arg(long, value_name = "DURATION", default_value = "24h", value_parser = humantime::parse_duration)]
+    last: Duration,
+    
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Aligned columns for a terminal
+    Table,
+    /// JSON document for scripts
+    Json,
+}
+
+#[derive(Subcommand)]
+enum LabelsAction {
+    /// List the Gmail labels with their IDs
+    List {
+        /// Only list labels whose name contains this text (case-insensitive)
+        #[arg(long, value_name = "TEXT")]
+        filter: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueryAction {
+    /// List emails dead-lettered after RETRY_MAX_ATTEMPTS failures
+    DeadLetters,
+    /// Show the Gmail API requests per day and source
+    ApiUsage {
+        /// Number of days, today included
+        #[arg(long, value_name = "DAYS", default_value = "7")]
+        days: u32,
+    },
+    /// List the Slack messages sent, most recent first, with their delivery result
+    Notifications {
+        /// Only the messages that could not be delivered
+        #[arg(long)]
+        failed: bool,
+        
+        /// Number of messages
+        #[arg(long, value_name = "N", default_value = "20")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthAction {
+    /// Run the OAuth2 consent flow now, unless a usable token is already cached
+    Login {
+        /// Delete the cached token first to always go through the consent flow
+        #[arg(long)]
+        force: bool,
+        
+        /// Authorize from another device (URL and code printed), without a local browser
+        #[arg(long)]
+        device: bool,
+    },
+    /// Show the cached token: scopes, access token expiry, refresh token
+    Status,
+    /// Refresh the access token with the cached refresh token
+    Refresh,
+    /// Revoke the token at Google and delete the cache
+    Revoke,
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Create the missing tables, columns and indexes, then record the schema version
+    Migrate,
     
-    /// Check configuration without connecting
-    #[arg(long)]
-    check_config: bool,
+    /// Delete the readings of one email, one attachment or a sensor over a time range
+    ///
+    /// Without --yes, only count what would be deleted.
+    #[command(group = clap::ArgGroup::new("scope").required(true).args(["email_id", "attachment", "sensor"]))]
+    Delete {
+        /// Gmail message ID, as shown by `trace`
+        #[arg(long, value_name = "ID")]
+        email_id: Option<String>,
+        
+        /// SHA-256 of the attachment, or a prefix of it as shown by `trace`
+        #[arg(long, value_name = "HASH", value_parser = homemetrics::trace::parse_hash)]
+        attachment: Option<String>,
+        
+        /// Sensor ID, or pool/<id> for a pool
+        #[arg(long, value_name = "SENSOR", requires_all = ["from", "to"])]
+        sensor: Option<String>,
+        
+        /// Start of the range, UTC, inclusive ("YYYY-MM-DD HH:MM")
+        #[arg(long, value_name = "TIME", requires = "sensor", value_parser = homemetrics::trace::parse_at)]
+        from: Option<DateTime<Utc>>,
+        
+        /// End of the range, UTC, inclusive
+        #[arg(long, value_name = "TIME", requires = "sensor", value_parser = homemetrics::trace::parse_at)]
+        to: Option<DateTime<Utc>>,
+        
+        /// Delete for real instead of counting
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum GmailAction {
+    /// Create the filters applying <prefix>/todo/<source> to each sender's emails (skipping the inbox)
+    SetupFilters,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(code) => code,
+        Err(e) => {
+            // Connection errors can carry URLs with credentials
+            eprintln!("Error: {}", redact(&format!("{:?}", e)));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run() -> Result<ExitCode> {
     // Load .env file if it exists
     dotenv::dotenv().ok();
     
     // Parse CLI arguments
     let args = Args::parse();
     
-    // Initialize logging
-    env_logger::init();
+    // Completions don't need any configuration
+    if let Command::Completions { shell } = args.command {
+        clap_complete::generate(shell, &mut Args::command(), "homemetrics", &mut std::io::stdout());
+        return Ok(ExitCode::SUCCESS);
+    }
+    
+    // Configuration file values fill in the variables the environment leaves unset
+    let config_file = args.config.clone()
+        .or_else(|| Some(PathBuf::from(config::DEFAULT_CONFIG_FILE)).filter(|path| path.exists()));
+    let file_values = config_file.as_deref().map(config::load_file).transpose()?;
+    
+    // Initialize logging (stderr, plus LOG_FILE when set)
+    logging::init(&LogConfig::from_env())?;
+    if let (Some(path), Some(count)) = (&config_file, file_values) {
+        info!("📄 Configuration file {} loaded ({} value(s) in use)", path.display(), count);
+    }
     
-    if args.dry_run {
+    let dry_run = match &args.command {
+        Command::Fetch { run, .. } | Command::Daemon(DaemonArgs { run, .. }) => run.dry_run,
+        _ => false,
+    };
+    if dry_run {
         info!("🧪 Starting HomeMetrics X-Sense mail client in DRY-RUN mode");
     } else {
         info!("🚀 Starting HomeMetrics X-Sense mail client");
     }
     
-    // Load configuration
-    let mut config = Config::new()?;
+    // Load configuration (one entry per profile)
+    let mut profiles = Config::load_profiles()?;
+    if let Some(name) = &args.profile {
+        profiles.retain(|config| config.profile.as_deref() == Some(name.as_str()));
+        if profiles.is_empty() {
+            anyhow::bail!("Unknown profile '{}' (check PROFILES)", name);
+        }
+    }
+    
+    Overrides { no_label_changes: args.no_label_changes, ..Default::default() }.apply(&mut profiles);
+    
+    let locale = profiles[0].locale;
     
-    // If requested, list Gmail labels and exit
-    if args.list_labels {
-        use gmail_client::GmailClient;
+    match args.command {
+        Command::Completions { .. } => unreachable!("handled before loading the configuration"),
         
-        println!("📋 Listing Gmail labels...\n");
-        let gmail = GmailClient::new(&config.gmail).await?;
-        gmail.list_labels().await?;
-        return Ok(());
+        Command::Auth { action } => {
+            for config in &profiles {
+                print_profile_header(config);
+                run_auth(&action, config).await?;
+            }
+        }
+        
+        Command::Export { output, sensors, from, to, format, duckdb } => {
+            let midnight = |day: NaiveDate| day.and_time(NaiveTime::MIN).and_utc();
+            let (from, to) = (from.map(midnight), to.map(midnight));
+            for config in &profiles {
+                print_profile_header(config);
+                let database = Database::new(&config.database).await?.with_site(config.site.clone());
+                let mut known = database.sensors().await?;
+                if !sensors.is_empty() {
+                    known.retain(|sensor| sensors.contains(&sensor.sensor_id));
+                }
+                let sensor_ids: Vec<String> = known.iter().map(|sensor| sensor.sensor_id.clone()).collect();
+                
+                // Profiles sharing a directory keep their files apart
+                let output = match &config.profile {
+                    Some(profile) => output.join(format!("profile={}", profile)),
+                    None => output.clone(),
+                };
+                let stats = export_readings(&database, &sensor_ids, from, to, &output, format).await?;
+                export::export_sensors(&known, &output, format)?;
+                println!("{}", config.locale.export_done(stats.readings, stats.files.len(), &output.display().to_string()));
+                // Pools are only part of a full export
+                if sensors.is_empty() {
+                    let (count, _) = export::export_pool_readings(&database, from, to, &output, format).await?;
+                    println!("{}", config.locale.export_pools_done(count));
+                }
+                
+                if let Some(duckdb) = &duckdb {
+                    let script = export::write_duckdb_script(&output, format)?;
+                    if export::load_into_duckdb(&script, duckdb)? {
+                        println!("{}", config.locale.duckdb_loaded(&duckdb.display().to_string()));
+                    } else {
+                        println!("{}", config.locale.duckdb_cli_missing(&script.display().to_string(), &duckdb.display().to_string()));
+                    }
+                }
+            }
+        }
+        
+        Command::Stats { period } => {
+            let to = Utc::now();
+            let from = to - chrono::TimeDelta::from_std(period)?;
+            for config in &profiles {
+                print_profile_header(config);
+                let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+                let stats = stats::collect_stats(&database, from, to).await?;
+                println!("{}", stats::render_stats(&stats, config.locale, config.units));
+            }
+        }
+        
+        Command::Tui { refresh } => {
+            let [config] = profiles.as_slice() else {
+                anyhow::bail!("Several profiles are defined, choose one with --profile");
+            };
+            let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+            tui::run(config, &database, refresh).await?;
+        }
+        
+        Command::Forecast { notify } => {
+            for config in &profiles {
+                print_profile_header(config);
+                let text = forecast_digest(config).await?;
+                let notifier = if notify { recording_notifier(config).await } else { None };
+                match notifier {
+                    Some(notifier) => notifier.send_message(NotificationKind::Forecast, &text).await?,
+                    None => println!("{}", text),
+                }
+            }
+        }
+        
+        Command::Health => {
+            let mut stale = 0;
+            for config in &profiles {
+                print_profile_header(config);
+                let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+                let report = health::check_freshness(&database, &config.health.sensors, Utc::now()).await?;
+                println!("{}", health::render_health(&report, config.locale));
+                stale += report.stale().count();
+            }
+            if stale > 0 {
+                anyhow::bail!("{} sensor(s) without fresh readings", stale);
+            }
+        }
+        
+        Command::Doctor => {
+            let mut failed = 0;
+            for config in &profiles {
+                print_profile_header(config);
+                let results = doctor::run_checks(config).await;
+                println!("{}", doctor::render_doctor(&results, config.locale));
+                failed += results.iter().filter(|result| result.failed()).count();
+            }
+            if failed > 0 {
+                anyhow::bail!("{} check(s) failed", failed);
+            }
+        }
+        
+        Command::DebugBundle { output } => {
+            let files = debug_bundle::collect(&profiles, &LogConfig::from_env(), config_file.as_deref()).await;
+            let output = output.unwrap_or_else(|| debug_bundle::default_path(chrono::Local::now()));
+            debug_bundle::write_archive(&files, &output)?;
+            println!("{}", locale.debug_bundle_written(&output.display().to_string(), files.len()));
+        }
+        
+        Command::Status { output } => {
+            let Some(path) = &profiles[0].scheduler.control_socket else {
+                anyhow::bail!("The control socket is disabled (CONTROL_SOCKET=off)");
+            };
+            let mut status = control::query(Path::new(path)).await?;
+            if let Some(name) = &args.profile {
+                status.retain_profile(name);
+            }
+            match output {
+                OutputFormat::Table => println!("{}", control::render_status(&status, locale)),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+            }
+        }
+        
+        Command::Db { action: DbAction::Migrate } => {
+            for config in &profiles {
+                print_profile_header(config);
+                let found = Database::migrate(&config.database).await?;
+                println!("{}", config.locale.schema_migrated(found, SCHEMA_VERSION));
+            }
+        }
+        
+        Command::Db { action: DbAction::Delete { email_id, attachment, sensor, from, to, yes } } => {
+            // clap guarantees exactly one scope, with both bounds for a sensor
+            let scope = match (email_id, attachment, sensor, from, to) {
+                (Some(email_id), ..) => DeleteScope::Email(email_id),
+                (_, Some(hash), ..) => DeleteScope::Attachment(hash),
+                (_, _, Some(sensor_id), Some(from), Some(to)) => DeleteScope::Sensor { sensor_id, from, to },
+                _ => anyhow::bail!("--email-id, --attachment or --sensor with --from and --to is required"),
+            };
+            for config in &profiles {
+                let database = Database::new(&config.database).await?.with_site(config.site.clone());
+                let deleted = database.delete_readings(&scope, yes).await?;
+                print_profile_header(config);
+                if deleted.total() == 0 && deleted.provenance == 0 {
+                    println!("{}", config.locale.delete_nothing());
+                    continue;
+                }
+                for (sensor_id, count) in &deleted.per_sensor {
+                    println!("  {:<24} {}", sensor_id, count);
+                }
+                if yes {
+                    println!("{}", config.locale.delete_done(deleted.total(), deleted.provenance));
+                } else {
+                    println!("{}", config.locale.delete_preview(deleted.total(), deleted.provenance));
+                }
+            }
+        }
+        
+        Command::Gmail { action } => {
+            for config in &profiles {
+                print_profile_header(config);
+                run_gmail(&action, config).await?;
+            }
+        }
+        
+        Command::Labels { action: LabelsAction::List { filter } } => {
+            for config in &profiles {
+                print_profile_header(config);
+                println!("{}\n", config.locale.listing_labels());
+                let gmail = gmail_client::GmailClient::new(&config.gmail).await?;
+                gmail.list_labels(config.locale, filter.as_deref()).await?;
+            }
+        }
+        
+        Command::Query { action: Some(action), .. } => {
+            for config in &profiles {
+                print_profile_header(config);
+                run_query(&action, config).await?;
+            }
+        }
+        
+        Command::Query { action: None, readings } => {
+            let to = Utc::now();
+            let from = to - chrono::TimeDelta::from_std(readings.last)?;
+            let mut documents = Vec::new();
+            for config in &profiles {
+                let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+                if readings.output == OutputFormat::Table {
+                    print_profile_header(config);
+                }
+                for sensor in &readings.sensors {
+                    let series = stats::collect_readings(&database, sensor, from, to).await?;
+                    match readings.output {
+                        OutputFormat::Table => println!("{}\n", stats::render_readings(&series, config.locale, config.units)),
+                        OutputFormat::Json => {
+                            let mut document = serde_json::to_value(&series)?;
+                            if let Some(profile) = &config.profile {
+                                document["profile"] = profile.as_str().into();
+                            }
+                            documents.push(document);
+                        }
+                    }
+                }
+            }
+            if readings.output == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&documents)?);
+            }
+        }
+        
+        Command::Backfill { label, source, since, until, limit, dry_run } => {
+            let source = match source {
+                Some(SourceArg::Xsense) => EmailSource::XSense,
+                Some(SourceArg::Blueriot) => EmailSource::BlueRiot,
+                Some(SourceArg::All) => anyhow::bail!("--source all is not supported by backfill"),
+                None => match label.as_deref() {
+                    Some(label) => EmailSource::from_label(label)
+                        .ok_or_else(|| anyhow::anyhow!("Unable to infer the source of label '{}', use --source", label))?,
+                    None => anyhow::bail!("backfill needs --label or --source"),
+                },
+            };
+            let dry_run = dry_run.then_some(DryRunDetail::Normal);
+            for config in &profiles {
+                print_profile_header(config);
+                let label = label.clone()
+                    .unwrap_or_else(|| format!("{}/done/{}", config.gmail.label_prefix, source.key()));
+                let started = Instant::now();
+                let summary = pipeline::backfill(config, source, &label, since, until, limit, dry_run).await?;
+                println!("{}", render_summary_table(&[summary], started.elapsed(), config.locale));
+            }
+        }
+        
+        Command::Inspect { message_id, source, detail } => {
+            let [config] = profiles.as_slice() else {
+                anyhow::bail!("Several profiles are defined, choose one with --profile");
+            };
+            let source = match source {
+                Some(SourceArg::Xsense) => Some(EmailSource::XSense),
+                Some(SourceArg::Blueriot) => Some(EmailSource::BlueRiot),
+                Some(SourceArg::All) => anyhow::bail!("--source all is not supported by inspect"),
+                None => None,
+            };
+            let started = Instant::now();
+            let summary = pipeline::inspect(config, &message_id, source, detail).await?;
+            println!("{}", render_summary_table(&[summary], started.elapsed(), config.locale));
+        }
+        
+        Command::BackfillGmail { source, label, all, chunk, pause, max_api_calls, restart } => {
+            let source = match source {
+                SourceArg::Xsense => EmailSource::XSense,
+                SourceArg::Blueriot => EmailSource::BlueRiot,
+                SourceArg::All => anyhow::bail!("--source all is not supported by backfill-gmail"),
+            };
+            let options = pipeline::GmailBackfill { chunk, all, pause, max_api_calls, restart };
+            for config in &profiles {
+                print_profile_header(config);
+                let label = label.clone()
+                    .unwrap_or_else(|| format!("{}/todo/{}", config.gmail.label_prefix, source.key()));
+                let started = Instant::now();
+                let run = pipeline::backfill_gmail(config, source, &label, &options).await?;
+                if !run.summaries.is_empty() {
+                    println!("{}", render_summary_table(&run.summaries, started.elapsed(), config.locale));
+                }
+                println!("{}", config.locale.backfill_gmail_status(run.remaining, run.stopped_by, run.interrupted));
+            }
+        }
+        
+        Command::Import { paths } => {
+            let [config] = profiles.as_slice() else {
+                anyhow::bail!("Several profiles are defined, choose one with --profile");
+            };
+            let mut files = Vec::new();
+            for path in &paths {
+                files.extend(receiver::import_files(path)?);
+            }
+            
+            let receiver = receiver::FileReceiver::new(config).await?;
+            let (mut saved, mut failed) = (0, 0);
+            for file in &files {
+                let name = file.display().to_string();
+                match receiver.import_file(file).await {
+                    Ok((extracted, inserted)) => {
+                        saved += inserted;
+                        println!("{}", locale.import_file_done(&name, extracted, inserted));
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        println!("{}", locale.import_file_failed(&name, &format!("{:#}", e)));
+                    }
+                }
+            }
+            println!("{}", locale.import_done(files.len(), saved, failed));
+            if failed > 0 {
+                anyhow::bail!("{} file(s) could not be imported", failed);
+            }
+        }
+        
+        Command::Cleanup => {
+            for config in &profiles {
+                print_profile_header(config);
+                if config.cleanup.after_days == 0 {
+                    println!("{}", config.locale.cleanup_disabled());
+                    continue;
+                }
+                let gmail = gmail_client::GmailClient::new(&config.gmail).await?;
+                let cleaned = cleanup_processed_emails(config, &gmail).await?;
+                println!("{}", config.locale.cleanup_done(cleaned, config.cleanup.after_days));
+            }
+        }
+        
+        Command::Trace { sensor, at, within } => {
+            let within = chrono::TimeDelta::from_std(within)?;
+            for config in &profiles {
+                let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+                let trace = trace::trace(&database, &sensor, at, within).await?;
+                print_profile_header(config);
+                println!("{}", trace::render_trace(&trace, config.locale, config.units));
+            }
+        }
+        
+        Command::Requeue { email_id } => {
+            for config in &profiles {
+                let database = Database::new(&config.database).await?.with_site(config.site.clone());
+                let Some(retry) = database.requeue_email(&email_id).await? else {
+                    continue;
+                };
+                
+                let gmail = gmail_client::GmailClient::new(&config.gmail).await?;
+                let (error_label, todo) = (format!("error/{}", retry.source), format!("todo/{}", retry.source));
+                if let Err(e) = gmail.move_email(&email_id, &error_label, &todo).await {
+                    warn!("Email {} requeued but its labels could not be restored: {}", email_id, e);
+                }
+                print_profile_header(config);
+                println!("{}", config.locale.email_requeued(&email_id, &retry.source));
+                return Ok(ExitCode::SUCCESS);
+            }
+            anyhow::bail!(locale.email_not_dead_lettered(&email_id));
+        }
+        
+        Command::Init => {
+            println!("{}", locale.config_valid());
+            for config in &profiles {
+                print_profile_header(config);
+                run_init(config).await?;
+            }
+            println!("{}", locale.init_complete());
+        }
+        
+        Command::Check => {
+            println!("{}", locale.config_valid());
+            for config in &profiles {
+                print_config(config)?;
+            }
+        }
+        
+        Command::Serve { data_dir } => {
+            Overrides { data_dir, ..Default::default() }.apply(&mut profiles);
+            info!("🌐 Starting in server mode");
+            futures::future::try_join_all(profiles.iter().map(run_listeners)).await?;
+        }
+        
+        Command::Daemon(daemon) => {
+            let overrides = daemon.run.overrides(args.no_label_changes)?;
+            overrides.apply(&mut profiles);
+            info!("🔄 Starting in daemon mode");
+            run_daemon_mode(profiles, daemon, overrides).await?;
+        }
+        
+        Command::Fetch { run, dry_run_db, output, summary_file } => {
+            run.overrides(args.no_label_changes)?.apply(&mut profiles);
+            let started_at = Utc::now();
+            let result = match dry_run_db {
+                Some(sqlite_path) => run_sandbox(&profiles, &run, &sqlite_path).await,
+                None if output == OutputFormat::Json => run_dry_run_json(&profiles, &run).await,
+                None => run_once(&profiles, &run).await,
+            };
+            
+            let summary = SummaryFile::new(started_at, &result);
+            if let Some(path) = &summary_file {
+                if let Err(e) = summary.write(path) {
+                    error!("❌ Unable to write run summary: {:#}", e);
+                }
+            }
+            result?;
+            return Ok(ExitCode::from(summary.exit_code));
+        }
     }
     
-    // If requested, refresh token and exit
-    if args.refresh_token {
-        use gmail_client::GmailClient;
-        
-        println!("🔄 Refreshing Gmail OAuth2 token...\n");
-        let gmail = GmailClient::new(&config.gmail).await?;
-        gmail.refresh_token().await?;
-        println!("✅ Token refreshed successfully and persisted to cache");
-        println!("💾 Token cache: {}", config.gmail.token_cache_path);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `homemetrics init` for one profile, each step being a no-op when already done
+async fn run_init(config: &Config) -> Result<()> {
+    let locale = config.locale;
+    NotificationTemplates::from_config(config)?;
+    
+    let found = Database::migrate(&config.database).await?;
+    println!("{}", locale.schema_migrated(found, SCHEMA_VERSION));
+    
+    // A cached token is reused, the device flow only runs without one
+    println!("{}", locale.auth_login_started());
+    auth::login(&config.gmail, false, true).await?;
+    println!("{}", locale.config_line(ConfigLabel::TokenCache, &config.gmail.token_cache_path));
+    
+    if !config.gmail.modifies_labels() {
+        println!("{}", locale.init_labels_skipped());
         return Ok(());
     }
-    
-    // If requested, only check configuration
-    if args.check_config {
-        println!("✅ Configuration valide !");
-        println!("📧 Gmail API OAuth2");
-        println!("🔑 Credentials: {}", config.gmail.credentials_path);
-        println!("💾 Token cache: {}", config.gmail.token_cache_path);
-        println!("📁 Data directory: {}", config.data_dir);
-        if !args.dry_run {
-            println!("🗄️  Database: {}@{}:{}/{}", 
-                     config.database.username, config.database.host, 
-                     config.database.port, config.database.database);
+    let gmail = gmail_client::GmailClient::new(&config.gmail).await?;
+    for source in &config.pipeline.sources {
+        for state in ["todo", "done", "error"] {
+            let name = format!("{}/{}", state, source.key());
+            let created = gmail.ensure_label(&name).await?;
+            println!("{}", locale.init_label(&gmail.label(&name), created));
         }
-        return Ok(());
     }
-    
-    // Override data_dir with CLI argument if provided
-    if args.data_dir != "./data" {
-        config.data_dir = args.data_dir.clone();
+    Ok(())
+}
+
+/// `homemetrics check` output for one profile
+fn print_config(config: &Config) -> Result<()> {
+    let locale = config.locale;
+    print_profile_header(config);
+    println!("📧 Gmail API OAuth2");
+    println!("{}", redact(&locale.config_line(ConfigLabel::Credentials, &config.gmail.credentials_path)));
+    println!("{}", redact(&locale.config_line(ConfigLabel::TokenCache, &config.gmail.token_cache_path)));
+    println!("{}", redact(&locale.config_line(ConfigLabel::Labels, &format!("{}/todo/*", config.gmail.label_prefix))));
+    println!("{}", redact(&locale.config_line(ConfigLabel::DataDir, &config.data_dir)));
+    if let Some(site) = &config.site {
+        println!("{}", redact(&locale.config_line(ConfigLabel::Site, site)));
     }
-    
-    // If daemon mode is enabled
-    if args.daemon {
-        info!("🔄 Starting in daemon mode");
-        run_daemon_mode(config, args).await?;
-        return Ok(());
+    println!("{}", redact(&locale.config_line(ConfigLabel::Units,
+             &format!("{:?} ({})", config.units, config.units.temperature_symbol()))));
+    println!("{}", redact(&locale.config_line(ConfigLabel::Language, &format!("{:?}", locale))));
+    println!("{}", redact(&locale.config_line(ConfigLabel::Database, &format!("{}@{}:{}/{}",
+             config.database.username, config.database.host,
+             config.database.port, config.database.database))));
+    if config.server.enabled {
+        println!("{}", redact(&locale.config_line(ConfigLabel::HttpServer, &format!("{} (ingest token: {})",
+                 config.server.bind_address,
+                 if config.server.ingest_token.is_some() { "set" } else { "missing" }))));
+    }
+    if let Some(drop_dir) = &config.receiver.drop_dir {
+        println!("{}", redact(&locale.config_line(ConfigLabel::DropDir, drop_dir)));
+    }
+    if let Some(templates_dir) = &config.notification_templates_dir {
+        NotificationTemplates::from_config(config)?;
+        println!("{}", redact(&locale.config_line(ConfigLabel::Templates, templates_dir)));
+    }
+    Ok(())
+}
+
+/// `homemetrics fetch --dry-run-db`: writes go to a SQLite file, Gmail and Slack are left untouched
+async fn run_sandbox(profiles: &[Config], run: &RunArgs, sqlite_path: &Path) -> Result<Vec<RunSummary>> {
+    let mut all = Vec::new();
+    for config in profiles {
+        print_profile_header(config);
+        info!("🧪 Processing X-Sense and Blue Riot emails into SQLite sandbox {}", sqlite_path.display());
+        
+        let (started_at, started) = (Utc::now(), Instant::now());
+        let result = pipeline::process_all(config, PipelineTarget::Sandbox(sqlite_path), run.run_limits()).await;
+        write_run_report(report_path(run.report.as_deref(), config).as_deref(), RunMode::Sandbox,
+                         started_at, started, &result, config.locale);
+        let summaries = result?;
+        println!("{}", render_summary_table(&summaries, started.elapsed(), config.locale));
+        all.extend(summaries);
     }
+    println!("{}", profiles[0].locale.sandbox_written(&sqlite_path.display().to_string()));
+    Ok(all)
+}
+
+/// `homemetrics fetch --dry-run --output json`: the run report on stdout instead of text
+async fn run_dry_run_json(profiles: &[Config], run: &RunArgs) -> Result<Vec<RunSummary>> {
+    let [config] = profiles else {
+        anyhow::bail!("--output json needs a single profile (use --profile)");
+    };
     
-    // One-shot mode (default behavior)
-    info!("🚀 Processing both X-Sense and Blue Riot emails in parallel");
+    let (started_at, started) = (Utc::now(), Instant::now());
+    let result = process_all_emails(config, Some(DryRunDetail::Json), run.run_limits()).await;
+    write_run_report(report_path(run.report.as_deref(), config).as_deref(), RunMode::DryRun,
+                     started_at, started, &result, config.locale);
     
-    let result = if args.dry_run {
-        // Dry-run mode: no database connection
-        let xsense_processor = XSenseEmailProcessor::new_dry_run(config.clone())?;
-        let pool_processor = BlueRiotEmailProcessor::new(&config, true).await?;
-        
-        // Process both types in parallel
-        let (xsense_result, pool_result) = tokio::join!(
-            xsense_processor.process_emails_dry_run(args.limit),
-            pool_processor.process_emails(args.limit)
-        );
-        
-        let xsense_count = xsense_result?;
-        pool_result?; // Just check for errors
-        
-        Ok(xsense_count)
-    } else {
-        // Production mode: with database
-        let xsense_processor = XSenseEmailProcessor::new(config.clone()).await?;
-        let pool_processor = BlueRiotEmailProcessor::new(&config, false).await?;
-        
-        // Process both types in parallel
-        let (xsense_result, pool_result) = tokio::join!(
-            xsense_processor.process_emails(args.limit),
-            pool_processor.process_emails(args.limit)
-        );
+    let report = RunReport::new(RunMode::DryRun, started_at, started.elapsed(), &result);
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    result
+}
+
+/// `homemetrics fetch`: profiles run one after the other, a failing profile does not stop the others
+async fn run_once(profiles: &[Config], run: &RunArgs) -> Result<Vec<RunSummary>> {
+    let mut all = Vec::new();
+    let mut failed_profiles = 0;
+    for config in profiles {
+        print_profile_header(config);
+        info!("🚀 Processing the email sources ({:?}, {:?})", config.pipeline.sources, config.pipeline.mode);
         
-        let xsense_count = xsense_result?;
-        pool_result?; // Just check for errors
+        let (started_at, started) = (Utc::now(), Instant::now());
+        let result = process_all_emails(config, run.dry_run(), run.run_limits()).await;
+        write_run_report(report_path(run.report.as_deref(), config).as_deref(), run_mode(run.dry_run),
+                         started_at, started, &result, config.locale);
         
-        Ok(xsense_count)
-    };
+        match result {
+            Ok(summaries) => {
+                println!("{}", render_summary_table(&summaries, started.elapsed(), config.locale));
+                let count: usize = summaries.iter().map(|s| s.processed_count()).sum();
+                if run.dry_run {
+                    info!("✅ Dry-run analysis completed successfully. {} emails analyzed.", count);
+                } else {
+                    info!("✅ Processing completed successfully. {} emails processed.", count);
+                }
+                all.extend(summaries);
+            }
+            Err(e) if profiles.len() == 1 => {
+                error!("❌ Error processing emails: {}", e);
+                return Err(e);
+            }
+            Err(e) => {
+                error!("❌ Error processing emails for profile {}: {}", profile_name(config), e);
+                failed_profiles += 1;
+            }
+        }
+    }
+    
+    if failed_profiles > 0 {
+        anyhow::bail!("{} of {} profiles failed", failed_profiles, profiles.len());
+    }
+    Ok(all)
+}
+
+/// `homemetrics query dead-letters|api-usage|notifications` for one profile
+async fn run_query(action: &QueryAction, config: &Config) -> Result<()> {
+    let locale = config.locale;
+    let database = Database::new(&config.database).await?.with_site(config.site.clone());
+    match action {
+        QueryAction::DeadLetters => {
+            let dead_letters = database.dead_letters().await?;
+            if dead_letters.is_empty() {
+                println!("{}", locale.no_dead_letters());
+                return Ok(());
+            }
+            println!("{}", locale.dead_letters_header(dead_letters.len()));
+            for retry in &dead_letters {
+                let since = retry.dead_lettered_at.unwrap_or(retry.next_attempt_at).format("%Y-%m-%d %H:%M UTC").to_string();
+                println!("{}", locale.dead_letter_line(&retry.source, &retry.email_id, retry.attempts, &since, &retry.last_error));
+            }
+        }
+        QueryAction::ApiUsage { days } => {
+            let days = (*days).max(1);
+            let usage = database.api_usage(days).await?;
+            if usage.is_empty() {
+                println!("{}", locale.no_api_usage());
+                return Ok(());
+            }
+            println!("{}", locale.api_usage_header(days));
+            for day in &usage {
+                println!("{}", locale.api_usage_line(&day.day.to_string(), &day.source, day.calls, day.runs));
+            }
+            let today = Utc::now().date_naive();
+            let today_calls: u64 = usage.iter().filter(|day| day.day == today).map(|day| day.calls).sum();
+            println!("{}", locale.api_usage_today(today_calls, config.gmail.daily_call_limit));
+        }
+        QueryAction::Notifications { failed, limit } => {
+            let notifications = database.notifications(*failed, *limit).await?;
+            if notifications.is_empty() {
+                println!("{}", locale.no_notifications());
+                return Ok(());
+            }
+            println!("{}", locale.notifications_header(notifications.len()));
+            for notification in &notifications {
+                println!("{}", locale.notification_line(
+                    &notification.created_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+                    notification.status == DeliveryStatus::Sent,
+                    &notification.channel,
+                    &notification.kind,
+                    notification.related_id.as_deref(),
+                    notification.error.as_deref(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `homemetrics auth login|status|revoke` for one profile
+async fn run_auth(action: &AuthAction, config: &Config) -> Result<()> {
+    let locale = config.locale;
+    let cache_path = Path::new(&config.gmail.token_cache_path);
     
-    match result {
-        Ok(count) => {
-            if args.dry_run {
-                info!("✅ Dry-run analysis completed successfully. {} emails analyzed.", count);
+    let tokens = match action {
+        AuthAction::Login { force, device } => {
+            println!("{}", locale.auth_login_started());
+            auth::login(&config.gmail, *force, *device).await?
+        }
+        AuthAction::Status => auth::read_token_cache(cache_path)?,
+        AuthAction::Refresh => {
+            println!("{}\n", locale.refreshing_token());
+            let gmail = gmail_client::GmailClient::new(&config.gmail).await?;
+            gmail.refresh_token().await?;
+            println!("{}", locale.token_refreshed());
+            auth::read_token_cache(cache_path)?
+        }
+        AuthAction::Revoke => {
+            if auth::revoke(&config.gmail).await? {
+                println!("{}", locale.auth_revoked(&config.gmail.token_cache_path));
             } else {
-                info!("✅ Processing completed successfully. {} emails processed.", count);
+                println!("{}", locale.auth_no_token(&config.gmail.token_cache_path));
+            }
+            return Ok(());
+        }
+    };
+    
+    if tokens.is_empty() {
+        println!("{}", locale.auth_no_token(&config.gmail.token_cache_path));
+        return Ok(());
+    }
+    println!("{}", locale.config_line(ConfigLabel::TokenCache, &config.gmail.token_cache_path));
+    let now = Utc::now();
+    for token in &tokens {
+        println!("{}", locale.auth_scopes(&token.scopes.join(", ")));
+        let expiry = token.expires_at.map(|expires_at| expires_at.format("%Y-%m-%d %H:%M UTC").to_string());
+        println!("{}", locale.auth_expiry(expiry.as_deref(), token.is_expired(now), token.refresh_token.is_some()));
+    }
+    Ok(())
+}
+
+async fn run_gmail(action: &GmailAction, config: &Config) -> Result<()> {
+    let locale = config.locale;
+    match action {
+        GmailAction::SetupFilters => {
+            if !config.gmail.modifies_labels() {
+                anyhow::bail!("Filters cannot be set up with GMAIL_SCOPE=readonly or --no-label-changes");
+            }
+            
+            let gmail = gmail_client::GmailClient::new(&config.gmail).await?;
+            let sources = [
+                ("xsense", "XSENSE", &config.xsense),
+                ("blueriot", "BLUERIOT", &config.blueriot),
+            ];
+            for (source, prefix, source_config) in sources {
+                let label = gmail.label(&format!("todo/{}", source));
+                match &source_config.sender {
+                    Some(sender) => match gmail.setup_filter(source, sender).await? {
+                        FilterSetup::Created => println!("{}", locale.filter_created(sender, &label)),
+                        FilterSetup::AlreadyExists => println!("{}", locale.filter_exists(sender, &label)),
+                    },
+                    None => println!("{}", locale.filter_no_sender(prefix)),
+                }
             }
         }
+    }
+    Ok(())
+}
+
+/// Print the profile name before its output when PROFILES is used
+fn print_profile_header(config: &Config) {
+    if let Some(profile) = &config.profile {
+        println!("{}", config.locale.profile_header(profile));
+    }
+}
+
+/// Slack notifier of a profile, its messages recorded in `notifications` when the database answers
+async fn recording_notifier(config: &Config) -> Option<SlackNotifier> {
+    let notifier = SlackNotifier::from_config(config.slack.as_ref())?;
+    match Database::new(&config.database).await {
+        Ok(database) => Some(notifier.with_log(Box::new(database.with_site(config.site.clone())))),
         Err(e) => {
-            error!("❌ Error processing emails: {}", e);
-            return Err(e);
+            warn!("⚠️  Database unreachable, the Slack message will not be recorded: {:#}", e);
+            Some(notifier)
         }
     }
+}
+
+fn profile_name(config: &Config) -> &str {
+    config.profile.as_deref().unwrap_or("default")
+}
+
+/// Report path of a profile: `last-run.md` becomes `last-run-<profile>.md`
+fn report_path(path: Option<&Path>, config: &Config) -> Option<PathBuf> {
+    let path = path?;
+    let Some(profile) = &config.profile else {
+        return Some(path.to_path_buf());
+    };
     
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, profile, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, profile),
+    };
+    Some(path.with_file_name(file_name))
+}
+
+/// Run the HTTP server, plus the drop directory watcher when configured
+async fn run_listeners(config: &Config) -> Result<()> {
+    if config.receiver.drop_dir.is_some() {
+        tokio::try_join!(
+            server::run_server(config),
+            receiver::run_drop_dir_watcher(config)
+        )?;
+    } else {
+        server::run_server(config).await?;
+    }
     Ok(())
 }
 
-async fn run_daemon_mode(config: Config, args: Args) -> Result<()> {
-    use tokio_cron_scheduler::{JobScheduler, Job};
+/// Process the enabled email sources with one Gmail connection, one summary per source
+async fn process_all_emails(config: &Config, dry_run: Option<DryRunDetail>, limits: RunLimits) -> Result<Vec<RunSummary>> {
+    let target = match dry_run {
+        // Dry-run mode: database only read to compare with stored readings
+        Some(detail) => PipelineTarget::DryRun(detail),
+        None => PipelineTarget::Database,
+    };
+    pipeline::process_all(config, target, limits).await
+}
+
+/// Scheduled run of a profile, retried within its slot while the error is transient
+///
+/// Network, quota and database outages get `SCHEDULER_RETRY_ATTEMPTS` more
+/// attempts, `SCHEDULER_RETRY_DELAY_SECONDS` apart then doubling. Auth and
+/// permanent errors are returned at once: the next slot tries again.
+async fn run_with_retries(config: &Config, dry_run: Option<DryRunDetail>, limits: RunLimits) -> Result<Vec<RunSummary>> {
+    let mut delay = std::time::Duration::from_secs(config.scheduler.retry_delay_seconds);
+    let mut retries = 0;
+    loop {
+        let result = process_all_emails(config, dry_run, limits).await;
+        match result {
+            Err(e) if classify(&e) == ErrorClass::Transient && retries < config.scheduler.retry_attempts => {
+                retries += 1;
+                warn!("⚠️  Scheduled processing failed with a transient error (profile {}), retry {}/{} in {}: {}",
+                      profile_name(config), retries, config.scheduler.retry_attempts, humantime::format_duration(delay), e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Daily forecast digest of a profile, read from its database
+async fn forecast_digest(config: &Config) -> Result<String> {
+    let database = Database::connect_read_only(&config.database).await?.with_site(config.site.clone());
+    let forecast = forecast::forecast(&database, Utc::now(), &config.forecast).await?;
+    Ok(forecast::render_forecast(&forecast, config.locale, config.units))
+}
+
+/// Cron expression of a scheduled run: a SCHEDULER_CRON expression, or a daily time
+fn schedule_cron(time: &str) -> Option<String> {
+    if time.contains(char::is_whitespace) {
+        return config::normalize_cron(time).ok();
+    }
+    daily_cron(time)
+}
+
+/// Cron expression running every day at a "HH:MM" time, or every hour at "*:MM"
+fn daily_cron(time: &str) -> Option<String> {
+    let (hour, minute) = time.split_once(':').filter(|(_, minute)| !minute.contains(':'))?;
+    // Cron format: "0 minute hour * * *" (every day, every hour with hour "*")
+    Some(format!("0 {} {} * * *", minute, hour))
+}
+
+fn run_mode(dry_run: bool) -> RunMode {
+    if dry_run { RunMode::DryRun } else { RunMode::Normal }
+}
+
+/// Write the --report file when requested (a failure is logged, never fatal)
+fn write_run_report(path: Option<&Path>, mode: RunMode, started_at: DateTime<Utc>, started: Instant,
+                    result: &Result<Vec<RunSummary>>, locale: Locale) {
+    let Some(path) = path else {
+        return;
+    };
+    
+    let report = RunReport::new(mode, started_at, started.elapsed(), result);
+    if let Err(e) = report.write(path, locale) {
+        error!("❌ Unable to write run report: {:#}", e);
+    }
+}
+
+/// Wait after a wake-up before looking for missed runs, so the jobs the scheduler fires late start first
+const CATCH_UP_GRACE: Duration = Duration::from_secs(90);
+
+/// Profile, source and start of a run
+type RunStarted = (Option<String>, EmailSource, DateTime<Utc>);
+
+/// Start of the last scheduled or catch-up run of each profile and source in this process
+///
+/// Runs are only recorded in the database once they end.
+static RUNS_STARTED: std::sync::Mutex<Vec<RunStarted>> = std::sync::Mutex::new(Vec::new());
+
+fn mark_runs_started(config: &Config, at: DateTime<Utc>) {
+    let mut started = RUNS_STARTED.lock().unwrap_or_else(|e| e.into_inner());
+    started.retain(|(profile, source, _)| *profile != config.profile || !config.pipeline.sources.contains(source));
+    started.extend(config.pipeline.sources.iter().map(|source| (config.profile.clone(), *source, at)));
+}
+
+/// Run at once the sources of a profile whose last scheduled time passed while the
+/// daemon was down or asleep (SCHEDULER_CATCH_UP), after `delay`
+///
+/// Last runs are read from the database, so a daemon restarted after a missed
+/// time catches up too. Dry runs are not recorded there and never catch up.
+fn spawn_catch_up(config: &Config, run: &RunArgs, delay: Duration) {
+    if !config.scheduler.catch_up || run.dry_run {
+        return;
+    }
+    let mut config = config.clone();
+    let limits = run.run_limits();
+    let report = report_path(run.report.as_deref(), &config);
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let profile = profile_name(&config).to_string();
+        let database = match Database::connect_read_only(&config.database).await {
+            Ok(database) => database.with_site(config.site.clone()),
+            Err(e) => {
+                warn!("⚠️  Missed runs not checked (profile {}): {:#}", profile, e);
+                return;
+            }
+        };
+        let mut last_runs: Vec<(EmailSource, DateTime<Utc>)> = match database.source_activities().await {
+            Ok(activities) => activities.iter()
+                .filter_map(|activity| Some((EmailSource::parse(&activity.source)?, activity.last_run_at)))
+                .collect(),
+            Err(e) => {
+                warn!("⚠️  Missed runs not checked (profile {}): {:#}", profile, e);
+                return;
+            }
+        };
+        last_runs.extend(RUNS_STARTED.lock().unwrap_or_else(|e| e.into_inner()).iter()
+            .filter(|(candidate, ..)| *candidate == config.profile)
+            .map(|(_, source, at)| (*source, *at)));
+        
+        let now = Utc::now();
+        let missed = scheduler::missed_runs(&config.scheduler, &config.pipeline.sources, &last_runs, now);
+        if missed.is_empty() {
+            return;
+        }
+        for (source, scheduled) in &missed {
+            info!("⏪ Scheduled run of {} at {} missed (profile {}) - catching up now",
+                  source.key(), scheduled.format("%Y-%m-%d %H:%M UTC"), profile);
+        }
+        config.pipeline.sources = missed.iter().map(|(source, _)| *source).collect();
+        mark_runs_started(&config, now);
+        
+        let (started_at, started) = (now, Instant::now());
+        let result = run_with_retries(&config, None, limits).await;
+        write_run_report(report.as_deref(), RunMode::Normal, started_at, started, &result, config.locale);
+        control::record_run(&config, "catch-up", Utc::now(), started.elapsed(), &result);
+        match result {
+            Ok(summaries) => {
+                print_profile_header(&config);
+                println!("{}", render_summary_table(&summaries, started.elapsed(), config.locale));
+                let count: usize = summaries.iter().map(|s| s.processed_count()).sum();
+                info!("✅ Catch-up processing completed. {} emails processed.", count);
+            }
+            Err(e) => error!("❌ Error during catch-up processing (profile {}): {}", profile, e),
+        }
+    });
+}
+
+async fn run_daemon_mode(profiles: Vec<Config>, args: DaemonArgs, overrides: Overrides) -> Result<()> {
+    use tokio_cron_scheduler::JobScheduler;
     use chrono::{Local, Timelike};
-    use std::sync::Arc;
-    use tokio::sync::Mutex;
+    use std::collections::HashSet;
+    
+    // Check every profile before starting anything
+    for config in &profiles {
+        check_daemon_config(config)?;
+        
+        // Scheduled runs would all fail on an outdated schema; an unreachable database may come back
+        if let Err(e) = Database::connect_read_only(&config.database).await {
+            if e.is::<SchemaMismatch>() {
+                error!("❌ {} (profile {})", e, profile_name(config));
+                return Err(e);
+            }
+            warn!("⚠️  Database schema not checked (profile {}): {}", profile_name(config), e);
+        }
+    }
+    
+    // Every socket answers for the whole daemon, so `status --profile` finds it through that profile's
+    control::set_profiles(&profiles);
+    let sockets: HashSet<String> = profiles.iter().filter_map(|config| config.scheduler.control_socket.clone()).collect();
+    for path in sockets {
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(Path::new(&path)).await {
+                error!("❌ Control socket stopped: {:#}", e);
+            }
+        });
+    }
+    
+    // Create the scheduler shared by all profiles
+    let scheduler = JobScheduler::new().await?;
+    
+    // Scheduled jobs of each profile, replaced when the configuration is reloaded
+    let mut running = Vec::new();
+    
+    // Listeners inherited unchanged by several profiles are only started once
+    let mut bind_addresses = HashSet::new();
+    let mut drop_dirs = HashSet::new();
+    
+    for config in &profiles {
+        if args.run.dry_run && (config.server.enabled || config.receiver.drop_dir.is_some()) {
+            warn!("⚠️  HTTP server and drop directory watcher not started in dry-run mode");
+        } else {
+            if config.server.enabled {
+                if bind_addresses.insert(config.server.bind_address.clone()) {
+                    let server_config = config.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = server::run_server(&server_config).await {
+                            error!("❌ HTTP server stopped: {}", e);
+                        }
+                    });
+                } else {
+                    warn!("⚠️  HTTP server {} already started by another profile, not started for profile {}",
+                          config.server.bind_address, profile_name(config));
+                }
+            }
+            
+            if let Some(drop_dir) = &config.receiver.drop_dir {
+                if drop_dirs.insert(drop_dir.clone()) {
+                    let receiver_config = config.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = receiver::run_drop_dir_watcher(&receiver_config).await {
+                            error!("❌ Drop directory watcher stopped: {}", e);
+                        }
+                    });
+                } else {
+                    warn!("⚠️  Drop directory {} already watched by another profile, not watched for profile {}",
+                          drop_dir, profile_name(config));
+                }
+            }
+        }
+        
+        let jobs = start_profile(config, &args, &scheduler).await?;
+        running.push((config.clone(), jobs));
+    }
+    
+    // Start the scheduler
+    scheduler.start().await?;
+    
+    info!("✅ Daemon mode started. Waiting for scheduled times...");
+    info!("⏸️  Press Ctrl+C to stop the daemon, send SIGHUP to reload the configuration");
     
+    // Keep the program alive
+    let mut reloads = reload_requests(args.watch_interval)?;
+    let mut last_tick = Utc::now();
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(60)) => {
+                // Periodic log to show the daemon is active
+                let now = Local::now();
+                if now.minute() == 0 {
+                    info!("💓 Daemon active - {}", now.format("%Y-%m-%d %H:%M"));
+                }
+                
+                // The timer stops while the machine sleeps, the wall clock does not
+                let asleep = now.to_utc() - last_tick;
+                last_tick = now.to_utc();
+                if asleep > chrono::TimeDelta::minutes(3) {
+                    info!("💤 Daemon resumed after {} without running", humantime::format_duration(asleep.to_std().unwrap_or_default()));
+                    for (config, _) in &running {
+                        spawn_catch_up(config, &args.run, CATCH_UP_GRACE);
+                    }
+                }
+            }
+            Some(()) = reloads.recv() => reload_profiles(&mut running, &args, &overrides, &scheduler).await,
+        }
+    }
+}
+
+/// Settings the daemon cannot run without
+fn check_daemon_config(config: &Config) -> Result<()> {
     // Check that the scheduler is enabled in configuration
     if !config.scheduler.enabled {
-        error!("❌ Daemon mode requires SCHEDULER_ENABLED=true in configuration");
+        error!("❌ Daemon mode requires SCHEDULER_ENABLED=true in configuration (profile {})", profile_name(config));
         anyhow::bail!("Scheduler not enabled in configuration");
     }
     
-    if config.scheduler.schedule_times.is_empty() {
-        error!("❌ No scheduling times defined (SCHEDULER_TIMES)");
+    if config.scheduler.runs(&config.pipeline.sources).is_empty() {
+        error!("❌ No scheduling times defined (SCHEDULER_TIMES or SCHEDULER_CRON, profile {})", profile_name(config));
         anyhow::bail!("No scheduling times defined");
     }
+    Ok(())
+}
+
+/// Configuration reload requests: SIGHUP on Unix, and changes to `.env` or the
+/// configuration file, checked every `watch_interval` seconds (0: never)
+fn reload_requests(watch_interval: u64) -> Result<tokio::sync::mpsc::Receiver<()>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(1);
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        let mut hangup = signal(SignalKind::hangup())?;
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                // A reload already pending covers this one
+                let _ = sender.try_send(());
+            }
+        });
+    }
+    if watch_interval > 0 {
+        let files = config::watched_files();
+        info!("👀 Watching {} for configuration changes", files.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "));
+        tokio::spawn(async move {
+            let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+            let mut known: Vec<_> = files.iter().map(modified).collect();
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(watch_interval));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let current: Vec<_> = files.iter().map(modified).collect();
+                if current != known {
+                    known = current;
+                    info!("📝 Configuration file changed");
+                    let _ = sender.try_send(());
+                }
+            }
+        });
+    }
+    Ok(receiver)
+}
+
+/// Reload the configuration and replace the scheduled jobs of every profile
+///
+/// Schedules, alert rules, notifiers and every setting read per run take
+/// effect; Gmail clients, token refresh, HTTP server and drop directory
+/// watcher keep running as started. An invalid configuration is logged and
+/// the daemon keeps the previous one.
+async fn reload_profiles(running: &mut [(Config, Vec<uuid::Uuid>)], args: &DaemonArgs, overrides: &Overrides, scheduler: &tokio_cron_scheduler::JobScheduler) {
+    info!("🔃 Reloading configuration...");
+    let mut profiles = match Config::reload_profiles() {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            error!("❌ Configuration not reloaded, keeping the previous one: {:#}", e);
+            return;
+        }
+    };
+    overrides.apply(&mut profiles);
+    if profiles.iter().any(|config| check_daemon_config(config).is_err()) {
+        error!("❌ Configuration not reloaded, keeping the previous one");
+        return;
+    }
+    
+    for config in &profiles {
+        if !running.iter().any(|(running, _)| running.profile == config.profile) {
+            warn!("⚠️  Profile {} added to PROFILES: restart the daemon to start it", profile_name(config));
+        }
+    }
+    for (current, jobs) in running.iter_mut() {
+        let Some(config) = profiles.iter().find(|config| config.profile == current.profile) else {
+            warn!("⚠️  Profile {} removed from PROFILES: restart the daemon to stop it", profile_name(current));
+            continue;
+        };
+        
+        for job in jobs.drain(..) {
+            if let Err(e) = scheduler.remove(&job).await {
+                warn!("Unable to remove scheduled job {}: {}", job, e);
+            }
+        }
+        match schedule_profile(config, args, scheduler).await {
+            Ok(scheduled) => *jobs = scheduled,
+            Err(e) => error!("❌ Unable to schedule profile {}: {}", profile_name(config), e),
+        }
+        *current = config.clone();
+    }
+    control::set_profiles(&running.iter().map(|(config, _)| config.clone()).collect::<Vec<_>>());
+    info!("✅ Configuration reloaded");
+}
+
+/// Start one profile in daemon mode: token refresh, initial run (unless disabled) and scheduled jobs
+///
+/// Each profile has its own Gmail client, schedule and run reports, so an
+/// error in one profile never affects the others. Returns the scheduled jobs.
+async fn start_profile(config: &Config, args: &DaemonArgs, scheduler: &tokio_cron_scheduler::JobScheduler) -> Result<Vec<uuid::Uuid>> {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
     
-    info!("📅 Configured retrieval times: {:?}", config.scheduler.schedule_times);
+    let profile = profile_name(config).to_string();
+    for source in &config.pipeline.sources {
+        info!("📅 Configured retrieval times for profile {}, source {}: {:?}", profile, source.key(), config.scheduler.times_for(*source));
+    }
     
     // Create a shared GmailClient for token refresh management
     // This client will be used by the token refresh manager to keep tokens alive
@@ -214,34 +1999,24 @@ async fn run_daemon_mode(config: Config, args: Args) -> Result<()> {
     
     info!("✅ Token refresh manager started");
     
-    // First, process emails immediately at startup
-    info!("🚀 Daemon starting - processing emails immediately...");
-    let initial_result = if args.dry_run {
-        let xsense_processor = XSenseEmailProcessor::new_dry_run(config.clone())?;
-        let pool_processor = BlueRiotEmailProcessor::new(&config, true).await?;
-        
-        let (xsense_result, pool_result) = tokio::join!(
-            xsense_processor.process_emails_dry_run(args.limit),
-            pool_processor.process_emails(args.limit)
-        );
-        
-        let _ = pool_result?; // Check for errors
-        xsense_result
-    } else {
-        let xsense_processor = XSenseEmailProcessor::new(config.clone()).await?;
-        let pool_processor = BlueRiotEmailProcessor::new(&config, false).await?;
-        
-        let (xsense_result, pool_result) = tokio::join!(
-            xsense_processor.process_emails(args.limit),
-            pool_processor.process_emails(args.limit)
-        );
-        
-        let _ = pool_result?; // Check for errors
-        xsense_result
-    };
+    // First, process emails immediately at startup, unless told to wait for the schedule
+    if !args.run_on_start.unwrap_or(config.scheduler.run_on_start) {
+        info!("⏭️  Initial processing skipped for profile {}, waiting for the first scheduled time", profile);
+        spawn_catch_up(config, &args.run, Duration::ZERO);
+        return schedule_profile(config, args, scheduler).await;
+    }
+    info!("🚀 Daemon starting - processing emails immediately for profile {}...", profile);
+    let report = report_path(args.run.report.as_deref(), config);
+    let (started_at, started) = (Utc::now(), Instant::now());
+    let initial_result = process_all_emails(config, args.run.dry_run(), args.run.run_limits()).await;
+    write_run_report(report.as_deref(), run_mode(args.run.dry_run), started_at, started, &initial_result, config.locale);
+    control::record_run(config, "start", Utc::now(), started.elapsed(), &initial_result);
     
     match initial_result {
-        Ok(count) => {
+        Ok(summaries) => {
+            print_profile_header(config);
+            println!("{}", render_summary_table(&summaries, started.elapsed(), config.locale));
+            let count: usize = summaries.iter().map(|s| s.processed_count()).sum();
             info!("✅ Initial processing completed. {} emails processed.", count);
         }
         Err(e) => {
@@ -250,116 +2025,151 @@ async fn run_daemon_mode(config: Config, args: Args) -> Result<()> {
         }
     }
     
-    // Create the scheduler
-    let scheduler = JobScheduler::new().await?;
+    schedule_profile(config, args, scheduler).await
+}
+
+/// Add the scheduled jobs of a profile: email retrieval times and forecast digest
+async fn schedule_profile(config: &Config, args: &DaemonArgs, scheduler: &tokio_cron_scheduler::JobScheduler) -> Result<Vec<uuid::Uuid>> {
+    use tokio_cron_scheduler::Job;
+    
+    let profile = profile_name(config).to_string();
+    let report = report_path(args.run.report.as_deref(), config);
+    let mut jobs = Vec::new();
     
-    // Add a job for each configured time
-    for schedule_time in &config.scheduler.schedule_times {
-        let parts: Vec<&str> = schedule_time.split(':').collect();
-        if parts.len() != 2 {
-            error!("❌ Invalid time format: {}. Use HH:MM format", schedule_time);
+    // Add a job for each configured time, running the sources scheduled then
+    for (schedule_time, sources) in config.scheduler.runs(&config.pipeline.sources) {
+        let Some(cron_expr) = schedule_cron(&schedule_time) else {
+            error!("❌ Invalid time format: {}. Use HH:MM or *:MM format, or SCHEDULER_CRON", schedule_time);
             continue;
-        }
-        
-        let hour = parts[0];
-        let minute = parts[1];
-        
-        // Cron format: "0 minute hour * * *" (every day)
-        let cron_expr = format!("0 {} {} * * *", minute, hour);
-        info!("📆 Adding scheduled job: {} (cron: {})", schedule_time, cron_expr);
+        };
+        let keys: Vec<&str> = sources.iter().map(EmailSource::key).collect();
+        info!("📆 Adding scheduled job: {} (cron: {}, profile {}, sources {})", schedule_time, cron_expr, profile, keys.join(", "));
         
         // Clone variables needed for the closure
-        let config_clone = config.clone();
-        let dry_run = args.dry_run;
-        let limit = args.limit;
+        let mut config_clone = config.clone();
+        config_clone.pipeline.sources = sources;
+        let dry_run = args.run.dry_run();
+        let limits = args.run.run_limits();
+        let report_path = report.clone();
         let schedule_time_clone = schedule_time.clone();
+        let profile_clone = profile.clone();
         
         let job = Job::new_async(cron_expr.as_str(), move |_uuid, _l| {
             let config = config_clone.clone();
             let schedule_time = schedule_time_clone.clone();
+            let report_path = report_path.clone();
+            let profile = profile_clone.clone();
             
             Box::pin(async move {
-                info!("⏰ Scheduled execution at {} - Retrieving emails...", schedule_time);
+                mark_runs_started(&config, Utc::now());
+                if let Some(jitter) = config.scheduler.jitter {
+                    let delay = Duration::from_millis(fastrand::u64(0..=jitter.as_millis() as u64));
+                    info!("🎲 Scheduled execution at {} for profile {} delayed by {} (SCHEDULER_JITTER)",
+                          schedule_time, profile, humantime::format_duration(delay));
+                    tokio::time::sleep(delay).await;
+                }
+                info!("⏰ Scheduled execution at {} for profile {} - Retrieving emails...", schedule_time, profile);
                 
-                let result = if dry_run {
-                    let xsense_processor = match XSenseEmailProcessor::new_dry_run(config.clone()) {
-                        Ok(p) => p,
-                        Err(e) => {
-                            error!("❌ Error creating X-Sense processor: {}", e);
-                            return;
-                        }
-                    };
-                    
-                    let pool_processor = match BlueRiotEmailProcessor::new(&config, true).await {
-                        Ok(p) => p,
-                        Err(e) => {
-                            error!("❌ Error creating pool processor: {}", e);
-                            return;
-                        }
-                    };
-                    
-                    let (xsense_result, pool_result) = tokio::join!(
-                        xsense_processor.process_emails_dry_run(limit),
-                        pool_processor.process_emails(limit)
-                    );
-                    
-                    let _ = pool_result; // Ignore pool result for count
-                    xsense_result
-                } else {
-                    let xsense_processor = match XSenseEmailProcessor::new(config.clone()).await {
-                        Ok(p) => p,
-                        Err(e) => {
-                            error!("❌ Error creating X-Sense processor: {}", e);
-                            return;
-                        }
-                    };
-                    
-                    let pool_processor = match BlueRiotEmailProcessor::new(&config, false).await {
-                        Ok(p) => p,
-                        Err(e) => {
-                            error!("❌ Error creating pool processor: {}", e);
-                            return;
-                        }
-                    };
-                    
-                    let (xsense_result, pool_result) = tokio::join!(
-                        xsense_processor.process_emails(limit),
-                        pool_processor.process_emails(limit)
-                    );
-                    
-                    let _ = pool_result; // Ignore pool result for count
-                    xsense_result
-                };
+                let (started_at, started) = (Utc::now(), Instant::now());
+                let result = run_with_retries(&config, dry_run, limits).await;
+                write_run_report(report_path.as_deref(), run_mode(dry_run.is_some()), started_at, started, &result, config.locale);
+                control::record_run(&config, "scheduled", Utc::now(), started.elapsed(), &result);
                 
                 match result {
-                    Ok(count) => {
+                    Ok(summaries) => {
+                        print_profile_header(&config);
+                        println!("{}", render_summary_table(&summaries, started.elapsed(), config.locale));
+                        let count: usize = summaries.iter().map(|s| s.processed_count()).sum();
                         info!("✅ Scheduled processing completed. {} emails processed at {}", count, schedule_time);
                     }
                     Err(e) => {
-                        error!("❌ Error during scheduled processing at {}: {}", schedule_time, e);
+                        let class = classify(&e);
+                        error!("❌ Error during scheduled processing at {} (profile {}, {} error): {}", schedule_time, profile, class.key(), e);
+                        // Transient errors already went through the retries and are logged only
+                        if !class.is_retryable() {
+                            if let Some(notifier) = recording_notifier(&config).await {
+                                let message = config.locale.notify_scheduled_run_failed(&profile, &schedule_time, class.key(), &format!("{:#}", e));
+                                if let Err(e) = notifier.send_message(NotificationKind::RunFailed, &message).await {
+                                    error!("❌ Unable to post the scheduled run failure: {}", e);
+                                }
+                            }
+                        }
                     }
                 }
             })
         })?;
         
-        scheduler.add(job).await?;
+        jobs.push(scheduler.add(job).await?);
     }
     
-    // Start the scheduler
-    scheduler.start().await?;
+    // Redelivery of the Slack messages that failed, every minute
+    if let Some(slack_config) = config.slack.clone().filter(|_| !args.run.dry_run) {
+        let config_clone = config.clone();
+        // Built on the first pass that reaches the database, then kept
+        let notifier = std::sync::Arc::new(tokio::sync::OnceCell::new());
+        let job = Job::new_async("0 * * * * *", move |_uuid, _l| {
+            let config = config_clone.clone();
+            let slack_config = slack_config.clone();
+            let notifier = notifier.clone();
+            Box::pin(async move {
+                let notifier = notifier.get_or_try_init(|| async {
+                    let database = Database::new(&config.database).await?.with_site(config.site.clone());
+                    anyhow::Ok(SlackNotifier::new(&slack_config)?.with_log(Box::new(database)))
+                }).await;
+                match notifier {
+                    Ok(notifier) => {
+                        notifier.retry_failed().await;
+                    }
+                    Err(e) => debug!("Slack redelivery skipped (profile {}): {:#}", profile_name(&config), e),
+                }
+            })
+        })?;
+        jobs.push(scheduler.add(job).await?);
+    }
     
-    info!("✅ Daemon mode started. Waiting for scheduled times...");
-    info!("📋 Next executions: {:?}", config.scheduler.schedule_times);
-    info!("⏸️  Press Ctrl+C to stop the daemon");
+    // Forecast digest, posted to Slack once a day
+    if let Some(forecast_time) = &config.forecast.time {
+        match daily_cron(forecast_time) {
+            Some(cron_expr) => {
+                info!("🔮 Adding forecast digest: {} (cron: {}, profile {})", forecast_time, cron_expr, profile);
+                let config_clone = config.clone();
+                let job = Job::new_async(cron_expr.as_str(), move |_uuid, _l| {
+                    let config = config_clone.clone();
+                    Box::pin(async move {
+                        let text = match forecast_digest(&config).await {
+                            Ok(text) => text,
+                            Err(e) => {
+                                error!("❌ Forecast failed (profile {}): {}", profile_name(&config), e);
+                                return;
+                            }
+                        };
+                        match recording_notifier(&config).await {
+                            Some(notifier) => {
+                                if let Err(e) = notifier.send_message(NotificationKind::Forecast, &text).await {
+                                    error!("❌ Unable to post the forecast digest: {}", e);
+                                }
+                            }
+                            None => println!("{}", text),
+                        }
+                    })
+                })?;
+                jobs.push(scheduler.add(job).await?);
+            }
+            None => error!("❌ Invalid FORECAST_TIME format: {}. Use HH:MM format", forecast_time),
+        }
+    }
     
-    // Keep the program alive
-    loop {
-        tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-        
-        // Periodic log to show the daemon is active
-        let now = Local::now();
-        if now.minute() == 0 {
-            info!("💓 Daemon active - {}", now.format("%Y-%m-%d %H:%M"));
+    Ok(jobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_subcommand_has_examples() {
+        for command in Args::command().get_subcommands() {
+            assert!(command.get_after_long_help().is_some(), "{} has no examples", command.get_name());
         }
     }
 }
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use log::warn;
+
+use crate::storage::Storage;
+use crate::xsense::TemperatureReading;
+
+/// Walls and corners are assumed this much colder than the room air (°C)
+pub const WALL_OFFSET: f64 = 3.0;
+
+/// Score from which a room is in the risk zone: 80 % relative humidity on the walls
+pub const RISK_THRESHOLD: f64 = 50.0;
+
+/// Magnus formula coefficients (over water, -45 °C to 60 °C)
+const MAGNUS_A: f64 = 17.62;
+const MAGNUS_B: f64 = 243.12;
+
+/// Saturation vapour pressure (hPa) at a temperature
+fn saturation_pressure(temperature: f64) -> f64 {
+    6.112 * (MAGNUS_A * temperature / (MAGNUS_B + temperature)).exp()
+}
+
+/// Dew point (°C) of air at this temperature (°C) and relative humidity (%)
+pub fn dew_point(temperature: f64, humidity: f64) -> f64 {
+    let gamma = (humidity / 100.0).ln() + MAGNUS_A * temperature / (MAGNUS_B + temperature);
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+/// Relative humidity (%) of the room air once cooled down to the wall temperature
+pub fn surface_humidity(temperature: f64, humidity: f64) -> f64 {
+    humidity * saturation_pressure(temperature) / saturation_pressure(temperature - WALL_OFFSET)
+}
+
+/// Mold/condensation risk score of a reading, from 0 to 100
+///
+/// Mold grows from about 80 % relative humidity at the surface (score 50),
+/// water condenses once the wall is below the dew point (score 100). None
+/// without humidity.
+pub fn mold_risk(reading: &TemperatureReading) -> Option<f64> {
+    let humidity = reading.humidity.filter(|humidity| *humidity > 0.0)?;
+    let surface = surface_humidity(reading.temperature, humidity);
+    Some(((surface - 60.0) * 2.5).clamp(0.0, 100.0))
+}
+
+/// Sensor staying in the risk zone for longer than `MOLD_ALERT_AFTER_HOURS`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoldAlert {
+    pub sensor_id: String,
+    /// First reading of the stay in the risk zone
+    pub since: DateTime<Utc>,
+    pub hours: i64,
+}
+
+/// Sensors whose latest readings stayed in the risk zone for at least `after_hours`
+///
+/// Each stay is alerted once: its start is recorded with the alert. Storage
+/// errors are logged, they never fail the processing of the readings.
+pub async fn due_alerts<'a>(storage: &dyn Storage, sensors: impl IntoIterator<Item = &'a String>, after_hours: u32) -> Vec<MoldAlert> {
+    let mut alerts = Vec::new();
+    if after_hours == 0 {
+        return alerts;
+    }
+
+    for sensor_id in sensors {
+        let (since, latest) = match storage.mold_risk_streak(sensor_id).await {
+            Ok(Some(streak)) => streak,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Unable to check the mold risk of {}: {:#}", sensor_id, e);
+                continue;
+            }
+        };
+
+        let hours = (latest - since).num_hours();
+        if hours < i64::from(after_hours) {
+            continue;
+        }
+        match storage.claim_mold_alert(sensor_id, since).await {
+            Ok(true) => alerts.push(MoldAlert { sensor_id: sensor_id.clone(), since, hours }),
+            Ok(false) => {}
+            Err(e) => warn!("Unable to record the mold alert of {}: {:#}", sensor_id, e),
+        }
+    }
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use chrono::TimeZone;
+
+    fn reading(hour: u32, temperature: f64, humidity: Option<f64>) -> TemperatureReading {
+        TemperatureReading {
+            sensor_id: "sdb".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 10, hour, 0, 0).unwrap(),
+            temperature,
+            humidity,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_mold_risk() {
+        assert!((dew_point(20.0, 50.0) - 9.26).abs() < 0.01);
+        assert_eq!(mold_risk(&reading(0, 20.0, None)), None);
+        assert_eq!(mold_risk(&reading(0, 21.0, Some(40.0))), Some(0.0));
+        // Bathroom after a shower: walls below the dew point
+        assert_eq!(mold_risk(&reading(0, 22.0, Some(90.0))), Some(100.0));
+        let risk = mold_risk(&reading(0, 19.0, Some(68.0))).unwrap();
+        assert!(risk > RISK_THRESHOLD && risk < 100.0, "{}", risk);
+    }
+
+    #[tokio::test]
+    async fn test_due_alerts() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let sensors = ["sdb".to_string()];
+        let readings = vec![reading(0, 20.0, Some(45.0)), reading(1, 19.0, Some(80.0)), reading(4, 19.0, Some(82.0))];
+        storage.save_temperature_readings(&readings).await.unwrap();
+
+        assert!(due_alerts(&storage, &sensors, 6).await.is_empty());
+        let alerts = due_alerts(&storage, &sensors, 3).await;
+        assert_eq!(alerts, [MoldAlert { sensor_id: "sdb".to_string(), since: readings[1].timestamp, hours: 3 }]);
+        // Same stay, already alerted
+        storage.save_temperature_readings(&[reading(6, 19.0, Some(85.0))]).await.unwrap();
+        assert!(due_alerts(&storage, &sensors, 3).await.is_empty());
+
+        // Back to normal, then a new stay
+        storage.save_temperature_readings(&[reading(8, 21.0, Some(50.0)), reading(9, 19.0, Some(85.0)),
+                                             reading(13, 19.0, Some(85.0))]).await.unwrap();
+        assert_eq!(due_alerts(&storage, &sensors, 3).await.len(), 1);
+    }
+}
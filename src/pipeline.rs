@@ -0,0 +1,433 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::blueriot::BlueRiotEmailProcessor;
+use crate::config::Config;
+use crate::email::cleanup::cleanup_processed_emails;
+use crate::database::Database;
+use crate::email::{DryRunDetail, RunBudget, RunLimits, RunSummary};
+use crate::storage::BatchCheckpoint;
+use crate::gmail_client::GmailClient;
+use crate::hooks::{self, HookEvent};
+use crate::xsense::XSenseEmailProcessor;
+
+/// Email source run by the pipeline (`PIPELINE_SOURCES`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailSource {
+    XSense,
+    BlueRiot,
+}
+
+impl EmailSource {
+    pub const ALL: [EmailSource; 2] = [EmailSource::XSense, EmailSource::BlueRiot];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "xsense" | "x-sense" => Some(EmailSource::XSense),
+            "blueriot" | "blue-riot" => Some(EmailSource::BlueRiot),
+            _ => None,
+        }
+    }
+
+    /// Comma-separated sources, None when one is unknown or the list is empty
+    pub fn parse_list(value: &str) -> Option<Vec<Self>> {
+        let mut sources = Vec::new();
+        for source in value.split(',').filter(|source| !source.trim().is_empty()) {
+            let source = Self::parse(source)?;
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        Some(sources).filter(|sources| !sources.is_empty())
+    }
+
+    /// Name used in the Gmail labels (`<prefix>/done/xsense`)
+    pub fn key(&self) -> &'static str {
+        match self {
+            EmailSource::XSense => "xsense",
+            EmailSource::BlueRiot => "blueriot",
+        }
+    }
+
+    /// Name of the processor of the source, as in run summaries
+    pub fn processor_name(&self) -> &'static str {
+        match self {
+            EmailSource::XSense => "X-Sense",
+            EmailSource::BlueRiot => "Blue Riot",
+        }
+    }
+
+    /// Source of a label, from its last segment (`homemetrics/done/xsense`)
+    pub fn from_label(label: &str) -> Option<Self> {
+        label.rsplit('/').next().and_then(Self::parse)
+    }
+
+    /// Source of an email, from its first todo, done or error label under `prefix`
+    pub fn from_labels(labels: &[String], prefix: &str) -> Option<Self> {
+        labels.iter().find_map(|label| {
+            let (state, source) = label.strip_prefix(prefix)?.strip_prefix('/')?.split_once('/')?;
+            ["todo", "done", "error"].contains(&state).then(|| Self::parse(source)).flatten()
+        })
+    }
+}
+
+/// How the sources of a profile share the run (`PIPELINE_MODE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineMode {
+    /// All sources at once
+    #[default]
+    Concurrent,
+    /// One source after the other, in `PIPELINE_SOURCES` order
+    Sequential,
+}
+
+impl PipelineMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "concurrent" | "parallel" => Some(PipelineMode::Concurrent),
+            "sequential" => Some(PipelineMode::Sequential),
+            _ => None,
+        }
+    }
+}
+
+/// Where the pipeline writes the processed rows
+#[derive(Debug, Clone, Copy)]
+pub enum PipelineTarget<'a> {
+    /// The configured database
+    Database,
+    /// Nowhere: the database is only read to compare with stored readings
+    DryRun(DryRunDetail),
+    /// A SQLite sandbox file (`--dry-run-db`)
+    Sandbox(&'a Path),
+}
+
+/// Process every enabled source of a profile with a single Gmail connection
+///
+/// Authenticates once, then runs the sources concurrently or sequentially
+/// (always sequentially into a SQLite sandbox, which has a single writer).
+/// In production, old processed emails are cleaned up with the same
+/// connection. Returns one summary per source, in `PIPELINE_SOURCES` order.
+pub async fn process_all(config: &Config, target: PipelineTarget<'_>, limits: RunLimits) -> Result<Vec<RunSummary>> {
+    let gmail = GmailClient::new(&config.gmail).await
+        .context("Unable to connect to Gmail API")?;
+
+    let sources = &config.pipeline.sources;
+    let concurrent = config.pipeline.mode == PipelineMode::Concurrent && !matches!(target, PipelineTarget::Sandbox(_));
+    let results = if concurrent {
+        futures::future::join_all(sources.iter().map(|source| run_source(config, *source, target, limits, &gmail))).await
+    } else {
+        let mut results = Vec::new();
+        for source in sources {
+            let result = run_source(config, *source, target, limits, &gmail).await;
+            let failed = result.is_err();
+            results.push(result);
+            if failed {
+                break;
+            }
+        }
+        results
+    };
+
+    if let PipelineTarget::Database = target {
+        for error in results.iter().filter_map(|result| result.as_ref().err()) {
+            let data = serde_json::json!({ "profile": config.profile, "error": format!("{:#}", error) });
+            hooks::run(&config.hooks, HookEvent::Error, data).await;
+        }
+
+        // Old processed emails are cleaned up once the new ones are handled
+        if let Err(e) = cleanup_processed_emails(config, &gmail).await {
+            warn!("⚠️  Clean-up of processed emails failed: {}", e);
+        }
+    }
+
+    let summaries = results.into_iter().collect::<Result<Vec<_>>>()?;
+    info!("📬 {} source(s) processed with one Gmail connection: {} email(s), {} failed, {} record(s), {} API request(s)",
+          summaries.len(),
+          summaries.iter().map(RunSummary::processed_count).sum::<usize>(),
+          summaries.iter().map(RunSummary::failed_count).sum::<usize>(),
+          summaries.iter().map(RunSummary::total_records).sum::<usize>(),
+          gmail.api_call_count());
+    Ok(summaries)
+}
+
+async fn run_source(config: &Config, source: EmailSource, target: PipelineTarget<'_>, limits: RunLimits,
+                    gmail: &GmailClient) -> Result<RunSummary> {
+    match source {
+        EmailSource::XSense => {
+            let processor = match target {
+                PipelineTarget::Database => XSenseEmailProcessor::new(config.clone()).await?,
+                PipelineTarget::DryRun(_) => XSenseEmailProcessor::new_dry_run(config.clone()).await?,
+                PipelineTarget::Sandbox(path) => XSenseEmailProcessor::new_sandbox(config.clone(), path).await?,
+            };
+            match target {
+                PipelineTarget::DryRun(detail) => processor.process_emails_dry_run(gmail, limits, detail).await,
+                _ => processor.process_emails(gmail, limits).await,
+            }
+        }
+        EmailSource::BlueRiot => {
+            let processor = match target {
+                PipelineTarget::Database => BlueRiotEmailProcessor::new(config, false).await?,
+                PipelineTarget::DryRun(_) => BlueRiotEmailProcessor::new(config, true).await?,
+                PipelineTarget::Sandbox(path) => BlueRiotEmailProcessor::new_sandbox(config, path).await?,
+            };
+            match target {
+                PipelineTarget::DryRun(detail) => processor.process_emails_dry_run(gmail, limits, detail).await,
+                _ => processor.process_emails(gmail, limits).await,
+            }
+        }
+    }
+}
+
+/// Re-read emails carrying `label` and save their readings again (`backfill`)
+///
+/// Readings already stored for an email are replaced, so fixed extraction
+/// rules apply to past emails. Labels are left unchanged.
+pub async fn backfill(config: &Config, source: EmailSource, label: &str, since: Option<NaiveDate>,
+                      until: Option<NaiveDate>, limit: Option<usize>, dry_run: Option<DryRunDetail>) -> Result<RunSummary> {
+    let gmail = GmailClient::new(&config.gmail).await
+        .context("Unable to connect to Gmail API")?;
+
+    let mut message_ids = gmail.search_label_emails(label, since, until).await?;
+    info!("🔁 {} email(s) found with label '{}'", message_ids.len(), label);
+    if let Some(limit) = limit {
+        message_ids.truncate(limit);
+    }
+
+    match source {
+        EmailSource::XSense => {
+            let processor = match dry_run {
+                Some(_) => XSenseEmailProcessor::new_dry_run(config.clone()).await?,
+                None => XSenseEmailProcessor::new_backfill(config.clone()).await?,
+            };
+            processor.reprocess(&gmail, &message_ids, dry_run).await
+        }
+        EmailSource::BlueRiot => {
+            let processor = match dry_run {
+                Some(_) => BlueRiotEmailProcessor::new(config, true).await?,
+                None => BlueRiotEmailProcessor::new_backfill(config).await?,
+            };
+            processor.reprocess(&gmail, &message_ids, dry_run).await
+        }
+    }
+}
+
+/// Run one email through the whole extraction in dry-run, whatever its labels (`inspect`)
+///
+/// Without `source`, the source comes from the todo, done or error label of
+/// the email. Nothing is saved and no label changes.
+pub async fn inspect(config: &Config, message_id: &str, source: Option<EmailSource>, detail: DryRunDetail) -> Result<RunSummary> {
+    let gmail = GmailClient::new(&config.gmail).await
+        .context("Unable to connect to Gmail API")?;
+
+    let labels = gmail.fetch_label_names(message_id).await?;
+    let source = match source {
+        Some(source) => source,
+        None => EmailSource::from_labels(&labels, &config.gmail.label_prefix).ok_or_else(|| anyhow::anyhow!(
+            "Unable to infer the source of email {} from its labels ({}), use --source", message_id, labels.join(", ")))?,
+    };
+    info!("🔎 Inspecting {} email {} (labels: {})", source.key(), message_id, labels.join(", "));
+
+    let message_ids = [message_id.to_string()];
+    match source {
+        EmailSource::XSense => XSenseEmailProcessor::new_dry_run(config.clone()).await?
+            .reprocess(&gmail, &message_ids, Some(detail)).await,
+        EmailSource::BlueRiot => BlueRiotEmailProcessor::new(config, true).await?
+            .reprocess(&gmail, &message_ids, Some(detail)).await,
+    }
+}
+
+/// Options of `backfill-gmail`
+#[derive(Debug, Clone)]
+pub struct GmailBackfill {
+    /// Emails processed between two checkpoints
+    pub chunk: usize,
+    /// Keep going chunk after chunk until the label is exhausted
+    pub all: bool,
+    /// Wait between two chunks, spreading the Gmail requests
+    pub pause: Duration,
+    /// Gmail API requests allowed to this invocation, checked between chunks
+    pub max_api_calls: Option<usize>,
+    /// Forget the checkpoint and start over from the oldest email
+    pub restart: bool,
+}
+
+/// Where `backfill-gmail` stopped
+#[derive(Debug)]
+pub struct GmailBackfillRun {
+    pub summaries: Vec<RunSummary>,
+    /// Emails left after the checkpoint
+    pub remaining: usize,
+    /// Why emails are left: one chunk without `--all`, or a budget or quota
+    pub stopped_by: Option<RunBudget>,
+    /// Stopped with Ctrl-C, the chunk in progress is done again on resume
+    pub interrupted: bool,
+}
+
+/// Process the whole history of a label oldest first, in checkpointed chunks (`backfill-gmail`)
+///
+/// Meant for a first install: emails with readings are marked as processed
+/// as a run would, every reading is checked against the database (the older
+/// history is not assumed stored as in a scheduled run), and the progress is kept in `batch_checkpoints` (as
+/// `backfill/<source>`) after each chunk, so the next invocation resumes after
+/// the last complete chunk. Chunks stop at `GMAIL_DAILY_CALL_LIMIT`, at
+/// `max_api_calls` or on a Gmail quota error.
+pub async fn backfill_gmail(config: &Config, source: EmailSource, label: &str, options: &GmailBackfill) -> Result<GmailBackfillRun> {
+    let gmail = GmailClient::new(&config.gmail).await
+        .context("Unable to connect to Gmail API")?;
+    let database = Database::new(&config.database).await?.with_site(config.site.clone());
+
+    let key = format!("backfill/{}", source.key());
+    if options.restart {
+        database.clear_batch_checkpoint(&key).await?;
+    }
+    let checkpoint = database.batch_checkpoint(&key).await?;
+    // Emails of the checkpoint's second are listed again, then skipped up to it
+    let after = checkpoint.as_ref().map(|checkpoint| checkpoint.email_date / 1000 - 1);
+    let message_ids = gmail.list_label_history(label, after).await?;
+    let message_ids = resume_after(message_ids, checkpoint.as_ref().map(|checkpoint| checkpoint.email_id.as_str()));
+    info!("🔁 {} email(s) to backfill with label '{}'{}", message_ids.len(), label,
+          checkpoint.as_ref().map_or(String::new(), |checkpoint| format!(", after {}", checkpoint.email_id)));
+
+    let gmail = &gmail;
+    match source {
+        EmailSource::XSense => {
+            let processor = &XSenseEmailProcessor::new_history(config.clone()).await?;
+            backfill_chunks(config, gmail, &database, &key, message_ids, options,
+                            |ids| async move { processor.reprocess(gmail, &ids, None).await }).await
+        }
+        EmailSource::BlueRiot => {
+            let processor = &BlueRiotEmailProcessor::new_history(config).await?;
+            backfill_chunks(config, gmail, &database, &key, message_ids, options,
+                            |ids| async move { processor.reprocess(gmail, &ids, None).await }).await
+        }
+    }
+}
+
+async fn backfill_chunks<F, Fut>(config: &Config, gmail: &GmailClient, database: &Database, key: &str, message_ids: Vec<String>,
+                                 options: &GmailBackfill, reprocess: F) -> Result<GmailBackfillRun>
+where
+    F: Fn(Vec<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<RunSummary>>,
+{
+    let mut run = GmailBackfillRun { summaries: Vec::new(), remaining: message_ids.len(), stopped_by: None, interrupted: false };
+    let chunks: Vec<&[String]> = message_ids.chunks(options.chunk.max(1)).collect();
+    for (index, chunk) in chunks.iter().enumerate() {
+        if index > 0 && !options.all {
+            run.stopped_by = Some(RunBudget::Batch);
+            break;
+        }
+        if let Some(budget) = quota_reached(config, gmail, database, options).await {
+            run.stopped_by = Some(budget);
+            break;
+        }
+        if index > 0 {
+            info!("⏳ Pausing {:?} before the next chunk", options.pause);
+            tokio::select! {
+                _ = tokio::time::sleep(options.pause) => {}
+                _ = tokio::signal::ctrl_c() => {
+                    run.interrupted = true;
+                    break;
+                }
+            }
+        }
+
+        let summary = tokio::select! {
+            summary = reprocess(chunk.to_vec()) => summary?,
+            _ = tokio::signal::ctrl_c() => {
+                warn!("⏹️  Interrupted, the current chunk will be done again on resume");
+                run.interrupted = true;
+                break;
+            }
+        };
+
+        // Up to the email refused for quota, if any
+        let done = chunk.len() - summary.deferred;
+        let stopped_by = summary.stopped_by;
+        run.summaries.push(summary);
+        if let Some(last) = done.checked_sub(1).map(|index| &chunk[index]) {
+            match gmail.fetch_internal_date(last).await {
+                Ok(email_date) => {
+                    let checkpoint = BatchCheckpoint { source: key.to_string(), email_id: last.clone(), email_date };
+                    database.save_batch_checkpoint(&checkpoint).await?;
+                    run.remaining -= done;
+                }
+                Err(e) => warn!("Unable to save the backfill checkpoint, the chunk will be done again on resume: {}", e),
+            }
+        }
+        info!("🔁 Backfill chunk {}/{} done, {} email(s) left", index + 1, chunks.len(), run.remaining);
+        if stopped_by.is_some() {
+            run.stopped_by = stopped_by;
+            break;
+        }
+    }
+
+    if run.remaining == 0 {
+        database.clear_batch_checkpoint(key).await?;
+    }
+    Ok(run)
+}
+
+/// Budget spent before the next chunk: `--max-api-calls` or `GMAIL_DAILY_CALL_LIMIT`
+async fn quota_reached(config: &Config, gmail: &GmailClient, database: &Database, options: &GmailBackfill) -> Option<RunBudget> {
+    if options.max_api_calls.is_some_and(|max| gmail.api_call_count() >= max) {
+        return Some(RunBudget::ApiCalls);
+    }
+    let limit = config.gmail.daily_call_limit;
+    if limit == 0 {
+        return None;
+    }
+    let today = chrono::Utc::now().date_naive();
+    match database.api_usage(1).await {
+        Ok(usage) => {
+            let calls: u64 = usage.iter().filter(|usage| usage.day == today).map(|usage| usage.calls).sum();
+            (calls >= limit).then_some(RunBudget::Quota)
+        }
+        Err(e) => {
+            warn!("Unable to read today's Gmail API usage: {}", e);
+            None
+        }
+    }
+}
+
+/// Emails listed after the checkpoint email, all of them when it is no longer listed
+fn resume_after(mut message_ids: Vec<String>, checkpoint: Option<&str>) -> Vec<String> {
+    if let Some(position) = checkpoint.and_then(|checkpoint| message_ids.iter().position(|id| id == checkpoint)) {
+        message_ids.drain(..=position);
+    }
+    message_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sources() {
+        assert_eq!(EmailSource::parse_list("blueriot, xsense"), Some(vec![EmailSource::BlueRiot, EmailSource::XSense]));
+        assert_eq!(EmailSource::parse_list("xsense,xsense"), Some(vec![EmailSource::XSense]));
+        assert_eq!(EmailSource::parse_list("xsense,netatmo"), None);
+        assert_eq!(EmailSource::parse_list(" "), None);
+        assert_eq!(PipelineMode::parse("Sequential"), Some(PipelineMode::Sequential));
+        assert_eq!(EmailSource::from_label("homemetrics/done/blueriot"), Some(EmailSource::BlueRiot));
+        assert_eq!(EmailSource::from_label("homemetrics/done"), None);
+
+        let labels = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(EmailSource::from_labels(&labels(&["INBOX", "homemetrics/error/blueriot"]), "homemetrics"), Some(EmailSource::BlueRiot));
+        assert_eq!(EmailSource::from_labels(&labels(&["homemetrics/archive/xsense", "other/todo/xsense"]), "homemetrics"), None);
+    }
+
+    #[test]
+    fn test_resume_after() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(resume_after(ids(&["a", "b", "c"]), Some("b")), ids(&["c"]));
+        assert_eq!(resume_after(ids(&["a", "b", "c"]), Some("gone")), ids(&["a", "b", "c"]));
+        assert_eq!(resume_after(ids(&["a", "b"]), None), ids(&["a", "b"]));
+    }
+}
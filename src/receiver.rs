@@ -0,0 +1,394 @@
+use anyhow::{Result, Context};
+use log::{info, debug, warn, error};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::alerts::Alerting;
+use crate::attachment_parser::{Attachment, AttachmentParser};
+use crate::config::Config;
+use crate::database::{Database, SaveStats};
+use crate::health::{check_freshness, FreshnessRule, HealthReport};
+use crate::i18n::Locale;
+use crate::slack_notifier::{NotificationKind, NotificationSource, Severity, SlackNotifier};
+use crate::storage::{ApiUsage, Bucket, ReadingBucket, ReadingPage, ReadingRange, SensorType};
+use crate::templates::{sensor_stats, NotificationEvent, NotificationTemplates};
+use crate::units::UnitSystem;
+use crate::validation::validate_temperature_readings;
+use crate::xsense::{CsvColumns, TemperatureExtractor, TemperatureReading};
+
+/// Files modified more recently than this are considered still being uploaded
+const SETTLE_DELAY: Duration = Duration::from_secs(5);
+
+/// Extensions used by SFTP/HTTP clients for in-progress uploads
+const PARTIAL_EXTENSIONS: [&str; 4] = [".part", ".tmp", ".filepart", ".partial"];
+
+/// Watch the configured drop directory until the process is stopped
+pub async fn run_drop_dir_watcher(config: &Config) -> Result<()> {
+    let Some(drop_dir) = &config.receiver.drop_dir else {
+        anyhow::bail!("RECEIVER_DROP_DIR is not defined");
+    };
+
+    let receiver = FileReceiver::new(config).await?;
+    receiver.watch_drop_dir(drop_dir, Duration::from_secs(config.receiver.poll_interval_secs.max(1))).await
+}
+
+/// Extensions read by `homemetrics import` in a directory
+const IMPORT_EXTENSIONS: [&str; 5] = ["eml", "csv", "json", "xml", "txt"];
+
+/// Files to import from a path: the file itself, or the supported files of a directory
+///
+/// Directories are read recursively, hidden entries skipped, files sorted by path.
+pub fn import_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Unable to read {}", path.display()))?;
+    if !metadata.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path).with_context(|| format!("Unable to list {}", path.display()))? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let entry_path = entry.path();
+        if entry.file_type()?.is_dir() {
+            files.extend(import_files(&entry_path)?);
+        } else if entry_path.extension()
+            .is_some_and(|ext| IMPORT_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())) {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Receiver for data pushed without Gmail in the loop
+///
+/// Runs pushed readings and dropped export files through the same
+/// attachment → extractor → database → alerts flow as the email processors.
+pub struct FileReceiver {
+    database: Database,
+    slack: Option<SlackNotifier>,
+    alerting: Alerting,
+    locale: Locale,
+    units: UnitSystem,
+    templates: NotificationTemplates,
+    columns: CsvColumns,
+}
+
+impl FileReceiver {
+    pub async fn new(config: &Config) -> Result<Self> {
+        let database = Database::new(&config.database).await
+            .context("Unable to initialize database")?
+            .with_site(config.site.clone());
+
+        let slack = SlackNotifier::from_config(config.slack.as_ref())
+            .map(|slack| slack.with_log(Box::new(database.clone())));
+
+        Ok(FileReceiver {
+            database,
+            slack,
+            alerting: Alerting::from_config(config)?,
+            locale: config.locale,
+            units: config.units,
+            templates: NotificationTemplates::from_config(config)?,
+            columns: config.xsense.csv_columns.clone(),
+        })
+    }
+
+    /// Gmail API usage recorded by the email processors, for `GET /metrics`
+    pub async fn api_usage(&self, days: u32) -> Result<Vec<ApiUsage>> {
+        self.database.api_usage(days).await
+    }
+
+    /// Freshness of the required sensors, for `GET /health`
+    pub async fn health(&self, rules: &[FreshnessRule]) -> Result<HealthReport> {
+        check_freshness(&self.database, rules, Utc::now()).await
+    }
+
+    /// One page of stored readings, for `GET /readings`
+    pub async fn readings_range(&self, range: &ReadingRange) -> Result<ReadingPage> {
+        self.database.get_readings_range(range).await
+    }
+
+    /// Temperature statistics per bucket, for `GET /readings/buckets`
+    pub async fn bucketed_readings(&self, sensor_id: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>,
+                                   bucket: Bucket) -> Result<Vec<ReadingBucket>> {
+        self.database.get_bucketed_readings(sensor_id, from, to, bucket).await
+    }
+
+    /// Save readings, keep the type of their sensors up to date and raise their alerts
+    ///
+    /// Every reading is checked against the database: a file may hold readings
+    /// older than the ones already stored.
+    async fn save_readings(&self, readings: &[TemperatureReading]) -> Result<SaveStats> {
+        let (readings, rejected) = validate_temperature_readings(readings.to_vec(), "ingest", None);
+        self.database.save_rejected_readings(&rejected).await?;
+        
+        let stats = self.database.save_temperature_readings(&readings).await?;
+        for sensor_id in stats.per_sensor.keys() {
+            if let Err(e) = self.database.register_sensor(sensor_id, SensorType::Temperature).await {
+                warn!("Unable to register sensor {}: {:#}", sensor_id, e);
+            }
+        }
+        
+        let inserted: BTreeSet<&String> = stats.per_sensor.iter()
+            .filter(|(_, counts)| counts.inserted > 0)
+            .map(|(sensor, _)| sensor)
+            .collect();
+        self.alerting.check_saved(Some(&self.database), self.slack.as_ref(), NotificationSource::Ingest, inserted, &readings).await;
+        Ok(stats)
+    }
+
+    /// Store readings pushed by a device and send a notification
+    pub async fn ingest_readings(&self, readings: &[TemperatureReading]) -> Result<usize> {
+        let saved = self.save_readings(readings).await?.total();
+
+        let context = minijinja::context! {
+            readings => readings.len(),
+            sensors => sensor_stats(readings, self.units),
+        };
+        let message = self.templates.render_or(NotificationEvent::PushedData, context, || {
+            self.locale.notify_pushed_data(readings.len(), &Self::sensor_list(readings))
+        });
+        self.notify(Severity::Info, NotificationKind::Readings, None, &message).await;
+
+        Ok(saved)
+    }
+
+    /// Process an export file (CSV/JSON/TXT attachment or complete .eml)
+    ///
+    /// Returns the number of readings saved.
+    pub async fn ingest_file(&self, filename: &str, content: Vec<u8>) -> Result<usize> {
+        info!("📂 Processing received file: {}", filename);
+
+        let readings = self.extract_file(filename, content)?;
+        let saved = self.save_readings(&readings).await?.total();
+
+        let context = minijinja::context! {
+            readings => readings.len(),
+            filename => filename,
+            sensors => sensor_stats(&readings, self.units),
+        };
+        let message = self.templates.render_or(NotificationEvent::FileReceived, context, || {
+            self.locale.notify_file_received(readings.len(), filename, &Self::sensor_list(&readings))
+        });
+        self.notify(Severity::Info, NotificationKind::Readings, Some(filename), &message).await;
+
+        Ok(saved)
+    }
+
+    /// Save the readings of a local file (`homemetrics import`), without data notification
+    ///
+    /// Returns the number of readings extracted and of new ones inserted.
+    pub async fn import_file(&self, path: &Path) -> Result<(usize, usize)> {
+        let filename = path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let content = tokio::fs::read(path).await
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+
+        let readings = self.extract_file(&filename, content)?;
+        let stats = self.save_readings(&readings).await?;
+        Ok((readings.len(), stats.inserted))
+    }
+
+    /// Readings of an export file, or of every attachment of an .eml
+    fn extract_file(&self, filename: &str, content: Vec<u8>) -> Result<Vec<TemperatureReading>> {
+        let attachments = if filename.to_lowercase().ends_with(".eml") {
+            AttachmentParser::parse_email(&content)?
+        } else {
+            vec![Attachment {
+                filename: filename.to_string(),
+                content_type: AttachmentParser::guess_content_type(filename),
+                content,
+            }]
+        };
+
+        let mut readings = Vec::new();
+        for attachment in &attachments {
+            let extracted = TemperatureExtractor::extract_from_attachment_with(attachment, &self.columns)
+                .with_context(|| format!("Unable to extract data from {}", attachment.filename))?;
+            readings.extend(extracted);
+        }
+
+        if readings.is_empty() {
+            anyhow::bail!("No readings extracted from {}", filename);
+        }
+        Ok(readings)
+    }
+
+    /// Poll a drop directory (SFTP target, phone automation upload folder, ...)
+    ///
+    /// Each complete file is processed then moved to `processed/` or `failed/`
+    /// inside the drop directory.
+    pub async fn watch_drop_dir(&self, drop_dir: &str, poll_interval: Duration) -> Result<()> {
+        let drop_dir = PathBuf::from(drop_dir);
+        let processed_dir = drop_dir.join("processed");
+        let failed_dir = drop_dir.join("failed");
+
+        tokio::fs::create_dir_all(&processed_dir).await
+            .with_context(|| format!("Unable to create {}", processed_dir.display()))?;
+        tokio::fs::create_dir_all(&failed_dir).await
+            .with_context(|| format!("Unable to create {}", failed_dir.display()))?;
+
+        info!("👀 Watching drop directory {} (every {}s)", drop_dir.display(), poll_interval.as_secs());
+
+        let mut ticker = tokio::time::interval(poll_interval);
+        loop {
+            ticker.tick().await;
+
+            let files = match Self::ready_files(&drop_dir).await {
+                Ok(files) => files,
+                Err(e) => {
+                    error!("❌ Unable to scan drop directory {}: {}", drop_dir.display(), e);
+                    continue;
+                }
+            };
+
+            for path in files {
+                let filename = path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                let result = match tokio::fs::read(&path).await {
+                    Ok(content) => self.ingest_file(&filename, content).await,
+                    Err(e) => Err(e.into()),
+                };
+
+                let target_dir = match result {
+                    Ok(saved) => {
+                        info!("✅ {} processed: {} reading(s) saved", filename, saved);
+                        &processed_dir
+                    }
+                    Err(e) => {
+                        error!("❌ Error processing dropped file {}: {}", filename, e);
+                        let context = minijinja::context! { filename => &filename, error => e.to_string() };
+                        let message = self.templates.render_or(NotificationEvent::FileError, context, || {
+                            self.locale.notify_file_error(&filename, &e.to_string())
+                        });
+                        self.notify(Severity::Error, NotificationKind::Error, Some(&filename), &message).await;
+                        &failed_dir
+                    }
+                };
+
+                // A file dropped again under the same name never replaces the kept copy
+                if let Err(e) = tokio::fs::rename(&path, archive_path(target_dir, &filename, Utc::now())).await {
+                    error!("Failed to move {} to {}: {}", filename, target_dir.display(), e);
+                }
+            }
+        }
+    }
+
+    /// List files in the drop directory that are fully uploaded
+    async fn ready_files(drop_dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(drop_dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if name.starts_with('.') || PARTIAL_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+                debug!("Skipping partial/hidden file: {}", name);
+                continue;
+            }
+
+            let settled = metadata.modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .map(|age| age >= SETTLE_DELAY)
+                .unwrap_or(true);
+            if !settled {
+                debug!("File {} still being written, will retry", name);
+                continue;
+            }
+
+            files.push(entry.path());
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    fn sensor_list(readings: &[TemperatureReading]) -> String {
+        let sensors: BTreeSet<&str> = readings.iter()
+            .map(|r| r.sensor_id.as_str())
+            .collect();
+        sensors.into_iter().collect::<Vec<_>>().join(", ")
+    }
+
+    async fn notify(&self, severity: Severity, kind: NotificationKind, related_id: Option<&str>, message: &str) {
+        if let Some(slack) = &self.slack {
+            if let Err(e) = slack.notify(NotificationSource::Ingest, severity, kind, related_id, message).await {
+                warn!("Failed to send Slack notification: {}", e);
+                return;
+            }
+            // Slack answers again: redeliver what failed before
+            slack.retry_failed().await;
+        }
+    }
+}
+
+/// Free path for a file moved to `processed/` or `failed/`: its own name, or
+/// `<stem>-<YYYYMMDD-HHMMSS>[-<n>].<ext>` when a file of that name is already there
+fn archive_path(dir: &Path, filename: &str, now: DateTime<Utc>) -> PathBuf {
+    let path = dir.join(filename);
+    if !path.exists() {
+        return path;
+    }
+    let name = Path::new(filename);
+    let stem = name.file_stem().map_or(filename.to_string(), |stem| stem.to_string_lossy().to_string());
+    let extension = name.extension().map_or(String::new(), |ext| format!(".{}", ext.to_string_lossy()));
+    let stamp = now.format("%Y%m%d-%H%M%S");
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{}-{}{}", stem, stamp, extension)),
+            n => dir.join(format!("{}-{}-{}{}", stem, stamp, n, extension)),
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_import_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("2024");
+        std::fs::create_dir(&nested).unwrap();
+        for path in [dir.path().join("salon.csv"), nested.join("export.EML"), dir.path().join("notes.pdf"), dir.path().join(".hidden.csv")] {
+            std::fs::write(path, b"").unwrap();
+        }
+
+        let files = import_files(dir.path()).unwrap();
+        assert_eq!(files, vec![nested.join("export.EML"), dir.path().join("salon.csv")]);
+        // A file given explicitly is always imported
+        assert_eq!(import_files(&dir.path().join("notes.pdf")).unwrap(), vec![dir.path().join("notes.pdf")]);
+        assert!(import_files(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_archive_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 8, 30, 0).unwrap();
+        assert_eq!(archive_path(dir.path(), "export.csv", now), dir.path().join("export.csv"));
+
+        std::fs::write(dir.path().join("export.csv"), b"first").unwrap();
+        let second = archive_path(dir.path(), "export.csv", now);
+        assert_eq!(second, dir.path().join("export-20250310-083000.csv"));
+        std::fs::write(&second, b"second").unwrap();
+        assert_eq!(archive_path(dir.path(), "export.csv", now), dir.path().join("export-20250310-083000-2.csv"));
+        std::fs::write(dir.path().join("README"), b"").unwrap();
+        assert_eq!(archive_path(dir.path(), "README", now), dir.path().join("README-20250310-083000"));
+    }
+}
@@ -0,0 +1,387 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::email::{AttachmentResult, EmailStatus, RunSummary, SensorCounts};
+use crate::email::common::merge_sensor_counts;
+use crate::i18n::Locale;
+
+/// How the run was executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    Normal,
+    DryRun,
+    Sandbox,
+}
+
+/// Structured report of one run, written by `--report <path>`
+///
+/// The format follows the file extension: `.md`/`.markdown` gives Markdown,
+/// anything else JSON.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub mode: RunMode,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub processors: Vec<RunSummary>,
+    pub sensors: BTreeMap<String, SensorCounts>,
+    /// Error that aborted the whole run (Gmail/database unreachable, ...)
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    pub fn new(mode: RunMode, started_at: DateTime<Utc>, duration: Duration, result: &Result<Vec<RunSummary>>) -> Self {
+        let (processors, error) = match result {
+            Ok(summaries) => (summaries.clone(), None),
+            Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+        };
+
+        let mut sensors = BTreeMap::new();
+        for summary in &processors {
+            merge_sensor_counts(&mut sensors, &summary.sensors);
+        }
+
+        RunReport {
+            mode,
+            started_at,
+            finished_at: Utc::now(),
+            duration_secs: duration.as_secs_f64(),
+            processors,
+            sensors,
+            error,
+        }
+    }
+
+    /// Write the report, choosing JSON or Markdown from the extension
+    pub fn write(&self, path: &Path, locale: Locale) -> Result<()> {
+        let is_markdown = path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| matches!(ext.to_lowercase().as_str(), "md" | "markdown"))
+            .unwrap_or(false);
+
+        let content = if is_markdown {
+            self.to_markdown(locale)
+        } else {
+            serde_json::to_string_pretty(self).context("Unable to serialize run report")?
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create report directory {}", parent.display()))?;
+        }
+        std::fs::write(path, content)
+            .with_context(|| format!("Unable to write run report {}", path.display()))?;
+
+        info!("📝 Run report written to {}", path.display());
+        Ok(())
+    }
+
+    pub fn to_markdown(&self, locale: Locale) -> String {
+        let mut out = String::new();
+
+        out.push_str(&format!("# {}\n\n", locale.report_title()));
+        out.push_str(&format!("{}\n\n", locale.report_header(
+            &self.started_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            locale.report_mode(self.mode),
+            self.duration_secs,
+        )));
+
+        if let Some(error) = &self.error {
+            out.push_str(&format!("**{}**\n\n", locale.report_run_failed(error)));
+        }
+
+        // Emails
+        out.push_str(&format!("| {} | {} | {} | {} | {} | {} |\n",
+                              locale.summary_col_source(), locale.summary_col_email(),
+                              locale.summary_col_status(), locale.summary_col_records(),
+                              locale.report_col_attachments(), locale.report_col_duration()));
+        out.push_str("|---|---|---|---:|---|---:|\n");
+        for summary in &self.processors {
+            for email in &summary.emails {
+                out.push_str(&format!("| {} | `{}` | {} | {} | {} | {:.1}s |\n",
+                                      summary.processor, email.message_id,
+                                      locale.email_status(&email.status), email.records,
+                                      attachment_names(&email.attachments), email.duration.as_secs_f64()));
+            }
+        }
+        if self.processors.iter().all(|s| s.emails.is_empty()) {
+            out.push_str(&format!("\n{}\n", locale.summary_no_emails()));
+        }
+
+        // Readings per sensor
+        if !self.sensors.is_empty() {
+            out.push_str(&format!("\n| {} | {} | {} | {} |\n|---|---:|---:|---:|\n",
+                                  locale.summary_col_sensor(), locale.summary_col_inserted(),
+                                  locale.summary_col_duplicates(), locale.summary_col_rejected()));
+            for (sensor, counts) in &self.sensors {
+                out.push_str(&format!("| {} | {} | {} | {} |\n",
+                                      sensor, counts.inserted, counts.duplicates, counts.rejected));
+            }
+        }
+
+        // Errors
+        let errors: Vec<_> = self.processors.iter()
+            .flat_map(|s| s.emails.iter().map(move |e| (s, e)))
+            .filter_map(|(s, e)| match &e.status {
+                EmailStatus::Failed(error) | EmailStatus::Partial(error) => {
+                    Some((s.processor.as_str(), e.message_id.as_str(), error.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        if !errors.is_empty() {
+            out.push_str(&format!("\n## {}\n\n", locale.report_errors_title()));
+            for (processor, message_id, error) in errors {
+                out.push_str(&format!("- {} `{}`: {}\n", processor, message_id, error.replace('\n', " ")));
+            }
+        }
+
+        out.push('\n');
+        out.push_str(&locale.summary_totals(
+            self.processors.iter().map(|s| s.processed_count()).sum(),
+            self.processors.iter().map(|s| s.failed_count()).sum(),
+            self.duration_secs,
+            self.processors.iter().map(|s| s.api_calls).sum(),
+        ));
+        out.push('\n');
+        let deferred: usize = self.processors.iter().map(|s| s.deferred).sum();
+        if let Some(budget) = self.processors.iter().find_map(|s| s.stopped_by) {
+            out.push_str(&format!("\n{}\n", locale.summary_deferred(deferred, budget)));
+        }
+
+        out
+    }
+}
+
+/// Overall result of a `fetch`, mapped to the process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    /// Emails were read and none failed
+    Success,
+    /// Gmail, the database or the configuration failed: nothing reliable was done
+    Fatal,
+    /// Some emails or attachments failed; the others were saved
+    PartialFailure,
+    /// No email to process (every found email skipped, or none found)
+    NothingToDo,
+}
+
+impl RunOutcome {
+    pub fn of(result: &Result<Vec<RunSummary>>) -> Self {
+        let Ok(summaries) = result else {
+            return RunOutcome::Fatal;
+        };
+        let emails = || summaries.iter().flat_map(|summary| &summary.emails);
+        if summaries.iter().any(|summary| summary.failed_count() > 0) {
+            RunOutcome::PartialFailure
+        } else if emails().all(|email| matches!(email.status, EmailStatus::Skipped(_))) {
+            RunOutcome::NothingToDo
+        } else {
+            RunOutcome::Success
+        }
+    }
+
+    /// Process exit code (2 is left to command-line errors)
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RunOutcome::Success => 0,
+            RunOutcome::Fatal => 1,
+            RunOutcome::PartialFailure => 3,
+            RunOutcome::NothingToDo => 4,
+        }
+    }
+}
+
+/// Counts of a `fetch`, written by `--summary-file` for automation
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryFile {
+    pub outcome: RunOutcome,
+    pub exit_code: u8,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub emails_found: usize,
+    pub emails_processed: usize,
+    pub emails_without_data: usize,
+    pub emails_skipped: usize,
+    pub emails_failed: usize,
+    /// Emails left for the next run by a budget or the batch size
+    pub emails_deferred: usize,
+    pub readings_inserted: usize,
+    pub readings_duplicate: usize,
+    pub readings_rejected: usize,
+    /// Error that aborted the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SummaryFile {
+    pub fn new(started_at: DateTime<Utc>, result: &Result<Vec<RunSummary>>) -> Self {
+        let outcome = RunOutcome::of(result);
+        let (summaries, error) = match result {
+            Ok(summaries) => (summaries.as_slice(), None),
+            Err(e) => (&[][..], Some(format!("{:#}", e))),
+        };
+        let emails: Vec<_> = summaries.iter().flat_map(|summary| &summary.emails).collect();
+        let count = |matches: fn(&EmailStatus) -> bool| emails.iter().filter(|email| matches(&email.status)).count();
+        let mut sensors = BTreeMap::new();
+        for summary in summaries {
+            merge_sensor_counts(&mut sensors, &summary.sensors);
+        }
+
+        SummaryFile {
+            outcome,
+            exit_code: outcome.exit_code(),
+            started_at,
+            finished_at: Utc::now(),
+            emails_found: emails.len(),
+            emails_processed: count(|status| *status == EmailStatus::Processed),
+            emails_without_data: count(|status| *status == EmailStatus::NoData),
+            emails_skipped: count(|status| matches!(status, EmailStatus::Skipped(_))),
+            emails_failed: count(|status| matches!(status, EmailStatus::Failed(_) | EmailStatus::Partial(_))),
+            emails_deferred: summaries.iter().map(|summary| summary.deferred).sum(),
+            readings_inserted: sensors.values().map(|counts| counts.inserted).sum(),
+            readings_duplicate: sensors.values().map(|counts| counts.duplicates).sum(),
+            readings_rejected: sensors.values().map(|counts| counts.rejected).sum(),
+            error,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("Unable to serialize run summary")?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create summary directory {}", parent.display()))?;
+        }
+        std::fs::write(path, content)
+            .with_context(|| format!("Unable to write run summary {}", path.display()))?;
+        info!("📝 Run summary written to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Attachment names for the Markdown table, failed ones marked with ❌
+fn attachment_names(attachments: &[AttachmentResult]) -> String {
+    attachments.iter()
+        .map(|attachment| match attachment.error {
+            Some(_) => format!("❌ {}", attachment.filename),
+            None => attachment.filename.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueriot::PoolReading;
+    use crate::email::{EmailHeaders, EmailOutcome, ProcessingResult, Provenance, Reading};
+
+    fn sample() -> RunReport {
+        let mut summary = RunSummary::new("X-Sense");
+        summary.emails.push(EmailOutcome {
+            message_id: "18c1".to_string(),
+            status: EmailStatus::Failed("bad csv".to_string()),
+            records: 0,
+            attachments: vec![AttachmentResult {
+                error: Some("bad header".to_string()),
+                ..AttachmentResult::new("export.csv")
+            }],
+            headers: None,
+            readings: Vec::new(),
+            duration: Duration::from_millis(200),
+        });
+        RunReport::new(RunMode::Normal, Utc::now(), Duration::from_secs(1), &Ok(vec![summary]))
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let markdown = sample().to_markdown(Locale::En);
+        assert!(markdown.contains("| X-Sense | `18c1` |"));
+        assert!(markdown.contains("export.csv"));
+        assert!(markdown.contains("## Errors"));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.json");
+        sample().write(&path, Locale::En).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["mode"], "normal");
+        assert_eq!(json["processors"][0]["emails"][0]["status"], "failed");
+        assert_eq!(json["processors"][0]["emails"][0]["error"], "bad csv");
+        assert_eq!(json["processors"][0]["emails"][0]["attachments"][0]["error"], "bad header");
+        assert!(json["processors"][0]["emails"][0].get("readings").is_none());
+    }
+
+    #[test]
+    fn test_summary_file_outcome() {
+        let summary = SummaryFile::new(Utc::now(), &Ok(sample().processors));
+        assert_eq!(summary.outcome, RunOutcome::PartialFailure);
+        assert_eq!((summary.emails_found, summary.emails_failed, summary.exit_code), (1, 1, 3));
+
+        let mut skipped = RunSummary::new("Blue Riot");
+        skipped.emails.push(EmailOutcome {
+            message_id: "18c3".to_string(),
+            status: EmailStatus::Skipped("subject excluded".to_string()),
+            records: 0,
+            attachments: Vec::new(),
+            headers: None,
+            readings: Vec::new(),
+            duration: Duration::ZERO,
+        });
+        assert_eq!(RunOutcome::of(&Ok(vec![skipped.clone()])), RunOutcome::NothingToDo);
+        assert_eq!(RunOutcome::of(&Ok(Vec::new())), RunOutcome::NothingToDo);
+
+        skipped.emails.push(EmailOutcome::from_result("18c4", EmailStatus::Processed, ProcessingResult::empty(), Duration::ZERO));
+        skipped.sensors.insert("pool/main".to_string(), SensorCounts { inserted: 1, duplicates: 2, rejected: 0 });
+        let summary = SummaryFile::new(Utc::now(), &Ok(vec![skipped]));
+        assert_eq!(summary.outcome, RunOutcome::Success);
+        assert_eq!((summary.emails_processed, summary.emails_skipped), (1, 1));
+        assert_eq!((summary.readings_inserted, summary.readings_duplicate), (1, 2));
+
+        let fatal = SummaryFile::new(Utc::now(), &Err(anyhow::anyhow!("Gmail unreachable")));
+        assert_eq!((fatal.outcome, fatal.exit_code), (RunOutcome::Fatal, 1));
+        assert_eq!(fatal.error.as_deref(), Some("Gmail unreachable"));
+    }
+
+    #[test]
+    fn test_dry_run_json_readings() {
+        let pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp: Utc::now(),
+            temperature: None,
+            ph: Some(7.2),
+            orp: None,
+        };
+        let result = ProcessingResult {
+            records: 1,
+            headers: Some(EmailHeaders {
+                subject: "Blue Riot".to_string(),
+                from: "noreply@blueriiot.com".to_string(),
+                date: Utc::now(),
+            }),
+            readings: vec![Reading::from_pool(&pool, None, Provenance::email("blueriot", "18c2", None))],
+            ..ProcessingResult::empty()
+        };
+        let mut summary = RunSummary::new("Blue Riot");
+        summary.emails.push(EmailOutcome::from_result("18c2", EmailStatus::Processed, result, Duration::ZERO));
+        let report = RunReport::new(RunMode::DryRun, Utc::now(), Duration::from_secs(1), &Ok(vec![summary]));
+
+        let json = serde_json::to_value(&report).unwrap();
+        let email = &json["processors"][0]["emails"][0];
+        assert_eq!(json["mode"], "dry_run");
+        assert_eq!(email["headers"]["subject"], "Blue Riot");
+        assert_eq!(email["readings"][0]["sensor_id"], "pool/main");
+        assert_eq!(email["readings"][0]["metrics"]["ph"], 7.2);
+        assert_eq!(email["readings"][0]["provenance"]["email_id"], "18c2");
+    }
+}
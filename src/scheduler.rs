@@ -0,0 +1,134 @@
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc};
+
+use crate::config::{self, Config, SchedulerConfig};
+use crate::pipeline::EmailSource;
+
+/// Next run of a daemon job (`SCHEDULER_TIMES`, `SCHEDULER_CRON`, `FORECAST_TIME`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledRun {
+    /// "fetch" or "forecast"
+    pub job: &'static str,
+    pub at: NaiveDateTime,
+}
+
+/// Next occurrence of a "HH:MM" time, hourly "*:MM" time or cron expression, strictly after `now`
+pub fn next_occurrence(time: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if time.contains(char::is_whitespace) {
+        let schedule: cron::Schedule = config::normalize_cron(time).ok()?.parse().ok()?;
+        return schedule.after(&now.and_utc()).next().map(|at| at.naive_utc());
+    }
+    if let Some(minute) = time.trim().strip_prefix("*:") {
+        let minute: u32 = minute.parse().ok().filter(|minute| *minute < 60)?;
+        let this_hour = now.date().and_hms_opt(now.hour(), minute, 0)?;
+        return Some(if this_hour > now { this_hour } else { this_hour + TimeDelta::hours(1) });
+    }
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?;
+    let today = now.date().and_time(time);
+    Some(if today > now { today } else { today + TimeDelta::days(1) })
+}
+
+/// Last occurrence of a "HH:MM" time, hourly "*:MM" time or cron expression, at or before `now`
+pub fn previous_occurrence(time: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if time.contains(char::is_whitespace) {
+        let schedule: cron::Schedule = config::normalize_cron(time).ok()?.parse().ok()?;
+        // Walking back from the next occurrence also finds one falling exactly on `now`
+        return schedule.after(&(now + TimeDelta::seconds(1)).and_utc()).next_back().map(|at| at.naive_utc());
+    }
+    if let Some(minute) = time.trim().strip_prefix("*:") {
+        let minute: u32 = minute.parse().ok().filter(|minute| *minute < 60)?;
+        let this_hour = now.date().and_hms_opt(now.hour(), minute, 0)?;
+        return Some(if this_hour <= now { this_hour } else { this_hour - TimeDelta::hours(1) });
+    }
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?;
+    let today = now.date().and_time(time);
+    Some(if today <= now { today } else { today - TimeDelta::days(1) })
+}
+
+/// Sources whose last scheduled time came after their last run, with that time
+///
+/// Times are read in UTC, like the daemon's scheduler does. Sources without
+/// any recorded run are left out: a fresh install has nothing to catch up.
+pub fn missed_runs(scheduler: &SchedulerConfig, sources: &[EmailSource], last_runs: &[(EmailSource, DateTime<Utc>)],
+                   now: DateTime<Utc>) -> Vec<(EmailSource, DateTime<Utc>)> {
+    let runs = scheduler.runs(sources);
+    sources.iter().filter_map(|&source| {
+        let last_run = last_runs.iter().filter(|(candidate, _)| *candidate == source).map(|(_, at)| *at).max()?;
+        let scheduled = runs.iter()
+            .filter(|(_, run_sources)| run_sources.contains(&source))
+            .filter_map(|(time, _)| previous_occurrence(time, now.naive_utc()))
+            .max()?
+            .and_utc();
+        (scheduled > last_run).then_some((source, scheduled))
+    }).collect()
+}
+
+/// Next run of each daemon job, soonest first
+///
+/// Empty when the scheduler is disabled, since no daemon would run them.
+pub fn upcoming_runs(config: &Config, now: NaiveDateTime) -> Vec<ScheduledRun> {
+    if !config.scheduler.enabled {
+        return Vec::new();
+    }
+    let fetches = config.scheduler.runs(&config.pipeline.sources).into_iter().map(|(time, _)| ("fetch", time));
+    let forecast = config.forecast.time.iter().map(|time| ("forecast", time.clone()));
+    let mut runs: Vec<ScheduledRun> = fetches.chain(forecast)
+        .filter_map(|(job, time)| Some(ScheduledRun { job, at: next_occurrence(&time, now)? }))
+        .collect();
+    runs.sort_by_key(|run| run.at);
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_next_occurrence() {
+        let now = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap().and_hms_opt(14, 0, 0).unwrap();
+        let at = |day, hour, minute| NaiveDate::from_ymd_opt(2025, 3, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+        assert_eq!(next_occurrence("18:30", now), Some(at(10, 18, 30)));
+        assert_eq!(next_occurrence("02:00", now), Some(at(11, 2, 0)));
+        assert_eq!(next_occurrence("14:00", now), Some(at(11, 14, 0)));
+        assert_eq!(next_occurrence("2pm", now), None);
+        assert_eq!(next_occurrence("*:15", now), Some(at(10, 14, 15)));
+        assert_eq!(next_occurrence("*:00", now), Some(at(10, 15, 0)));
+        assert_eq!(next_occurrence("0 */4 * * *", now), Some(at(10, 16, 0)));
+        // 2025-03-10 is a Monday
+        assert_eq!(next_occurrence("30 8 * * 6", now), Some(at(15, 8, 30)));
+        assert_eq!(next_occurrence("30 8 * * 1-5", now), Some(at(11, 8, 30)));
+    }
+
+    #[test]
+    fn test_missed_runs() {
+        let now = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap().and_hms_opt(14, 0, 0).unwrap();
+        let at = |day, hour, minute| NaiveDate::from_ymd_opt(2025, 3, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+        assert_eq!(previous_occurrence("18:30", now), Some(at(9, 18, 30)));
+        assert_eq!(previous_occurrence("14:00", now), Some(at(10, 14, 0)));
+        assert_eq!(previous_occurrence("*:15", now), Some(at(10, 13, 15)));
+        assert_eq!(previous_occurrence("0 */4 * * *", now), Some(at(10, 12, 0)));
+        assert_eq!(previous_occurrence("30 8 * * 6", now), Some(at(8, 8, 30)));
+
+        let scheduler = SchedulerConfig {
+            enabled: true,
+            schedule_times: vec!["02:00".to_string(), "12:00".to_string()],
+            source_times: vec![(EmailSource::BlueRiot, vec!["06:00".to_string()])],
+            cron: Vec::new(),
+            retry_attempts: 3,
+            retry_delay_seconds: 60,
+            run_on_start: true,
+            jitter: None,
+            catch_up: true,
+            control_socket: None,
+        };
+        let sources = [EmailSource::XSense, EmailSource::BlueRiot];
+        let last_runs = [
+            (EmailSource::XSense, at(10, 2, 5).and_utc()),
+            (EmailSource::BlueRiot, at(10, 6, 1).and_utc()),
+        ];
+        // Down from 02:05 to 14:00: the 12:00 X-Sense run was missed, Blue Riot ran at 06:00
+        assert_eq!(missed_runs(&scheduler, &sources, &last_runs, now.and_utc()), vec![(EmailSource::XSense, at(10, 12, 0).and_utc())]);
+        assert!(missed_runs(&scheduler, &sources, &last_runs[1..], now.and_utc()).is_empty());
+        assert!(missed_runs(&scheduler, &sources, &last_runs, at(10, 11, 59).and_utc()).is_empty());
+    }
+}
@@ -0,0 +1,684 @@
+use anyhow::{Result, Context};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{info, debug, warn, error};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::health::FreshnessRule;
+use crate::receiver::FileReceiver;
+use crate::storage::{self, ApiUsage, Bucket, ReadingBucket, ReadingPage, ReadingRange, SortOrder};
+use crate::units::UnitSystem;
+use crate::xsense::TemperatureExtractor;
+
+/// Maximum accepted request body size (16 MiB, large enough for .eml exports)
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// State shared by all HTTP connections
+struct ServerState {
+    ingest_token: Option<String>,
+    receiver: FileReceiver,
+    daily_call_limit: u64,
+    freshness: Vec<FreshnessRule>,
+    cache: ResponseCache,
+    units: UnitSystem,
+}
+
+/// Successful GET responses kept for `HTTP_CACHE_TTL` seconds, per path and query string
+///
+/// Dashboards polling the same URL every few seconds share one database query.
+/// A response is dropped as soon as this process writes readings (ingest, upload,
+/// drop directory, scheduled run of the daemon); writes of other processes and
+/// the Gmail API usage of `/metrics` are only seen once the TTL expires.
+struct ResponseCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    stored_at: Instant,
+    /// `storage::reading_writes()` when the response was computed
+    writes: u64,
+    content_type: Option<hyper::header::HeaderValue>,
+    body: hyper::body::Bytes,
+}
+
+impl ResponseCache {
+    fn new(ttl: Duration) -> Self {
+        ResponseCache { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn get(&self, key: &str, writes: u64) -> Option<CachedResponse> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(key)
+            .filter(|entry| entry.stored_at.elapsed() < self.ttl && entry.writes == writes)
+            .cloned()
+    }
+
+    fn insert(&self, key: String, writes: u64, content_type: Option<hyper::header::HeaderValue>, body: hyper::body::Bytes) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl && entry.writes == writes);
+        entries.insert(key, CachedResponse { stored_at: Instant::now(), writes, content_type, body });
+    }
+}
+
+/// Run the HTTP server until the process is stopped
+///
+/// Endpoints:
+/// * `POST /ingest` - push JSON readings from LAN devices (ESP32, Shelly, ...)
+/// * `POST /upload?filename=<name>` - push an X-Sense export file (CSV, JSON, .eml)
+/// * `GET /metrics` - Gmail API usage of the day in Prometheus text format
+/// * `GET /health` - 200 when every sensor of `HEALTH_SENSORS` has a fresh reading, 503 otherwise
+/// * `GET /readings?sensor=<id>` - stored readings of a sensor, one page at a time
+/// * `GET /readings/buckets?sensor=<id>&bucket=1h` - min/max/average per minute, hour or day
+///
+/// The POST endpoints and `GET /readings*` are authenticated with
+/// `Authorization: Bearer <HTTP_INGEST_TOKEN>`.
+pub async fn run_server(config: &Config) -> Result<()> {
+    let addr: SocketAddr = config.server.bind_address.parse()
+        .with_context(|| format!("Invalid HTTP bind address: {}", config.server.bind_address))?;
+
+    if config.server.ingest_token.is_none() {
+        warn!("⚠️  HTTP_INGEST_TOKEN not defined - POST /ingest will reject all requests");
+    }
+
+    let state = Arc::new(ServerState {
+        ingest_token: config.server.ingest_token.clone(),
+        receiver: FileReceiver::new(config).await?,
+        daily_call_limit: config.gmail.daily_call_limit,
+        freshness: config.health.sensors.clone(),
+        cache: ResponseCache::new(Duration::from_secs(config.server.cache_ttl_secs)),
+        units: config.units,
+    });
+
+    let make_service = make_service_fn(move |_conn| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| handle_request(state.clone(), req)))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("Unable to bind HTTP server on {}", addr))?
+        .serve(make_service);
+
+    info!("🌐 HTTP server listening on http://{}", addr);
+
+    server.await.context("HTTP server error")?;
+    Ok(())
+}
+
+async fn handle_request(state: Arc<ServerState>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    debug!("HTTP {} {}", req.method(), req.uri().path());
+
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::POST, "/ingest") => handle_ingest(&state, req).await,
+        (&Method::POST, "/upload") => handle_upload(&state, req).await,
+        (&Method::GET, "/metrics") => cached(&state, &req, handle_metrics(&state)).await,
+        (&Method::GET, "/health") => handle_health(&state).await,
+        (&Method::GET, "/readings") => handle_readings(&state, &req).await,
+        (&Method::GET, "/readings/buckets") => handle_reading_buckets(&state, &req).await,
+        (_, "/ingest") | (_, "/upload") | (_, "/metrics") | (_, "/health") | (_, "/readings") | (_, "/readings/buckets") => json_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            serde_json::json!({ "error": "Method not allowed" }),
+        ),
+        _ => json_response(
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": "Not found" }),
+        ),
+    };
+
+    Ok(response)
+}
+
+/// Serve a GET from the response cache, or run the handler and cache its successful response
+async fn cached(
+    state: &ServerState,
+    req: &Request<Body>,
+    handler: impl std::future::Future<Output = Response<Body>>,
+) -> Response<Body> {
+    let key = req.uri().path_and_query().map_or_else(|| req.uri().path().to_string(), |pq| pq.to_string());
+    // Read before the query: a write during it leaves the entry stale
+    let writes = storage::reading_writes();
+    if let Some(entry) = state.cache.get(&key, writes) {
+        debug!("HTTP cache hit for {}", key);
+        let mut response = Response::new(Body::from(entry.body));
+        if let Some(content_type) = entry.content_type {
+            response.headers_mut().insert(hyper::header::CONTENT_TYPE, content_type);
+        }
+        return response;
+    }
+
+    let response = handler.await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    match hyper::body::to_bytes(body).await {
+        Ok(bytes) => {
+            state.cache.insert(key, writes, parts.headers.get(hyper::header::CONTENT_TYPE).cloned(), bytes.clone());
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(e) => {
+            error!("❌ Error buffering response: {}", e);
+            json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({ "error": "Unable to build response" }),
+            )
+        }
+    }
+}
+
+/// Handle `POST /ingest`: parse, deduplicate, store and notify
+async fn handle_ingest(state: &ServerState, req: Request<Body>) -> Response<Body> {
+    let body = match authorized_body(state, req).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let readings = match TemperatureExtractor::extract_from_json(&body) {
+        Ok(readings) if !readings.is_empty() => readings,
+        Ok(_) => {
+            return json_response(
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({ "error": "No valid readings in payload" }),
+            );
+        }
+        Err(e) => {
+            return json_response(
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({ "error": format!("Invalid payload: {}", e) }),
+            );
+        }
+    };
+
+    info!("📥 Received {} pushed reading(s)", readings.len());
+
+    match state.receiver.ingest_readings(&readings).await {
+        Ok(saved) => json_response(
+            StatusCode::OK,
+            serde_json::json!({ "received": readings.len(), "saved": saved }),
+        ),
+        Err(e) => {
+            error!("❌ Error saving pushed readings: {}", e);
+            json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({ "error": "Unable to save readings" }),
+            )
+        }
+    }
+}
+
+/// Handle `POST /upload`: run an export file through the extraction pipeline
+async fn handle_upload(state: &ServerState, req: Request<Body>) -> Response<Body> {
+    // Unauthenticated callers get 401 whatever they sent
+    let filename = upload_filename(&req);
+    let body = match authorized_body(state, req).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let filename = match filename {
+        Some(filename) => filename,
+        None => {
+            return json_response(
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({ "error": "Missing filename (query parameter or X-Filename header)" }),
+            );
+        }
+    };
+
+    match state.receiver.ingest_file(&filename, body).await {
+        Ok(saved) => json_response(
+            StatusCode::OK,
+            serde_json::json!({ "filename": filename, "saved": saved }),
+        ),
+        Err(e) => {
+            error!("❌ Error processing uploaded file {}: {}", filename, e);
+            json_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                serde_json::json!({ "filename": filename, "error": e.to_string() }),
+            )
+        }
+    }
+}
+
+/// Handle `GET /metrics`: today's Gmail API usage for Prometheus
+async fn handle_metrics(state: &ServerState) -> Response<Body> {
+    match state.receiver.api_usage(1).await {
+        Ok(usage) => Response::builder()
+            .status(StatusCode::OK)
+            .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(render_metrics(&usage, state.daily_call_limit)))
+            .unwrap_or_else(|_| Response::new(Body::empty())),
+        Err(e) => {
+            error!("❌ Error reading Gmail API usage: {}", e);
+            json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({ "error": "Unable to read metrics" }),
+            )
+        }
+    }
+}
+
+/// Handle `GET /health`: freshness of the required sensors, never cached
+///
+/// Unauthenticated so Docker HEALTHCHECK and uptime monitors can poll it.
+async fn handle_health(state: &ServerState) -> Response<Body> {
+    match state.receiver.health(&state.freshness).await {
+        Ok(report) => {
+            let status = if report.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            json_response(status, serde_json::to_value(&report).unwrap_or_default())
+        }
+        Err(e) => {
+            error!("❌ Error checking sensor freshness: {}", e);
+            json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                serde_json::json!({ "healthy": false, "error": "Unable to read the database" }),
+            )
+        }
+    }
+}
+
+/// Handle `GET /readings`: one page of the stored readings of a sensor
+async fn handle_readings(state: &ServerState, req: &Request<Body>) -> Response<Body> {
+    if let Some(response) = unauthorized(state, req) {
+        return response;
+    }
+
+    let range = match reading_range(req.uri().query().unwrap_or("")) {
+        Ok(range) => range,
+        Err(error) => return json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": error })),
+    };
+
+    cached(state, req, async {
+        match state.receiver.readings_range(&range).await {
+            Ok(page) => json_response(StatusCode::OK, readings_json(&range.sensor_id, page, state.units)),
+            Err(e) => {
+                error!("❌ Error reading readings of {}: {}", range.sensor_id, e);
+                json_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    serde_json::json!({ "error": "Unable to read readings" }),
+                )
+            }
+        }
+    }).await
+}
+
+/// Handle `GET /readings/buckets`: temperature statistics of a sensor per bucket, for charts
+async fn handle_reading_buckets(state: &ServerState, req: &Request<Body>) -> Response<Body> {
+    if let Some(response) = unauthorized(state, req) {
+        return response;
+    }
+
+    let query = req.uri().query().unwrap_or("");
+    let (range, bucket) = match reading_range(query).and_then(|range| Ok((range, bucket_param(query)?))) {
+        Ok(params) => params,
+        Err(error) => return json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": error })),
+    };
+
+    cached(state, req, async {
+        match state.receiver.bucketed_readings(&range.sensor_id, range.from, range.to, bucket).await {
+            Ok(buckets) => json_response(StatusCode::OK, buckets_json(&range.sensor_id, buckets, state.units)),
+            Err(e) => {
+                error!("❌ Error reading buckets of {}: {}", range.sensor_id, e);
+                json_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    serde_json::json!({ "error": "Unable to read readings" }),
+                )
+            }
+        }
+    }).await
+}
+
+/// Body of `GET /readings`: temperatures in the configured `UNITS`, named by `unit`
+fn readings_json(sensor_id: &str, mut page: ReadingPage, units: UnitSystem) -> serde_json::Value {
+    for reading in &mut page.readings {
+        reading.temperature = units.temperature(reading.temperature);
+    }
+    serde_json::json!({
+        "sensor": sensor_id,
+        "unit": units.temperature_symbol(),
+        "readings": page.readings,
+        "next": page.next,
+    })
+}
+
+/// Body of `GET /readings/buckets`: min, max and average in the configured `UNITS`
+fn buckets_json(sensor_id: &str, mut buckets: Vec<ReadingBucket>, units: UnitSystem) -> serde_json::Value {
+    for bucket in &mut buckets {
+        bucket.min = units.temperature(bucket.min);
+        bucket.max = units.temperature(bucket.max);
+        bucket.avg = units.temperature(bucket.avg);
+    }
+    serde_json::json!({
+        "sensor": sensor_id,
+        "unit": units.temperature_symbol(),
+        "buckets": buckets,
+    })
+}
+
+/// `bucket` query parameter (`1m`, `1h` or `1d`), one hour by default
+fn bucket_param(query: &str) -> Result<Bucket, String> {
+    match form_urlencoded::parse(query.as_bytes()).find(|(name, _)| name == "bucket") {
+        Some((_, value)) => Bucket::parse(&value)
+            .ok_or_else(|| format!("Invalid bucket '{}' (expected 1m, 1h or 1d)", value)),
+        None => Ok(Bucket::Hour),
+    }
+}
+
+/// Range of `GET /readings?sensor=<id>&from=&to=&order=asc|desc&limit=&after=`
+///
+/// Timestamps are RFC 3339; `after` is the `next` cursor of the previous page.
+fn reading_range(query: &str) -> Result<ReadingRange, String> {
+    let mut range = ReadingRange::default();
+    let timestamp = |name: &str, value: &str| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|timestamp| timestamp.with_timezone(&chrono::Utc))
+            .map_err(|_| format!("Invalid {} timestamp '{}' (expected RFC 3339)", name, value))
+    };
+
+    for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+        match name.as_ref() {
+            "sensor" => range.sensor_id = value.into_owned(),
+            "from" => range.from = Some(timestamp("from", &value)?),
+            "to" => range.to = Some(timestamp("to", &value)?),
+            "after" => range.after = Some(timestamp("after", &value)?),
+            "order" => range.order = SortOrder::parse(&value)
+                .ok_or_else(|| format!("Invalid order '{}' (expected asc or desc)", value))?,
+            "limit" => range.page_size = value.parse()
+                .map_err(|_| format!("Invalid limit '{}'", value))?,
+            _ => {}
+        }
+    }
+
+    if range.sensor_id.is_empty() {
+        return Err("Missing sensor parameter".to_string());
+    }
+    Ok(range)
+}
+
+/// Prometheus text exposition of the day's Gmail API usage
+fn render_metrics(usage: &[ApiUsage], daily_call_limit: u64) -> String {
+    let mut text = String::new();
+
+    text.push_str("# HELP homemetrics_gmail_api_calls_today Gmail API requests issued today (UTC)\n");
+    text.push_str("# TYPE homemetrics_gmail_api_calls_today gauge\n");
+    for day in usage {
+        text.push_str(&format!("homemetrics_gmail_api_calls_today{{source=\"{}\"}} {}\n", day.source, day.calls));
+    }
+
+    text.push_str("# HELP homemetrics_gmail_api_runs_today Processing runs today (UTC)\n");
+    text.push_str("# TYPE homemetrics_gmail_api_runs_today gauge\n");
+    for day in usage {
+        text.push_str(&format!("homemetrics_gmail_api_runs_today{{source=\"{}\"}} {}\n", day.source, day.runs));
+    }
+
+    if daily_call_limit > 0 {
+        text.push_str("# HELP homemetrics_gmail_api_daily_limit Configured GMAIL_DAILY_CALL_LIMIT\n");
+        text.push_str("# TYPE homemetrics_gmail_api_daily_limit gauge\n");
+        text.push_str(&format!("homemetrics_gmail_api_daily_limit {}\n", daily_call_limit));
+    }
+
+    text
+}
+
+/// Check authentication and read the body, or build the error response
+async fn authorized_body(state: &ServerState, req: Request<Body>) -> Result<Vec<u8>, Response<Body>> {
+    if let Some(response) = unauthorized(state, &req) {
+        return Err(response);
+    }
+
+    read_body(req.into_body()).await.map_err(|e| {
+        json_response(
+            e.status(),
+            serde_json::json!({ "error": e.to_string() }),
+        )
+    })
+}
+
+/// 401 response when the request lacks the bearer token
+fn unauthorized(state: &ServerState, req: &Request<Body>) -> Option<Response<Body>> {
+    let auth_header = req.headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    if !is_authorized(auth_header, state.ingest_token.as_deref()) {
+        warn!("Rejected unauthorized request on {}", req.uri().path());
+        return Some(json_response(
+            StatusCode::UNAUTHORIZED,
+            serde_json::json!({ "error": "Missing or invalid bearer token" }),
+        ));
+    }
+    None
+}
+
+/// Uploaded file name from `?filename=` or the `X-Filename` header
+///
+/// Only the final path component is kept.
+fn upload_filename(req: &Request<Body>) -> Option<String> {
+    let from_query = req.uri().query().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "filename")
+            .map(|(_, value)| value.into_owned())
+    });
+
+    let from_header = || {
+        req.headers()
+            .get("x-filename")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+
+    from_query
+        .or_else(from_header)
+        .and_then(|name| name.rsplit(['/', '\\']).next().map(|s| s.trim().to_string()))
+        .filter(|name| !name.is_empty())
+}
+
+/// Check the `Authorization` header against the configured ingest token
+///
+/// Requests are always rejected when no token is configured.
+fn is_authorized(auth_header: Option<&str>, expected_token: Option<&str>) -> bool {
+    match (auth_header, expected_token) {
+        (Some(header), Some(token)) => header
+            .strip_prefix("Bearer ")
+            .map(|provided| constant_time_eq(provided.trim().as_bytes(), token.as_bytes()))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Compare secrets in a time that depends on neither their content nor their length
+///
+/// Both sides are hashed first, so the comparison always covers 32 bytes.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(b.iter()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Why a request body could not be read
+#[derive(Debug, thiserror::Error)]
+enum BodyError {
+    #[error("Payload too large (max {MAX_BODY_SIZE} bytes)")]
+    TooLarge,
+    #[error("Error reading request body: {0}")]
+    Read(#[from] hyper::Error),
+}
+
+impl BodyError {
+    fn status(&self) -> StatusCode {
+        match self {
+            BodyError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            BodyError::Read(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Read the request body, refusing payloads larger than MAX_BODY_SIZE
+async fn read_body(mut body: Body) -> Result<Vec<u8>, BodyError> {
+    let mut buffer = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if buffer.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(BodyError::TooLarge);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+
+    Ok(buffer)
+}
+
+fn json_response(status: StatusCode, value: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(value.to_string()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xsense::TemperatureReading;
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(Some("Bearer secret"), Some("secret")));
+        assert!(!is_authorized(Some("Bearer wrong"), Some("secret")));
+        assert!(!is_authorized(Some("secret"), Some("secret")));
+        assert!(!is_authorized(None, Some("secret")));
+        assert!(!is_authorized(Some("Bearer secret"), None));
+        assert!(!is_authorized(Some("Bearer secre"), Some("secret")));
+        assert!(!is_authorized(Some("Bearer "), Some("secret")));
+    }
+
+    #[tokio::test]
+    async fn test_read_body() {
+        assert_eq!(read_body(Body::from("{}")).await.unwrap(), b"{}");
+        let too_large = read_body(Body::from(vec![0u8; MAX_BODY_SIZE + 1])).await.unwrap_err();
+        assert_eq!(too_large.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // A client that goes away mid-body is a bad request, not a too large one
+        let (sender, body) = Body::channel();
+        sender.abort();
+        assert_eq!(read_body(body).await.unwrap_err().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let usage = vec![ApiUsage {
+            day: chrono::Utc::now().date_naive(),
+            source: "xsense".to_string(),
+            calls: 42,
+            runs: 3,
+        }];
+
+        let text = render_metrics(&usage, 0);
+        assert!(text.contains("homemetrics_gmail_api_calls_today{source=\"xsense\"} 42\n"));
+        assert!(text.contains("homemetrics_gmail_api_runs_today{source=\"xsense\"} 3\n"));
+        assert!(!text.contains("homemetrics_gmail_api_daily_limit"));
+        assert!(render_metrics(&usage, 1000).ends_with("homemetrics_gmail_api_daily_limit 1000\n"));
+    }
+
+    #[test]
+    fn test_response_cache() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        assert!(cache.get("/metrics", 7).is_none());
+
+        cache.insert("/metrics".to_string(), 7, None, hyper::body::Bytes::from_static(b"calls 42\n"));
+        assert_eq!(cache.get("/metrics", 7).unwrap().body, "calls 42\n");
+        assert!(cache.get("/metrics?source=xsense", 7).is_none());
+
+        // Readings written since: stale
+        assert!(cache.get("/metrics", 8).is_none());
+
+        let disabled = ResponseCache::new(Duration::ZERO);
+        disabled.insert("/metrics".to_string(), 7, None, hyper::body::Bytes::from_static(b"calls 42\n"));
+        assert!(disabled.get("/metrics", 7).is_none());
+    }
+
+    #[test]
+    fn test_reading_range() {
+        let range = reading_range("sensor=cabane&from=2025-01-15T00:00:00Z&order=desc&limit=50&after=2025-01-20T08:00:00%2B01:00").unwrap();
+        assert_eq!(range.sensor_id, "cabane");
+        assert_eq!(range.from.unwrap().to_rfc3339(), "2025-01-15T00:00:00+00:00");
+        assert_eq!(range.to, None);
+        assert_eq!(range.order, SortOrder::Desc);
+        assert_eq!(range.page_size, 50);
+        assert_eq!(range.after.unwrap().to_rfc3339(), "2025-01-20T07:00:00+00:00");
+
+        assert!(reading_range("from=2025-01-15T00:00:00Z").is_err());
+        assert!(reading_range("sensor=cabane&from=yesterday").is_err());
+        assert!(reading_range("sensor=cabane&order=random").is_err());
+
+        assert_eq!(bucket_param("sensor=cabane&bucket=1d"), Ok(Bucket::Day));
+        assert_eq!(bucket_param("sensor=cabane"), Ok(Bucket::Hour));
+        assert!(bucket_param("bucket=1w").is_err());
+    }
+
+    #[test]
+    fn test_readings_json() {
+        let reading = TemperatureReading {
+            sensor_id: "cabane".to_string(),
+            timestamp: chrono::Utc::now(),
+            temperature: 20.0,
+            humidity: Some(55.0),
+            location: None,
+        };
+        let page = ReadingPage { readings: vec![reading], next: None };
+
+        let metric = readings_json("cabane", page.clone(), UnitSystem::Metric);
+        assert_eq!(metric["unit"], "°C");
+        assert_eq!(metric["readings"][0]["temperature"], 20.0);
+
+        let imperial = readings_json("cabane", page, UnitSystem::Imperial);
+        assert_eq!(imperial["unit"], "°F");
+        assert_eq!(imperial["readings"][0]["temperature"], 68.0);
+        assert_eq!(imperial["readings"][0]["humidity"], 55.0);
+    }
+
+    #[test]
+    fn test_buckets_json() {
+        let bucket = ReadingBucket { start: chrono::Utc::now(), count: 4, min: 10.0, max: 30.0, avg: 20.0 };
+
+        let metric = buckets_json("cabane", vec![bucket.clone()], UnitSystem::Metric);
+        assert_eq!(metric["unit"], "°C");
+        assert_eq!(metric["buckets"][0]["avg"], 20.0);
+
+        let imperial = buckets_json("cabane", vec![bucket], UnitSystem::Imperial);
+        assert_eq!(imperial["unit"], "°F");
+        assert_eq!(imperial["buckets"][0]["min"], 50.0);
+        assert_eq!(imperial["buckets"][0]["max"], 86.0);
+        assert_eq!(imperial["buckets"][0]["avg"], 68.0);
+        assert_eq!(imperial["buckets"][0]["count"], 4);
+    }
+
+    #[test]
+    fn test_upload_filename() {
+        let req = Request::post("/upload?filename=Thermo-cabane_Export%20data_20251105.csv")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(upload_filename(&req).as_deref(), Some("Thermo-cabane_Export data_20251105.csv"));
+
+        let req = Request::post("/upload")
+            .header("X-Filename", "../../etc/Salon_Export data_20251105.csv")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(upload_filename(&req).as_deref(), Some("Salon_Export data_20251105.csv"));
+
+        let req = Request::post("/upload").body(Body::empty()).unwrap();
+        assert_eq!(upload_filename(&req), None);
+    }
+}
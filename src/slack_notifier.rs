@@ -1,13 +1,142 @@
 use anyhow::Result;
-use log::{info, error};
+use chrono::{TimeDelta, Utc};
+use log::{info, debug, error, warn};
+use serde::Deserialize;
 use slack_morphism::prelude::*;
 
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
 use crate::config::SlackConfig;
+use crate::redact::redact;
+use crate::storage::{DeliveryStatus, NotificationRecord, Storage};
+
+/// Where a notification comes from, used as routing key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationSource {
+    XSense,
+    BlueRiot,
+    /// HTTP push and drop directory receiver
+    Ingest,
+}
+
+impl NotificationSource {
+    pub fn key(&self) -> &'static str {
+        match self {
+            NotificationSource::XSense => "xsense",
+            NotificationSource::BlueRiot => "blueriot",
+            NotificationSource::Ingest => "ingest",
+        }
+    }
+}
+
+/// Notification severity, used as routing key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Error,
+}
+
+impl Severity {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// What a notification is about, recorded in `notifications.kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Readings of a processed email or received file
+    Readings,
+    /// Temperature, humidity or mold alert
+    Alert,
+    /// Email or file that failed, dead letter, Gmail authorization lost
+    Error,
+    /// A source without emails for too long
+    NoEmails,
+    /// End-of-run summary (`SLACK_MODE=run`)
+    RunSummary,
+    /// Scheduled run aborted
+    RunFailed,
+    /// Daily forecast digest
+    Forecast,
+}
+
+impl NotificationKind {
+    pub fn key(&self) -> &'static str {
+        match self {
+            NotificationKind::Readings => "readings",
+            NotificationKind::Alert => "alert",
+            NotificationKind::Error => "error",
+            NotificationKind::NoEmails => "no_emails",
+            NotificationKind::RunSummary => "run_summary",
+            NotificationKind::RunFailed => "run_failed",
+            NotificationKind::Forecast => "forecast",
+        }
+    }
+}
+
+/// Context of a message, recorded with its delivery result
+#[derive(Debug, Clone)]
+struct Delivery {
+    source: Option<NotificationSource>,
+    kind: NotificationKind,
+    related_id: Option<String>,
+}
+
+/// Longest wait between two delivery attempts of a failed message
+const MAX_RETRY_DELAY: TimeDelta = TimeDelta::hours(1);
+
+/// Failed messages redelivered per pass
+const RETRY_BATCH: usize = 50;
+
+/// Wait before the next delivery attempt of a message that failed `attempts` times
+///
+/// Starts at `base` (`SLACK_RETRY_DELAY_SECONDS`) and doubles on each failure, up to one hour.
+pub fn retry_delay(base: TimeDelta, attempts: u32) -> TimeDelta {
+    let factor = 1i32.checked_shl(attempts.saturating_sub(1)).unwrap_or(i32::MAX);
+    base.checked_mul(factor)
+        .map_or(MAX_RETRY_DELAY, |delay| delay.min(MAX_RETRY_DELAY))
+}
+
+/// When email notifications are posted (`SLACK_MODE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlackMode {
+    /// One message per processed email
+    #[default]
+    Email,
+    /// One summary per run, per-email messages as thread replies; errors stay immediate
+    Run,
+}
+
+impl SlackMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "email" | "per-email" => Some(SlackMode::Email),
+            "run" | "summary" => Some(SlackMode::Run),
+            _ => None,
+        }
+    }
+}
 
 pub struct SlackNotifier {
     client: SlackClient<SlackClientHyperHttpsConnector>,
     token: SlackApiToken,
     channel_id: SlackChannelId,
+    routes: BTreeMap<String, String>,
+    mode: SlackMode,
+    /// Per-email messages held back until the run summary (run mode)
+    pending: Mutex<Vec<(Delivery, String)>>,
+    /// Where each message and its delivery result is recorded (`notifications`)
+    log: Option<Box<dyn Storage>>,
+    retry_base_delay: TimeDelta,
+    retry_expiry: TimeDelta,
+    /// Failed messages waiting for redelivery when they could not be recorded
+    retries: Mutex<Vec<NotificationRecord>>,
 }
 
 impl SlackNotifier {
@@ -22,24 +151,140 @@ impl SlackNotifier {
             client,
             token,
             channel_id,
+            routes: config.routes.clone(),
+            mode: config.mode,
+            pending: Mutex::new(Vec::new()),
+            log: None,
+            retry_base_delay: TimeDelta::seconds(config.retry_delay_seconds.max(1) as i64),
+            retry_expiry: TimeDelta::hours(config.retry_expiry_hours as i64),
+            retries: Mutex::new(Vec::new()),
         })
     }
     
-    /// Send a simple text message to Slack
-    pub async fn send_message(&self, text: &str) -> Result<()> {
-        info!("Sending Slack message");
+    /// Record every message posted from now on in the `notifications` table
+    ///
+    /// Failed messages are then redelivered from the table, by any process
+    /// sharing the database, instead of this notifier's memory.
+    pub fn with_log(mut self, storage: Box<dyn Storage>) -> Self {
+        self.log = Some(storage);
+        self
+    }
+    
+    /// Build a notifier from the optional Slack configuration
+    /// 
+    /// Returns None (notifications disabled) when Slack is not configured
+    /// or the client cannot be initialized.
+    pub fn from_config(config: Option<&SlackConfig>) -> Option<Self> {
+        match config {
+            Some(slack_config) => match SlackNotifier::new(slack_config) {
+                Ok(notifier) => {
+                    info!("✅ Slack notifications enabled");
+                    Some(notifier)
+                }
+                Err(e) => {
+                    warn!("⚠️  Unable to initialize Slack notifier: {} - notifications disabled", e);
+                    None
+                }
+            },
+            None => {
+                info!("ℹ️  Slack notifications not configured");
+                None
+            }
+        }
+    }
+    
+    pub fn mode(&self) -> SlackMode {
+        self.mode
+    }
+    
+    /// Send a simple text message to the default channel
+    pub async fn send_message(&self, kind: NotificationKind, text: &str) -> Result<()> {
+        let delivery = Delivery { source: None, kind, related_id: None };
+        self.post(self.channel_id.clone(), text, None, &delivery).await.map(|_| ())
+    }
+    
+    /// Post a message to the default channel, neither recorded nor retried (`homemetrics doctor`)
+    pub async fn send_test(&self, text: &str) -> Result<()> {
+        self.send(self.channel_id.clone(), text, None).await.map(|_| ())
+    }
+
+    /// Send a message to the channel routed for this source and severity
+    /// 
+    /// `related_id` is the email or alert the message is about, for the
+    /// `notifications` table. In run mode, informational email messages are
+    /// held back and posted in the thread of the run summary (see `post_run_summary`).
+    pub async fn notify(&self, source: NotificationSource, severity: Severity, kind: NotificationKind,
+                        related_id: Option<&str>, text: &str) -> Result<()> {
+        let delivery = Delivery { source: Some(source), kind, related_id: related_id.map(str::to_string) };
+        if self.mode == SlackMode::Run && severity == Severity::Info && source != NotificationSource::Ingest {
+            debug!("Slack message held for the run summary thread");
+            self.pending.lock().unwrap_or_else(|e| e.into_inner()).push((delivery, text.to_string()));
+            return Ok(());
+        }
+        self.post(self.channel_for(source, severity), text, None, &delivery).await.map(|_| ())
+    }
+    
+    /// Post the run summary, then the held per-email messages as replies in its thread
+    pub async fn post_run_summary(&self, source: NotificationSource, text: &str) -> Result<()> {
+        let details = std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()));
+        
+        let delivery = Delivery { source: Some(source), kind: NotificationKind::RunSummary, related_id: None };
+        let (channel, ts) = self.post(self.channel_for(source, Severity::Info), text, None, &delivery).await?;
+        for (delivery, detail) in details {
+            self.post(channel.clone(), &detail, Some(ts.clone()), &delivery).await?;
+        }
+        Ok(())
+    }
+    
+    fn channel_for(&self, source: NotificationSource, severity: Severity) -> SlackChannelId {
+        match route_channel(&self.routes, source, severity) {
+            Some(channel) => SlackChannelId::new(channel.to_string()),
+            None => self.channel_id.clone(),
+        }
+    }
+    
+    /// Post a message, optionally as a thread reply; returns where it was posted
+    ///
+    /// A failed message is queued for redelivery (see `retry_failed`).
+    async fn post(&self, channel: SlackChannelId, text: &str, thread_ts: Option<SlackTs>, delivery: &Delivery) -> Result<(SlackChannelId, SlackTs)> {
+        let text = redact(text).into_owned();
+        let result = self.send(channel.clone(), &text, thread_ts.clone()).await;
+        
+        let now = Utc::now();
+        let record = NotificationRecord {
+            id: 0,
+            created_at: now,
+            channel: channel.to_string(),
+            kind: delivery.kind.key().to_string(),
+            source: delivery.source.map(|source| source.key().to_string()),
+            related_id: delivery.related_id.clone(),
+            text,
+            thread_ts: thread_ts.map(|ts| ts.to_string()),
+            status: if result.is_ok() { DeliveryStatus::Sent } else { DeliveryStatus::Failed },
+            error: result.as_ref().err().map(|e| e.to_string()),
+            message_ts: result.as_ref().ok().map(|(_, ts)| ts.to_string()),
+            attempts: 1,
+            next_attempt_at: result.is_err().then(|| now + retry_delay(self.retry_base_delay, 1)),
+        };
+        self.record(record).await;
+        result
+    }
+    
+    async fn send(&self, channel: SlackChannelId, text: &str, thread_ts: Option<SlackTs>) -> Result<(SlackChannelId, SlackTs)> {
+        info!("Sending Slack message to {}", channel);
         
-        let post_chat_req = SlackApiChatPostMessageRequest::new(
-            self.channel_id.clone(),
+        let mut post_chat_req = SlackApiChatPostMessageRequest::new(
+            channel,
             SlackMessageContent::new().with_text(text.to_string()),
         );
+        post_chat_req.thread_ts = thread_ts;
         
         let session = self.client.open_session(&self.token);
         
         match session.chat_post_message(&post_chat_req).await {
             Ok(response) => {
                 info!("✅ Slack message sent successfully: {:?}", response.ts);
-                Ok(())
+                Ok((response.channel, response.ts))
             }
             Err(e) => {
                 error!("❌ Error sending Slack message: {}", e);
@@ -47,4 +292,149 @@ impl SlackNotifier {
             }
         }
     }
+    
+    /// Keep the message in `notifications`; a storage failure never fails the notification
+    ///
+    /// A failed message that cannot be recorded is kept in memory for redelivery.
+    async fn record(&self, record: NotificationRecord) {
+        if let Some(log) = &self.log {
+            match log.record_notification(&record).await {
+                Ok(_) => return,
+                Err(e) => warn!("Unable to record the Slack notification: {:#}", e),
+            }
+        }
+        if record.status == DeliveryStatus::Failed {
+            self.retries.lock().unwrap_or_else(|e| e.into_inner()).push(record);
+        }
+    }
+    
+    /// Redeliver the failed messages whose next attempt is due, returns how many got through
+    ///
+    /// Takes the messages kept in memory, then those of the `notifications`
+    /// table. A message still failing is rescheduled with a doubled delay;
+    /// once older than `SLACK_RETRY_EXPIRY_HOURS` it is marked expired.
+    pub async fn retry_failed(&self) -> usize {
+        let now = Utc::now();
+        let mut due: Vec<NotificationRecord> = {
+            let mut retries = self.retries.lock().unwrap_or_else(|e| e.into_inner());
+            let (due, waiting) = std::mem::take(&mut *retries).into_iter()
+                .partition(|record| record.next_attempt_at.is_none_or(|at| at <= now));
+            *retries = waiting;
+            due
+        };
+        if let Some(log) = &self.log {
+            // The lease keeps other processes away while this one is posting
+            match log.claim_notifications(now, self.retry_base_delay, RETRY_BATCH).await {
+                Ok(claimed) => due.extend(claimed),
+                Err(e) => warn!("Unable to read the Slack notifications to redeliver: {:#}", e),
+            }
+        }
+        
+        let mut delivered = 0;
+        for mut record in due {
+            if now - record.created_at > self.retry_expiry {
+                warn!("⚠️  Slack message ({}, {}) undelivered after {} attempt(s), given up", record.kind, record.created_at, record.attempts);
+                record.status = DeliveryStatus::Expired;
+                record.next_attempt_at = None;
+                self.store_retry(record).await;
+                continue;
+            }
+            
+            debug!("Redelivering Slack message {} ({}), attempt {}", record.id, record.kind, record.attempts + 1);
+            record.attempts += 1;
+            let thread_ts = record.thread_ts.clone().map(SlackTs::new);
+            match self.send(SlackChannelId::new(record.channel.clone()), &record.text, thread_ts).await {
+                Ok((_, ts)) => {
+                    record.status = DeliveryStatus::Sent;
+                    record.error = None;
+                    record.message_ts = Some(ts.to_string());
+                    record.next_attempt_at = None;
+                    delivered += 1;
+                }
+                Err(e) => {
+                    record.error = Some(e.to_string());
+                    record.next_attempt_at = Some(now + retry_delay(self.retry_base_delay, record.attempts));
+                }
+            }
+            self.store_retry(record).await;
+        }
+        if delivered > 0 {
+            info!("✅ {} failed Slack message(s) redelivered", delivered);
+        }
+        delivered
+    }
+    
+    /// Save a redelivery outcome: its row when recorded, else the memory queue while still failing
+    async fn store_retry(&self, record: NotificationRecord) {
+        if let Some(log) = self.log.as_ref().filter(|_| record.id != 0) {
+            if let Err(e) = log.update_notification(&record).await {
+                warn!("Unable to update the Slack notification {}: {:#}", record.id, e);
+            }
+            return;
+        }
+        if record.status == DeliveryStatus::Failed {
+            self.retries.lock().unwrap_or_else(|e| e.into_inner()).push(record);
+        }
+    }
+}
+
+/// Pick the channel for a notification from the `SLACK_ROUTES` table
+///
+/// Most specific key wins: `source.severity`, `*.severity`, `source`.
+/// Returns None to use the default `SLACK_CHANNEL_ID`.
+pub fn route_channel(routes: &BTreeMap<String, String>, source: NotificationSource, severity: Severity) -> Option<&str> {
+    [
+        format!("{}.{}", source.key(), severity.key()),
+        format!("*.{}", severity.key()),
+        source.key().to_string(),
+    ]
+    .iter()
+    .find_map(|key| routes.get(key))
+    .map(String::as_str)
+}
+
+/// Parse `SLACK_ROUTES` ("blueriot=#pool, xsense=#home, *.error=#alerts")
+pub fn parse_routes(value: &str) -> BTreeMap<String, String> {
+    value.split([',', ';'])
+        .filter_map(|entry| {
+            let (key, channel) = entry.split_once('=')?;
+            let (key, channel) = (key.trim().to_lowercase(), channel.trim());
+            if key.is_empty() || channel.is_empty() {
+                warn!("Ignoring invalid SLACK_ROUTES entry '{}'", entry.trim());
+                return None;
+            }
+            Some((key, channel.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routing() {
+        let routes = parse_routes("blueriot=#pool, xsense=#home; *.error=#alerts, xsense.error=#xsense-errors");
+
+        assert_eq!(route_channel(&routes, NotificationSource::BlueRiot, Severity::Info), Some("#pool"));
+        assert_eq!(route_channel(&routes, NotificationSource::BlueRiot, Severity::Error), Some("#alerts"));
+        assert_eq!(route_channel(&routes, NotificationSource::XSense, Severity::Error), Some("#xsense-errors"));
+        assert_eq!(route_channel(&routes, NotificationSource::Ingest, Severity::Info), None);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let base = TimeDelta::seconds(30);
+        assert_eq!(retry_delay(base, 1), TimeDelta::seconds(30));
+        assert_eq!(retry_delay(base, 3), TimeDelta::minutes(2));
+        assert_eq!(retry_delay(base, 8), TimeDelta::hours(1));
+        assert_eq!(retry_delay(base, 100), TimeDelta::hours(1));
+    }
+
+    #[test]
+    fn test_slack_mode_parse() {
+        assert_eq!(SlackMode::parse("Run"), Some(SlackMode::Run));
+        assert_eq!(SlackMode::parse("per-email"), Some(SlackMode::Email));
+        assert_eq!(SlackMode::parse("daily"), None);
+    }
 }
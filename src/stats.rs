@@ -0,0 +1,444 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::blueriot::PoolReading;
+use crate::i18n::Locale;
+use crate::storage::{Bucket, ReadingBucket, ReadingRange, SensorType, SortOrder, Storage, MAX_PAGE_SIZE};
+use crate::units::UnitSystem;
+use crate::xsense::TemperatureReading;
+
+/// Count, min, max and average of a series of values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+impl Summary {
+    /// Summary of the values, None when there are none
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        Self::merge(values.into_iter().map(|value| ReadingBucket {
+            start: DateTime::<Utc>::MIN_UTC,
+            count: 1,
+            min: value,
+            max: value,
+            avg: value,
+        }))
+    }
+
+    /// Combine per-bucket aggregates, the average weighted by the bucket counts
+    pub fn merge(buckets: impl IntoIterator<Item = ReadingBucket>) -> Option<Self> {
+        let mut summary: Option<Summary> = None;
+        let mut total = 0.0;
+        for bucket in buckets.into_iter().filter(|bucket| bucket.count > 0) {
+            total += bucket.avg * bucket.count as f64;
+            summary = Some(match summary {
+                None => Summary { count: bucket.count, min: bucket.min, max: bucket.max, avg: 0.0 },
+                Some(summary) => Summary {
+                    count: summary.count + bucket.count,
+                    min: summary.min.min(bucket.min),
+                    max: summary.max.max(bucket.max),
+                    avg: 0.0,
+                },
+            });
+        }
+        summary.map(|summary| Summary { avg: total / summary.count as f64, ..summary })
+    }
+}
+
+/// Average stored temperature of each sensor over the period preceding its new readings
+///
+/// The previous period has the length of the new one (first to last new
+/// reading) and ends at the first new reading. Sensors with a single new
+/// reading or nothing stored before are left out.
+pub async fn previous_period_averages(storage: &dyn Storage, readings: &[TemperatureReading]) -> Result<BTreeMap<String, f64>> {
+    let mut periods: BTreeMap<&str, (DateTime<Utc>, DateTime<Utc>)> = BTreeMap::new();
+    for reading in readings {
+        let period = periods.entry(&reading.sensor_id).or_insert((reading.timestamp, reading.timestamp));
+        period.0 = period.0.min(reading.timestamp);
+        period.1 = period.1.max(reading.timestamp);
+    }
+
+    let mut averages = BTreeMap::new();
+    for (sensor_id, (first, last)) in periods {
+        let span = last - first;
+        if span <= chrono::TimeDelta::zero() {
+            continue;
+        }
+        let hours = storage.get_bucketed_readings(sensor_id, Some(first - span), Some(first), Bucket::Hour).await?;
+        if let Some(summary) = Summary::merge(hours) {
+            averages.insert(sensor_id.to_string(), summary.avg);
+        }
+    }
+    Ok(averages)
+}
+
+/// Temperatures of a sensor over the period
+#[derive(Debug, Clone)]
+pub struct SensorStats {
+    pub sensor_id: String,
+    pub location: Option<String>,
+    pub temperature: Summary,
+    pub latest: TemperatureReading,
+}
+
+/// Chemistry of a pool over the period
+#[derive(Debug, Clone)]
+pub struct PoolStats {
+    pub pool_id: String,
+    pub readings: usize,
+    pub temperature: Option<Summary>,
+    pub ph: Option<Summary>,
+    pub orp: Option<Summary>,
+    pub latest: PoolReading,
+}
+
+/// Everything shown by `homemetrics stats`
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub sensors: Vec<SensorStats>,
+    pub pools: Vec<PoolStats>,
+}
+
+/// Summaries of every sensor and pool with readings over `[from, to)`
+///
+/// Sensor temperatures are aggregated per day by the storage
+/// (`get_bucketed_readings`), then combined here; pools are few enough
+/// to be summarized from their readings.
+pub async fn collect_stats(storage: &dyn Storage, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Stats> {
+    let mut sensors = Vec::new();
+    // Pools have their own table below
+    let pool_type = Some(SensorType::Pool.as_str());
+    for sensor in storage.sensors().await?.into_iter().filter(|sensor| sensor.sensor_type.as_deref() != pool_type) {
+        let days = storage.get_bucketed_readings(&sensor.sensor_id, Some(from), Some(to), Bucket::Day).await?;
+        let Some(temperature) = Summary::merge(days) else {
+            continue;
+        };
+
+        let range = ReadingRange {
+            sensor_id: sensor.sensor_id.clone(),
+            from: Some(from),
+            to: Some(to),
+            order: SortOrder::Desc,
+            page_size: 1,
+            ..Default::default()
+        };
+        let Some(latest) = storage.get_readings_range(&range).await?.readings.into_iter().next() else {
+            continue;
+        };
+        sensors.push(SensorStats { sensor_id: sensor.sensor_id, location: sensor.location, temperature, latest });
+    }
+
+    let mut by_pool: BTreeMap<String, Vec<PoolReading>> = BTreeMap::new();
+    for reading in storage.pool_readings(Some(from), Some(to)).await? {
+        by_pool.entry(reading.pool_id.clone()).or_default().push(reading);
+    }
+    let pools = by_pool
+        .into_iter()
+        .filter_map(|(pool_id, readings)| {
+            Some(PoolStats {
+                pool_id,
+                readings: readings.len(),
+                temperature: Summary::of(readings.iter().filter_map(|reading| reading.temperature)),
+                ph: Summary::of(readings.iter().filter_map(|reading| reading.ph)),
+                orp: Summary::of(readings.iter().filter_map(|reading| reading.orp.map(f64::from))),
+                // Readings come oldest first
+                latest: readings.last()?.clone(),
+            })
+        })
+        .collect();
+
+    Ok(Stats { from, to, sensors, pools })
+}
+
+/// Stored readings of one sensor or pool (`homemetrics query --sensor`)
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorReadings {
+    /// Sensor ID, or `pool/<id>` for a Blue Riot pool
+    pub sensor: String,
+    pub readings: ReadingSeries,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ReadingSeries {
+    Temperature(Vec<TemperatureReading>),
+    Pool(Vec<PoolReading>),
+}
+
+impl ReadingSeries {
+    pub fn len(&self) -> usize {
+        match self {
+            ReadingSeries::Temperature(readings) => readings.len(),
+            ReadingSeries::Pool(readings) => readings.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Readings of a sensor over `[from, to)`, oldest first
+///
+/// `pool/<id>` reads the pool table; sensor readings are fetched page by page.
+pub async fn collect_readings(storage: &dyn Storage, sensor: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<SensorReadings> {
+    let readings = match sensor.strip_prefix("pool/") {
+        Some(pool_id) => {
+            let mut readings = storage.pool_readings(Some(from), Some(to)).await?;
+            readings.retain(|reading| reading.pool_id == pool_id);
+            ReadingSeries::Pool(readings)
+        }
+        None => {
+            let mut range = ReadingRange {
+                sensor_id: sensor.to_string(),
+                from: Some(from),
+                to: Some(to),
+                page_size: MAX_PAGE_SIZE,
+                ..Default::default()
+            };
+            let mut readings = Vec::new();
+            loop {
+                let page = storage.get_readings_range(&range).await?;
+                readings.extend(page.readings);
+                match page.next {
+                    Some(next) => range.after = Some(next),
+                    None => break,
+                }
+            }
+            ReadingSeries::Temperature(readings)
+        }
+    };
+    Ok(SensorReadings { sensor: sensor.to_string(), readings })
+}
+
+/// Plain text table of the readings of a sensor, temperatures in the display units
+pub fn render_readings(readings: &SensorReadings, locale: Locale, units: UnitSystem) -> String {
+    if readings.readings.is_empty() {
+        return locale.no_readings(&readings.sensor);
+    }
+
+    let mut lines = vec![locale.readings_header(&readings.sensor, readings.readings.len())];
+    let temperature = |celsius: f64| format!("{:.1}", units.temperature(celsius));
+    let at = |timestamp: &DateTime<Utc>| timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    match &readings.readings {
+        ReadingSeries::Temperature(series) => {
+            let rows = series.iter().map(|reading| {
+                vec![
+                    at(&reading.timestamp),
+                    temperature(reading.temperature),
+                    optional(reading.humidity.map(|humidity| format!("{:.0}", humidity))),
+                ]
+            });
+            lines.extend(table(locale.readings_sensor_columns(units.temperature_symbol()), rows));
+        }
+        ReadingSeries::Pool(series) => {
+            let rows = series.iter().map(|reading| {
+                vec![
+                    at(&reading.timestamp),
+                    optional(reading.temperature.map(temperature)),
+                    optional(reading.ph.map(|ph| format!("{:.2}", ph))),
+                    optional(reading.orp.map(|orp| orp.to_string())),
+                ]
+            });
+            lines.extend(table(locale.readings_pool_columns(units.temperature_symbol()), rows));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Plain text tables of the stats, temperatures in the display units
+pub fn render_stats(stats: &Stats, locale: Locale, units: UnitSystem) -> String {
+    let mut lines = vec![locale.stats_header(
+        &stats.from.format("%Y-%m-%d %H:%M").to_string(),
+        &stats.to.format("%Y-%m-%d %H:%M UTC").to_string(),
+    )];
+    if stats.sensors.is_empty() && stats.pools.is_empty() {
+        lines.push(locale.no_stats().to_string());
+        return lines.join("\n");
+    }
+
+    let temperature = |celsius: f64| format!("{:.1}", units.temperature(celsius));
+    if !stats.sensors.is_empty() {
+        let header = locale.stats_sensor_columns(units.temperature_symbol());
+        let rows = stats.sensors.iter().map(|sensor| {
+            vec![
+                sensor.sensor_id.clone(),
+                sensor.location.clone().unwrap_or_default(),
+                sensor.temperature.count.to_string(),
+                temperature(sensor.temperature.min),
+                temperature(sensor.temperature.max),
+                temperature(sensor.temperature.avg),
+                temperature(sensor.latest.temperature),
+                sensor.latest.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+            ]
+        });
+        lines.push(String::new());
+        lines.extend(table(header, rows));
+    }
+
+    if !stats.pools.is_empty() {
+        let range = |summary: Option<Summary>, format: &dyn Fn(f64) -> String| match summary {
+            Some(summary) => format!("{} / {} / {}", format(summary.min), format(summary.max), format(summary.avg)),
+            None => "-".to_string(),
+        };
+        let header = locale.stats_pool_columns(units.temperature_symbol());
+        let rows = stats.pools.iter().map(|pool| {
+            vec![
+                pool.pool_id.clone(),
+                pool.readings.to_string(),
+                range(pool.temperature, &temperature),
+                range(pool.ph, &|ph| format!("{:.2}", ph)),
+                range(pool.orp, &|orp| format!("{:.0}", orp)),
+                pool.latest.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+            ]
+        });
+        lines.push(String::new());
+        lines.extend(table(header, rows));
+    }
+    lines.join("\n")
+}
+
+/// Left-aligned columns, as wide as their widest cell
+fn table(header: Vec<String>, rows: impl Iterator<Item = Vec<String>>) -> Vec<String> {
+    let mut widths = vec![0; header.len()];
+    let mut cells = vec![header];
+    cells.extend(rows);
+
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    cells
+        .iter()
+        .map(|row| {
+            let padded: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            format!("  {}", padded.join("  ").trim_end())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn test_collect_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let readings: Vec<_> = [(1, 8, 18.0), (1, 20, 21.0), (2, 8, 19.5), (9, 8, 30.0)]
+            .into_iter()
+            .map(|(day, hour, temperature)| TemperatureReading {
+                sensor_id: "salon".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2025, 3, day, hour, 0, 0).unwrap(),
+                temperature,
+                humidity: None,
+                location: None,
+            })
+            .collect();
+        storage.save_temperature_readings(&readings).await.unwrap();
+        for (day, ph) in [(1, 7.0), (2, 7.4)] {
+            let reading = PoolReading {
+                pool_id: "main".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2025, 3, day, 9, 0, 0).unwrap(),
+                temperature: Some(24.0),
+                ph: Some(ph),
+                orp: None,
+            };
+            storage.save_pool_reading(&reading, &format!("email-{}", day)).await.unwrap();
+        }
+
+        let from = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let stats = collect_stats(&storage, from, from + chrono::TimeDelta::days(7)).await.unwrap();
+        assert_eq!(stats.sensors.len(), 1);
+        let salon = &stats.sensors[0];
+        assert_eq!(salon.temperature, Summary { count: 3, min: 18.0, max: 21.0, avg: 19.5 });
+        assert_eq!(salon.latest.timestamp, readings[2].timestamp);
+
+        assert_eq!(stats.pools.len(), 1);
+        let pool = &stats.pools[0];
+        assert_eq!(pool.readings, 2);
+        assert!((pool.ph.unwrap().avg - 7.2).abs() < 1e-9);
+        assert_eq!(pool.orp, None);
+
+        let table = render_stats(&stats, Locale::En, UnitSystem::Metric);
+        assert!(table.contains("salon"), "{}", table);
+        assert!(table.contains("7.00 / 7.40 / 7.20"), "{}", table);
+    }
+
+    #[tokio::test]
+    async fn test_previous_period_averages() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let reading = |sensor_id: &str, hour, temperature| TemperatureReading {
+            sensor_id: sensor_id.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 3, 1, hour, 0, 0).unwrap(),
+            temperature,
+            humidity: None,
+            location: None,
+        };
+        storage.save_temperature_readings(&[reading("salon", 2, 15.0), reading("salon", 6, 18.0), reading("salon", 8, 20.0)]).await.unwrap();
+
+        // 12:00 → 18:00: compared with 06:00 → 12:00
+        let new = [reading("salon", 12, 21.0), reading("salon", 18, 22.0), reading("patio", 12, 9.0)];
+        let averages = previous_period_averages(&storage, &new).await.unwrap();
+        assert_eq!(averages.len(), 1);
+        assert!((averages["salon"] - 19.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_collect_readings() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let readings: Vec<_> = [(8, 18.0), (9, 18.5), (20, 21.0)]
+            .into_iter()
+            .map(|(hour, temperature)| TemperatureReading {
+                sensor_id: "patio".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2025, 3, 1, hour, 0, 0).unwrap(),
+                temperature,
+                humidity: Some(60.0),
+                location: None,
+            })
+            .collect();
+        storage.save_temperature_readings(&readings).await.unwrap();
+        let pool = PoolReading {
+            pool_id: "spa".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap(),
+            temperature: Some(36.0),
+            ph: Some(7.2),
+            orp: None,
+        };
+        storage.save_pool_reading(&pool, "email-1").await.unwrap();
+
+        let from = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        let to = from + chrono::TimeDelta::days(1);
+        let patio = collect_readings(&storage, "patio", from, to).await.unwrap();
+        let ReadingSeries::Temperature(series) = &patio.readings else { panic!("temperature series expected") };
+        assert_eq!(series.iter().map(|reading| reading.temperature).collect::<Vec<_>>(), [18.5, 21.0]);
+
+        let spa = collect_readings(&storage, "pool/spa", from, to).await.unwrap();
+        assert_eq!(spa.readings.len(), 1);
+        let table = render_readings(&spa, Locale::En, UnitSystem::Metric);
+        assert!(table.contains("2025-03-01 09:00:00  36.0              7.20  -"), "{}", table);
+        assert!(collect_readings(&storage, "pool/main", from, to).await.unwrap().readings.is_empty());
+
+        let json = serde_json::to_value(&patio).unwrap();
+        assert_eq!(json["sensor"], "patio");
+        assert_eq!(json["readings"][0]["temperature"], 18.5);
+    }
+}
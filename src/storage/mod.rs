@@ -0,0 +1,751 @@
+pub mod sqlite;
+pub(crate) mod watermarks;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::blueriot::PoolReading;
+use crate::database::SaveStats;
+use crate::email::Reading;
+use crate::error::ErrorClass;
+use crate::validation::RejectedReading;
+use crate::xsense::TemperatureReading;
+
+pub use sqlite::SqliteStorage;
+
+/// Database failure, split between outages and data the database refuses
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    /// Connection refused, lost or timed out
+    #[error("Unable to connect to the database")]
+    Unavailable(#[source] sqlx::Error),
+    /// Data exception or constraint violation (SQLSTATE classes 22 and 23)
+    #[error("Database rejected the data")]
+    Rejected(#[source] sqlx::Error),
+}
+
+impl StorageError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            StorageError::Unavailable(_) => ErrorClass::Transient,
+            StorageError::Rejected(_) => ErrorClass::Permanent,
+        }
+    }
+    
+    /// Class of a raw sqlx error: only refused data is permanent
+    pub fn sqlx_class(error: &sqlx::Error) -> ErrorClass {
+        let code = error.as_database_error().and_then(|e| e.code());
+        match code.as_deref() {
+            Some(code) if code.starts_with("22") || code.starts_with("23") => ErrorClass::Permanent,
+            _ => ErrorClass::Transient,
+        }
+    }
+}
+
+/// Committed writes of readings in this process (saves, replacements, deletions)
+///
+/// Lets the HTTP response cache drop what a scheduled run, the drop directory
+/// or the server itself changed. Writes of other processes are not seen.
+static READING_WRITES: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn record_reading_write() {
+    READING_WRITES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Changes whenever readings are written by this process
+pub(crate) fn reading_writes() -> u64 {
+    READING_WRITES.load(Ordering::Relaxed)
+}
+
+/// Future returned by storage operations
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Table layout used for readings (`STORAGE_LAYOUT`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageLayout {
+    /// One table per domain: `temperature_readings`, `pool_readings`
+    #[default]
+    Domain,
+    /// A single `readings(sensor_id, timestamp, metric, value)` table
+    Generic,
+}
+
+impl StorageLayout {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "domain" => Some(StorageLayout::Domain),
+            "generic" => Some(StorageLayout::Generic),
+            _ => None,
+        }
+    }
+}
+
+/// Readings older than the latest stored one of their sensor by more than this are assumed
+/// already stored when the history skip is enabled: overlapping X-Sense exports are then only
+/// checked row by row near the boundary
+pub(crate) const DELTA_BOUNDARY_WINDOW: TimeDelta = TimeDelta::hours(24);
+
+/// How a temperature reading compares with the latest stored timestamp of its sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DeltaCheck {
+    /// After the latest stored reading: new, no duplicate query needed
+    New,
+    /// Within the boundary window: checked against the database
+    Check,
+    /// Before the boundary window with the history skip, or repeated in the batch: already stored
+    Known,
+}
+
+/// Classify readings against the latest stored timestamp per sensor (`MAX(timestamp)`)
+///
+/// A reading repeated within the batch after the boundary is only `New` once. Without
+/// `skip_history`, readings before the boundary window are checked like the ones within it.
+pub(crate) fn delta_checks(readings: &[TemperatureReading], latest: &HashMap<String, DateTime<Utc>>,
+                           skip_history: bool) -> Vec<DeltaCheck> {
+    let mut seen = HashSet::new();
+    readings
+        .iter()
+        .map(|reading| match latest.get(&reading.sensor_id) {
+            Some(&latest) if skip_history && reading.timestamp <= latest - DELTA_BOUNDARY_WINDOW => DeltaCheck::Known,
+            Some(&latest) if reading.timestamp <= latest => DeltaCheck::Check,
+            _ if seen.insert((reading.sensor_id.as_str(), reading.timestamp)) => DeltaCheck::New,
+            _ => DeltaCheck::Known,
+        })
+        .collect()
+}
+
+/// Content an extractor could not parse, kept in `failed_extractions`
+///
+/// Stored once per email and file (a later failure replaces the error), so a
+/// parser bug can be reproduced from the real data.
+#[derive(Debug, Clone)]
+pub struct FailedExtraction {
+    /// "xsense" or "blueriot"
+    pub source: String,
+    pub email_id: String,
+    /// Attachment name, empty for an email body
+    pub filename: String,
+    pub content: Vec<u8>,
+    pub error: String,
+}
+
+/// An email waiting in the retry queue (`email_retries`)
+#[derive(Debug, Clone)]
+pub struct EmailRetry {
+    /// "xsense" or "blueriot"
+    pub source: String,
+    pub email_id: String,
+    /// Number of failed attempts so far
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: String,
+    /// Set once the email exhausted its attempts; it is no longer retried
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+}
+
+impl EmailRetry {
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.dead_lettered_at.is_none() && self.next_attempt_at <= now
+    }
+}
+
+/// `email_retries` columns as selected by both storages
+pub(crate) type RetryRow = (String, String, i64, DateTime<Utc>, String, Option<DateTime<Utc>>);
+
+pub(crate) fn retry_from_row((source, email_id, attempts, next_attempt_at, last_error, dead_lettered_at): RetryRow) -> EmailRetry {
+    EmailRetry {
+        source,
+        email_id,
+        attempts: attempts.max(0) as u32,
+        next_attempt_at,
+        last_error,
+        dead_lettered_at,
+    }
+}
+
+/// Email activity of a source (`source_activity`), used to detect a broken export
+#[derive(Debug, Clone)]
+pub struct SourceActivity {
+    /// "xsense" or "blueriot"
+    pub source: String,
+    /// Last run that found emails, or the first recorded run
+    pub last_email_at: DateTime<Utc>,
+    /// Consecutive runs without any email
+    pub empty_runs: u32,
+    /// When the "no emails" warning was sent, reset once emails come back
+    pub alerted_at: Option<DateTime<Utc>>,
+    /// Last recorded run, with or without emails
+    pub last_run_at: DateTime<Utc>,
+}
+
+impl SourceActivity {
+    pub fn days_without_email(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.last_email_at).num_days()
+    }
+}
+
+/// `source_activity` columns as selected by both storages
+pub(crate) type ActivityRow = (String, DateTime<Utc>, i64, Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+pub(crate) fn activity_from_row((source, last_email_at, empty_runs, alerted_at, updated_at): ActivityRow) -> SourceActivity {
+    SourceActivity {
+        source,
+        last_email_at,
+        empty_runs: empty_runs.max(0) as u32,
+        alerted_at,
+        last_run_at: updated_at.unwrap_or(last_email_at),
+    }
+}
+
+/// Where the last run of a source capped by `BATCH_SIZE` stopped (`batch_checkpoints`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchCheckpoint {
+    /// "xsense" or "blueriot"
+    pub source: String,
+    /// Last email examined by the run
+    pub email_id: String,
+    /// Gmail internal date of that email, in milliseconds since the epoch
+    pub email_date: i64,
+}
+
+/// Delivery result of an outbound notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Sent,
+    /// Not delivered yet, retried at `next_attempt_at`
+    Failed,
+    /// Still failing once too old to be worth delivering (`SLACK_RETRY_EXPIRY_HOURS`)
+    Expired,
+}
+
+impl DeliveryStatus {
+    pub fn key(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Expired => "expired",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "sent" => DeliveryStatus::Sent,
+            "expired" => DeliveryStatus::Expired,
+            _ => DeliveryStatus::Failed,
+        }
+    }
+}
+
+/// An outbound Slack message (`notifications`), kept for auditing and redelivery
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationRecord {
+    /// Row id, 0 until recorded
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub channel: String,
+    /// What the message is about ("readings", "alert", "error", ...)
+    pub kind: String,
+    /// "xsense", "blueriot" or "ingest", None outside the email pipeline
+    pub source: Option<String>,
+    /// Email id or alert the message is about
+    pub related_id: Option<String>,
+    /// Text posted, secrets redacted
+    pub text: String,
+    /// Message this one replies to (`SLACK_MODE=run` threads)
+    pub thread_ts: Option<String>,
+    pub status: DeliveryStatus,
+    pub error: Option<String>,
+    /// Slack timestamp of the posted message, None when it failed
+    pub message_ts: Option<String>,
+    /// Delivery attempts so far
+    pub attempts: u32,
+    /// When a failed message is retried, None once sent or expired
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
+/// `notifications` columns as selected by both storages
+pub(crate) type NotificationRow = (i64, DateTime<Utc>, String, String, Option<String>, Option<String>, String,
+                                   Option<String>, String, Option<String>, Option<String>, i32, Option<DateTime<Utc>>);
+
+pub(crate) fn notification_from_row(row: NotificationRow) -> NotificationRecord {
+    let (id, created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts, attempts, next_attempt_at) = row;
+    NotificationRecord {
+        id,
+        created_at,
+        channel,
+        kind,
+        source,
+        related_id,
+        text,
+        thread_ts,
+        status: DeliveryStatus::parse(&status),
+        error,
+        message_ts,
+        attempts: attempts.max(0) as u32,
+        next_attempt_at,
+    }
+}
+
+/// Gmail API requests of a source on one UTC day (`api_usage`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiUsage {
+    pub day: NaiveDate,
+    /// "xsense" or "blueriot"
+    pub source: String,
+    /// Requests issued that day
+    pub calls: u64,
+    /// Runs that issued them
+    pub runs: u32,
+}
+
+/// `api_usage` columns as selected by both storages
+pub(crate) type ApiUsageRow = (NaiveDate, String, i64, i64);
+
+pub(crate) fn api_usage_from_row((day, source, calls, runs): ApiUsageRow) -> ApiUsage {
+    ApiUsage {
+        day,
+        source,
+        calls: calls.max(0) as u64,
+        runs: runs.max(0) as u32,
+    }
+}
+
+/// Readings of one sensor brought by one attachment, or email body, in a run (`reading_provenance`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProvenanceRecord {
+    pub sensor_id: String,
+    /// "xsense" or "blueriot"
+    pub source: String,
+    pub email_id: String,
+    /// Attachment name, None for readings of the email body
+    pub file: Option<String>,
+    /// SHA-256 of the attachment, lowercase hex
+    pub file_hash: Option<String>,
+    pub first_timestamp: DateTime<Utc>,
+    pub last_timestamp: DateTime<Utc>,
+    pub readings: u32,
+    /// Start of the run that stored them
+    pub run_started_at: DateTime<Utc>,
+}
+
+/// One record per sensor, email and attachment of the stored readings
+pub(crate) fn provenance_records(readings: &[Reading], run_started_at: DateTime<Utc>) -> Vec<ProvenanceRecord> {
+    let mut records: Vec<ProvenanceRecord> = Vec::new();
+    for reading in readings {
+        let Some(email_id) = &reading.provenance.email_id else {
+            continue;
+        };
+        let existing = records.iter_mut().find(|record| {
+            record.sensor_id == reading.sensor_id && &record.email_id == email_id
+                && record.file == reading.provenance.file && record.file_hash == reading.provenance.file_hash
+        });
+        match existing {
+            Some(record) => {
+                record.first_timestamp = record.first_timestamp.min(reading.timestamp);
+                record.last_timestamp = record.last_timestamp.max(reading.timestamp);
+                record.readings += 1;
+            }
+            None => records.push(ProvenanceRecord {
+                sensor_id: reading.sensor_id.clone(),
+                source: reading.provenance.source.clone(),
+                email_id: email_id.clone(),
+                file: reading.provenance.file.clone(),
+                file_hash: reading.provenance.file_hash.clone(),
+                first_timestamp: reading.timestamp,
+                last_timestamp: reading.timestamp,
+                readings: 1,
+                run_started_at,
+            }),
+        }
+    }
+    records
+}
+
+/// `reading_provenance` columns as selected by both storages
+pub(crate) type SpanRow = (String, Option<String>, DateTime<Utc>, DateTime<Utc>);
+
+pub(crate) type ProvenanceRow = (String, String, String, Option<String>, Option<String>, DateTime<Utc>, DateTime<Utc>, i64, DateTime<Utc>);
+
+pub(crate) fn provenance_from_row((sensor_id, source, email_id, file, file_hash, first_timestamp, last_timestamp, readings, run_started_at): ProvenanceRow) -> ProvenanceRecord {
+    ProvenanceRecord {
+        sensor_id,
+        source,
+        email_id,
+        file,
+        file_hash,
+        first_timestamp,
+        last_timestamp,
+        readings: readings.max(0) as u32,
+        run_started_at,
+    }
+}
+
+/// Readings removed by `homemetrics db delete`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeleteScope {
+    /// Readings brought by one email
+    Email(String),
+    /// Readings brought by one attachment, from its SHA-256 or a prefix of it
+    Attachment(String),
+    /// Readings of a sensor (`pool/<id>` for a pool) over `[from, to]`
+    Sensor { sensor_id: String, from: DateTime<Utc>, to: DateTime<Utc> },
+}
+
+/// Readings deleted, or that would be, per sensor
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeletedReadings {
+    pub per_sensor: BTreeMap<String, usize>,
+    /// `reading_provenance` rows removed with them
+    pub provenance: usize,
+}
+
+impl DeletedReadings {
+    pub fn total(&self) -> usize {
+        self.per_sensor.values().sum()
+    }
+
+    /// Count the deleted rows, one per sensor and timestamp (the generic layout has a row per metric)
+    pub(crate) fn add(&mut self, rows: Vec<(String, DateTime<Utc>)>) {
+        let rows: BTreeSet<(String, DateTime<Utc>)> = rows.into_iter().collect();
+        for (sensor_id, _) in rows {
+            *self.per_sensor.entry(sensor_id).or_default() += 1;
+        }
+    }
+}
+
+/// Readings per page when `ReadingRange::page_size` is not set
+const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Largest page returned by `get_readings_range`
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Order of a reading range, by timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "asc" => Some(SortOrder::Asc),
+            "desc" => Some(SortOrder::Desc),
+            _ => None,
+        }
+    }
+
+    /// `ORDER BY` direction and the comparison selecting rows after the cursor
+    pub(crate) fn sql(&self) -> (&'static str, &'static str) {
+        match self {
+            SortOrder::Asc => ("ASC", ">"),
+            SortOrder::Desc => ("DESC", "<"),
+        }
+    }
+}
+
+/// Temperature readings of one sensor over `[from, to)`, read one page at a time
+///
+/// Pages use keyset pagination: `after` is the `next` cursor of the previous
+/// page, so large ranges are read incrementally without OFFSET scans.
+#[derive(Debug, Clone, Default)]
+pub struct ReadingRange {
+    pub sensor_id: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub order: SortOrder,
+    /// Readings per page (default 100, at most `MAX_PAGE_SIZE`)
+    pub page_size: u32,
+    /// Timestamp of the last reading of the previous page
+    pub after: Option<DateTime<Utc>>,
+}
+
+impl ReadingRange {
+    pub(crate) fn page_size(&self) -> u32 {
+        match self.page_size {
+            0 => DEFAULT_PAGE_SIZE,
+            size => size.min(MAX_PAGE_SIZE),
+        }
+    }
+}
+
+/// One page of a reading range
+#[derive(Debug, Clone, Default)]
+pub struct ReadingPage {
+    pub readings: Vec<TemperatureReading>,
+    /// Cursor of the next page, None on the last one
+    pub next: Option<DateTime<Utc>>,
+}
+
+/// Reading columns as selected by both storages, in either layout
+pub(crate) type ReadingRow = (String, DateTime<Utc>, f64, Option<f64>, Option<String>);
+
+/// Page from rows fetched with one extra row, which tells whether another page follows
+pub(crate) fn reading_page(rows: Vec<ReadingRow>, page_size: u32) -> ReadingPage {
+    let mut readings: Vec<TemperatureReading> = rows
+        .into_iter()
+        .map(|(sensor_id, timestamp, temperature, humidity, location)| TemperatureReading {
+            sensor_id,
+            timestamp,
+            temperature,
+            humidity,
+            location,
+        })
+        .collect();
+
+    let next = if readings.len() > page_size as usize {
+        readings.truncate(page_size as usize);
+        readings.last().map(|reading| reading.timestamp)
+    } else {
+        None
+    };
+    ReadingPage { readings, next }
+}
+
+/// Width of the buckets of `get_bucketed_readings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    /// `1m`, `1h` or `1d`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "1m" | "minute" => Some(Bucket::Minute),
+            "1h" | "hour" => Some(Bucket::Hour),
+            "1d" | "day" => Some(Bucket::Day),
+            _ => None,
+        }
+    }
+
+    /// PostgreSQL unit, for `time_bucket` intervals and `date_trunc`
+    pub(crate) fn unit(&self) -> &'static str {
+        match self {
+            Bucket::Minute => "minute",
+            Bucket::Hour => "hour",
+            Bucket::Day => "day",
+        }
+    }
+
+    /// SQLite `strftime` format truncating a timestamp to its bucket (UTC)
+    pub(crate) fn strftime_format(&self) -> &'static str {
+        match self {
+            Bucket::Minute => "%Y-%m-%dT%H:%M:00Z",
+            Bucket::Hour => "%Y-%m-%dT%H:00:00Z",
+            Bucket::Day => "%Y-%m-%dT00:00:00Z",
+        }
+    }
+}
+
+/// Temperature statistics of a sensor over one bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingBucket {
+    /// Start of the bucket (UTC)
+    pub start: DateTime<Utc>,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+/// Bucket columns as selected by both storages
+pub(crate) type BucketRow = (DateTime<Utc>, i64, f64, f64, f64);
+
+pub(crate) fn bucket_from_row((start, count, min, max, avg): BucketRow) -> ReadingBucket {
+    ReadingBucket { start, count: count.max(0) as u64, min, max, avg }
+}
+
+/// Row of the `sensors` table
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorInfo {
+    pub sensor_id: String,
+    pub location: Option<String>,
+    pub site: Option<String>,
+    pub sensor_type: Option<String>,
+    pub unit: Option<String>,
+}
+
+/// `sensors` columns as selected by both storages
+pub(crate) type SensorRow = (String, Option<String>, Option<String>, Option<String>, Option<String>);
+
+pub(crate) fn sensor_from_row((sensor_id, location, site, sensor_type, unit): SensorRow) -> SensorInfo {
+    SensorInfo { sensor_id, location, site, sensor_type, unit }
+}
+
+/// Pool reading columns as selected by both storages, in either layout
+pub(crate) type PoolRow = (String, DateTime<Utc>, Option<f64>, Option<f64>, Option<i32>);
+
+pub(crate) fn pool_from_row((pool_id, timestamp, temperature, ph, orp): PoolRow) -> PoolReading {
+    PoolReading { pool_id, timestamp, temperature, ph, orp }
+}
+
+/// Kind of sensor stored in `sensors.sensor_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorType {
+    Temperature,
+    Humidity,
+    Pool,
+    Energy,
+    Water,
+    Event,
+}
+
+impl SensorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SensorType::Temperature => "temperature",
+            SensorType::Humidity => "humidity",
+            SensorType::Pool => "pool",
+            SensorType::Energy => "energy",
+            SensorType::Water => "water",
+            SensorType::Event => "event",
+        }
+    }
+
+    /// Unit of the stored values, None when a sensor reports several metrics
+    pub fn unit(&self) -> Option<&'static str> {
+        match self {
+            SensorType::Temperature => Some("°C"),
+            SensorType::Humidity => Some("%"),
+            SensorType::Pool => None,
+            SensorType::Energy => Some("kWh"),
+            SensorType::Water => Some("m³"),
+            SensorType::Event => None,
+        }
+    }
+}
+
+/// Where the processing strategies write their readings
+///
+/// Implemented by the TimescaleDB `Database` and by `SqliteStorage`
+/// (dry-run sandbox), so the pipeline runs unchanged against either one.
+pub trait Storage: Send + Sync {
+    /// Save temperature readings, skipping those already stored
+    fn save_temperature_readings<'a>(&'a self, readings: &'a [TemperatureReading]) -> StorageFuture<'a, SaveStats>;
+
+    /// Save a pool reading, returns false when this email was already stored for its pool
+    fn save_pool_reading<'a>(&'a self, reading: &'a PoolReading, email_id: &'a str) -> StorageFuture<'a, bool>;
+
+    /// Count new vs already stored readings without writing anything
+    fn diff_temperature_readings<'a>(&'a self, readings: &'a [TemperatureReading]) -> StorageFuture<'a, SaveStats>;
+
+    /// Check whether a reading of this pool from this email is already stored
+    fn pool_reading_exists<'a>(&'a self, pool_id: &'a str, email_id: &'a str) -> StorageFuture<'a, bool>;
+
+    /// Create the sensor if needed and set its type and unit
+    fn register_sensor<'a>(&'a self, sensor_id: &'a str, sensor_type: SensorType) -> StorageFuture<'a, ()>;
+
+    /// Keep readings refused by validation, with the reason
+    fn save_rejected_readings<'a>(&'a self, rejected: &'a [RejectedReading]) -> StorageFuture<'a, usize>;
+
+    /// Keep the raw content of a failed extraction
+    fn save_failed_extraction<'a>(&'a self, failure: &'a FailedExtraction) -> StorageFuture<'a, ()>;
+
+    /// Emails of a source waiting in the retry queue
+    fn email_retries<'a>(&'a self, source: &'a str) -> StorageFuture<'a, Vec<EmailRetry>>;
+
+    /// Insert or update the retry entry of a failed email
+    fn schedule_email_retry<'a>(&'a self, retry: &'a EmailRetry) -> StorageFuture<'a, ()>;
+
+    /// Remove an email from the retry queue once processed
+    fn clear_email_retry<'a>(&'a self, source: &'a str, email_id: &'a str) -> StorageFuture<'a, ()>;
+
+    /// Dead-lettered emails of every source, most recent first
+    fn dead_letters<'a>(&'a self) -> StorageFuture<'a, Vec<EmailRetry>>;
+
+    /// Put a dead-lettered email back in the queue, due now with its attempts reset
+    ///
+    /// Returns the requeued entry, None when the email is not dead-lettered.
+    fn requeue_email<'a>(&'a self, email_id: &'a str) -> StorageFuture<'a, Option<EmailRetry>>;
+
+    /// Record a run of a source: emails found reset the activity, otherwise an empty run is counted
+    fn record_source_run<'a>(&'a self, source: &'a str, found_emails: bool) -> StorageFuture<'a, SourceActivity>;
+
+    /// Activity of every source of the site, by source name
+    fn source_activities<'a>(&'a self) -> StorageFuture<'a, Vec<SourceActivity>>;
+
+    /// Remember that the "no emails" warning of a source was sent
+    fn mark_source_alerted<'a>(&'a self, source: &'a str) -> StorageFuture<'a, ()>;
+
+    /// Remember an email processed without changing its labels (read-only Gmail access)
+    fn record_processed_email<'a>(&'a self, source: &'a str, email_id: &'a str) -> StorageFuture<'a, ()>;
+
+    /// Emails of a source recorded by `record_processed_email`
+    fn processed_emails<'a>(&'a self, source: &'a str) -> StorageFuture<'a, HashSet<String>>;
+
+    /// Where the previous batch of a source stopped, None when it reached the end
+    fn batch_checkpoint<'a>(&'a self, source: &'a str) -> StorageFuture<'a, Option<BatchCheckpoint>>;
+
+    /// Record the last email examined by a run capped by `BATCH_SIZE`
+    fn save_batch_checkpoint<'a>(&'a self, checkpoint: &'a BatchCheckpoint) -> StorageFuture<'a, ()>;
+
+    /// Forget the checkpoint of a source, the next run starts from the oldest email again
+    fn clear_batch_checkpoint<'a>(&'a self, source: &'a str) -> StorageFuture<'a, ()>;
+
+    /// Add the Gmail API requests of a run to today's usage (UTC)
+    ///
+    /// Returns the requests issued today by all sources of the site.
+    fn record_api_calls<'a>(&'a self, source: &'a str, calls: u64) -> StorageFuture<'a, u64>;
+
+    /// Gmail API usage of the last `days` days, most recent first
+    fn api_usage<'a>(&'a self, days: u32) -> StorageFuture<'a, Vec<ApiUsage>>;
+
+    /// Keep an outbound notification with its delivery result, returns its id
+    fn record_notification<'a>(&'a self, notification: &'a NotificationRecord) -> StorageFuture<'a, i64>;
+
+    /// Latest notifications, most recent first, only the failed ones with `failed_only`
+    fn notifications<'a>(&'a self, failed_only: bool, limit: usize) -> StorageFuture<'a, Vec<NotificationRecord>>;
+
+    /// Failed notifications due for a retry at `now`, oldest first
+    ///
+    /// Their next attempt is pushed to `now + lease`, so two processes sharing
+    /// the database never redeliver the same message.
+    fn claim_notifications<'a>(&'a self, now: DateTime<Utc>, lease: TimeDelta, limit: usize) -> StorageFuture<'a, Vec<NotificationRecord>>;
+
+    /// Store the outcome of a redelivery: status, error, message ts, attempts and next attempt
+    fn update_notification<'a>(&'a self, notification: &'a NotificationRecord) -> StorageFuture<'a, ()>;
+
+    /// Known sensors, sorted by id
+    fn sensors<'a>(&'a self) -> StorageFuture<'a, Vec<SensorInfo>>;
+
+    /// Pool readings over `[from, to)` of every pool, oldest first
+    fn pool_readings<'a>(&'a self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> StorageFuture<'a, Vec<PoolReading>>;
+
+    /// One page of the temperature readings of a sensor over a time range
+    fn get_readings_range<'a>(&'a self, range: &'a ReadingRange) -> StorageFuture<'a, ReadingPage>;
+
+    /// Min/max/average temperature of a sensor per bucket over `[from, to)`, oldest first
+    fn get_bucketed_readings<'a>(&'a self, sensor_id: &'a str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>,
+                                 bucket: Bucket) -> StorageFuture<'a, Vec<ReadingBucket>>;
+
+    /// First and latest reading of the sensor's current stay in the mold risk zone, None outside it
+    fn mold_risk_streak<'a>(&'a self, sensor_id: &'a str) -> StorageFuture<'a, Option<(DateTime<Utc>, DateTime<Utc>)>>;
+
+    /// Record the mold alert of a stay in the risk zone, returns false when it was already sent
+    fn claim_mold_alert<'a>(&'a self, sensor_id: &'a str, since: DateTime<Utc>) -> StorageFuture<'a, bool>;
+
+    /// Keep which email and attachment brought the readings stored by a run
+    fn record_provenance<'a>(&'a self, records: &'a [ProvenanceRecord]) -> StorageFuture<'a, ()>;
+
+    /// Provenance of the readings of a sensor overlapping `[from, to]`, oldest run first
+    fn provenance<'a>(&'a self, sensor_id: &'a str, from: DateTime<Utc>, to: DateTime<Utc>) -> StorageFuture<'a, Vec<ProvenanceRecord>>;
+
+    /// Delete the readings of a scope in one transaction, rolled back unless `apply`
+    ///
+    /// Emails and attachments are resolved through `reading_provenance`: the
+    /// readings of each recorded sensor and time span are deleted, and pool
+    /// readings by their email. Returns what was (or would be) deleted.
+    fn delete_readings<'a>(&'a self, scope: &'a DeleteScope, apply: bool) -> StorageFuture<'a, DeletedReadings>;
+}
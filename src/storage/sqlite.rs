@@ -0,0 +1,1898 @@
+use anyhow::{Result, Context};
+use log::{info, debug};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::blueriot::PoolReading;
+use crate::database::SaveStats;
+use crate::email::common::{pool_metrics, temperature_metrics};
+use crate::mold::{mold_risk, RISK_THRESHOLD};
+use crate::validation::RejectedReading;
+use crate::trace::parse_hash;
+use crate::xsense::TemperatureReading;
+use super::{activity_from_row, api_usage_from_row, bucket_from_row, pool_from_row, sensor_from_row, PoolRow, SensorInfo, SensorRow, delta_checks, Bucket, BucketRow, ReadingBucket, reading_page, ReadingPage, ReadingRange, ReadingRow, retry_from_row, ActivityRow, ApiUsage, BatchCheckpoint, ApiUsageRow, DeltaCheck, EmailRetry, RetryRow, FailedExtraction, notification_from_row, NotificationRecord, NotificationRow, DeleteScope, DeletedReadings, provenance_from_row, ProvenanceRecord, ProvenanceRow, SpanRow, SensorType, SourceActivity, Storage, StorageFuture, StorageLayout};
+
+/// SQLite storage used by `--dry-run-db` as a throwaway sandbox
+///
+/// Mirrors the TimescaleDB tables (without hypertables/extensions) so the
+/// rows the pipeline would write can be inspected with any SQLite browser.
+/// The file is created if missing; existing rows are kept, so successive
+/// sandbox runs show the same duplicate detection as production.
+pub struct SqliteStorage {
+    pool: SqlitePool,
+    site: Option<String>,
+    layout: StorageLayout,
+    skip_history: bool,
+}
+
+impl SqliteStorage {
+    pub async fn open(path: &Path) -> Result<Self> {
+        info!("Opening SQLite sandbox database {}", path.display());
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .with_context(|| format!("Unable to open SQLite database {}", path.display()))?;
+
+        let storage = SqliteStorage { pool, site: None, layout: StorageLayout::Domain, skip_history: false };
+        storage.create_tables_if_not_exists().await?;
+
+        Ok(storage)
+    }
+
+    /// Stamp written rows with this site, like `Database::with_site`
+    pub fn with_site(mut self, site: Option<String>) -> Self {
+        self.site = site;
+        self
+    }
+
+    /// Write readings with this layout, like `STORAGE_LAYOUT` in production
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Skip readings before the boundary window, like `Database::with_history_skip`
+    pub fn with_history_skip(mut self, skip_history: bool) -> Self {
+        self.skip_history = skip_history;
+        self
+    }
+
+    async fn create_tables_if_not_exists(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sensors (
+                sensor_id TEXT PRIMARY KEY NOT NULL,
+                location TEXT,
+                site TEXT,
+                sensor_type TEXT,
+                unit TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create sensors table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS temperature_readings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                sensor_id TEXT NOT NULL REFERENCES sensors(sensor_id) ON DELETE CASCADE,
+                timestamp TEXT NOT NULL,
+                temperature REAL NOT NULL,
+                humidity REAL,
+                mold_risk REAL,
+                location TEXT,
+                site TEXT,
+                processed_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create temperature_readings table")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_temp_readings_sensor_time ON temperature_readings (sensor_id, timestamp)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on sensor_id and timestamp")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pool_readings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                temperature REAL,
+                ph REAL,
+                orp INTEGER,
+                email_id TEXT,
+                pool_id TEXT NOT NULL DEFAULT 'main',
+                site TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create pool_readings table")?;
+
+        // Sandbox files created before multi-pool support (fails when the column exists)
+        let _ = sqlx::query("ALTER TABLE pool_readings ADD COLUMN pool_id TEXT NOT NULL DEFAULT 'main'")
+            .execute(&self.pool)
+            .await;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_pool_readings_email ON pool_readings (email_id)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on pool_readings email_id")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rejected_readings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                sensor_id TEXT,
+                timestamp TEXT,
+                payload TEXT,
+                reason TEXT NOT NULL,
+                email_id TEXT,
+                site TEXT,
+                rejected_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create rejected_readings table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS failed_extractions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                email_id TEXT NOT NULL,
+                filename TEXT NOT NULL DEFAULT '',
+                content BLOB NOT NULL,
+                error TEXT NOT NULL,
+                site TEXT,
+                failed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (source, email_id, filename)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create failed_extractions table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS email_retries (
+                source TEXT NOT NULL,
+                email_id TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                next_attempt_at TEXT NOT NULL,
+                last_error TEXT NOT NULL,
+                dead_lettered_at TEXT,
+                site TEXT,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (source, email_id)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create email_retries table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS source_activity (
+                source TEXT NOT NULL,
+                site TEXT NOT NULL DEFAULT '',
+                last_email_at TEXT NOT NULL,
+                empty_runs INTEGER NOT NULL DEFAULT 0,
+                alerted_at TEXT,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (source, site)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create source_activity table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mold_alerts (
+                sensor_id TEXT NOT NULL,
+                site TEXT NOT NULL DEFAULT '',
+                since TEXT NOT NULL,
+                alerted_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (sensor_id, site, since)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create mold_alerts table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS processed_emails (
+                source TEXT NOT NULL,
+                email_id TEXT NOT NULL,
+                site TEXT NOT NULL DEFAULT '',
+                processed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (source, email_id, site)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create processed_emails table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS batch_checkpoints (
+                source TEXT NOT NULL,
+                site TEXT NOT NULL DEFAULT '',
+                email_id TEXT NOT NULL,
+                email_date INTEGER NOT NULL,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (source, site)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create batch_checkpoints table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_usage (
+                day TEXT NOT NULL,
+                source TEXT NOT NULL,
+                site TEXT NOT NULL DEFAULT '',
+                calls INTEGER NOT NULL DEFAULT 0,
+                runs INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (day, source, site)
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create api_usage table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS notifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                source TEXT,
+                related_id TEXT,
+                text TEXT NOT NULL,
+                thread_ts TEXT,
+                status TEXT NOT NULL,
+                error TEXT,
+                message_ts TEXT,
+                attempts INTEGER NOT NULL DEFAULT 1,
+                next_attempt_at TEXT,
+                site TEXT
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create notifications table")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS reading_provenance (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                sensor_id TEXT NOT NULL,
+                source TEXT NOT NULL,
+                email_id TEXT NOT NULL,
+                file TEXT,
+                file_hash TEXT,
+                first_timestamp TEXT NOT NULL,
+                last_timestamp TEXT NOT NULL,
+                readings INTEGER NOT NULL,
+                run_started_at TEXT NOT NULL,
+                site TEXT,
+                recorded_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create reading_provenance table")?;
+
+        // Generic layout table, always created since the layout is chosen after opening
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS readings (
+                sensor_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                metric TEXT NOT NULL,
+                value REAL NOT NULL,
+                site TEXT,
+                email_id TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create readings table")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_readings_sensor_metric_time ON readings (sensor_id, metric, timestamp)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create index on readings")?;
+
+        // Sandbox files created before the site label (fails when the column exists)
+        for table in ["sensors", "temperature_readings", "pool_readings"] {
+            let _ = sqlx::query(&format!("ALTER TABLE {} ADD COLUMN site TEXT", table))
+                .execute(&self.pool)
+                .await;
+        }
+        let _ = sqlx::query("ALTER TABLE email_retries ADD COLUMN dead_lettered_at TEXT")
+            .execute(&self.pool)
+            .await;
+        let _ = sqlx::query("ALTER TABLE temperature_readings ADD COLUMN mold_risk REAL")
+            .execute(&self.pool)
+            .await;
+        for column in ["sensor_type", "unit"] {
+            let _ = sqlx::query(&format!("ALTER TABLE sensors ADD COLUMN {} TEXT", column))
+                .execute(&self.pool)
+                .await;
+        }
+        for column in ["attempts INTEGER NOT NULL DEFAULT 1", "next_attempt_at TEXT"] {
+            let _ = sqlx::query(&format!("ALTER TABLE notifications ADD COLUMN {}", column))
+                .execute(&self.pool)
+                .await;
+        }
+        let _ = sqlx::query("ALTER TABLE reading_provenance ADD COLUMN file_hash TEXT")
+            .execute(&self.pool)
+            .await;
+
+        Ok(())
+    }
+
+    async fn reading_exists(&self, reading: &TemperatureReading) -> Result<bool> {
+        let query = match self.layout {
+            StorageLayout::Domain => "SELECT EXISTS(SELECT 1 FROM temperature_readings WHERE sensor_id = ?1 AND timestamp = ?2 AND site IS ?3)",
+            StorageLayout::Generic => "SELECT EXISTS(SELECT 1 FROM readings WHERE sensor_id = ?1 AND timestamp = ?2 AND metric = 'temperature' AND site IS ?3)",
+        };
+        sqlx::query_scalar::<_, bool>(query)
+        .bind(&reading.sensor_id)
+        .bind(reading.timestamp)
+        .bind(&self.site)
+        .fetch_one(&self.pool)
+        .await
+        .context("Error checking for duplicates")
+    }
+
+    async fn latest_temperature_timestamps(&self, readings: &[TemperatureReading]) -> Result<HashMap<String, DateTime<Utc>>> {
+        let query = match self.layout {
+            StorageLayout::Domain => "SELECT MAX(timestamp) FROM temperature_readings WHERE sensor_id = ?1 AND site IS ?2",
+            StorageLayout::Generic => "SELECT MAX(timestamp) FROM readings WHERE sensor_id = ?1 AND metric = 'temperature' AND site IS ?2",
+        };
+
+        let sensors: HashSet<&str> = readings.iter().map(|reading| reading.sensor_id.as_str()).collect();
+        let mut latest = HashMap::new();
+        for sensor_id in sensors {
+            let timestamp = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(query)
+                .bind(sensor_id)
+                .bind(&self.site)
+                .fetch_one(&self.pool)
+                .await
+                .context("Error reading the latest sensor timestamp")?;
+            if let Some(timestamp) = timestamp {
+                latest.insert(sensor_id.to_string(), timestamp);
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Whether a reading is stored, querying only readings not classified by `delta_checks`
+    async fn reading_known(&self, reading: &TemperatureReading, check: DeltaCheck) -> Result<bool> {
+        match check {
+            DeltaCheck::New => Ok(false),
+            DeltaCheck::Known => Ok(true),
+            DeltaCheck::Check => self.reading_exists(reading).await,
+        }
+    }
+
+    async fn save_temperature_readings_impl(&self, readings: &[TemperatureReading]) -> Result<SaveStats> {
+        let mut stats = SaveStats::default();
+        let checks = delta_checks(readings, &self.latest_temperature_timestamps(readings).await?, self.skip_history);
+
+        for (reading, check) in readings.iter().zip(checks) {
+            sqlx::query(
+                r#"
+                INSERT INTO sensors (sensor_id, location, site) VALUES (?1, ?2, ?3)
+                ON CONFLICT (sensor_id) DO UPDATE SET
+                    location = COALESCE(sensors.location, excluded.location),
+                    site = COALESCE(sensors.site, excluded.site),
+                    updated_at = CURRENT_TIMESTAMP
+                "#
+            )
+            .bind(&reading.sensor_id)
+            .bind(&reading.location)
+            .bind(&self.site)
+            .execute(&self.pool)
+            .await
+            .context("Error inserting sensor")?;
+
+            if self.reading_known(reading, check).await? {
+                stats.duplicates += 1;
+                stats.per_sensor.entry(reading.sensor_id.clone()).or_default().duplicates += 1;
+                continue;
+            }
+
+            match self.layout {
+                StorageLayout::Domain => {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO temperature_readings
+                        (sensor_id, timestamp, temperature, humidity, mold_risk, location, site)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                        "#
+                    )
+                    .bind(&reading.sensor_id)
+                    .bind(reading.timestamp)
+                    .bind(reading.temperature)
+                    .bind(reading.humidity)
+                    .bind(mold_risk(reading))
+                    .bind(&reading.location)
+                    .bind(&self.site)
+                    .execute(&self.pool)
+                    .await
+                    .context("Error inserting temperature reading")?;
+                }
+                StorageLayout::Generic => {
+                    for (metric, value) in temperature_metrics(reading) {
+                        let metric = metric.key();
+                        self.insert_metric(&reading.sensor_id, reading.timestamp, metric, value, None).await?;
+                    }
+                }
+            }
+
+            stats.inserted += 1;
+            stats.per_sensor.entry(reading.sensor_id.clone()).or_default().inserted += 1;
+        }
+
+        debug!("Sandbox save: {} new, {} duplicates", stats.inserted, stats.duplicates);
+        Ok(stats)
+    }
+
+    async fn diff_temperature_readings_impl(&self, readings: &[TemperatureReading]) -> Result<SaveStats> {
+        let mut stats = SaveStats::default();
+        let checks = delta_checks(readings, &self.latest_temperature_timestamps(readings).await?, self.skip_history);
+
+        for (reading, check) in readings.iter().zip(checks) {
+            let counts = stats.per_sensor.entry(reading.sensor_id.clone()).or_default();
+            if self.reading_known(reading, check).await? {
+                stats.duplicates += 1;
+                counts.duplicates += 1;
+            } else {
+                stats.inserted += 1;
+                counts.inserted += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    async fn insert_metric(&self, sensor_id: &str, timestamp: DateTime<Utc>, metric: &str, value: f64,
+                           email_id: Option<&str>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO readings (sensor_id, timestamp, metric, value, site, email_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+        )
+        .bind(sensor_id)
+        .bind(timestamp)
+        .bind(metric)
+        .bind(value)
+        .bind(&self.site)
+        .bind(email_id)
+        .execute(&self.pool)
+        .await
+        .context("Error inserting metric")?;
+
+        Ok(())
+    }
+
+    async fn save_rejected_readings_impl(&self, rejected: &[RejectedReading]) -> Result<usize> {
+        for reading in rejected {
+            sqlx::query(
+                r#"
+                INSERT INTO rejected_readings (source, sensor_id, timestamp, payload, reason, email_id, site)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#
+            )
+            .bind(&reading.source)
+            .bind(&reading.sensor_id)
+            .bind(reading.timestamp)
+            .bind(&reading.payload)
+            .bind(&reading.reason)
+            .bind(&reading.email_id)
+            .bind(&self.site)
+            .execute(&self.pool)
+            .await
+            .context("Error inserting rejected reading")?;
+        }
+
+        Ok(rejected.len())
+    }
+
+    async fn save_failed_extraction_impl(&self, failure: &FailedExtraction) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO failed_extractions (source, email_id, filename, content, error, site)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (source, email_id, filename) DO UPDATE SET
+                content = excluded.content,
+                error = excluded.error,
+                failed_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(&failure.source)
+        .bind(&failure.email_id)
+        .bind(&failure.filename)
+        .bind(&failure.content)
+        .bind(&failure.error)
+        .bind(&self.site)
+        .execute(&self.pool)
+        .await
+        .context("Error saving failed extraction")?;
+
+        Ok(())
+    }
+
+    async fn email_retries_impl(&self, source: &str) -> Result<Vec<EmailRetry>> {
+        let rows: Vec<RetryRow> = sqlx::query_as(
+            r#"
+            SELECT source, email_id, attempts, next_attempt_at, last_error, dead_lettered_at FROM email_retries
+            WHERE source = ?1 AND site IS ?2
+            ORDER BY next_attempt_at
+            "#
+        )
+        .bind(source)
+        .bind(&self.site)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading email retry queue")?;
+
+        Ok(rows.into_iter().map(retry_from_row).collect())
+    }
+
+    async fn schedule_email_retry_impl(&self, retry: &EmailRetry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_retries (source, email_id, attempts, next_attempt_at, last_error, site, dead_lettered_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT (source, email_id) DO UPDATE SET
+                attempts = excluded.attempts,
+                next_attempt_at = excluded.next_attempt_at,
+                last_error = excluded.last_error,
+                dead_lettered_at = excluded.dead_lettered_at,
+                updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(&retry.source)
+        .bind(&retry.email_id)
+        .bind(retry.attempts as i64)
+        .bind(retry.next_attempt_at)
+        .bind(&retry.last_error)
+        .bind(&self.site)
+        .bind(retry.dead_lettered_at)
+        .execute(&self.pool)
+        .await
+        .context("Error scheduling email retry")?;
+
+        Ok(())
+    }
+
+    async fn clear_email_retry_impl(&self, source: &str, email_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM email_retries WHERE source = ?1 AND email_id = ?2")
+            .bind(source)
+            .bind(email_id)
+            .execute(&self.pool)
+            .await
+            .context("Error clearing email retry")?;
+
+        Ok(())
+    }
+
+    async fn dead_letters_impl(&self) -> Result<Vec<EmailRetry>> {
+        let rows: Vec<RetryRow> = sqlx::query_as(
+            r#"
+            SELECT source, email_id, attempts, next_attempt_at, last_error, dead_lettered_at FROM email_retries
+            WHERE dead_lettered_at IS NOT NULL AND site IS ?1
+            ORDER BY dead_lettered_at DESC
+            "#
+        )
+        .bind(&self.site)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading dead-lettered emails")?;
+
+        Ok(rows.into_iter().map(retry_from_row).collect())
+    }
+
+    async fn requeue_email_impl(&self, email_id: &str) -> Result<Option<EmailRetry>> {
+        let row: Option<RetryRow> = sqlx::query_as(
+            r#"
+            UPDATE email_retries SET attempts = 0, next_attempt_at = ?3, dead_lettered_at = NULL, updated_at = CURRENT_TIMESTAMP
+            WHERE email_id = ?1 AND dead_lettered_at IS NOT NULL AND site IS ?2
+            RETURNING source, email_id, attempts, next_attempt_at, last_error, dead_lettered_at
+            "#
+        )
+        .bind(email_id)
+        .bind(&self.site)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .context("Error requeuing email")?;
+
+        Ok(row.map(retry_from_row))
+    }
+
+    async fn record_source_run_impl(&self, source: &str, found_emails: bool) -> Result<SourceActivity> {
+        let row: ActivityRow = sqlx::query_as(
+            r#"
+            INSERT INTO source_activity (source, site, last_email_at, empty_runs, updated_at)
+            VALUES (?1, ?2, ?3, CASE WHEN ?4 THEN 0 ELSE 1 END, ?3)
+            ON CONFLICT (source, site) DO UPDATE SET
+                last_email_at = CASE WHEN ?4 THEN excluded.last_email_at ELSE source_activity.last_email_at END,
+                empty_runs = CASE WHEN ?4 THEN 0 ELSE source_activity.empty_runs + 1 END,
+                alerted_at = CASE WHEN ?4 THEN NULL ELSE source_activity.alerted_at END,
+                updated_at = excluded.updated_at
+            RETURNING source, last_email_at, empty_runs, alerted_at, updated_at
+            "#
+        )
+        .bind(source)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .bind(Utc::now())
+        .bind(found_emails)
+        .fetch_one(&self.pool)
+        .await
+        .context("Error recording source activity")?;
+
+        Ok(activity_from_row(row))
+    }
+
+    async fn source_activities_impl(&self) -> Result<Vec<SourceActivity>> {
+        let rows: Vec<ActivityRow> = sqlx::query_as(
+            r#"
+            SELECT source, last_email_at, empty_runs, alerted_at, updated_at
+            FROM source_activity
+            WHERE site = ?1
+            ORDER BY source
+            "#
+        )
+        .bind(self.site.as_deref().unwrap_or(""))
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading source activity")?;
+
+        Ok(rows.into_iter().map(activity_from_row).collect())
+    }
+
+    async fn mark_source_alerted_impl(&self, source: &str) -> Result<()> {
+        sqlx::query("UPDATE source_activity SET alerted_at = ?3 WHERE source = ?1 AND site = ?2")
+            .bind(source)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .context("Error updating source activity")?;
+
+        Ok(())
+    }
+
+    async fn record_processed_email_impl(&self, source: &str, email_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO processed_emails (source, email_id, site) VALUES (?1, ?2, ?3)
+            ON CONFLICT (source, email_id, site) DO UPDATE SET processed_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(source)
+        .bind(email_id)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .execute(&self.pool)
+        .await
+        .context("Error recording processed email")?;
+
+        Ok(())
+    }
+
+    async fn processed_emails_impl(&self, source: &str) -> Result<HashSet<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT email_id FROM processed_emails WHERE source = ?1 AND site = ?2")
+            .bind(source)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading processed emails")?;
+
+        Ok(rows.into_iter().map(|(email_id,)| email_id).collect())
+    }
+
+    async fn batch_checkpoint_impl(&self, source: &str) -> Result<Option<BatchCheckpoint>> {
+        let row: Option<(String, i64)> = sqlx::query_as(
+            "SELECT email_id, email_date FROM batch_checkpoints WHERE source = ?1 AND site = ?2"
+        )
+        .bind(source)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .fetch_optional(&self.pool)
+        .await
+        .context("Error reading batch checkpoint")?;
+
+        Ok(row.map(|(email_id, email_date)| BatchCheckpoint { source: source.to_string(), email_id, email_date }))
+    }
+
+    async fn save_batch_checkpoint_impl(&self, checkpoint: &BatchCheckpoint) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO batch_checkpoints (source, site, email_id, email_date) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (source, site) DO UPDATE SET
+                email_id = excluded.email_id,
+                email_date = excluded.email_date,
+                updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(&checkpoint.source)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .bind(&checkpoint.email_id)
+        .bind(checkpoint.email_date)
+        .execute(&self.pool)
+        .await
+        .context("Error saving batch checkpoint")?;
+
+        Ok(())
+    }
+
+    async fn clear_batch_checkpoint_impl(&self, source: &str) -> Result<()> {
+        sqlx::query("DELETE FROM batch_checkpoints WHERE source = ?1 AND site = ?2")
+            .bind(source)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .execute(&self.pool)
+            .await
+            .context("Error clearing batch checkpoint")?;
+
+        Ok(())
+    }
+
+    async fn record_api_calls_impl(&self, source: &str, calls: u64) -> Result<u64> {
+        let today = Utc::now().date_naive();
+        let site = self.site.as_deref().unwrap_or("");
+
+        sqlx::query(
+            r#"
+            INSERT INTO api_usage (day, source, site, calls, runs)
+            VALUES (?1, ?2, ?3, ?4, 1)
+            ON CONFLICT (day, source, site) DO UPDATE SET
+                calls = api_usage.calls + excluded.calls,
+                runs = api_usage.runs + 1,
+                updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(today)
+        .bind(source)
+        .bind(site)
+        .bind(calls as i64)
+        .execute(&self.pool)
+        .await
+        .context("Error recording Gmail API usage")?;
+
+        let (total,): (i64,) = sqlx::query_as("SELECT COALESCE(SUM(calls), 0) FROM api_usage WHERE day = ?1 AND site = ?2")
+            .bind(today)
+            .bind(site)
+            .fetch_one(&self.pool)
+            .await
+            .context("Error reading Gmail API usage")?;
+
+        Ok(total.max(0) as u64)
+    }
+
+    async fn sensors_impl(&self) -> Result<Vec<SensorInfo>> {
+        let rows: Vec<SensorRow> = sqlx::query_as(
+            "SELECT sensor_id, location, site, sensor_type, unit FROM sensors ORDER BY sensor_id"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Error listing sensors")?;
+
+        Ok(rows.into_iter().map(sensor_from_row).collect())
+    }
+
+    async fn pool_readings_impl(&self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<Vec<PoolReading>> {
+        let query = match self.layout {
+            StorageLayout::Domain => r#"
+                SELECT pool_id, timestamp, temperature, ph, orp
+                FROM pool_readings
+                WHERE site IS ?1
+                  AND (?2 IS NULL OR timestamp >= ?2)
+                  AND (?3 IS NULL OR timestamp < ?3)
+                ORDER BY timestamp, pool_id
+                "#,
+            StorageLayout::Generic => r#"
+                SELECT substr(sensor_id, 6) AS pool_id, timestamp,
+                       MAX(CASE WHEN metric = 'temperature' THEN value END),
+                       MAX(CASE WHEN metric = 'ph' THEN value END),
+                       CAST(MAX(CASE WHEN metric = 'orp' THEN value END) AS INTEGER)
+                FROM readings
+                WHERE sensor_id LIKE 'pool/%' AND site IS ?1
+                  AND (?2 IS NULL OR timestamp >= ?2)
+                  AND (?3 IS NULL OR timestamp < ?3)
+                GROUP BY sensor_id, timestamp
+                ORDER BY timestamp, pool_id
+                "#,
+        };
+
+        let rows: Vec<PoolRow> = sqlx::query_as(query)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading pool readings")?;
+
+        Ok(rows.into_iter().map(pool_from_row).collect())
+    }
+
+    async fn get_readings_range_impl(&self, range: &ReadingRange) -> Result<ReadingPage> {
+        let (direction, after) = range.order.sql();
+        let query = match self.layout {
+            StorageLayout::Domain => format!(
+                r#"
+                SELECT sensor_id, timestamp, temperature, humidity, location FROM temperature_readings
+                WHERE sensor_id = ?1 AND site IS ?2
+                  AND (?3 IS NULL OR timestamp >= ?3)
+                  AND (?4 IS NULL OR timestamp < ?4)
+                  AND (?5 IS NULL OR timestamp {after} ?5)
+                ORDER BY timestamp {direction}
+                LIMIT ?6
+                "#
+            ),
+            StorageLayout::Generic => format!(
+                r#"
+                SELECT sensor_id, timestamp,
+                       MAX(CASE WHEN metric = 'temperature' THEN value END) AS temperature,
+                       MAX(CASE WHEN metric = 'humidity' THEN value END) AS humidity,
+                       NULL AS location
+                FROM readings
+                WHERE sensor_id = ?1 AND site IS ?2
+                  AND (?3 IS NULL OR timestamp >= ?3)
+                  AND (?4 IS NULL OR timestamp < ?4)
+                  AND (?5 IS NULL OR timestamp {after} ?5)
+                GROUP BY sensor_id, timestamp
+                HAVING MAX(CASE WHEN metric = 'temperature' THEN value END) IS NOT NULL
+                ORDER BY timestamp {direction}
+                LIMIT ?6
+                "#
+            ),
+        };
+
+        let page_size = range.page_size();
+        let rows: Vec<ReadingRow> = sqlx::query_as(&query)
+            .bind(&range.sensor_id)
+            .bind(&self.site)
+            .bind(range.from)
+            .bind(range.to)
+            .bind(range.after)
+            .bind(page_size as i64 + 1)
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading sensor readings")?;
+
+        Ok(reading_page(rows, page_size))
+    }
+
+    async fn get_bucketed_readings_impl(&self, sensor_id: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>,
+                                        bucket: Bucket) -> Result<Vec<ReadingBucket>> {
+        let (value, table, metric_filter) = match self.layout {
+            StorageLayout::Domain => ("temperature", "temperature_readings", ""),
+            StorageLayout::Generic => ("value", "readings", "AND metric = 'temperature'"),
+        };
+        let query = format!(
+            r#"
+            SELECT strftime('{format}', timestamp) AS bucket, COUNT(*), MIN({value}), MAX({value}), AVG({value})
+            FROM {table}
+            WHERE sensor_id = ?1 AND site IS ?2 {metric_filter}
+              AND (?3 IS NULL OR timestamp >= ?3)
+              AND (?4 IS NULL OR timestamp < ?4)
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            format = bucket.strftime_format(),
+        );
+
+        let rows: Vec<BucketRow> = sqlx::query_as(&query)
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .context("Error reading bucketed readings")?;
+
+        Ok(rows.into_iter().map(bucket_from_row).collect())
+    }
+
+    async fn mold_risk_streak_impl(&self, sensor_id: &str) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let (table, risk, metric_filter) = match self.layout {
+            StorageLayout::Domain => ("temperature_readings", "mold_risk", ""),
+            StorageLayout::Generic => ("readings", "value", "AND metric = 'mold_risk'"),
+        };
+        let query = format!(
+            r#"
+            SELECT MIN(timestamp), MAX(timestamp)
+            FROM {table}
+            WHERE sensor_id = ?1 AND site IS ?2 {metric_filter}
+              AND timestamp > COALESCE((
+                  SELECT MAX(timestamp) FROM {table}
+                  WHERE sensor_id = ?1 AND site IS ?2 {metric_filter}
+                    AND ({risk} IS NULL OR {risk} < ?3)
+              ), '')
+            "#
+        );
+
+        let (since, latest): (Option<DateTime<Utc>>, Option<DateTime<Utc>>) = sqlx::query_as(&query)
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(RISK_THRESHOLD)
+            .fetch_one(&self.pool)
+            .await
+            .context("Error reading mold risk")?;
+
+        Ok(since.zip(latest))
+    }
+
+    async fn claim_mold_alert_impl(&self, sensor_id: &str, since: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query("INSERT INTO mold_alerts (sensor_id, site, since) VALUES (?1, ?2, ?3) ON CONFLICT DO NOTHING")
+            .bind(sensor_id)
+            .bind(self.site.as_deref().unwrap_or(""))
+            .bind(since)
+            .execute(&self.pool)
+            .await
+            .context("Error recording mold alert")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record_provenance_impl(&self, records: &[ProvenanceRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Error recording reading provenance")?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO reading_provenance (sensor_id, source, email_id, file, file_hash, first_timestamp, last_timestamp,
+                                                readings, run_started_at, site)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#
+            )
+            .bind(&record.sensor_id)
+            .bind(&record.source)
+            .bind(&record.email_id)
+            .bind(&record.file)
+            .bind(&record.file_hash)
+            .bind(record.first_timestamp)
+            .bind(record.last_timestamp)
+            .bind(record.readings as i64)
+            .bind(record.run_started_at)
+            .bind(&self.site)
+            .execute(&mut *tx)
+            .await
+            .context("Error recording reading provenance")?;
+        }
+        tx.commit().await.context("Error recording reading provenance")?;
+        Ok(())
+    }
+
+    async fn provenance_impl(&self, sensor_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<ProvenanceRecord>> {
+        let rows: Vec<ProvenanceRow> = sqlx::query_as(
+            r#"
+            SELECT sensor_id, source, email_id, file, file_hash, first_timestamp, last_timestamp, readings, run_started_at
+            FROM reading_provenance
+            WHERE sensor_id = ?1 AND site IS ?2 AND first_timestamp <= ?4 AND last_timestamp >= ?3
+            ORDER BY run_started_at, id
+            "#
+        )
+        .bind(sensor_id)
+        .bind(&self.site)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading reading provenance")?;
+
+        Ok(rows.into_iter().map(provenance_from_row).collect())
+    }
+
+    async fn delete_readings_impl(&self, scope: &DeleteScope, apply: bool) -> Result<DeletedReadings> {
+        let mut tx = self.pool.begin().await.context("Error deleting readings")?;
+        let mut deleted = DeletedReadings::default();
+
+        let (condition, value) = match scope {
+            DeleteScope::Email(email_id) => ("email_id = ?2", email_id.clone()),
+            // Compared as text, never as a LIKE pattern: an empty prefix or a '%' would match every attachment
+            DeleteScope::Attachment(hash) => ("substr(file_hash, 1, length(?2)) = ?2", parse_hash(hash).map_err(anyhow::Error::msg)?),
+            DeleteScope::Sensor { sensor_id, .. } => ("sensor_id = ?2", sensor_id.clone()),
+        };
+        let spans: Vec<SpanRow> = match scope {
+            DeleteScope::Sensor { sensor_id, from, to } => vec![(sensor_id.clone(), None, *from, *to)],
+            _ => sqlx::query_as(&format!(
+                "SELECT sensor_id, email_id, first_timestamp, last_timestamp FROM reading_provenance WHERE site IS ?1 AND {}",
+                condition
+            ))
+            .bind(&self.site)
+            .bind(&value)
+            .fetch_all(&mut *tx)
+            .await
+            .context("Error reading reading provenance")?,
+        };
+        for (sensor_id, email_id, from, to) in &spans {
+            deleted.add(self.delete_span(&mut tx, sensor_id, email_id.as_deref(), *from, *to).await?);
+        }
+
+        if let DeleteScope::Email(email_id) = scope {
+            let query = match self.layout {
+                StorageLayout::Domain => "DELETE FROM pool_readings WHERE email_id = ?1 AND site IS ?2 RETURNING 'pool/' || pool_id, timestamp",
+                StorageLayout::Generic => "DELETE FROM readings WHERE email_id = ?1 AND site IS ?2 RETURNING sensor_id, timestamp",
+            };
+            deleted.add(sqlx::query_as(query)
+                .bind(email_id)
+                .bind(&self.site)
+                .fetch_all(&mut *tx)
+                .await
+                .context("Error deleting pool readings")?);
+        }
+
+        let provenance = match scope {
+            DeleteScope::Sensor { from, to, .. } => sqlx::query(
+                "DELETE FROM reading_provenance WHERE site IS ?1 AND sensor_id = ?2 AND first_timestamp >= ?3 AND last_timestamp <= ?4"
+            )
+            .bind(&self.site)
+            .bind(&value)
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await,
+            _ => sqlx::query(&format!("DELETE FROM reading_provenance WHERE site IS ?1 AND {}", condition))
+                .bind(&self.site)
+                .bind(&value)
+                .execute(&mut *tx)
+                .await,
+        };
+        deleted.provenance = provenance.context("Error deleting reading provenance")?.rows_affected() as usize;
+
+        if apply {
+            tx.commit().await.context("Error deleting readings")?;
+        } else {
+            tx.rollback().await.context("Error counting readings to delete")?;
+        }
+        Ok(deleted)
+    }
+
+    async fn delete_span(&self, tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, sensor_id: &str, email_id: Option<&str>,
+                         from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let query = match (self.layout, sensor_id.strip_prefix("pool/")) {
+            (StorageLayout::Domain, Some(pool_id)) => sqlx::query_as(
+                r#"
+                DELETE FROM pool_readings
+                WHERE pool_id = ?1 AND site IS ?2 AND timestamp BETWEEN ?3 AND ?4 AND (?5 IS NULL OR email_id = ?5)
+                RETURNING 'pool/' || pool_id, timestamp
+                "#
+            )
+            .bind(pool_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .bind(email_id),
+            (StorageLayout::Domain, None) => sqlx::query_as(
+                r#"
+                DELETE FROM temperature_readings
+                WHERE sensor_id = ?1 AND site IS ?2 AND timestamp BETWEEN ?3 AND ?4
+                RETURNING sensor_id, timestamp
+                "#
+            )
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to),
+            (StorageLayout::Generic, _) => sqlx::query_as(
+                r#"
+                DELETE FROM readings
+                WHERE sensor_id = ?1 AND site IS ?2 AND timestamp BETWEEN ?3 AND ?4
+                  AND (?5 IS NULL OR email_id IS NULL OR email_id = ?5)
+                RETURNING sensor_id, timestamp
+                "#
+            )
+            .bind(sensor_id)
+            .bind(&self.site)
+            .bind(from)
+            .bind(to)
+            .bind(email_id),
+        };
+        query.fetch_all(&mut **tx)
+            .await
+            .with_context(|| format!("Error deleting readings of {}", sensor_id))
+    }
+
+    async fn api_usage_impl(&self, days: u32) -> Result<Vec<ApiUsage>> {
+        let since = Utc::now().date_naive() - chrono::Duration::days(days.saturating_sub(1) as i64);
+
+        let rows: Vec<ApiUsageRow> = sqlx::query_as(
+            r#"
+            SELECT day, source, calls, runs FROM api_usage
+            WHERE day >= ?1 AND site = ?2
+            ORDER BY day DESC, source
+            "#
+        )
+        .bind(since)
+        .bind(self.site.as_deref().unwrap_or(""))
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading Gmail API usage")?;
+
+        Ok(rows.into_iter().map(api_usage_from_row).collect())
+    }
+
+    async fn record_notification_impl(&self, notification: &NotificationRecord) -> Result<i64> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO notifications (created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts,
+                                       attempts, next_attempt_at, site)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            RETURNING id
+            "#
+        )
+        .bind(notification.created_at)
+        .bind(&notification.channel)
+        .bind(&notification.kind)
+        .bind(&notification.source)
+        .bind(&notification.related_id)
+        .bind(&notification.text)
+        .bind(&notification.thread_ts)
+        .bind(notification.status.key())
+        .bind(&notification.error)
+        .bind(&notification.message_ts)
+        .bind(notification.attempts as i32)
+        .bind(notification.next_attempt_at)
+        .bind(&self.site)
+        // Stepped to the end: a RETURNING statement left pending keeps the row from other connections
+        .fetch_all(&self.pool)
+        .await
+        .context("Error recording notification")?
+        .pop()
+        .context("Notification not recorded")
+    }
+
+    async fn notifications_impl(&self, failed_only: bool, limit: usize) -> Result<Vec<NotificationRecord>> {
+        let rows: Vec<NotificationRow> = sqlx::query_as(
+            r#"
+            SELECT id, created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts, attempts, next_attempt_at
+            FROM notifications
+            WHERE site IS ?1 AND (NOT ?2 OR status = 'failed')
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?3
+            "#
+        )
+        .bind(&self.site)
+        .bind(failed_only)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error reading notifications")?;
+
+        Ok(rows.into_iter().map(notification_from_row).collect())
+    }
+
+    async fn claim_notifications_impl(&self, now: DateTime<Utc>, lease: TimeDelta, limit: usize) -> Result<Vec<NotificationRecord>> {
+        let rows: Vec<NotificationRow> = sqlx::query_as(
+            r#"
+            UPDATE notifications SET next_attempt_at = ?2
+            WHERE id IN (
+                SELECT id FROM notifications
+                WHERE site IS ?1 AND status = 'failed' AND next_attempt_at <= ?3
+                ORDER BY created_at, id
+                LIMIT ?4
+            )
+            RETURNING id, created_at, channel, kind, source, related_id, text, thread_ts, status, error, message_ts, attempts, next_attempt_at
+            "#
+        )
+        .bind(&self.site)
+        .bind(now + lease)
+        .bind(now)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Error claiming notifications to retry")?;
+
+        let mut notifications: Vec<NotificationRecord> = rows.into_iter().map(notification_from_row).collect();
+        notifications.sort_by_key(|notification| (notification.created_at, notification.id));
+        Ok(notifications)
+    }
+
+    async fn update_notification_impl(&self, notification: &NotificationRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE notifications
+            SET status = ?2, error = ?3, message_ts = ?4, attempts = ?5, next_attempt_at = ?6
+            WHERE id = ?1
+            "#
+        )
+        .bind(notification.id)
+        .bind(notification.status.key())
+        .bind(&notification.error)
+        .bind(&notification.message_ts)
+        .bind(notification.attempts as i32)
+        .bind(notification.next_attempt_at)
+        .execute(&self.pool)
+        .await
+        .context("Error updating notification")?;
+        Ok(())
+    }
+
+    async fn register_sensor_impl(&self, sensor_id: &str, sensor_type: SensorType) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sensors (sensor_id, site, sensor_type, unit) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (sensor_id) DO UPDATE SET
+                site = COALESCE(sensors.site, excluded.site),
+                sensor_type = excluded.sensor_type,
+                unit = excluded.unit,
+                updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(sensor_id)
+        .bind(&self.site)
+        .bind(sensor_type.as_str())
+        .bind(sensor_type.unit())
+        .execute(&self.pool)
+        .await
+        .context("Error registering sensor type")?;
+
+        Ok(())
+    }
+
+    async fn pool_reading_exists_impl(&self, pool_id: &str, email_id: &str) -> Result<bool> {
+        let (query, pool_key) = match self.layout {
+            StorageLayout::Domain => (
+                "SELECT EXISTS(SELECT 1 FROM pool_readings WHERE email_id = ?1 AND pool_id = ?2 AND site IS ?3)",
+                pool_id.to_string(),
+            ),
+            StorageLayout::Generic => (
+                "SELECT EXISTS(SELECT 1 FROM readings WHERE email_id = ?1 AND sensor_id = ?2 AND site IS ?3)",
+                format!("pool/{}", pool_id),
+            ),
+        };
+        sqlx::query_scalar::<_, bool>(query)
+        .bind(email_id)
+        .bind(pool_key)
+        .bind(&self.site)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to check for existing pool reading")
+    }
+
+    async fn save_pool_reading_impl(&self, reading: &PoolReading, email_id: &str) -> Result<bool> {
+        if self.pool_reading_exists_impl(&reading.pool_id, email_id).await? {
+            return Ok(false);
+        }
+
+        if self.layout == StorageLayout::Generic {
+            for (metric, value) in pool_metrics(reading) {
+                    let metric = metric.key();
+                self.insert_metric(&reading.sensor_id(), reading.timestamp, metric, value, Some(email_id)).await?;
+            }
+            return Ok(true);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO pool_readings (timestamp, temperature, ph, orp, email_id, pool_id, site)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#
+        )
+        .bind(reading.timestamp)
+        .bind(reading.temperature)
+        .bind(reading.ph)
+        .bind(reading.orp)
+        .bind(email_id)
+        .bind(&reading.pool_id)
+        .bind(&self.site)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert pool reading")?;
+
+        Ok(true)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn save_temperature_readings<'a>(&'a self, readings: &'a [TemperatureReading]) -> StorageFuture<'a, SaveStats> {
+        Box::pin(self.save_temperature_readings_impl(readings))
+    }
+
+    fn save_pool_reading<'a>(&'a self, reading: &'a PoolReading, email_id: &'a str) -> StorageFuture<'a, bool> {
+        Box::pin(self.save_pool_reading_impl(reading, email_id))
+    }
+
+    fn diff_temperature_readings<'a>(&'a self, readings: &'a [TemperatureReading]) -> StorageFuture<'a, SaveStats> {
+        Box::pin(self.diff_temperature_readings_impl(readings))
+    }
+
+    fn pool_reading_exists<'a>(&'a self, pool_id: &'a str, email_id: &'a str) -> StorageFuture<'a, bool> {
+        Box::pin(self.pool_reading_exists_impl(pool_id, email_id))
+    }
+
+    fn register_sensor<'a>(&'a self, sensor_id: &'a str, sensor_type: SensorType) -> StorageFuture<'a, ()> {
+        Box::pin(self.register_sensor_impl(sensor_id, sensor_type))
+    }
+
+    fn save_rejected_readings<'a>(&'a self, rejected: &'a [RejectedReading]) -> StorageFuture<'a, usize> {
+        Box::pin(self.save_rejected_readings_impl(rejected))
+    }
+
+    fn save_failed_extraction<'a>(&'a self, failure: &'a FailedExtraction) -> StorageFuture<'a, ()> {
+        Box::pin(self.save_failed_extraction_impl(failure))
+    }
+
+    fn email_retries<'a>(&'a self, source: &'a str) -> StorageFuture<'a, Vec<EmailRetry>> {
+        Box::pin(self.email_retries_impl(source))
+    }
+
+    fn schedule_email_retry<'a>(&'a self, retry: &'a EmailRetry) -> StorageFuture<'a, ()> {
+        Box::pin(self.schedule_email_retry_impl(retry))
+    }
+
+    fn clear_email_retry<'a>(&'a self, source: &'a str, email_id: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(self.clear_email_retry_impl(source, email_id))
+    }
+
+    fn dead_letters<'a>(&'a self) -> StorageFuture<'a, Vec<EmailRetry>> {
+        Box::pin(self.dead_letters_impl())
+    }
+
+    fn requeue_email<'a>(&'a self, email_id: &'a str) -> StorageFuture<'a, Option<EmailRetry>> {
+        Box::pin(self.requeue_email_impl(email_id))
+    }
+
+    fn record_source_run<'a>(&'a self, source: &'a str, found_emails: bool) -> StorageFuture<'a, SourceActivity> {
+        Box::pin(self.record_source_run_impl(source, found_emails))
+    }
+
+    fn source_activities<'a>(&'a self) -> StorageFuture<'a, Vec<SourceActivity>> {
+        Box::pin(self.source_activities_impl())
+    }
+
+    fn mark_source_alerted<'a>(&'a self, source: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(self.mark_source_alerted_impl(source))
+    }
+
+    fn record_processed_email<'a>(&'a self, source: &'a str, email_id: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(self.record_processed_email_impl(source, email_id))
+    }
+
+    fn processed_emails<'a>(&'a self, source: &'a str) -> StorageFuture<'a, HashSet<String>> {
+        Box::pin(self.processed_emails_impl(source))
+    }
+
+    fn batch_checkpoint<'a>(&'a self, source: &'a str) -> StorageFuture<'a, Option<BatchCheckpoint>> {
+        Box::pin(self.batch_checkpoint_impl(source))
+    }
+
+    fn save_batch_checkpoint<'a>(&'a self, checkpoint: &'a BatchCheckpoint) -> StorageFuture<'a, ()> {
+        Box::pin(self.save_batch_checkpoint_impl(checkpoint))
+    }
+
+    fn clear_batch_checkpoint<'a>(&'a self, source: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(self.clear_batch_checkpoint_impl(source))
+    }
+
+    fn record_api_calls<'a>(&'a self, source: &'a str, calls: u64) -> StorageFuture<'a, u64> {
+        Box::pin(self.record_api_calls_impl(source, calls))
+    }
+
+    fn api_usage<'a>(&'a self, days: u32) -> StorageFuture<'a, Vec<ApiUsage>> {
+        Box::pin(self.api_usage_impl(days))
+    }
+
+    fn record_notification<'a>(&'a self, notification: &'a NotificationRecord) -> StorageFuture<'a, i64> {
+        Box::pin(self.record_notification_impl(notification))
+    }
+
+    fn notifications<'a>(&'a self, failed_only: bool, limit: usize) -> StorageFuture<'a, Vec<NotificationRecord>> {
+        Box::pin(self.notifications_impl(failed_only, limit))
+    }
+
+    fn claim_notifications<'a>(&'a self, now: DateTime<Utc>, lease: TimeDelta, limit: usize) -> StorageFuture<'a, Vec<NotificationRecord>> {
+        Box::pin(self.claim_notifications_impl(now, lease, limit))
+    }
+
+    fn update_notification<'a>(&'a self, notification: &'a NotificationRecord) -> StorageFuture<'a, ()> {
+        Box::pin(self.update_notification_impl(notification))
+    }
+
+    fn sensors<'a>(&'a self) -> StorageFuture<'a, Vec<SensorInfo>> {
+        Box::pin(self.sensors_impl())
+    }
+
+    fn pool_readings<'a>(&'a self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> StorageFuture<'a, Vec<PoolReading>> {
+        Box::pin(self.pool_readings_impl(from, to))
+    }
+
+    fn get_readings_range<'a>(&'a self, range: &'a ReadingRange) -> StorageFuture<'a, ReadingPage> {
+        Box::pin(self.get_readings_range_impl(range))
+    }
+
+    fn get_bucketed_readings<'a>(&'a self, sensor_id: &'a str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>,
+                                 bucket: Bucket) -> StorageFuture<'a, Vec<ReadingBucket>> {
+        Box::pin(self.get_bucketed_readings_impl(sensor_id, from, to, bucket))
+    }
+
+    fn mold_risk_streak<'a>(&'a self, sensor_id: &'a str) -> StorageFuture<'a, Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        Box::pin(self.mold_risk_streak_impl(sensor_id))
+    }
+
+    fn claim_mold_alert<'a>(&'a self, sensor_id: &'a str, since: DateTime<Utc>) -> StorageFuture<'a, bool> {
+        Box::pin(self.claim_mold_alert_impl(sensor_id, since))
+    }
+
+    fn record_provenance<'a>(&'a self, records: &'a [ProvenanceRecord]) -> StorageFuture<'a, ()> {
+        Box::pin(self.record_provenance_impl(records))
+    }
+
+    fn provenance<'a>(&'a self, sensor_id: &'a str, from: DateTime<Utc>, to: DateTime<Utc>) -> StorageFuture<'a, Vec<ProvenanceRecord>> {
+        Box::pin(self.provenance_impl(sensor_id, from, to))
+    }
+
+    fn delete_readings<'a>(&'a self, scope: &'a DeleteScope, apply: bool) -> StorageFuture<'a, DeletedReadings> {
+        Box::pin(self.delete_readings_impl(scope, apply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike, Utc};
+    use crate::storage::{DeliveryStatus, SortOrder};
+
+    fn reading(sensor: &str, hour: u32) -> TemperatureReading {
+        TemperatureReading {
+            sensor_id: sensor.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, hour, 0, 0).unwrap(),
+            temperature: 19.5,
+            humidity: Some(55.0),
+            location: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delta_dedup() {
+        let dir = tempfile::tempdir().unwrap();
+        let readings = vec![reading("cabane", 10), reading("cabane", 12), reading("garage", 12)];
+        // 11h is a gap within the boundary window, 13h is repeated in the export
+        let mut two_days_ago = reading("cabane", 10);
+        two_days_ago.timestamp -= chrono::TimeDelta::days(2);
+        let overlap = vec![two_days_ago, reading("cabane", 10), reading("cabane", 11), reading("cabane", 12),
+                           reading("cabane", 13), reading("cabane", 13), reading("garage", 13)];
+
+        // Default: the reading before the boundary window is checked, and missing
+        let storage = SqliteStorage::open(&dir.path().join("default.sqlite")).await.unwrap();
+        assert_eq!(storage.save_temperature_readings(&readings).await.unwrap().inserted, 3);
+        let diff = storage.diff_temperature_readings(&overlap).await.unwrap();
+        assert_eq!((diff.inserted, diff.duplicates), (4, 3));
+        let stats = storage.save_temperature_readings(&overlap).await.unwrap();
+        assert_eq!((stats.inserted, stats.duplicates), (4, 3));
+        assert_eq!(stats.per_sensor["cabane"].inserted, 3);
+
+        // Scheduled run: the history before the boundary window is assumed stored
+        let storage = SqliteStorage::open(&dir.path().join("scheduled.sqlite")).await.unwrap()
+            .with_history_skip(true);
+        assert_eq!(storage.save_temperature_readings(&readings).await.unwrap().inserted, 3);
+        let diff = storage.diff_temperature_readings(&overlap).await.unwrap();
+        assert_eq!((diff.inserted, diff.duplicates), (3, 4));
+        let stats = storage.save_temperature_readings(&overlap).await.unwrap();
+        assert_eq!((stats.inserted, stats.duplicates), (3, 4));
+        assert_eq!(stats.per_sensor["cabane"].inserted, 2);
+    }
+
+    #[tokio::test]
+    async fn test_readings_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let readings: Vec<_> = (8..14).map(|hour| reading("cabane", hour)).collect();
+        storage.save_temperature_readings(&readings).await.unwrap();
+        storage.save_temperature_readings(&[reading("garage", 9)]).await.unwrap();
+
+        let mut range = ReadingRange {
+            sensor_id: "cabane".to_string(),
+            from: Some(readings[1].timestamp),
+            to: Some(readings[5].timestamp),
+            page_size: 3,
+            ..Default::default()
+        };
+        let page = storage.get_readings_range(&range).await.unwrap();
+        let hours: Vec<_> = page.readings.iter().map(|reading| reading.timestamp.hour()).collect();
+        assert_eq!(hours, [9, 10, 11]);
+        assert_eq!(page.next, Some(readings[3].timestamp));
+
+        range.after = page.next;
+        let page = storage.get_readings_range(&range).await.unwrap();
+        assert_eq!(page.readings.len(), 1);
+        assert_eq!(page.readings[0].timestamp.hour(), 12);
+        assert_eq!(page.next, None);
+
+        let range = ReadingRange {
+            sensor_id: "cabane".to_string(),
+            order: SortOrder::Desc,
+            page_size: 2,
+            ..Default::default()
+        };
+        let page = storage.get_readings_range(&range).await.unwrap();
+        let hours: Vec<_> = page.readings.iter().map(|reading| reading.timestamp.hour()).collect();
+        assert_eq!(hours, [13, 12]);
+        assert_eq!(page.readings[0].humidity, Some(55.0));
+
+        let generic = SqliteStorage::open(&dir.path().join("generic.sqlite")).await.unwrap()
+            .with_layout(StorageLayout::Generic);
+        generic.save_temperature_readings(&readings).await.unwrap();
+        let page = generic.get_readings_range(&range).await.unwrap();
+        assert_eq!(page.readings.len(), 2);
+        assert_eq!((page.readings[0].temperature, page.readings[0].humidity), (19.5, Some(55.0)));
+        assert_eq!(page.next, Some(readings[4].timestamp));
+    }
+
+    #[tokio::test]
+    async fn test_bucketed_readings() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let mut readings: Vec<_> = (8..11).map(|hour| reading("cabane", hour)).collect();
+        for (index, reading) in readings.iter_mut().enumerate() {
+            reading.temperature = 18.0 + index as f64;
+        }
+        let mut half_past = reading("cabane", 10);
+        half_past.timestamp += chrono::TimeDelta::minutes(30);
+        half_past.temperature = 23.0;
+        readings.push(half_past);
+        storage.save_temperature_readings(&readings).await.unwrap();
+
+        let buckets = storage.get_bucketed_readings("cabane", None, None, Bucket::Hour).await.unwrap();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].start, readings[0].timestamp);
+        assert_eq!((buckets[2].count, buckets[2].min, buckets[2].max, buckets[2].avg), (2, 20.0, 23.0, 21.5));
+
+        let buckets = storage.get_bucketed_readings("cabane", Some(readings[1].timestamp), None, Bucket::Day).await.unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].start, Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap());
+        assert_eq!((buckets[0].count, buckets[0].min, buckets[0].max), (3, 19.0, 23.0));
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_dedup() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+
+        let readings = vec![reading("cabane", 10), reading("cabane", 11)];
+        let stats = storage.save_temperature_readings(&readings).await.unwrap();
+        assert_eq!(stats.inserted, 2);
+
+        let again = vec![reading("cabane", 11), reading("cabane", 12)];
+        let diff = storage.diff_temperature_readings(&again).await.unwrap();
+        assert_eq!((diff.inserted, diff.duplicates), (1, 1));
+
+        let stats = storage.save_temperature_readings(&again).await.unwrap();
+        assert_eq!((stats.inserted, stats.duplicates), (1, 1));
+
+        let mut pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, 8, 0, 0).unwrap(),
+            temperature: Some(26.0),
+            ph: Some(7.2),
+            orp: Some(650),
+        };
+        assert!(storage.save_pool_reading(&pool, "msg-1").await.unwrap());
+        assert!(!storage.save_pool_reading(&pool, "msg-1").await.unwrap());
+        // The same email may report several pools
+        pool.pool_id = "spa".to_string();
+        assert!(storage.save_pool_reading(&pool, "msg-1").await.unwrap());
+
+        storage.register_sensor("cabane", SensorType::Temperature).await.unwrap();
+        let (sensor_type, unit): (String, String) = sqlx::query_as("SELECT sensor_type, unit FROM sensors WHERE sensor_id = 'cabane'")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!((sensor_type.as_str(), unit.as_str()), ("temperature", "°C"));
+
+        // A failed extraction is kept once per email and file
+        let mut failure = FailedExtraction {
+            source: "xsense".to_string(),
+            email_id: "msg-2".to_string(),
+            filename: "export.csv".to_string(),
+            content: b"garbage".to_vec(),
+            error: "bad header".to_string(),
+        };
+        storage.save_failed_extraction(&failure).await.unwrap();
+        failure.error = "still bad".to_string();
+        storage.save_failed_extraction(&failure).await.unwrap();
+        let errors: Vec<String> = sqlx::query_scalar("SELECT error FROM failed_extractions")
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(errors, vec!["still bad".to_string()]);
+
+        // Retry queue: one entry per email, updated on each failure
+        let mut retry = EmailRetry {
+            source: "xsense".to_string(),
+            email_id: "msg-2".to_string(),
+            attempts: 1,
+            next_attempt_at: Utc.with_ymd_and_hms(2025, 1, 15, 9, 0, 0).unwrap(),
+            last_error: "bad header".to_string(),
+            dead_lettered_at: None,
+        };
+        storage.schedule_email_retry(&retry).await.unwrap();
+        retry.attempts = 2;
+        storage.schedule_email_retry(&retry).await.unwrap();
+        let retries = storage.email_retries("xsense").await.unwrap();
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].attempts, 2);
+        assert_eq!(retries[0].next_attempt_at, retry.next_attempt_at);
+        assert!(storage.email_retries("blueriot").await.unwrap().is_empty());
+        assert!(storage.dead_letters().await.unwrap().is_empty());
+
+        // Dead-lettered, then requeued with its attempts reset
+        retry.dead_lettered_at = Some(Utc::now());
+        storage.schedule_email_retry(&retry).await.unwrap();
+        assert!(!storage.email_retries("xsense").await.unwrap()[0].is_due(Utc::now()));
+        assert_eq!(storage.dead_letters().await.unwrap().len(), 1);
+        let requeued = storage.requeue_email("msg-2").await.unwrap().unwrap();
+        assert_eq!((requeued.source.as_str(), requeued.attempts), ("xsense", 0));
+        assert!(requeued.is_due(Utc::now()));
+        assert!(storage.requeue_email("msg-2").await.unwrap().is_none());
+
+        storage.clear_email_retry("xsense", "msg-2").await.unwrap();
+        assert!(storage.email_retries("xsense").await.unwrap().is_empty());
+
+        // Duplicates are scoped to the site
+        let storage = storage.with_site(Some("chalet".to_string()));
+        let diff = storage.diff_temperature_readings(&again).await.unwrap();
+        assert_eq!((diff.inserted, diff.duplicates), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_source_activity() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+
+        let activity = storage.record_source_run("xsense", false).await.unwrap();
+        assert_eq!((activity.empty_runs, activity.days_without_email(Utc::now())), (1, 0));
+        let activity = storage.record_source_run("xsense", false).await.unwrap();
+        assert_eq!(activity.empty_runs, 2);
+        assert_eq!(storage.record_source_run("blueriot", false).await.unwrap().empty_runs, 1);
+
+        storage.mark_source_alerted("xsense").await.unwrap();
+        let activity = storage.record_source_run("xsense", false).await.unwrap();
+        assert_eq!(activity.empty_runs, 3);
+        assert!(activity.alerted_at.is_some());
+
+        // Emails coming back reset the count and re-arm the alert
+        let activity = storage.record_source_run("xsense", true).await.unwrap();
+        assert_eq!(activity.empty_runs, 0);
+        assert!(activity.alerted_at.is_none());
+        let activities = storage.source_activities().await.unwrap();
+        assert_eq!(activities.iter().map(|a| a.source.as_str()).collect::<Vec<_>>(), ["blueriot", "xsense"]);
+        assert!(activities[1].last_run_at >= activities[0].last_run_at);
+
+        // Tracked per site
+        let storage = storage.with_site(Some("chalet".to_string()));
+        assert_eq!(storage.record_source_run("xsense", false).await.unwrap().empty_runs, 1);
+    }
+
+    #[tokio::test]
+    async fn test_processed_emails() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+
+        storage.record_processed_email("xsense", "msg-1").await.unwrap();
+        storage.record_processed_email("xsense", "msg-1").await.unwrap();
+        storage.record_processed_email("blueriot", "msg-2").await.unwrap();
+
+        let processed = storage.processed_emails("xsense").await.unwrap();
+        assert_eq!(processed.len(), 1);
+        assert!(processed.contains("msg-1"));
+
+        let storage = storage.with_site(Some("chalet".to_string()));
+        assert!(storage.processed_emails("xsense").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        assert!(storage.batch_checkpoint("xsense").await.unwrap().is_none());
+
+        let checkpoint = |email_id: &str, email_date| BatchCheckpoint {
+            source: "xsense".to_string(),
+            email_id: email_id.to_string(),
+            email_date,
+        };
+        storage.save_batch_checkpoint(&checkpoint("msg-1", 1_000)).await.unwrap();
+        storage.save_batch_checkpoint(&checkpoint("msg-2", 2_000)).await.unwrap();
+        assert_eq!(storage.batch_checkpoint("xsense").await.unwrap(), Some(checkpoint("msg-2", 2_000)));
+        assert!(storage.batch_checkpoint("blueriot").await.unwrap().is_none());
+
+        let storage = storage.with_site(Some("chalet".to_string()));
+        assert!(storage.batch_checkpoint("xsense").await.unwrap().is_none());
+        storage.save_batch_checkpoint(&checkpoint("msg-3", 3_000)).await.unwrap();
+        storage.clear_batch_checkpoint("xsense").await.unwrap();
+        assert!(storage.batch_checkpoint("xsense").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_api_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+
+        assert_eq!(storage.record_api_calls("xsense", 12).await.unwrap(), 12);
+        assert_eq!(storage.record_api_calls("blueriot", 5).await.unwrap(), 17);
+        assert_eq!(storage.record_api_calls("xsense", 3).await.unwrap(), 20);
+
+        let usage = storage.api_usage(7).await.unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[1].source.as_str(), usage[1].calls, usage[1].runs), ("xsense", 15, 2));
+        assert_eq!(usage[0].day, Utc::now().date_naive());
+
+        // Counted per site
+        let storage = storage.with_site(Some("chalet".to_string()));
+        assert_eq!(storage.record_api_calls("xsense", 4).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_notifications() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let notification = |status, created_at| NotificationRecord {
+            id: 0,
+            created_at,
+            channel: "C123".to_string(),
+            kind: "readings".to_string(),
+            source: Some("xsense".to_string()),
+            related_id: Some("18c1".to_string()),
+            text: "3 readings".to_string(),
+            thread_ts: None,
+            status,
+            error: (status == DeliveryStatus::Failed).then(|| "channel_not_found".to_string()),
+            message_ts: (status == DeliveryStatus::Sent).then(|| "1700000000.000100".to_string()),
+            attempts: 1,
+            next_attempt_at: (status == DeliveryStatus::Failed).then_some(created_at),
+        };
+        let now = Utc::now();
+        let sent = storage.record_notification(&notification(DeliveryStatus::Sent, now - chrono::TimeDelta::minutes(5))).await.unwrap();
+        let failed = storage.record_notification(&notification(DeliveryStatus::Failed, now)).await.unwrap();
+        assert_ne!(sent, failed);
+
+        let all = storage.notifications(false, 10).await.unwrap();
+        assert_eq!(all.iter().map(|n| n.id).collect::<Vec<_>>(), [failed, sent]);
+        assert_eq!(all[1].message_ts.as_deref(), Some("1700000000.000100"));
+
+        let failures = storage.notifications(true, 10).await.unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].status, failures[0].error.as_deref()), (DeliveryStatus::Failed, Some("channel_not_found")));
+        assert_eq!(storage.notifications(false, 1).await.unwrap().len(), 1);
+
+        // A claimed message is not handed out again before its lease ends
+        let claimed = storage.claim_notifications(now, chrono::TimeDelta::minutes(5), 10).await.unwrap();
+        assert_eq!(claimed.iter().map(|n| n.id).collect::<Vec<_>>(), [failed]);
+        assert!(storage.claim_notifications(now, chrono::TimeDelta::minutes(5), 10).await.unwrap().is_empty());
+
+        let mut delivered = claimed[0].clone();
+        delivered.status = DeliveryStatus::Sent;
+        delivered.attempts = 2;
+        delivered.next_attempt_at = None;
+        storage.update_notification(&delivered).await.unwrap();
+        assert!(storage.notifications(true, 10).await.unwrap().is_empty());
+        assert!(storage.claim_notifications(now + chrono::TimeDelta::hours(1), chrono::TimeDelta::minutes(5), 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_generic_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap()
+            .with_layout(StorageLayout::Generic);
+
+        let stats = storage.save_temperature_readings(&[reading("cabane", 10)]).await.unwrap();
+        assert_eq!(stats.inserted, 1);
+        let stats = storage.save_temperature_readings(&[reading("cabane", 10)]).await.unwrap();
+        assert_eq!(stats.duplicates, 1);
+
+        let pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, 8, 0, 0).unwrap(),
+            temperature: Some(26.0),
+            ph: None,
+            orp: Some(650),
+        };
+        assert!(storage.save_pool_reading(&pool, "msg-1").await.unwrap());
+        assert!(storage.pool_reading_exists("main", "msg-1").await.unwrap());
+
+        // temperature + humidity + derived mold_risk for the sensor, temperature + orp for the pool
+        let metrics: Vec<(String, String)> = sqlx::query_as("SELECT sensor_id, metric FROM readings ORDER BY sensor_id, metric")
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(metrics.len(), 5);
+        assert_eq!(metrics[1], ("cabane".to_string(), "mold_risk".to_string()));
+        assert_eq!(metrics[3], ("pool/main".to_string(), "orp".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_delete_readings() {
+        use crate::email::{Provenance, Reading};
+        use crate::storage::provenance_records;
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let cabane = vec![reading("cabane", 10), reading("cabane", 11), reading("cabane", 12)];
+        let garage = vec![reading("garage", 12)];
+        storage.save_temperature_readings(&cabane).await.unwrap();
+        storage.save_temperature_readings(&garage).await.unwrap();
+        let pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, 8, 0, 0).unwrap(),
+            temperature: Some(26.0),
+            ph: None,
+            orp: Some(650),
+        };
+        assert!(storage.save_pool_reading(&pool, "msg-1").await.unwrap());
+
+        let first = Provenance::email("xsense", "msg-1", Some("cabane.csv")).with_content(b"cabane");
+        let second = Provenance::email("xsense", "msg-2", Some("garage.csv")).with_content(b"garage");
+        let mut stored: Vec<_> = cabane.iter().map(|r| Reading::from_temperature(r, None, first.clone())).collect();
+        stored.push(Reading::from_pool(&pool, None, Provenance::email("blueriot", "msg-1", None)));
+        stored.extend(garage.iter().map(|r| Reading::from_temperature(r, None, second.clone())));
+        storage.record_provenance(&provenance_records(&stored, Utc::now())).await.unwrap();
+
+        // Counting rolls back
+        let scope = DeleteScope::Email("msg-1".to_string());
+        for apply in [false, true] {
+            let deleted = storage.delete_readings(&scope, apply).await.unwrap();
+            assert_eq!((deleted.per_sensor["cabane"], deleted.per_sensor["pool/main"], deleted.provenance), (3, 1, 2));
+        }
+        assert_eq!(storage.delete_readings(&scope, true).await.unwrap().total(), 0);
+
+        let hash = second.file_hash.unwrap();
+        for pattern in ["", "%", "________"] {
+            assert!(storage.delete_readings(&DeleteScope::Attachment(pattern.to_string()), true).await.is_err());
+        }
+        let deleted = storage.delete_readings(&DeleteScope::Attachment(hash[..8].to_uppercase()), true).await.unwrap();
+        assert_eq!((deleted.total(), deleted.provenance), (1, 1));
+
+        storage.save_temperature_readings(&cabane).await.unwrap();
+        let sensor = DeleteScope::Sensor {
+            sensor_id: "cabane".to_string(),
+            from: Utc.with_ymd_and_hms(2025, 1, 15, 11, 0, 0).unwrap(),
+            to: Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap(),
+        };
+        assert_eq!(storage.delete_readings(&sensor, true).await.unwrap().total(), 2);
+    }
+}
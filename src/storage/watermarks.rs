@@ -0,0 +1,117 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::xsense::TemperatureReading;
+
+/// Marks older than this are ignored, so rows changed outside the process are seen again
+const MARK_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Per-sensor marks of the last stored batch, kept across the runs of a daemon
+///
+/// Keyed by storage scope (database, layout, site) then sensor.
+static MARKS: Mutex<BTreeMap<(String, String), SensorMark>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy)]
+struct SensorMark {
+    /// Hash of the last batch of readings stored for the sensor
+    batch_hash: u64,
+    recorded_at: Instant,
+}
+
+/// Readings of a batch grouped by sensor, in batch order
+fn by_sensor(readings: &[TemperatureReading]) -> HashMap<&str, Vec<&TemperatureReading>> {
+    let mut groups: HashMap<&str, Vec<&TemperatureReading>> = HashMap::new();
+    for reading in readings {
+        groups.entry(reading.sensor_id.as_str()).or_default().push(reading);
+    }
+    groups
+}
+
+fn batch_hash(readings: &[&TemperatureReading]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for reading in readings {
+        reading.timestamp.hash(&mut hasher);
+        reading.temperature.to_bits().hash(&mut hasher);
+        reading.humidity.map(f64::to_bits).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Sensors of the batch whose readings are all known to be stored already
+///
+/// Only an exact repeat of the readings an earlier run stored qualifies; any other
+/// batch, older readings included, goes to the database.
+pub(crate) fn stored_sensors(scope: &str, readings: &[TemperatureReading]) -> HashSet<String> {
+    let marks = MARKS.lock().unwrap_or_else(|e| e.into_inner());
+    by_sensor(readings)
+        .into_iter()
+        .filter(|(sensor_id, group)| {
+            let Some(mark) = marks.get(&(scope.to_string(), sensor_id.to_string())) else {
+                return false;
+            };
+            mark.recorded_at.elapsed() < MARK_TTL && mark.batch_hash == batch_hash(group)
+        })
+        .map(|(sensor_id, _)| sensor_id.to_string())
+        .collect()
+}
+
+/// Remember a batch once committed
+pub(crate) fn record(scope: &str, readings: &[TemperatureReading]) {
+    let mut marks = MARKS.lock().unwrap_or_else(|e| e.into_inner());
+    for (sensor_id, group) in by_sensor(readings) {
+        marks.insert((scope.to_string(), sensor_id.to_string()), SensorMark {
+            batch_hash: batch_hash(&group),
+            recorded_at: Instant::now(),
+        });
+    }
+}
+
+/// Forget the sensors of a batch whose save failed
+pub(crate) fn invalidate(scope: &str, readings: &[TemperatureReading]) {
+    let mut marks = MARKS.lock().unwrap_or_else(|e| e.into_inner());
+    for sensor_id in by_sensor(readings).into_keys() {
+        marks.remove(&(scope.to_string(), sensor_id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    fn reading(sensor: &str, hour: u32, temperature: f64) -> TemperatureReading {
+        TemperatureReading {
+            sensor_id: sensor.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, hour, 0, 0).unwrap(),
+            temperature,
+            humidity: None,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_stored_sensors() {
+        let scope = "test_stored_sensors";
+        let batch = vec![reading("cabane", 10, 19.5), reading("cabane", 11, 19.0), reading("garage", 11, 8.0)];
+        assert!(stored_sensors(scope, &batch).is_empty());
+
+        record(scope, &batch);
+        assert_eq!(stored_sensors(scope, &batch).len(), 2);
+        assert!(stored_sensors("other_site", &batch).is_empty());
+
+        // Same timestamps with a different value, or a newer reading: back to the database
+        assert!(stored_sensors(scope, &[reading("cabane", 10, 19.5), reading("cabane", 11, 19.1)]).is_empty());
+        assert!(stored_sensors(scope, &[reading("garage", 12, 8.0)]).is_empty());
+
+        // Older readings may be missing from the database: never assumed stored
+        let mut old = reading("garage", 11, 8.0);
+        old.timestamp -= TimeDelta::days(2);
+        assert!(stored_sensors(scope, &[old]).is_empty());
+
+        invalidate(scope, &batch);
+        assert!(stored_sensors(scope, &batch).is_empty());
+    }
+}
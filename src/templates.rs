@@ -0,0 +1,269 @@
+use anyhow::{Result, Context};
+use log::{info, warn};
+use minijinja::{Environment, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::units::UnitSystem;
+use crate::xsense::TemperatureReading;
+
+/// Notification kinds that can be overridden by a user template
+///
+/// Each one maps to `<name>.j2` (or `<name>.jinja`) in `NOTIFICATION_TEMPLATES_DIR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationEvent {
+    XSenseData,
+    PoolReading,
+    ProcessingError,
+    DeadLetter,
+    RunSummary,
+    NoEmails,
+    PushedData,
+    FileReceived,
+    FileError,
+    Alert,
+    AlertWebhook,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 11] = [
+        NotificationEvent::XSenseData,
+        NotificationEvent::PoolReading,
+        NotificationEvent::ProcessingError,
+        NotificationEvent::DeadLetter,
+        NotificationEvent::RunSummary,
+        NotificationEvent::NoEmails,
+        NotificationEvent::PushedData,
+        NotificationEvent::FileReceived,
+        NotificationEvent::FileError,
+        NotificationEvent::Alert,
+        NotificationEvent::AlertWebhook,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationEvent::XSenseData => "xsense_data",
+            NotificationEvent::PoolReading => "pool_reading",
+            NotificationEvent::ProcessingError => "processing_error",
+            NotificationEvent::DeadLetter => "dead_letter",
+            NotificationEvent::RunSummary => "run_summary",
+            NotificationEvent::NoEmails => "no_emails",
+            NotificationEvent::PushedData => "pushed_data",
+            NotificationEvent::FileReceived => "file_received",
+            NotificationEvent::FileError => "file_error",
+            NotificationEvent::Alert => "alert",
+            NotificationEvent::AlertWebhook => "alert_webhook",
+        }
+    }
+}
+
+/// Per-sensor statistics exposed to templates as `sensors`
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorStats {
+    pub name: String,
+    pub count: usize,
+    /// Temperatures in the configured display units
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    /// Average over the period of the same length just before `first` (stored readings)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_avg: Option<f64>,
+    /// `avg - previous_avg`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<f64>,
+    pub first: String,
+    pub last: String,
+    /// Export files the readings came from (empty for pushed readings)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+impl SensorStats {
+    /// Compare with the average of the previous period, in display units
+    pub fn set_previous_avg(&mut self, previous_avg: f64) {
+        self.previous_avg = Some(previous_avg);
+        self.delta = Some(self.avg - previous_avg);
+    }
+}
+
+/// Group readings by sensor with count, min/max/average and time range
+pub fn sensor_stats(readings: &[TemperatureReading], units: UnitSystem) -> Vec<SensorStats> {
+    let mut by_sensor: BTreeMap<&str, Vec<&TemperatureReading>> = BTreeMap::new();
+    for reading in readings {
+        by_sensor.entry(reading.sensor_id.as_str()).or_default().push(reading);
+    }
+
+    by_sensor.into_iter()
+        .map(|(name, sensor_readings)| {
+            let temperatures = sensor_readings.iter().map(|r| r.temperature);
+            let min = temperatures.clone().fold(f64::INFINITY, f64::min);
+            let max = temperatures.clone().fold(f64::NEG_INFINITY, f64::max);
+            let avg = temperatures.sum::<f64>() / sensor_readings.len() as f64;
+            let first = sensor_readings.iter().map(|r| r.timestamp).min().unwrap_or_default();
+            let last = sensor_readings.iter().map(|r| r.timestamp).max().unwrap_or_default();
+
+            SensorStats {
+                name: name.to_string(),
+                count: sensor_readings.len(),
+                min: units.temperature(min),
+                max: units.temperature(max),
+                avg: units.temperature(avg),
+                previous_avg: None,
+                delta: None,
+                first: first.format("%Y-%m-%d %H:%M").to_string(),
+                last: last.format("%Y-%m-%d %H:%M").to_string(),
+                files: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// Per-device statistics of a multi-file export (one CSV per device)
+///
+/// Readings of a device spread over several files are merged, and each
+/// device lists the files it came from.
+pub fn device_stats(files: &[(String, Vec<TemperatureReading>)], units: UnitSystem) -> Vec<SensorStats> {
+    let readings: Vec<TemperatureReading> = files.iter()
+        .flat_map(|(_, readings)| readings.iter().cloned())
+        .collect();
+
+    let mut stats = sensor_stats(&readings, units);
+    for device in &mut stats {
+        device.files = files.iter()
+            .filter(|(_, readings)| readings.iter().any(|r| r.sensor_id == device.name))
+            .map(|(filename, _)| filename.clone())
+            .collect();
+    }
+    stats
+}
+
+/// User-defined notification bodies (minijinja), falling back to the built-in messages
+///
+/// Every template also receives `units` (temperature symbol) and `locale`.
+#[derive(Clone, Default)]
+pub struct NotificationTemplates {
+    env: Option<Arc<Environment<'static>>>,
+    units: &'static str,
+    locale: &'static str,
+}
+
+impl NotificationTemplates {
+    /// Load the templates from `NOTIFICATION_TEMPLATES_DIR`, if defined
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut templates = NotificationTemplates {
+            env: None,
+            units: config.units.temperature_symbol(),
+            locale: match config.locale {
+                crate::i18n::Locale::En => "en",
+                crate::i18n::Locale::Fr => "fr",
+            },
+        };
+
+        if let Some(dir) = &config.notification_templates_dir {
+            templates.env = Some(Arc::new(Self::load_dir(Path::new(dir))?));
+        }
+
+        Ok(templates)
+    }
+
+    fn load_dir(dir: &Path) -> Result<Environment<'static>> {
+        let mut env = Environment::new();
+
+        for event in NotificationEvent::ALL {
+            let path = ["j2", "jinja"].iter()
+                .map(|ext| dir.join(format!("{}.{}", event.name(), ext)))
+                .find(|path| path.is_file());
+
+            if let Some(path) = path {
+                let source = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Unable to read template {}", path.display()))?;
+                env.add_template_owned(event.name(), source)
+                    .with_context(|| format!("Invalid template {}", path.display()))?;
+                info!("Notification template loaded: {}", path.display());
+            }
+        }
+
+        if env.templates().next().is_none() {
+            warn!("No notification templates found in {} - using built-in messages", dir.display());
+        }
+
+        Ok(env)
+    }
+
+    /// Render the user template for this event, or build the default message
+    pub fn render_or(&self, event: NotificationEvent, context: Value, default: impl FnOnce() -> String) -> String {
+        self.render(event, context).unwrap_or_else(default)
+    }
+
+    fn render(&self, event: NotificationEvent, context: Value) -> Option<String> {
+        let template = self.env.as_ref()?.get_template(event.name()).ok()?;
+        let context = minijinja::context! { units => self.units, locale => self.locale, ..context };
+
+        match template.render(context) {
+            Ok(message) => Some(message.trim().to_string()),
+            Err(e) => {
+                warn!("Unable to render {} template, using built-in message: {:#}", event.name(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn reading(sensor: &str, hour: u32, temperature: f64) -> TemperatureReading {
+        TemperatureReading {
+            sensor_id: sensor.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, hour, 0, 0).unwrap(),
+            temperature,
+            humidity: None,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let mut env = Environment::new();
+        env.add_template_owned(
+            "xsense_data",
+            "{% for s in sensors %}{{ s.name }} {{ s.min }}-{{ s.max }}{{ units }}{% endfor %}".to_string(),
+        ).unwrap();
+        let templates = NotificationTemplates {
+            env: Some(Arc::new(env)),
+            units: "°C",
+            locale: "en",
+        };
+
+        let readings = vec![reading("cabane", 8, 2.5), reading("cabane", 9, 4.0)];
+        let context = minijinja::context! { sensors => sensor_stats(&readings, UnitSystem::Metric) };
+        let message = templates.render_or(NotificationEvent::XSenseData, context, || "default".to_string());
+        assert_eq!(message, "cabane 2.5-4.0°C");
+
+        // Events without a user template fall back to the built-in message
+        let message = templates.render_or(NotificationEvent::FileError, Value::UNDEFINED, || "default".to_string());
+        assert_eq!(message, "default");
+    }
+
+    #[test]
+    fn test_device_stats() {
+        let files = vec![
+            ("cabane_1.csv".to_string(), vec![reading("cabane", 8, 2.5), reading("cabane", 9, 4.0)]),
+            ("cabane_2.csv".to_string(), vec![reading("cabane", 12, 6.0)]),
+            ("patio.csv".to_string(), vec![reading("patio", 8, 1.0)]),
+        ];
+
+        let devices = device_stats(&files, UnitSystem::Metric);
+        assert_eq!(devices.len(), 2);
+        assert_eq!((devices[0].name.as_str(), devices[0].count), ("cabane", 3));
+        assert_eq!(devices[0].files, vec!["cabane_1.csv", "cabane_2.csv"]);
+        assert_eq!((devices[0].first.as_str(), devices[0].last.as_str()), ("2025-01-15 08:00", "2025-01-15 12:00"));
+        assert_eq!(devices[1].files, vec!["patio.csv"]);
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+
+use crate::email::common::{pool_metrics, temperature_metrics};
+use crate::email::{Metric, MetricSet};
+use crate::i18n::Locale;
+use crate::storage::{ProvenanceRecord, ReadingRange, Storage, MAX_PAGE_SIZE};
+use crate::units::UnitSystem;
+
+/// Where a stored reading comes from (`homemetrics trace`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub sensor_id: String,
+    pub at: DateTime<Utc>,
+    /// Stored reading closest to `at`, with its timestamp
+    pub reading: Option<(DateTime<Utc>, MetricSet)>,
+    /// Emails and attachments that brought it, oldest run first
+    pub origins: Vec<ProvenanceRecord>,
+}
+
+/// "YYYY-MM-DD HH:MM[:SS]" in UTC, or RFC 3339
+pub fn parse_at(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|at| at.and_utc())
+        .ok_or_else(|| format!("invalid time '{}' (expected YYYY-MM-DD HH:MM, UTC)", value))
+}
+
+/// SHA-256 of an attachment, or a prefix of at least 8 digits of it as shown by `trace`
+pub fn parse_hash(value: &str) -> Result<String, String> {
+    let value = value.trim().to_lowercase();
+    if value.len() < 8 || value.len() > 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid attachment hash '{}' (expected 8 to 64 hexadecimal digits)", value));
+    }
+    Ok(value)
+}
+
+/// Find the reading of a sensor closest to `at` within `within`, and the
+/// emails and attachments it was stored from
+///
+/// Pools are traced as `pool/<id>`.
+pub async fn trace(storage: &dyn Storage, sensor_id: &str, at: DateTime<Utc>, within: TimeDelta) -> Result<Trace> {
+    let (from, to) = (at - within, at + within);
+    let distance = |timestamp: DateTime<Utc>| (timestamp - at).abs();
+
+    let reading = match sensor_id.strip_prefix("pool/") {
+        Some(pool_id) => storage.pool_readings(Some(from), Some(to)).await?
+            .into_iter()
+            .filter(|reading| reading.pool_id == pool_id)
+            .min_by_key(|reading| distance(reading.timestamp))
+            .map(|reading| (reading.timestamp, pool_metrics(&reading))),
+        None => {
+            let range = ReadingRange {
+                sensor_id: sensor_id.to_string(),
+                from: Some(from),
+                to: Some(to),
+                page_size: MAX_PAGE_SIZE,
+                ..Default::default()
+            };
+            storage.get_readings_range(&range).await?
+                .readings
+                .into_iter()
+                .min_by_key(|reading| distance(reading.timestamp))
+                .map(|reading| (reading.timestamp, temperature_metrics(&reading)))
+        }
+    };
+
+    // The origins of the reading found, or of anything stored around `at`
+    let (from, to) = reading.as_ref().map_or((from, to), |(timestamp, _)| (*timestamp, *timestamp));
+    let origins = storage.provenance(sensor_id, from, to).await?;
+    Ok(Trace { sensor_id: sensor_id.to_string(), at, reading, origins })
+}
+
+pub fn render_trace(trace: &Trace, locale: Locale, units: UnitSystem) -> String {
+    let format = |at: DateTime<Utc>| at.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+    let mut lines = vec![match &trace.reading {
+        Some((timestamp, metrics)) => {
+            let values: Vec<String> = metrics.iter().map(|(metric, value)| match metric {
+                Metric::Temperature => format!("{} {}", metric.key(), units.format_temperature(*value)),
+                Metric::Humidity => format!("{} {:.0}%", metric.key(), value),
+                Metric::Ph => format!("{} {:.2}", metric.key(), value),
+                Metric::Orp => format!("{} {:.0} mV", metric.key(), value),
+                Metric::MoldRisk => format!("{} {:.2}", metric.key(), value),
+            }).collect();
+            locale.trace_reading(&trace.sensor_id, &format(*timestamp), &values.join(", "))
+        }
+        None => locale.trace_no_reading(&trace.sensor_id, &format(trace.at)),
+    }];
+
+    for origin in &trace.origins {
+        let span = if origin.first_timestamp == origin.last_timestamp {
+            format(origin.first_timestamp)
+        } else {
+            format!("{} → {}", format(origin.first_timestamp), format(origin.last_timestamp))
+        };
+        // The first 12 digits of the hash are enough for `db delete --attachment`
+        let file = origin.file.as_ref().map(|file| match &origin.file_hash {
+            Some(hash) => format!("{} [{}]", file, &hash[..hash.len().min(12)]),
+            None => file.clone(),
+        });
+        lines.push(locale.trace_origin(&origin.source, &origin.email_id, file.as_deref(), origin.readings,
+                                       &span, &format(origin.run_started_at)));
+    }
+    if trace.origins.is_empty() && trace.reading.is_some() {
+        lines.push(locale.trace_no_provenance().to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::{Provenance, Reading};
+    use crate::storage::{provenance_records, SqliteStorage};
+    use crate::xsense::TemperatureReading;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_at() {
+        let at = Utc.with_ymd_and_hms(2024, 5, 1, 14, 0, 0).unwrap();
+        assert_eq!(parse_at("2024-05-01 14:00"), Ok(at));
+        assert_eq!(parse_at("2024-05-01T16:00:00+02:00"), Ok(at));
+        assert!(parse_at("yesterday").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trace_reading() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let readings: Vec<_> = [13, 14, 15].into_iter().map(|hour| TemperatureReading {
+            sensor_id: "patio".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, hour, 0, 0).unwrap(),
+            temperature: 21.5,
+            humidity: Some(48.0),
+            location: None,
+        }).collect();
+        storage.save_temperature_readings(&readings).await.unwrap();
+
+        let run = Utc.with_ymd_and_hms(2024, 5, 2, 2, 0, 0).unwrap();
+        let provenance = Provenance::email("xsense", "18c2", Some("export.csv"));
+        let stored: Vec<_> = readings.iter().map(|reading| Reading::from_temperature(reading, None, provenance.clone())).collect();
+        storage.record_provenance(&provenance_records(&stored, run)).await.unwrap();
+
+        let found = trace(&storage, "patio", Utc.with_ymd_and_hms(2024, 5, 1, 14, 10, 0).unwrap(), TimeDelta::hours(1)).await.unwrap();
+        assert_eq!(found.reading.as_ref().map(|(timestamp, _)| timestamp.to_rfc3339()), Some("2024-05-01T14:00:00+00:00".to_string()));
+        assert_eq!(found.origins.len(), 1);
+        assert_eq!((found.origins[0].readings, found.origins[0].file.as_deref()), (3, Some("export.csv")));
+
+        let text = render_trace(&found, Locale::En, UnitSystem::Metric);
+        assert_eq!(text, "\
+📍 patio at 2024-05-01 14:00:00 UTC: temperature 21.5°C, humidity 48%, mold_risk 0.00
+  ✉️  xsense email 18c2, export.csv (3 reading(s), 2024-05-01 13:00:00 UTC → 2024-05-01 15:00:00 UTC) - run of 2024-05-02 02:00:00 UTC");
+
+        let missing = trace(&storage, "patio", Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(), TimeDelta::hours(1)).await.unwrap();
+        assert!(missing.reading.is_none() && missing.origins.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use log::warn;
+use serde::Deserialize;
+
+use crate::blueriot::PoolReading;
+use crate::email::Metric;
+use crate::xsense::TemperatureReading;
+
+/// Change applied to the readings of a source before they are validated and stored
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    /// `rename:Capteur 1=salon`
+    Rename { from: String, to: String },
+    /// `offset:salon.temperature=-0.5`, calibration of a sensor reading too high or too low
+    Offset { sensor: String, metric: Metric, value: f64 },
+    /// `scale:pool/main.orp=1.05`
+    Scale { sensor: String, metric: Metric, factor: f64 },
+    /// `round:*.temperature=1`, number of decimals kept
+    Round { sensor: String, metric: Metric, decimals: u32 },
+    /// `convert:garage.temperature=fahrenheit`, from a sensor reporting in °F to the stored °C
+    Convert { sensor: String, metric: Metric },
+    /// `site:chalet`, site stamped on the readings of the source instead of `SITE`
+    Site(String),
+}
+
+/// Ordered transforms of a source (`<SOURCE>_TRANSFORMS`)
+///
+/// Each transform sees the result of the previous ones: selectors after a
+/// `rename` use the new sensor name. `*` selects every sensor.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TransformChain {
+    transforms: Vec<Transform>,
+}
+
+impl TransformChain {
+    /// Parse transforms separated by ';' (`rename:Capteur 1=salon;offset:salon.temperature=-0.5`)
+    ///
+    /// Invalid entries are logged and skipped.
+    pub fn parse(value: &str) -> Self {
+        let transforms = value
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let transform = parse_transform(entry);
+                if transform.is_none() {
+                    warn!("Invalid transform '{}' (expected rename:old=new, offset|scale|round|convert:sensor.metric=value or site:name) - ignored", entry);
+                }
+                transform
+            })
+            .collect();
+        TransformChain { transforms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Site set by the last `site` transform
+    pub fn site(&self) -> Option<&str> {
+        self.transforms.iter().rev().find_map(|transform| match transform {
+            Transform::Site(site) => Some(site.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn apply_temperature(&self, reading: &mut TemperatureReading) {
+        let mut values = [
+            (Metric::Temperature, Some(&mut reading.temperature)),
+            (Metric::Humidity, reading.humidity.as_mut()),
+        ];
+        self.apply(&mut reading.sensor_id, &mut values);
+    }
+
+    /// Pools are selected and renamed as `pool/<id>`
+    pub fn apply_pool(&self, reading: &mut PoolReading) {
+        let mut sensor_id = reading.sensor_id();
+        let mut orp = reading.orp.map(f64::from);
+        let mut values = [
+            (Metric::Temperature, reading.temperature.as_mut()),
+            (Metric::Ph, reading.ph.as_mut()),
+            (Metric::Orp, orp.as_mut()),
+        ];
+        self.apply(&mut sensor_id, &mut values);
+        reading.orp = orp.map(|orp| orp.round() as i32);
+        reading.pool_id = sensor_id.strip_prefix("pool/").unwrap_or(&sensor_id).to_string();
+    }
+
+    fn apply(&self, sensor_id: &mut String, values: &mut [(Metric, Option<&mut f64>)]) {
+        for transform in &self.transforms {
+            if let Transform::Rename { from, to } = transform {
+                if sensor_id == from {
+                    *sensor_id = to.clone();
+                }
+                continue;
+            }
+            let Some((sensor, metric)) = transform.target() else {
+                continue;
+            };
+            if sensor != "*" && sensor != sensor_id {
+                continue;
+            }
+            for (_, value) in values.iter_mut().filter(|(candidate, _)| *candidate == metric) {
+                if let Some(value) = value {
+                    **value = transform.change(**value);
+                }
+            }
+        }
+    }
+}
+
+impl Transform {
+    /// Sensor selector and metric changed by a value transform
+    fn target(&self) -> Option<(&str, Metric)> {
+        match self {
+            Transform::Offset { sensor, metric, .. }
+            | Transform::Scale { sensor, metric, .. }
+            | Transform::Round { sensor, metric, .. }
+            | Transform::Convert { sensor, metric } => Some((sensor, *metric)),
+            Transform::Rename { .. } | Transform::Site(_) => None,
+        }
+    }
+
+    fn change(&self, value: f64) -> f64 {
+        match self {
+            Transform::Offset { value: offset, .. } => value + offset,
+            Transform::Scale { factor, .. } => value * factor,
+            Transform::Round { decimals, .. } => {
+                let scale = 10f64.powi(*decimals as i32);
+                (value * scale).round() / scale
+            }
+            Transform::Convert { .. } => (value - 32.0) * 5.0 / 9.0,
+            Transform::Rename { .. } | Transform::Site(_) => value,
+        }
+    }
+}
+
+fn parse_transform(entry: &str) -> Option<Transform> {
+    let (kind, rest) = entry.split_once(':')?;
+    let kind = kind.trim().to_lowercase();
+    if kind == "site" {
+        let site = rest.trim();
+        return (!site.is_empty()).then(|| Transform::Site(site.to_string()));
+    }
+
+    let (target, value) = rest.split_once('=')?;
+    let (target, value) = (target.trim(), value.trim());
+    if kind == "rename" {
+        return (!target.is_empty() && !value.is_empty())
+            .then(|| Transform::Rename { from: target.to_string(), to: value.to_string() });
+    }
+
+    let (sensor, metric) = target.rsplit_once('.')?;
+    let (sensor, metric) = (sensor.trim().to_string(), Metric::parse(metric.trim())?);
+    if sensor.is_empty() || metric == Metric::MoldRisk {
+        return None;
+    }
+    match kind.as_str() {
+        "offset" => Some(Transform::Offset { sensor, metric, value: value.parse().ok()? }),
+        "scale" => Some(Transform::Scale { sensor, metric, factor: value.parse().ok()? }),
+        "round" => Some(Transform::Round { sensor, metric, decimals: value.parse().ok().filter(|decimals| *decimals <= 6)? }),
+        "convert" if metric == Metric::Temperature && value.eq_ignore_ascii_case("fahrenheit") => {
+            Some(Transform::Convert { sensor, metric })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_parse_transforms() {
+        let chain = TransformChain::parse(
+            "rename:Capteur 1=salon; offset:salon.temperature=-0.5;round:*.humidity=0;site:chalet;\
+             convert:garage.humidity=fahrenheit;scale:salon.mold_risk=2;offset:salon.co2=1;bogus"
+        );
+        assert_eq!(chain.transforms, vec![
+            Transform::Rename { from: "Capteur 1".to_string(), to: "salon".to_string() },
+            Transform::Offset { sensor: "salon".to_string(), metric: Metric::Temperature, value: -0.5 },
+            Transform::Round { sensor: "*".to_string(), metric: Metric::Humidity, decimals: 0 },
+            Transform::Site("chalet".to_string()),
+        ]);
+        assert_eq!(chain.site(), Some("chalet"));
+        assert!(TransformChain::parse("").is_empty());
+    }
+
+    #[test]
+    fn test_apply_transforms() {
+        let chain = TransformChain::parse(
+            "rename:Capteur 1=salon;offset:salon.temperature=-0.5;convert:garage.temperature=fahrenheit;\
+             round:*.temperature=1;round:*.humidity=0;rename:pool/spa=pool/jacuzzi;scale:*.orp=1.1"
+        );
+        let mut room = TemperatureReading {
+            sensor_id: "Capteur 1".to_string(),
+            timestamp: Utc::now(),
+            temperature: 21.27,
+            humidity: Some(45.6),
+            location: None,
+        };
+        chain.apply_temperature(&mut room);
+        assert_eq!((room.sensor_id.as_str(), room.temperature, room.humidity), ("salon", 20.8, Some(46.0)));
+
+        let mut garage = TemperatureReading { sensor_id: "garage".to_string(), temperature: 50.0, humidity: None, ..room };
+        chain.apply_temperature(&mut garage);
+        assert_eq!(garage.temperature, 10.0);
+
+        let mut pool = PoolReading {
+            pool_id: "spa".to_string(),
+            timestamp: Utc::now(),
+            temperature: None,
+            ph: Some(7.2),
+            orp: Some(650),
+        };
+        chain.apply_pool(&mut pool);
+        assert_eq!((pool.pool_id.as_str(), pool.ph, pool.orp), ("jacuzzi", Some(7.2), Some(715)));
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::blueriot::PoolReading;
+use crate::config::Config;
+use crate::scheduler::{upcoming_runs, ScheduledRun};
+use crate::storage::{ReadingRange, SensorType, SortOrder, SourceActivity, Storage};
+use crate::xsense::TemperatureReading;
+
+/// Pool readings older than this are not shown
+const POOL_LOOKBACK_DAYS: i64 = 30;
+
+/// Latest reading of a sensor
+#[derive(Debug, Clone)]
+pub struct SensorLatest {
+    pub sensor_id: String,
+    pub location: Option<String>,
+    pub reading: TemperatureReading,
+}
+
+/// Everything read from the storage at each refresh of `homemetrics tui`
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    pub loaded_at: DateTime<Utc>,
+    pub sensors: Vec<SensorLatest>,
+    /// Latest reading of each pool
+    pub pools: Vec<PoolReading>,
+    pub sources: Vec<SourceActivity>,
+    pub dead_letters: usize,
+    pub failed_notifications: usize,
+}
+
+/// Read the latest reading of every sensor and pool, and the state of the runs
+pub async fn load_dashboard(storage: &dyn Storage, now: DateTime<Utc>) -> Result<Dashboard> {
+    let mut sensors = Vec::new();
+    // Pools have their own table below
+    let pool_type = Some(SensorType::Pool.as_str());
+    for sensor in storage.sensors().await?.into_iter().filter(|sensor| sensor.sensor_type.as_deref() != pool_type) {
+        let range = ReadingRange {
+            sensor_id: sensor.sensor_id.clone(),
+            order: SortOrder::Desc,
+            page_size: 1,
+            ..Default::default()
+        };
+        if let Some(reading) = storage.get_readings_range(&range).await?.readings.into_iter().next() {
+            sensors.push(SensorLatest { sensor_id: sensor.sensor_id, location: sensor.location, reading });
+        }
+    }
+
+    // Readings come oldest first: the last one of each pool wins
+    let mut pools: BTreeMap<String, PoolReading> = BTreeMap::new();
+    for reading in storage.pool_readings(Some(now - TimeDelta::days(POOL_LOOKBACK_DAYS)), None).await? {
+        pools.insert(reading.pool_id.clone(), reading);
+    }
+
+    Ok(Dashboard {
+        loaded_at: now,
+        sensors,
+        pools: pools.into_values().collect(),
+        sources: storage.source_activities().await?,
+        dead_letters: storage.dead_letters().await?.len(),
+        failed_notifications: storage.notifications(true, 1000).await?.len(),
+    })
+}
+
+/// Show the dashboard until `q` or Esc, reading the storage every `refresh`
+///
+/// Log records are muted while the dashboard is on screen, they would
+/// otherwise be written over it.
+pub async fn run(config: &Config, storage: &dyn Storage, refresh: Duration) -> Result<()> {
+    let max_level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, config, storage, refresh).await;
+    ratatui::restore();
+    log::set_max_level(max_level);
+    result
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, config: &Config, storage: &dyn Storage, refresh: Duration) -> Result<()> {
+    let mut dashboard = None;
+    let mut error = None;
+    let mut next_refresh = Instant::now();
+    loop {
+        if Instant::now() >= next_refresh {
+            match load_dashboard(storage, Utc::now()).await {
+                Ok(loaded) => {
+                    dashboard = Some(loaded);
+                    error = None;
+                }
+                // Keep showing the previous readings with the error below
+                Err(e) => error = Some(format!("{:#}", e)),
+            }
+            next_refresh = Instant::now() + refresh;
+        }
+
+        let upcoming = upcoming_runs(config, Local::now().naive_local());
+        terminal.draw(|frame| draw(frame, config, dashboard.as_ref(), error.as_deref(), &upcoming))?;
+
+        let timeout = next_refresh.saturating_duration_since(Instant::now()).min(Duration::from_millis(250));
+        if !tokio::task::block_in_place(|| event::poll(timeout))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('r') => next_refresh = Instant::now(),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, config: &Config, dashboard: Option<&Dashboard>, error: Option<&str>, upcoming: &[ScheduledRun]) {
+    let locale = config.locale;
+    let units = config.units;
+    let [sensors_area, bottom_area, footer_area] = Layout::vertical([
+        Constraint::Min(6),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [pools_area, runs_area, schedule_area] = Layout::horizontal([
+        Constraint::Percentage(40),
+        Constraint::Percentage(35),
+        Constraint::Percentage(25),
+    ]).areas(bottom_area);
+    let header = |columns: Vec<String>| Row::new(columns).style(Style::new().add_modifier(Modifier::BOLD));
+    let at = |timestamp: DateTime<Utc>| timestamp.with_timezone(&Local).format("%m-%d %H:%M").to_string();
+
+    let sensors: Vec<Row> = dashboard.map_or(Vec::new(), |dashboard| dashboard.sensors.iter().map(|sensor| {
+        Row::new(vec![
+            sensor.sensor_id.clone(),
+            sensor.location.clone().unwrap_or_default(),
+            format!("{:.1}", units.temperature(sensor.reading.temperature)),
+            sensor.reading.humidity.map_or("-".to_string(), |humidity| format!("{:.0}", humidity)),
+            at(sensor.reading.timestamp),
+        ])
+    }).collect());
+    let table = Table::new(sensors, [Constraint::Fill(2), Constraint::Fill(2), Constraint::Length(8), Constraint::Length(6), Constraint::Length(12)])
+        .header(header(locale.tui_sensor_columns(units.temperature_symbol())))
+        .block(Block::bordered().title(locale.tui_sensors_title(config.profile.as_deref())));
+    frame.render_widget(table, sensors_area);
+
+    let pools: Vec<Row> = dashboard.map_or(Vec::new(), |dashboard| dashboard.pools.iter().map(|pool| {
+        Row::new(vec![
+            pool.pool_id.clone(),
+            pool.temperature.map_or("-".to_string(), |celsius| format!("{:.1}", units.temperature(celsius))),
+            pool.ph.map_or("-".to_string(), |ph| format!("{:.2}", ph)),
+            pool.orp.map_or("-".to_string(), |orp| orp.to_string()),
+            at(pool.timestamp),
+        ])
+    }).collect());
+    let table = Table::new(pools, [Constraint::Fill(1), Constraint::Length(6), Constraint::Length(5), Constraint::Length(5), Constraint::Length(12)])
+        .header(header(locale.tui_pool_columns(units.temperature_symbol())))
+        .block(Block::bordered().title(locale.tui_pools_title()));
+    frame.render_widget(table, pools_area);
+
+    let mut runs: Vec<Line> = dashboard.map_or(Vec::new(), |dashboard| dashboard.sources.iter().map(|source| {
+        let style = if source.empty_runs > 0 { Style::new().fg(Color::Yellow) } else { Style::new() };
+        Line::styled(locale.tui_source_line(&source.source, &at(source.last_run_at), &at(source.last_email_at), source.empty_runs), style)
+    }).collect());
+    if let Some(dashboard) = dashboard {
+        let style = if dashboard.dead_letters + dashboard.failed_notifications > 0 { Style::new().fg(Color::Red) } else { Style::new() };
+        runs.push(Line::styled(locale.tui_queue_line(dashboard.dead_letters, dashboard.failed_notifications), style));
+    }
+    frame.render_widget(Paragraph::new(runs).block(Block::bordered().title(locale.tui_runs_title())), runs_area);
+
+    let schedule: Vec<Line> = match upcoming {
+        [] => vec![Line::raw(locale.tui_no_schedule())],
+        upcoming => upcoming.iter().map(|run| Line::raw(format!("{}  {}", run.at.format("%m-%d %H:%M"), run.job))).collect(),
+    };
+    frame.render_widget(Paragraph::new(schedule).block(Block::bordered().title(locale.tui_schedule_title())), schedule_area);
+
+    let footer = match error {
+        Some(error) => Line::styled(locale.tui_load_error(error), Style::new().fg(Color::Red)),
+        None => Line::raw(locale.tui_footer(&dashboard.map_or("-".to_string(), |dashboard| at(dashboard.loaded_at)))),
+    };
+    frame.render_widget(Paragraph::new(footer), footer_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+
+    #[tokio::test]
+    async fn test_load_dashboard() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join("sandbox.sqlite")).await.unwrap();
+        let now = Utc::now();
+        let reading = |hours_ago, temperature| TemperatureReading {
+            sensor_id: "salon".to_string(),
+            timestamp: now - TimeDelta::hours(hours_ago),
+            temperature,
+            humidity: Some(45.0),
+            location: None,
+        };
+        storage.save_temperature_readings(&[reading(3, 18.0), reading(1, 19.5)]).await.unwrap();
+        for (hours_ago, ph) in [(5, 7.1), (2, 7.4)] {
+            let pool = PoolReading {
+                pool_id: "main".to_string(),
+                timestamp: now - TimeDelta::hours(hours_ago),
+                temperature: Some(24.0),
+                ph: Some(ph),
+                orp: None,
+            };
+            storage.save_pool_reading(&pool, &format!("email-{}", hours_ago)).await.unwrap();
+        }
+        storage.record_source_run("xsense", true).await.unwrap();
+
+        let dashboard = load_dashboard(&storage, now).await.unwrap();
+        assert_eq!(dashboard.sensors.len(), 1);
+        assert_eq!(dashboard.sensors[0].reading.temperature, 19.5);
+        assert_eq!(dashboard.pools.len(), 1);
+        assert_eq!(dashboard.pools[0].ph, Some(7.4));
+        assert_eq!(dashboard.sources[0].source, "xsense");
+        assert_eq!((dashboard.dead_letters, dashboard.failed_notifications), (0, 0));
+    }
+}
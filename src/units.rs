@@ -0,0 +1,66 @@
+use serde::Deserialize;
+
+/// Unit system used for display (CLI, Slack messages, API responses)
+///
+/// Storage always stays canonical: temperatures are saved in Celsius.
+/// ORP (mV) and pH are unit-independent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl UnitSystem {
+    /// Parse a `UNITS` value ("metric" or "imperial", case-insensitive)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "metric" | "si" | "celsius" => Some(UnitSystem::Metric),
+            "imperial" | "us" | "fahrenheit" => Some(UnitSystem::Imperial),
+            _ => None,
+        }
+    }
+
+    /// Convert a canonical Celsius temperature to this unit system
+    pub fn temperature(&self, celsius: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => celsius,
+            UnitSystem::Imperial => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Temperature unit symbol ("°C" or "°F")
+    pub fn temperature_symbol(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "°C",
+            UnitSystem::Imperial => "°F",
+        }
+    }
+
+    /// Format a canonical Celsius temperature with one decimal and its unit
+    pub fn format_temperature(&self, celsius: f64) -> String {
+        format!("{:.1}{}", self.temperature(celsius), self.temperature_symbol())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(UnitSystem::parse("metric"), Some(UnitSystem::Metric));
+        assert_eq!(UnitSystem::parse("Imperial"), Some(UnitSystem::Imperial));
+        assert_eq!(UnitSystem::parse("kelvin"), None);
+    }
+
+    #[test]
+    fn test_temperature_conversion() {
+        assert_eq!(UnitSystem::Metric.temperature(20.0), 20.0);
+        assert_eq!(UnitSystem::Imperial.temperature(0.0), 32.0);
+        assert_eq!(UnitSystem::Imperial.temperature(100.0), 212.0);
+        assert_eq!(UnitSystem::Imperial.format_temperature(25.5), "77.9°F");
+        assert_eq!(UnitSystem::Metric.format_temperature(25.5), "25.5°C");
+    }
+}
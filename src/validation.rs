@@ -0,0 +1,164 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::blueriot::PoolReading;
+use crate::xsense::TemperatureReading;
+
+/// Air temperature range accepted from X-Sense and pushed sensors (°C)
+const TEMPERATURE_RANGE: (f64, f64) = (-60.0, 80.0);
+/// Pool water temperature range (°C)
+const POOL_TEMPERATURE_RANGE: (f64, f64) = (-5.0, 50.0);
+const HUMIDITY_RANGE: (f64, f64) = (0.0, 100.0);
+const PH_RANGE: (f64, f64) = (0.0, 14.0);
+const ORP_RANGE: (i32, i32) = (-2000, 2000);
+/// Tolerated clock skew for readings dated in the future
+const MAX_FUTURE_SKEW_HOURS: i64 = 24;
+
+/// A reading refused by the validation stage, stored in `rejected_readings`
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedReading {
+    /// Origin of the reading: "xsense", "blueriot" or "ingest"
+    pub source: String,
+    pub sensor_id: String,
+    pub timestamp: DateTime<Utc>,
+    /// The reading as extracted, serialized to JSON
+    pub payload: String,
+    pub reason: String,
+    pub email_id: Option<String>,
+}
+
+impl RejectedReading {
+    fn new(source: &str, sensor_id: &str, timestamp: DateTime<Utc>, payload: &impl Serialize,
+           reason: String, email_id: Option<&str>) -> Self {
+        RejectedReading {
+            source: source.to_string(),
+            sensor_id: sensor_id.to_string(),
+            timestamp,
+            payload: serde_json::to_string(payload).unwrap_or_default(),
+            reason,
+            email_id: email_id.map(str::to_string),
+        }
+    }
+}
+
+/// Split extracted temperature readings into valid and rejected ones
+///
+/// Checks the schema (sensor id, finite values), plausible ranges, the
+/// timestamp and duplicates within the batch. Readings already stored are not
+/// rejected here: the storage reports them as duplicates.
+pub fn validate_temperature_readings(readings: Vec<TemperatureReading>, source: &str, email_id: Option<&str>)
+    -> (Vec<TemperatureReading>, Vec<RejectedReading>) {
+    let mut valid = Vec::with_capacity(readings.len());
+    let mut rejected = Vec::new();
+    let mut seen = BTreeSet::new();
+
+    for reading in readings {
+        let reason = temperature_reading_error(&reading)
+            .or_else(|| {
+                (!seen.insert((reading.sensor_id.clone(), reading.timestamp)))
+                    .then(|| "duplicate sensor/timestamp in the same batch".to_string())
+            });
+
+        match reason {
+            Some(reason) => rejected.push(RejectedReading::new(
+                source, &reading.sensor_id, reading.timestamp, &reading, reason, email_id,
+            )),
+            None => valid.push(reading),
+        }
+    }
+
+    (valid, rejected)
+}
+
+/// Check a pool reading, returning the rejection when it is invalid
+pub fn validate_pool_reading(reading: &PoolReading, email_id: &str) -> Option<RejectedReading> {
+    let reason = timestamp_error(reading.timestamp)
+        .or_else(|| reading.temperature.and_then(|t| range_error("temperature", t, POOL_TEMPERATURE_RANGE)))
+        .or_else(|| reading.ph.and_then(|ph| range_error("pH", ph, PH_RANGE)))
+        .or_else(|| reading.orp.and_then(|orp| {
+            (orp < ORP_RANGE.0 || orp > ORP_RANGE.1)
+                .then(|| format!("ORP {} mV outside [{}, {}]", orp, ORP_RANGE.0, ORP_RANGE.1))
+        }))?;
+
+    Some(RejectedReading::new("blueriot", &reading.sensor_id(), reading.timestamp, reading, reason, Some(email_id)))
+}
+
+fn temperature_reading_error(reading: &TemperatureReading) -> Option<String> {
+    if reading.sensor_id.trim().is_empty() {
+        return Some("missing sensor id".to_string());
+    }
+    timestamp_error(reading.timestamp)
+        .or_else(|| range_error("temperature", reading.temperature, TEMPERATURE_RANGE))
+        .or_else(|| reading.humidity.and_then(|h| range_error("humidity", h, HUMIDITY_RANGE)))
+}
+
+fn range_error(name: &str, value: f64, (min, max): (f64, f64)) -> Option<String> {
+    if !value.is_finite() {
+        return Some(format!("{} is not a number", name));
+    }
+    (value < min || value > max).then(|| format!("{} {} outside [{}, {}]", name, value, min, max))
+}
+
+fn timestamp_error(timestamp: DateTime<Utc>) -> Option<String> {
+    let oldest = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+    if timestamp < oldest {
+        return Some(format!("timestamp {} before 2000", timestamp));
+    }
+    (timestamp > Utc::now() + Duration::hours(MAX_FUTURE_SKEW_HOURS))
+        .then(|| format!("timestamp {} in the future", timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(sensor: &str, hour: u32, temperature: f64, humidity: Option<f64>) -> TemperatureReading {
+        TemperatureReading {
+            sensor_id: sensor.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 15, hour, 0, 0).unwrap(),
+            temperature,
+            humidity,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_temperature_readings() {
+        let readings = vec![
+            reading("cabane", 8, 4.5, Some(60.0)),
+            reading("cabane", 8, 4.5, Some(60.0)),
+            reading("cabane", 9, 250.0, None),
+            reading("cave", 9, 12.0, Some(140.0)),
+            reading("", 9, 12.0, None),
+            reading("cave", 10, f64::NAN, None),
+        ];
+
+        let (valid, rejected) = validate_temperature_readings(readings, "xsense", Some("msg-1"));
+        assert_eq!(valid.len(), 1);
+        let reasons: Vec<_> = rejected.iter().map(|r| r.reason.as_str()).collect();
+        assert!(reasons[0].starts_with("duplicate"));
+        assert!(reasons[1].starts_with("temperature 250"));
+        assert!(reasons[2].starts_with("humidity 140"));
+        assert_eq!(reasons[3], "missing sensor id");
+        assert_eq!(reasons[4], "temperature is not a number");
+        assert_eq!(rejected[0].email_id.as_deref(), Some("msg-1"));
+    }
+
+    #[test]
+    fn test_validate_pool_reading() {
+        let mut pool = PoolReading {
+            pool_id: "main".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 7, 1, 8, 0, 0).unwrap(),
+            temperature: Some(26.0),
+            ph: Some(7.2),
+            orp: Some(650),
+        };
+        assert!(validate_pool_reading(&pool, "msg-1").is_none());
+
+        pool.ph = Some(72.0);
+        let rejected = validate_pool_reading(&pool, "msg-1").unwrap();
+        assert_eq!(rejected.sensor_id, "pool/main");
+        assert!(rejected.reason.starts_with("pH 72"));
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+/// A CSV column, chosen by position or by header name
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum CsvColumn {
+    /// Zero-based position
+    Index(usize),
+    /// Header name, matched case-insensitively (exact match first, then prefix)
+    Header(String),
+}
+
+impl CsvColumn {
+    /// Parse a column setting: a 1-based position ("2") or a header name ("Température")
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        match value.parse::<usize>() {
+            Ok(0) => None,
+            Ok(position) => Some(CsvColumn::Index(position - 1)),
+            Err(_) => Some(CsvColumn::Header(value.to_string())),
+        }
+    }
+
+    /// Position of this column in the header row
+    pub fn resolve(&self, headers: &csv::StringRecord) -> Result<usize> {
+        match self {
+            CsvColumn::Index(index) if *index < headers.len() => Ok(*index),
+            CsvColumn::Index(index) => {
+                anyhow::bail!("CSV column {} does not exist ({} columns)", index + 1, headers.len())
+            }
+            CsvColumn::Header(name) => {
+                let wanted = name.to_lowercase();
+                let normalized: Vec<String> = headers.iter()
+                    .map(|header| header.trim().trim_start_matches('\u{feff}').to_lowercase())
+                    .collect();
+                normalized.iter().position(|header| *header == wanted)
+                    .or_else(|| normalized.iter().position(|header| header.starts_with(&wanted)))
+                    .ok_or_else(|| anyhow::anyhow!("CSV column '{}' not found in headers {:?}", name, headers))
+            }
+        }
+    }
+}
+
+/// Where the X-Sense CSV extractor finds each value
+///
+/// Defaults to the X-Sense app layout (timestamp, temperature, humidity);
+/// overridden with `XSENSE_CSV_TIMESTAMP`, `XSENSE_CSV_TEMPERATURE` and
+/// `XSENSE_CSV_HUMIDITY` (`none` when the export has no humidity).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CsvColumns {
+    pub timestamp: CsvColumn,
+    pub temperature: CsvColumn,
+    pub humidity: Option<CsvColumn>,
+}
+
+impl Default for CsvColumns {
+    fn default() -> Self {
+        CsvColumns {
+            timestamp: CsvColumn::Index(0),
+            temperature: CsvColumn::Index(1),
+            humidity: Some(CsvColumn::Index(2)),
+        }
+    }
+}
+
+/// Column positions resolved against the header row of one file
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedColumns {
+    pub timestamp: usize,
+    pub temperature: usize,
+    pub humidity: Option<usize>,
+}
+
+impl CsvColumns {
+    pub fn resolve(&self, headers: &csv::StringRecord) -> Result<ResolvedColumns> {
+        Ok(ResolvedColumns {
+            timestamp: self.timestamp.resolve(headers)?,
+            temperature: self.temperature.resolve(headers)?,
+            humidity: self.humidity.as_ref().map(|column| column.resolve(headers)).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_columns() {
+        let headers = csv::StringRecord::from(vec!["Humidité relative_Pourcentage", "Temps", "Température_Celsius"]);
+        let columns = CsvColumns {
+            timestamp: CsvColumn::parse("temps").unwrap(),
+            temperature: CsvColumn::parse("Température").unwrap(),
+            humidity: CsvColumn::parse("1"),
+        };
+
+        let resolved = columns.resolve(&headers).unwrap();
+        assert_eq!((resolved.timestamp, resolved.temperature, resolved.humidity), (1, 2, Some(0)));
+
+        assert!(CsvColumn::parse("0").is_none());
+        assert!(CsvColumn::parse("Pression").unwrap().resolve(&headers).is_err());
+        assert!(CsvColumn::Index(3).resolve(&headers).is_err());
+    }
+}
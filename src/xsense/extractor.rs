@@ -1,12 +1,14 @@
 use anyhow::{Result, Context};
 use chrono::{DateTime, Utc, NaiveDateTime};
 use csv::ReaderBuilder;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
 use log::{info, debug, warn};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 
 use crate::attachment_parser::Attachment;
+use super::columns::CsvColumns;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemperatureReading {
@@ -21,6 +23,11 @@ pub struct TemperatureExtractor;
 
 impl TemperatureExtractor {
     pub fn extract_from_attachment(attachment: &Attachment) -> Result<Vec<TemperatureReading>> {
+        Self::extract_from_attachment_with(attachment, &CsvColumns::default())
+    }
+    
+    /// Extract readings, locating CSV values with a column mapping (`XSENSE_CSV_*`)
+    pub fn extract_from_attachment_with(attachment: &Attachment, columns: &CsvColumns) -> Result<Vec<TemperatureReading>> {
         info!("Extracting temperature data from: {}", attachment.filename);
         
         // Extract sensor name from filename
@@ -29,7 +36,7 @@ impl TemperatureExtractor {
         
         match attachment.filename.to_lowercase() {
             name if name.ends_with(".csv") => {
-                Self::extract_from_xsense_csv(&attachment.content, &sensor_name)
+                Self::extract_from_xsense_csv_with(&attachment.content, &sensor_name, columns)
             }
             name if name.ends_with(".json") => {
                 Self::extract_from_json(&attachment.content)
@@ -77,22 +84,23 @@ impl TemperatureExtractor {
     }
     
     pub fn extract_from_xsense_csv(content: &[u8], sensor_name: &str) -> Result<Vec<TemperatureReading>> {
+        Self::extract_from_xsense_csv_with(content, sensor_name, &CsvColumns::default())
+    }
+    
+    pub fn extract_from_xsense_csv_with(content: &[u8], sensor_name: &str, columns: &CsvColumns) -> Result<Vec<TemperatureReading>> {
         debug!("Extracting from X-Sense CSV file for sensor: {}", sensor_name);
         
-        // Try UTF-8 first, then other encodings
-        let content_str = match std::str::from_utf8(content) {
-            Ok(s) => s.to_string(),
-            Err(_) => {
-                // Fallback: replace invalid characters with placeholders
-                String::from_utf8_lossy(content).to_string()
-            }
-        };
+        let content_str = Self::decode_text(content);
         
         debug!("CSV content size: {} characters", content_str.len());
         
+        let delimiter = Self::detect_csv_delimiter(&content_str);
+        debug!("CSV delimiter: {:?}", delimiter as char);
+        
         let mut readings = Vec::new();
         let mut rdr = ReaderBuilder::new()
             .has_headers(true)
+            .delimiter(delimiter)
             .flexible(true)  // Tolerant to column differences
             .from_reader(content_str.as_bytes());
         
@@ -101,40 +109,49 @@ impl TemperatureExtractor {
             .context("Unable to read CSV headers")?;
         debug!("Found CSV headers: {:?}", headers);
         
-        // Validate we have at least 3 columns
-        if headers.len() < 3 {
-            return Err(anyhow::anyhow!("Invalid CSV: found {} columns, expected at least 3", headers.len()));
-        }
+        // Locate timestamp/temperature/humidity (positional by default)
+        let resolved = columns.resolve(headers)
+            .context("Invalid CSV: expected columns not found")?;
+        let min_columns = [Some(resolved.timestamp), Some(resolved.temperature), resolved.humidity]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(0) + 1;
         
         // Parse each data line
         for (line_num, result) in rdr.records().enumerate() {
             let record = result.context(format!("Error on line {}", line_num + 2))?;
             
-            if record.len() < 3 {
-                warn!("Line {} skipped: not enough columns ({} < 3)", line_num + 2, record.len());
+            if record.len() < min_columns {
+                warn!("Line {} skipped: not enough columns ({} < {})", line_num + 2, record.len(), min_columns);
                 continue;
             }
             
-            // Column 1: Timestamp (format: "2023/12/26 23:59")
-            let timestamp_str = record.get(0).unwrap_or("");
+            // Timestamp (format: "2023/12/26 23:59")
+            let timestamp_str = record.get(resolved.timestamp).unwrap_or("");
             let timestamp = Self::parse_xsense_timestamp(timestamp_str)
                 .with_context(|| format!("Unable to parse timestamp '{}' on line {}", timestamp_str, line_num + 2))?;
             
-            // Column 2: Temperature (format: "5.5")
-            let temperature_str = record.get(1).unwrap_or("");
-            let temperature: f64 = temperature_str.parse()
+            // Temperature (format: "5.5")
+            let temperature_str = record.get(resolved.temperature).unwrap_or("");
+            let temperature: f64 = Self::parse_csv_number(temperature_str, delimiter)
                 .with_context(|| format!("Unable to parse temperature '{}' on line {}", temperature_str, line_num + 2))?;
             
-            // Column 3: Humidity (format: "89.6")
-            let humidity_str = record.get(2).unwrap_or("");
-            let humidity: f64 = humidity_str.parse()
-                .with_context(|| format!("Unable to parse humidity '{}' on line {}", humidity_str, line_num + 2))?;
+            // Humidity (format: "89.6"), unless the mapping has none
+            let humidity = match resolved.humidity {
+                Some(index) => {
+                    let humidity_str = record.get(index).unwrap_or("");
+                    Some(Self::parse_csv_number(humidity_str, delimiter)
+                        .with_context(|| format!("Unable to parse humidity '{}' on line {}", humidity_str, line_num + 2))?)
+                }
+                None => None,
+            };
             
             readings.push(TemperatureReading {
                 sensor_id: sensor_name.to_string(),
                 timestamp,
                 temperature,
-                humidity: Some(humidity),
+                humidity,
                 location: Some(sensor_name.to_string()),
             });
         }
@@ -143,6 +160,60 @@ impl TemperatureExtractor {
         Ok(readings)
     }
     
+    /// Decode an exported text file, sniffing its encoding
+    /// 
+    /// A BOM wins (UTF-8, UTF-16LE/BE); UTF-16 without BOM is recognized by its
+    /// zero bytes (Excel "Unicode text" exports); otherwise UTF-8 when valid,
+    /// else Windows-1252 (legacy Excel CSV on French Windows).
+    pub fn decode_text(content: &[u8]) -> String {
+        let encoding = match Encoding::for_bom(content) {
+            Some((encoding, _)) => encoding,
+            None if Self::looks_like_utf16(content, 1) => UTF_16LE,
+            None if Self::looks_like_utf16(content, 0) => UTF_16BE,
+            None if std::str::from_utf8(content).is_ok() => UTF_8,
+            None => WINDOWS_1252,
+        };
+        debug!("Decoding text as {}", encoding.name());
+        
+        // decode() strips the BOM; invalid sequences become U+FFFD
+        let (text, _, had_errors) = encoding.decode(content);
+        if had_errors {
+            warn!("Invalid {} sequences replaced while decoding", encoding.name());
+        }
+        text.into_owned()
+    }
+    
+    /// ASCII text in UTF-16 has a zero byte in every pair, at `zero_offset`
+    fn looks_like_utf16(content: &[u8], zero_offset: usize) -> bool {
+        let sample = &content[..content.len().min(200) & !1];
+        let pairs = sample.len() / 2;
+        pairs >= 2 && sample.chunks(2).filter(|pair| pair[zero_offset] == 0).count() * 10 >= pairs * 9
+    }
+    
+    /// Pick the delimiter of a CSV export from its header line: `,`, `;` or tab
+    /// 
+    /// French-locale apps export with `;`, which a `,` reader sees as a single column.
+    fn detect_csv_delimiter(content: &str) -> u8 {
+        let header = content.lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("");
+        
+        [b',', b';', b'\t'].into_iter()
+            .max_by_key(|&delimiter| header.matches(delimiter as char).count())
+            .filter(|&delimiter| header.contains(delimiter as char))
+            .unwrap_or(b',')
+    }
+    
+    /// Parse a number, accepting a decimal comma when fields are not comma-separated ("5,5")
+    fn parse_csv_number(value: &str, delimiter: u8) -> Result<f64, std::num::ParseFloatError> {
+        let value = value.trim();
+        if delimiter != b',' {
+            value.replace(',', ".").parse()
+        } else {
+            value.parse()
+        }
+    }
+    
     fn parse_xsense_timestamp(timestamp_str: &str) -> Result<DateTime<Utc>> {
         // X-Sense format: "2023/12/26 23:59"
         let naive_dt = NaiveDateTime::parse_from_str(timestamp_str, "%Y/%m/%d %H:%M")
@@ -154,7 +225,11 @@ impl TemperatureExtractor {
     
 
     
-    fn extract_from_json(content: &[u8]) -> Result<Vec<TemperatureReading>> {
+    /// Extract readings from a JSON document (attachment or pushed payload)
+    /// 
+    /// Accepted shapes: an array of readings, `{"data": [...]}`, `{"readings": [...]}`
+    /// or a single reading object.
+    pub fn extract_from_json(content: &[u8]) -> Result<Vec<TemperatureReading>> {
         debug!("Extracting from JSON file");
         
         let content_str = std::str::from_utf8(content)
@@ -188,6 +263,18 @@ impl TemperatureExtractor {
                 }
             }
         }
+        // Format: [readings...] using field aliases (temp, time, device_id, ...)
+        else if let Some(items) = value.as_array() {
+            for item in items {
+                if let Ok(reading) = Self::parse_json_reading(item) {
+                    readings.push(reading);
+                }
+            }
+        }
+        // Format: single reading object (typical of devices pushing one measurement)
+        else if value.is_object() {
+            readings.push(Self::parse_json_reading(&value)?);
+        }
         
         info!("Extracted {} temperature readings from JSON", readings.len());
         Ok(readings)
@@ -243,8 +330,7 @@ impl TemperatureExtractor {
     fn extract_from_text(content: &[u8]) -> Result<Vec<TemperatureReading>> {
         debug!("Extracting from text file");
         
-        let content_str = std::str::from_utf8(content)
-            .context("Unable to decode text content as UTF-8")?;
+        let content_str = Self::decode_text(content);
         
         let mut readings = Vec::new();
         
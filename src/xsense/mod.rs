@@ -1,6 +1,8 @@
 /// X-Sense temperature sensor email processing module
+pub mod columns;
 pub mod extractor;
 pub mod processor;
 
-pub use extractor::{TemperatureReading};
+pub use columns::{CsvColumn, CsvColumns};
+pub use extractor::{TemperatureReading, TemperatureExtractor};
 pub use processor::XSenseEmailProcessor;
@@ -1,16 +1,59 @@
 use anyhow::Result;
-use log::{debug, info};
+use std::collections::BTreeSet;
+use std::path::Path;
+use log::{debug, info, warn};
 
 use crate::config::Config;
-use crate::gmail_client::GmailClient;
-use crate::database::Database;
-use crate::slack_notifier::SlackNotifier;
+use crate::pipeline::EmailSource;
+use crate::gmail_client::{GmailClient, PostAction};
+use crate::storage::{FailedExtraction, SensorType, Storage};
+use crate::slack_notifier::{NotificationKind, NotificationSource, Severity, SlackNotifier};
 use crate::attachment_parser::AttachmentParser;
-use crate::email::{EmailProcessingStrategy, BaseEmailProcessor};
+use crate::email::{AttachmentResult, DryRunDetail, EmailHeaders, EmailProcessingStrategy, BaseEmailProcessor, EmailFilter, ExtractError, ProcessingResult, Provenance, Reading, RunLimits, RunSummary};
+use crate::email::common::merge_sensor_counts;
+use crate::i18n::Locale;
+use crate::alerts::Alerting;
+use crate::archive::AttachmentArchive;
+use crate::stats;
+use crate::templates::{device_stats, NotificationEvent, NotificationTemplates};
+use crate::units::UnitSystem;
+use crate::transforms::TransformChain;
+use crate::validation::validate_temperature_readings;
+use super::columns::CsvColumns;
 use super::extractor::TemperatureExtractor;
 
 /// X-Sense specific processing strategy
-pub struct XSenseStrategy;
+pub struct XSenseStrategy {
+    units: UnitSystem,
+    locale: Locale,
+    templates: NotificationTemplates,
+    filter: EmailFilter,
+    columns: CsvColumns,
+    todo_label: String,
+    after_processing: Vec<PostAction>,
+    alerting: Alerting,
+    save_attachments: bool,
+    transforms: TransformChain,
+    site: Option<String>,
+}
+
+impl XSenseStrategy {
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(XSenseStrategy {
+            units: config.units,
+            locale: config.locale,
+            templates: NotificationTemplates::from_config(config)?,
+            filter: EmailFilter::from_config(&config.xsense.filter)?,
+            columns: config.xsense.csv_columns.clone(),
+            todo_label: format!("{}/todo/xsense", config.gmail.label_prefix),
+            after_processing: config.xsense.after_processing.clone(),
+            alerting: Alerting::from_config(config)?,
+            save_attachments: config.xsense.save_attachments,
+            transforms: config.xsense.transforms.clone(),
+            site: config.site.clone(),
+        })
+    }
+}
 
 impl EmailProcessingStrategy for XSenseStrategy {
     fn search_emails<'a, 'b: 'a>(&'a self, gmail: &'b GmailClient) -> 
@@ -21,11 +64,12 @@ impl EmailProcessingStrategy for XSenseStrategy {
     fn process_single_email<'a, 'b: 'a, 'c: 'a>(
         &'a self,
         gmail: &'b GmailClient,
-        database: Option<&'c Database>,
+        database: Option<&'c dyn Storage>,
         slack: Option<&'c SlackNotifier>,
+        archive: Option<&'c AttachmentArchive>,
         message_id: &'a str,
-        is_dry_run: bool,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize>> + Send + 'a>> {
+        dry_run: Option<DryRunDetail>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ProcessingResult>> + Send + 'a>> {
         Box::pin(async move {
             debug!("Processing X-Sense email ID: {}", message_id);
             
@@ -37,23 +81,33 @@ impl EmailProcessingStrategy for XSenseStrategy {
                         .await
                         .unwrap_or((String::from("Unknown subject"), String::from("Unknown sender")));
                     
-                    return Err(anyhow::anyhow!(
-                        "Unable to retrieve complete email\n  Subject: {}\n  From: {}\n  Error: {}", 
-                        subject, from, e
-                    ));
+                    let message = format!("Unable to retrieve complete email\n  Subject: {}\n  From: {}\n  Error: {}", 
+                                          subject, from, e);
+                    return Err(e.context(message));
                 }
             };
             
+            let locale = self.locale;
+            let is_dry_run = dry_run.is_some();
+            let show = dry_run.is_some_and(|detail| detail.per_email());
+            let keep_readings = dry_run.is_some_and(|detail| detail.keeps_readings());
+            let headers = EmailHeaders {
+                subject: email_info.subject.clone(),
+                from: email_info.from.clone(),
+                date: email_info.date,
+            };
+            
             // 2. In dry-run mode, display headers and date
-            if is_dry_run {
-                println!("📋 Headers:");
-                println!("{}", email_info.headers);
+            if show {
+                println!("{}", locale.headers_title());
+                println!("{}: {}", locale.label_from(), email_info.from);
+                println!("{}: {}", locale.label_subject(), email_info.subject);
                 println!();
                 
-                println!("📅 Email date: {}", email_info.date.format("%Y-%m-%d %H:%M:%S UTC"));
+                println!("{}", locale.email_date(&email_info.date.format("%Y-%m-%d %H:%M:%S UTC").to_string()));
                 println!();
-                println!("📄 Email content:");
-                println!("   Size: {} bytes", email_info.content.len());
+                println!("{}", locale.email_content_title());
+                println!("{}", locale.email_size(email_info.content.len()));
                 println!();
             }
             
@@ -61,80 +115,213 @@ impl EmailProcessingStrategy for XSenseStrategy {
             let attachments = AttachmentParser::parse_email(&email_info.content)?;
             
             if attachments.is_empty() {
-                if is_dry_run {
-                    println!("⚠️  No attachments found in this email");
+                if show {
+                    println!("{}", locale.no_attachments());
                 }
-                return Ok(0);
+                return Ok(ProcessingResult { headers: Some(headers), ..ProcessingResult::empty() });
             }
             
-            if is_dry_run {
-                println!("📎 Found {} attachment(s):", attachments.len());
+            if show {
+                println!("{}", locale.attachments_found(attachments.len()));
                 for (i, att) in attachments.iter().enumerate() {
-                    println!("   {}. {} ({} bytes, type: {})", 
-                             i + 1, att.filename, att.content.len(), att.content_type);
+                    println!("{}", locale.attachment_line(i + 1, &att.filename, att.content.len(), &att.content_type));
                 }
                 println!();
             }
             
+            // Keep the original exports, browsable per source and month
+            if let Some(archive) = archive {
+                archive.save_all(email_info.date, &attachments).await;
+            }
+            
             // 4. Process each attachment
-            let mut total_readings = 0;
+            let mut result = ProcessingResult { headers: Some(headers), ..ProcessingResult::empty() };
+            // Attachments lost to the database rather than to their content
+            let mut storage_failed = false;
+            // Valid readings per file, consolidated per device once all attachments are read
+            let mut device_files = Vec::new();
             
             for (index, attachment) in attachments.iter().enumerate() {
-                if is_dry_run {
-                    println!("🔍 Processing attachment {}/{}: {}", 
-                             index + 1, attachments.len(), attachment.filename);
+                let mut detail = AttachmentResult::new(&attachment.filename);
+                
+                if show {
+                    println!("{}", locale.processing_attachment(index + 1, attachments.len(), &attachment.filename));
                 }
                 
-                match TemperatureExtractor::extract_from_attachment(attachment) {
-                    Ok(readings) => {
+                match TemperatureExtractor::extract_from_attachment_with(attachment, &self.columns) {
+                    Ok(mut readings) => {
+                        detail.extracted = readings.len();
+                        readings.iter_mut().for_each(|reading| self.transforms.apply_temperature(reading));
+                        let (readings, rejected) = validate_temperature_readings(readings, "xsense", Some(message_id));
+                        detail.rejected = rejected.len();
+                        for rejection in &rejected {
+                            result.sensors.entry(rejection.sensor_id.clone()).or_default().rejected += 1;
+                        }
+                        
                         if is_dry_run {
-                            Self::display_readings_dry_run(&readings);
+                            if show {
+                                for rejection in &rejected {
+                                    println!("{}", locale.reading_rejected(&rejection.sensor_id, &rejection.reason));
+                                }
+                                self.display_readings_dry_run(&readings, dry_run == Some(DryRunDetail::Full));
+                            }
+                            result.records += readings.len();
+                            detail.saved = readings.len();
+                            if keep_readings {
+                                let provenance = Provenance::email("xsense", message_id, Some(&attachment.filename)).with_content(&attachment.content);
+                                result.readings.extend(readings.iter().map(|reading| Reading::from_temperature(reading, self.site.as_deref(), provenance.clone())));
+                            }
+                            device_files.push((attachment.filename.clone(), readings.clone()));
+                            
+                            // Compare with stored readings when the database is reachable
+                            if let Some(db) = database {
+                                match db.diff_temperature_readings(&readings).await {
+                                    Ok(stats) => {
+                                        if show {
+                                            println!("{}", locale.dry_run_diff_title());
+                                            for (sensor, counts) in &stats.per_sensor {
+                                                println!("{}", locale.dry_run_diff_line(sensor, counts.inserted, counts.duplicates));
+                                            }
+                                            println!();
+                                        }
+                                        (detail.saved, detail.duplicates) = (stats.inserted, stats.duplicates);
+                                        merge_sensor_counts(&mut result.sensors, &stats.per_sensor);
+                                    }
+                                    Err(e) => {
+                                        if show {
+                                            println!("{}", locale.dry_run_diff_failed(&e.to_string()));
+                                        }
+                                    }
+                                }
+                            }
                         } else if let Some(db) = database {
+                            if let Err(e) = db.save_rejected_readings(&rejected).await {
+                                warn!("Unable to store rejected readings from {}: {:#}", attachment.filename, e);
+                            }
+                            
                             // Save to database
                             match db.save_temperature_readings(&readings).await {
-                                Ok(count) => {
-                                    total_readings += count;
-                                    debug!("Saved {} readings from {}", count, attachment.filename);
+                                Ok(stats) => {
+                                    result.records += stats.total();
+                                    (detail.saved, detail.duplicates) = (stats.inserted, stats.duplicates);
+                                    merge_sensor_counts(&mut result.sensors, &stats.per_sensor);
+                                    debug!("Saved {} readings from {}", stats.total(), attachment.filename);
+                                    for (sensor, counts) in &stats.per_sensor {
+                                        info!(source = "xsense", email_id = message_id, sensor = sensor.as_str();
+                                              "Sensor {}: {} new, {} duplicate reading(s)", sensor, counts.inserted, counts.duplicates);
+                                    }
+                                    for sensor_id in stats.per_sensor.keys() {
+                                        if let Err(e) = db.register_sensor(sensor_id, SensorType::Temperature).await {
+                                            warn!("Unable to register sensor {}: {:#}", sensor_id, e);
+                                        }
+                                    }
+                                    let provenance = Provenance::email("xsense", message_id, Some(&attachment.filename)).with_content(&attachment.content);
+                                    result.readings.extend(readings.iter().map(|reading| Reading::from_temperature(reading, self.site.as_deref(), provenance.clone())));
+                                    device_files.push((attachment.filename.clone(), readings));
                                 }
                                 Err(e) => {
-                                    debug!("Error saving readings from {}: {}", attachment.filename, e);
+                                    warn!("Error saving readings from {}: {:#}", attachment.filename, e);
+                                    detail.error = Some(format!("{:#}", e));
+                                    storage_failed = true;
                                 }
                             }
                         }
                     }
                     Err(e) => {
+                        detail.error = Some(format!("{:#}", e));
                         if is_dry_run {
-                            println!("   ⚠️  Unable to extract data: {}", e);
+                            if show {
+                                println!("{}", locale.extraction_failed(&e.to_string()));
+                            }
+                        } else if let Some(db) = database {
+                            // Keep the attachment so the parser failure can be reproduced
+                            let failure = FailedExtraction {
+                                source: "xsense".to_string(),
+                                email_id: message_id.to_string(),
+                                filename: attachment.filename.clone(),
+                                content: attachment.content.clone(),
+                                error: format!("{:#}", e),
+                            };
+                            if let Err(e) = db.save_failed_extraction(&failure).await {
+                                warn!("Unable to keep failed attachment {}: {:#}", attachment.filename, e);
+                            }
                         }
                     }
                 }
+                
+                if show {
+                    println!("{}\n", locale.attachment_result(&detail));
+                }
+                result.attachments.push(detail);
+            }
+            
+            // Nothing usable: the email fails as a whole and goes to the retry queue,
+            // or straight to the dead letters when only the content is to blame
+            if result.failed_attachments().count() == attachments.len() {
+                if storage_failed {
+                    anyhow::bail!("All attachments failed: {}", result.failures_summary());
+                }
+                return Err(ExtractError::Attachments(result.failures_summary()).into());
+            }
+            
+            // One consolidated line per device rather than per file
+            let mut devices = device_stats(&device_files, self.units);
+            
+            // Trend of each device versus the previous period, for the notification
+            if let (Some(db), Some(_), false) = (database, slack, is_dry_run) {
+                let extracted: Vec<_> = device_files.iter().flat_map(|(_, readings)| readings.iter().cloned()).collect();
+                match stats::previous_period_averages(db, &extracted).await {
+                    Ok(averages) => for device in &mut devices {
+                        if let Some(avg) = averages.get(&device.name) {
+                            device.set_previous_avg(self.units.temperature(*avg));
+                        }
+                    },
+                    Err(e) => debug!("Unable to read the previous period of the devices: {:#}", e),
+                }
+            }
+            if show && attachments.len() > 1 && !devices.is_empty() {
+                println!("{}", locale.device_summary_title());
+                for device in &devices {
+                    println!("{}", locale.device_summary_line(device, self.units.temperature_symbol()));
+                }
+                println!();
+            }
+            for device in &devices {
+                debug!("Device {}: {} reading(s) from {} file(s), {} → {}", 
+                       device.name, device.count, device.files.len(), device.first, device.last);
+            }
+            
+            // Mold risk, alert rules and humidity rules on the saved readings
+            if !is_dry_run {
+                let inserted: BTreeSet<&String> = result.sensors.iter()
+                    .filter(|(_, counts)| counts.inserted > 0)
+                    .map(|(sensor, _)| sensor)
+                    .collect();
+                let saved: Vec<_> = device_files.iter().flat_map(|(_, readings)| readings.iter().cloned()).collect();
+                self.alerting.check_saved(database, slack, NotificationSource::XSense, inserted, &saved).await;
             }
             
             // 5. Send Slack notification (if not dry-run and has data)
-            if !is_dry_run && total_readings > 0 {
+            if !is_dry_run && result.records > 0 {
                 if let Some(slack) = slack {
-                    // Extract "From" and "Subject" from headers
-                    // Format: "De: sender\nObjet: subject"
-                    let from = email_info.headers.lines()
-                        .find(|l| l.starts_with("De:"))
-                        .unwrap_or("De: Unknown")
-                        .trim_start_matches("De:")
-                        .trim();
-                    
-                    let subject = email_info.headers.lines()
-                        .find(|l| l.starts_with("Objet:"))
-                        .unwrap_or("Objet: Unknown")
-                        .trim_start_matches("Objet:")
-                        .trim();
-                    
-                    let message = format!(
-                        "📊 New X-Sense data: {} temperature readings\nFrom: {}\nSubject: {}",
-                        total_readings,
-                        from,
-                        subject
-                    );
+                    let context = minijinja::context! {
+                        readings => result.records,
+                        from => &email_info.from,
+                        subject => &email_info.subject,
+                        sensors => &devices,
+                        failed_attachments => result.failed_attachments().collect::<Vec<_>>(),
+                    };
+                    let message = self.templates.render_or(NotificationEvent::XSenseData, context, || {
+                        let mut message = locale.notify_xsense_data(result.records, &email_info.from, &email_info.subject,
+                                                                    &devices, self.units.temperature_symbol());
+                        if result.failed_attachments().next().is_some() {
+                            message.push('\n');
+                            message.push_str(&locale.notify_failed_attachments(&result.failures_summary()));
+                        }
+                        message
+                    });
                     info!("Sending Slack notification for X-Sense readings");
-                    if let Err(e) = slack.send_message(&message).await {
+                    if let Err(e) = slack.notify(NotificationSource::XSense, Severity::Info, NotificationKind::Readings, Some(message_id), &message).await {
                         debug!("Failed to send Slack notification: {}", e);
                     } else {
                         debug!("Slack notification sent successfully");
@@ -142,7 +329,7 @@ impl EmailProcessingStrategy for XSenseStrategy {
                 }
             }
             
-            Ok(total_readings)
+            Ok(result)
         })
     }
     
@@ -151,7 +338,7 @@ impl EmailProcessingStrategy for XSenseStrategy {
         gmail: &'b GmailClient,
         message_id: &'a str,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
-        Box::pin(gmail.mark_email_as_processed(message_id))
+        Box::pin(gmail.mark_processed(message_id, "xsense", &self.after_processing))
     }
     
     fn processor_name(&self) -> &str {
@@ -159,18 +346,35 @@ impl EmailProcessingStrategy for XSenseStrategy {
     }
     
     fn label_name(&self) -> &str {
-        "homemetrics-todo-xsense"
+        &self.todo_label
+    }
+    
+    fn email_filter(&self) -> &EmailFilter {
+        &self.filter
+    }
+    
+    fn saves_attachments(&self) -> bool {
+        self.save_attachments
+    }
+    
+    fn processed_label_changes(&self, gmail: &GmailClient) -> (Vec<String>, Vec<String>) {
+        gmail.processed_label_changes("xsense", &self.after_processing)
+    }
+    
+    fn notification_source(&self) -> NotificationSource {
+        NotificationSource::XSense
     }
 }
 
 impl XSenseStrategy {
-    fn display_readings_dry_run(readings: &[crate::xsense::TemperatureReading]) {
+    /// Readings of each sensor: first and last one, or all of them with `--dry-run-detail full`
+    fn display_readings_dry_run(&self, readings: &[crate::xsense::TemperatureReading], all: bool) {
         if readings.is_empty() {
-            println!("   ⚠️  No valid readings extracted");
+            println!("{}", self.locale.no_valid_readings());
             return;
         }
         
-        println!("   ✅ Extracted {} reading(s):", readings.len());
+        println!("{}", self.locale.readings_extracted(readings.len()));
         
         // Group by sensor
         let mut by_sensor: std::collections::HashMap<String, Vec<&crate::xsense::TemperatureReading>> = 
@@ -178,37 +382,52 @@ impl XSenseStrategy {
         
         for reading in readings {
             by_sensor.entry(reading.sensor_id.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(reading);
         }
         
         for (sensor_id, sensor_readings) in by_sensor.iter() {
-            println!("\n   📡 Sensor: {}", sensor_id);
+            println!("\n{}", self.locale.sensor_title(sensor_id));
+            
+            if all {
+                for reading in sensor_readings {
+                    let humidity_str = reading.humidity
+                        .map(|h| format!("{:.1}%", h))
+                        .unwrap_or_else(|| self.locale.not_available().to_string());
+                    println!("{}", self.locale.reading_row(
+                        &reading.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        &self.units.format_temperature(reading.temperature),
+                        &humidity_str));
+                }
+                continue;
+            }
             
             // Display first and last reading
             if let Some(first) = sensor_readings.first() {
                 let humidity_str = first.humidity
                     .map(|h| format!("{:.1}%", h))
-                    .unwrap_or_else(|| "N/A".to_string());
-                println!("      First: {} | Temp: {:.1}°C | Humidity: {}",
-                         first.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                         first.temperature,
-                         humidity_str);
+                    .unwrap_or_else(|| self.locale.not_available().to_string());
+                println!("{}", self.locale.reading_line(
+                    true,
+                    &first.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    &self.units.format_temperature(first.temperature),
+                    &humidity_str));
             }
             
             if sensor_readings.len() > 1 {
                 if let Some(last) = sensor_readings.last() {
                     let humidity_str = last.humidity
                         .map(|h| format!("{:.1}%", h))
-                        .unwrap_or_else(|| "N/A".to_string());
-                    println!("      Last:  {} | Temp: {:.1}°C | Humidity: {}",
-                             last.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                             last.temperature,
-                             humidity_str);
+                        .unwrap_or_else(|| self.locale.not_available().to_string());
+                    println!("{}", self.locale.reading_line(
+                        false,
+                        &last.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        &self.units.format_temperature(last.temperature),
+                        &humidity_str));
                 }
                 
                 if sensor_readings.len() > 2 {
-                    println!("      ... {} more readings", sensor_readings.len() - 2);
+                    println!("{}", self.locale.more_readings(sensor_readings.len() - 2));
                 }
             }
         }
@@ -223,22 +442,49 @@ pub struct XSenseEmailProcessor {
 
 impl XSenseEmailProcessor {
     pub async fn new(config: Config) -> Result<Self> {
+        let config = config.for_source(EmailSource::XSense);
+        Ok(XSenseEmailProcessor {
+            base: BaseEmailProcessor::new(config.clone(), XSenseStrategy::new(&config)?).await?,
+        })
+    }
+    
+    pub async fn new_history(config: Config) -> Result<Self> {
+        let config = config.for_source(EmailSource::XSense);
+        Ok(XSenseEmailProcessor {
+            base: BaseEmailProcessor::new_history(config.clone(), XSenseStrategy::new(&config)?).await?,
+        })
+    }
+    
+    pub async fn new_dry_run(config: Config) -> Result<Self> {
+        let config = config.for_source(EmailSource::XSense);
+        Ok(XSenseEmailProcessor {
+            base: BaseEmailProcessor::new_dry_run(config.clone(), XSenseStrategy::new(&config)?).await?,
+        })
+    }
+    
+    pub async fn new_sandbox(config: Config, sqlite_path: &Path) -> Result<Self> {
+        let config = config.for_source(EmailSource::XSense);
         Ok(XSenseEmailProcessor {
-            base: BaseEmailProcessor::new(config, XSenseStrategy).await?,
+            base: BaseEmailProcessor::new_sandbox(config.clone(), XSenseStrategy::new(&config)?, sqlite_path).await?,
         })
     }
     
-    pub fn new_dry_run(config: Config) -> Result<Self> {
+    pub async fn new_backfill(config: Config) -> Result<Self> {
+        let config = config.for_source(EmailSource::XSense);
         Ok(XSenseEmailProcessor {
-            base: BaseEmailProcessor::new_dry_run(config, XSenseStrategy)?,
+            base: BaseEmailProcessor::new_backfill(config.clone(), XSenseStrategy::new(&config)?).await?,
         })
     }
     
-    pub async fn process_emails(&self, limit: Option<usize>) -> Result<usize> {
-        self.base.process_emails(limit).await
+    pub async fn process_emails(&self, gmail: &GmailClient, limits: RunLimits) -> Result<RunSummary> {
+        self.base.process_emails(gmail, limits).await
+    }
+    
+    pub async fn process_emails_dry_run(&self, gmail: &GmailClient, limits: RunLimits, detail: DryRunDetail) -> Result<RunSummary> {
+        self.base.process_emails_dry_run(gmail, limits, detail).await
     }
     
-    pub async fn process_emails_dry_run(&self, limit: Option<usize>) -> Result<usize> {
-        self.base.process_emails_dry_run(limit).await
+    pub async fn reprocess(&self, gmail: &GmailClient, message_ids: &[String], dry_run: Option<DryRunDetail>) -> Result<RunSummary> {
+        self.base.reprocess(gmail, message_ids, dry_run).await
     }
 }
@@ -53,7 +53,7 @@ echo ""
 echo "🎯 Test du projet:"
 echo ""
 echo "# Test rapide en mode dry-run (sans base de données)"
-echo "cargo run -- --dry-run --limit 3"
+echo "cargo run -- fetch --dry-run --limit 3"
 echo ""
 echo "# Compilation optimisée"
 echo "cargo build --release"
@@ -71,5 +71,5 @@ echo "Voulez-vous lancer un test dry-run ? (y/N)"
 read -r response
 if [[ "$response" =~ ^([yY][eE][sS]|[yY])$ ]]; then
     echo "🚀 Lancement du test dry-run..."
-    cargo run -- --dry-run --limit 2
+    cargo run -- fetch --dry-run --limit 2
 fi
\ No newline at end of file
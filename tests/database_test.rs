@@ -0,0 +1,180 @@
+use chrono::{TimeZone, Utc};
+use homemetrics::config::{Config, DatabaseConfig};
+use homemetrics::database::Database;
+use homemetrics::receiver::FileReceiver;
+use homemetrics::storage::StorageLayout;
+use homemetrics::xsense::TemperatureReading;
+use sqlx::PgPool;
+
+/// Test database from TEST_DB_HOST, TEST_DB_PORT, TEST_DB_NAME, TEST_DB_USERNAME and TEST_DB_PASSWORD
+fn test_database(layout: StorageLayout) -> DatabaseConfig {
+    let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+    DatabaseConfig {
+        host: var("TEST_DB_HOST", "localhost"),
+        port: var("TEST_DB_PORT", "5432").parse().expect("Invalid TEST_DB_PORT"),
+        database: var("TEST_DB_NAME", "homemetrics_test"),
+        username: var("TEST_DB_USERNAME", "postgres"),
+        password: var("TEST_DB_PASSWORD", "postgres"),
+        layout,
+        sensor_partitions: 0,
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn test_backfill_replaces_every_generic_metric() {
+    let config = test_database(StorageLayout::Generic);
+    Database::migrate(&config).await.unwrap();
+    let url = format!("postgres://{}:{}@{}:{}/{}", config.username, config.password, config.host, config.port, config.database);
+    let pool = PgPool::connect(&url).await.unwrap();
+    let sensor_id = format!("backfill-{}", std::process::id());
+    let reading = TemperatureReading {
+        sensor_id: sensor_id.clone(),
+        timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 14, 0, 0).unwrap(),
+        temperature: 21.5,
+        humidity: Some(80.0),
+        location: None,
+    };
+
+    let database = Database::connect_read_only(&config).await.unwrap().with_replace_existing(true);
+    for _ in 0..3 {
+        database.save_temperature_readings(std::slice::from_ref(&reading)).await.unwrap();
+    }
+    let rows: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT metric, COUNT(*) FROM readings WHERE sensor_id = $1 GROUP BY metric ORDER BY metric"
+    )
+    .bind(&sensor_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(rows, vec![
+        ("humidity".to_string(), 1),
+        ("mold_risk".to_string(), 1),
+        ("temperature".to_string(), 1),
+    ]);
+
+    sqlx::query("DELETE FROM readings WHERE sensor_id = $1").bind(&sensor_id).execute(&pool).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn test_backfill_inserts_missing_history() {
+    let config = test_database(StorageLayout::Domain);
+    Database::migrate(&config).await.unwrap();
+    let url = format!("postgres://{}:{}@{}:{}/{}", config.username, config.password, config.host, config.port, config.database);
+    let pool = PgPool::connect(&url).await.unwrap();
+    let sensor_id = format!("history-{}", std::process::id());
+    let reading = |day: u32| TemperatureReading {
+        sensor_id: sensor_id.clone(),
+        timestamp: Utc.with_ymd_and_hms(2024, 5, day, 14, 0, 0).unwrap(),
+        temperature: 21.5,
+        humidity: Some(60.0),
+        location: None,
+    };
+
+    // Even with the history skip, a replacing save queries the readings before the boundary window
+    let database = Database::connect_read_only(&config).await.unwrap()
+        .with_history_skip(true)
+        .with_replace_existing(true);
+    database.save_temperature_readings(&[reading(10)]).await.unwrap();
+    let stats = database.save_temperature_readings(&[reading(1), reading(10)]).await.unwrap();
+    assert_eq!((stats.inserted, stats.duplicates), (1, 1));
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM temperature_readings WHERE sensor_id = $1")
+        .bind(&sensor_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+
+    sqlx::query("DELETE FROM temperature_readings WHERE sensor_id = $1").bind(&sensor_id).execute(&pool).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn test_history_backfill_inserts_older_readings() {
+    let config = test_database(StorageLayout::Domain);
+    Database::migrate(&config).await.unwrap();
+    let url = format!("postgres://{}:{}@{}:{}/{}", config.username, config.password, config.host, config.port, config.database);
+    let pool = PgPool::connect(&url).await.unwrap();
+    let sensor_id = format!("history-gmail-{}", std::process::id());
+    let reading = |day: u32| TemperatureReading {
+        sensor_id: sensor_id.clone(),
+        timestamp: Utc.with_ymd_and_hms(2024, 6, day, 8, 0, 0).unwrap(),
+        temperature: 19.0,
+        humidity: None,
+        location: None,
+    };
+
+    // A scheduled run stores the recent reading...
+    let scheduled = Database::connect_read_only(&config).await.unwrap().with_history_skip(true);
+    scheduled.save_temperature_readings(&[reading(20)]).await.unwrap();
+    let stats = scheduled.diff_temperature_readings(&[reading(1)]).await.unwrap();
+    assert_eq!((stats.inserted, stats.duplicates), (0, 1));
+
+    // ...then backfill-gmail (no history skip) saves the older emails
+    let backfill = Database::connect_read_only(&config).await.unwrap();
+    let stats = backfill.save_temperature_readings(&[reading(1), reading(2), reading(20)]).await.unwrap();
+    assert_eq!((stats.inserted, stats.duplicates), (2, 1));
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM temperature_readings WHERE sensor_id = $1")
+        .bind(&sensor_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+
+    sqlx::query("DELETE FROM temperature_readings WHERE sensor_id = $1").bind(&sensor_id).execute(&pool).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn test_import_file_older_than_stored_readings() {
+    let database = test_database(StorageLayout::Domain);
+    for (name, value) in [
+        ("DB_HOST", database.host.clone()),
+        ("DB_PORT", database.port.to_string()),
+        ("DB_NAME", database.database.clone()),
+        ("DB_USERNAME", database.username.clone()),
+        ("DB_PASSWORD", database.password.clone()),
+        ("GMAIL_CREDENTIALS_PATH", "credentials.json".to_string()),
+    ] {
+        std::env::set_var(name, value);
+    }
+    let config = Config::new().unwrap();
+    let receiver = FileReceiver::new(&config).await.unwrap();
+    let url = format!("postgres://{}:{}@{}:{}/{}", database.username, database.password, database.host, database.port, database.database);
+    let pool = PgPool::connect(&url).await.unwrap();
+    let sensor_id = format!("import{}", std::process::id());
+
+    // The sensor already has a reading a year after the exported file
+    let recent = TemperatureReading {
+        sensor_id: sensor_id.clone(),
+        timestamp: Utc.with_ymd_and_hms(2025, 6, 1, 8, 0, 0).unwrap(),
+        temperature: 20.0,
+        humidity: None,
+        location: None,
+    };
+    Database::connect_read_only(&database).await.unwrap()
+        .with_site(config.site.clone())
+        .save_temperature_readings(&[recent])
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(format!("Thermo-{}_Export data_20240601.csv", sensor_id));
+    std::fs::write(&path, "Temps;Température_Celsius;Humidité relative_Pourcentage\n\
+                           2024/06/01 08:01;19,0;60,0\n\
+                           2024/06/01 08:00;19,1;60,5\n").unwrap();
+    assert_eq!(receiver.import_file(&path).await.unwrap(), (2, 2));
+    assert_eq!(receiver.import_file(&path).await.unwrap(), (2, 0));
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM temperature_readings WHERE sensor_id = $1")
+        .bind(&sensor_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+
+    sqlx::query("DELETE FROM temperature_readings WHERE sensor_id = $1").bind(&sensor_id).execute(&pool).await.unwrap();
+}
@@ -1,6 +1,6 @@
 use std::fs;
 use homemetrics::attachment_parser::AttachmentParser;
-use homemetrics::xsense::TemperatureExtractor;
+use homemetrics::xsense::{CsvColumn, CsvColumns, TemperatureExtractor};
 
 #[test]
 fn test_extract_from_xsense_email() {
@@ -27,7 +27,7 @@ fn test_extract_from_xsense_email() {
         assert!(reading.temperature > -50.0 && reading.temperature < 50.0, 
                "Temperature {} should be in reasonable range", reading.temperature);
         if let Some(humidity) = reading.humidity {
-            assert!(humidity >= 0.0 && humidity <= 100.0, 
+            assert!((0.0..=100.0).contains(&humidity), 
                    "Humidity {} should be between 0 and 100", humidity);
         }
     }
@@ -99,3 +99,95 @@ fn test_csv_parsing() {
              readings[0].temperature, 
              readings[0].humidity.unwrap());
 }
+
+#[test]
+fn test_csv_delimiter_detection() {
+    // French-locale export: semicolons and decimal commas
+    let semicolon = b"Temps;Temp\xC3\xA9rature_Celsius;Humidit\xC3\xA9 relative_Pourcentage
+2025/11/04 23:59;15,0;84,0
+2025/11/04 23:58;15,1;83,2";
+    let readings = TemperatureExtractor::extract_from_xsense_csv(semicolon, "TEST_SENSOR")
+        .expect("Failed to parse semicolon CSV");
+    assert_eq!(readings.len(), 2);
+    assert_eq!(readings[1].temperature, 15.1);
+    assert_eq!(readings[1].humidity, Some(83.2));
+    
+    let tab = b"Time\tTemperature_Celsius\tRelative humidity_Percentage
+2025/11/04 23:59\t15.0\t84.0";
+    let readings = TemperatureExtractor::extract_from_xsense_csv(tab, "TEST_SENSOR")
+        .expect("Failed to parse tab-separated CSV");
+    assert_eq!(readings.len(), 1);
+    assert_eq!(readings[0].humidity, Some(84.0));
+}
+
+#[test]
+fn test_csv_column_mapping() {
+    // Newer app version: humidity first, English headers, plus a battery column
+    let csv_content = b"Humidity_Percentage,Time,Battery,Temperature_Celsius
+84.0,2025/11/04 23:59,95,15.0
+83.2,2025/11/04 23:58,95,15.1";
+    let columns = CsvColumns {
+        timestamp: CsvColumn::parse("time").unwrap(),
+        temperature: CsvColumn::parse("Temperature").unwrap(),
+        humidity: CsvColumn::parse("1"),
+    };
+    
+    let readings = TemperatureExtractor::extract_from_xsense_csv_with(csv_content, "TEST_SENSOR", &columns)
+        .expect("Failed to parse mapped CSV");
+    assert_eq!(readings.len(), 2);
+    assert_eq!(readings[1].temperature, 15.1);
+    assert_eq!(readings[1].humidity, Some(83.2));
+    
+    // Default positional columns do not fit this layout
+    assert!(TemperatureExtractor::extract_from_xsense_csv(csv_content, "TEST_SENSOR").is_err());
+    
+    let no_humidity = CsvColumns { humidity: None, ..columns };
+    let readings = TemperatureExtractor::extract_from_xsense_csv_with(csv_content, "TEST_SENSOR", &no_humidity)
+        .expect("Failed to parse CSV without humidity");
+    assert_eq!(readings[0].humidity, None);
+}
+
+#[test]
+fn test_csv_encodings() {
+    // Same export saved by Excel/Windows in different encodings, mapped by (accented) header names
+    let columns = CsvColumns {
+        timestamp: CsvColumn::parse("Temps").unwrap(),
+        temperature: CsvColumn::parse("Température").unwrap(),
+        humidity: CsvColumn::parse("Humidité"),
+    };
+    
+    for fixture in ["export_utf8_bom.csv", "export_utf16le.csv", "export_utf16le_nobom.csv", "export_windows1252.csv"] {
+        let content = fs::read(format!("data_test/csv/{}", fixture))
+            .unwrap_or_else(|_| panic!("Failed to read fixture {}", fixture));
+        
+        let readings = TemperatureExtractor::extract_from_xsense_csv_with(&content, "TEST_SENSOR", &columns)
+            .unwrap_or_else(|e| panic!("Failed to parse {}: {:#}", fixture, e));
+        assert_eq!(readings.len(), 2, "{}", fixture);
+        assert_eq!(readings[1].temperature, 15.1, "{}", fixture);
+        assert_eq!(readings[1].humidity, Some(83.2), "{}", fixture);
+    }
+}
+
+#[test]
+fn test_json_payload_parsing() {
+    // Single reading object, as pushed by a device on POST /ingest
+    let single = br#"{"sensor": "garage", "time": "2025-11-05T08:00:00Z", "temp": 12.5, "hum": 70.0}"#;
+    let readings = TemperatureExtractor::extract_from_json(single)
+        .expect("Failed to parse single JSON reading");
+    
+    assert_eq!(readings.len(), 1);
+    assert_eq!(readings[0].sensor_id, "garage");
+    assert_eq!(readings[0].temperature, 12.5);
+    assert_eq!(readings[0].humidity, Some(70.0));
+    
+    // Wrapped array of readings
+    let wrapped = br#"{"readings": [
+        {"sensor_id": "garage", "timestamp": "2025-11-05 08:00:00", "temperature": 12.5},
+        {"sensor_id": "garage", "timestamp": "2025-11-05 08:05:00", "temperature": 12.7}
+    ]}"#;
+    let readings = TemperatureExtractor::extract_from_json(wrapped)
+        .expect("Failed to parse wrapped JSON readings");
+    
+    assert_eq!(readings.len(), 2);
+    assert_eq!(readings[1].temperature, 12.7);
+}